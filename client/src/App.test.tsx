@@ -102,7 +102,6 @@ vi.mock('@/lib/api', () => ({
   })),
   getIdentityInfo: vi.fn(async () => ({
     pseudonymId: 'pseudo-123',
-    participantType: 'human',
     active: true,
     capabilities: {
       can_voice: false,
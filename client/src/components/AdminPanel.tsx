@@ -504,6 +504,23 @@ function PolicyEditor({ pseudonymId }: { pseudonymId: string }) {
               }
             />
           </label>
+          <label>
+            Connection Requests
+            <input
+              type="number"
+              min="1"
+              value={policy.rate_limit.connection_request_limit}
+              onChange={(e) =>
+                setPolicy({
+                  ...policy,
+                  rate_limit: {
+                    ...policy.rate_limit,
+                    connection_request_limit: parseInt(e.target.value) || 1,
+                  },
+                })
+              }
+            />
+          </label>
         </div>
       </div>
 
@@ -11,6 +11,9 @@
  * For the local user's own messages, the persona display name and avatar
  * are shown (if set). Other users' messages show their granted username
  * (if available) or truncated pseudonyms.
+ *
+ * Messages from muted participants are hidden; the mute list is synced
+ * from the server.
  */
 
 import { useEffect, useRef, useState, useCallback } from 'react';
@@ -18,6 +21,7 @@ import { useChannelsStore } from '@/stores/channels';
 import { useIdentityStore } from '@/stores/identity';
 import { useServersStore } from '@/stores/servers';
 import { useUsernameStore } from '@/stores/usernames';
+import { useMuteStore } from '@/stores/mutes';
 import { LinkPreview } from '@/components/LinkPreview';
 import { extractUrls } from '@/lib/link-preview';
 import { getPersonasForIdentity } from '@/lib/personas';
@@ -116,6 +120,7 @@ function MessageBubble({
   const editMessage = useChannelsStore((s) => s.editMessage);
   const deleteMessage = useChannelsStore((s) => s.deleteMessage);
   const activeChannelId = useChannelsStore((s) => s.activeChannelId);
+  const mute = useMuteStore((s) => s.mute);
 
   const [editing, setEditing] = useState(false);
   const [editText, setEditText] = useState(message.content);
@@ -193,6 +198,12 @@ function MessageBubble({
     deleteMessage(message.message_id);
   }, [message.message_id, deleteMessage]);
 
+  const handleMute = useCallback(() => {
+    mute(pseudonymId, message.sender_pseudonym).catch((err) => {
+      console.warn('Failed to mute participant:', err);
+    });
+  }, [mute, pseudonymId, message.sender_pseudonym]);
+
   const handleShowHistory = useCallback(async () => {
     if (showHistory) {
       setShowHistory(false);
@@ -248,6 +259,16 @@ function MessageBubble({
           </button>
         )}
         <span className="timestamp">{time}</span>
+        {!isSelf && (
+          <span className="message-actions">
+            <button className="msg-action-btn mute-btn" onClick={handleMute} title="Mute participant">
+              <svg width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+                <circle cx="12" cy="12" r="10" />
+                <line x1="4.9" y1="4.9" x2="19.1" y2="19.1" />
+              </svg>
+            </button>
+          </span>
+        )}
         {canModify && !editing && (
           <span className="message-actions">
             <button className="msg-action-btn edit-btn" onClick={handleEdit} title="Edit message">
@@ -377,6 +398,7 @@ export function MessageView() {
   const identity = useIdentityStore((s) => s.identity);
   const { messages, activeChannelId, loadOlderMessages, loadingOlder, hasMoreMessages } = useChannelsStore();
   const loadVisibleUsernames = useUsernameStore((s) => s.loadVisibleUsernames);
+  const { muted, loadMutes, unmute } = useMuteStore();
   const bottomRef = useRef<HTMLDivElement>(null);
   const containerRef = useRef<HTMLDivElement>(null);
   const prevMessageCount = useRef(0);
@@ -400,6 +422,12 @@ export function MessageView() {
     loadVisibleUsernames(identity.pseudonymId);
   }, [identity?.pseudonymId, loadVisibleUsernames]);
 
+  // Load the mute list from server
+  useEffect(() => {
+    if (!identity?.pseudonymId) return;
+    loadMutes(identity.pseudonymId);
+  }, [identity?.pseudonymId, loadMutes]);
+
   // Auto-scroll to bottom on new messages; preserve scroll position on prepend
   useEffect(() => {
     const el = containerRef.current;
@@ -436,6 +464,9 @@ export function MessageView() {
     }
   };
 
+  const visibleMessages = messages.filter((msg) => !muted.includes(msg.sender_pseudonym));
+  const hiddenSenders = muted.filter((p) => messages.some((msg) => msg.sender_pseudonym === p));
+
   if (!activeChannelId) {
     return (
       <div className="message-view empty">
@@ -447,7 +478,22 @@ export function MessageView() {
   return (
     <>
       <div className="message-view" ref={containerRef} onScroll={handleScroll}>
-        {messages.map((msg: Message) => (
+        {hiddenSenders.length > 0 && (
+          <div className="muted-notice">
+            Messages from muted participants are hidden.
+            {hiddenSenders.map((p) => (
+              <button
+                key={p}
+                className="unmute-btn"
+                onClick={() => pseudonymId && unmute(pseudonymId, p)}
+                title={p}
+              >
+                Unmute {p.slice(0, 12)}...
+              </button>
+            ))}
+          </div>
+        )}
+        {visibleMessages.map((msg: Message) => (
           <MessageBubble
             key={msg.message_id}
             message={msg}
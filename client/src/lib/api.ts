@@ -81,6 +81,9 @@ async function request<T>(path: string, options?: RequestInit): Promise<T> {
     const body = await res.text();
     throw new ApiError(res.status, body);
   }
+  if (res.status === 204) {
+    return undefined as T;
+  }
   return res.json() as Promise<T>;
 }
 
@@ -508,6 +511,48 @@ export async function getVisibleUsernames(
   });
 }
 
+// ── Blocks & Mutes ──
+
+export async function listBlocks(pseudonymId: string): Promise<{ blocked: string[] }> {
+  return request<{ blocked: string[] }>('/api/graph/blocks', {
+    headers: authHeaders(pseudonymId),
+  });
+}
+
+export async function blockParticipant(pseudonymId: string, target: string): Promise<void> {
+  return request<void>(`/api/graph/blocks/${encodeURIComponent(target)}`, {
+    method: 'PUT',
+    headers: authHeaders(pseudonymId),
+  });
+}
+
+export async function unblockParticipant(pseudonymId: string, target: string): Promise<void> {
+  return request<void>(`/api/graph/blocks/${encodeURIComponent(target)}`, {
+    method: 'DELETE',
+    headers: authHeaders(pseudonymId),
+  });
+}
+
+export async function listMutes(pseudonymId: string): Promise<{ muted: string[] }> {
+  return request<{ muted: string[] }>('/api/graph/mutes', {
+    headers: authHeaders(pseudonymId),
+  });
+}
+
+export async function muteParticipant(pseudonymId: string, target: string): Promise<void> {
+  return request<void>(`/api/graph/mutes/${encodeURIComponent(target)}`, {
+    method: 'PUT',
+    headers: authHeaders(pseudonymId),
+  });
+}
+
+export async function unmuteParticipant(pseudonymId: string, target: string): Promise<void> {
+  return request<void>(`/api/graph/mutes/${encodeURIComponent(target)}`, {
+    method: 'DELETE',
+    headers: authHeaders(pseudonymId),
+  });
+}
+
 // ── Remote Server Discovery (federation hopping) ──
 
 export async function getRemoteServerSummary(
@@ -0,0 +1,49 @@
+/**
+ * Mute list store — participants whose messages this client hides.
+ *
+ * Mutes are stored on the server so they follow the user across sessions,
+ * but filtering is done here; the server still delivers muted messages.
+ */
+
+import { create } from 'zustand';
+import * as api from '@/lib/api';
+
+interface MuteStore {
+  /** Pseudonyms the current user has muted. */
+  muted: string[];
+  /** Load the mute list from the server. */
+  loadMutes: (pseudonymId: string) => Promise<void>;
+  /** Mute a participant. */
+  mute: (pseudonymId: string, target: string) => Promise<void>;
+  /** Unmute a participant. */
+  unmute: (pseudonymId: string, target: string) => Promise<void>;
+  /** Clear the list (e.g., on disconnect or server switch). */
+  clear: () => void;
+}
+
+export const useMuteStore = create<MuteStore>((set) => ({
+  muted: [],
+
+  loadMutes: async (pseudonymId: string) => {
+    try {
+      const resp = await api.listMutes(pseudonymId);
+      set({ muted: resp.muted });
+    } catch (err) {
+      console.warn('Failed to load mutes:', err);
+    }
+  },
+
+  mute: async (pseudonymId: string, target: string) => {
+    await api.muteParticipant(pseudonymId, target);
+    set((s) => (s.muted.includes(target) ? s : { muted: [...s.muted, target] }));
+  },
+
+  unmute: async (pseudonymId: string, target: string) => {
+    await api.unmuteParticipant(pseudonymId, target);
+    set((s) => ({ muted: s.muted.filter((p) => p !== target) }));
+  },
+
+  clear: () => {
+    set({ muted: [] });
+  },
+}));
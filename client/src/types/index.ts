@@ -62,7 +62,6 @@ export interface Capabilities {
 /** Identity info from GET /api/identity/:pseudonymId. */
 export interface IdentityInfo {
   pseudonymId: string;
-  participantType: ParticipantType;
   active: boolean;
   capabilities: Capabilities;
 }
@@ -105,9 +104,17 @@ export interface WsSendFrame {
   messageId?: string;
 }
 
+/** Self-declared presence status (matches server PresenceStatus). */
+export type PresenceStatus = 'online' | 'away' | 'do_not_disturb' | 'invisible' | 'offline';
+
+/** WebSocket frames for presence status and idle tracking. */
+export type WsPresenceFrame =
+  | { type: 'set_status'; status: Exclude<PresenceStatus, 'offline'>; statusText?: string | null }
+  | { type: 'heartbeat' };
+
 /** WebSocket frame received from server. */
 export interface WsReceiveFrame {
-  type: 'message' | 'message_edited' | 'message_deleted' | 'rtx_bundle' | 'transcription' | 'error';
+  type: 'message' | 'message_edited' | 'message_deleted' | 'rtx_bundle' | 'transcription' | 'caption' | 'status' | 'error';
   // Message fields (camelCase from WsMessagePayload)
   channelId?: string;
   messageId?: string;
@@ -117,9 +124,16 @@ export interface WsReceiveFrame {
   createdAt?: string;
   editedAt?: string | null;
   deletedAt?: string | null;
-  // Transcription fields
+  // Transcription and caption fields
   speakerPseudonym?: string;
   text?: string;
+  segmentId?: number;
+  isFinal?: boolean;
+  startMs?: number;
+  endMs?: number;
+  // Own status fields
+  status?: PresenceStatus;
+  statusText?: string | null;
   // Error fields
   error?: string;
   message?: string;
@@ -180,15 +194,37 @@ export interface RateLimitConfig {
   registration_limit: number;
   verification_limit: number;
   default_limit: number;
+  connection_request_limit: number;
+}
+
+/** VRP reputation weights and decay (matches server ReputationConfig). */
+export interface ReputationConfig {
+  half_life_secs: number;
+  aligned_weight: number;
+  partial_weight: number;
+  conflict_weight: number;
+  incident_weight: number;
+  rtx_positive_weight: number;
+  rtx_negative_weight: number;
+  moderation_report_weight: number;
 }
 
 /** Server access mode. */
 export type AccessMode = 'public' | 'invite_only' | 'password';
 
+/** Differential-privacy budget for published graph statistics. */
+export interface GraphAnalyticsConfig {
+  epsilon: number;
+  min_group_size: number;
+  release_interval_secs: number;
+}
+
 /** Server policy (matches server ServerPolicy). */
 export interface ServerPolicy {
   agent_min_alignment_score: number;
   agent_required_capabilities: string[];
+  agent_optional_capabilities: string[];
+  negotiation_timeout_secs: number;
   federation_enabled: boolean;
   default_retention_days: number;
   voice_enabled: boolean;
@@ -205,6 +241,16 @@ export interface ServerPolicy {
   max_video_size_mb: number;
   max_file_size_mb: number;
   usernames_enabled: boolean;
+  agent_min_reputation: number;
+  reputation: ReputationConfig;
+  agent_redacted_topics: string[];
+  federation_redacted_topics: string[];
+  channel_redacted_topics: Record<string, string[]>;
+  contract_validity_secs: number;
+  contract_renewal_window_secs: number;
+  graph_analytics: GraphAnalyticsConfig;
+  presence_idle_timeout_secs: number;
+  graph_history_retention_days: number;
 }
 
 // ── Multi-Server Hub ──
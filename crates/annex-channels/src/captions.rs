@@ -0,0 +1,230 @@
+//! Live captions for voice channels.
+//!
+//! Captions are off by default and can only be enabled on `Voice` and
+//! `Hybrid` channels. Even then, a participant's speech is only transcribed
+//! after they consent via [`set_caption_consent`]. When `persist` is set,
+//! final captions are stored as ordinary messages through
+//! [`create_message`](crate::create_message) and so expire with the
+//! channel's retention.
+
+use crate::{get_channel, ChannelError};
+use annex_types::ChannelType;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// Caption settings of a channel.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CaptionSettings {
+    /// Whether speech in the channel is captioned.
+    pub enabled: bool,
+    /// Whether final captions are stored as channel messages.
+    pub persist: bool,
+}
+
+/// Returns a channel's caption settings.
+pub fn get_caption_settings(
+    conn: &Connection,
+    channel_id: &str,
+) -> Result<CaptionSettings, ChannelError> {
+    get_channel(conn, channel_id)?;
+    let settings = conn
+        .query_row(
+            "SELECT enabled, persist FROM channel_caption_settings WHERE channel_id = ?1",
+            [channel_id],
+            |row| {
+                Ok(CaptionSettings {
+                    enabled: row.get(0)?,
+                    persist: row.get(1)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(settings.unwrap_or_default())
+}
+
+/// Updates a channel's caption settings.
+///
+/// Fails with [`ChannelError::CaptionsUnsupported`] when enabling captions
+/// on a channel without voice.
+pub fn set_caption_settings(
+    conn: &Connection,
+    channel_id: &str,
+    settings: &CaptionSettings,
+) -> Result<(), ChannelError> {
+    let channel = get_channel(conn, channel_id)?;
+    if settings.enabled
+        && !matches!(
+            channel.channel_type,
+            ChannelType::Voice | ChannelType::Hybrid
+        )
+    {
+        return Err(ChannelError::CaptionsUnsupported(channel_id.to_string()));
+    }
+    conn.execute(
+        "INSERT INTO channel_caption_settings (channel_id, server_id, enabled, persist)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(channel_id) DO UPDATE SET
+           enabled = excluded.enabled,
+           persist = excluded.persist,
+           updated_at = datetime('now')",
+        params![
+            channel_id,
+            channel.server_id,
+            settings.enabled,
+            settings.persist
+        ],
+    )?;
+    Ok(())
+}
+
+/// Grants or withdraws a participant's consent to having their speech in a
+/// channel captioned.
+pub fn set_caption_consent(
+    conn: &Connection,
+    server_id: i64,
+    channel_id: &str,
+    pseudonym_id: &str,
+    consent: bool,
+) -> Result<(), ChannelError> {
+    get_channel(conn, channel_id)?;
+    if consent {
+        conn.execute(
+            "INSERT INTO caption_consents (channel_id, pseudonym_id, server_id)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(channel_id, pseudonym_id) DO NOTHING",
+            params![channel_id, pseudonym_id, server_id],
+        )?;
+    } else {
+        conn.execute(
+            "DELETE FROM caption_consents WHERE channel_id = ?1 AND pseudonym_id = ?2",
+            params![channel_id, pseudonym_id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns whether a participant consented to captions in a channel.
+pub fn has_caption_consent(
+    conn: &Connection,
+    channel_id: &str,
+    pseudonym_id: &str,
+) -> Result<bool, ChannelError> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(
+            SELECT 1 FROM caption_consents WHERE channel_id = ?1 AND pseudonym_id = ?2
+         )",
+        params![channel_id, pseudonym_id],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
+
+/// Lists the participants who consented to captions in a channel.
+pub fn list_caption_consents(
+    conn: &Connection,
+    channel_id: &str,
+) -> Result<Vec<String>, ChannelError> {
+    let mut stmt = conn.prepare(
+        "SELECT pseudonym_id FROM caption_consents WHERE channel_id = ?1 ORDER BY pseudonym_id",
+    )?;
+    let rows = stmt.query_map([channel_id], |row| row.get(0))?;
+    Ok(rows.collect::<Result<Vec<String>, _>>()?)
+}
+
+/// Deletes a channel's caption settings and consents.
+pub(crate) fn delete_channel_captions(
+    conn: &Connection,
+    channel_id: &str,
+) -> Result<(), ChannelError> {
+    conn.execute(
+        "DELETE FROM caption_consents WHERE channel_id = ?1",
+        [channel_id],
+    )?;
+    conn.execute(
+        "DELETE FROM channel_caption_settings WHERE channel_id = ?1",
+        [channel_id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_channel, delete_channel, CreateChannelParams};
+    use annex_db::run_migrations;
+    use annex_types::{FederationScope, ServerPolicy};
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().expect("failed to open in-memory db");
+        run_migrations(&conn).expect("failed to run migrations");
+        let policy_json =
+            serde_json::to_string(&ServerPolicy::default()).expect("failed to serialize policy");
+        conn.execute(
+            "INSERT INTO servers (slug, label, policy_json) VALUES ('test-server', 'Test Server', ?1)",
+            [policy_json],
+        )
+        .expect("failed to create dummy server");
+        for (channel_id, channel_type) in [
+            ("voice-1", ChannelType::Voice),
+            ("text-1", ChannelType::Text),
+        ] {
+            create_channel(
+                &conn,
+                &CreateChannelParams {
+                    server_id: 1,
+                    channel_id: channel_id.to_string(),
+                    name: channel_id.to_string(),
+                    channel_type,
+                    topic: None,
+                    vrp_topic_binding: None,
+                    required_capabilities_json: None,
+                    required_roles_json: None,
+                    agent_min_alignment: None,
+                    retention_days: None,
+                    federation_scope: FederationScope::Local,
+                },
+            )
+            .expect("create channel failed");
+        }
+        conn
+    }
+
+    #[test]
+    fn test_caption_settings_and_consent() {
+        let conn = setup_db();
+        assert_eq!(
+            get_caption_settings(&conn, "voice-1").unwrap(),
+            CaptionSettings::default()
+        );
+
+        let settings = CaptionSettings {
+            enabled: true,
+            persist: true,
+        };
+        set_caption_settings(&conn, "voice-1", &settings).unwrap();
+        assert_eq!(get_caption_settings(&conn, "voice-1").unwrap(), settings);
+        assert!(matches!(
+            set_caption_settings(&conn, "text-1", &settings),
+            Err(ChannelError::CaptionsUnsupported(_))
+        ));
+
+        assert!(!has_caption_consent(&conn, "voice-1", "alice").unwrap());
+        set_caption_consent(&conn, 1, "voice-1", "alice", true).unwrap();
+        set_caption_consent(&conn, 1, "voice-1", "alice", true).unwrap();
+        set_caption_consent(&conn, 1, "voice-1", "bob", true).unwrap();
+        assert_eq!(
+            list_caption_consents(&conn, "voice-1").unwrap(),
+            vec!["alice", "bob"]
+        );
+        set_caption_consent(&conn, 1, "voice-1", "bob", false).unwrap();
+        assert!(has_caption_consent(&conn, "voice-1", "alice").unwrap());
+        assert!(!has_caption_consent(&conn, "voice-1", "bob").unwrap());
+
+        delete_channel(&conn, "voice-1").unwrap();
+        assert!(!has_caption_consent(&conn, "voice-1", "alice").unwrap());
+        assert!(matches!(
+            get_caption_settings(&conn, "voice-1"),
+            Err(ChannelError::NotFound(_))
+        ));
+    }
+}
@@ -12,6 +12,18 @@ use rusqlite::{params, Connection, OptionalExtension, Row};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub mod captions;
+pub mod polls;
+
+pub use captions::{
+    get_caption_settings, has_caption_consent, list_caption_consents, set_caption_consent,
+    set_caption_settings, CaptionSettings,
+};
+pub use polls::{
+    close_expired_polls, close_poll, create_poll, get_poll, is_poll_closed, list_poll_eligibility,
+    list_polls, record_poll_vote, tally_poll, CreatePollParams, Poll, PollTally,
+};
+
 /// Errors that can occur during channel operations.
 #[derive(Debug, Error)]
 pub enum ChannelError {
@@ -21,6 +33,12 @@ pub enum ChannelError {
     NotFound(String),
     #[error("json serialization error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("poll is closed: {0}")]
+    PollClosed(String),
+    #[error("invalid poll option: {0}")]
+    InvalidPollOption(u32),
+    #[error("channel does not support captions: {0}")]
+    CaptionsUnsupported(String),
 }
 
 /// A communication channel.
@@ -44,6 +62,10 @@ pub struct Channel {
     pub vrp_topic_binding: Option<String>,
     /// JSON string of required capabilities.
     pub required_capabilities_json: Option<String>,
+    /// JSON array of [`RoleCode`](annex_types::RoleCode)s; joining requires a
+    /// ZK proof that the member's role is in this set.
+    #[serde(default)]
+    pub required_roles_json: Option<String>,
     /// Minimum alignment status for agents to join.
     pub agent_min_alignment: Option<AlignmentStatus>,
     /// Message retention in days (None = use server default).
@@ -64,6 +86,7 @@ pub struct CreateChannelParams {
     pub topic: Option<String>,
     pub vrp_topic_binding: Option<String>,
     pub required_capabilities_json: Option<String>,
+    pub required_roles_json: Option<String>,
     pub agent_min_alignment: Option<AlignmentStatus>,
     pub retention_days: Option<u32>,
     pub federation_scope: FederationScope,
@@ -76,6 +99,7 @@ pub struct UpdateChannelParams {
     pub topic: Option<String>,
     pub vrp_topic_binding: Option<String>,
     pub required_capabilities_json: Option<String>,
+    pub required_roles_json: Option<String>,
     pub agent_min_alignment: Option<AlignmentStatus>,
     pub retention_days: Option<u32>,
     pub federation_scope: Option<FederationScope>,
@@ -94,8 +118,8 @@ pub fn create_channel(conn: &Connection, params: &CreateChannelParams) -> Result
         "INSERT INTO channels (
             server_id, channel_id, name, channel_type, topic,
             vrp_topic_binding, required_capabilities_json, agent_min_alignment,
-            retention_days, federation_scope
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            retention_days, federation_scope, required_roles_json
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
         params![
             params.server_id,
             params.channel_id,
@@ -107,6 +131,7 @@ pub fn create_channel(conn: &Connection, params: &CreateChannelParams) -> Result
             alignment_json,
             params.retention_days,
             federation_scope_json,
+            params.required_roles_json,
         ],
     )?;
     Ok(())
@@ -118,7 +143,7 @@ pub fn get_channel(conn: &Connection, channel_id: &str) -> Result<Channel, Chann
         "SELECT
             id, server_id, channel_id, name, channel_type, topic,
             vrp_topic_binding, required_capabilities_json, agent_min_alignment,
-            retention_days, federation_scope, created_at
+            retention_days, federation_scope, created_at, required_roles_json
         FROM channels WHERE channel_id = ?1",
         [channel_id],
         map_row_to_channel,
@@ -133,7 +158,7 @@ pub fn list_channels(conn: &Connection, server_id: i64) -> Result<Vec<Channel>,
         "SELECT
             id, server_id, channel_id, name, channel_type, topic,
             vrp_topic_binding, required_capabilities_json, agent_min_alignment,
-            retention_days, federation_scope, created_at
+            retention_days, federation_scope, created_at, required_roles_json
         FROM channels WHERE server_id = ?1 ORDER BY name ASC
         LIMIT 1000",
     )?;
@@ -157,7 +182,7 @@ pub fn list_federated_channels(
         "SELECT
             id, server_id, channel_id, name, channel_type, topic,
             vrp_topic_binding, required_capabilities_json, agent_min_alignment,
-            retention_days, federation_scope, created_at
+            retention_days, federation_scope, created_at, required_roles_json
         FROM channels
         WHERE server_id = ?1 AND federation_scope = ?2
         ORDER BY name ASC",
@@ -205,6 +230,11 @@ pub fn update_channel(
         values.push(Box::new(caps.clone()));
         idx += 1;
     }
+    if let Some(roles) = &updates.required_roles_json {
+        set_parts.push(format!("required_roles_json = ?{}", idx));
+        values.push(Box::new(roles.clone()));
+        idx += 1;
+    }
     if let Some(align) = &updates.agent_min_alignment {
         let json = serde_json::to_string(align)?;
         set_parts.push(format!("agent_min_alignment = ?{}", idx));
@@ -253,6 +283,8 @@ pub fn update_channel(
 /// atomicity with other operations is required.
 pub fn delete_channel(conn: &Connection, channel_id: &str) -> Result<(), ChannelError> {
     // Delete child rows first to satisfy FK constraints.
+    polls::delete_channel_polls(conn, channel_id)?;
+    captions::delete_channel_captions(conn, channel_id)?;
     conn.execute("DELETE FROM messages WHERE channel_id = ?1", [channel_id])?;
     conn.execute(
         "DELETE FROM channel_members WHERE channel_id = ?1",
@@ -294,6 +326,7 @@ fn map_row_to_channel(row: &Row) -> rusqlite::Result<Channel> {
         topic: row.get(5)?,
         vrp_topic_binding: row.get(6)?,
         required_capabilities_json: row.get(7)?,
+        required_roles_json: row.get(12)?,
         agent_min_alignment,
         retention_days: row.get(9)?,
         federation_scope,
@@ -834,6 +867,7 @@ mod tests {
             topic: Some("General discussion".to_string()),
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: Some(AlignmentStatus::Aligned),
             retention_days: Some(30),
             federation_scope: FederationScope::Local,
@@ -859,6 +893,7 @@ mod tests {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: None,
             federation_scope: None,
@@ -892,6 +927,7 @@ mod tests {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: Some(7),
             federation_scope: FederationScope::Local,
@@ -948,6 +984,7 @@ mod tests {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: None, // Use server default
             federation_scope: FederationScope::Local,
@@ -981,6 +1018,7 @@ mod tests {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: None,
             federation_scope: FederationScope::Local,
@@ -1039,6 +1077,7 @@ mod tests {
             topic: Some("original".to_string()),
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: None,
             federation_scope: FederationScope::Local,
@@ -1079,6 +1118,7 @@ mod tests {
             topic: Some("old topic".to_string()),
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: Some(7),
             federation_scope: FederationScope::Local,
@@ -1118,6 +1158,7 @@ mod tests {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: None,
             federation_scope: FederationScope::Local,
@@ -1196,6 +1237,7 @@ mod tests {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: None,
             federation_scope: FederationScope::Local,
@@ -1245,6 +1287,7 @@ mod tests {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: None,
             federation_scope: FederationScope::Local,
@@ -1294,6 +1337,7 @@ mod tests {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: None,
             federation_scope: FederationScope::Local,
@@ -0,0 +1,413 @@
+//! Anonymous channel polls.
+//!
+//! Ballots are stored without any voter identifier. Double-vote prevention is
+//! the caller's responsibility: the server records the poll-scoped ZK
+//! nullifier in `zk_nullifiers` within the same transaction as
+//! [`record_poll_vote`].
+//!
+//! Each poll also stores an eligibility snapshot: the identity commitments of
+//! the channel's members when the poll was created, in Merkle leaf order,
+//! together with the resulting root. Vote proofs are checked against that
+//! root.
+
+use crate::ChannelError;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+/// A poll attached to a channel.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Poll {
+    /// Internal database ID.
+    #[serde(skip_serializing, default)]
+    pub id: i64,
+    /// ID of the server this poll belongs to.
+    #[serde(skip_serializing, default)]
+    pub server_id: i64,
+    /// Unique public ID for the poll.
+    pub poll_id: String,
+    /// Channel the poll is attached to.
+    pub channel_id: String,
+    /// The question being asked.
+    pub question: String,
+    /// Option labels, addressed by index when voting.
+    pub options: Vec<String>,
+    /// Pseudonym of the poll author.
+    pub created_by: String,
+    /// Scheduled close time (None = open until closed manually).
+    pub closes_at: Option<String>,
+    /// Time the poll was closed (None = still open).
+    pub closed_at: Option<String>,
+    /// Creation timestamp.
+    pub created_at: String,
+    /// Root of the eligibility tree ballots must prove against (None for
+    /// polls created before eligibility snapshots existed).
+    pub eligibility_root_hex: Option<String>,
+}
+
+/// Parameters for creating a new poll.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePollParams {
+    pub server_id: i64,
+    pub poll_id: String,
+    pub channel_id: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub created_by: String,
+    /// Seconds from now until the poll closes automatically.
+    pub duration_secs: Option<u64>,
+    /// Root of the eligibility tree built from `eligible_commitments`.
+    pub eligibility_root_hex: String,
+    /// Commitments of the eligible voters, in leaf order.
+    pub eligible_commitments: Vec<String>,
+}
+
+/// Per-option vote counts for a poll.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PollTally {
+    pub poll_id: String,
+    /// Vote count for each option, in option order.
+    pub counts: Vec<u64>,
+    pub total_votes: u64,
+}
+
+const POLL_COLUMNS: &str = "id, server_id, poll_id, channel_id, question, options_json, \
+     created_by, closes_at, closed_at, created_at, eligibility_root_hex";
+
+/// Creates a new poll in a channel together with its eligibility snapshot.
+///
+/// Callers should run this inside a transaction so the poll never exists
+/// without its leaves.
+pub fn create_poll(conn: &Connection, params: &CreatePollParams) -> Result<Poll, ChannelError> {
+    let options_json = serde_json::to_string(&params.options)?;
+
+    // `duration_secs` is a u64, so formatting it into the modifier is safe.
+    let closes_expr = match params.duration_secs {
+        Some(secs) => format!("datetime('now', '+{} seconds')", secs),
+        None => "NULL".to_string(),
+    };
+
+    let sql = format!(
+        "INSERT INTO polls (
+            server_id, poll_id, channel_id, question, options_json, created_by,
+            eligibility_root_hex, closes_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, {})
+        RETURNING {}",
+        closes_expr, POLL_COLUMNS
+    );
+
+    let poll = conn.query_row(
+        &sql,
+        params![
+            params.server_id,
+            params.poll_id,
+            params.channel_id,
+            params.question,
+            options_json,
+            params.created_by,
+            params.eligibility_root_hex,
+        ],
+        map_row_to_poll,
+    )?;
+
+    let mut stmt = conn.prepare(
+        "INSERT INTO poll_eligibility (poll_id, leaf_index, commitment_hex) VALUES (?1, ?2, ?3)",
+    )?;
+    for (index, commitment_hex) in params.eligible_commitments.iter().enumerate() {
+        stmt.execute(params![params.poll_id, index as i64, commitment_hex])?;
+    }
+    Ok(poll)
+}
+
+/// Retrieves a poll by its public ID.
+pub fn get_poll(conn: &Connection, poll_id: &str) -> Result<Poll, ChannelError> {
+    conn.query_row(
+        &format!("SELECT {} FROM polls WHERE poll_id = ?1", POLL_COLUMNS),
+        [poll_id],
+        map_row_to_poll,
+    )
+    .optional()?
+    .ok_or_else(|| ChannelError::NotFound(poll_id.to_string()))
+}
+
+/// Lists polls attached to a channel, newest first (capped at 100).
+pub fn list_polls(conn: &Connection, channel_id: &str) -> Result<Vec<Poll>, ChannelError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM polls WHERE channel_id = ?1 ORDER BY created_at DESC, id DESC LIMIT 100",
+        POLL_COLUMNS
+    ))?;
+    let polls = stmt
+        .query_map([channel_id], map_row_to_poll)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(polls)
+}
+
+/// Lists the commitments in a poll's eligibility snapshot, in leaf order.
+pub fn list_poll_eligibility(
+    conn: &Connection,
+    poll_id: &str,
+) -> Result<Vec<String>, ChannelError> {
+    let mut stmt = conn.prepare(
+        "SELECT commitment_hex FROM poll_eligibility WHERE poll_id = ?1 ORDER BY leaf_index ASC",
+    )?;
+    let leaves = stmt
+        .query_map([poll_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(leaves)
+}
+
+/// Returns `true` if the poll has been closed or its close time has passed.
+pub fn is_poll_closed(conn: &Connection, poll_id: &str) -> Result<bool, ChannelError> {
+    conn.query_row(
+        "SELECT closed_at IS NOT NULL
+                OR (closes_at IS NOT NULL AND closes_at <= datetime('now'))
+         FROM polls WHERE poll_id = ?1",
+        [poll_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| ChannelError::NotFound(poll_id.to_string()))
+}
+
+/// Records an anonymous ballot for `option_index`.
+///
+/// # Errors
+///
+/// Returns [`ChannelError::PollClosed`] if the poll no longer accepts votes and
+/// [`ChannelError::InvalidPollOption`] if the index is out of range.
+pub fn record_poll_vote(
+    conn: &Connection,
+    poll_id: &str,
+    option_index: u32,
+) -> Result<(), ChannelError> {
+    let poll = get_poll(conn, poll_id)?;
+    if is_poll_closed(conn, poll_id)? {
+        return Err(ChannelError::PollClosed(poll_id.to_string()));
+    }
+    if option_index as usize >= poll.options.len() {
+        return Err(ChannelError::InvalidPollOption(option_index));
+    }
+
+    conn.execute(
+        "INSERT INTO poll_votes (poll_id, option_index) VALUES (?1, ?2)",
+        params![poll_id, option_index],
+    )?;
+    Ok(())
+}
+
+/// Computes per-option vote counts for a poll.
+pub fn tally_poll(conn: &Connection, poll_id: &str) -> Result<PollTally, ChannelError> {
+    let poll = get_poll(conn, poll_id)?;
+    let mut counts = vec![0u64; poll.options.len()];
+
+    let mut stmt = conn.prepare(
+        "SELECT option_index, COUNT(*) FROM poll_votes WHERE poll_id = ?1 GROUP BY option_index",
+    )?;
+    let rows = stmt.query_map([poll_id], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+    })?;
+    for row in rows {
+        let (index, count) = row?;
+        if let Some(slot) = usize::try_from(index).ok().and_then(|i| counts.get_mut(i)) {
+            *slot = count as u64;
+        }
+    }
+
+    let total_votes = counts.iter().sum();
+    Ok(PollTally {
+        poll_id: poll.poll_id,
+        counts,
+        total_votes,
+    })
+}
+
+/// Closes a poll immediately. Returns `false` if it was already closed.
+pub fn close_poll(conn: &Connection, poll_id: &str) -> Result<bool, ChannelError> {
+    // Ensure the poll exists so callers get a NotFound rather than `false`.
+    get_poll(conn, poll_id)?;
+    let count = conn.execute(
+        "UPDATE polls SET closed_at = datetime('now') WHERE poll_id = ?1 AND closed_at IS NULL",
+        [poll_id],
+    )?;
+    Ok(count > 0)
+}
+
+/// Marks every poll whose close time has passed as closed and returns them,
+/// so the caller can broadcast final results.
+pub fn close_expired_polls(conn: &Connection) -> Result<Vec<Poll>, ChannelError> {
+    let mut stmt = conn.prepare(&format!(
+        "UPDATE polls SET closed_at = datetime('now')
+         WHERE closed_at IS NULL AND closes_at IS NOT NULL AND closes_at <= datetime('now')
+         RETURNING {}",
+        POLL_COLUMNS
+    ))?;
+    let polls = stmt
+        .query_map([], map_row_to_poll)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(polls)
+}
+
+/// Deletes all polls (and their ballots) attached to a channel.
+pub(crate) fn delete_channel_polls(
+    conn: &Connection,
+    channel_id: &str,
+) -> Result<(), ChannelError> {
+    conn.execute(
+        "DELETE FROM poll_votes WHERE poll_id IN (SELECT poll_id FROM polls WHERE channel_id = ?1)",
+        [channel_id],
+    )?;
+    conn.execute(
+        "DELETE FROM poll_eligibility WHERE poll_id IN (SELECT poll_id FROM polls WHERE channel_id = ?1)",
+        [channel_id],
+    )?;
+    conn.execute("DELETE FROM polls WHERE channel_id = ?1", [channel_id])?;
+    Ok(())
+}
+
+fn map_row_to_poll(row: &Row) -> rusqlite::Result<Poll> {
+    let options_json: String = row.get(5)?;
+    let options: Vec<String> = serde_json::from_str(&options_json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+
+    Ok(Poll {
+        id: row.get(0)?,
+        server_id: row.get(1)?,
+        poll_id: row.get(2)?,
+        channel_id: row.get(3)?,
+        question: row.get(4)?,
+        options,
+        created_by: row.get(6)?,
+        closes_at: row.get(7)?,
+        closed_at: row.get(8)?,
+        created_at: row.get(9)?,
+        eligibility_root_hex: row.get(10)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_channel, delete_channel, CreateChannelParams};
+    use annex_db::run_migrations;
+    use annex_types::{ChannelType, FederationScope, ServerPolicy};
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().expect("failed to open in-memory db");
+        run_migrations(&conn).expect("failed to run migrations");
+        let policy_json =
+            serde_json::to_string(&ServerPolicy::default()).expect("failed to serialize policy");
+        conn.execute(
+            "INSERT INTO servers (slug, label, policy_json) VALUES ('test-server', 'Test Server', ?1)",
+            [policy_json],
+        )
+        .expect("failed to create dummy server");
+        create_channel(
+            &conn,
+            &CreateChannelParams {
+                server_id: 1,
+                channel_id: "chan-1".to_string(),
+                name: "General".to_string(),
+                channel_type: ChannelType::Text,
+                topic: None,
+                vrp_topic_binding: None,
+                required_capabilities_json: None,
+                required_roles_json: None,
+                agent_min_alignment: None,
+                retention_days: None,
+                federation_scope: FederationScope::Local,
+            },
+        )
+        .expect("create channel failed");
+        conn
+    }
+
+    fn poll_params(poll_id: &str, duration_secs: Option<u64>) -> CreatePollParams {
+        CreatePollParams {
+            server_id: 1,
+            poll_id: poll_id.to_string(),
+            channel_id: "chan-1".to_string(),
+            question: "Lunch?".to_string(),
+            options: vec!["Pizza".to_string(), "Salad".to_string()],
+            created_by: "author".to_string(),
+            duration_secs,
+            eligibility_root_hex: "00".repeat(32),
+            eligible_commitments: vec!["aa".repeat(32), "bb".repeat(32)],
+        }
+    }
+
+    #[test]
+    fn test_poll_vote_and_tally() {
+        let conn = setup_db();
+        let poll = create_poll(&conn, &poll_params("poll-1", Some(3600))).expect("create failed");
+        assert_eq!(poll.options.len(), 2);
+        assert!(poll.closes_at.is_some());
+
+        record_poll_vote(&conn, "poll-1", 0).expect("vote failed");
+        record_poll_vote(&conn, "poll-1", 1).expect("vote failed");
+        record_poll_vote(&conn, "poll-1", 1).expect("vote failed");
+
+        let tally = tally_poll(&conn, "poll-1").expect("tally failed");
+        assert_eq!(tally.counts, vec![1, 2]);
+        assert_eq!(tally.total_votes, 3);
+
+        assert!(matches!(
+            record_poll_vote(&conn, "poll-1", 2),
+            Err(ChannelError::InvalidPollOption(2))
+        ));
+
+        let polls = list_polls(&conn, "chan-1").expect("list failed");
+        assert_eq!(polls.len(), 1);
+        assert_eq!(polls[0].eligibility_root_hex, Some("00".repeat(32)));
+        assert_eq!(
+            list_poll_eligibility(&conn, "poll-1").expect("leaves failed"),
+            vec!["aa".repeat(32), "bb".repeat(32)]
+        );
+    }
+
+    #[test]
+    fn test_closed_poll_rejects_votes() {
+        let conn = setup_db();
+        create_poll(&conn, &poll_params("poll-1", None)).expect("create failed");
+
+        assert!(close_poll(&conn, "poll-1").expect("close failed"));
+        assert!(!close_poll(&conn, "poll-1").expect("close failed"));
+        assert!(matches!(
+            record_poll_vote(&conn, "poll-1", 0),
+            Err(ChannelError::PollClosed(_))
+        ));
+    }
+
+    #[test]
+    fn test_close_expired_polls() {
+        let conn = setup_db();
+        create_poll(&conn, &poll_params("open", None)).expect("create failed");
+        create_poll(&conn, &poll_params("expiring", Some(3600))).expect("create failed");
+        conn.execute(
+            "UPDATE polls SET closes_at = datetime('now', '-1 minute') WHERE poll_id = 'expiring'",
+            [],
+        )
+        .expect("backdate failed");
+
+        let closed = close_expired_polls(&conn).expect("sweep failed");
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].poll_id, "expiring");
+        assert!(closed[0].closed_at.is_some());
+        assert!(!is_poll_closed(&conn, "open").expect("query failed"));
+    }
+
+    #[test]
+    fn test_delete_channel_removes_polls() {
+        let conn = setup_db();
+        create_poll(&conn, &poll_params("poll-1", None)).expect("create failed");
+        record_poll_vote(&conn, "poll-1", 0).expect("vote failed");
+
+        delete_channel(&conn, "chan-1").expect("delete failed");
+        assert!(matches!(
+            get_poll(&conn, "poll-1"),
+            Err(ChannelError::NotFound(_))
+        ));
+        assert!(list_poll_eligibility(&conn, "poll-1")
+            .expect("leaves failed")
+            .is_empty());
+    }
+}
@@ -139,6 +139,86 @@ const MIGRATIONS: &[Migration] = &[
         name: "030_federated_identity_verification",
         sql: include_str!("migrations/030_federated_identity_verification.sql"),
     },
+    Migration {
+        name: "031_polls",
+        sql: include_str!("migrations/031_polls.sql"),
+    },
+    Migration {
+        name: "032_channel_required_roles",
+        sql: include_str!("migrations/032_channel_required_roles.sql"),
+    },
+    Migration {
+        name: "033_identity_provenance",
+        sql: include_str!("migrations/033_identity_provenance.sql"),
+    },
+    Migration {
+        name: "034_devices",
+        sql: include_str!("migrations/034_devices.sql"),
+    },
+    Migration {
+        name: "035_vrp_topic_deprecation",
+        sql: include_str!("migrations/035_vrp_topic_deprecation.sql"),
+    },
+    Migration {
+        name: "036_vrp_negotiations",
+        sql: include_str!("migrations/036_vrp_negotiations.sql"),
+    },
+    Migration {
+        name: "037_reputation_signals",
+        sql: include_str!("migrations/037_reputation_signals.sql"),
+    },
+    Migration {
+        name: "038_contract_expiry",
+        sql: include_str!("migrations/038_contract_expiry.sql"),
+    },
+    Migration {
+        name: "039_graph_connection_requests",
+        sql: include_str!("migrations/039_graph_connection_requests.sql"),
+    },
+    Migration {
+        name: "040_graph_visibility_preferences",
+        sql: include_str!("migrations/040_graph_visibility_preferences.sql"),
+    },
+    Migration {
+        name: "041_graph_blocks_and_mutes",
+        sql: include_str!("migrations/041_graph_blocks_and_mutes.sql"),
+    },
+    Migration {
+        name: "042_graph_analytics_releases",
+        sql: include_str!("migrations/042_graph_analytics_releases.sql"),
+    },
+    Migration {
+        name: "043_presence_statuses",
+        sql: include_str!("migrations/043_presence_statuses.sql"),
+    },
+    Migration {
+        name: "044_graph_history",
+        sql: include_str!("migrations/044_graph_history.sql"),
+    },
+    Migration {
+        name: "045_channel_captions",
+        sql: include_str!("migrations/045_channel_captions.sql"),
+    },
+    Migration {
+        name: "046_instance_anchor_tracking",
+        sql: include_str!("migrations/046_instance_anchor_tracking.sql"),
+    },
+    Migration {
+        name: "047_instance_renewal_tracking",
+        sql: include_str!("migrations/047_instance_renewal_tracking.sql"),
+    },
+    Migration {
+        name: "048_identity_bundle_nonce",
+        sql: include_str!("migrations/048_identity_bundle_nonce.sql"),
+    },
+    Migration {
+        name: "049_role_nullifiers",
+        sql: include_str!("migrations/049_role_nullifiers.sql"),
+    },
+    Migration {
+        name: "050_poll_eligibility",
+        sql: include_str!("migrations/050_poll_eligibility.sql"),
+    },
 ];
 
 /// Errors that can occur during migration execution.
@@ -252,7 +332,7 @@ mod tests {
     fn run_migrations_on_fresh_db() {
         let conn = Connection::open_in_memory().expect("should open in-memory db");
         let applied = run_migrations(&conn).expect("migrations should succeed");
-        assert_eq!(applied, 31, "should apply all migrations");
+        assert_eq!(applied, 51, "should apply all migrations");
 
         // Verify tracking table exists and has a record
         let count: i32 = conn
@@ -260,7 +340,7 @@ mod tests {
                 row.get(0)
             })
             .expect("should query migration count");
-        assert_eq!(count, 31);
+        assert_eq!(count, 51);
     }
 
     #[test]
@@ -268,7 +348,7 @@ mod tests {
         let conn = Connection::open_in_memory().expect("should open in-memory db");
 
         let first = run_migrations(&conn).expect("first run should succeed");
-        assert_eq!(first, 31);
+        assert_eq!(first, 51);
 
         let second = run_migrations(&conn).expect("second run should succeed");
         assert_eq!(second, 0, "no new migrations to apply");
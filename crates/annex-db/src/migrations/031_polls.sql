@@ -0,0 +1,31 @@
+-- Anonymous channel polls.
+-- Votes are authorized by a ZK proof carrying a poll-scoped nullifier
+-- (tracked in zk_nullifiers under topic 'annex:poll:<poll_id>').
+CREATE TABLE polls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL,
+    poll_id TEXT NOT NULL UNIQUE,
+    channel_id TEXT NOT NULL,
+    question TEXT NOT NULL,
+    options_json TEXT NOT NULL,        -- JSON array of option labels
+    created_by TEXT NOT NULL,          -- pseudonym of the poll author
+    closes_at TEXT,                    -- NULL = open until closed manually
+    closed_at TEXT,                    -- NULL = still open
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id),
+    FOREIGN KEY (channel_id) REFERENCES channels(channel_id)
+);
+
+CREATE INDEX idx_polls_channel ON polls(channel_id, created_at);
+CREATE INDEX idx_polls_open ON polls(closes_at) WHERE closed_at IS NULL;
+
+-- Individual ballots. Deliberately carries no voter pseudonym and no
+-- timestamp so a ballot cannot be correlated with channel activity.
+CREATE TABLE poll_votes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    poll_id TEXT NOT NULL,
+    option_index INTEGER NOT NULL,
+    FOREIGN KEY (poll_id) REFERENCES polls(poll_id)
+);
+
+CREATE INDEX idx_poll_votes_poll ON poll_votes(poll_id);
@@ -0,0 +1,3 @@
+-- Role-gated channels: a JSON array of RoleCodes that joining members must
+-- prove membership in via a selective-disclosure ZK proof.
+ALTER TABLE channels ADD COLUMN required_roles_json TEXT;
@@ -0,0 +1,18 @@
+-- Provenance of identities imported from another server via a signed
+-- identity bundle. The full bundle is kept so imported profile data
+-- (usernames, voice profile) can be restored once the holder derives
+-- pseudonyms locally.
+CREATE TABLE identity_provenance (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL,
+    commitment_hex TEXT NOT NULL,
+    origin_instance_id INTEGER NOT NULL,
+    origin_server TEXT NOT NULL,
+    bundle_json TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    issued_at TEXT NOT NULL,
+    imported_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (server_id, commitment_hex),
+    FOREIGN KEY (server_id) REFERENCES servers(id),
+    FOREIGN KEY (origin_instance_id) REFERENCES instances(id)
+);
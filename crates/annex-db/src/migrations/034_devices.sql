@@ -0,0 +1,19 @@
+-- Devices registered under a platform identity. Each device holds its own
+-- Ed25519 key and authenticates its WebSocket sessions independently, so a
+-- single device can be revoked without affecting the others.
+CREATE TABLE devices (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL,
+    pseudonym_id TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    label TEXT NOT NULL,
+    public_key_hex TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    last_seen_at TEXT,
+    revoked_at TEXT,
+    UNIQUE (server_id, device_id),
+    UNIQUE (server_id, public_key_hex),
+    FOREIGN KEY (server_id) REFERENCES servers(id)
+);
+
+CREATE INDEX idx_devices_pseudonym ON devices(server_id, pseudonym_id);
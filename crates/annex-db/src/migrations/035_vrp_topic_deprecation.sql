@@ -0,0 +1 @@
+ALTER TABLE vrp_topics ADD COLUMN deprecated_at TEXT;
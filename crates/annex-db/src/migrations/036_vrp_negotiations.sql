@@ -0,0 +1,22 @@
+-- Multi-round VRP capability contract negotiations.
+CREATE TABLE vrp_negotiations (
+    id TEXT PRIMARY KEY,
+    server_id INTEGER NOT NULL,
+    peer_id TEXT NOT NULL, -- agent pseudonym or peer base URL
+    peer_type TEXT NOT NULL, -- AI_AGENT | SERVER
+    status TEXT NOT NULL DEFAULT 'OPEN', -- OPEN | ACCEPTED | REJECTED | EXPIRED
+    round INTEGER NOT NULL DEFAULT 1,
+    alignment_status TEXT NOT NULL,
+    anchor_snapshot_json TEXT NOT NULL,
+    explanation_json TEXT NOT NULL,
+    peer_offer_json TEXT NOT NULL,
+    local_offer_json TEXT NOT NULL,
+    outcome_reason TEXT,
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id)
+);
+
+CREATE INDEX idx_vrp_negotiations_peer
+    ON vrp_negotiations(server_id, peer_type, peer_id);
@@ -0,0 +1,27 @@
+-- Reputation signals beyond VRP handshakes: operator-flagged incidents,
+-- RTX feedback and moderation reports. Combined with vrp_handshake_log to
+-- compute time-decayed reputation.
+CREATE TABLE reputation_signals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER NOT NULL,
+    peer_pseudonym TEXT NOT NULL,
+    kind TEXT NOT NULL, -- INCIDENT | RTX_POSITIVE | RTX_NEGATIVE | MODERATION_REPORT
+    source_pseudonym TEXT NOT NULL, -- moderator, reviewer or reporter
+    reference TEXT, -- RTX bundle ID for feedback
+    note TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id)
+);
+
+CREATE INDEX idx_reputation_signals_peer
+    ON reputation_signals(server_id, peer_pseudonym);
+
+-- One feedback entry per reviewer per bundle.
+CREATE UNIQUE INDEX idx_reputation_signals_rtx_feedback
+    ON reputation_signals(server_id, source_pseudonym, reference)
+    WHERE kind IN ('RTX_POSITIVE', 'RTX_NEGATIVE');
+
+-- One moderation report per reporter per peer.
+CREATE UNIQUE INDEX idx_reputation_signals_report
+    ON reputation_signals(server_id, peer_pseudonym, source_pseudonym)
+    WHERE kind = 'MODERATION_REPORT';
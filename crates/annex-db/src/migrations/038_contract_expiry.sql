@@ -0,0 +1,17 @@
+-- Capability contracts carry a validity period. The renewal task asks the
+-- agent or peer to re-handshake once `expires_at` falls inside the policy's
+-- renewal window, and downgrades the transfer scope if the contract lapses.
+-- NULL `expires_at` means the contract never expires.
+ALTER TABLE agent_registrations ADD COLUMN expires_at TEXT;
+ALTER TABLE agent_registrations ADD COLUMN renewal_requested_at TEXT;
+
+ALTER TABLE federation_agreements ADD COLUMN expires_at TEXT;
+ALTER TABLE federation_agreements ADD COLUMN renewal_requested_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_agent_registrations_expires_at
+    ON agent_registrations(server_id, expires_at)
+    WHERE expires_at IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_federation_agreements_expires_at
+    ON federation_agreements(local_server_id, expires_at)
+    WHERE expires_at IS NOT NULL;
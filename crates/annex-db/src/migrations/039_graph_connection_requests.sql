@@ -0,0 +1,19 @@
+-- Participant-initiated connection requests. Accepting a request creates
+-- CONNECTED edges between the two parties in graph_edges.
+CREATE TABLE graph_connection_requests (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  server_id INTEGER NOT NULL,
+  from_node TEXT NOT NULL,
+  to_node TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'PENDING',   -- PENDING | ACCEPTED | DECLINED
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  responded_at TEXT
+);
+
+-- At most one pending request per ordered pair.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_graph_connection_requests_pending
+    ON graph_connection_requests(server_id, from_node, to_node)
+    WHERE status = 'PENDING';
+
+CREATE INDEX IF NOT EXISTS idx_graph_connection_requests_to
+    ON graph_connection_requests(server_id, to_node, status);
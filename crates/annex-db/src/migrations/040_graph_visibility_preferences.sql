@@ -0,0 +1,9 @@
+-- Per-participant overrides of the degree-based visibility defaults.
+-- A missing row means the participant uses the defaults.
+CREATE TABLE graph_visibility_preferences (
+  server_id INTEGER NOT NULL,
+  pseudonym_id TEXT NOT NULL,
+  preferences_json TEXT NOT NULL,
+  updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+  PRIMARY KEY (server_id, pseudonym_id)
+);
@@ -0,0 +1,23 @@
+-- Blocks are enforced by the server: the blocked participant loses all
+-- visibility of the blocker, cannot request a connection with them, and
+-- their messages are kept out of the blocker's WebSocket stream.
+CREATE TABLE graph_blocks (
+  server_id INTEGER NOT NULL,
+  blocker TEXT NOT NULL,
+  blocked TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  PRIMARY KEY (server_id, blocker, blocked)
+);
+
+CREATE INDEX IF NOT EXISTS idx_graph_blocks_blocked
+    ON graph_blocks(server_id, blocked);
+
+-- Mutes are only stored here so clients can sync them; filtering muted
+-- participants is left to the client.
+CREATE TABLE graph_mutes (
+  server_id INTEGER NOT NULL,
+  muter TEXT NOT NULL,
+  muted TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  PRIMARY KEY (server_id, muter, muted)
+);
@@ -0,0 +1,12 @@
+-- Published differentially private graph statistics. Each row spends
+-- `epsilon` of privacy budget; the latest one is served until it ages out.
+CREATE TABLE graph_analytics_releases (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  server_id INTEGER NOT NULL,
+  epsilon REAL NOT NULL,
+  release_json TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_graph_analytics_releases_server
+    ON graph_analytics_releases(server_id, created_at);
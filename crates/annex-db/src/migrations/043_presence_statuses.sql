@@ -0,0 +1,10 @@
+-- Self-declared presence statuses. Idleness is tracked in memory from live
+-- WebSocket sessions and is not stored.
+CREATE TABLE presence_statuses (
+  server_id INTEGER NOT NULL,
+  pseudonym_id TEXT NOT NULL,
+  status TEXT NOT NULL,
+  status_text TEXT,
+  updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+  PRIMARY KEY (server_id, pseudonym_id)
+);
@@ -0,0 +1,71 @@
+-- Append-only change log of the presence graph, so the graph can be
+-- reconstructed as of a past timestamp after pruning and edge deletion.
+-- Rows are written by triggers and may only be deleted by retention
+-- compaction, never updated.
+CREATE TABLE graph_node_history (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  server_id INTEGER NOT NULL,
+  pseudonym_id TEXT NOT NULL,
+  node_type TEXT NOT NULL,
+  active INTEGER NOT NULL,
+  changed_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX idx_graph_node_history_server_time
+  ON graph_node_history(server_id, changed_at);
+
+CREATE TABLE graph_edge_history (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  server_id INTEGER NOT NULL,
+  from_node TEXT NOT NULL,
+  to_node TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  weight REAL,
+  change TEXT NOT NULL,              -- ADDED | REMOVED
+  changed_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX idx_graph_edge_history_server_time
+  ON graph_edge_history(server_id, changed_at);
+
+-- Existing graph state, as of when each row was created.
+INSERT INTO graph_node_history (server_id, pseudonym_id, node_type, active, changed_at)
+  SELECT server_id, pseudonym_id, node_type, active, created_at FROM graph_nodes;
+
+INSERT INTO graph_edge_history (server_id, from_node, to_node, kind, weight, change, changed_at)
+  SELECT server_id, from_node, to_node, kind, weight, 'ADDED', created_at FROM graph_edges;
+
+CREATE TRIGGER graph_nodes_history_insert AFTER INSERT ON graph_nodes
+BEGIN
+  INSERT INTO graph_node_history (server_id, pseudonym_id, node_type, active)
+  VALUES (NEW.server_id, NEW.pseudonym_id, NEW.node_type, NEW.active);
+END;
+
+CREATE TRIGGER graph_nodes_history_update AFTER UPDATE OF active ON graph_nodes
+WHEN OLD.active IS NOT NEW.active
+BEGIN
+  INSERT INTO graph_node_history (server_id, pseudonym_id, node_type, active)
+  VALUES (NEW.server_id, NEW.pseudonym_id, NEW.node_type, NEW.active);
+END;
+
+CREATE TRIGGER graph_edges_history_insert AFTER INSERT ON graph_edges
+BEGIN
+  INSERT INTO graph_edge_history (server_id, from_node, to_node, kind, weight, change)
+  VALUES (NEW.server_id, NEW.from_node, NEW.to_node, NEW.kind, NEW.weight, 'ADDED');
+END;
+
+CREATE TRIGGER graph_edges_history_delete AFTER DELETE ON graph_edges
+BEGIN
+  INSERT INTO graph_edge_history (server_id, from_node, to_node, kind, weight, change)
+  VALUES (OLD.server_id, OLD.from_node, OLD.to_node, OLD.kind, OLD.weight, 'REMOVED');
+END;
+
+CREATE TRIGGER graph_node_history_append_only BEFORE UPDATE ON graph_node_history
+BEGIN
+  SELECT RAISE(ABORT, 'graph history is append-only');
+END;
+
+CREATE TRIGGER graph_edge_history_append_only BEFORE UPDATE ON graph_edge_history
+BEGIN
+  SELECT RAISE(ABORT, 'graph history is append-only');
+END;
@@ -0,0 +1,20 @@
+-- Opt-in live captions for voice and hybrid channels. Only speech from
+-- participants with a consent row is transcribed; persisted captions are
+-- ordinary messages and expire with the channel's retention.
+CREATE TABLE channel_caption_settings (
+  channel_id TEXT PRIMARY KEY,
+  server_id INTEGER NOT NULL,
+  enabled INTEGER NOT NULL DEFAULT 0,
+  persist INTEGER NOT NULL DEFAULT 0,
+  updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+  FOREIGN KEY (channel_id) REFERENCES channels(channel_id)
+);
+
+CREATE TABLE caption_consents (
+  channel_id TEXT NOT NULL,
+  pseudonym_id TEXT NOT NULL,
+  server_id INTEGER NOT NULL,
+  granted_at TEXT NOT NULL DEFAULT (datetime('now')),
+  PRIMARY KEY (channel_id, pseudonym_id),
+  FOREIGN KEY (channel_id) REFERENCES channels(channel_id)
+);
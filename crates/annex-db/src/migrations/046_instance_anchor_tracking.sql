@@ -0,0 +1,4 @@
+-- Tracks the newest anchor each peer has presented. Once a peer has sent a
+-- signed anchor, unsigned or older anchors from it are refused.
+ALTER TABLE instances ADD COLUMN anchor_signed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE instances ADD COLUMN anchor_timestamp INTEGER;
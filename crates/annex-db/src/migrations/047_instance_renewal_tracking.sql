@@ -0,0 +1,4 @@
+-- Signing time (Unix seconds) of the last renewal request honoured from each
+-- peer. Requests signed less than a minute after it, replays included, are
+-- refused.
+ALTER TABLE instances ADD COLUMN renewal_received_at INTEGER;
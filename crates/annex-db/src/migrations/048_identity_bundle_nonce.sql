@@ -0,0 +1,6 @@
+-- Nonce of the bundle each identity was imported from, so a bundle can be
+-- imported only once per origin. The unique index enforces this even when
+-- two imports of the same bundle race.
+ALTER TABLE identity_provenance ADD COLUMN bundle_nonce TEXT;
+CREATE UNIQUE INDEX idx_identity_provenance_bundle_nonce
+    ON identity_provenance(origin_instance_id, bundle_nonce);
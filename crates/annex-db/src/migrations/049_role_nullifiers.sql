@@ -0,0 +1,12 @@
+-- Context-scoped nullifiers from role disclosure proofs, each bound to the
+-- pseudonym that first presented it. One identity can vouch for only one
+-- pseudonym per context, and a pseudonym for only one identity.
+CREATE TABLE role_nullifiers (
+  server_id INTEGER NOT NULL,
+  context_hex TEXT NOT NULL,
+  nullifier_hex TEXT NOT NULL,
+  pseudonym_id TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  PRIMARY KEY (server_id, context_hex, nullifier_hex),
+  UNIQUE (server_id, context_hex, pseudonym_id)
+);
@@ -0,0 +1,14 @@
+-- Per-poll eligibility snapshots.
+-- When a poll is created the server builds a Merkle tree over the identity
+-- commitments of the channel's members. A ballot's vote proof must show
+-- inclusion in that tree, so eligibility is enforced by the circuit rather
+-- than by authenticating the voter.
+ALTER TABLE polls ADD COLUMN eligibility_root_hex TEXT;
+
+CREATE TABLE poll_eligibility (
+    poll_id TEXT NOT NULL,
+    leaf_index INTEGER NOT NULL,
+    commitment_hex TEXT NOT NULL,
+    PRIMARY KEY (poll_id, leaf_index),
+    FOREIGN KEY (poll_id) REFERENCES polls(poll_id)
+);
@@ -3,12 +3,16 @@ use annex_vrp::{VrpFederationHandshake, VrpValidationReport};
 use rusqlite::{params, Connection, Result};
 
 /// Creates a new federation agreement record.
+///
+/// The agreement expires `expires_in_secs` from now; `None` creates an
+/// agreement that never expires.
 pub fn create_agreement(
     conn: &mut Connection,
     local_server_id: i64,
     remote_instance_id: i64,
     report: &VrpValidationReport,
     handshake: Option<&VrpFederationHandshake>,
+    expires_in_secs: Option<u64>,
 ) -> Result<i64> {
     let report_json = serde_json::to_string(report).map_err(|e| {
         rusqlite::Error::FromSqlConversionFailure(
@@ -56,15 +60,17 @@ pub fn create_agreement(
             alignment_status,
             transfer_scope,
             agreement_json,
-            remote_handshake_json
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            remote_handshake_json,
+            expires_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now', ?7))",
         params![
             local_server_id,
             remote_instance_id,
             alignment_status,
             transfer_scope,
             report_json,
-            handshake_json
+            handshake_json,
+            expires_in_secs.map(|secs| format!("+{} seconds", secs))
         ],
     )?;
 
@@ -194,7 +200,9 @@ mod tests {
                 remote_handshake_json TEXT,
                 active INTEGER NOT NULL DEFAULT 1,
                 created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                expires_at TEXT,
+                renewal_requested_at TEXT
             )",
             [],
         )
@@ -208,6 +216,7 @@ mod tests {
             transfer_scope: VrpTransferScope::ReflectionSummariesOnly,
             alignment_score: 1.0,
             negotiation_notes: vec![],
+            explanation: Default::default(),
         }
     }
 
@@ -217,7 +226,7 @@ mod tests {
         let report = make_report();
 
         // Create first agreement
-        let id1 = create_agreement(&mut conn, 1, 10, &report, None).unwrap();
+        let id1 = create_agreement(&mut conn, 1, 10, &report, None, None).unwrap();
         assert!(id1 > 0);
 
         // Verify it's active
@@ -231,7 +240,7 @@ mod tests {
         assert_eq!(count, 1);
 
         // Create second agreement for same remote instance
-        let id2 = create_agreement(&mut conn, 1, 10, &report, None).unwrap();
+        let id2 = create_agreement(&mut conn, 1, 10, &report, None, None).unwrap();
         assert!(id2 > id1);
 
         // Verify old is deactivated and new is active
@@ -260,9 +269,9 @@ mod tests {
         let report = make_report();
 
         // Server 1 creates agreement with remote 10
-        create_agreement(&mut conn, 1, 10, &report, None).unwrap();
+        create_agreement(&mut conn, 1, 10, &report, None, None).unwrap();
         // Server 2 creates agreement with same remote 10
-        create_agreement(&mut conn, 2, 10, &report, None).unwrap();
+        create_agreement(&mut conn, 2, 10, &report, None, None).unwrap();
 
         // Both should be active (different local servers)
         let count: i64 = conn
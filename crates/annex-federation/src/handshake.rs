@@ -1,9 +1,9 @@
 use crate::db::create_agreement;
 use annex_types::ServerPolicy;
 use annex_vrp::{
-    validate_federation_handshake, ServerPolicyRoot, VrpAlignmentConfig,
-    VrpCapabilitySharingContract, VrpError, VrpFederationHandshake, VrpTransferAcceptanceConfig,
-    VrpValidationReport,
+    resolve_transfer_scope, validate_federation_handshake, SemanticEmbedder, ServerPolicyRoot,
+    VrpAlignmentConfig, VrpAlignmentStatus, VrpCapabilitySharingContract, VrpContractOffer,
+    VrpError, VrpFederationHandshake, VrpTransferAcceptanceConfig, VrpValidationReport,
 };
 use rusqlite::Connection;
 use thiserror::Error;
@@ -19,25 +19,8 @@ pub enum HandshakeError {
     Vrp(#[from] VrpError),
 }
 
-/// Processes an incoming federation handshake from a peer server.
-///
-/// This function:
-/// 1. Derives the local server's policy root and anchor.
-/// 2. Defines the local capability contract and transfer acceptance config.
-/// 3. Validates the incoming handshake using `annex-vrp`.
-/// 4. Persists the resulting agreement in the database.
-pub fn process_incoming_handshake(
-    conn: &mut Connection,
-    local_server_id: i64,
-    local_policy: &ServerPolicy,
-    remote_instance_id: i64,
-    handshake: &VrpFederationHandshake,
-) -> Result<VrpValidationReport, HandshakeError> {
-    // 1. Derive local policy root and anchor
-    let local_policy_root = ServerPolicyRoot::from_policy(local_policy);
-    let local_anchor = local_policy_root.to_anchor_snapshot()?;
-
-    // 2. Define local capability contract
+/// The capability contract this server presents to federation peers.
+pub fn local_capability_contract(local_policy: &ServerPolicy) -> VrpCapabilitySharingContract {
     // In a real implementation, this would be more granular based on policy.
     let mut offered_capabilities = Vec::new();
     if local_policy.voice_enabled {
@@ -47,26 +30,69 @@ pub fn process_incoming_handshake(
         offered_capabilities.push("federation".to_string());
     }
 
-    let required_capabilities = local_policy.agent_required_capabilities.clone();
-
-    let local_contract = VrpCapabilitySharingContract {
-        required_capabilities,
+    VrpCapabilitySharingContract {
+        required_capabilities: local_policy.agent_required_capabilities.clone(),
         offered_capabilities,
-        redacted_topics: vec![],
-    };
+        redacted_topics: local_policy.federation_redacted_topics.clone(),
+    }
+}
 
-    // 3. Define alignment config
-    let alignment_config = VrpAlignmentConfig {
+/// How this server compares federation peers' anchors.
+pub fn local_alignment_config(local_policy: &ServerPolicy) -> VrpAlignmentConfig {
+    VrpAlignmentConfig {
         semantic_alignment_required: true,
         min_alignment_score: local_policy.agent_min_alignment_score,
-    };
+    }
+}
 
-    // 4. Define transfer acceptance config
+/// Which transfers this server accepts from federation peers.
+pub fn local_transfer_config(local_policy: &ServerPolicy) -> VrpTransferAcceptanceConfig {
     // For now, we allow reflection summaries if federation is enabled.
-    let transfer_config = VrpTransferAcceptanceConfig {
+    VrpTransferAcceptanceConfig {
         allow_reflection_summaries: local_policy.federation_enabled,
         allow_full_knowledge: false, // Conservative default
-    };
+    }
+}
+
+/// This server's opening terms for a contract negotiation with a
+/// federation peer whose anchor has `alignment_status`.
+pub fn local_negotiation_terms(
+    local_policy: &ServerPolicy,
+    alignment_status: VrpAlignmentStatus,
+) -> VrpContractOffer {
+    let mut terms = VrpContractOffer::from_contract(
+        &local_capability_contract(local_policy),
+        resolve_transfer_scope(alignment_status, &local_transfer_config(local_policy)),
+    );
+    terms.optional_capabilities = local_policy.agent_optional_capabilities.clone();
+    terms
+}
+
+/// Processes an incoming federation handshake from a peer server.
+///
+/// This function:
+/// 1. Derives the local server's policy root and anchor.
+/// 2. Defines the local capability contract and transfer acceptance config.
+/// 3. Validates the incoming handshake using `annex-vrp`, comparing
+///    principles with `embedder`.
+/// 4. Persists the resulting agreement in the database, expiring after the
+///    policy's contract validity period.
+pub fn process_incoming_handshake(
+    conn: &mut Connection,
+    local_server_id: i64,
+    local_policy: &ServerPolicy,
+    remote_instance_id: i64,
+    handshake: &VrpFederationHandshake,
+    embedder: &dyn SemanticEmbedder,
+) -> Result<VrpValidationReport, HandshakeError> {
+    // 1. Derive local policy root and anchor
+    let local_policy_root = ServerPolicyRoot::from_policy(local_policy);
+    let local_anchor = local_policy_root.to_anchor_snapshot()?;
+
+    // 2-4. Local capability contract, alignment and transfer configs
+    let local_contract = local_capability_contract(local_policy);
+    let alignment_config = local_alignment_config(local_policy);
+    let transfer_config = local_transfer_config(local_policy);
 
     // 5. Validate handshake
     let report = validate_federation_handshake(
@@ -75,7 +101,8 @@ pub fn process_incoming_handshake(
         handshake,
         &alignment_config,
         &transfer_config,
-    );
+        embedder,
+    )?;
 
     // 6. Persist agreement
     create_agreement(
@@ -84,6 +111,7 @@ pub fn process_incoming_handshake(
         remote_instance_id,
         &report,
         Some(handshake),
+        local_policy.contract_validity(),
     )?;
 
     Ok(report)
@@ -109,7 +137,9 @@ mod tests {
                 remote_handshake_json TEXT,
                 active INTEGER NOT NULL DEFAULT 1,
                 created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                expires_at TEXT,
+                renewal_requested_at TEXT
             )",
             [],
         )
@@ -127,7 +157,15 @@ mod tests {
             capability_contract: contract,
         };
 
-        let report = process_incoming_handshake(&mut conn, 1, &policy, 10, &handshake).unwrap();
+        let report = process_incoming_handshake(
+            &mut conn,
+            1,
+            &policy,
+            10,
+            &handshake,
+            &annex_vrp::BagOfWordsEmbedder::new(),
+        )
+        .unwrap();
         assert_eq!(
             report.alignment_status,
             annex_vrp::VrpAlignmentStatus::Aligned
@@ -21,7 +21,12 @@ pub use db::{
     create_agreement, expire_stale_agreements, get_agreement, list_active_agreements,
     revoke_agreement,
 };
-pub use handshake::{process_incoming_handshake, HandshakeError};
+pub use handshake::{
+    local_alignment_config, local_capability_contract, local_negotiation_terms,
+    local_transfer_config, process_incoming_handshake, HandshakeError,
+};
 pub use types::{
-    AttestationRequest, FederatedMessageEnvelope, FederatedRtxEnvelope, FederationAgreement,
+    AttestationRequest, BundleUsername, BundleVoiceProfile, FederatedMessageEnvelope,
+    FederatedRtxEnvelope, FederationAgreement, IdentityBundle, SignedIdentityBundle,
+    IDENTITY_BUNDLE_VERSION,
 };
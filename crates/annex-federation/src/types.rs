@@ -37,6 +37,81 @@ pub struct AttestationRequest {
     pub signature: String,
 }
 
+/// Current version of the portable identity bundle format.
+pub const IDENTITY_BUNDLE_VERSION: u32 = 2;
+
+/// A portable snapshot of an identity, issued by its origin server so the
+/// holder can move to another server without registering from scratch.
+///
+/// The bundle carries the commitment (never the secret key), so importing it
+/// only re-registers the commitment; the holder still proves membership on
+/// the new server with their own key.
+///
+/// A bundle names the one server it may be imported into, carries a nonce
+/// so it can be imported only once, and is only accepted for a limited time
+/// after `issued_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityBundle {
+    /// Bundle format version (see [`IDENTITY_BUNDLE_VERSION`]).
+    pub version: u32,
+    /// The base URL of the issuing server.
+    pub origin_server: String,
+    /// The issuing server's Ed25519 public key (hex).
+    pub origin_public_key: String,
+    /// The base URL of the only server that may import the bundle.
+    pub audience: String,
+    /// Random value (hex) identifying this bundle, so it can be imported
+    /// only once.
+    pub nonce: String,
+    /// The identity commitment (hex).
+    pub commitment: String,
+    /// The VRP role code the commitment was registered with.
+    pub role_code: u8,
+    /// The node ID used in the commitment derivation.
+    pub node_id: i64,
+    /// VRP topics the identity had derived pseudonyms for.
+    pub topics: Vec<String>,
+    /// Usernames the identity had set, keyed by topic.
+    pub usernames: Vec<BundleUsername>,
+    /// The voice profile assigned to the identity, if any.
+    pub voice_profile: Option<BundleVoiceProfile>,
+    /// When the origin server issued the bundle (ISO 8601).
+    pub issued_at: String,
+}
+
+/// A username carried in an [`IdentityBundle`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleUsername {
+    /// The VRP topic of the pseudonym that held the username.
+    pub topic: String,
+    /// The plaintext username.
+    pub username: String,
+}
+
+/// Voice profile settings carried in an [`IdentityBundle`].
+///
+/// Model files are server-local, so only the settings travel; the importing
+/// server re-links the profile by `profile_id` if it has one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleVoiceProfile {
+    pub profile_id: String,
+    pub name: String,
+    pub model: String,
+    pub speed: f64,
+    pub pitch: f64,
+    pub speaker_id: Option<i64>,
+}
+
+/// An [`IdentityBundle`] with the origin server's attestation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedIdentityBundle {
+    /// The bundle contents.
+    pub bundle: IdentityBundle,
+    /// Ed25519 signature (hex) over the bundle's signing payload, made with
+    /// the key in `bundle.origin_public_key`.
+    pub signature: String,
+}
+
 /// A message relayed from a federation peer.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FederatedMessageEnvelope {
@@ -0,0 +1,573 @@
+//! Differentially private graph statistics.
+//!
+//! Observers who only get [`VisibilityLevel::AggregateOnly`](annex_types::VisibilityLevel::AggregateOnly)
+//! may still learn the shape of a community: how many participants of each
+//! type it has, how connected they are, how channels mix agents and humans,
+//! and how active everyone is. [`compute_graph_statistics`] gathers the exact
+//! figures and [`privatize`] releases them with Laplace noise.
+//!
+//! The guarantee is edge- and membership-level: a release hides whether any
+//! single edge or channel membership exists, and whether a participant with
+//! no edges or memberships exists. It does not hide a well-connected
+//! participant as a whole: removing one of degree `d` moves up to `2d + 1`
+//! degree histogram entries, and one in `k` channels changes `k` channel
+//! counts, while the noise is calibrated to a single edge or membership.
+//!
+//! The release's `epsilon` is split evenly across its six statistics. Each
+//! one is calibrated to its L1 sensitivity under that neighbor relation:
+//! one participant changes the node-type and activity histograms by 1, one
+//! channel membership changes a channel count by 1, and one edge changes the
+//! component count by 1, the component-size histogram by up to 3 and the
+//! degree histogram by up to 4. Budgets add up across releases, so
+//! [`current_analytics_release`] stops computing new ones once
+//! `epsilon_budget` would be exceeded.
+//! Everything after the noise (rounding, clamping, ratios, suppressing small
+//! channels) is post-processing and costs no extra budget.
+
+use crate::GraphError;
+use annex_types::GraphAnalyticsConfig;
+use rand::Rng;
+use rusqlite::{params, Connection, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Number of separately noised statistics in a release.
+const STATISTIC_COUNT: f64 = 6.0;
+
+/// Upper bounds (inclusive) and labels of the degree histogram buckets.
+const DEGREE_BUCKETS: [(usize, &str); 6] = [
+    (0, "0"),
+    (1, "1"),
+    (2, "2"),
+    (5, "3-5"),
+    (10, "6-10"),
+    (usize::MAX, "11+"),
+];
+
+/// Upper bounds (inclusive) and labels of the component size buckets.
+const COMPONENT_BUCKETS: [(usize, &str); 5] = [
+    (1, "1"),
+    (2, "2"),
+    (5, "3-5"),
+    (20, "6-20"),
+    (usize::MAX, "21+"),
+];
+
+/// Stored `NodeType` names.
+const NODE_TYPES: [&str; 5] = ["HUMAN", "AI_AGENT", "COLLECTIVE", "BRIDGE", "SERVICE"];
+
+/// Labels of the activity buckets, by time since `last_seen_at`.
+const ACTIVITY_BUCKETS: [&str; 5] = ["1h", "24h", "7d", "30d", "older"];
+
+/// A labelled histogram bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub label: String,
+    pub count: f64,
+}
+
+/// Agent and human membership of one channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelComposition {
+    pub channel_id: String,
+    pub humans: f64,
+    pub agents: f64,
+}
+
+/// Exact statistics for one server. Never published as-is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphStatistics {
+    /// Active nodes per `NodeType` (e.g. `HUMAN`).
+    pub nodes_by_type: BTreeMap<String, f64>,
+    /// Active nodes by number of distinct neighbors.
+    pub degree_distribution: Vec<HistogramBucket>,
+    /// Number of connected components among active nodes.
+    pub component_count: f64,
+    /// Components by number of nodes.
+    pub component_sizes: Vec<HistogramBucket>,
+    /// Per-channel agent and human membership.
+    pub channels: Vec<ChannelComposition>,
+    /// Active nodes by time since they were last seen.
+    pub activity: Vec<HistogramBucket>,
+}
+
+/// Agent-to-human ratio of one channel in a release.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelRatio {
+    pub channel_id: String,
+    /// Agents per human; `None` when the channel has no humans.
+    pub agent_to_human_ratio: Option<f64>,
+}
+
+/// Published, differentially private statistics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphAnalyticsRelease {
+    /// Privacy budget spent on this release.
+    pub epsilon: f64,
+    pub nodes_by_type: BTreeMap<String, u64>,
+    pub degree_distribution: Vec<HistogramBucket>,
+    pub component_count: u64,
+    pub component_sizes: Vec<HistogramBucket>,
+    /// Ratios for channels at or above the configured minimum group size.
+    pub channel_ratios: Vec<ChannelRatio>,
+    /// Channels left out of `channel_ratios` for being too small.
+    pub suppressed_channels: u64,
+    pub activity: Vec<HistogramBucket>,
+    /// When the release was computed (ISO 8601).
+    pub generated_at: String,
+}
+
+fn bucket_label<'a>(buckets: &[(usize, &'a str)], value: usize) -> &'a str {
+    buckets
+        .iter()
+        .find(|(max, _)| value <= *max)
+        .map(|(_, label)| *label)
+        .unwrap_or(buckets[buckets.len() - 1].1)
+}
+
+fn histogram(labels: &[&str], counts: &HashMap<&str, usize>) -> Vec<HistogramBucket> {
+    labels
+        .iter()
+        .map(|label| HistogramBucket {
+            label: label.to_string(),
+            count: counts.get(label).copied().unwrap_or(0) as f64,
+        })
+        .collect()
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Computes the exact statistics for a server's active nodes.
+pub fn compute_graph_statistics(
+    conn: &Connection,
+    server_id: i64,
+) -> Result<GraphStatistics, GraphError> {
+    let mut stmt = conn.prepare(
+        "SELECT pseudonym_id, node_type,
+                CASE
+                    WHEN last_seen_at IS NULL THEN 4
+                    WHEN last_seen_at >= datetime('now', '-1 hour') THEN 0
+                    WHEN last_seen_at >= datetime('now', '-1 day') THEN 1
+                    WHEN last_seen_at >= datetime('now', '-7 days') THEN 2
+                    WHEN last_seen_at >= datetime('now', '-30 days') THEN 3
+                    ELSE 4
+                END
+         FROM graph_nodes WHERE server_id = ?1 AND active = 1",
+    )?;
+    let nodes = stmt
+        .query_map(params![server_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, usize>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let index: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, (pseudonym, _, _))| (pseudonym.as_str(), i))
+        .collect();
+
+    // Every type is listed, so a key's presence reveals nothing.
+    let mut nodes_by_type: BTreeMap<String, f64> = NODE_TYPES
+        .iter()
+        .map(|node_type| (node_type.to_string(), 0.0))
+        .collect();
+    let mut activity = HashMap::new();
+    for (_, node_type, bucket) in &nodes {
+        *nodes_by_type.entry(node_type.clone()).or_insert(0.0) += 1.0;
+        *activity.entry(ACTIVITY_BUCKETS[*bucket]).or_insert(0) += 1;
+    }
+
+    // Undirected, deduplicated adjacency between active nodes.
+    let mut neighbors: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    let mut parent: Vec<usize> = (0..nodes.len()).collect();
+    let mut stmt =
+        conn.prepare("SELECT DISTINCT from_node, to_node FROM graph_edges WHERE server_id = ?1")?;
+    let edges = stmt.query_map(params![server_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for edge in edges {
+        let (from, to) = edge?;
+        let (Some(&a), Some(&b)) = (index.get(from.as_str()), index.get(to.as_str())) else {
+            continue;
+        };
+        if a == b {
+            continue;
+        }
+        neighbors[a].push(b);
+        neighbors[b].push(a);
+        let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+        parent[ra] = rb;
+    }
+
+    let mut degrees = HashMap::new();
+    for list in &mut neighbors {
+        list.sort_unstable();
+        list.dedup();
+        *degrees
+            .entry(bucket_label(&DEGREE_BUCKETS, list.len()))
+            .or_insert(0) += 1;
+    }
+
+    let mut component_size: HashMap<usize, usize> = HashMap::new();
+    for i in 0..nodes.len() {
+        *component_size.entry(find(&mut parent, i)).or_insert(0) += 1;
+    }
+    let mut sizes = HashMap::new();
+    for size in component_size.values() {
+        *sizes
+            .entry(bucket_label(&COMPONENT_BUCKETS, *size))
+            .or_insert(0) += 1;
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT c.channel_id,
+                COALESCE(SUM(n.node_type = 'HUMAN'), 0),
+                COALESCE(SUM(n.node_type = 'AI_AGENT'), 0)
+         FROM channels c
+         LEFT JOIN channel_members m ON m.channel_id = c.channel_id
+         LEFT JOIN graph_nodes n
+             ON n.server_id = c.server_id AND n.pseudonym_id = m.pseudonym_id AND n.active = 1
+         WHERE c.server_id = ?1
+         GROUP BY c.channel_id
+         ORDER BY c.channel_id",
+    )?;
+    let channels = stmt
+        .query_map(params![server_id], |row| {
+            Ok(ChannelComposition {
+                channel_id: row.get(0)?,
+                humans: row.get::<_, i64>(1)? as f64,
+                agents: row.get::<_, i64>(2)? as f64,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let degree_labels: Vec<&str> = DEGREE_BUCKETS.iter().map(|(_, l)| *l).collect();
+    let component_labels: Vec<&str> = COMPONENT_BUCKETS.iter().map(|(_, l)| *l).collect();
+    Ok(GraphStatistics {
+        nodes_by_type,
+        degree_distribution: histogram(&degree_labels, &degrees),
+        component_count: component_size.len() as f64,
+        component_sizes: histogram(&component_labels, &sizes),
+        channels,
+        activity: histogram(&ACTIVITY_BUCKETS, &activity),
+    })
+}
+
+/// Samples Laplace noise with the given scale.
+fn laplace<R: Rng + ?Sized>(rng: &mut R, scale: f64) -> f64 {
+    // Inverse CDF on u in (-0.5, 0.5); the open interval avoids ln(0).
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    let u = if u == -0.5 { 0.0 } else { u };
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+/// Adds noise for `sensitivity` at `epsilon`, then rounds and clamps to a
+/// non-negative count.
+fn noisy<R: Rng + ?Sized>(rng: &mut R, value: f64, sensitivity: f64, epsilon: f64) -> f64 {
+    (value + laplace(rng, sensitivity / epsilon))
+        .round()
+        .max(0.0)
+}
+
+fn noisy_histogram<R: Rng + ?Sized>(
+    rng: &mut R,
+    buckets: &[HistogramBucket],
+    sensitivity: f64,
+    epsilon: f64,
+) -> Vec<HistogramBucket> {
+    buckets
+        .iter()
+        .map(|bucket| HistogramBucket {
+            label: bucket.label.clone(),
+            count: noisy(rng, bucket.count, sensitivity, epsilon),
+        })
+        .collect()
+}
+
+/// Releases `stats` under `config`'s privacy budget.
+pub fn privatize<R: Rng + ?Sized>(
+    stats: &GraphStatistics,
+    config: &GraphAnalyticsConfig,
+    generated_at: String,
+    rng: &mut R,
+) -> GraphAnalyticsRelease {
+    let epsilon = config.epsilon / STATISTIC_COUNT;
+
+    let nodes_by_type = stats
+        .nodes_by_type
+        .iter()
+        .map(|(node_type, count)| (node_type.clone(), noisy(rng, *count, 1.0, epsilon) as u64))
+        .collect();
+
+    let mut channel_ratios = Vec::new();
+    let mut suppressed_channels = 0;
+    for channel in &stats.channels {
+        let humans = noisy(rng, channel.humans, 1.0, epsilon);
+        let agents = noisy(rng, channel.agents, 1.0, epsilon);
+        if humans + agents < f64::from(config.min_group_size) {
+            suppressed_channels += 1;
+            continue;
+        }
+        channel_ratios.push(ChannelRatio {
+            channel_id: channel.channel_id.clone(),
+            agent_to_human_ratio: (humans > 0.0).then(|| agents / humans),
+        });
+    }
+
+    GraphAnalyticsRelease {
+        epsilon: config.epsilon,
+        nodes_by_type,
+        degree_distribution: noisy_histogram(rng, &stats.degree_distribution, 4.0, epsilon),
+        component_count: noisy(rng, stats.component_count, 1.0, epsilon) as u64,
+        component_sizes: noisy_histogram(rng, &stats.component_sizes, 3.0, epsilon),
+        channel_ratios,
+        suppressed_channels,
+        activity: noisy_histogram(rng, &stats.activity, 1.0, epsilon),
+        generated_at,
+    }
+}
+
+/// Returns the server's current release, computing and storing a new one if
+/// the latest is older than `config.release_interval_secs` and the server's
+/// remaining `config.epsilon_budget` covers it.
+///
+/// Serving the same release for the whole interval keeps observers from
+/// averaging out the noise with repeated queries; the lifetime budget bounds
+/// what they can learn by combining releases.
+pub fn current_analytics_release(
+    conn: &mut Connection,
+    server_id: i64,
+    config: &GraphAnalyticsConfig,
+) -> Result<GraphAnalyticsRelease, GraphError> {
+    if !(config.epsilon.is_finite() && config.epsilon > 0.0) {
+        return Err(GraphError::InvalidAnalyticsConfig(
+            "epsilon must be a positive number".to_string(),
+        ));
+    }
+    if config.epsilon_budget.is_nan() || config.epsilon_budget < config.epsilon {
+        return Err(GraphError::InvalidAnalyticsConfig(
+            "epsilon_budget must be at least epsilon".to_string(),
+        ));
+    }
+
+    // Immediate, so concurrent requests can't both spend budget on a release.
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let (latest, fresh, spent): (Option<String>, bool, f64) = tx.query_row(
+        "SELECT (SELECT release_json FROM graph_analytics_releases
+                 WHERE server_id = ?1 ORDER BY id DESC LIMIT 1),
+                COALESCE((SELECT created_at > datetime('now', ?2) FROM graph_analytics_releases
+                          WHERE server_id = ?1 ORDER BY id DESC LIMIT 1), 0),
+                COALESCE((SELECT SUM(epsilon) FROM graph_analytics_releases
+                          WHERE server_id = ?1), 0.0)",
+        params![
+            server_id,
+            format!("-{} seconds", config.release_interval_secs)
+        ],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    if let Some(json) = latest {
+        if fresh || spent + config.epsilon > config.epsilon_budget {
+            return Ok(serde_json::from_str(&json)?);
+        }
+    }
+
+    let stats = compute_graph_statistics(&tx, server_id)?;
+    let generated_at: String = tx.query_row("SELECT datetime('now')", [], |row| row.get(0))?;
+    let release = privatize(&stats, config, generated_at, &mut rand::thread_rng());
+    tx.execute(
+        "INSERT INTO graph_analytics_releases (server_id, epsilon, release_json)
+         VALUES (?1, ?2, ?3)",
+        params![server_id, config.epsilon, serde_json::to_string(&release)?],
+    )?;
+    tx.commit()?;
+    Ok(release)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_edge, ensure_graph_node};
+    use annex_db::run_migrations;
+    use annex_types::{EdgeKind, NodeType};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().expect("db open failed");
+        run_migrations(&conn).expect("migrations failed");
+        conn.execute(
+            "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 's', 'S', '{}')",
+            [],
+        )
+        .unwrap();
+        for n in ["a", "b", "c", "d"] {
+            ensure_graph_node(&conn, 1, n, NodeType::Human, None).unwrap();
+        }
+        ensure_graph_node(&conn, 1, "bot", NodeType::AiAgent, None).unwrap();
+        // a - b - c, plus bot - a; d is isolated.
+        create_edge(&conn, 1, "a", "b", EdgeKind::Connected, 1.0).unwrap();
+        create_edge(&conn, 1, "b", "a", EdgeKind::Connected, 1.0).unwrap();
+        create_edge(&conn, 1, "b", "c", EdgeKind::Connected, 1.0).unwrap();
+        create_edge(&conn, 1, "bot", "a", EdgeKind::AgentServing, 1.0).unwrap();
+        conn
+    }
+
+    fn count(buckets: &[HistogramBucket], label: &str) -> f64 {
+        buckets.iter().find(|b| b.label == label).unwrap().count
+    }
+
+    #[test]
+    fn exact_statistics() {
+        let conn = setup();
+        let stats = compute_graph_statistics(&conn, 1).unwrap();
+
+        assert_eq!(stats.nodes_by_type["HUMAN"], 4.0);
+        assert_eq!(stats.nodes_by_type["AI_AGENT"], 1.0);
+        assert_eq!(stats.nodes_by_type["BRIDGE"], 0.0);
+        assert_eq!(count(&stats.degree_distribution, "0"), 1.0); // d
+        assert_eq!(count(&stats.degree_distribution, "1"), 2.0); // c, bot
+        assert_eq!(count(&stats.degree_distribution, "2"), 2.0); // a, b
+        assert_eq!(stats.component_count, 2.0);
+        assert_eq!(count(&stats.component_sizes, "1"), 1.0);
+        assert_eq!(count(&stats.component_sizes, "3-5"), 1.0);
+        assert_eq!(count(&stats.activity, "1h"), 5.0);
+    }
+
+    #[test]
+    fn noise_is_centered_and_scaled_by_epsilon() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mean_error = |rng: &mut StdRng, epsilon: f64| {
+            let n = 20_000;
+            (0..n)
+                .map(|_| laplace(rng, 1.0 / epsilon))
+                .map(f64::abs)
+                .sum::<f64>()
+                / n as f64
+        };
+        // E|Laplace(b)| = b.
+        assert!((mean_error(&mut rng, 1.0) - 1.0).abs() < 0.05);
+        assert!((mean_error(&mut rng, 0.1) - 10.0).abs() < 0.5);
+
+        let n = 20_000;
+        let mean = (0..n).map(|_| laplace(&mut rng, 1.0)).sum::<f64>() / n as f64;
+        assert!(mean.abs() < 0.05);
+    }
+
+    #[test]
+    fn small_channels_are_suppressed() {
+        let stats = GraphStatistics {
+            nodes_by_type: BTreeMap::new(),
+            degree_distribution: vec![],
+            component_count: 0.0,
+            component_sizes: vec![],
+            channels: vec![
+                ChannelComposition {
+                    channel_id: "tiny".to_string(),
+                    humans: 1.0,
+                    agents: 0.0,
+                },
+                ChannelComposition {
+                    channel_id: "big".to_string(),
+                    humans: 1000.0,
+                    agents: 500.0,
+                },
+            ],
+            activity: vec![],
+        };
+        let config = GraphAnalyticsConfig {
+            epsilon: 60.0,
+            min_group_size: 20,
+            ..Default::default()
+        };
+        let release = privatize(
+            &stats,
+            &config,
+            "now".to_string(),
+            &mut StdRng::seed_from_u64(1),
+        );
+        assert_eq!(release.suppressed_channels, 1);
+        assert_eq!(release.channel_ratios.len(), 1);
+        let ratio = release.channel_ratios[0].agent_to_human_ratio.unwrap();
+        assert!((ratio - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn release_is_reused_within_interval() {
+        let mut conn = setup();
+        let config = GraphAnalyticsConfig::default();
+        let first = current_analytics_release(&mut conn, 1, &config).unwrap();
+        let second = current_analytics_release(&mut conn, 1, &config).unwrap();
+        assert_eq!(first, second);
+
+        let fresh = GraphAnalyticsConfig {
+            release_interval_secs: 0,
+            ..config
+        };
+        current_analytics_release(&mut conn, 1, &fresh).unwrap();
+        let releases: i64 = conn
+            .query_row("SELECT COUNT(*) FROM graph_analytics_releases", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(releases, 2);
+
+        assert!(matches!(
+            current_analytics_release(
+                &mut conn,
+                1,
+                &GraphAnalyticsConfig {
+                    epsilon: 0.0,
+                    ..Default::default()
+                }
+            ),
+            Err(GraphError::InvalidAnalyticsConfig(_))
+        ));
+        assert!(matches!(
+            current_analytics_release(
+                &mut conn,
+                1,
+                &GraphAnalyticsConfig {
+                    epsilon: 2.0,
+                    epsilon_budget: 1.0,
+                    ..Default::default()
+                }
+            ),
+            Err(GraphError::InvalidAnalyticsConfig(_))
+        ));
+    }
+
+    #[test]
+    fn releases_stop_once_budget_is_spent() {
+        let mut conn = setup();
+        let config = GraphAnalyticsConfig {
+            epsilon: 1.0,
+            epsilon_budget: 2.5,
+            release_interval_secs: 0,
+            ..Default::default()
+        };
+        current_analytics_release(&mut conn, 1, &config).unwrap();
+        let second = current_analytics_release(&mut conn, 1, &config).unwrap();
+        // A third release would spend 3.0 of the 2.5 budget.
+        let third = current_analytics_release(&mut conn, 1, &config).unwrap();
+        assert_eq!(third, second);
+
+        let (releases, spent): (i64, f64) = conn
+            .query_row(
+                "SELECT COUNT(*), SUM(epsilon) FROM graph_analytics_releases",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(releases, 2);
+        assert_eq!(spent, 2.0);
+    }
+}
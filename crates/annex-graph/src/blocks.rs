@@ -0,0 +1,297 @@
+//! Blocks and mutes between participants.
+//!
+//! A block is enforced by the server. The blocked participant sees the
+//! blocker at [`VisibilityLevel::None`](annex_types::VisibilityLevel::None)
+//! whatever their degree or the blocker's allow list, cannot request a
+//! connection with them, and their messages are kept out of the blocker's
+//! WebSocket stream. Blocking also removes any existing connection and
+//! declines pending requests between the two.
+//!
+//! A mute is only persisted here so it follows the participant across
+//! sessions; clients hide muted participants themselves.
+
+use crate::{get_graph_node, AdjacencyIndex, GraphError};
+use annex_types::EdgeKind;
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
+
+/// Rejects relationships a participant cannot have with `other`.
+fn check_target(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+    other: &str,
+) -> Result<(), GraphError> {
+    if pseudonym_id == other {
+        return Err(GraphError::InvalidRelationship(
+            "cannot block or mute yourself".to_string(),
+        ));
+    }
+    if get_graph_node(conn, server_id, other)?.is_none() {
+        return Err(GraphError::NodeNotFound(other.to_string()));
+    }
+    Ok(())
+}
+
+/// Blocks `blocked` on behalf of `blocker`.
+///
+/// Deletes any `CONNECTED` edges between the two and declines pending
+/// connection requests in either direction. Returns the `(from_node,
+/// to_node)` pairs of the edges removed. Blocking someone already blocked
+/// is a no-op.
+pub fn block_participant(
+    conn: &mut Connection,
+    index: &AdjacencyIndex,
+    server_id: i64,
+    blocker: &str,
+    blocked: &str,
+) -> Result<Vec<(String, String)>, GraphError> {
+    check_target(conn, server_id, blocker, blocked)?;
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT OR IGNORE INTO graph_blocks (server_id, blocker, blocked) VALUES (?1, ?2, ?3)",
+        params![server_id, blocker, blocked],
+    )?;
+    let mut removed = Vec::new();
+    for (from, to) in [(blocker, blocked), (blocked, blocker)] {
+        if crate::delete_edge(&tx, server_id, from, to, EdgeKind::Connected)? > 0 {
+            removed.push((from.to_string(), to.to_string()));
+        }
+    }
+    tx.execute(
+        "UPDATE graph_connection_requests
+         SET status = 'DECLINED', responded_at = datetime('now')
+         WHERE server_id = ?1 AND status = 'PENDING'
+           AND ((from_node = ?2 AND to_node = ?3) OR (from_node = ?3 AND to_node = ?2))",
+        params![server_id, blocker, blocked],
+    )?;
+    tx.commit()?;
+
+    for (from, to) in &removed {
+        index.edge_deleted(server_id, from, to, EdgeKind::Connected);
+    }
+    Ok(removed)
+}
+
+/// Lifts a block. Unblocking someone who is not blocked is a no-op.
+///
+/// Connections removed by the block are not restored.
+pub fn unblock_participant(
+    conn: &Connection,
+    server_id: i64,
+    blocker: &str,
+    blocked: &str,
+) -> Result<(), GraphError> {
+    conn.execute(
+        "DELETE FROM graph_blocks WHERE server_id = ?1 AND blocker = ?2 AND blocked = ?3",
+        params![server_id, blocker, blocked],
+    )?;
+    Ok(())
+}
+
+/// Returns whether `blocker` has blocked `blocked`.
+pub fn is_blocked(
+    conn: &Connection,
+    server_id: i64,
+    blocker: &str,
+    blocked: &str,
+) -> Result<bool, GraphError> {
+    let blocked = conn.query_row(
+        "SELECT EXISTS(
+            SELECT 1 FROM graph_blocks
+            WHERE server_id = ?1 AND blocker = ?2 AND blocked = ?3
+         )",
+        params![server_id, blocker, blocked],
+        |row| row.get(0),
+    )?;
+    Ok(blocked)
+}
+
+/// Returns whether either participant has blocked the other.
+pub fn is_blocked_either(
+    conn: &Connection,
+    server_id: i64,
+    a: &str,
+    b: &str,
+) -> Result<bool, GraphError> {
+    Ok(is_blocked(conn, server_id, a, b)? || is_blocked(conn, server_id, b, a)?)
+}
+
+/// Lists the participants `blocker` has blocked, oldest first.
+pub fn list_blocks(
+    conn: &Connection,
+    server_id: i64,
+    blocker: &str,
+) -> Result<Vec<String>, GraphError> {
+    let mut stmt = conn.prepare(
+        "SELECT blocked FROM graph_blocks
+         WHERE server_id = ?1 AND blocker = ?2
+         ORDER BY created_at, blocked",
+    )?;
+    let rows = stmt.query_map(params![server_id, blocker], |row| row.get(0))?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Returns everyone who has blocked `blocked`.
+pub fn blockers_of(
+    conn: &Connection,
+    server_id: i64,
+    blocked: &str,
+) -> Result<HashSet<String>, GraphError> {
+    let mut stmt =
+        conn.prepare("SELECT blocker FROM graph_blocks WHERE server_id = ?1 AND blocked = ?2")?;
+    let rows = stmt.query_map(params![server_id, blocked], |row| row.get(0))?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Mutes `muted` on behalf of `muter`. Muting twice is a no-op.
+pub fn mute_participant(
+    conn: &Connection,
+    server_id: i64,
+    muter: &str,
+    muted: &str,
+) -> Result<(), GraphError> {
+    check_target(conn, server_id, muter, muted)?;
+    conn.execute(
+        "INSERT OR IGNORE INTO graph_mutes (server_id, muter, muted) VALUES (?1, ?2, ?3)",
+        params![server_id, muter, muted],
+    )?;
+    Ok(())
+}
+
+/// Lifts a mute. Unmuting someone who is not muted is a no-op.
+pub fn unmute_participant(
+    conn: &Connection,
+    server_id: i64,
+    muter: &str,
+    muted: &str,
+) -> Result<(), GraphError> {
+    conn.execute(
+        "DELETE FROM graph_mutes WHERE server_id = ?1 AND muter = ?2 AND muted = ?3",
+        params![server_id, muter, muted],
+    )?;
+    Ok(())
+}
+
+/// Lists the participants `muter` has muted, oldest first.
+pub fn list_mutes(
+    conn: &Connection,
+    server_id: i64,
+    muter: &str,
+) -> Result<Vec<String>, GraphError> {
+    let mut stmt = conn.prepare(
+        "SELECT muted FROM graph_mutes
+         WHERE server_id = ?1 AND muter = ?2
+         ORDER BY created_at, muted",
+    )?;
+    let rows = stmt.query_map(params![server_id, muter], |row| row.get(0))?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        are_connected, create_connection_request, ensure_graph_node, get_visible_profile,
+        list_connection_requests, set_visibility_preferences, VisibilityPreferences,
+    };
+    use annex_db::run_migrations;
+    use annex_types::{NodeType, VisibilityLevel};
+
+    fn setup() -> (Connection, AdjacencyIndex) {
+        let conn = Connection::open_in_memory().expect("db open failed");
+        run_migrations(&conn).expect("migrations failed");
+        for n in ["alice", "bob", "carol"] {
+            ensure_graph_node(&conn, 1, n, NodeType::Human, None).unwrap();
+        }
+        (conn, AdjacencyIndex::new())
+    }
+
+    #[test]
+    fn block_severs_connection_and_hides_profile() {
+        let (mut conn, index) = setup();
+        index
+            .create_edge(&conn, 1, "alice", "bob", EdgeKind::Connected, 1.0)
+            .unwrap();
+        index
+            .create_edge(&conn, 1, "bob", "alice", EdgeKind::Connected, 1.0)
+            .unwrap();
+        create_connection_request(&conn, 1, "carol", "alice").unwrap();
+        // An allow-list entry does not survive a block.
+        set_visibility_preferences(
+            &conn,
+            1,
+            "alice",
+            &VisibilityPreferences {
+                allow: vec!["bob".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let removed = block_participant(&mut conn, &index, 1, "alice", "bob").unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(!are_connected(&conn, 1, "alice", "bob").unwrap());
+        assert_eq!(
+            index.node_visibility(&conn, 1, "bob", "alice").unwrap(),
+            VisibilityLevel::None
+        );
+        assert_eq!(
+            get_visible_profile(&conn, 1, "bob", "alice")
+                .unwrap()
+                .visibility,
+            VisibilityLevel::None
+        );
+        assert!(is_blocked_either(&conn, 1, "bob", "alice").unwrap());
+        assert!(matches!(
+            create_connection_request(&conn, 1, "bob", "alice"),
+            Err(GraphError::Blocked(_))
+        ));
+        assert!(matches!(
+            create_connection_request(&conn, 1, "alice", "bob"),
+            Err(GraphError::Blocked(_))
+        ));
+
+        block_participant(&mut conn, &index, 1, "carol", "alice").unwrap();
+        assert!(list_connection_requests(&conn, 1, "alice")
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            blockers_of(&conn, 1, "alice").unwrap(),
+            HashSet::from(["carol".to_string()])
+        );
+
+        assert_eq!(list_blocks(&conn, 1, "alice").unwrap(), vec!["bob"]);
+        unblock_participant(&conn, 1, "alice", "bob").unwrap();
+        assert!(list_blocks(&conn, 1, "alice").unwrap().is_empty());
+        assert_eq!(
+            get_visible_profile(&conn, 1, "bob", "alice")
+                .unwrap()
+                .visibility,
+            VisibilityLevel::Degree1
+        );
+    }
+
+    #[test]
+    fn mutes_are_listed_and_validated() {
+        let (conn, _) = setup();
+        mute_participant(&conn, 1, "alice", "bob").unwrap();
+        mute_participant(&conn, 1, "alice", "bob").unwrap();
+        assert_eq!(list_mutes(&conn, 1, "alice").unwrap(), vec!["bob"]);
+        // Mutes have no effect on visibility.
+        assert!(!is_blocked_either(&conn, 1, "alice", "bob").unwrap());
+
+        assert!(matches!(
+            mute_participant(&conn, 1, "alice", "alice"),
+            Err(GraphError::InvalidRelationship(_))
+        ));
+        assert!(matches!(
+            mute_participant(&conn, 1, "alice", "nobody"),
+            Err(GraphError::NodeNotFound(_))
+        ));
+
+        unmute_participant(&conn, 1, "alice", "bob").unwrap();
+        assert!(list_mutes(&conn, 1, "alice").unwrap().is_empty());
+    }
+}
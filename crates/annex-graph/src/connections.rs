@@ -0,0 +1,477 @@
+//! Participant-controlled connections.
+//!
+//! `CONNECTED` edges decide who reaches `Degree1` visibility, so they are
+//! only created when the other party agrees: one participant sends a
+//! connection request, the recipient accepts or declines it, and either side
+//! can later remove the connection. A request is only visible to its two
+//! parties.
+
+use crate::{
+    edge_kind_to_str, get_graph_node, is_blocked_either, AdjacencyIndex, GraphEdge, GraphError,
+};
+use annex_types::EdgeKind;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of outgoing requests a participant may have pending.
+pub const MAX_PENDING_CONNECTION_REQUESTS: i64 = 50;
+
+/// Lifecycle state of a connection request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConnectionRequestStatus {
+    Pending,
+    Accepted,
+    Declined,
+}
+
+impl ConnectionRequestStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            ConnectionRequestStatus::Pending => "PENDING",
+            ConnectionRequestStatus::Accepted => "ACCEPTED",
+            ConnectionRequestStatus::Declined => "DECLINED",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self, GraphError> {
+        match s {
+            "PENDING" => Ok(ConnectionRequestStatus::Pending),
+            "ACCEPTED" => Ok(ConnectionRequestStatus::Accepted),
+            "DECLINED" => Ok(ConnectionRequestStatus::Declined),
+            other => Err(GraphError::DatabaseError(
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Text,
+                    format!("unknown connection request status: {}", other).into(),
+                ),
+            )),
+        }
+    }
+}
+
+/// A request from one participant to connect with another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionRequest {
+    /// The database ID.
+    pub id: i64,
+    /// The requesting participant.
+    pub from_node: String,
+    /// The participant asked to connect.
+    pub to_node: String,
+    pub status: ConnectionRequestStatus,
+    /// Creation timestamp (ISO 8601).
+    pub created_at: String,
+    /// When the request was accepted or declined.
+    pub responded_at: Option<String>,
+}
+
+const REQUEST_COLUMNS: &str = "id, from_node, to_node, status, created_at, responded_at";
+
+type RawRequest = (i64, String, String, String, String, Option<String>);
+
+fn request_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<RawRequest> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+    ))
+}
+
+fn finish_request(raw: RawRequest) -> Result<ConnectionRequest, GraphError> {
+    Ok(ConnectionRequest {
+        id: raw.0,
+        from_node: raw.1,
+        to_node: raw.2,
+        status: ConnectionRequestStatus::parse(&raw.3)?,
+        created_at: raw.4,
+        responded_at: raw.5,
+    })
+}
+
+/// Returns `true` if a `CONNECTED` edge links the two participants in either
+/// direction.
+pub fn are_connected(
+    conn: &Connection,
+    server_id: i64,
+    a: &str,
+    b: &str,
+) -> Result<bool, GraphError> {
+    let connected = conn.query_row(
+        "SELECT EXISTS(
+            SELECT 1 FROM graph_edges
+            WHERE server_id = ?1 AND kind = ?2
+              AND ((from_node = ?3 AND to_node = ?4) OR (from_node = ?4 AND to_node = ?3))
+         )",
+        params![server_id, edge_kind_to_str(EdgeKind::Connected), a, b],
+        |row| row.get(0),
+    )?;
+    Ok(connected)
+}
+
+/// Asks `to_node` to connect with `from_node`.
+///
+/// Fails if the target has no graph node, either party has blocked the
+/// other, the two are already connected, a request between them is already
+/// pending in either direction, or the requester has
+/// [`MAX_PENDING_CONNECTION_REQUESTS`] outstanding.
+pub fn create_connection_request(
+    conn: &Connection,
+    server_id: i64,
+    from_node: &str,
+    to_node: &str,
+) -> Result<ConnectionRequest, GraphError> {
+    if from_node == to_node {
+        return Err(GraphError::InvalidConnection(
+            "cannot connect to yourself".to_string(),
+        ));
+    }
+    if get_graph_node(conn, server_id, to_node)?.is_none() {
+        return Err(GraphError::NodeNotFound(to_node.to_string()));
+    }
+    if is_blocked_either(conn, server_id, from_node, to_node)? {
+        return Err(GraphError::Blocked(format!(
+            "cannot request a connection with {}",
+            to_node
+        )));
+    }
+    if are_connected(conn, server_id, from_node, to_node)? {
+        return Err(GraphError::ConnectionConflict(format!(
+            "already connected to {}",
+            to_node
+        )));
+    }
+
+    let pending_between: bool = conn.query_row(
+        "SELECT EXISTS(
+            SELECT 1 FROM graph_connection_requests
+            WHERE server_id = ?1 AND status = 'PENDING'
+              AND ((from_node = ?2 AND to_node = ?3) OR (from_node = ?3 AND to_node = ?2))
+         )",
+        params![server_id, from_node, to_node],
+        |row| row.get(0),
+    )?;
+    if pending_between {
+        return Err(GraphError::ConnectionConflict(format!(
+            "a request with {} is already pending",
+            to_node
+        )));
+    }
+
+    let outstanding: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM graph_connection_requests
+         WHERE server_id = ?1 AND from_node = ?2 AND status = 'PENDING'",
+        params![server_id, from_node],
+        |row| row.get(0),
+    )?;
+    if outstanding >= MAX_PENDING_CONNECTION_REQUESTS {
+        return Err(GraphError::TooManyPendingRequests);
+    }
+
+    let raw = conn.query_row(
+        &format!(
+            "INSERT INTO graph_connection_requests (server_id, from_node, to_node)
+             VALUES (?1, ?2, ?3)
+             RETURNING {}",
+            REQUEST_COLUMNS
+        ),
+        params![server_id, from_node, to_node],
+        request_from_row,
+    )?;
+    finish_request(raw)
+}
+
+/// Lists the pending requests `pseudonym_id` has sent or received, newest
+/// first.
+pub fn list_connection_requests(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+) -> Result<Vec<ConnectionRequest>, GraphError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM graph_connection_requests
+         WHERE server_id = ?1 AND status = 'PENDING' AND (from_node = ?2 OR to_node = ?2)
+         ORDER BY id DESC",
+        REQUEST_COLUMNS
+    ))?;
+    let rows = stmt.query_map(params![server_id, pseudonym_id], request_from_row)?;
+
+    let mut requests = Vec::new();
+    for row in rows {
+        requests.push(finish_request(row?)?);
+    }
+    Ok(requests)
+}
+
+/// Loads a request on behalf of `viewer`. Requests the viewer is not a party
+/// to are reported as not found.
+fn get_request_for(
+    conn: &Connection,
+    server_id: i64,
+    request_id: i64,
+    viewer: &str,
+) -> Result<ConnectionRequest, GraphError> {
+    let raw = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM graph_connection_requests
+                 WHERE server_id = ?1 AND id = ?2 AND (from_node = ?3 OR to_node = ?3)",
+                REQUEST_COLUMNS
+            ),
+            params![server_id, request_id, viewer],
+            request_from_row,
+        )
+        .optional()?
+        .ok_or(GraphError::ConnectionRequestNotFound(request_id))?;
+    finish_request(raw)
+}
+
+fn respond(
+    conn: &Connection,
+    request: &mut ConnectionRequest,
+    status: ConnectionRequestStatus,
+) -> Result<(), GraphError> {
+    request.responded_at = Some(conn.query_row(
+        "UPDATE graph_connection_requests
+         SET status = ?1, responded_at = datetime('now')
+         WHERE id = ?2
+         RETURNING responded_at",
+        params![status.as_str(), request.id],
+        |row| row.get(0),
+    )?);
+    request.status = status;
+    Ok(())
+}
+
+/// Accepts a pending request addressed to `responder`, creating a
+/// `CONNECTED` edge in each direction.
+///
+/// Returns the updated request and the edges created.
+pub fn accept_connection_request(
+    conn: &mut Connection,
+    index: &AdjacencyIndex,
+    server_id: i64,
+    request_id: i64,
+    responder: &str,
+) -> Result<(ConnectionRequest, Vec<GraphEdge>), GraphError> {
+    let tx = conn.transaction()?;
+    let mut request = get_request_for(&tx, server_id, request_id, responder)?;
+    if request.to_node != responder {
+        return Err(GraphError::InvalidConnection(
+            "only the recipient can accept a connection request".to_string(),
+        ));
+    }
+    if request.status != ConnectionRequestStatus::Pending {
+        return Err(GraphError::ConnectionConflict(format!(
+            "connection request {} is no longer pending",
+            request_id
+        )));
+    }
+
+    respond(&tx, &mut request, ConnectionRequestStatus::Accepted)?;
+    let edges = vec![
+        crate::create_edge(
+            &tx,
+            server_id,
+            &request.from_node,
+            &request.to_node,
+            EdgeKind::Connected,
+            1.0,
+        )?,
+        crate::create_edge(
+            &tx,
+            server_id,
+            &request.to_node,
+            &request.from_node,
+            EdgeKind::Connected,
+            1.0,
+        )?,
+    ];
+    tx.commit()?;
+
+    for edge in &edges {
+        index.edge_created(server_id, &edge.from_node, &edge.to_node, edge.kind);
+    }
+    Ok((request, edges))
+}
+
+/// Declines a pending request. Either party may decline; for the requester
+/// this withdraws the request.
+pub fn decline_connection_request(
+    conn: &Connection,
+    server_id: i64,
+    request_id: i64,
+    responder: &str,
+) -> Result<ConnectionRequest, GraphError> {
+    let mut request = get_request_for(conn, server_id, request_id, responder)?;
+    if request.status != ConnectionRequestStatus::Pending {
+        return Err(GraphError::ConnectionConflict(format!(
+            "connection request {} is no longer pending",
+            request_id
+        )));
+    }
+    respond(conn, &mut request, ConnectionRequestStatus::Declined)?;
+    Ok(request)
+}
+
+/// Removes the connection between two participants, deleting the
+/// `CONNECTED` edges in both directions.
+///
+/// Returns the `(from_node, to_node)` pairs of the edges removed.
+pub fn remove_connection(
+    conn: &mut Connection,
+    index: &AdjacencyIndex,
+    server_id: i64,
+    pseudonym_id: &str,
+    other: &str,
+) -> Result<Vec<(String, String)>, GraphError> {
+    let tx = conn.transaction()?;
+    let mut removed = Vec::new();
+    for (from, to) in [(pseudonym_id, other), (other, pseudonym_id)] {
+        if crate::delete_edge(&tx, server_id, from, to, EdgeKind::Connected)? > 0 {
+            removed.push((from.to_string(), to.to_string()));
+        }
+    }
+    if removed.is_empty() {
+        return Err(GraphError::NotConnected(other.to_string()));
+    }
+    tx.commit()?;
+
+    for (from, to) in &removed {
+        index.edge_deleted(server_id, from, to, EdgeKind::Connected);
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ensure_graph_node;
+    use annex_db::run_migrations;
+    use annex_types::{NodeType, VisibilityLevel};
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().expect("db open failed");
+        run_migrations(&conn).expect("migrations failed");
+        for n in ["alice", "bob", "carol"] {
+            ensure_graph_node(&conn, 1, n, NodeType::Human, None).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn accept_creates_connection_and_remove_deletes_it() {
+        let mut conn = setup();
+        let index = AdjacencyIndex::new();
+        assert_eq!(
+            index.node_visibility(&conn, 1, "alice", "bob").unwrap(),
+            VisibilityLevel::None
+        );
+
+        let request = create_connection_request(&conn, 1, "alice", "bob").unwrap();
+        assert_eq!(request.status, ConnectionRequestStatus::Pending);
+
+        // Only the two parties see the request.
+        assert_eq!(
+            list_connection_requests(&conn, 1, "alice").unwrap().len(),
+            1
+        );
+        assert_eq!(list_connection_requests(&conn, 1, "bob").unwrap().len(), 1);
+        assert!(list_connection_requests(&conn, 1, "carol")
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            accept_connection_request(&mut conn, &index, 1, request.id, "carol"),
+            Err(GraphError::ConnectionRequestNotFound(_))
+        ));
+
+        // The requester cannot accept their own request.
+        assert!(matches!(
+            accept_connection_request(&mut conn, &index, 1, request.id, "alice"),
+            Err(GraphError::InvalidConnection(_))
+        ));
+
+        let (accepted, edges) =
+            accept_connection_request(&mut conn, &index, 1, request.id, "bob").unwrap();
+        assert_eq!(accepted.status, ConnectionRequestStatus::Accepted);
+        assert!(accepted.responded_at.is_some());
+        assert_eq!(edges.len(), 2);
+        assert!(are_connected(&conn, 1, "bob", "alice").unwrap());
+        assert_eq!(
+            index.node_visibility(&conn, 1, "alice", "bob").unwrap(),
+            VisibilityLevel::Degree1
+        );
+        assert!(list_connection_requests(&conn, 1, "bob")
+            .unwrap()
+            .is_empty());
+
+        // Already connected.
+        assert!(matches!(
+            create_connection_request(&conn, 1, "bob", "alice"),
+            Err(GraphError::ConnectionConflict(_))
+        ));
+
+        let removed = remove_connection(&mut conn, &index, 1, "bob", "alice").unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(!are_connected(&conn, 1, "alice", "bob").unwrap());
+        assert_eq!(
+            index.node_visibility(&conn, 1, "alice", "bob").unwrap(),
+            VisibilityLevel::None
+        );
+        assert!(matches!(
+            remove_connection(&mut conn, &index, 1, "bob", "alice"),
+            Err(GraphError::NotConnected(_))
+        ));
+    }
+
+    #[test]
+    fn request_validation() {
+        let conn = setup();
+
+        assert!(matches!(
+            create_connection_request(&conn, 1, "alice", "alice"),
+            Err(GraphError::InvalidConnection(_))
+        ));
+        assert!(matches!(
+            create_connection_request(&conn, 1, "alice", "nobody"),
+            Err(GraphError::NodeNotFound(_))
+        ));
+
+        let request = create_connection_request(&conn, 1, "alice", "bob").unwrap();
+        // Duplicate in either direction.
+        assert!(matches!(
+            create_connection_request(&conn, 1, "alice", "bob"),
+            Err(GraphError::ConnectionConflict(_))
+        ));
+        assert!(matches!(
+            create_connection_request(&conn, 1, "bob", "alice"),
+            Err(GraphError::ConnectionConflict(_))
+        ));
+
+        // Declined requests free the pair up again.
+        let declined = decline_connection_request(&conn, 1, request.id, "bob").unwrap();
+        assert_eq!(declined.status, ConnectionRequestStatus::Declined);
+        assert!(matches!(
+            decline_connection_request(&conn, 1, request.id, "bob"),
+            Err(GraphError::ConnectionConflict(_))
+        ));
+        create_connection_request(&conn, 1, "bob", "alice").unwrap();
+    }
+
+    #[test]
+    fn pending_requests_are_capped() {
+        let conn = setup();
+        for i in 0..MAX_PENDING_CONNECTION_REQUESTS {
+            let target = format!("target-{}", i);
+            ensure_graph_node(&conn, 1, &target, NodeType::Human, None).unwrap();
+            create_connection_request(&conn, 1, "alice", &target).unwrap();
+        }
+        assert!(matches!(
+            create_connection_request(&conn, 1, "alice", "bob"),
+            Err(GraphError::TooManyPendingRequests)
+        ));
+    }
+}
@@ -0,0 +1,549 @@
+//! Offline export of the presence graph.
+//!
+//! [`export_graph`] serializes a server's `graph_nodes` and `graph_edges` as
+//! GraphML, Graphviz DOT or JSON-LD so the graph can be inspected in external
+//! tools. [`ExportOptions`] narrows the export to some node types, edge kinds
+//! or a neighborhood (see
+//! [`AdjacencyIndex::neighborhood`](crate::AdjacencyIndex::neighborhood)), and
+//! can replace every pseudonym with a salted hash so the file can be shared
+//! without handing out participant identifiers.
+
+use crate::{edge_kind_to_str, node_type_to_str, str_to_edge_kind, str_to_node_type, GraphError};
+use annex_types::{EdgeKind, NodeType};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+/// File format of a graph export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    GraphMl,
+    Dot,
+    JsonLd,
+}
+
+impl ExportFormat {
+    /// MIME type to serve the export with.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::GraphMl => "application/graphml+xml",
+            ExportFormat::Dot => "text/vnd.graphviz",
+            ExportFormat::JsonLd => "application/ld+json",
+        }
+    }
+
+    /// Conventional file extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::GraphMl => "graphml",
+            ExportFormat::Dot => "dot",
+            ExportFormat::JsonLd => "jsonld",
+        }
+    }
+}
+
+/// Which part of the graph to export, and how.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Only export nodes of these types. Empty exports every type.
+    pub node_types: Vec<NodeType>,
+    /// Only export edges of these kinds. Empty exports every kind.
+    pub edge_kinds: Vec<EdgeKind>,
+    /// Only export these pseudonyms, typically a neighborhood.
+    pub pseudonyms: Option<HashSet<String>>,
+    /// Replace pseudonyms with `SHA-256(salt || pseudonym)`, leave out
+    /// node metadata, which may name the participant, and coarsen node
+    /// timestamps to the day, since exact join and activity times can be
+    /// matched against other records.
+    pub pseudonymize_salt: Option<String>,
+}
+
+/// A node as written to an export.
+#[derive(Debug, Clone, PartialEq)]
+struct ExportNode {
+    id: String,
+    node_type: &'static str,
+    active: bool,
+    last_seen_at: Option<String>,
+    metadata_json: Option<String>,
+    created_at: String,
+}
+
+/// An edge as written to an export.
+#[derive(Debug, Clone, PartialEq)]
+struct ExportEdge {
+    source: String,
+    target: String,
+    kind: &'static str,
+    weight: f64,
+}
+
+/// Parses a stored node type name (e.g. `AI_AGENT`).
+pub fn parse_node_type(s: &str) -> Result<NodeType, GraphError> {
+    str_to_node_type(s).map_err(|_| GraphError::InvalidExport(format!("unknown node type: {s}")))
+}
+
+/// Parses a stored edge kind name (e.g. `MEMBER_OF`).
+pub fn parse_edge_kind(s: &str) -> Result<EdgeKind, GraphError> {
+    str_to_edge_kind(s).map_err(|_| GraphError::InvalidExport(format!("unknown edge kind: {s}")))
+}
+
+fn pseudonymize(salt: &str, pseudonym: &str) -> String {
+    let mut hasher = Sha256::new();
+    // Length prefix so ("ab", "c") and ("a", "bc") hash differently.
+    hasher.update((salt.len() as u64).to_be_bytes());
+    hasher.update(salt.as_bytes());
+    hasher.update(pseudonym.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Truncates a stored timestamp (`YYYY-MM-DD HH:MM:SS` or RFC 3339) to its
+/// date.
+fn coarsen_to_day(mut timestamp: String) -> String {
+    if let Some(end) = timestamp.find([' ', 'T']) {
+        timestamp.truncate(end);
+    }
+    timestamp
+}
+
+/// Exports the server's presence graph in `format`.
+///
+/// Edges are kept only when both endpoints are exported. Nodes and edges are
+/// sorted by their exported identifiers, so a pseudonymized export does not
+/// reveal the order participants joined in.
+pub fn export_graph(
+    conn: &Connection,
+    server_id: i64,
+    options: &ExportOptions,
+    format: ExportFormat,
+) -> Result<String, GraphError> {
+    let node_types: HashSet<&str> = options
+        .node_types
+        .iter()
+        .map(|t| node_type_to_str(*t))
+        .collect();
+    let edge_kinds: HashSet<&str> = options
+        .edge_kinds
+        .iter()
+        .map(|k| edge_kind_to_str(*k))
+        .collect();
+    let export_id = |pseudonym: &str| match &options.pseudonymize_salt {
+        Some(salt) => pseudonymize(salt, pseudonym),
+        None => pseudonym.to_string(),
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT pseudonym_id, node_type, active, last_seen_at, metadata_json, created_at
+         FROM graph_nodes WHERE server_id = ?1",
+    )?;
+    let rows = stmt.query_map(params![server_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, bool>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, String>(5)?,
+        ))
+    })?;
+
+    // pseudonym -> exported id, for mapping edge endpoints
+    let mut exported: BTreeMap<String, String> = BTreeMap::new();
+    let mut nodes = Vec::new();
+    for row in rows {
+        let (pseudonym, node_type, active, last_seen_at, metadata_json, created_at) = row?;
+        let node_type = node_type_to_str(str_to_node_type(&node_type)?);
+        if !node_types.is_empty() && !node_types.contains(node_type) {
+            continue;
+        }
+        if let Some(allowed) = &options.pseudonyms {
+            if !allowed.contains(&pseudonym) {
+                continue;
+            }
+        }
+        let id = export_id(&pseudonym);
+        exported.insert(pseudonym, id.clone());
+        let pseudonymized = options.pseudonymize_salt.is_some();
+        let (last_seen_at, created_at) = if pseudonymized {
+            (last_seen_at.map(coarsen_to_day), coarsen_to_day(created_at))
+        } else {
+            (last_seen_at, created_at)
+        };
+        nodes.push(ExportNode {
+            id,
+            node_type,
+            active,
+            last_seen_at,
+            metadata_json: metadata_json.filter(|_| !pseudonymized),
+            created_at,
+        });
+    }
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut stmt = conn
+        .prepare("SELECT from_node, to_node, kind, weight FROM graph_edges WHERE server_id = ?1")?;
+    let rows = stmt.query_map(params![server_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, f64>(3)?,
+        ))
+    })?;
+    let mut edges = Vec::new();
+    for row in rows {
+        let (from, to, kind, weight) = row?;
+        let kind = edge_kind_to_str(str_to_edge_kind(&kind)?);
+        if !edge_kinds.is_empty() && !edge_kinds.contains(kind) {
+            continue;
+        }
+        let (Some(source), Some(target)) = (exported.get(&from), exported.get(&to)) else {
+            continue;
+        };
+        edges.push(ExportEdge {
+            source: source.clone(),
+            target: target.clone(),
+            kind,
+            weight,
+        });
+    }
+    edges.sort_by(|a, b| (&a.source, &a.target, a.kind).cmp(&(&b.source, &b.target, b.kind)));
+
+    Ok(match format {
+        ExportFormat::GraphMl => to_graphml(server_id, &nodes, &edges),
+        ExportFormat::Dot => to_dot(&nodes, &edges),
+        ExportFormat::JsonLd => to_json_ld(&nodes, &edges)?,
+    })
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn escape_dot(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn to_graphml(server_id: i64, nodes: &[ExportNode], edges: &[ExportEdge]) -> String {
+    // `write!` into a String cannot fail.
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for (id, domain, name, ty) in [
+        ("node_type", "node", "node_type", "string"),
+        ("active", "node", "active", "boolean"),
+        ("last_seen_at", "node", "last_seen_at", "string"),
+        ("created_at", "node", "created_at", "string"),
+        ("metadata_json", "node", "metadata_json", "string"),
+        ("kind", "edge", "kind", "string"),
+        ("weight", "edge", "weight", "double"),
+    ] {
+        let _ = writeln!(
+            out,
+            "  <key id=\"{id}\" for=\"{domain}\" attr.name=\"{name}\" attr.type=\"{ty}\"/>"
+        );
+    }
+    let _ = writeln!(
+        out,
+        "  <graph id=\"server-{server_id}\" edgedefault=\"directed\">"
+    );
+    for node in nodes {
+        let _ = writeln!(out, "    <node id=\"{}\">", escape_xml(&node.id));
+        let _ = writeln!(
+            out,
+            "      <data key=\"node_type\">{}</data>",
+            node.node_type
+        );
+        let _ = writeln!(out, "      <data key=\"active\">{}</data>", node.active);
+        if let Some(last_seen_at) = &node.last_seen_at {
+            let _ = writeln!(
+                out,
+                "      <data key=\"last_seen_at\">{}</data>",
+                escape_xml(last_seen_at)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "      <data key=\"created_at\">{}</data>",
+            escape_xml(&node.created_at)
+        );
+        if let Some(metadata_json) = &node.metadata_json {
+            let _ = writeln!(
+                out,
+                "      <data key=\"metadata_json\">{}</data>",
+                escape_xml(metadata_json)
+            );
+        }
+        out.push_str("    </node>\n");
+    }
+    for edge in edges {
+        let _ = writeln!(
+            out,
+            "    <edge source=\"{}\" target=\"{}\">",
+            escape_xml(&edge.source),
+            escape_xml(&edge.target)
+        );
+        let _ = writeln!(out, "      <data key=\"kind\">{}</data>", edge.kind);
+        let _ = writeln!(out, "      <data key=\"weight\">{}</data>", edge.weight);
+        out.push_str("    </edge>\n");
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn to_dot(nodes: &[ExportNode], edges: &[ExportEdge]) -> String {
+    let mut out = String::from("digraph annex {\n");
+    for node in nodes {
+        let _ = write!(
+            out,
+            "  \"{}\" [node_type=\"{}\", active={}, created_at=\"{}\"",
+            escape_dot(&node.id),
+            node.node_type,
+            node.active,
+            escape_dot(&node.created_at)
+        );
+        if let Some(last_seen_at) = &node.last_seen_at {
+            let _ = write!(out, ", last_seen_at=\"{}\"", escape_dot(last_seen_at));
+        }
+        if let Some(metadata_json) = &node.metadata_json {
+            let _ = write!(out, ", metadata_json=\"{}\"", escape_dot(metadata_json));
+        }
+        out.push_str("];\n");
+    }
+    for edge in edges {
+        let _ = writeln!(
+            out,
+            "  \"{}\" -> \"{}\" [kind=\"{}\", weight={}];",
+            escape_dot(&edge.source),
+            escape_dot(&edge.target),
+            edge.kind,
+            edge.weight
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Node IRI: pseudonyms percent-encoded under an `urn:annex:node:` prefix.
+fn node_iri(id: &str) -> String {
+    let mut iri = String::from("urn:annex:node:");
+    for byte in id.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            iri.push(byte as char);
+        } else {
+            let _ = write!(iri, "%{byte:02X}");
+        }
+    }
+    iri
+}
+
+fn to_json_ld(nodes: &[ExportNode], edges: &[ExportEdge]) -> Result<String, GraphError> {
+    let mut graph: Vec<serde_json::Value> = nodes
+        .iter()
+        .map(|node| {
+            let mut value = serde_json::json!({
+                "@id": node_iri(&node.id),
+                "@type": "Node",
+                "pseudonymId": node.id,
+                "nodeType": node.node_type,
+                "active": node.active,
+                "createdAt": node.created_at,
+            });
+            if let Some(last_seen_at) = &node.last_seen_at {
+                value["lastSeenAt"] = last_seen_at.clone().into();
+            }
+            if let Some(metadata_json) = &node.metadata_json {
+                value["metadataJson"] = metadata_json.clone().into();
+            }
+            value
+        })
+        .collect();
+    graph.extend(edges.iter().map(|edge| {
+        serde_json::json!({
+            "@type": "Edge",
+            "source": node_iri(&edge.source),
+            "target": node_iri(&edge.target),
+            "kind": edge.kind,
+            "weight": edge.weight,
+        })
+    }));
+
+    let document = serde_json::json!({
+        "@context": {
+            "@vocab": "urn:annex:graph:",
+            "source": { "@type": "@id" },
+            "target": { "@type": "@id" },
+        },
+        "@graph": graph,
+    });
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_edge, ensure_graph_node, AdjacencyIndex};
+    use annex_db::run_migrations;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().expect("db open failed");
+        run_migrations(&conn).expect("migrations failed");
+        ensure_graph_node(
+            &conn,
+            1,
+            "alice",
+            NodeType::Human,
+            Some(r#"{"name":"A & B"}"#.to_string()),
+        )
+        .unwrap();
+        for n in ["bob", "carol", "dave"] {
+            ensure_graph_node(&conn, 1, n, NodeType::Human, None).unwrap();
+        }
+        ensure_graph_node(&conn, 1, "bot", NodeType::AiAgent, None).unwrap();
+        // alice - bob - carol - dave, bot - alice
+        create_edge(&conn, 1, "alice", "bob", EdgeKind::Connected, 1.0).unwrap();
+        create_edge(&conn, 1, "bob", "carol", EdgeKind::Connected, 1.0).unwrap();
+        create_edge(&conn, 1, "carol", "dave", EdgeKind::Connected, 1.0).unwrap();
+        create_edge(&conn, 1, "bot", "alice", EdgeKind::AgentServing, 0.5).unwrap();
+        conn
+    }
+
+    #[test]
+    fn graphml_escapes_and_lists_everything() {
+        let conn = setup();
+        let out = export_graph(&conn, 1, &ExportOptions::default(), ExportFormat::GraphMl).unwrap();
+
+        assert!(out.contains("<node id=\"alice\">"));
+        assert!(out.contains("<node id=\"bot\">"));
+        assert!(out.contains("{&quot;name&quot;:&quot;A &amp; B&quot;}"));
+        assert!(out.contains("<edge source=\"bot\" target=\"alice\">"));
+        assert_eq!(out.matches("<node ").count(), 5);
+        assert_eq!(out.matches("<edge ").count(), 4);
+    }
+
+    #[test]
+    fn filters_by_node_type_and_edge_kind() {
+        let conn = setup();
+        let options = ExportOptions {
+            edge_kinds: vec![EdgeKind::AgentServing],
+            ..Default::default()
+        };
+        let out = export_graph(&conn, 1, &options, ExportFormat::Dot).unwrap();
+        assert_eq!(out.matches(" -> ").count(), 1);
+        assert!(out.contains("\"bot\" -> \"alice\" [kind=\"AGENT_SERVING\", weight=0.5];"));
+
+        // Dropping the agent drops its edge too.
+        let options = ExportOptions {
+            node_types: vec![NodeType::Human],
+            ..Default::default()
+        };
+        let out = export_graph(&conn, 1, &options, ExportFormat::Dot).unwrap();
+        assert!(!out.contains("bot"));
+        assert_eq!(out.matches(" -> ").count(), 3);
+    }
+
+    #[test]
+    fn pseudonymized_export_hides_ids_and_metadata() {
+        let conn = setup();
+        conn.execute(
+            "UPDATE graph_nodes SET last_seen_at = '2026-01-02 03:04:05'
+             WHERE pseudonym_id = 'alice'",
+            [],
+        )
+        .unwrap();
+        let options = ExportOptions {
+            pseudonymize_salt: Some("pepper".to_string()),
+            ..Default::default()
+        };
+        let out = export_graph(&conn, 1, &options, ExportFormat::JsonLd).unwrap();
+        assert!(!out.contains("alice"));
+        assert!(!out.contains("metadataJson"));
+        assert!(out.contains(&pseudonymize("pepper", "alice")));
+
+        // Same salt, same ids; different salt, unlinkable ids.
+        let again = export_graph(&conn, 1, &options, ExportFormat::JsonLd).unwrap();
+        assert_eq!(out, again);
+        assert_ne!(
+            pseudonymize("pepper", "alice"),
+            pseudonymize("salt", "alice")
+        );
+
+        let document: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(document["@graph"].as_array().unwrap().len(), 9);
+
+        // Timestamps keep only the date.
+        let alice = document["@graph"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| {
+                item["@id"]
+                    .as_str()
+                    .unwrap_or("")
+                    .contains(&pseudonymize("pepper", "alice"))
+            })
+            .unwrap();
+        let created_at = alice["createdAt"].as_str().unwrap();
+        assert_eq!(created_at.len(), "YYYY-MM-DD".len(), "{created_at}");
+        assert_eq!(alice["lastSeenAt"], "2026-01-02");
+        assert_eq!(
+            coarsen_to_day("2026-01-02T03:04:05Z".to_string()),
+            "2026-01-02"
+        );
+    }
+
+    #[test]
+    fn neighborhood_limits_export() {
+        let conn = setup();
+        let index = AdjacencyIndex::new();
+        let options = ExportOptions {
+            pseudonyms: Some(index.neighborhood(&conn, 1, "bob", 1).unwrap()),
+            ..Default::default()
+        };
+        let out = export_graph(&conn, 1, &options, ExportFormat::Dot).unwrap();
+        for present in ["alice", "bob", "carol"] {
+            assert!(out.contains(&format!("\"{present}\" [")), "{present}");
+        }
+        assert!(!out.contains("dave"));
+        assert!(!out.contains("bot"));
+        assert_eq!(out.matches(" -> ").count(), 2);
+    }
+
+    #[test]
+    fn parses_filter_names() {
+        assert_eq!(parse_node_type("AI_AGENT").unwrap(), NodeType::AiAgent);
+        assert_eq!(parse_edge_kind("MEMBER_OF").unwrap(), EdgeKind::MemberOf);
+        assert!(matches!(
+            parse_node_type("ALIEN"),
+            Err(GraphError::InvalidExport(_))
+        ));
+        assert!(matches!(
+            parse_edge_kind("FRIENDS"),
+            Err(GraphError::InvalidExport(_))
+        ));
+    }
+}
@@ -0,0 +1,541 @@
+//! Historical presence-graph snapshots.
+//!
+//! `prune_inactive_nodes` and `delete_edge` only keep the current graph, so
+//! triggers on `graph_nodes` and `graph_edges` append every node activation
+//! change and every edge insertion and deletion to `graph_node_history` and
+//! `graph_edge_history`. [`graph_snapshot_at`] replays that log to rebuild
+//! the graph as of a timestamp, and [`graph_changes`] lists the raw entries.
+//!
+//! History is kept for `graph_history_retention_days`.
+//! [`compact_graph_history`] drops entries older than that while keeping the
+//! graph at the horizon reconstructible, and [`resolve_history_timestamp`]
+//! rejects queries from before it.
+
+use crate::{str_to_edge_kind, str_to_node_type, GraphError};
+use annex_types::{EdgeKind, NodeType};
+use rusqlite::{params, Connection, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Whether an edge was added to or removed from the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EdgeChange {
+    Added,
+    Removed,
+}
+
+/// A node's state after a change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeHistoryEntry {
+    pub id: i64,
+    pub pseudonym_id: String,
+    pub node_type: NodeType,
+    pub active: bool,
+    /// When the change happened (ISO 8601).
+    pub changed_at: String,
+}
+
+/// An edge added to or removed from the graph.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeHistoryEntry {
+    pub id: i64,
+    pub from_node: String,
+    pub to_node: String,
+    pub kind: EdgeKind,
+    pub weight: f64,
+    pub change: EdgeChange,
+    /// When the change happened (ISO 8601).
+    pub changed_at: String,
+}
+
+/// Change log entries in a time window, oldest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphChanges {
+    pub nodes: Vec<NodeHistoryEntry>,
+    pub edges: Vec<EdgeHistoryEntry>,
+}
+
+/// A node as it was at a snapshot's timestamp.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotNode {
+    pub pseudonym_id: String,
+    pub node_type: NodeType,
+    pub active: bool,
+}
+
+/// An edge that existed at a snapshot's timestamp.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEdge {
+    pub from_node: String,
+    pub to_node: String,
+    pub kind: EdgeKind,
+    pub weight: f64,
+}
+
+/// The presence graph reconstructed as of `at`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    /// The snapshot timestamp (`YYYY-MM-DD HH:MM:SS`, UTC).
+    pub at: String,
+    pub nodes: Vec<SnapshotNode>,
+    pub edges: Vec<SnapshotEdge>,
+}
+
+/// Returns the retention horizon, or `None` if history is kept forever.
+pub fn history_horizon(
+    conn: &Connection,
+    retention_days: u32,
+) -> Result<Option<String>, GraphError> {
+    if retention_days == 0 {
+        return Ok(None);
+    }
+    Ok(Some(conn.query_row(
+        "SELECT datetime('now', '-' || ?1 || ' days')",
+        params![retention_days],
+        |row| row.get(0),
+    )?))
+}
+
+/// Normalizes a timestamp and checks it against the retention horizon.
+///
+/// Accepts anything SQLite's `datetime()` does, including ISO 8601 with a
+/// `Z` or offset suffix. With a non-zero `retention_days`, timestamps older
+/// than the horizon are rejected because history before it has been
+/// compacted away.
+pub fn resolve_history_timestamp(
+    conn: &Connection,
+    timestamp: &str,
+    retention_days: u32,
+) -> Result<String, GraphError> {
+    let (normalized, before_horizon): (Option<String>, bool) = conn.query_row(
+        "SELECT datetime(?1), ?2 > 0 AND datetime(?1) < datetime('now', '-' || ?2 || ' days')",
+        params![timestamp, retention_days],
+        |row| Ok((row.get(0)?, row.get::<_, Option<bool>>(1)?.unwrap_or(false))),
+    )?;
+    let normalized = normalized.ok_or_else(|| {
+        GraphError::InvalidHistoryQuery(format!("invalid timestamp: {}", timestamp))
+    })?;
+    if before_horizon {
+        return Err(GraphError::InvalidHistoryQuery(format!(
+            "{} is outside the {} day history retention window",
+            normalized, retention_days
+        )));
+    }
+    Ok(normalized)
+}
+
+/// Edge state while replaying the log: live copies and latest weight.
+type EdgeState = BTreeMap<(String, String, String), (usize, f64)>;
+
+fn replay_edges(conn: &Connection, server_id: i64, at: &str) -> Result<EdgeState, GraphError> {
+    let mut stmt = conn.prepare(
+        "SELECT from_node, to_node, kind, weight, change FROM graph_edge_history
+         WHERE server_id = ?1 AND changed_at <= ?2
+         ORDER BY changed_at, id",
+    )?;
+    let rows = stmt.query_map(params![server_id, at], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<f64>>(3)?,
+            row.get::<_, String>(4)?,
+        ))
+    })?;
+
+    let mut edges = EdgeState::new();
+    for row in rows {
+        let (from, to, kind, weight, change) = row?;
+        let entry = edges.entry((from, to, kind)).or_insert((0, 1.0));
+        if change == "ADDED" {
+            entry.0 += 1;
+            entry.1 = weight.unwrap_or(1.0);
+        } else {
+            entry.0 = entry.0.saturating_sub(1);
+        }
+    }
+    edges.retain(|_, (count, _)| *count > 0);
+    Ok(edges)
+}
+
+/// Reconstructs the graph as it was at `at`.
+///
+/// `at` should come from [`resolve_history_timestamp`]. With `pseudonym_id`
+/// set, only that participant's edges and the nodes they touch are returned.
+pub fn graph_snapshot_at(
+    conn: &Connection,
+    server_id: i64,
+    at: &str,
+    pseudonym_id: Option<&str>,
+) -> Result<GraphSnapshot, GraphError> {
+    let mut edges = Vec::new();
+    for ((from_node, to_node, kind), (_, weight)) in replay_edges(conn, server_id, at)? {
+        if pseudonym_id.is_some_and(|p| p != from_node && p != to_node) {
+            continue;
+        }
+        edges.push(SnapshotEdge {
+            kind: str_to_edge_kind(&kind)?,
+            from_node,
+            to_node,
+            weight,
+        });
+    }
+
+    let wanted: Option<BTreeSet<&str>> = pseudonym_id.map(|p| {
+        edges
+            .iter()
+            .flat_map(|e| [e.from_node.as_str(), e.to_node.as_str()])
+            .chain(std::iter::once(p))
+            .collect()
+    });
+
+    let mut stmt = conn.prepare(
+        "SELECT pseudonym_id, node_type, active FROM graph_node_history
+         WHERE id IN (
+            SELECT MAX(id) FROM graph_node_history
+            WHERE server_id = ?1 AND changed_at <= ?2
+            GROUP BY pseudonym_id
+         )
+         ORDER BY pseudonym_id",
+    )?;
+    let rows = stmt.query_map(params![server_id, at], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, bool>(2)?,
+        ))
+    })?;
+
+    let mut nodes = Vec::new();
+    for row in rows {
+        let (pseudonym_id, node_type, active) = row?;
+        if wanted
+            .as_ref()
+            .is_some_and(|w| !w.contains(pseudonym_id.as_str()))
+        {
+            continue;
+        }
+        nodes.push(SnapshotNode {
+            pseudonym_id,
+            node_type: str_to_node_type(&node_type)?,
+            active,
+        });
+    }
+
+    Ok(GraphSnapshot {
+        at: at.to_string(),
+        nodes,
+        edges,
+    })
+}
+
+/// Lists change log entries with `since <= changed_at <= until`.
+///
+/// Both bounds should come from [`resolve_history_timestamp`]; without
+/// `since` the window starts at the oldest entry. At most
+/// `limit` node and `limit` edge entries are returned, oldest first.
+pub fn graph_changes(
+    conn: &Connection,
+    server_id: i64,
+    since: Option<&str>,
+    until: &str,
+    pseudonym_id: Option<&str>,
+    limit: u32,
+) -> Result<GraphChanges, GraphError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, pseudonym_id, node_type, active, changed_at FROM graph_node_history
+         WHERE server_id = ?1 AND (?2 IS NULL OR changed_at >= ?2) AND changed_at <= ?3
+           AND (?4 IS NULL OR pseudonym_id = ?4)
+         ORDER BY changed_at, id
+         LIMIT ?5",
+    )?;
+    let rows = stmt.query_map(
+        params![server_id, since, until, pseudonym_id, limit],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, bool>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        },
+    )?;
+    let mut nodes = Vec::new();
+    for row in rows {
+        let (id, pseudonym_id, node_type, active, changed_at) = row?;
+        nodes.push(NodeHistoryEntry {
+            id,
+            pseudonym_id,
+            node_type: str_to_node_type(&node_type)?,
+            active,
+            changed_at,
+        });
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, from_node, to_node, kind, weight, change, changed_at FROM graph_edge_history
+         WHERE server_id = ?1 AND (?2 IS NULL OR changed_at >= ?2) AND changed_at <= ?3
+           AND (?4 IS NULL OR from_node = ?4 OR to_node = ?4)
+         ORDER BY changed_at, id
+         LIMIT ?5",
+    )?;
+    let rows = stmt.query_map(
+        params![server_id, since, until, pseudonym_id, limit],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<f64>>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        },
+    )?;
+    let mut edges = Vec::new();
+    for row in rows {
+        let (id, from_node, to_node, kind, weight, change, changed_at) = row?;
+        edges.push(EdgeHistoryEntry {
+            id,
+            from_node,
+            to_node,
+            kind: str_to_edge_kind(&kind)?,
+            weight: weight.unwrap_or(1.0),
+            change: if change == "ADDED" {
+                EdgeChange::Added
+            } else {
+                EdgeChange::Removed
+            },
+            changed_at,
+        });
+    }
+
+    Ok(GraphChanges { nodes, edges })
+}
+
+/// Deletes history older than `retention_days`, keeping only what is
+/// needed to reconstruct the graph at the horizon.
+///
+/// For each node the latest entry before the horizon is kept. For each edge
+/// the `ADDED` entries still live at the horizon are kept, and everything
+/// else before it is deleted. Returns the number of deleted entries. Zero
+/// `retention_days` keeps history forever.
+pub fn compact_graph_history(
+    conn: &mut Connection,
+    server_id: i64,
+    retention_days: u32,
+) -> Result<usize, GraphError> {
+    let Some(horizon) = history_horizon(conn, retention_days)? else {
+        return Ok(0);
+    };
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    let mut deleted = tx.execute(
+        "DELETE FROM graph_node_history
+         WHERE server_id = ?1 AND changed_at < ?2
+           AND id NOT IN (
+              SELECT MAX(id) FROM graph_node_history
+              WHERE server_id = ?1 AND changed_at < ?2
+              GROUP BY pseudonym_id
+           )",
+        params![server_id, horizon],
+    )?;
+
+    // Replaying the log up to the horizon leaves each edge with some live
+    // copies; those are the most recent `ADDED` entries for it.
+    let mut live: BTreeMap<(String, String, String), Vec<i64>> = BTreeMap::new();
+    let mut expired = Vec::new();
+    {
+        let mut stmt = tx.prepare(
+            "SELECT id, from_node, to_node, kind, change FROM graph_edge_history
+             WHERE server_id = ?1 AND changed_at < ?2
+             ORDER BY changed_at, id",
+        )?;
+        let rows = stmt.query_map(params![server_id, horizon], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+        for row in rows {
+            let (id, from, to, kind, change) = row?;
+            let copies = live.entry((from, to, kind)).or_default();
+            if change == "ADDED" {
+                copies.push(id);
+            } else if let Some(added) = copies.pop() {
+                expired.push(added);
+            }
+            if change != "ADDED" {
+                expired.push(id);
+            }
+        }
+    }
+    {
+        let mut stmt = tx.prepare("DELETE FROM graph_edge_history WHERE id = ?1")?;
+        for id in expired {
+            deleted += stmt.execute(params![id])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_edge, delete_edge, ensure_graph_node, prune_inactive_nodes};
+    use annex_db::run_migrations;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().expect("db open failed");
+        run_migrations(&conn).expect("migrations failed");
+        conn
+    }
+
+    fn add_edge_event(conn: &Connection, from: &str, to: &str, change: &str, at: &str) {
+        conn.execute(
+            "INSERT INTO graph_edge_history (server_id, from_node, to_node, kind, weight, change, changed_at)
+             VALUES (1, ?1, ?2, 'CONNECTED', 1.0, ?3, ?4)",
+            params![from, to, change, at],
+        )
+        .unwrap();
+    }
+
+    fn edge_pairs(snapshot: &GraphSnapshot) -> Vec<(&str, &str)> {
+        snapshot
+            .edges
+            .iter()
+            .map(|e| (e.from_node.as_str(), e.to_node.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_survives_deletion_and_pruning() {
+        let conn = setup();
+        for n in ["alice", "bob", "carol"] {
+            ensure_graph_node(&conn, 1, n, NodeType::Human, None).unwrap();
+        }
+        create_edge(&conn, 1, "alice", "bob", EdgeKind::Connected, 2.0).unwrap();
+        create_edge(&conn, 1, "bob", "carol", EdgeKind::Connected, 1.0).unwrap();
+        let before = resolve_history_timestamp(&conn, "now", 90).unwrap();
+
+        delete_edge(&conn, 1, "alice", "bob", EdgeKind::Connected).unwrap();
+        conn.execute(
+            "UPDATE graph_nodes SET last_seen_at = datetime('now', '-1 hour') WHERE pseudonym_id = 'carol'",
+            [],
+        )
+        .unwrap();
+        prune_inactive_nodes(&conn, 1, 60).unwrap();
+
+        // Later changes land in the same second; move them into the future
+        // so they sort after `before`.
+        conn.execute_batch(
+            "DROP TRIGGER graph_node_history_append_only;
+             DROP TRIGGER graph_edge_history_append_only;
+             UPDATE graph_node_history SET changed_at = datetime('now', '+1 minute') WHERE active = 0;
+             UPDATE graph_edge_history SET changed_at = datetime('now', '+1 minute') WHERE change = 'REMOVED';",
+        )
+        .unwrap();
+
+        let past = graph_snapshot_at(&conn, 1, &before, None).unwrap();
+        assert_eq!(past.nodes.len(), 3);
+        assert!(past.nodes.iter().all(|n| n.active));
+        assert_eq!(edge_pairs(&past), vec![("alice", "bob"), ("bob", "carol")]);
+        assert_eq!(past.edges[0].weight, 2.0);
+
+        let later = resolve_history_timestamp(&conn, "now", 90).unwrap();
+        let later = conn
+            .query_row("SELECT datetime(?1, '+2 minutes')", [later], |row| {
+                row.get::<_, String>(0)
+            })
+            .unwrap();
+        let now = graph_snapshot_at(&conn, 1, &later, None).unwrap();
+        assert_eq!(edge_pairs(&now), vec![("bob", "carol")]);
+        let carol = now
+            .nodes
+            .iter()
+            .find(|n| n.pseudonym_id == "carol")
+            .unwrap();
+        assert!(!carol.active);
+
+        let alice = graph_snapshot_at(&conn, 1, &before, Some("alice")).unwrap();
+        assert_eq!(edge_pairs(&alice), vec![("alice", "bob")]);
+        let names: Vec<_> = alice
+            .nodes
+            .iter()
+            .map(|n| n.pseudonym_id.as_str())
+            .collect();
+        assert_eq!(names, vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn history_is_append_only() {
+        let conn = setup();
+        ensure_graph_node(&conn, 1, "alice", NodeType::Human, None).unwrap();
+        assert!(conn
+            .execute("UPDATE graph_node_history SET active = 0", [])
+            .is_err());
+    }
+
+    #[test]
+    fn resolves_timestamps_within_retention() {
+        let conn = setup();
+        assert_eq!(
+            resolve_history_timestamp(&conn, "2030-01-02T03:04:05Z", 90).unwrap(),
+            "2030-01-02 03:04:05"
+        );
+        assert!(matches!(
+            resolve_history_timestamp(&conn, "last month", 90),
+            Err(GraphError::InvalidHistoryQuery(_))
+        ));
+        assert!(matches!(
+            resolve_history_timestamp(&conn, "2000-01-01", 90),
+            Err(GraphError::InvalidHistoryQuery(_))
+        ));
+        assert!(resolve_history_timestamp(&conn, "2000-01-01", 0).is_ok());
+    }
+
+    #[test]
+    fn compaction_keeps_state_at_horizon() {
+        let mut conn = setup();
+        // alice-bob added and removed long ago; bob-carol added long ago and
+        // removed recently; carol-dave added twice long ago, one removed.
+        add_edge_event(&conn, "alice", "bob", "ADDED", "2000-01-01 00:00:00");
+        add_edge_event(&conn, "alice", "bob", "REMOVED", "2000-01-02 00:00:00");
+        add_edge_event(&conn, "bob", "carol", "ADDED", "2000-01-01 00:00:00");
+        add_edge_event(&conn, "bob", "carol", "REMOVED", "2099-01-01 00:00:00");
+        add_edge_event(&conn, "carol", "dave", "ADDED", "2000-01-01 00:00:00");
+        add_edge_event(&conn, "carol", "dave", "ADDED", "2000-01-02 00:00:00");
+        add_edge_event(&conn, "carol", "dave", "REMOVED", "2000-01-03 00:00:00");
+        for (active, at) in [(1, "2000-01-01 00:00:00"), (0, "2000-01-02 00:00:00")] {
+            conn.execute(
+                "INSERT INTO graph_node_history (server_id, pseudonym_id, node_type, active, changed_at)
+                 VALUES (1, 'alice', 'HUMAN', ?1, ?2)",
+                params![active, at],
+            )
+            .unwrap();
+        }
+
+        let horizon = "2050-01-01 00:00:00";
+        let before = graph_snapshot_at(&conn, 1, horizon, None).unwrap();
+        assert_eq!(compact_graph_history(&mut conn, 1, 30).unwrap(), 5);
+        assert_eq!(graph_snapshot_at(&conn, 1, horizon, None).unwrap(), before);
+        assert_eq!(
+            edge_pairs(&before),
+            vec![("bob", "carol"), ("carol", "dave")]
+        );
+        assert!(!before.nodes[0].active);
+
+        let changes = graph_changes(&conn, 1, None, "2100-01-01", Some("bob"), 100).unwrap();
+        assert_eq!(changes.edges.len(), 2);
+        assert_eq!(changes.edges[1].change, EdgeChange::Removed);
+        assert_eq!(compact_graph_history(&mut conn, 1, 0).unwrap(), 0);
+    }
+}
@@ -0,0 +1,629 @@
+//! In-memory adjacency index for the presence graph.
+//!
+//! [`find_path_bfs`](crate::find_path_bfs) issues two SQL queries per visited
+//! node. [`AdjacencyIndex`] loads a server's edges once and answers path and
+//! visibility queries from memory. Each server's adjacency is loaded on first
+//! use and then kept in sync by [`AdjacencyIndex::create_edge`] and
+//! [`AdjacencyIndex::delete_edge`]. Pruning only flips `graph_nodes.active`
+//! and never removes edges, so [`prune_inactive_nodes`](crate::prune_inactive_nodes)
+//! leaves the index untouched, just as it leaves BFS results untouched.
+//!
+//! Edges written inside a transaction should be reported with
+//! [`AdjacencyIndex::edge_created`] and [`AdjacencyIndex::edge_deleted`] once
+//! it commits. Any other edge write that bypasses the index must be followed
+//! by [`AdjacencyIndex::invalidate`].
+
+use crate::{
+    get_graph_node, get_visibility_preferences, list_blocks, resolve_profile,
+    visibility_for_degree, visibility_for_path, BfsPath, GraphEdge, GraphError, GraphProfile,
+    MAX_BFS_VISITED_NODES, VISIBILITY_MAX_DEPTH,
+};
+use annex_types::{EdgeKind, VisibilityLevel};
+use rusqlite::{params, Connection};
+use std::collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// One server's edges, stored undirected.
+#[derive(Debug, Default)]
+struct Adjacency {
+    /// Every `(from_node, to_node, kind)` edge, so repeated inserts and
+    /// deletes of the same edge are idempotent.
+    edges: HashSet<(String, String, String)>,
+    /// Neighbor -> number of edges (of any kind, in either direction) to it.
+    /// Ordered so traversal, and therefore the returned path, is stable.
+    neighbors: HashMap<String, BTreeMap<String, usize>>,
+}
+
+impl Adjacency {
+    fn load(conn: &Connection, server_id: i64) -> Result<Self, GraphError> {
+        let mut stmt =
+            conn.prepare("SELECT from_node, to_node, kind FROM graph_edges WHERE server_id = ?1")?;
+        let rows = stmt.query_map(params![server_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut adjacency = Adjacency::default();
+        for row in rows {
+            let (from, to, kind) = row?;
+            adjacency.insert(from, to, kind);
+        }
+        Ok(adjacency)
+    }
+
+    fn insert(&mut self, from: String, to: String, kind: String) {
+        if !self.edges.insert((from.clone(), to.clone(), kind)) {
+            return;
+        }
+        *self
+            .neighbors
+            .entry(from.clone())
+            .or_default()
+            .entry(to.clone())
+            .or_default() += 1;
+        *self
+            .neighbors
+            .entry(to)
+            .or_default()
+            .entry(from)
+            .or_default() += 1;
+    }
+
+    fn remove(&mut self, from: &str, to: &str, kind: &str) {
+        if !self
+            .edges
+            .remove(&(from.to_string(), to.to_string(), kind.to_string()))
+        {
+            return;
+        }
+        self.unlink(from, to);
+        self.unlink(to, from);
+    }
+
+    fn unlink(&mut self, node: &str, neighbor: &str) {
+        let Some(map) = self.neighbors.get_mut(node) else {
+            return;
+        };
+        if let Some(count) = map.get_mut(neighbor) {
+            *count -= 1;
+            if *count == 0 {
+                map.remove(neighbor);
+            }
+        }
+        if map.is_empty() {
+            self.neighbors.remove(node);
+        }
+    }
+
+    fn neighbors<'a>(&'a self, node: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.neighbors
+            .get(node)
+            .into_iter()
+            .flat_map(|map| map.keys())
+            .map(String::as_str)
+            .filter(move |n| *n != node)
+    }
+
+    /// Shortest undirected path from `from` to `to` of at most `max_depth`
+    /// edges, searching from both ends at once.
+    fn find_path(&self, from: &str, to: &str, max_depth: u32) -> BfsPath {
+        if from == to {
+            return BfsPath {
+                found: true,
+                path: vec![from.to_string()],
+                length: 0,
+            };
+        }
+
+        // node -> (parent, depth) for each side
+        let mut forward: HashMap<&str, (Option<&str>, usize)> = HashMap::from([(from, (None, 0))]);
+        let mut backward: HashMap<&str, (Option<&str>, usize)> = HashMap::from([(to, (None, 0))]);
+        let mut forward_frontier = vec![from];
+        let mut backward_frontier = vec![to];
+        let (mut forward_depth, mut backward_depth) = (0usize, 0usize);
+
+        while forward_depth + backward_depth < max_depth as usize
+            && !forward_frontier.is_empty()
+            && !backward_frontier.is_empty()
+            && forward.len() + backward.len() < MAX_BFS_VISITED_NODES
+        {
+            // Expand whichever side has the smaller frontier.
+            let expand_forward = forward_frontier.len() <= backward_frontier.len();
+            let (frontier, seen, other, depth) = if expand_forward {
+                (
+                    &mut forward_frontier,
+                    &mut forward,
+                    &backward,
+                    &mut forward_depth,
+                )
+            } else {
+                (
+                    &mut backward_frontier,
+                    &mut backward,
+                    &forward,
+                    &mut backward_depth,
+                )
+            };
+            *depth += 1;
+
+            let mut next = Vec::new();
+            let mut meeting: Option<(&str, usize)> = None;
+            for &node in frontier.iter() {
+                for neighbor in self.neighbors(node) {
+                    if seen.contains_key(neighbor) {
+                        continue;
+                    }
+                    seen.insert(neighbor, (Some(node), *depth));
+                    if let Some(&(_, other_depth)) = other.get(neighbor) {
+                        if meeting.is_none_or(|(_, best)| other_depth < best) {
+                            meeting = Some((neighbor, other_depth));
+                        }
+                    }
+                    next.push(neighbor);
+                }
+            }
+            *frontier = next;
+
+            if let Some((meet, _)) = meeting {
+                let mut path: Vec<String> = Vec::new();
+                let mut cursor = Some(meet);
+                while let Some(node) = cursor {
+                    path.push(node.to_string());
+                    cursor = forward[node].0;
+                }
+                path.reverse();
+                let mut cursor = backward[meet].0;
+                while let Some(node) = cursor {
+                    path.push(node.to_string());
+                    cursor = backward[node].0;
+                }
+                return BfsPath {
+                    found: true,
+                    length: path.len() - 1,
+                    path,
+                };
+            }
+        }
+
+        BfsPath {
+            found: false,
+            path: Vec::new(),
+            length: 0,
+        }
+    }
+
+    /// Degrees of separation from `origin` to every node within `max_depth`.
+    fn degrees_from(&self, origin: &str, max_depth: usize) -> HashMap<&str, usize> {
+        let mut degrees = HashMap::new();
+        let Some((origin, _)) = self.neighbors.get_key_value(origin) else {
+            return degrees;
+        };
+        degrees.insert(origin.as_str(), 0);
+
+        let mut queue = VecDeque::from([(origin.as_str(), 0usize)]);
+        while let Some((node, degree)) = queue.pop_front() {
+            if degree >= max_depth || degrees.len() >= MAX_BFS_VISITED_NODES {
+                continue;
+            }
+            for neighbor in self.neighbors(node) {
+                if !degrees.contains_key(neighbor) {
+                    degrees.insert(neighbor, degree + 1);
+                    queue.push_back((neighbor, degree + 1));
+                }
+            }
+        }
+        degrees
+    }
+}
+
+/// Cached, per-server adjacency lists for the presence graph.
+///
+/// Cheap to clone; clones share the same cache.
+#[derive(Debug, Clone, Default)]
+pub struct AdjacencyIndex {
+    servers: Arc<RwLock<HashMap<i64, Adjacency>>>,
+}
+
+impl AdjacencyIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The cache is rebuilt from the database on demand, so a poisoned lock
+    // is recovered rather than propagated.
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<i64, Adjacency>> {
+        self.servers.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<i64, Adjacency>> {
+        self.servers.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs `f` against the server's adjacency, loading it first if needed.
+    fn with_adjacency<T>(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        f: impl FnOnce(&Adjacency) -> T,
+    ) -> Result<T, GraphError> {
+        {
+            let servers = self.read();
+            if let Some(adjacency) = servers.get(&server_id) {
+                return Ok(f(adjacency));
+            }
+        }
+
+        // Load under the write lock so an edge written concurrently is either
+        // in the loaded snapshot or applied after it.
+        let mut servers = self.write();
+        if let Entry::Vacant(entry) = servers.entry(server_id) {
+            entry.insert(Adjacency::load(conn, server_id)?);
+        }
+        Ok(f(&servers[&server_id]))
+    }
+
+    /// Drops the cached adjacency for a server; the next query reloads it.
+    pub fn invalidate(&self, server_id: i64) {
+        self.write().remove(&server_id);
+    }
+
+    /// Creates an edge (see [`create_edge`](crate::create_edge)) and records
+    /// it in the index.
+    pub fn create_edge(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        from_node: &str,
+        to_node: &str,
+        kind: EdgeKind,
+        weight: f64,
+    ) -> Result<GraphEdge, GraphError> {
+        let edge = crate::create_edge(conn, server_id, from_node, to_node, kind, weight)?;
+        self.edge_created(server_id, from_node, to_node, kind);
+        Ok(edge)
+    }
+
+    /// Deletes an edge (see [`delete_edge`](crate::delete_edge)) and removes
+    /// it from the index.
+    pub fn delete_edge(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        from_node: &str,
+        to_node: &str,
+        kind: EdgeKind,
+    ) -> Result<usize, GraphError> {
+        let count = crate::delete_edge(conn, server_id, from_node, to_node, kind)?;
+        self.edge_deleted(server_id, from_node, to_node, kind);
+        Ok(count)
+    }
+
+    /// Records an edge that was written to `graph_edges` directly.
+    pub fn edge_created(&self, server_id: i64, from_node: &str, to_node: &str, kind: EdgeKind) {
+        if let Some(adjacency) = self.write().get_mut(&server_id) {
+            adjacency.insert(
+                from_node.to_string(),
+                to_node.to_string(),
+                crate::edge_kind_to_str(kind).to_string(),
+            );
+        }
+    }
+
+    /// Records an edge that was deleted from `graph_edges` directly.
+    pub fn edge_deleted(&self, server_id: i64, from_node: &str, to_node: &str, kind: EdgeKind) {
+        if let Some(adjacency) = self.write().get_mut(&server_id) {
+            adjacency.remove(from_node, to_node, crate::edge_kind_to_str(kind));
+        }
+    }
+
+    /// Finds a shortest path between two nodes, treating edges as undirected.
+    ///
+    /// Returns the same `found` and `length` as
+    /// [`find_path_bfs`](crate::find_path_bfs); when several shortest paths
+    /// exist, the one returned may differ.
+    pub fn find_path(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        from_node: &str,
+        to_node: &str,
+        max_depth: u32,
+    ) -> Result<BfsPath, GraphError> {
+        self.with_adjacency(conn, server_id, |adjacency| {
+            adjacency.find_path(from_node, to_node, max_depth)
+        })
+    }
+
+    /// Calculates the visibility level of `target` from `viewer`; see
+    /// [`get_node_visibility`](crate::get_node_visibility).
+    pub fn node_visibility(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        viewer: &str,
+        target: &str,
+    ) -> Result<VisibilityLevel, GraphError> {
+        if viewer == target {
+            return Ok(VisibilityLevel::Self_);
+        }
+        let path = self.find_path(conn, server_id, viewer, target, VISIBILITY_MAX_DEPTH)?;
+        Ok(visibility_for_path(&path))
+    }
+
+    /// Calculates the visibility level of each target from `viewer` with a
+    /// single traversal.
+    pub fn node_visibilities(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        viewer: &str,
+        targets: &[String],
+    ) -> Result<HashMap<String, VisibilityLevel>, GraphError> {
+        self.with_adjacency(conn, server_id, |adjacency| {
+            let degrees = adjacency.degrees_from(viewer, VISIBILITY_MAX_DEPTH as usize);
+            targets
+                .iter()
+                .map(|target| {
+                    let visibility = if target == viewer {
+                        VisibilityLevel::Self_
+                    } else {
+                        degrees
+                            .get(target.as_str())
+                            .map_or(VisibilityLevel::None, |&d| visibility_for_degree(d))
+                    };
+                    (target.clone(), visibility)
+                })
+                .collect()
+        })
+    }
+
+    /// Returns `center` and every node within `hops` edges of it, treating
+    /// edges as undirected.
+    pub fn neighborhood(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        center: &str,
+        hops: u32,
+    ) -> Result<HashSet<String>, GraphError> {
+        self.with_adjacency(conn, server_id, |adjacency| {
+            let mut nodes: HashSet<String> = adjacency
+                .degrees_from(center, hops as usize)
+                .into_keys()
+                .map(str::to_string)
+                .collect();
+            nodes.insert(center.to_string());
+            nodes
+        })
+    }
+
+    /// Retrieves the profile of `target` as visible to `viewer`; see
+    /// [`get_visible_profile`](crate::get_visible_profile).
+    pub fn visible_profile(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        viewer: &str,
+        target: &str,
+    ) -> Result<GraphProfile, GraphError> {
+        let node = get_graph_node(conn, server_id, target)?
+            .ok_or_else(|| GraphError::NodeNotFound(target.to_string()))?;
+        let visibility = self.node_visibility(conn, server_id, viewer, target)?;
+        resolve_profile(conn, server_id, viewer, node, visibility)
+    }
+
+    /// Returns every viewer to whom [`visible_profile`](Self::visible_profile)
+    /// shows `target` within three degrees, after `target`'s blocks and
+    /// visibility preferences, using a single traversal from `target`.
+    pub fn close_viewers(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        target: &str,
+    ) -> Result<HashSet<String>, GraphError> {
+        get_graph_node(conn, server_id, target)?
+            .ok_or_else(|| GraphError::NodeNotFound(target.to_string()))?;
+        let preferences = get_visibility_preferences(conn, server_id, target)?;
+        let blocked: HashSet<String> = list_blocks(conn, server_id, target)?.into_iter().collect();
+        let degrees: HashMap<String, usize> =
+            self.with_adjacency(conn, server_id, |adjacency| {
+                adjacency
+                    .degrees_from(target, VISIBILITY_MAX_DEPTH as usize)
+                    .into_iter()
+                    .map(|(node, degree)| (node.to_string(), degree))
+                    .collect()
+            })?;
+
+        // Allow-listed viewers see the target however far away they are.
+        let candidates = degrees
+            .keys()
+            .chain(&preferences.allow)
+            .map(String::as_str)
+            .chain([target]);
+        Ok(candidates
+            .filter(|viewer| {
+                let computed = if *viewer == target {
+                    VisibilityLevel::Self_
+                } else if blocked.contains(*viewer) {
+                    return false;
+                } else {
+                    degrees
+                        .get(*viewer)
+                        .map_or(VisibilityLevel::None, |&d| visibility_for_degree(d))
+                };
+                matches!(
+                    preferences.effective_visibility(viewer, computed),
+                    VisibilityLevel::Self_
+                        | VisibilityLevel::Degree1
+                        | VisibilityLevel::Degree2
+                        | VisibilityLevel::Degree3
+                )
+            })
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ensure_graph_node, find_path_bfs, get_node_visibility};
+    use annex_db::run_migrations;
+    use annex_types::NodeType;
+
+    fn setup(nodes: &[&str]) -> Connection {
+        let conn = Connection::open_in_memory().expect("db open failed");
+        run_migrations(&conn).expect("migrations failed");
+        for n in nodes {
+            ensure_graph_node(&conn, 1, n, NodeType::Human, None).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn visibility_matches_sql_bfs() {
+        let nodes = ["A", "B", "C", "D", "E", "F", "G", "H"];
+        let conn = setup(&nodes);
+        // A - B - C - D - E, B - F - D, G - H (separate component), plus a
+        // second edge kind between A and B.
+        for (from, to) in [
+            ("A", "B"),
+            ("C", "B"),
+            ("C", "D"),
+            ("D", "E"),
+            ("B", "F"),
+            ("F", "D"),
+            ("H", "G"),
+        ] {
+            crate::create_edge(&conn, 1, from, to, EdgeKind::Connected, 1.0).unwrap();
+        }
+        crate::create_edge(&conn, 1, "A", "B", EdgeKind::MemberOf, 1.0).unwrap();
+
+        let index = AdjacencyIndex::new();
+        let targets: Vec<String> = nodes.iter().map(|n| n.to_string()).collect();
+        for viewer in nodes {
+            let batch = index.node_visibilities(&conn, 1, viewer, &targets).unwrap();
+            for target in nodes {
+                let expected = get_node_visibility(&conn, 1, viewer, target).unwrap();
+                assert_eq!(
+                    index.node_visibility(&conn, 1, viewer, target).unwrap(),
+                    expected,
+                    "{viewer} -> {target}"
+                );
+                assert_eq!(batch[target], expected, "batch {viewer} -> {target}");
+
+                for depth in 0..6 {
+                    let sql = find_path_bfs(&conn, 1, viewer, target, depth).unwrap();
+                    let cached = index.find_path(&conn, 1, viewer, target, depth).unwrap();
+                    assert_eq!(cached.found, sql.found, "{viewer} -> {target} @ {depth}");
+                    assert_eq!(cached.length, sql.length, "{viewer} -> {target} @ {depth}");
+                    if cached.found {
+                        assert_eq!(cached.path.first().map(String::as_str), Some(viewer));
+                        assert_eq!(cached.path.last().map(String::as_str), Some(target));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn close_viewers_match_visible_profiles() {
+        let nodes = ["A", "B", "C", "D", "E", "F"];
+        let mut conn = setup(&nodes);
+        // A - B - C - D - E, F isolated.
+        for (from, to) in [("A", "B"), ("B", "C"), ("C", "D"), ("D", "E")] {
+            crate::create_edge(&conn, 1, from, to, EdgeKind::Connected, 1.0).unwrap();
+        }
+        let index = AdjacencyIndex::new();
+        let check = |conn: &Connection| {
+            for target in nodes {
+                let viewers = index.close_viewers(conn, 1, target).unwrap();
+                for viewer in nodes {
+                    let profile = index.visible_profile(conn, 1, viewer, target).unwrap();
+                    let close = matches!(
+                        profile.visibility,
+                        VisibilityLevel::Self_
+                            | VisibilityLevel::Degree1
+                            | VisibilityLevel::Degree2
+                            | VisibilityLevel::Degree3
+                    );
+                    assert_eq!(viewers.contains(viewer), close, "{viewer} -> {target}");
+                }
+            }
+        };
+        check(&conn);
+
+        // A blocks B, allows F and denies C.
+        crate::block_participant(&mut conn, &index, 1, "A", "B").unwrap();
+        crate::set_visibility_preferences(
+            &conn,
+            1,
+            "A",
+            &crate::VisibilityPreferences {
+                allow: vec!["F".to_string()],
+                deny: vec!["C".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        check(&conn);
+        assert_eq!(
+            index.close_viewers(&conn, 1, "A").unwrap(),
+            HashSet::from(["A".to_string(), "F".to_string()])
+        );
+    }
+
+    #[test]
+    fn edge_writes_keep_index_in_sync() {
+        let conn = setup(&["A", "B", "C"]);
+        let index = AdjacencyIndex::new();
+
+        // Load the (empty) adjacency before any edges exist.
+        assert_eq!(
+            index.node_visibility(&conn, 1, "A", "C").unwrap(),
+            VisibilityLevel::None
+        );
+
+        index
+            .create_edge(&conn, 1, "A", "B", EdgeKind::Connected, 1.0)
+            .unwrap();
+        index
+            .create_edge(&conn, 1, "B", "C", EdgeKind::Connected, 1.0)
+            .unwrap();
+        index
+            .create_edge(&conn, 1, "B", "C", EdgeKind::MemberOf, 1.0)
+            .unwrap();
+        assert_eq!(
+            index.node_visibility(&conn, 1, "A", "C").unwrap(),
+            VisibilityLevel::Degree2
+        );
+
+        // One of two B-C edges removed: still reachable.
+        index
+            .delete_edge(&conn, 1, "B", "C", EdgeKind::Connected)
+            .unwrap();
+        assert_eq!(
+            index.node_visibility(&conn, 1, "A", "C").unwrap(),
+            VisibilityLevel::Degree2
+        );
+
+        index
+            .delete_edge(&conn, 1, "B", "C", EdgeKind::MemberOf)
+            .unwrap();
+        assert_eq!(
+            index.node_visibility(&conn, 1, "A", "C").unwrap(),
+            VisibilityLevel::None
+        );
+
+        // Writes that bypass the index are picked up after invalidation.
+        crate::create_edge(&conn, 1, "C", "A", EdgeKind::Connected, 1.0).unwrap();
+        index.invalidate(1);
+        assert_eq!(
+            index.node_visibility(&conn, 1, "A", "C").unwrap(),
+            VisibilityLevel::Degree1
+        );
+    }
+}
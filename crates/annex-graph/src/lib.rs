@@ -12,6 +12,43 @@
 //!
 //! The full implementation of this crate is Phase 5 of the roadmap.
 
+pub mod analytics;
+pub mod blocks;
+pub mod connections;
+pub mod export;
+pub mod history;
+pub mod index;
+pub mod status;
+pub mod visibility;
+
+pub use analytics::{
+    compute_graph_statistics, current_analytics_release, privatize, ChannelComposition,
+    ChannelRatio, GraphAnalyticsRelease, GraphStatistics, HistogramBucket,
+};
+pub use blocks::{
+    block_participant, blockers_of, is_blocked, is_blocked_either, list_blocks, list_mutes,
+    mute_participant, unblock_participant, unmute_participant,
+};
+pub use connections::{
+    accept_connection_request, are_connected, create_connection_request,
+    decline_connection_request, list_connection_requests, remove_connection, ConnectionRequest,
+    ConnectionRequestStatus, MAX_PENDING_CONNECTION_REQUESTS,
+};
+pub use export::{export_graph, parse_edge_kind, parse_node_type, ExportFormat, ExportOptions};
+pub use history::{
+    compact_graph_history, graph_changes, graph_snapshot_at, history_horizon,
+    resolve_history_timestamp, EdgeChange, EdgeHistoryEntry, GraphChanges, GraphSnapshot,
+    NodeHistoryEntry, SnapshotEdge, SnapshotNode,
+};
+pub use index::AdjacencyIndex;
+pub use status::{
+    get_participant_status, set_participant_status, ParticipantStatus, MAX_STATUS_TEXT_LEN,
+};
+pub use visibility::{
+    get_visibility_preferences, set_visibility_preferences, VisibilityPreferenceCache,
+    VisibilityPreferences, MAX_VISIBILITY_LIST_LEN,
+};
+
 use annex_types::{EdgeKind, NodeType, RoleCode, VisibilityLevel};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
@@ -22,6 +59,9 @@ use thiserror::Error;
 /// Prevents unbounded memory growth on densely connected graphs.
 const MAX_BFS_VISITED_NODES: usize = 10_000;
 
+/// Degrees of separation beyond which a node is not visible at all.
+const VISIBILITY_MAX_DEPTH: u32 = 3;
+
 /// Errors specific to the graph module.
 #[derive(Debug, Error)]
 pub enum GraphError {
@@ -33,6 +73,30 @@ pub enum GraphError {
     NodeAlreadyExists(String),
     #[error("node not found: {0}")]
     NodeNotFound(String),
+    #[error("invalid connection: {0}")]
+    InvalidConnection(String),
+    #[error("connection conflict: {0}")]
+    ConnectionConflict(String),
+    #[error("connection request not found: {0}")]
+    ConnectionRequestNotFound(i64),
+    #[error("not connected to {0}")]
+    NotConnected(String),
+    #[error("too many pending connection requests")]
+    TooManyPendingRequests,
+    #[error("invalid visibility preferences: {0}")]
+    InvalidVisibilityPreferences(String),
+    #[error("invalid block or mute: {0}")]
+    InvalidRelationship(String),
+    #[error("blocked: {0}")]
+    Blocked(String),
+    #[error("invalid analytics configuration: {0}")]
+    InvalidAnalyticsConfig(String),
+    #[error("invalid export: {0}")]
+    InvalidExport(String),
+    #[error("invalid status: {0}")]
+    InvalidStatus(String),
+    #[error("invalid history query: {0}")]
+    InvalidHistoryQuery(String),
 }
 
 /// A filtered view of a graph node, respecting visibility rules.
@@ -42,10 +106,10 @@ pub struct GraphProfile {
     pub node_type: NodeType,
     pub active: bool,
     pub created_at: String,
-    /// Only visible if visibility >= Degree1 (or Self).
+    /// Only visible to Self and, unless the target hides it, Degree1.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_seen_at: Option<String>,
-    /// Only visible if visibility == Self or specific permission (not implemented yet, so Self only).
+    /// Only visible to Self and to the degrees the target exposes it to.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata_json: Option<String>,
     pub visibility: VisibilityLevel,
@@ -106,11 +170,26 @@ pub struct BfsPath {
 ///
 /// Returns `true` if the node was previously inactive, allowing the caller to emit a reactivation event.
 /// Returns `false` if the node was already active or does not exist.
+///
+/// Participants whose stored status is `INVISIBLE` are left untouched, so
+/// their activity neither moves `last_seen_at` nor reactivates their node.
 pub fn update_node_activity(
     conn: &Connection,
     server_id: i64,
     pseudonym_id: &str,
 ) -> Result<bool, GraphError> {
+    let invisible: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM presence_statuses
+             WHERE server_id = ?1 AND pseudonym_id = ?2 AND status = 'INVISIBLE')",
+            params![server_id, pseudonym_id],
+            |row| row.get(0),
+        )
+        .map_err(GraphError::DatabaseError)?;
+    if invisible {
+        return Ok(false);
+    }
+
     // 1. Try updating an already-active node (most common case).
     let count = conn
         .execute(
@@ -213,6 +292,16 @@ fn str_to_edge_kind(s: &str) -> Result<EdgeKind, GraphError> {
     }
 }
 
+fn node_type_to_str(node_type: NodeType) -> &'static str {
+    match node_type {
+        NodeType::Human => "HUMAN",
+        NodeType::AiAgent => "AI_AGENT",
+        NodeType::Collective => "COLLECTIVE",
+        NodeType::Bridge => "BRIDGE",
+        NodeType::Service => "SERVICE",
+    }
+}
+
 fn str_to_node_type(s: &str) -> Result<NodeType, GraphError> {
     match s {
         "HUMAN" => Ok(NodeType::Human),
@@ -243,14 +332,7 @@ pub fn ensure_graph_node(
     node_type: NodeType,
     metadata_json: Option<String>,
 ) -> Result<GraphNode, GraphError> {
-    // Serialize node type
-    let node_type_str = match node_type {
-        NodeType::Human => "HUMAN",
-        NodeType::AiAgent => "AI_AGENT",
-        NodeType::Collective => "COLLECTIVE",
-        NodeType::Bridge => "BRIDGE",
-        NodeType::Service => "SERVICE",
-    };
+    let node_type_str = node_type_to_str(node_type);
 
     // Upsert logic using ON CONFLICT DO UPDATE
     let raw = conn.query_row(
@@ -441,19 +523,25 @@ pub fn get_node_visibility(
         return Ok(VisibilityLevel::Self_);
     }
 
-    // Max depth 3 for visibility calculation
-    let path = find_path_bfs(conn, server_id, viewer, target, 3)?;
+    let path = find_path_bfs(conn, server_id, viewer, target, VISIBILITY_MAX_DEPTH)?;
+    Ok(visibility_for_path(&path))
+}
 
+/// Maps a viewer-to-target path onto a visibility level.
+fn visibility_for_path(path: &BfsPath) -> VisibilityLevel {
     if !path.found {
-        return Ok(VisibilityLevel::None);
+        return VisibilityLevel::None;
     }
+    visibility_for_degree(path.length)
+}
 
-    match path.length {
-        0 => Ok(VisibilityLevel::Self_), // Should be covered by viewer == target, but just in case
-        1 => Ok(VisibilityLevel::Degree1),
-        2 => Ok(VisibilityLevel::Degree2),
-        3 => Ok(VisibilityLevel::Degree3),
-        _ => Ok(VisibilityLevel::None), // Should not happen given max_depth=3
+fn visibility_for_degree(degree: usize) -> VisibilityLevel {
+    match degree {
+        0 => VisibilityLevel::Self_,
+        1 => VisibilityLevel::Degree1,
+        2 => VisibilityLevel::Degree2,
+        3 => VisibilityLevel::Degree3,
+        _ => VisibilityLevel::None, // Should not happen given max_depth=3
     }
 }
 
@@ -468,17 +556,45 @@ pub fn get_visible_profile(
         .ok_or_else(|| GraphError::NodeNotFound(target.to_string()))?;
 
     let visibility = get_node_visibility(conn, server_id, viewer, target)?;
+    resolve_profile(conn, server_id, viewer, node, visibility)
+}
 
-    let (last_seen_at, metadata_json) = match visibility {
-        VisibilityLevel::Self_ => (node.last_seen_at, node.metadata_json),
-        VisibilityLevel::Degree1 => (node.last_seen_at, None), // Hide metadata for degree 1
-        VisibilityLevel::Degree2 => (None, None),              // Hide last_seen and metadata
-        VisibilityLevel::Degree3 => (None, None),              // Hide last_seen and metadata
-        VisibilityLevel::AggregateOnly => (None, None),
-        VisibilityLevel::None => (None, None),
+/// Applies the target's blocks and visibility preferences to the
+/// degree-based `computed` level and strips the fields of `node` the result
+/// does not permit.
+fn resolve_profile(
+    conn: &Connection,
+    server_id: i64,
+    viewer: &str,
+    node: GraphNode,
+    computed: VisibilityLevel,
+) -> Result<GraphProfile, GraphError> {
+    let preferences = get_visibility_preferences(conn, server_id, &node.pseudonym_id)?;
+    let visibility = if computed != VisibilityLevel::Self_
+        && is_blocked(conn, server_id, &node.pseudonym_id, viewer)?
+    {
+        VisibilityLevel::None
+    } else {
+        preferences.effective_visibility(viewer, computed)
     };
+    Ok(filter_profile(node, visibility, &preferences))
+}
 
-    Ok(GraphProfile {
+/// Strips the fields of `node` that `visibility` does not permit under the
+/// target's `preferences`.
+fn filter_profile(
+    node: GraphNode,
+    visibility: VisibilityLevel,
+    preferences: &VisibilityPreferences,
+) -> GraphProfile {
+    let last_seen_at = node
+        .last_seen_at
+        .filter(|_| preferences.reveals_last_seen(visibility));
+    let metadata_json = node
+        .metadata_json
+        .filter(|_| preferences.reveals_metadata(visibility));
+
+    GraphProfile {
         pseudonym_id: node.pseudonym_id,
         node_type: node.node_type,
         active: node.active,
@@ -486,7 +602,7 @@ pub fn get_visible_profile(
         last_seen_at,
         metadata_json,
         visibility,
-    })
+    }
 }
 
 /// Finds the shortest path between two nodes using BFS.
@@ -618,6 +734,49 @@ mod tests {
         assert!(edges_after.is_empty());
     }
 
+    #[test]
+    fn test_invisible_activity_is_not_recorded() {
+        let conn = Connection::open_in_memory().expect("db open failed");
+        run_migrations(&conn).expect("migrations failed");
+        let server_id = 1;
+        ensure_graph_node(&conn, server_id, "ghost", NodeType::Human, None).unwrap();
+        conn.execute(
+            "UPDATE graph_nodes SET active = 0, last_seen_at = '2020-01-01 00:00:00'
+             WHERE pseudonym_id = 'ghost'",
+            [],
+        )
+        .unwrap();
+        status::set_participant_status(
+            &conn,
+            server_id,
+            "ghost",
+            annex_types::PresenceStatus::Invisible,
+            None,
+        )
+        .unwrap();
+
+        assert!(!update_node_activity(&conn, server_id, "ghost").unwrap());
+        let (active, last_seen): (bool, String) = conn
+            .query_row(
+                "SELECT active, last_seen_at FROM graph_nodes WHERE pseudonym_id = 'ghost'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(!active);
+        assert_eq!(last_seen, "2020-01-01 00:00:00");
+
+        status::set_participant_status(
+            &conn,
+            server_id,
+            "ghost",
+            annex_types::PresenceStatus::Online,
+            None,
+        )
+        .unwrap();
+        assert!(update_node_activity(&conn, server_id, "ghost").unwrap());
+    }
+
     #[test]
     fn test_bfs() {
         let conn = Connection::open_in_memory().expect("db open failed");
@@ -0,0 +1,246 @@
+//! Self-declared presence statuses.
+//!
+//! Participants pick a [`PresenceStatus`] and optional status text, stored
+//! in `presence_statuses`. Whether they are connected and idle comes from
+//! their live WebSocket sessions, so [`ParticipantStatus::event`] combines
+//! the two into the [`PresenceEvent::StatusChanged`] other participants see.
+
+use crate::GraphError;
+use annex_types::{PresenceEvent, PresenceStatus};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// Maximum length of status text, in characters.
+pub const MAX_STATUS_TEXT_LEN: usize = 128;
+
+/// A participant's stored status.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParticipantStatus {
+    pub status: PresenceStatus,
+    pub status_text: Option<String>,
+    /// When the status was last set (ISO 8601); `None` if it never was.
+    pub updated_at: Option<String>,
+}
+
+impl ParticipantStatus {
+    /// The event announcing this status to other participants.
+    ///
+    /// `presence` is `None` when the participant has no live session, and
+    /// otherwise whether every session is idle. Invisible and disconnected
+    /// participants are reported as `Offline` with no status text.
+    pub fn event(&self, pseudonym_id: &str, presence: Option<bool>) -> PresenceEvent {
+        match presence {
+            Some(idle) if self.status != PresenceStatus::Invisible => {
+                PresenceEvent::StatusChanged {
+                    pseudonym_id: pseudonym_id.to_string(),
+                    status: self.status,
+                    status_text: self.status_text.clone(),
+                    idle,
+                }
+            }
+            _ => PresenceEvent::StatusChanged {
+                pseudonym_id: pseudonym_id.to_string(),
+                status: PresenceStatus::Offline,
+                status_text: None,
+                idle: false,
+            },
+        }
+    }
+}
+
+fn status_to_str(status: PresenceStatus) -> &'static str {
+    match status {
+        PresenceStatus::Online => "ONLINE",
+        PresenceStatus::Away => "AWAY",
+        PresenceStatus::DoNotDisturb => "DO_NOT_DISTURB",
+        PresenceStatus::Invisible => "INVISIBLE",
+        PresenceStatus::Offline => "OFFLINE",
+    }
+}
+
+fn str_to_status(s: &str) -> Result<PresenceStatus, GraphError> {
+    match s {
+        "ONLINE" => Ok(PresenceStatus::Online),
+        "AWAY" => Ok(PresenceStatus::Away),
+        "DO_NOT_DISTURB" => Ok(PresenceStatus::DoNotDisturb),
+        "INVISIBLE" => Ok(PresenceStatus::Invisible),
+        other => Err(GraphError::DatabaseError(
+            rusqlite::Error::FromSqlConversionFailure(
+                0,
+                rusqlite::types::Type::Text,
+                format!("unknown presence status: {}", other).into(),
+            ),
+        )),
+    }
+}
+
+/// Loads a participant's status, defaulting to `Online` with no text.
+pub fn get_participant_status(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+) -> Result<ParticipantStatus, GraphError> {
+    let row = conn
+        .query_row(
+            "SELECT status, status_text, updated_at FROM presence_statuses
+             WHERE server_id = ?1 AND pseudonym_id = ?2",
+            params![server_id, pseudonym_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            },
+        )
+        .optional()?;
+
+    match row {
+        Some((status, status_text, updated_at)) => Ok(ParticipantStatus {
+            status: str_to_status(&status)?,
+            status_text,
+            updated_at: Some(updated_at),
+        }),
+        None => Ok(ParticipantStatus::default()),
+    }
+}
+
+/// Stores a participant's status and status text.
+///
+/// Blank text clears it. `Offline` is reserved for the server.
+pub fn set_participant_status(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+    status: PresenceStatus,
+    status_text: Option<&str>,
+) -> Result<ParticipantStatus, GraphError> {
+    if status == PresenceStatus::Offline {
+        return Err(GraphError::InvalidStatus(
+            "offline cannot be set; use invisible".to_string(),
+        ));
+    }
+    let status_text = status_text.map(str::trim).filter(|t| !t.is_empty());
+    if let Some(text) = status_text {
+        if text.chars().count() > MAX_STATUS_TEXT_LEN {
+            return Err(GraphError::InvalidStatus(format!(
+                "status text exceeds {} characters",
+                MAX_STATUS_TEXT_LEN
+            )));
+        }
+        if text.chars().any(char::is_control) {
+            return Err(GraphError::InvalidStatus(
+                "status text may not contain control characters".to_string(),
+            ));
+        }
+    }
+
+    let updated_at: String = conn.query_row(
+        "INSERT INTO presence_statuses (server_id, pseudonym_id, status, status_text)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(server_id, pseudonym_id) DO UPDATE SET
+            status = excluded.status,
+            status_text = excluded.status_text,
+            updated_at = datetime('now')
+         RETURNING updated_at",
+        params![server_id, pseudonym_id, status_to_str(status), status_text],
+        |row| row.get(0),
+    )?;
+
+    Ok(ParticipantStatus {
+        status,
+        status_text: status_text.map(str::to_string),
+        updated_at: Some(updated_at),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use annex_db::run_migrations;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().expect("db open failed");
+        run_migrations(&conn).expect("migrations failed");
+        conn
+    }
+
+    #[test]
+    fn status_round_trip() {
+        let conn = setup();
+        assert_eq!(
+            get_participant_status(&conn, 1, "alice").unwrap(),
+            ParticipantStatus::default()
+        );
+
+        set_participant_status(
+            &conn,
+            1,
+            "alice",
+            PresenceStatus::DoNotDisturb,
+            Some("  focusing  "),
+        )
+        .unwrap();
+        let status = get_participant_status(&conn, 1, "alice").unwrap();
+        assert_eq!(status.status, PresenceStatus::DoNotDisturb);
+        assert_eq!(status.status_text.as_deref(), Some("focusing"));
+        assert!(status.updated_at.is_some());
+
+        set_participant_status(&conn, 1, "alice", PresenceStatus::Away, Some(" ")).unwrap();
+        let status = get_participant_status(&conn, 1, "alice").unwrap();
+        assert_eq!(status.status, PresenceStatus::Away);
+        assert_eq!(status.status_text, None);
+    }
+
+    #[test]
+    fn rejects_invalid_statuses() {
+        let conn = setup();
+        let too_long = "x".repeat(MAX_STATUS_TEXT_LEN + 1);
+        for (status, text) in [
+            (PresenceStatus::Offline, None),
+            (PresenceStatus::Online, Some(too_long.as_str())),
+            (PresenceStatus::Online, Some("line\nbreak")),
+        ] {
+            assert!(matches!(
+                set_participant_status(&conn, 1, "alice", status, text),
+                Err(GraphError::InvalidStatus(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn invisible_and_disconnected_appear_offline() {
+        let status = ParticipantStatus {
+            status: PresenceStatus::Away,
+            status_text: Some("lunch".to_string()),
+            updated_at: None,
+        };
+        let offline = PresenceEvent::StatusChanged {
+            pseudonym_id: "alice".to_string(),
+            status: PresenceStatus::Offline,
+            status_text: None,
+            idle: false,
+        };
+        assert!(matches!(
+            status.event("alice", Some(true)),
+            PresenceEvent::StatusChanged {
+                status: PresenceStatus::Away,
+                idle: true,
+                ..
+            }
+        ));
+        assert_eq!(
+            serde_json::to_value(status.event("alice", None)).unwrap(),
+            serde_json::to_value(&offline).unwrap()
+        );
+
+        let invisible = ParticipantStatus {
+            status: PresenceStatus::Invisible,
+            ..status
+        };
+        assert_eq!(
+            serde_json::to_value(invisible.event("alice", Some(false))).unwrap(),
+            serde_json::to_value(&offline).unwrap()
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! Per-participant visibility preferences.
+//!
+//! By default what a viewer sees of a node depends only on their degree of
+//! separation (see [`get_visible_profile`](crate::get_visible_profile)).
+//! [`VisibilityPreferences`] let each participant tighten or loosen those
+//! defaults: hide `last_seen_at` even from direct connections, expose
+//! metadata further out, go fully private, or pin individual pseudonyms with
+//! allow and deny lists.
+//!
+//! The anonymous presence stream has no viewer to rank, so it consults
+//! [`VisibilityPreferences::hides_presence`] through a
+//! [`VisibilityPreferenceCache`] instead.
+
+use crate::GraphError;
+use annex_types::VisibilityLevel;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Maximum number of pseudonyms on each of the allow and deny lists.
+pub const MAX_VISIBILITY_LIST_LEN: usize = 500;
+
+/// Highest degree `metadata_max_degree` may name.
+const MAX_METADATA_DEGREE: u8 = 3;
+
+/// A participant's overrides of the degree-based visibility defaults.
+///
+/// The default value reproduces the defaults exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VisibilityPreferences {
+    /// Show everyone except allow-listed viewers no more than
+    /// `AggregateOnly`.
+    pub private: bool,
+    /// Hide `last_seen_at` from `Degree1` viewers too.
+    pub hide_last_seen: bool,
+    /// Furthest degree that may see metadata; `0` means only the participant.
+    pub metadata_max_degree: u8,
+    /// Pseudonyms always treated as direct connections.
+    pub allow: Vec<String>,
+    /// Pseudonyms that never see anything.
+    pub deny: Vec<String>,
+}
+
+impl VisibilityPreferences {
+    /// Checks the preferences `owner` wants to store.
+    pub fn validate(&self, owner: &str) -> Result<(), GraphError> {
+        if self.metadata_max_degree > MAX_METADATA_DEGREE {
+            return Err(GraphError::InvalidVisibilityPreferences(format!(
+                "metadata_max_degree must be at most {}",
+                MAX_METADATA_DEGREE
+            )));
+        }
+        for (name, list) in [("allow", &self.allow), ("deny", &self.deny)] {
+            if list.len() > MAX_VISIBILITY_LIST_LEN {
+                return Err(GraphError::InvalidVisibilityPreferences(format!(
+                    "{} list exceeds {} entries",
+                    name, MAX_VISIBILITY_LIST_LEN
+                )));
+            }
+            if list.iter().any(|p| p.trim().is_empty() || p == owner) {
+                return Err(GraphError::InvalidVisibilityPreferences(format!(
+                    "{} list may not contain empty pseudonyms or yourself",
+                    name
+                )));
+            }
+        }
+        if let Some(both) = self.allow.iter().find(|p| self.deny.contains(p)) {
+            return Err(GraphError::InvalidVisibilityPreferences(format!(
+                "{} is on both the allow and deny lists",
+                both
+            )));
+        }
+        Ok(())
+    }
+
+    /// Adjusts the degree-based `computed` level for `viewer`.
+    pub fn effective_visibility(&self, viewer: &str, computed: VisibilityLevel) -> VisibilityLevel {
+        if computed == VisibilityLevel::Self_ {
+            return computed;
+        }
+        if self.deny.iter().any(|p| p == viewer) {
+            return VisibilityLevel::None;
+        }
+        if self.allow.iter().any(|p| p == viewer) {
+            return VisibilityLevel::Degree1;
+        }
+        if self.private && computed != VisibilityLevel::None {
+            return VisibilityLevel::AggregateOnly;
+        }
+        computed
+    }
+
+    /// Whether a viewer at `level` may see `last_seen_at`.
+    pub fn reveals_last_seen(&self, level: VisibilityLevel) -> bool {
+        match level {
+            VisibilityLevel::Self_ => true,
+            VisibilityLevel::Degree1 => !self.hide_last_seen,
+            _ => false,
+        }
+    }
+
+    /// Whether a viewer at `level` may see metadata.
+    pub fn reveals_metadata(&self, level: VisibilityLevel) -> bool {
+        let degree = match level {
+            VisibilityLevel::Self_ => return true,
+            VisibilityLevel::Degree1 => 1,
+            VisibilityLevel::Degree2 => 2,
+            VisibilityLevel::Degree3 => 3,
+            VisibilityLevel::AggregateOnly | VisibilityLevel::None => return false,
+        };
+        degree <= self.metadata_max_degree
+    }
+
+    /// Whether activity events about this participant must be withheld from
+    /// viewers who cannot be ranked, such as the anonymous presence stream.
+    pub fn hides_presence(&self) -> bool {
+        self.private || self.hide_last_seen
+    }
+}
+
+/// Loads a participant's preferences, falling back to the defaults.
+pub fn get_visibility_preferences(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+) -> Result<VisibilityPreferences, GraphError> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT preferences_json FROM graph_visibility_preferences
+             WHERE server_id = ?1 AND pseudonym_id = ?2",
+            params![server_id, pseudonym_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match json {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(VisibilityPreferences::default()),
+    }
+}
+
+/// Validates and stores a participant's preferences, replacing any previous
+/// ones.
+pub fn set_visibility_preferences(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+    preferences: &VisibilityPreferences,
+) -> Result<(), GraphError> {
+    preferences.validate(pseudonym_id)?;
+    conn.execute(
+        "INSERT INTO graph_visibility_preferences (server_id, pseudonym_id, preferences_json)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(server_id, pseudonym_id) DO UPDATE SET
+             preferences_json = excluded.preferences_json,
+             updated_at = datetime('now')",
+        params![server_id, pseudonym_id, serde_json::to_string(preferences)?],
+    )?;
+    Ok(())
+}
+
+/// Cached preferences keyed by `(server_id, pseudonym_id)`.
+type PreferenceMap = HashMap<(i64, String), Arc<VisibilityPreferences>>;
+
+/// Shared cache of stored preferences, for hot paths that would otherwise
+/// query them once per event.
+///
+/// Preferences must be written through [`VisibilityPreferenceCache::set`] for
+/// the cache to stay current.
+#[derive(Debug, Clone, Default)]
+pub struct VisibilityPreferenceCache {
+    entries: Arc<RwLock<PreferenceMap>>,
+}
+
+impl VisibilityPreferenceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a participant's preferences if they are already cached.
+    pub fn cached(&self, server_id: i64, pseudonym_id: &str) -> Option<Arc<VisibilityPreferences>> {
+        self.entries
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(server_id, pseudonym_id.to_string()))
+            .cloned()
+    }
+
+    /// Returns a participant's preferences, loading them on first use.
+    pub fn get(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        pseudonym_id: &str,
+    ) -> Result<Arc<VisibilityPreferences>, GraphError> {
+        let key = (server_id, pseudonym_id.to_string());
+        if let Some(cached) = self
+            .entries
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+        {
+            return Ok(cached.clone());
+        }
+
+        let loaded = Arc::new(get_visibility_preferences(conn, server_id, pseudonym_id)?);
+        self.entries
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, loaded.clone());
+        Ok(loaded)
+    }
+
+    /// Stores a participant's preferences; see [`set_visibility_preferences`].
+    pub fn set(
+        &self,
+        conn: &Connection,
+        server_id: i64,
+        pseudonym_id: &str,
+        preferences: VisibilityPreferences,
+    ) -> Result<(), GraphError> {
+        set_visibility_preferences(conn, server_id, pseudonym_id, &preferences)?;
+        self.entries
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert((server_id, pseudonym_id.to_string()), Arc::new(preferences));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ensure_graph_node, get_visible_profile, AdjacencyIndex};
+    use annex_db::run_migrations;
+    use annex_types::{EdgeKind, NodeType};
+
+    /// alice - bob - carol, with metadata on carol.
+    fn setup() -> (Connection, AdjacencyIndex) {
+        let conn = Connection::open_in_memory().expect("db open failed");
+        run_migrations(&conn).expect("migrations failed");
+        for n in ["alice", "bob"] {
+            ensure_graph_node(&conn, 1, n, NodeType::Human, None).unwrap();
+        }
+        ensure_graph_node(
+            &conn,
+            1,
+            "carol",
+            NodeType::Human,
+            Some("{\"bio\":\"hi\"}".to_string()),
+        )
+        .unwrap();
+        let index = AdjacencyIndex::new();
+        index
+            .create_edge(&conn, 1, "alice", "bob", EdgeKind::Connected, 1.0)
+            .unwrap();
+        index
+            .create_edge(&conn, 1, "bob", "carol", EdgeKind::Connected, 1.0)
+            .unwrap();
+        (conn, index)
+    }
+
+    #[test]
+    fn defaults_match_degree_rules() {
+        let (conn, index) = setup();
+        assert_eq!(
+            get_visibility_preferences(&conn, 1, "carol").unwrap(),
+            VisibilityPreferences::default()
+        );
+
+        let profile = index.visible_profile(&conn, 1, "bob", "carol").unwrap();
+        assert_eq!(profile.visibility, VisibilityLevel::Degree1);
+        assert!(profile.last_seen_at.is_some());
+        assert!(profile.metadata_json.is_none());
+
+        let profile = index.visible_profile(&conn, 1, "alice", "carol").unwrap();
+        assert_eq!(profile.visibility, VisibilityLevel::Degree2);
+        assert!(profile.last_seen_at.is_none());
+    }
+
+    #[test]
+    fn preferences_override_degree_rules() {
+        let (conn, index) = setup();
+        let cache = VisibilityPreferenceCache::new();
+        assert!(!cache.get(&conn, 1, "carol").unwrap().hides_presence());
+
+        cache
+            .set(
+                &conn,
+                1,
+                "carol",
+                VisibilityPreferences {
+                    hide_last_seen: true,
+                    metadata_max_degree: 2,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(cache.get(&conn, 1, "carol").unwrap().hides_presence());
+
+        let profile = index.visible_profile(&conn, 1, "bob", "carol").unwrap();
+        assert!(profile.last_seen_at.is_none());
+        assert!(profile.metadata_json.is_some());
+        let profile = get_visible_profile(&conn, 1, "alice", "carol").unwrap();
+        assert!(profile.metadata_json.is_some());
+        let profile = get_visible_profile(&conn, 1, "carol", "carol").unwrap();
+        assert!(profile.last_seen_at.is_some());
+
+        set_visibility_preferences(
+            &conn,
+            1,
+            "carol",
+            &VisibilityPreferences {
+                private: true,
+                allow: vec!["alice".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let profile = index.visible_profile(&conn, 1, "bob", "carol").unwrap();
+        assert_eq!(profile.visibility, VisibilityLevel::AggregateOnly);
+        assert!(profile.last_seen_at.is_none());
+        let profile = index.visible_profile(&conn, 1, "alice", "carol").unwrap();
+        assert_eq!(profile.visibility, VisibilityLevel::Degree1);
+        assert!(profile.last_seen_at.is_some());
+
+        set_visibility_preferences(
+            &conn,
+            1,
+            "carol",
+            &VisibilityPreferences {
+                deny: vec!["bob".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let profile = index.visible_profile(&conn, 1, "bob", "carol").unwrap();
+        assert_eq!(profile.visibility, VisibilityLevel::None);
+    }
+
+    #[test]
+    fn invalid_preferences_are_rejected() {
+        let (conn, _) = setup();
+        for preferences in [
+            VisibilityPreferences {
+                metadata_max_degree: 4,
+                ..Default::default()
+            },
+            VisibilityPreferences {
+                allow: vec!["carol".to_string()],
+                ..Default::default()
+            },
+            VisibilityPreferences {
+                allow: vec!["bob".to_string()],
+                deny: vec!["bob".to_string()],
+                ..Default::default()
+            },
+            VisibilityPreferences {
+                deny: vec!["x".to_string(); MAX_VISIBILITY_LIST_LEN + 1],
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                set_visibility_preferences(&conn, 1, "carol", &preferences),
+                Err(GraphError::InvalidVisibilityPreferences(_))
+            ));
+        }
+        assert_eq!(
+            get_visibility_preferences(&conn, 1, "carol").unwrap(),
+            VisibilityPreferences::default()
+        );
+    }
+}
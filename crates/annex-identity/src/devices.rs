@@ -0,0 +1,264 @@
+//! Device Registry.
+//!
+//! Manages the `devices` table. A platform identity may register several
+//! devices, each holding its own Ed25519 key. Devices authenticate their
+//! WebSocket sessions independently and can be revoked one at a time without
+//! affecting the identity's other devices.
+
+use crate::IdentityError;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+/// Maximum number of active (non-revoked) devices per platform identity.
+pub const MAX_DEVICES_PER_IDENTITY: usize = 16;
+
+/// A registered device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Device {
+    pub device_id: String,
+    pub pseudonym_id: String,
+    pub label: String,
+    /// The device's Ed25519 public key (64-char lowercase hex).
+    pub public_key: String,
+    pub created_at: String,
+    pub last_seen_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+fn row_to_device(row: &rusqlite::Row) -> rusqlite::Result<Device> {
+    Ok(Device {
+        device_id: row.get(0)?,
+        pseudonym_id: row.get(1)?,
+        label: row.get(2)?,
+        public_key: row.get(3)?,
+        created_at: row.get(4)?,
+        last_seen_at: row.get(5)?,
+        revoked_at: row.get(6)?,
+    })
+}
+
+const DEVICE_COLUMNS: &str =
+    "device_id, pseudonym_id, label, public_key_hex, created_at, last_seen_at, revoked_at";
+
+/// Registers a new device for a platform identity.
+///
+/// # Errors
+///
+/// Returns `IdentityError::InvalidDeviceKey` if `public_key_hex` is not a
+/// 32-byte hex key, `IdentityError::DeviceLimitReached` if the identity
+/// already has [`MAX_DEVICES_PER_IDENTITY`] active devices, or
+/// `IdentityError::DatabaseError` if the insert fails (e.g. the key is
+/// already registered).
+pub fn register_device(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+    device_id: &str,
+    label: &str,
+    public_key_hex: &str,
+) -> Result<Device, IdentityError> {
+    if public_key_hex.len() != 64 || !public_key_hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(IdentityError::InvalidDeviceKey);
+    }
+    let public_key_hex = public_key_hex.to_ascii_lowercase();
+
+    if count_active_devices(conn, server_id, pseudonym_id)? >= MAX_DEVICES_PER_IDENTITY {
+        return Err(IdentityError::DeviceLimitReached(MAX_DEVICES_PER_IDENTITY));
+    }
+
+    conn.execute(
+        "INSERT INTO devices (server_id, pseudonym_id, device_id, label, public_key_hex)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![server_id, pseudonym_id, device_id, label, public_key_hex],
+    )?;
+
+    get_device(conn, server_id, device_id)?
+        .ok_or_else(|| IdentityError::DeviceNotFound(device_id.to_string()))
+}
+
+/// Retrieves a device by ID, including revoked devices.
+///
+/// # Errors
+///
+/// Returns `IdentityError::DatabaseError` if the query fails.
+pub fn get_device(
+    conn: &Connection,
+    server_id: i64,
+    device_id: &str,
+) -> Result<Option<Device>, IdentityError> {
+    let device = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM devices WHERE server_id = ?1 AND device_id = ?2",
+                DEVICE_COLUMNS
+            ),
+            params![server_id, device_id],
+            row_to_device,
+        )
+        .optional()?;
+    Ok(device)
+}
+
+/// Counts the active (non-revoked) devices registered to a pseudonym.
+///
+/// # Errors
+///
+/// Returns `IdentityError::DatabaseError` if the query fails.
+pub fn count_active_devices(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+) -> Result<usize, IdentityError> {
+    let active: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM devices
+         WHERE server_id = ?1 AND pseudonym_id = ?2 AND revoked_at IS NULL",
+        params![server_id, pseudonym_id],
+        |row| row.get(0),
+    )?;
+    Ok(active as usize)
+}
+
+/// Lists all devices (including revoked ones) registered to a pseudonym,
+/// oldest first.
+///
+/// # Errors
+///
+/// Returns `IdentityError::DatabaseError` if the query fails.
+pub fn list_devices(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+) -> Result<Vec<Device>, IdentityError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM devices WHERE server_id = ?1 AND pseudonym_id = ?2 ORDER BY id",
+        DEVICE_COLUMNS
+    ))?;
+    let devices = stmt
+        .query_map(params![server_id, pseudonym_id], row_to_device)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(devices)
+}
+
+/// Revokes a device owned by `pseudonym_id`.
+///
+/// Revocation is permanent; a revoked device must be registered again with a
+/// new key.
+///
+/// # Errors
+///
+/// Returns `IdentityError::DeviceNotFound` if the pseudonym has no active
+/// device with this ID.
+pub fn revoke_device(
+    conn: &Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+    device_id: &str,
+) -> Result<(), IdentityError> {
+    let changed = conn.execute(
+        "UPDATE devices SET revoked_at = datetime('now')
+         WHERE server_id = ?1 AND pseudonym_id = ?2 AND device_id = ?3 AND revoked_at IS NULL",
+        params![server_id, pseudonym_id, device_id],
+    )?;
+    if changed == 0 {
+        return Err(IdentityError::DeviceNotFound(device_id.to_string()));
+    }
+    Ok(())
+}
+
+/// Records that a device has just connected.
+///
+/// # Errors
+///
+/// Returns `IdentityError::DatabaseError` if the update fails.
+pub fn touch_device(
+    conn: &Connection,
+    server_id: i64,
+    device_id: &str,
+) -> Result<(), IdentityError> {
+    conn.execute(
+        "UPDATE devices SET last_seen_at = datetime('now') WHERE server_id = ?1 AND device_id = ?2",
+        params![server_id, device_id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().expect("open in-memory db");
+        annex_db::run_migrations(&conn).expect("migrations should apply");
+        conn.execute(
+            "INSERT INTO servers (slug, label, policy_json) VALUES ('test', 'Test', '{}')",
+            [],
+        )
+        .expect("insert server");
+        conn
+    }
+
+    fn key(byte: u8) -> String {
+        hex::encode([byte; 32])
+    }
+
+    #[test]
+    fn register_list_and_revoke() {
+        let conn = setup();
+        register_device(&conn, 1, "alice", "dev-1", "Laptop", &key(1)).expect("register");
+        register_device(&conn, 1, "alice", "dev-2", "Phone", &key(2)).expect("register");
+        register_device(&conn, 1, "bob", "dev-3", "Desktop", &key(3)).expect("register");
+
+        let devices = list_devices(&conn, 1, "alice").expect("list");
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].label, "Laptop");
+        assert!(devices.iter().all(|d| d.revoked_at.is_none()));
+
+        assert_eq!(count_active_devices(&conn, 1, "alice").expect("count"), 2);
+        revoke_device(&conn, 1, "alice", "dev-1").expect("revoke");
+        assert_eq!(count_active_devices(&conn, 1, "alice").expect("count"), 1);
+        let revoked = get_device(&conn, 1, "dev-1").expect("get").expect("exists");
+        assert!(revoked.revoked_at.is_some());
+
+        // Revoking twice, or another identity's device, fails.
+        assert!(matches!(
+            revoke_device(&conn, 1, "alice", "dev-1"),
+            Err(IdentityError::DeviceNotFound(_))
+        ));
+        assert!(matches!(
+            revoke_device(&conn, 1, "alice", "dev-3"),
+            Err(IdentityError::DeviceNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_and_duplicate_keys() {
+        let conn = setup();
+        assert!(matches!(
+            register_device(&conn, 1, "alice", "dev-1", "Laptop", "abcd"),
+            Err(IdentityError::InvalidDeviceKey)
+        ));
+
+        register_device(&conn, 1, "alice", "dev-1", "Laptop", &key(1)).expect("register");
+        assert!(matches!(
+            register_device(&conn, 1, "bob", "dev-2", "Stolen", &key(1)),
+            Err(IdentityError::DatabaseError(_))
+        ));
+    }
+
+    #[test]
+    fn enforces_device_limit() {
+        let conn = setup();
+        for i in 0..MAX_DEVICES_PER_IDENTITY {
+            register_device(&conn, 1, "alice", &format!("dev-{i}"), "d", &key(i as u8))
+                .expect("register");
+        }
+        assert!(matches!(
+            register_device(&conn, 1, "alice", "dev-extra", "d", &key(200)),
+            Err(IdentityError::DeviceLimitReached(_))
+        ));
+
+        // Revoking frees a slot.
+        revoke_device(&conn, 1, "alice", "dev-0").expect("revoke");
+        register_device(&conn, 1, "alice", "dev-extra", "d", &key(200)).expect("register");
+    }
+}
@@ -0,0 +1,305 @@
+//! Selective disclosure of participant roles.
+//!
+//! A proof from `role.circom` shows that the prover's commitment is in the
+//! Merkle tree and that its role code belongs to a set `S`, without revealing
+//! the commitment or which member of `S` the role is. The verifier supplies a
+//! `contextHash` (for example, a channel), and the proof outputs a
+//! context-scoped nullifier `Poseidon(sk, contextHash)`. Like a poll
+//! nullifier it is derived from the secret key, so it cannot be recomputed
+//! from the public commitment list, but it is the same every time one
+//! identity proves in one context. [`bind_role_nullifier`] ties it to the
+//! first pseudonym that presents it, so a role holder cannot vouch for
+//! anyone else.
+//!
+//! The role set is encoded canonically: role codes sorted ascending,
+//! de-duplicated, and padded with `0` to [`ROLE_SET_SIZE`] slots.
+
+use crate::poseidon::hash_inputs;
+use crate::vote::{field_to_hex, hash_to_field};
+use crate::zk::{verify_proof, Bn254, Fr, Proof, VerifyingKey, ZkError};
+use crate::IdentityError;
+use annex_types::RoleCode;
+use rusqlite::{params, Connection};
+use thiserror::Error;
+
+/// Number of role slots in the circuit's public `allowedRoles` input.
+pub const ROLE_SET_SIZE: usize = 5;
+
+/// Errors produced while checking a role disclosure proof.
+#[derive(Debug, Error)]
+pub enum DisclosureError {
+    /// The requested role set is empty.
+    #[error("role set cannot be empty")]
+    EmptyRoleSet,
+    /// The proof carries the wrong number of public signals.
+    #[error("expected {expected} public signals, got {actual}")]
+    InvalidSignalCount { expected: usize, actual: usize },
+    /// The proof discloses a different role set than the verifier requires.
+    #[error("proof role set does not match the required role set")]
+    RoleSetMismatch,
+    /// The proof was generated for a different context.
+    #[error("proof context does not match")]
+    ContextMismatch,
+    /// The proof's nullifier is bound to another pseudonym in this context,
+    /// or the caller is bound to another nullifier.
+    #[error("proof nullifier is bound to another pseudonym")]
+    NullifierBound,
+    /// The Groth16 proof did not verify.
+    #[error("invalid proof")]
+    InvalidProof,
+    /// Proof verification failed internally.
+    #[error("zk error: {0}")]
+    Zk(#[from] ZkError),
+    /// Hashing or the nullifier lookup failed.
+    #[error("identity error: {0}")]
+    Identity(#[from] IdentityError),
+}
+
+/// The public outputs of a verified role disclosure proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoleDisclosure {
+    /// The Merkle root the proof was generated against.
+    pub root: Fr,
+    /// The context-scoped nullifier `Poseidon(sk, contextHash)`.
+    pub nullifier: Fr,
+}
+
+/// Encodes a role set as the circuit's `allowedRoles` public input.
+///
+/// # Errors
+///
+/// Returns [`DisclosureError::EmptyRoleSet`] if `roles` is empty.
+pub fn role_set_inputs(roles: &[RoleCode]) -> Result<Vec<Fr>, DisclosureError> {
+    if roles.is_empty() {
+        return Err(DisclosureError::EmptyRoleSet);
+    }
+    let mut codes: Vec<u8> = roles.iter().map(|r| r.as_u8()).collect();
+    codes.sort_unstable();
+    codes.dedup();
+
+    let mut inputs: Vec<Fr> = codes.into_iter().map(|c| Fr::from(c as u64)).collect();
+    inputs.resize(ROLE_SET_SIZE, Fr::from(0u64));
+    Ok(inputs)
+}
+
+/// Returns the `contextHash` for role proofs presented to join `channel_id`.
+///
+/// The context names only the channel, so each identity has one nullifier
+/// per channel whichever pseudonym presents it. Its prefix differs from
+/// [`role_context`], so no challenge string can ask for a channel's
+/// nullifier.
+pub fn channel_role_context(channel_id: &str) -> Fr {
+    hash_to_field(&format!("annex:channel-role:{channel_id}"))
+}
+
+/// Returns the `contextHash` for an arbitrary verifier-chosen context string,
+/// such as a challenge issued by a federated peer.
+pub fn role_context(context: &str) -> Fr {
+    hash_to_field(&format!("annex:role:{context}"))
+}
+
+/// Derives the role nullifier `Poseidon(sk, contextHash)` exactly as the
+/// circuit does. Intended for clients and tests; the server only ever sees
+/// the nullifier as a public signal.
+///
+/// # Errors
+///
+/// Returns [`IdentityError::PoseidonError`] if hashing fails.
+pub fn derive_role_nullifier(sk: Fr, context_hash: Fr) -> Result<Fr, IdentityError> {
+    hash_inputs(&[sk, context_hash])
+}
+
+/// Verifies a role disclosure proof against the required role set and
+/// context.
+///
+/// The caller is responsible for checking that the returned root is one the
+/// server has published, and, when it knows who is presenting the proof, for
+/// binding the returned nullifier to them with [`bind_role_nullifier`].
+/// Otherwise any registered identity with a matching role could prove it on
+/// the caller's behalf.
+///
+/// # Errors
+///
+/// Returns a [`DisclosureError`] describing the first check that failed.
+pub fn verify_role_disclosure(
+    vk: &VerifyingKey<Bn254>,
+    proof: &Proof<Bn254>,
+    public_signals: &[Fr],
+    required_roles: &[RoleCode],
+    context_hash: Fr,
+) -> Result<RoleDisclosure, DisclosureError> {
+    // role.circom public signals: [root, nullifier, allowedRoles[0..5], contextHash]
+    let expected = ROLE_SET_SIZE + 3;
+    if public_signals.len() != expected {
+        return Err(DisclosureError::InvalidSignalCount {
+            expected,
+            actual: public_signals.len(),
+        });
+    }
+
+    let allowed = role_set_inputs(required_roles)?;
+    if public_signals[2..ROLE_SET_SIZE + 2] != allowed[..] {
+        return Err(DisclosureError::RoleSetMismatch);
+    }
+    if public_signals[ROLE_SET_SIZE + 2] != context_hash {
+        return Err(DisclosureError::ContextMismatch);
+    }
+    if !verify_proof(vk, proof, public_signals)? {
+        return Err(DisclosureError::InvalidProof);
+    }
+    Ok(RoleDisclosure {
+        root: public_signals[0],
+        nullifier: public_signals[1],
+    })
+}
+
+/// Binds a verified role proof's nullifier to `pseudonym_id` in
+/// `context_hash`.
+///
+/// The first pseudonym to present a nullifier in a context keeps it: the
+/// same pair may be presented again, but the nullifier is refused for any
+/// other pseudonym, and the pseudonym may not switch to another nullifier.
+///
+/// # Errors
+///
+/// Returns [`DisclosureError::NullifierBound`] if either side is already
+/// bound to something else, or [`DisclosureError::Identity`] if the database
+/// access fails.
+pub fn bind_role_nullifier(
+    conn: &Connection,
+    server_id: i64,
+    context_hash: Fr,
+    nullifier: Fr,
+    pseudonym_id: &str,
+) -> Result<(), DisclosureError> {
+    let context_hex = field_to_hex(&context_hash);
+    let nullifier_hex = field_to_hex(&nullifier);
+    // The insert is ignored when either unique key is taken; the binding
+    // holds only if this exact row exists afterwards.
+    conn.execute(
+        "INSERT OR IGNORE INTO role_nullifiers (server_id, context_hex, nullifier_hex, pseudonym_id)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![server_id, context_hex, nullifier_hex, pseudonym_id],
+    )
+    .map_err(IdentityError::from)?;
+    let bound: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM role_nullifiers
+             WHERE server_id = ?1 AND context_hex = ?2 AND nullifier_hex = ?3 AND pseudonym_id = ?4)",
+            params![server_id, context_hex, nullifier_hex, pseudonym_id],
+            |row| row.get(0),
+        )
+        .map_err(IdentityError::from)?;
+    if !bound {
+        return Err(DisclosureError::NullifierBound);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn role_set_is_sorted_deduped_and_padded() {
+        let inputs =
+            role_set_inputs(&[RoleCode::Collective, RoleCode::Human, RoleCode::Collective])
+                .expect("non-empty set");
+        assert_eq!(
+            inputs,
+            vec![
+                Fr::from(1u64),
+                Fr::from(3u64),
+                Fr::from(0u64),
+                Fr::from(0u64),
+                Fr::from(0u64)
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_role_set_is_rejected() {
+        assert!(matches!(
+            role_set_inputs(&[]),
+            Err(DisclosureError::EmptyRoleSet)
+        ));
+    }
+
+    #[test]
+    fn channel_context_is_scoped_to_channel() {
+        assert_eq!(channel_role_context("chan"), channel_role_context("chan"));
+        assert_ne!(channel_role_context("chan"), channel_role_context("other"));
+        assert_ne!(channel_role_context("chan"), role_context("channel:chan"));
+    }
+
+    #[test]
+    fn nullifier_is_scoped_to_context() {
+        let sk = Fr::from(123456789u64);
+        let chan = channel_role_context("chan");
+        let a = derive_role_nullifier(sk, chan).expect("hash");
+        assert_eq!(a, derive_role_nullifier(sk, chan).expect("hash"));
+        assert_ne!(
+            a,
+            derive_role_nullifier(sk, channel_role_context("other")).expect("hash")
+        );
+        assert_ne!(
+            a,
+            derive_role_nullifier(Fr::from(1u64), chan).expect("hash")
+        );
+    }
+
+    #[test]
+    fn nullifier_binds_to_first_pseudonym() {
+        let conn = Connection::open_in_memory().expect("open in-memory db");
+        annex_db::run_migrations(&conn).expect("migrations should apply");
+        let chan = channel_role_context("chan");
+        let (n1, n2) = (Fr::from(1u64), Fr::from(2u64));
+
+        bind_role_nullifier(&conn, 1, chan, n1, "alice").expect("first use binds");
+        bind_role_nullifier(&conn, 1, chan, n1, "alice").expect("same pair again");
+        assert!(matches!(
+            bind_role_nullifier(&conn, 1, chan, n1, "bob"),
+            Err(DisclosureError::NullifierBound)
+        ));
+        assert!(matches!(
+            bind_role_nullifier(&conn, 1, chan, n2, "alice"),
+            Err(DisclosureError::NullifierBound)
+        ));
+
+        // Other contexts are independent.
+        bind_role_nullifier(&conn, 1, channel_role_context("other"), n1, "bob")
+            .expect("other context");
+    }
+
+    #[test]
+    fn verify_rejects_wrong_role_set_before_pairing() {
+        let vk = crate::zk::generate_dummy_vkey();
+        let proof = Proof {
+            a: vk.alpha_g1,
+            b: vk.beta_g2,
+            c: vk.alpha_g1,
+        };
+        let context = role_context("challenge");
+        let mut signals = vec![Fr::from(7u64), Fr::from(8u64)];
+        signals.extend(role_set_inputs(&[RoleCode::AiAgent]).expect("non-empty set"));
+        signals.push(context);
+
+        let err = verify_role_disclosure(&vk, &proof, &signals, &[RoleCode::Human], context)
+            .expect_err("role set mismatch");
+        assert!(matches!(err, DisclosureError::RoleSetMismatch));
+
+        let err = verify_role_disclosure(
+            &vk,
+            &proof,
+            &signals,
+            &[RoleCode::AiAgent],
+            role_context("other"),
+        )
+        .expect_err("context mismatch");
+        assert!(matches!(err, DisclosureError::ContextMismatch));
+
+        let err = verify_role_disclosure(&vk, &proof, &signals[..3], &[RoleCode::AiAgent], context)
+            .expect_err("signal count");
+        assert!(matches!(err, DisclosureError::InvalidSignalCount { .. }));
+    }
+}
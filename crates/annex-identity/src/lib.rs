@@ -8,14 +8,21 @@ use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 pub mod commitment;
+pub mod devices;
+pub mod disclosure;
 pub mod merkle;
 pub mod nullifier;
 pub mod platform;
 pub mod poseidon;
 pub mod registry;
+pub mod vote;
 pub mod zk;
 
 pub use commitment::generate_commitment;
+pub use devices::{
+    count_active_devices, get_device, list_devices, register_device, revoke_device, touch_device,
+    Device, MAX_DEVICES_PER_IDENTITY,
+};
 pub use merkle::MerkleTree;
 pub use nullifier::{check_nullifier_exists, insert_nullifier};
 pub use platform::{
@@ -24,9 +31,12 @@ pub use platform::{
 };
 pub use poseidon::hash_inputs;
 pub use registry::{
-    get_all_roles, get_all_topics, get_path_for_commitment, register_identity, VrpRoleEntry,
-    VrpTopic,
+    create_role, create_topic, delete_role, delete_topic, deprecate_topic,
+    ensure_topic_not_deprecated, get_all_roles, get_all_topics, get_path_for_commitment, get_topic,
+    register_identity, stage_identity, update_role_label, update_topic_description,
+    StagedRegistration, VrpRoleEntry, VrpTopic,
 };
+pub use vote::{derive_poll_nullifier, poll_topic, poll_topic_hash, poll_vote_hash};
 
 /// Errors produced by identity derivation operations.
 #[derive(Debug, Error)]
@@ -73,6 +83,40 @@ pub enum IdentityError {
     /// Commitment not found in the registry.
     #[error("commitment not found: {0}")]
     CommitmentNotFound(String),
+    /// The device public key is not 32 bytes of hex.
+    #[error("device public key must be 64 hex characters")]
+    InvalidDeviceKey,
+    /// The identity already has the maximum number of active devices.
+    #[error("device limit reached (max {0})")]
+    DeviceLimitReached(usize),
+    /// No active device with this ID belongs to the identity.
+    #[error("device not found: {0}")]
+    DeviceNotFound(String),
+    /// A VRP topic or role identifier/label is malformed.
+    #[error("invalid registry entry: {0}")]
+    InvalidRegistryEntry(String),
+    /// The VRP topic is already registered.
+    #[error("topic already exists: {0}")]
+    TopicExists(String),
+    /// The VRP topic is not registered.
+    #[error("topic not found: {0}")]
+    TopicNotFound(String),
+    /// The VRP topic is deprecated and accepts no new bindings or
+    /// verifications.
+    #[error("topic is deprecated: {0}")]
+    TopicDeprecated(String),
+    /// The VRP topic is still bound to one or more channels.
+    #[error("topic is bound to {count} channel(s): {topic}")]
+    TopicInUse { topic: String, count: i64 },
+    /// The role code is already registered.
+    #[error("role already exists: {0}")]
+    RoleExists(u8),
+    /// The role code is not registered.
+    #[error("role not found: {0}")]
+    RoleNotFound(u8),
+    /// The role is built in or still held by registered identities.
+    #[error("role cannot be modified: {0}")]
+    RoleInUse(String),
     /// Merkle root mismatch between stored and computed values.
     #[error("merkle root mismatch: stored={stored}, computed={computed}")]
     MerkleRootMismatch { stored: String, computed: String },
@@ -98,6 +142,26 @@ impl PartialEq for IdentityError {
             (Self::DuplicateNullifier(a), Self::DuplicateNullifier(b)) => a == b,
             (Self::DuplicateCommitment(a), Self::DuplicateCommitment(b)) => a == b,
             (Self::CommitmentNotFound(a), Self::CommitmentNotFound(b)) => a == b,
+            (Self::InvalidDeviceKey, Self::InvalidDeviceKey) => true,
+            (Self::DeviceLimitReached(a), Self::DeviceLimitReached(b)) => a == b,
+            (Self::DeviceNotFound(a), Self::DeviceNotFound(b)) => a == b,
+            (Self::InvalidRegistryEntry(a), Self::InvalidRegistryEntry(b)) => a == b,
+            (Self::TopicExists(a), Self::TopicExists(b)) => a == b,
+            (Self::TopicNotFound(a), Self::TopicNotFound(b)) => a == b,
+            (Self::TopicDeprecated(a), Self::TopicDeprecated(b)) => a == b,
+            (
+                Self::TopicInUse {
+                    topic: t1,
+                    count: c1,
+                },
+                Self::TopicInUse {
+                    topic: t2,
+                    count: c2,
+                },
+            ) => t1 == t2 && c1 == c2,
+            (Self::RoleExists(a), Self::RoleExists(b)) => a == b,
+            (Self::RoleNotFound(a), Self::RoleNotFound(b)) => a == b,
+            (Self::RoleInUse(a), Self::RoleInUse(b)) => a == b,
             (
                 Self::MerkleRootMismatch {
                     stored: s1,
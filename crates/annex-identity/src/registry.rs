@@ -16,6 +16,11 @@ pub struct VrpTopic {
     pub topic: String,
     /// Human-readable description.
     pub description: String,
+    /// When the topic was deprecated, if it has been. Deprecated topics
+    /// remain listed so existing bindings keep resolving, but take no new
+    /// channel bindings or membership verifications.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated_at: Option<String>,
 }
 
 /// VRP Role definition.
@@ -42,6 +47,48 @@ pub struct RegistrationResult {
     pub path_indices: Vec<u8>,
 }
 
+/// A registration written inside a caller's transaction but not yet applied
+/// to the in-memory Merkle tree. Returned by [`stage_identity`].
+///
+/// Apply it with [`StagedRegistration::apply`] only after the transaction
+/// commits; dropping it (e.g. on rollback) leaves the tree untouched.
+#[derive(Debug)]
+#[must_use = "a staged registration must be applied after its transaction commits"]
+pub struct StagedRegistration {
+    identity_id: i64,
+    leaf_index: usize,
+    new_root: Fr,
+    updates: Vec<((usize, usize), Fr)>,
+}
+
+impl StagedRegistration {
+    /// Applies the staged insertion to `tree` and returns the Merkle path.
+    ///
+    /// The tree must not have changed since [`stage_identity`]; callers hold
+    /// its lock across staging, committing and applying.
+    pub fn apply(self, tree: &mut MerkleTree) -> Result<RegistrationResult, IdentityError> {
+        let leaf_index = self.leaf_index;
+        tree.apply_updates(leaf_index + 1, self.updates);
+
+        let (path_elements_fr, path_indices) = tree.get_proof(leaf_index)?;
+
+        let path_elements = path_elements_fr
+            .into_iter()
+            .map(|fr| hex::encode(fr.into_bigint().to_bytes_be()))
+            .collect();
+
+        let root_hex = hex::encode(self.new_root.into_bigint().to_bytes_be());
+
+        Ok(RegistrationResult {
+            identity_id: self.identity_id,
+            leaf_index,
+            root_hex,
+            path_elements,
+            path_indices,
+        })
+    }
+}
+
 /// Registers a new identity commitment.
 ///
 /// 1. Checks if the commitment is already registered in `vrp_identities`.
@@ -50,7 +97,9 @@ pub struct RegistrationResult {
 /// 4. Persists the tree update to `vrp_leaves` and `vrp_roots`.
 /// 5. Returns the Merkle path and new root.
 ///
-/// All database operations are wrapped in a transaction.
+/// All database operations are wrapped in a transaction. Callers that need
+/// to write more rows atomically with the registration use
+/// [`stage_identity`] inside their own transaction instead.
 ///
 /// # Errors
 ///
@@ -65,6 +114,27 @@ pub fn register_identity(
     role: RoleCode,
     node_id: i64,
 ) -> Result<RegistrationResult, IdentityError> {
+    let tx = conn.transaction().map_err(IdentityError::DatabaseError)?;
+    let staged = stage_identity(tree, &tx, commitment_hex, role, node_id)?;
+    tx.commit().map_err(IdentityError::DatabaseError)?;
+
+    // Only done if the transaction succeeds.
+    staged.apply(tree)
+}
+
+/// Writes a new identity and its Merkle leaf through `conn`, which should be
+/// an open transaction, without touching the in-memory tree.
+///
+/// # Errors
+///
+/// The same as [`register_identity`].
+pub fn stage_identity(
+    tree: &MerkleTree,
+    conn: &Connection,
+    commitment_hex: &str,
+    role: RoleCode,
+    node_id: i64,
+) -> Result<StagedRegistration, IdentityError> {
     // Validate format
     if commitment_hex.len() != 64 || !commitment_hex.chars().all(|c| c.is_ascii_hexdigit()) {
         return Err(IdentityError::InvalidCommitmentFormat);
@@ -83,16 +153,13 @@ pub fn register_identity(
     // This calculates the new root and updates without modifying the tree.
     let (leaf_index, new_root, updates) = tree.preview_insert(leaf)?;
 
-    // Start transaction
-    let tx = conn.transaction().map_err(IdentityError::DatabaseError)?;
-
     // 2. Check & Insert into vrp_identities
     // We try to insert directly. If it fails due to UNIQUE constraint, it's a duplicate.
-    let identity_id = match tx.execute(
+    let identity_id = match conn.execute(
         "INSERT INTO vrp_identities (commitment_hex, role_code, node_id) VALUES (?1, ?2, ?3)",
         params![commitment_hex, role.as_u8(), node_id],
     ) {
-        Ok(_) => tx.last_insert_rowid(),
+        Ok(_) => conn.last_insert_rowid(),
         Err(rusqlite::Error::SqliteFailure(err, _)) => {
             if err.code == rusqlite::ErrorCode::ConstraintViolation {
                 return Err(IdentityError::DuplicateCommitment(format!(
@@ -108,31 +175,13 @@ pub fn register_identity(
     };
 
     // 3. Persist Merkle Tree update (In Transaction)
-    tree.persist_leaf_and_root(&tx, leaf_index, leaf, new_root)?;
-
-    // 4. Commit Transaction
-    tx.commit().map_err(IdentityError::DatabaseError)?;
+    tree.persist_leaf_and_root(conn, leaf_index, leaf, new_root)?;
 
-    // 5. Apply updates to In-Memory Tree
-    // Only done if transaction succeeds.
-    tree.apply_updates(leaf_index + 1, updates);
-
-    // 5. Generate Proof (Read-only)
-    let (path_elements_fr, path_indices) = tree.get_proof(leaf_index)?;
-
-    let path_elements = path_elements_fr
-        .into_iter()
-        .map(|fr| hex::encode(fr.into_bigint().to_bytes_be()))
-        .collect();
-
-    let root_hex = hex::encode(new_root.into_bigint().to_bytes_be());
-
-    Ok(RegistrationResult {
+    Ok(StagedRegistration {
         identity_id,
         leaf_index,
-        root_hex,
-        path_elements,
-        path_indices,
+        new_root,
+        updates,
     })
 }
 
@@ -180,19 +229,65 @@ pub fn get_path_for_commitment(
     Ok((leaf_index, root_hex, path_elements, path_indices))
 }
 
-/// Retrieves all registered VRP topics.
+/// Maximum length of a VRP topic identifier.
+const MAX_TOPIC_LEN: usize = 128;
+
+/// Maximum length of a VRP topic description or role label.
+const MAX_REGISTRY_TEXT_LEN: usize = 256;
+
+fn validate_topic(topic: &str) -> Result<(), IdentityError> {
+    if topic.is_empty()
+        || topic.len() > MAX_TOPIC_LEN
+        || !topic
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '.' | '_' | '-'))
+    {
+        return Err(IdentityError::InvalidRegistryEntry(format!(
+            "topic must be 1-{} characters of [A-Za-z0-9:._-]",
+            MAX_TOPIC_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn validate_description(description: &str) -> Result<(), IdentityError> {
+    if description.chars().count() > MAX_REGISTRY_TEXT_LEN {
+        return Err(IdentityError::InvalidRegistryEntry(format!(
+            "description must be at most {} characters",
+            MAX_REGISTRY_TEXT_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn validate_role_label(label: &str) -> Result<(), IdentityError> {
+    if label.is_empty()
+        || label.len() > 32
+        || !label
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(IdentityError::InvalidRoleLabel(label.to_string()));
+    }
+    Ok(())
+}
+
+fn row_to_topic(row: &rusqlite::Row) -> rusqlite::Result<VrpTopic> {
+    Ok(VrpTopic {
+        topic: row.get(0)?,
+        description: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+        deprecated_at: row.get(2)?,
+    })
+}
+
+/// Retrieves all registered VRP topics, including deprecated ones.
 pub fn get_all_topics(conn: &Connection) -> Result<Vec<VrpTopic>, IdentityError> {
     let mut stmt = conn
-        .prepare("SELECT topic, description FROM vrp_topics ORDER BY created_at ASC")
+        .prepare("SELECT topic, description, deprecated_at FROM vrp_topics ORDER BY created_at ASC")
         .map_err(IdentityError::DatabaseError)?;
 
     let topics = stmt
-        .query_map([], |row| {
-            Ok(VrpTopic {
-                topic: row.get(0)?,
-                description: row.get(1)?,
-            })
-        })
+        .query_map([], row_to_topic)
         .map_err(IdentityError::DatabaseError)?
         .collect::<Result<Vec<_>, _>>()
         .map_err(IdentityError::DatabaseError)?;
@@ -200,6 +295,125 @@ pub fn get_all_topics(conn: &Connection) -> Result<Vec<VrpTopic>, IdentityError>
     Ok(topics)
 }
 
+/// Retrieves a single VRP topic.
+pub fn get_topic(conn: &Connection, topic: &str) -> Result<Option<VrpTopic>, IdentityError> {
+    let topic = conn
+        .query_row(
+            "SELECT topic, description, deprecated_at FROM vrp_topics WHERE topic = ?1",
+            params![topic],
+            row_to_topic,
+        )
+        .optional()?;
+    Ok(topic)
+}
+
+/// Registers a new VRP topic.
+///
+/// # Errors
+///
+/// Returns [`IdentityError::InvalidRegistryEntry`] if the topic or description
+/// is malformed, or [`IdentityError::TopicExists`] if it is already registered.
+pub fn create_topic(
+    conn: &Connection,
+    topic: &str,
+    description: &str,
+) -> Result<VrpTopic, IdentityError> {
+    validate_topic(topic)?;
+    validate_description(description)?;
+
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO vrp_topics (topic, description) VALUES (?1, ?2)",
+        params![topic, description],
+    )?;
+    if inserted == 0 {
+        return Err(IdentityError::TopicExists(topic.to_string()));
+    }
+
+    get_topic(conn, topic)?.ok_or_else(|| IdentityError::TopicNotFound(topic.to_string()))
+}
+
+/// Replaces the description of a VRP topic.
+///
+/// # Errors
+///
+/// Returns [`IdentityError::TopicNotFound`] if the topic is not registered.
+pub fn update_topic_description(
+    conn: &Connection,
+    topic: &str,
+    description: &str,
+) -> Result<VrpTopic, IdentityError> {
+    validate_description(description)?;
+
+    let changed = conn.execute(
+        "UPDATE vrp_topics SET description = ?2 WHERE topic = ?1",
+        params![topic, description],
+    )?;
+    if changed == 0 {
+        return Err(IdentityError::TopicNotFound(topic.to_string()));
+    }
+
+    get_topic(conn, topic)?.ok_or_else(|| IdentityError::TopicNotFound(topic.to_string()))
+}
+
+/// Marks a VRP topic as deprecated. Deprecating an already deprecated topic
+/// keeps the original timestamp.
+///
+/// # Errors
+///
+/// Returns [`IdentityError::TopicNotFound`] if the topic is not registered.
+pub fn deprecate_topic(conn: &Connection, topic: &str) -> Result<VrpTopic, IdentityError> {
+    conn.execute(
+        "UPDATE vrp_topics SET deprecated_at = datetime('now')
+         WHERE topic = ?1 AND deprecated_at IS NULL",
+        params![topic],
+    )?;
+
+    get_topic(conn, topic)?.ok_or_else(|| IdentityError::TopicNotFound(topic.to_string()))
+}
+
+/// Checks that `topic` may take new channel bindings and verifications.
+/// Topics missing from the registry are not checked here.
+///
+/// # Errors
+///
+/// Returns [`IdentityError::TopicDeprecated`] if the topic is deprecated.
+pub fn ensure_topic_not_deprecated(conn: &Connection, topic: &str) -> Result<(), IdentityError> {
+    match get_topic(conn, topic)? {
+        Some(VrpTopic {
+            deprecated_at: Some(_),
+            ..
+        }) => Err(IdentityError::TopicDeprecated(topic.to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// Deletes a VRP topic.
+///
+/// # Errors
+///
+/// Returns [`IdentityError::TopicInUse`] if any channel's
+/// `vrp_topic_binding` still references the topic, or
+/// [`IdentityError::TopicNotFound`] if it is not registered.
+pub fn delete_topic(conn: &Connection, topic: &str) -> Result<(), IdentityError> {
+    let bound: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM channels WHERE vrp_topic_binding = ?1",
+        params![topic],
+        |row| row.get(0),
+    )?;
+    if bound > 0 {
+        return Err(IdentityError::TopicInUse {
+            topic: topic.to_string(),
+            count: bound,
+        });
+    }
+
+    let deleted = conn.execute("DELETE FROM vrp_topics WHERE topic = ?1", params![topic])?;
+    if deleted == 0 {
+        return Err(IdentityError::TopicNotFound(topic.to_string()));
+    }
+    Ok(())
+}
+
 /// Retrieves all registered VRP roles.
 pub fn get_all_roles(conn: &Connection) -> Result<Vec<VrpRoleEntry>, IdentityError> {
     let mut stmt = conn
@@ -220,6 +434,117 @@ pub fn get_all_roles(conn: &Connection) -> Result<Vec<VrpRoleEntry>, IdentityErr
     Ok(roles)
 }
 
+/// Registers a new VRP role entry.
+///
+/// Labels are upper-case identifiers (e.g. `AUDITOR`).
+///
+/// # Errors
+///
+/// Returns [`IdentityError::InvalidRoleCode`] for code 0,
+/// [`IdentityError::InvalidRoleLabel`] for a malformed label, or
+/// [`IdentityError::RoleExists`] if the code is already registered.
+pub fn create_role(
+    conn: &Connection,
+    role_code: u8,
+    label: &str,
+) -> Result<VrpRoleEntry, IdentityError> {
+    if role_code == 0 {
+        return Err(IdentityError::InvalidRoleCode(role_code));
+    }
+    validate_role_label(label)?;
+
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO vrp_roles (role_code, label) VALUES (?1, ?2)",
+        params![role_code, label],
+    )?;
+    if inserted == 0 {
+        return Err(IdentityError::RoleExists(role_code));
+    }
+
+    Ok(VrpRoleEntry {
+        role_code,
+        label: label.to_string(),
+    })
+}
+
+/// Relabels a custom VRP role entry.
+///
+/// # Errors
+///
+/// Returns [`IdentityError::RoleInUse`] for built-in roles, whose labels are
+/// fixed by [`RoleCode::label`], or [`IdentityError::RoleNotFound`] if the
+/// code is not registered.
+pub fn update_role_label(
+    conn: &Connection,
+    role_code: u8,
+    label: &str,
+) -> Result<VrpRoleEntry, IdentityError> {
+    if RoleCode::from_u8(role_code).is_some() {
+        return Err(IdentityError::RoleInUse(format!(
+            "{} is a built-in role",
+            role_code
+        )));
+    }
+    validate_role_label(label)?;
+
+    let changed = conn.execute(
+        "UPDATE vrp_roles SET label = ?2 WHERE role_code = ?1",
+        params![role_code, label],
+    )?;
+    if changed == 0 {
+        return Err(IdentityError::RoleNotFound(role_code));
+    }
+
+    Ok(VrpRoleEntry {
+        role_code,
+        label: label.to_string(),
+    })
+}
+
+/// Deletes a custom VRP role entry and returns it.
+///
+/// # Errors
+///
+/// Returns [`IdentityError::RoleInUse`] for built-in roles or roles still
+/// held by registered identities, or [`IdentityError::RoleNotFound`] if the
+/// code is not registered.
+pub fn delete_role(conn: &Connection, role_code: u8) -> Result<VrpRoleEntry, IdentityError> {
+    if RoleCode::from_u8(role_code).is_some() {
+        return Err(IdentityError::RoleInUse(format!(
+            "{} is a built-in role",
+            role_code
+        )));
+    }
+
+    let label: String = conn
+        .query_row(
+            "SELECT label FROM vrp_roles WHERE role_code = ?1",
+            params![role_code],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or(IdentityError::RoleNotFound(role_code))?;
+
+    let holders: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM vrp_identities WHERE role_code = ?1",
+        params![role_code],
+        |row| row.get(0),
+    )?;
+    if holders > 0 {
+        return Err(IdentityError::RoleInUse(format!(
+            "{} is held by {} identities",
+            role_code, holders
+        )));
+    }
+
+    conn.execute(
+        "DELETE FROM vrp_roles WHERE role_code = ?1",
+        params![role_code],
+    )?;
+
+    Ok(VrpRoleEntry { role_code, label })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -251,6 +576,29 @@ mod tests {
         assert!(exists);
     }
 
+    #[test]
+    fn test_staged_identity_rolls_back_with_its_transaction() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        let mut tree = MerkleTree::new(5).unwrap();
+        let commitment = "0000000000000000000000000000000000000000000000000000000000000001";
+
+        {
+            let tx = conn.transaction().unwrap();
+            let _staged = stage_identity(&tree, &tx, commitment, RoleCode::Human, 100).unwrap();
+            // Dropped without committing.
+        }
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM vrp_identities", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+
+        // The tree was left alone, so the commitment takes the first leaf.
+        let result = register_identity(&mut tree, &mut conn, commitment, RoleCode::Human, 100)
+            .expect("registration should succeed");
+        assert_eq!(result.leaf_index, 0);
+    }
+
     #[test]
     fn test_register_duplicate_commitment_fails() {
         let mut conn = Connection::open_in_memory().unwrap();
@@ -331,4 +679,113 @@ mod tests {
             _ => panic!("expected DuplicateCommitment, got {:?}", err),
         }
     }
+
+    #[test]
+    fn test_topic_lifecycle() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO servers (slug, label, policy_json) VALUES ('test', 'Test', '{}')",
+            [],
+        )
+        .unwrap();
+
+        let topic = create_topic(&conn, "annex:research:v1", "Research scope").unwrap();
+        assert_eq!(topic.description, "Research scope");
+        assert!(topic.deprecated_at.is_none());
+        assert_eq!(
+            create_topic(&conn, "annex:research:v1", "again").unwrap_err(),
+            IdentityError::TopicExists("annex:research:v1".to_string())
+        );
+        assert!(matches!(
+            create_topic(&conn, "bad topic", ""),
+            Err(IdentityError::InvalidRegistryEntry(_))
+        ));
+
+        let topic = update_topic_description(&conn, "annex:research:v1", "Lab scope").unwrap();
+        assert_eq!(topic.description, "Lab scope");
+
+        let topic = deprecate_topic(&conn, "annex:research:v1").unwrap();
+        assert!(topic.deprecated_at.is_some());
+        assert!(get_all_topics(&conn)
+            .unwrap()
+            .iter()
+            .any(|t| t.topic == "annex:research:v1" && t.deprecated_at.is_some()));
+
+        // A topic bound to a channel cannot be deleted.
+        conn.execute(
+            "INSERT INTO channels (server_id, channel_id, name, channel_type, vrp_topic_binding, federation_scope)
+             VALUES (1, 'lab', 'Lab', '\"Text\"', 'annex:research:v1', '\"Local\"')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(
+            delete_topic(&conn, "annex:research:v1").unwrap_err(),
+            IdentityError::TopicInUse {
+                topic: "annex:research:v1".to_string(),
+                count: 1
+            }
+        );
+
+        conn.execute("DELETE FROM channels WHERE channel_id = 'lab'", [])
+            .unwrap();
+        delete_topic(&conn, "annex:research:v1").unwrap();
+        assert!(get_topic(&conn, "annex:research:v1").unwrap().is_none());
+        assert_eq!(
+            delete_topic(&conn, "annex:research:v1").unwrap_err(),
+            IdentityError::TopicNotFound("annex:research:v1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_role_lifecycle() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+
+        create_role(&conn, 6, "AUDITOR").unwrap();
+        assert_eq!(
+            create_role(&conn, 6, "AUDITOR").unwrap_err(),
+            IdentityError::RoleExists(6)
+        );
+        assert!(matches!(
+            create_role(&conn, 7, "lower"),
+            Err(IdentityError::InvalidRoleLabel(_))
+        ));
+
+        let role = update_role_label(&conn, 6, "EXTERNAL_AUDITOR").unwrap();
+        assert_eq!(role.label, "EXTERNAL_AUDITOR");
+        assert!(get_all_roles(&conn)
+            .unwrap()
+            .iter()
+            .any(|r| r.role_code == 6 && r.label == "EXTERNAL_AUDITOR"));
+
+        // Built-in roles are fixed.
+        assert!(matches!(
+            update_role_label(&conn, 1, "PERSON"),
+            Err(IdentityError::RoleInUse(_))
+        ));
+        assert!(matches!(
+            delete_role(&conn, 1),
+            Err(IdentityError::RoleInUse(_))
+        ));
+
+        // Roles held by identities cannot be deleted.
+        conn.execute(
+            "INSERT INTO vrp_identities (commitment_hex, role_code, node_id) VALUES ('c', 6, 1)",
+            [],
+        )
+        .unwrap();
+        assert!(matches!(
+            delete_role(&conn, 6),
+            Err(IdentityError::RoleInUse(_))
+        ));
+        conn.execute("DELETE FROM vrp_identities", []).unwrap();
+
+        let removed = delete_role(&conn, 6).unwrap();
+        assert_eq!(removed.label, "EXTERNAL_AUDITOR");
+        assert_eq!(
+            delete_role(&conn, 6).unwrap_err(),
+            IdentityError::RoleNotFound(6)
+        );
+    }
 }
@@ -0,0 +1,96 @@
+//! Helpers for anonymous poll voting.
+//!
+//! A vote is authorized by a proof from `vote.circom`, which shows Merkle
+//! membership of the voter's commitment and outputs a poll-scoped nullifier
+//! `Poseidon(sk, topicHash)`. Because the nullifier depends on the secret key
+//! rather than the (public) commitment, the server can enforce one vote per
+//! identity without being able to link a ballot back to a pseudonym.
+//!
+//! The topic and vote values are mapped into the BN254 scalar field as
+//! `sha256(label) mod r`, so clients and the server derive identical public
+//! inputs from plain strings.
+
+use crate::{poseidon::hash_inputs, IdentityError};
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use sha2::{Digest, Sha256};
+
+/// Prefix of the nullifier topic used for poll votes.
+pub const POLL_TOPIC_PREFIX: &str = "annex:poll:";
+
+/// Returns the nullifier topic for a poll: `annex:poll:<poll_id>`.
+pub fn poll_topic(poll_id: &str) -> String {
+    format!("{POLL_TOPIC_PREFIX}{poll_id}")
+}
+
+/// Maps an arbitrary label into the scalar field as `sha256(label) mod r`.
+pub fn hash_to_field(label: &str) -> Fr {
+    let digest = Sha256::digest(label.as_bytes());
+    Fr::from_be_bytes_mod_order(&digest)
+}
+
+/// Returns the `topicHash` public input for a poll.
+pub fn poll_topic_hash(poll_id: &str) -> Fr {
+    hash_to_field(&poll_topic(poll_id))
+}
+
+/// Returns the `voteHash` public input for choosing `option_index` in a poll.
+///
+/// The poll topic is part of the preimage so a vote hash from one poll is
+/// never a valid selection in another.
+pub fn poll_vote_hash(poll_id: &str, option_index: u32) -> Fr {
+    hash_to_field(&format!("{}:{option_index}", poll_topic(poll_id)))
+}
+
+/// Derives the poll nullifier `Poseidon(sk, topicHash)` exactly as the
+/// circuit does. Intended for clients and tests; the server only ever sees
+/// the nullifier as a public signal.
+///
+/// # Errors
+///
+/// Returns [`IdentityError::PoseidonError`] if hashing fails.
+pub fn derive_poll_nullifier(sk: Fr, poll_id: &str) -> Result<Fr, IdentityError> {
+    hash_inputs(&[sk, poll_topic_hash(poll_id)])
+}
+
+/// Encodes a field element as 64-character lowercase big-endian hex, the
+/// format stored in `zk_nullifiers.nullifier_hex`.
+pub fn field_to_hex(value: &Fr) -> String {
+    hex::encode(value.into_bigint().to_bytes_be())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk::parse_fr_from_hex;
+
+    #[test]
+    fn poll_topic_is_prefixed() {
+        assert_eq!(poll_topic("abc"), "annex:poll:abc");
+    }
+
+    #[test]
+    fn vote_hash_differs_per_option_and_poll() {
+        assert_ne!(poll_vote_hash("p1", 0), poll_vote_hash("p1", 1));
+        assert_ne!(poll_vote_hash("p1", 0), poll_vote_hash("p2", 0));
+        assert_ne!(poll_vote_hash("p1", 0), poll_topic_hash("p1"));
+    }
+
+    #[test]
+    fn nullifier_is_deterministic_and_poll_scoped() {
+        let sk = Fr::from(123456789u64);
+        let a = derive_poll_nullifier(sk, "p1").expect("hash should succeed");
+        let b = derive_poll_nullifier(sk, "p1").expect("hash should succeed");
+        let c = derive_poll_nullifier(sk, "p2").expect("hash should succeed");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn field_to_hex_round_trips() {
+        let value = poll_topic_hash("p1");
+        let hex = field_to_hex(&value);
+        assert_eq!(hex.len(), 64);
+        assert_eq!(parse_fr_from_hex(&hex).expect("valid hex"), value);
+    }
+}
@@ -87,6 +87,16 @@ pub enum EventPayload {
         role_code: u8,
     },
 
+    /// An identity was imported from another server via a signed bundle.
+    IdentityImported {
+        /// The hex-encoded commitment.
+        commitment_hex: String,
+        /// The role code of the registrant.
+        role_code: u8,
+        /// The base URL of the server that issued the bundle.
+        origin_server: String,
+    },
+
     /// A zero-knowledge membership proof was verified.
     IdentityVerified {
         /// The hex-encoded commitment whose membership was proved.
@@ -103,6 +113,28 @@ pub enum EventPayload {
         topic: String,
     },
 
+    /// A VRP topic was created, described, deprecated or deleted.
+    VrpTopicChanged {
+        /// The topic identifier.
+        topic: String,
+        /// The change ("created", "updated", "deprecated" or "deleted").
+        action: String,
+        /// The pseudonym of the moderator who made the change.
+        moderator_pseudonym: String,
+    },
+
+    /// A VRP role registry entry was created, relabeled or deleted.
+    VrpRoleChanged {
+        /// The numeric role code.
+        role_code: u8,
+        /// The role label after the change (before it, for deletions).
+        label: String,
+        /// The change ("created", "updated" or "deleted").
+        action: String,
+        /// The pseudonym of the moderator who made the change.
+        moderator_pseudonym: String,
+    },
+
     // ── Presence domain ──────────────────────────────────────────────
     /// A new node was added to the presence graph.
     NodeAdded {
@@ -141,6 +173,9 @@ pub enum EventPayload {
         alignment_status: String,
         /// The previous alignment status.
         previous_status: String,
+        /// The new transfer scope, when known.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transfer_scope: Option<String>,
     },
 
     /// A federation agreement was severed.
@@ -168,6 +203,9 @@ pub enum EventPayload {
         alignment_status: String,
         /// The previous alignment status.
         previous_status: String,
+        /// The new transfer scope, when known.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transfer_scope: Option<String>,
     },
 
     /// An agent was disconnected from the server.
@@ -197,8 +235,11 @@ impl EventPayload {
     pub fn event_type(&self) -> &'static str {
         match self {
             Self::IdentityRegistered { .. } => "IDENTITY_REGISTERED",
+            Self::IdentityImported { .. } => "IDENTITY_IMPORTED",
             Self::IdentityVerified { .. } => "IDENTITY_VERIFIED",
             Self::PseudonymDerived { .. } => "PSEUDONYM_DERIVED",
+            Self::VrpTopicChanged { .. } => "VRP_TOPIC_CHANGED",
+            Self::VrpRoleChanged { .. } => "VRP_ROLE_CHANGED",
             Self::NodeAdded { .. } => "NODE_ADDED",
             Self::NodePruned { .. } => "NODE_PRUNED",
             Self::NodeReactivated { .. } => "NODE_REACTIVATED",
@@ -217,8 +258,11 @@ impl EventPayload {
     pub fn entity_type(&self) -> &'static str {
         match self {
             Self::IdentityRegistered { .. }
+            | Self::IdentityImported { .. }
             | Self::IdentityVerified { .. }
             | Self::PseudonymDerived { .. } => "identity",
+            Self::VrpTopicChanged { .. } => "vrp_topic",
+            Self::VrpRoleChanged { .. } => "vrp_role",
             Self::NodeAdded { .. } | Self::NodePruned { .. } | Self::NodeReactivated { .. } => {
                 "node"
             }
@@ -236,8 +280,11 @@ impl EventPayload {
     pub fn domain(&self) -> EventDomain {
         match self {
             Self::IdentityRegistered { .. }
+            | Self::IdentityImported { .. }
             | Self::IdentityVerified { .. }
-            | Self::PseudonymDerived { .. } => EventDomain::Identity,
+            | Self::PseudonymDerived { .. }
+            | Self::VrpTopicChanged { .. }
+            | Self::VrpRoleChanged { .. } => EventDomain::Identity,
             Self::NodeAdded { .. } | Self::NodePruned { .. } | Self::NodeReactivated { .. } => {
                 EventDomain::Presence
             }
@@ -12,7 +12,7 @@
 //!
 //! | Domain | Example events |
 //! |--------|---------------|
-//! | `IDENTITY` | `IDENTITY_REGISTERED`, `IDENTITY_VERIFIED`, `PSEUDONYM_DERIVED` |
+//! | `IDENTITY` | `IDENTITY_REGISTERED`, `IDENTITY_IMPORTED`, `IDENTITY_VERIFIED`, `PSEUDONYM_DERIVED`, `VRP_TOPIC_CHANGED`, `VRP_ROLE_CHANGED` |
 //! | `PRESENCE` | `NODE_ADDED`, `NODE_PRUNED`, `NODE_REACTIVATED` |
 //! | `FEDERATION` | `FEDERATION_ESTABLISHED`, `FEDERATION_REALIGNED`, `FEDERATION_SEVERED` |
 //! | `AGENT` | `AGENT_CONNECTED`, `AGENT_REALIGNED`, `AGENT_DISCONNECTED` |
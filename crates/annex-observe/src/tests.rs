@@ -446,6 +446,15 @@ fn event_payload_type_and_domain_consistency() {
             EventDomain::Identity,
             "IDENTITY_REGISTERED",
         ),
+        (
+            EventPayload::IdentityImported {
+                commitment_hex: "0x1".to_string(),
+                role_code: 1,
+                origin_server: "https://origin.example".to_string(),
+            },
+            EventDomain::Identity,
+            "IDENTITY_IMPORTED",
+        ),
         (
             EventPayload::IdentityVerified {
                 commitment_hex: "0x1".to_string(),
@@ -462,6 +471,25 @@ fn event_payload_type_and_domain_consistency() {
             EventDomain::Identity,
             "PSEUDONYM_DERIVED",
         ),
+        (
+            EventPayload::VrpTopicChanged {
+                topic: "t".to_string(),
+                action: "created".to_string(),
+                moderator_pseudonym: "m".to_string(),
+            },
+            EventDomain::Identity,
+            "VRP_TOPIC_CHANGED",
+        ),
+        (
+            EventPayload::VrpRoleChanged {
+                role_code: 6,
+                label: "AUDITOR".to_string(),
+                action: "created".to_string(),
+                moderator_pseudonym: "m".to_string(),
+            },
+            EventDomain::Identity,
+            "VRP_ROLE_CHANGED",
+        ),
         (
             EventPayload::NodeAdded {
                 pseudonym_id: "p".to_string(),
@@ -497,6 +525,7 @@ fn event_payload_type_and_domain_consistency() {
                 remote_url: "u".to_string(),
                 alignment_status: "PARTIAL".to_string(),
                 previous_status: "ALIGNED".to_string(),
+                transfer_scope: None,
             },
             EventDomain::Federation,
             "FEDERATION_REALIGNED",
@@ -522,6 +551,7 @@ fn event_payload_type_and_domain_consistency() {
                 pseudonym_id: "a".to_string(),
                 alignment_status: "PARTIAL".to_string(),
                 previous_status: "ALIGNED".to_string(),
+                transfer_scope: Some("REFLECTION_SUMMARIES_ONLY".to_string()),
             },
             EventDomain::Agent,
             "AGENT_REALIGNED",
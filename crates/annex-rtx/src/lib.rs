@@ -36,8 +36,8 @@ pub mod validation;
 pub use error::RtxError;
 pub use types::{BundleProvenance, ReflectionSummaryBundle, RtxSubscription};
 pub use validation::{
-    bundle_signing_payload, check_redacted_topics, enforce_transfer_scope,
-    validate_bundle_structure,
+    bundle_signing_payload, check_redacted_topics, enforce_transfer_scope, redacted_topic_matches,
+    redacted_topics_note, validate_bundle_structure,
 };
 
 #[cfg(test)]
@@ -193,6 +193,26 @@ mod tests {
         assert!(matches!(err, RtxError::RedactedTopic(ref t) if t == "rust"));
     }
 
+    #[test]
+    fn test_redacted_topic_matches_lists_every_overlap() {
+        let bundle = make_test_bundle();
+        let redacted = vec![
+            "cryptography".to_string(),
+            "rust".to_string(),
+            "politics".to_string(),
+        ];
+        let matches = redacted_topic_matches(&bundle, &redacted);
+        assert_eq!(
+            matches,
+            vec!["rust".to_string(), "cryptography".to_string()]
+        );
+        assert_eq!(
+            redacted_topics_note(&matches),
+            "redacted_topics:rust,cryptography"
+        );
+        assert!(redacted_topic_matches(&bundle, &[]).is_empty());
+    }
+
     // -----------------------------------------------------------------------
     // Bundle validation tests
     // -----------------------------------------------------------------------
@@ -51,6 +51,26 @@ pub fn check_redacted_topics(
     Ok(())
 }
 
+/// Returns the bundle's `domain_tags` that appear in `redacted_topics`,
+/// in tag order without duplicates. Empty when the bundle may cross.
+pub fn redacted_topic_matches(
+    bundle: &ReflectionSummaryBundle,
+    redacted_topics: &[String],
+) -> Vec<String> {
+    let mut matches: Vec<String> = Vec::new();
+    for tag in &bundle.domain_tags {
+        if redacted_topics.contains(tag) && !matches.contains(tag) {
+            matches.push(tag.clone());
+        }
+    }
+    matches
+}
+
+/// Formats withheld topics for `rtx_transfer_log.redactions_applied`.
+pub fn redacted_topics_note(topics: &[String]) -> String {
+    format!("redacted_topics:{}", topics.join(","))
+}
+
 /// Validates that a bundle has all required fields populated.
 ///
 /// This performs structural validation only — it does not verify
@@ -3,9 +3,11 @@
 use crate::AppState;
 use annex_graph::{ensure_graph_node, role_code_to_node_type};
 use annex_identity::{
-    create_platform_identity, derive_nullifier_hex, derive_pseudonym_id, ensure_founder,
-    get_all_roles, get_all_topics, get_path_for_commitment, get_platform_identity,
+    create_platform_identity, derive_nullifier_hex, derive_pseudonym_id,
+    disclosure::{role_context, verify_role_disclosure, DisclosureError},
+    ensure_founder, get_all_roles, get_all_topics, get_path_for_commitment, get_platform_identity,
     insert_nullifier, register_identity,
+    vote::field_to_hex,
     zk::{parse_fr_from_hex, parse_proof, parse_public_signals, verify_proof},
     Capabilities, PlatformIdentity, RoleCode, VrpRoleEntry, VrpTopic,
 };
@@ -121,15 +123,48 @@ pub struct VerifyMembershipResponse {
     pub pseudonym_id: String,
 }
 
+/// Request body for `POST /api/zk/verify-role`.
+///
+/// Verifies a selective-disclosure proof that the prover's role is one of
+/// `roles`, bound to a verifier-chosen `context` string. Nothing about the
+/// prover's commitment or exact role is revealed.
+#[derive(Debug, Deserialize)]
+pub struct VerifyRoleRequest {
+    /// The role set the proof must disclose membership in.
+    pub roles: Vec<RoleCode>,
+    /// The verifier-chosen context (e.g. a challenge nonce).
+    pub context: String,
+    /// The Groth16 proof (JSON object) from `role.circom`.
+    pub proof: serde_json::Value,
+    /// The public signals: `[root, nullifier, allowedRoles[0..5], contextHash]`.
+    #[serde(rename = "publicSignals")]
+    pub public_signals: Vec<String>,
+}
+
+/// Response body for successful role verification.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyRoleResponse {
+    /// Whether verification succeeded.
+    pub ok: bool,
+    /// The Merkle root the proof was generated against.
+    pub root: String,
+    /// The context-scoped nullifier `Poseidon(sk, contextHash)`, as hex. It
+    /// is the same whenever one identity proves in one context, so the
+    /// challenger can bind it to whoever presented the proof, since this
+    /// endpoint does not know who that is.
+    pub nullifier: String,
+}
+
 /// Response body for identity query.
+///
+/// The participant type is deliberately absent: channels gate on roles
+/// through selective-disclosure proofs, which would reveal nothing if anyone
+/// could look up a pseudonym's role here.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetIdentityResponse {
     /// The pseudonym ID.
     #[serde(rename = "pseudonymId")]
     pub pseudonym_id: String,
-    /// The participant type (role).
-    #[serde(rename = "participantType")]
-    pub participant_type: RoleCode,
     /// Whether the identity is active.
     pub active: bool,
     /// Capability flags.
@@ -331,7 +366,12 @@ pub async fn verify_membership_handler(
             .get()
             .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
 
-        // 1. Verify root exists and is active/valid
+        // 1. Refuse topics that have been deprecated, then verify the root
+        // exists and is active/valid
+        annex_identity::ensure_topic_not_deprecated(&conn, &payload.topic).map_err(|e| match e {
+            annex_identity::IdentityError::TopicDeprecated(_) => ApiError::Conflict(e.to_string()),
+            _ => ApiError::InternalServerError(format!("db query failed: {}", e)),
+        })?;
         let root_exists: bool = conn
             .query_row(
                 "SELECT COUNT(*) FROM vrp_roots WHERE root_hex = ?1",
@@ -534,6 +574,18 @@ pub async fn verify_membership_handler(
             ApiError::InternalServerError(format!("failed to create platform identity: {}", e))
         })?;
 
+        // Restore profile data carried over in an imported identity bundle
+        crate::api_identity_bundle::restore_imported_profile(
+            &tx,
+            &state,
+            &payload.commitment,
+            &payload.topic,
+            &pseudonym_id,
+        )
+        .map_err(|e| {
+            ApiError::InternalServerError(format!("failed to restore imported profile: {}", e))
+        })?;
+
         // Create/Update Graph Node
         ensure_graph_node(&tx, server_id, &pseudonym_id, node_type, metadata_json).map_err(|e| {
             ApiError::InternalServerError(format!("failed to ensure graph node: {}", e))
@@ -574,6 +626,68 @@ pub async fn verify_membership_handler(
     }))
 }
 
+/// Handler for `POST /api/zk/verify-role`.
+pub async fn verify_role_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Json(payload): Json<VerifyRoleRequest>,
+) -> Result<Json<VerifyRoleResponse>, ApiError> {
+    let result = tokio::task::spawn_blocking(move || {
+        let proof_json = serde_json::to_string(&payload.proof)
+            .map_err(|e| ApiError::BadRequest(format!("failed to serialize proof: {}", e)))?;
+        let proof = parse_proof(&proof_json)
+            .map_err(|e| ApiError::BadRequest(format!("invalid proof format: {}", e)))?;
+        let signals_json = serde_json::to_string(&payload.public_signals).map_err(|e| {
+            ApiError::BadRequest(format!("failed to serialize public signals: {}", e))
+        })?;
+        let signals = parse_public_signals(&signals_json)
+            .map_err(|e| ApiError::BadRequest(format!("invalid public signals format: {}", e)))?;
+
+        // 1. The proven root must be one this server has published.
+        let root_hex = signals
+            .first()
+            .map(field_to_hex)
+            .ok_or_else(|| ApiError::BadRequest("missing public signals".to_string()))?;
+
+        let conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+        let root_exists = crate::middleware::is_local_root(&conn, &root_hex)
+            .map_err(ApiError::InternalServerError)?;
+        if !root_exists {
+            return Err(ApiError::Conflict(format!(
+                "stale or invalid root: {}",
+                root_hex
+            )));
+        }
+
+        // 2. Check the disclosed role set and context, then the proof itself.
+        let disclosure = verify_role_disclosure(
+            &state.role_vkey,
+            &proof,
+            &signals,
+            &payload.roles,
+            role_context(&payload.context),
+        )
+        .map_err(|e| match e {
+            DisclosureError::InvalidProof | DisclosureError::Zk(_) => {
+                ApiError::Unauthorized(format!("proof verification failed: {}", e))
+            }
+            _ => ApiError::BadRequest(e.to_string()),
+        })?;
+
+        Ok(VerifyRoleResponse {
+            ok: true,
+            root: root_hex,
+            nullifier: field_to_hex(&disclosure.nullifier),
+        })
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(result))
+}
+
 /// Handler for `GET /api/registry/topics`.
 pub async fn get_topics_handler(
     Extension(state): Extension<Arc<AppState>>,
@@ -687,7 +801,6 @@ pub async fn get_identity_handler(
 
     Ok(Json(GetIdentityResponse {
         pseudonym_id: result.pseudonym_id,
-        participant_type: result.participant_type,
         active: result.active,
         capabilities: Capabilities {
             can_voice: result.can_voice,
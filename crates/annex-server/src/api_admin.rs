@@ -1,11 +1,19 @@
 //! Admin API handlers for the Annex server.
 
 use crate::{
-    api::ApiError, middleware::IdentityContext, policy::recalculate_all_alignments, AppState,
+    api::ApiError,
+    api_vrp::{reputation_config, reputation_error},
+    middleware::IdentityContext,
+    policy::{preview_policy_change, recalculate_all_alignments, PolicyImpact},
+    AppState,
+};
+use annex_identity::{
+    create_role, create_topic, delete_role, delete_topic, deprecate_topic, update_capabilities,
+    update_role_label, update_topic_description, IdentityError,
 };
-use annex_identity::update_capabilities;
 use annex_observe::EventPayload;
 use annex_types::{Capabilities, ServerPolicy};
+use annex_vrp::{compute_reputation, record_reputation_signal, ReputationSignalKind};
 use axum::{
     extract::{Extension, Json, Path},
     response::{IntoResponse, Response},
@@ -37,6 +45,42 @@ pub async fn get_policy_handler(
     Ok(AxumJson(policy).into_response())
 }
 
+/// Handler for `POST /api/admin/policy/dry-run`.
+///
+/// Evaluates a proposed policy against the stored agent and federation
+/// handshakes and reports which alignments, transfer scopes and channel
+/// admissions would change. Nothing is persisted and no events are emitted.
+///
+/// Requires `can_moderate` permission.
+pub async fn policy_dry_run_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Json(proposed_policy): Json<ServerPolicy>,
+) -> Result<AxumJson<PolicyImpact>, ApiError> {
+    if !identity.can_moderate {
+        return Err(ApiError::Forbidden(
+            "insufficient permissions to preview policy changes".to_string(),
+        ));
+    }
+
+    let impact = tokio::task::spawn_blocking(move || {
+        let conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+        preview_policy_change(
+            &conn,
+            state.server_id,
+            &proposed_policy,
+            state.embedder.as_ref(),
+        )
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(AxumJson(impact))
+}
+
 /// Handler for `PUT /api/admin/policy`.
 ///
 /// Updates the server's policy, persists it to the database, logs the version,
@@ -480,3 +524,312 @@ pub async fn update_member_capabilities_handler(
 
     Ok(AxumJson(serde_json::json!({ "status": "ok" })).into_response())
 }
+
+/// Request body for `POST /api/admin/vrp/topics`.
+#[derive(Debug, Deserialize)]
+pub struct CreateTopicRequest {
+    pub topic: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Request body for `PATCH /api/admin/vrp/topics/{topic}`.
+#[derive(Debug, Deserialize)]
+pub struct UpdateTopicRequest {
+    pub description: String,
+}
+
+/// Request body for `POST /api/admin/vrp/roles`.
+#[derive(Debug, Deserialize)]
+pub struct CreateRoleRequest {
+    pub role_code: u8,
+    pub label: String,
+}
+
+/// Request body for `PATCH /api/admin/vrp/roles/{roleCode}`.
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub label: String,
+}
+
+/// Maps VRP registry errors to API errors.
+fn registry_error(e: IdentityError) -> ApiError {
+    match e {
+        IdentityError::InvalidRegistryEntry(_)
+        | IdentityError::InvalidRoleCode(_)
+        | IdentityError::InvalidRoleLabel(_) => ApiError::BadRequest(e.to_string()),
+        IdentityError::TopicNotFound(_) | IdentityError::RoleNotFound(_) => {
+            ApiError::NotFound(e.to_string())
+        }
+        IdentityError::TopicExists(_)
+        | IdentityError::TopicDeprecated(_)
+        | IdentityError::TopicInUse { .. }
+        | IdentityError::RoleExists(_)
+        | IdentityError::RoleInUse(_) => ApiError::Conflict(e.to_string()),
+        _ => ApiError::InternalServerError(format!("registry update failed: {}", e)),
+    }
+}
+
+/// Runs a VRP registry change for a moderator and emits the observe event
+/// it produces.
+async fn run_registry_change<T, F>(
+    state: Arc<AppState>,
+    identity: &annex_identity::PlatformIdentity,
+    change: F,
+) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&rusqlite::Connection, &str) -> Result<(T, EventPayload), IdentityError>
+        + Send
+        + 'static,
+{
+    if !identity.can_moderate {
+        return Err(ApiError::Forbidden(
+            "insufficient permissions to manage the VRP registry".to_string(),
+        ));
+    }
+
+    let moderator = identity.pseudonym_id.clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+
+        let (result, observe_payload) = change(&conn, &moderator).map_err(registry_error)?;
+        let entity_id = match &observe_payload {
+            EventPayload::VrpTopicChanged { topic, .. } => topic.clone(),
+            EventPayload::VrpRoleChanged { role_code, .. } => role_code.to_string(),
+            _ => moderator.clone(),
+        };
+        crate::emit_and_broadcast(
+            &conn,
+            state.server_id,
+            &entity_id,
+            &observe_payload,
+            &state.observe_tx,
+        );
+
+        Ok::<T, ApiError>(result)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))?
+}
+
+fn topic_event(topic: &str, action: &str, moderator: &str) -> EventPayload {
+    EventPayload::VrpTopicChanged {
+        topic: topic.to_string(),
+        action: action.to_string(),
+        moderator_pseudonym: moderator.to_string(),
+    }
+}
+
+fn role_event(role_code: u8, label: &str, action: &str, moderator: &str) -> EventPayload {
+    EventPayload::VrpRoleChanged {
+        role_code,
+        label: label.to_string(),
+        action: action.to_string(),
+        moderator_pseudonym: moderator.to_string(),
+    }
+}
+
+/// Handler for `POST /api/admin/vrp/topics`.
+///
+/// Registers a new VRP topic. Requires `can_moderate` permission.
+pub async fn create_topic_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Json(body): Json<CreateTopicRequest>,
+) -> Result<Response, ApiError> {
+    let topic = run_registry_change(state, &identity, move |conn, moderator| {
+        let topic = create_topic(conn, body.topic.trim(), body.description.trim())?;
+        let event = topic_event(&topic.topic, "created", moderator);
+        Ok((topic, event))
+    })
+    .await?;
+
+    Ok(AxumJson(topic).into_response())
+}
+
+/// Handler for `PATCH /api/admin/vrp/topics/{topic}`.
+///
+/// Replaces a topic's description. Requires `can_moderate` permission.
+pub async fn update_topic_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(topic): Path<String>,
+    Json(body): Json<UpdateTopicRequest>,
+) -> Result<Response, ApiError> {
+    let topic = run_registry_change(state, &identity, move |conn, moderator| {
+        let topic = update_topic_description(conn, &topic, body.description.trim())?;
+        let event = topic_event(&topic.topic, "updated", moderator);
+        Ok((topic, event))
+    })
+    .await?;
+
+    Ok(AxumJson(topic).into_response())
+}
+
+/// Handler for `POST /api/admin/vrp/topics/{topic}/deprecate`.
+///
+/// Marks a topic as deprecated without removing it, so existing channel
+/// bindings keep resolving. Requires `can_moderate` permission.
+pub async fn deprecate_topic_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(topic): Path<String>,
+) -> Result<Response, ApiError> {
+    let topic = run_registry_change(state, &identity, move |conn, moderator| {
+        let topic = deprecate_topic(conn, &topic)?;
+        let event = topic_event(&topic.topic, "deprecated", moderator);
+        Ok((topic, event))
+    })
+    .await?;
+
+    Ok(AxumJson(topic).into_response())
+}
+
+/// Handler for `DELETE /api/admin/vrp/topics/{topic}`.
+///
+/// Deletes a topic. Fails with 409 while any channel's `vrp_topic_binding`
+/// references it. Requires `can_moderate` permission.
+pub async fn delete_topic_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(topic): Path<String>,
+) -> Result<Response, ApiError> {
+    run_registry_change(state, &identity, move |conn, moderator| {
+        delete_topic(conn, &topic)?;
+        Ok(((), topic_event(&topic, "deleted", moderator)))
+    })
+    .await?;
+
+    Ok(AxumJson(serde_json::json!({ "status": "deleted" })).into_response())
+}
+
+/// Handler for `POST /api/admin/vrp/roles`.
+///
+/// Registers a new VRP role entry. Requires `can_moderate` permission.
+pub async fn create_role_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Json(body): Json<CreateRoleRequest>,
+) -> Result<Response, ApiError> {
+    let role = run_registry_change(state, &identity, move |conn, moderator| {
+        let role = create_role(conn, body.role_code, body.label.trim())?;
+        let event = role_event(role.role_code, &role.label, "created", moderator);
+        Ok((role, event))
+    })
+    .await?;
+
+    Ok(AxumJson(role).into_response())
+}
+
+/// Handler for `PATCH /api/admin/vrp/roles/{roleCode}`.
+///
+/// Relabels a custom role. Built-in roles are fixed. Requires
+/// `can_moderate` permission.
+pub async fn update_role_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(role_code): Path<u8>,
+    Json(body): Json<UpdateRoleRequest>,
+) -> Result<Response, ApiError> {
+    let role = run_registry_change(state, &identity, move |conn, moderator| {
+        let role = update_role_label(conn, role_code, body.label.trim())?;
+        let event = role_event(role.role_code, &role.label, "updated", moderator);
+        Ok((role, event))
+    })
+    .await?;
+
+    Ok(AxumJson(role).into_response())
+}
+
+/// Handler for `DELETE /api/admin/vrp/roles/{roleCode}`.
+///
+/// Deletes a custom role that no registered identity holds. Requires
+/// `can_moderate` permission.
+pub async fn delete_role_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(role_code): Path<u8>,
+) -> Result<Response, ApiError> {
+    run_registry_change(state, &identity, move |conn, moderator| {
+        let role = delete_role(conn, role_code)?;
+        let event = role_event(role.role_code, &role.label, "deleted", moderator);
+        Ok(((), event))
+    })
+    .await?;
+
+    Ok(AxumJson(serde_json::json!({ "status": "deleted" })).into_response())
+}
+
+/// Request body for `POST /api/admin/vrp/reputation/{peer}/incidents`.
+#[derive(Debug, Deserialize)]
+pub struct FlagIncidentRequest {
+    /// What the peer did.
+    pub note: String,
+}
+
+/// Handler for `POST /api/admin/vrp/reputation/{peer}/incidents`.
+///
+/// Flags an incident against an agent pseudonym or federation peer base
+/// URL, which weighs more heavily on its reputation than a handshake
+/// conflict. Emits a `MODERATION_ACTION` event. Requires `can_moderate`
+/// permission.
+pub async fn flag_incident_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(peer): Path<String>,
+    Json(body): Json<FlagIncidentRequest>,
+) -> Result<Response, ApiError> {
+    if !identity.can_moderate {
+        return Err(ApiError::Forbidden(
+            "insufficient permissions to flag incidents".to_string(),
+        ));
+    }
+    let note = body.note.trim().to_string();
+    if note.is_empty() {
+        return Err(ApiError::BadRequest("note must not be empty".to_string()));
+    }
+
+    let moderator = identity.pseudonym_id.clone();
+    let summary = tokio::task::spawn_blocking(move || {
+        let conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+
+        record_reputation_signal(
+            &conn,
+            state.server_id,
+            &peer,
+            ReputationSignalKind::Incident,
+            &moderator,
+            None,
+            Some(&note),
+        )
+        .map_err(reputation_error)?;
+
+        let observe_payload = EventPayload::ModerationAction {
+            moderator_pseudonym: moderator.clone(),
+            action_type: "flag_incident".to_string(),
+            target_pseudonym: Some(peer.clone()),
+            description: note,
+        };
+        crate::emit_and_broadcast(
+            &conn,
+            state.server_id,
+            &peer,
+            &observe_payload,
+            &state.observe_tx,
+        );
+
+        let config = reputation_config(&state)?;
+        compute_reputation(&conn, state.server_id, &peer, &config).map_err(reputation_error)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(AxumJson(summary).into_response())
+}
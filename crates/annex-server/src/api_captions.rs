@@ -0,0 +1,449 @@
+//! Live captions for voice channels.
+//!
+//! When a moderator enables captions on a `Voice` or `Hybrid` channel,
+//! speech from participants who consented is transcribed by a per-channel
+//! [`StreamingTranscriber`] and broadcast to the channel's WebSocket
+//! subscribers as `caption` frames. Agents' voice intents are captioned from
+//! their text. The server has no WebRTC media path, so participants' clients
+//! post their own microphone PCM to
+//! `POST /api/channels/{channelId}/captions/audio`.
+//!
+//! With `persist` set, final captions are also stored as channel messages
+//! and expire with the channel's `retention_days`.
+
+use crate::api::ApiError;
+use crate::api_ws::{ConnectionManager, OutgoingMessage, WsCaptionPayload};
+use crate::middleware::IdentityContext;
+use crate::AppState;
+use annex_channels::{
+    create_message, get_caption_settings, get_channel, has_caption_consent, is_member,
+    set_caption_consent, set_caption_settings, CaptionSettings, ChannelError, CreateMessageParams,
+};
+use annex_db::DbPool;
+use annex_voice::{StreamingTranscriber, TranscriptionEvent, VadConfig};
+use axum::body::Bytes;
+use axum::extract::{Extension, Json, Path, Query};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+
+/// Maximum PCM accepted per audio request (1 MiB, about 32 s at 16 kHz).
+const MAX_CAPTION_AUDIO_BYTES: usize = 1024 * 1024;
+
+/// Transcription events buffered per channel before the forwarder lags.
+const CAPTION_EVENT_BUFFER: usize = 256;
+
+/// Running caption pipelines, keyed by channel.
+#[derive(Debug, Clone, Default)]
+pub struct CaptionHub {
+    sessions: Arc<Mutex<HashMap<String, Arc<CaptionSession>>>>,
+}
+
+/// A channel's caption pipeline. Dropping it stops captioning at once,
+/// including segments still being transcribed.
+#[derive(Debug)]
+struct CaptionSession {
+    transcriber: StreamingTranscriber,
+    forwarder: JoinHandle<()>,
+}
+
+impl Drop for CaptionSession {
+    fn drop(&mut self) {
+        self.forwarder.abort();
+    }
+}
+
+impl CaptionHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the channel's pipeline, starting one with `settings`.
+    async fn session(
+        &self,
+        state: &AppState,
+        channel_id: &str,
+        settings: CaptionSettings,
+    ) -> Arc<CaptionSession> {
+        let mut sessions = self.sessions.lock().await;
+        if let Some(session) = sessions.get(channel_id) {
+            return session.clone();
+        }
+
+        let (tx, rx) = broadcast::channel(CAPTION_EVENT_BUFFER);
+        let transcriber = StreamingTranscriber::new(
+            state.stt_service.clone(),
+            VadConfig::default(),
+            channel_id,
+            tx,
+        );
+        let forwarder = tokio::spawn(forward_captions(
+            state.pool.clone(),
+            state.connection_manager.clone(),
+            settings.persist,
+            rx,
+        ));
+        let session = Arc::new(CaptionSession {
+            transcriber,
+            forwarder,
+        });
+        sessions.insert(channel_id.to_string(), session.clone());
+        session
+    }
+
+    /// Stops captioning a channel, discarding speech not yet captioned.
+    /// The next audio restarts the pipeline with the current settings.
+    pub async fn stop(&self, channel_id: &str) {
+        self.sessions.lock().await.remove(channel_id);
+    }
+
+    /// Discards a speaker's speech not yet captioned in a channel, leaving
+    /// the rest of the channel's pipeline running.
+    pub async fn discard_speaker(&self, channel_id: &str, speaker: &str) {
+        let session = self.sessions.lock().await.get(channel_id).cloned();
+        if let Some(session) = session {
+            session.transcriber.discard(speaker).await;
+        }
+    }
+}
+
+/// Broadcasts a channel's transcriptions as captions, persisting final ones
+/// when `persist` is set.
+async fn forward_captions(
+    pool: DbPool,
+    connections: ConnectionManager,
+    persist: bool,
+    mut events: broadcast::Receiver<TranscriptionEvent>,
+) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!(skipped, "caption forwarder lagged");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        let message_id = if persist && event.is_final {
+            persist_caption(
+                &pool,
+                &event.channel_id,
+                &event.speaker_pseudonym,
+                &event.text,
+            )
+            .await
+        } else {
+            None
+        };
+        broadcast_caption(
+            &connections,
+            WsCaptionPayload {
+                channel_id: event.channel_id,
+                speaker_pseudonym: event.speaker_pseudonym,
+                text: event.text,
+                is_final: event.is_final,
+                segment_id: Some(event.segment_id),
+                start_ms: Some(event.start_ms),
+                end_ms: Some(event.end_ms),
+                message_id,
+            },
+        )
+        .await;
+    }
+}
+
+/// Stores a final caption as a message from the speaker, returning its ID.
+async fn persist_caption(
+    pool: &DbPool,
+    channel_id: &str,
+    speaker: &str,
+    text: &str,
+) -> Option<String> {
+    let pool = pool.clone();
+    let params = CreateMessageParams {
+        channel_id: channel_id.to_string(),
+        message_id: uuid::Uuid::new_v4().to_string(),
+        sender_pseudonym: speaker.to_string(),
+        content: text.to_string(),
+        reply_to_message_id: None,
+    };
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = pool.get().map_err(|e| e.to_string())?;
+        create_message(&conn, &params).map_err(|e| e.to_string())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(message)) => Some(message.message_id),
+        Ok(Err(e)) => {
+            tracing::error!(channel_id = %channel_id, "failed to persist caption: {}", e);
+            None
+        }
+        Err(e) => {
+            tracing::error!("persist caption task failed: {}", e);
+            None
+        }
+    }
+}
+
+async fn broadcast_caption(connections: &ConnectionManager, caption: WsCaptionPayload) {
+    let channel_id = caption.channel_id.clone();
+    match serde_json::to_string(&OutgoingMessage::Caption(caption)) {
+        Ok(json) => connections.broadcast(&channel_id, json).await,
+        Err(e) => {
+            tracing::error!(channel_id = %channel_id, "failed to serialize caption: {}", e)
+        }
+    }
+}
+
+/// Captions an agent's voice intent from its text, if the channel has
+/// captions enabled and the agent consented.
+pub(crate) async fn caption_agent_speech(
+    state: &AppState,
+    channel_id: &str,
+    pseudonym: &str,
+    text: &str,
+) {
+    let pool = state.pool.clone();
+    let cid = channel_id.to_string();
+    let pid = pseudonym.to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = pool.get().map_err(|e| e.to_string())?;
+        let settings = get_caption_settings(&conn, &cid).map_err(|e| e.to_string())?;
+        let consented = has_caption_consent(&conn, &cid, &pid).map_err(|e| e.to_string())?;
+        Ok::<_, String>((settings, consented))
+    })
+    .await;
+
+    let settings = match result {
+        Ok(Ok((settings, true))) if settings.enabled => settings,
+        Ok(Ok(_)) => return,
+        Ok(Err(e)) => {
+            tracing::warn!(channel_id = %channel_id, "caption settings lookup failed: {}", e);
+            return;
+        }
+        Err(e) => {
+            tracing::error!("caption settings task failed: {}", e);
+            return;
+        }
+    };
+
+    let message_id = if settings.persist {
+        persist_caption(&state.pool, channel_id, pseudonym, text).await
+    } else {
+        None
+    };
+    broadcast_caption(
+        &state.connection_manager,
+        WsCaptionPayload {
+            channel_id: channel_id.to_string(),
+            speaker_pseudonym: pseudonym.to_string(),
+            text: text.to_string(),
+            is_final: true,
+            segment_id: None,
+            start_ms: None,
+            end_ms: None,
+            message_id,
+        },
+    )
+    .await;
+}
+
+/// A channel's caption settings and the requester's consent.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CaptionSettingsResponse {
+    #[serde(flatten)]
+    pub settings: CaptionSettings,
+    /// Whether the requester consented to having their speech captioned.
+    pub consented: bool,
+}
+
+/// Request body for `PUT /api/channels/{channelId}/captions/consent`.
+#[derive(Debug, Deserialize)]
+pub struct CaptionConsentRequest {
+    pub consent: bool,
+}
+
+/// Query parameters for `POST /api/channels/{channelId}/captions/audio`.
+#[derive(Debug, Deserialize)]
+pub struct CaptionAudioParams {
+    /// Marks the end of the requester's speech, finalizing the utterance in
+    /// progress.
+    #[serde(default)]
+    pub end: bool,
+}
+
+fn channel_err(e: ChannelError) -> ApiError {
+    match e {
+        ChannelError::NotFound(id) => ApiError::NotFound(format!("channel not found: {}", id)),
+        ChannelError::CaptionsUnsupported(id) => {
+            ApiError::BadRequest(format!("channel does not support captions: {}", id))
+        }
+        other => ApiError::InternalServerError(format!("caption operation failed: {}", other)),
+    }
+}
+
+fn db_err(e: impl std::fmt::Display) -> ApiError {
+    ApiError::InternalServerError(format!("db error: {}", e))
+}
+
+/// Checks the channel exists and the requester is a member.
+fn ensure_member(
+    conn: &rusqlite::Connection,
+    server_id: i64,
+    channel_id: &str,
+    pseudonym: &str,
+) -> Result<(), ApiError> {
+    get_channel(conn, channel_id).map_err(channel_err)?;
+    if !is_member(conn, server_id, channel_id, pseudonym).map_err(db_err)? {
+        return Err(ApiError::Forbidden("not a channel member".to_string()));
+    }
+    Ok(())
+}
+
+fn settings_response(
+    conn: &rusqlite::Connection,
+    channel_id: &str,
+    pseudonym: &str,
+) -> Result<CaptionSettingsResponse, ApiError> {
+    Ok(CaptionSettingsResponse {
+        settings: get_caption_settings(conn, channel_id).map_err(channel_err)?,
+        consented: has_caption_consent(conn, channel_id, pseudonym).map_err(channel_err)?,
+    })
+}
+
+/// Handler for `GET /api/channels/{channelId}/captions`.
+pub async fn get_captions_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(channel_id): Path<String>,
+) -> Result<Json<CaptionSettingsResponse>, ApiError> {
+    let response = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(db_err)?;
+        if !identity.can_moderate {
+            ensure_member(&conn, state.server_id, &channel_id, &identity.pseudonym_id)?;
+        }
+        settings_response(&conn, &channel_id, &identity.pseudonym_id)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(response))
+}
+
+/// Handler for `PUT /api/channels/{channelId}/captions`. Moderators only.
+pub async fn update_captions_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(channel_id): Path<String>,
+    Json(settings): Json<CaptionSettings>,
+) -> Result<Json<CaptionSettingsResponse>, ApiError> {
+    if !identity.can_moderate {
+        return Err(ApiError::Forbidden(
+            "only moderators can change caption settings".to_string(),
+        ));
+    }
+
+    let task_state = state.clone();
+    let cid = channel_id.clone();
+    let response = tokio::task::spawn_blocking(move || {
+        let conn = task_state.pool.get().map_err(db_err)?;
+        set_caption_settings(&conn, &cid, &settings).map_err(channel_err)?;
+        settings_response(&conn, &cid, &identity.pseudonym_id)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    // Restart the pipeline so it picks up the new settings.
+    state.captions.stop(&channel_id).await;
+
+    Ok(Json(response))
+}
+
+/// Handler for `PUT /api/channels/{channelId}/captions/consent`.
+///
+/// Withdrawing consent also discards the requester's speech not yet
+/// captioned.
+pub async fn set_caption_consent_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(channel_id): Path<String>,
+    Json(payload): Json<CaptionConsentRequest>,
+) -> Result<Json<CaptionSettingsResponse>, ApiError> {
+    let task_state = state.clone();
+    let cid = channel_id.clone();
+    let pid = identity.pseudonym_id.clone();
+    let response = tokio::task::spawn_blocking(move || {
+        let conn = task_state.pool.get().map_err(db_err)?;
+        ensure_member(&conn, task_state.server_id, &cid, &pid)?;
+        set_caption_consent(&conn, task_state.server_id, &cid, &pid, payload.consent)
+            .map_err(channel_err)?;
+        settings_response(&conn, &cid, &pid)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    if !response.consented {
+        state
+            .captions
+            .discard_speaker(&channel_id, &identity.pseudonym_id)
+            .await;
+    }
+
+    Ok(Json(response))
+}
+
+/// Handler for `POST /api/channels/{channelId}/captions/audio`.
+///
+/// Accepts the next chunk of the requester's own speech as raw PCM (s16le,
+/// 16 kHz mono). Captions follow asynchronously on the channel's WebSocket.
+pub async fn caption_audio_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(channel_id): Path<String>,
+    Query(params): Query<CaptionAudioParams>,
+    body: Bytes,
+) -> Result<StatusCode, ApiError> {
+    if body.len() > MAX_CAPTION_AUDIO_BYTES {
+        return Err(ApiError::BadRequest(format!(
+            "audio exceeds maximum size of {} bytes",
+            MAX_CAPTION_AUDIO_BYTES
+        )));
+    }
+
+    let task_state = state.clone();
+    let cid = channel_id.clone();
+    let pid = identity.pseudonym_id.clone();
+    let settings = tokio::task::spawn_blocking(move || {
+        let conn = task_state.pool.get().map_err(db_err)?;
+        ensure_member(&conn, task_state.server_id, &cid, &pid)?;
+        let settings = get_caption_settings(&conn, &cid).map_err(channel_err)?;
+        if !settings.enabled {
+            return Err(ApiError::Conflict(
+                "captions are not enabled for this channel".to_string(),
+            ));
+        }
+        if !has_caption_consent(&conn, &cid, &pid).map_err(channel_err)? {
+            return Err(ApiError::Forbidden(
+                "caption consent has not been given".to_string(),
+            ));
+        }
+        Ok(settings)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    let session = state.captions.session(&state, &channel_id, settings).await;
+    let speaker = &identity.pseudonym_id;
+    let result = match session.transcriber.push(speaker, &body).await {
+        Ok(()) if params.end => session.transcriber.end_speech(speaker).await,
+        other => other,
+    };
+    result.map_err(|e| ApiError::InternalServerError(format!("captioning failed: {}", e)))?;
+
+    Ok(StatusCode::ACCEPTED)
+}
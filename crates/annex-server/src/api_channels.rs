@@ -1,13 +1,14 @@
 use crate::api_federation::find_commitment_for_pseudonym;
-use crate::middleware::{verify_zk_membership_header, IdentityContext};
+use crate::middleware::{verify_role_proof_header, verify_zk_membership_header, IdentityContext};
 use crate::AppState;
 use annex_channels::{
     add_member, create_channel, delete_channel, get_channel, get_edit_history, is_member,
     list_channels, list_messages, remove_member, Channel, CreateChannelParams, Message,
     MessageEdit,
 };
-use annex_graph::{create_edge, delete_edge};
+use annex_identity::disclosure::channel_role_context;
 use annex_types::{AlignmentStatus, ChannelType, EdgeKind, FederationScope, RoleCode};
+use annex_vrp::compute_reputation;
 use axum::{
     extract::{Extension, Path, Query},
     http::StatusCode,
@@ -59,6 +60,14 @@ fn channel_err_to_status(e: annex_channels::ChannelError) -> StatusCode {
     }
 }
 
+/// Parses a channel's `required_roles_json`, returning `None` if it is not a
+/// non-empty JSON array of role codes.
+fn parse_required_roles(roles_json: &str) -> Option<Vec<RoleCode>> {
+    serde_json::from_str::<Vec<RoleCode>>(roles_json)
+        .ok()
+        .filter(|roles| !roles.is_empty())
+}
+
 #[derive(Deserialize)]
 pub struct HistoryParams {
     pub before: Option<String>,
@@ -73,6 +82,9 @@ pub struct CreateChannelRequest {
     pub topic: Option<String>,
     pub vrp_topic_binding: Option<String>,
     pub required_capabilities_json: Option<String>,
+    /// JSON array of role codes, e.g. `["Human"]`.
+    #[serde(default)]
+    pub required_roles_json: Option<String>,
     pub agent_min_alignment: Option<AlignmentStatus>,
     pub retention_days: Option<u32>,
     pub federation_scope: FederationScope,
@@ -94,6 +106,18 @@ pub struct JoinVoiceResponse {
     pub ice_servers: Vec<IceServerResponse>,
 }
 
+/// Returns whether an agent with `status` meets a channel's `agent_min_alignment`.
+///
+/// Aligned > Partial > Conflict. Conflict agents are blocked from every
+/// channel separately, so a `Conflict` minimum admits everyone.
+pub(crate) fn meets_min_alignment(status: AlignmentStatus, min_alignment: AlignmentStatus) -> bool {
+    match min_alignment {
+        AlignmentStatus::Conflict => true,
+        AlignmentStatus::Partial => status != AlignmentStatus::Conflict,
+        AlignmentStatus::Aligned => status == AlignmentStatus::Aligned,
+    }
+}
+
 /// POST /api/channels
 pub async fn create_channel_handler(
     Extension(state): Extension<Arc<AppState>>,
@@ -116,6 +140,9 @@ pub async fn create_channel_handler(
             return Err(StatusCode::BAD_REQUEST);
         }
     }
+    if let Some(ref roles_json) = payload.required_roles_json {
+        parse_required_roles(roles_json).ok_or(StatusCode::BAD_REQUEST)?;
+    }
 
     let params = CreateChannelParams {
         server_id: state.server_id,
@@ -125,6 +152,7 @@ pub async fn create_channel_handler(
         topic: payload.topic,
         vrp_topic_binding: payload.vrp_topic_binding,
         required_capabilities_json: payload.required_capabilities_json,
+        required_roles_json: payload.required_roles_json,
         agent_min_alignment: payload.agent_min_alignment,
         retention_days: payload.retention_days,
         federation_scope: payload.federation_scope,
@@ -133,6 +161,12 @@ pub async fn create_channel_handler(
     let pool = state.pool.clone();
     tokio::task::spawn_blocking(move || {
         let conn = pool.get().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if let Some(binding) = &params.vrp_topic_binding {
+            annex_identity::ensure_topic_not_deprecated(&conn, binding).map_err(|e| match e {
+                annex_identity::IdentityError::TopicDeprecated(_) => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            })?;
+        }
         create_channel(&conn, &params).map_err(|e| {
             // Handle unique constraint violation -> 409 Conflict
             if let annex_channels::ChannelError::Database(rusqlite::Error::SqliteFailure(
@@ -353,22 +387,61 @@ pub async fn join_channel_handler(
         }
     }
 
+    // 2b. Check required roles via selective-disclosure proof. The proof is
+    // scoped to this channel, its nullifier is bound to the caller, and it
+    // reveals only that the caller's role is in the required set.
+    if let Some(roles_json) = &channel.required_roles_json {
+        let required_roles =
+            parse_required_roles(roles_json).ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        let context = channel_role_context(&channel_id);
+        let task_state = state.clone();
+        let pseudonym_id = identity.pseudonym_id.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = task_state
+                .pool
+                .get()
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            verify_role_proof_header(
+                &conn,
+                &task_state,
+                &headers,
+                &required_roles,
+                context,
+                &pseudonym_id,
+            )
+        })
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)??;
+    }
+
     // 3. Check Agent Alignment
     if identity.participant_type == RoleCode::AiAgent {
-        // Query agent registration
-        let alignment_status: Option<String> = tokio::task::spawn_blocking({
+        let (min_reputation, reputation_config) = {
+            let policy = state
+                .policy
+                .read()
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            (policy.agent_min_reputation, policy.reputation.clone())
+        };
+
+        // Query agent registration and current reputation
+        let (alignment_status, reputation): (Option<String>, f32) = tokio::task::spawn_blocking({
             let pool = state.pool.clone();
             let server_id = state.server_id;
             let pseudo = identity.pseudonym_id.clone();
             move || {
                 let conn = pool.get().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-                conn.query_row(
-                    "SELECT alignment_status FROM agent_registrations WHERE server_id = ?1 AND pseudonym_id = ?2",
-                    params![server_id, pseudo],
-                    |row| row.get(0),
-                )
-                .optional()
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+                let status = conn
+                    .query_row(
+                        "SELECT alignment_status FROM agent_registrations WHERE server_id = ?1 AND pseudonym_id = ?2",
+                        params![server_id, pseudo],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                let summary = compute_reputation(&conn, server_id, &pseudo, &reputation_config)
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                Ok::<_, StatusCode>((status, summary.score))
             }
         })
         .await
@@ -393,17 +466,15 @@ pub async fn join_channel_handler(
 
         // Rule: Channel minimum alignment requirement
         if let Some(min_alignment) = channel.agent_min_alignment {
-            // Logic: Aligned > Partial > Conflict
-            let allowed = match min_alignment {
-                AlignmentStatus::Conflict => true, // Conflict agents are already blocked above, but for completeness
-                AlignmentStatus::Partial => status != AlignmentStatus::Conflict,
-                AlignmentStatus::Aligned => status == AlignmentStatus::Aligned,
-            };
-
-            if !allowed {
+            if !meets_min_alignment(status, min_alignment) {
                 return Err(StatusCode::FORBIDDEN);
             }
         }
+
+        // Rule: Server-wide minimum reputation
+        if reputation < min_reputation {
+            return Err(StatusCode::FORBIDDEN);
+        }
     }
 
     // 4. Add Member
@@ -413,13 +484,15 @@ pub async fn join_channel_handler(
         let cid = channel_id.clone();
         let pid = identity.pseudonym_id.clone();
         let is_agent = identity.participant_type == RoleCode::AiAgent;
+        let graph_index = state.graph_index.clone();
         move || {
             let conn = pool.get().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
             add_member(&conn, server_id, &cid, &pid)
                 .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
             if is_agent {
-                create_edge(&conn, server_id, &pid, &cid, EdgeKind::AgentServing, 1.0)
+                graph_index
+                    .create_edge(&conn, server_id, &pid, &cid, EdgeKind::AgentServing, 1.0)
                     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
             }
             Ok::<(), StatusCode>(())
@@ -488,6 +561,10 @@ pub async fn join_channel_handler(
                             channel_id: event.channel_id,
                             speaker_pseudonym: event.speaker_pseudonym,
                             text: event.text,
+                            segment_id: event.segment_id,
+                            is_final: event.is_final,
+                            start_ms: event.start_ms,
+                            end_ms: event.end_ms,
                         };
 
                         match serde_json::to_string(&msg) {
@@ -540,13 +617,15 @@ pub async fn leave_channel_handler(
         let cid = channel_id.clone();
         let pid = identity.pseudonym_id.clone();
         let is_agent = identity.participant_type == RoleCode::AiAgent;
+        let graph_index = state.graph_index.clone();
         move || {
             let conn = pool.get().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
             remove_member(&conn, server_id, &cid, &pid)
                 .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
             if is_agent {
-                delete_edge(&conn, server_id, &pid, &cid, EdgeKind::AgentServing)
+                graph_index
+                    .delete_edge(&conn, server_id, &pid, &cid, EdgeKind::AgentServing)
                     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
             }
             Ok::<(), StatusCode>(())
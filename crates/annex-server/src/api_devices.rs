@@ -0,0 +1,383 @@
+//! Device management API handlers.
+//!
+//! A platform identity can register several devices, each with its own
+//! Ed25519 key. A device proves possession of its key when it registers and
+//! again whenever it requests a WebSocket token, so every device holds an
+//! independent, individually revocable session. Revoking a device closes its
+//! live WebSocket sessions immediately; the identity's other devices stay
+//! connected.
+//!
+//! Once an identity has a registered device, its pseudonym alone no longer
+//! manages devices: registering another device or revoking one must also be
+//! signed by an active device key, sent in the `x-annex-device-signature`
+//! header.
+
+use crate::{
+    api::ApiError,
+    api_ws::{generate_ws_token, WS_TOKEN_TTL_SECS},
+    middleware::IdentityContext,
+    AppState,
+};
+use annex_identity::{
+    count_active_devices, get_device, list_devices, register_device, revoke_device, Device,
+};
+use axum::extract::{Extension, Json, Path};
+use axum::http::HeaderMap;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Maximum device label length in characters.
+const MAX_DEVICE_LABEL_LEN: usize = 64;
+
+/// Maximum clock skew accepted for device-signed requests, in seconds.
+const DEVICE_TOKEN_MAX_SKEW_SECS: u64 = 60;
+
+/// JSON payload, base64-encoded in the `x-annex-device-signature` header, by
+/// which an active device approves a device-management request.
+#[derive(Debug, Deserialize, serde::Serialize)]
+pub struct DeviceSignaturePayload {
+    /// The signing device's ID.
+    pub device_id: String,
+    /// Current Unix time in seconds.
+    pub timestamp: u64,
+    /// Signature (hex) by the device key over [`device_approval_message`] or
+    /// [`device_revocation_message`].
+    pub signature: String,
+}
+
+/// Request body for `POST /api/devices`.
+#[derive(Debug, serde::Deserialize)]
+pub struct RegisterDeviceRequest {
+    /// Human-readable device name (e.g. "Laptop").
+    pub label: String,
+    /// The device's Ed25519 public key (hex).
+    pub public_key: String,
+    /// Signature (hex) by the device key over
+    /// [`device_registration_message`], proving possession of the key.
+    pub signature: String,
+}
+
+/// Request body for `POST /api/devices/{deviceId}/ws-token`.
+#[derive(Debug, serde::Deserialize)]
+pub struct DeviceTokenRequest {
+    /// Current Unix time in seconds.
+    pub timestamp: u64,
+    /// Signature (hex) by the device key over [`device_token_message`].
+    pub signature: String,
+}
+
+/// The message a device signs to register its key under `pseudonym_id`.
+pub fn device_registration_message(pseudonym_id: &str, public_key_hex: &str) -> String {
+    format!(
+        "annex-device-register\n{}\n{}",
+        pseudonym_id,
+        public_key_hex.to_ascii_lowercase()
+    )
+}
+
+/// The message a device signs to request a WebSocket token.
+pub fn device_token_message(device_id: &str, timestamp: u64) -> String {
+    format!("annex-device-ws\n{}\n{}", device_id, timestamp)
+}
+
+/// The message an active device signs to approve registering the device key
+/// `public_key_hex` under `pseudonym_id`.
+pub fn device_approval_message(
+    pseudonym_id: &str,
+    device_id: &str,
+    public_key_hex: &str,
+    timestamp: u64,
+) -> String {
+    format!(
+        "annex-device-approve\n{}\n{}\n{}\n{}",
+        pseudonym_id,
+        device_id,
+        public_key_hex.to_ascii_lowercase(),
+        timestamp
+    )
+}
+
+/// The message an active device signs to revoke `target_device_id`.
+pub fn device_revocation_message(
+    pseudonym_id: &str,
+    device_id: &str,
+    target_device_id: &str,
+    timestamp: u64,
+) -> String {
+    format!(
+        "annex-device-revoke\n{}\n{}\n{}\n{}",
+        pseudonym_id, device_id, target_device_id, timestamp
+    )
+}
+
+fn check_timestamp(timestamp: u64) -> Result<(), ApiError> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if now.abs_diff(timestamp) > DEVICE_TOKEN_MAX_SKEW_SECS {
+        return Err(ApiError::Unauthorized(
+            "request timestamp is too old or in the future".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Requires a device-management request to be signed by one of the
+/// identity's active devices, once it has any.
+///
+/// `message` builds the signed message from the signing device's ID and the
+/// timestamp. An identity without active devices may manage devices with its
+/// pseudonym alone, so it can register its first one.
+fn require_device_signature(
+    conn: &rusqlite::Connection,
+    server_id: i64,
+    pseudonym_id: &str,
+    headers: &HeaderMap,
+    message: impl Fn(&str, u64) -> String,
+) -> Result<(), ApiError> {
+    let db_err = |e: annex_identity::IdentityError| {
+        ApiError::InternalServerError(format!("failed to load devices: {}", e))
+    };
+    if count_active_devices(conn, server_id, pseudonym_id).map_err(db_err)? == 0 {
+        return Ok(());
+    }
+
+    let header = headers
+        .get("x-annex-device-signature")
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| {
+            ApiError::Unauthorized(
+                "a signature from one of the identity's devices is required".to_string(),
+            )
+        })?;
+    use base64::Engine;
+    let payload: DeviceSignaturePayload = base64::engine::general_purpose::STANDARD
+        .decode(header)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| ApiError::BadRequest("malformed device signature header".to_string()))?;
+    check_timestamp(payload.timestamp)?;
+
+    let device = get_device(conn, server_id, &payload.device_id)
+        .map_err(db_err)?
+        .filter(|d| d.pseudonym_id == pseudonym_id && d.revoked_at.is_none())
+        .ok_or_else(|| ApiError::Unauthorized("signing device is not active".to_string()))?;
+    verify_device_signature(
+        &device.public_key,
+        &message(&device.device_id, payload.timestamp),
+        &payload.signature,
+    )
+}
+
+/// Verifies an Ed25519 signature (hex) over `message` with `public_key_hex`.
+fn verify_device_signature(
+    public_key_hex: &str,
+    message: &str,
+    signature_hex: &str,
+) -> Result<(), ApiError> {
+    let key_bytes: [u8; 32] = hex::decode(public_key_hex)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| ApiError::BadRequest("invalid device public key".to_string()))?;
+    let key = VerifyingKey::from_bytes(&key_bytes)
+        .map_err(|e| ApiError::BadRequest(format!("invalid device public key: {}", e)))?;
+    let signature_bytes: [u8; 64] = hex::decode(signature_hex)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| ApiError::Unauthorized("invalid signature encoding".to_string()))?;
+    key.verify(message.as_bytes(), &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| ApiError::Unauthorized("invalid device signature".to_string()))
+}
+
+/// Handler for `POST /api/devices`.
+///
+/// Registers a new device for the authenticated identity. Once the identity
+/// has an active device, one of them must approve the new key.
+pub async fn register_device_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    headers: HeaderMap,
+    Json(payload): Json<RegisterDeviceRequest>,
+) -> Result<Json<Device>, ApiError> {
+    let label = payload.label.trim().to_string();
+    if label.is_empty() || label.chars().count() > MAX_DEVICE_LABEL_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "device label must be 1-{} characters",
+            MAX_DEVICE_LABEL_LEN
+        )));
+    }
+
+    verify_device_signature(
+        &payload.public_key,
+        &device_registration_message(&identity.pseudonym_id, &payload.public_key),
+        &payload.signature,
+    )?;
+
+    let device = tokio::task::spawn_blocking(move || {
+        let conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+        require_device_signature(
+            &conn,
+            state.server_id,
+            &identity.pseudonym_id,
+            &headers,
+            |device_id, timestamp| {
+                device_approval_message(
+                    &identity.pseudonym_id,
+                    device_id,
+                    &payload.public_key,
+                    timestamp,
+                )
+            },
+        )?;
+
+        let device_id = uuid::Uuid::new_v4().to_string();
+        register_device(
+            &conn,
+            state.server_id,
+            &identity.pseudonym_id,
+            &device_id,
+            &label,
+            &payload.public_key,
+        )
+        .map_err(|e| match e {
+            annex_identity::IdentityError::InvalidDeviceKey => ApiError::BadRequest(e.to_string()),
+            annex_identity::IdentityError::DeviceLimitReached(_) => {
+                ApiError::Conflict(e.to_string())
+            }
+            annex_identity::IdentityError::DatabaseError(rusqlite::Error::SqliteFailure(
+                ref err,
+                _,
+            )) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+                ApiError::Conflict("device key is already registered".to_string())
+            }
+            _ => ApiError::InternalServerError(format!("failed to register device: {}", e)),
+        })
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    tracing::info!(device_id = %device.device_id, "device registered");
+
+    Ok(Json(device))
+}
+
+/// Handler for `GET /api/devices`.
+///
+/// Lists the authenticated identity's devices, including revoked ones.
+pub async fn list_devices_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+) -> Result<Json<Vec<Device>>, ApiError> {
+    let devices = tokio::task::spawn_blocking(move || {
+        let conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+        list_devices(&conn, state.server_id, &identity.pseudonym_id)
+            .map_err(|e| ApiError::InternalServerError(format!("failed to list devices: {}", e)))
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(devices))
+}
+
+/// Handler for `DELETE /api/devices/{deviceId}`.
+///
+/// Revokes one of the authenticated identity's devices and closes its live
+/// WebSocket sessions. The request must be signed by one of the identity's
+/// active devices, which may be the one being revoked.
+pub async fn revoke_device_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(device_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let pseudonym = identity.pseudonym_id.clone();
+    let state_clone = state.clone();
+    let device_clone = device_id.clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = state_clone
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+        require_device_signature(
+            &conn,
+            state_clone.server_id,
+            &pseudonym,
+            &headers,
+            |signer, timestamp| {
+                device_revocation_message(&pseudonym, signer, &device_clone, timestamp)
+            },
+        )?;
+        revoke_device(&conn, state_clone.server_id, &pseudonym, &device_clone).map_err(
+            |e| match e {
+                annex_identity::IdentityError::DeviceNotFound(_) => {
+                    ApiError::NotFound(e.to_string())
+                }
+                _ => ApiError::InternalServerError(format!("failed to revoke device: {}", e)),
+            },
+        )
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    state
+        .connection_manager
+        .disconnect_device(&identity.pseudonym_id, &device_id)
+        .await;
+
+    tracing::info!(device_id = %device_id, "device revoked");
+
+    Ok(Json(serde_json::json!({ "status": "revoked" })))
+}
+
+/// Handler for `POST /api/devices/{deviceId}/ws-token`.
+///
+/// Issues a WebSocket token bound to the device after checking a fresh
+/// signature from the device key. Sessions opened with this token are
+/// tracked per device and closed when the device is revoked.
+pub async fn create_device_ws_token_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(device_id): Path<String>,
+    Json(payload): Json<DeviceTokenRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    check_timestamp(payload.timestamp)?;
+
+    let state_clone = state.clone();
+    let device_clone = device_id.clone();
+    let device = tokio::task::spawn_blocking(move || {
+        let conn = state_clone
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+        get_device(&conn, state_clone.server_id, &device_clone)
+            .map_err(|e| ApiError::InternalServerError(format!("failed to load device: {}", e)))
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??
+    .filter(|d| d.pseudonym_id == identity.pseudonym_id && d.revoked_at.is_none())
+    .ok_or_else(|| ApiError::NotFound(format!("device not found: {}", device_id)))?;
+
+    verify_device_signature(
+        &device.public_key,
+        &device_token_message(&device_id, payload.timestamp),
+        &payload.signature,
+    )?;
+
+    let token = generate_ws_token(
+        &identity.pseudonym_id,
+        Some(&device_id),
+        &state.ws_token_secret,
+    );
+    Ok(Json(serde_json::json!({
+        "token": token,
+        "expires_in_secs": WS_TOKEN_TTL_SECS,
+    })))
+}
@@ -1,22 +1,36 @@
 use crate::{
-    api::GetRootResponse, api_rtx::rtx_relay_signing_payload, parse_transfer_scope, AppState,
+    api::GetRootResponse,
+    api_rtx::{
+        federation_redactions, local_agent_redactions, log_withheld_transfer,
+        rtx_relay_signing_payload,
+    },
+    api_vrp::NegotiationStepResponse,
+    middleware::{check_role_proof, RoleProofPayload},
+    parse_transfer_scope, AppState,
 };
 use annex_channels::{
     add_member, create_message, list_federated_channels, Channel, CreateMessageParams,
 };
 use annex_federation::{
-    process_incoming_handshake, AttestationRequest, FederatedMessageEnvelope, FederatedRtxEnvelope,
-    HandshakeError,
+    create_agreement, local_alignment_config, local_negotiation_terms, process_incoming_handshake,
+    AttestationRequest, FederatedMessageEnvelope, FederatedRtxEnvelope, HandshakeError,
 };
 use annex_graph::{ensure_graph_node, GraphError};
 use annex_identity::{
     derive_nullifier_hex, derive_pseudonym_id,
+    disclosure::{bind_role_nullifier, channel_role_context},
+    vote::field_to_hex,
     zk::{parse_fr_from_hex, parse_proof, verify_proof},
 };
 use annex_observe::EventPayload;
-use annex_rtx::{enforce_transfer_scope, validate_bundle_structure};
-use annex_types::NodeType;
-use annex_vrp::{VrpFederationHandshake, VrpTransferScope, VrpValidationReport};
+use annex_rtx::{enforce_transfer_scope, redacted_topic_matches, validate_bundle_structure};
+use annex_types::{NodeType, RoleCode};
+use annex_vrp::{
+    explain_peer_anchor, get_negotiation, insert_negotiation, record_vrp_outcome,
+    update_negotiation, NegotiationError, ReputationError, ServerPolicyRoot, VrpAnchorSnapshot,
+    VrpContractOffer, VrpFederationHandshake, VrpNegotiation, VrpNegotiationMessage,
+    VrpNegotiationStatus, VrpTransferScope, VrpValidationReport,
+};
 use axum::{
     extract::{Extension, Path},
     Json,
@@ -32,6 +46,11 @@ use thiserror::Error;
 const FEDERATION_HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 const FEDERATION_HTTP_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// How far a renewal request's timestamp may be from our clock.
+const RENEWAL_MAX_SKEW_SECS: i64 = 300;
+/// Minimum spacing between renewal requests honoured from one peer.
+const RENEWAL_MIN_INTERVAL_SECS: i64 = 60;
+
 /// Builds a reqwest client with timeouts to prevent resource exhaustion
 /// from slow or malicious federation peers.
 pub fn federation_http_client() -> Result<reqwest::Client, reqwest::Error> {
@@ -68,6 +87,10 @@ pub enum FederationError {
     Forbidden(String),
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+    #[error("Negotiation error: {0}")]
+    Negotiation(#[from] NegotiationError),
+    #[error("Too many requests: {0}")]
+    RateLimited(String),
 }
 
 impl axum::response::IntoResponse for FederationError {
@@ -80,6 +103,9 @@ impl axum::response::IntoResponse for FederationError {
                 (axum::http::StatusCode::NOT_FOUND, self.to_string())
             }
             FederationError::Forbidden(_) => (axum::http::StatusCode::FORBIDDEN, self.to_string()),
+            FederationError::RateLimited(_) => {
+                (axum::http::StatusCode::TOO_MANY_REQUESTS, self.to_string())
+            }
             FederationError::InvalidSignature(_) => {
                 (axum::http::StatusCode::UNAUTHORIZED, self.to_string())
             }
@@ -102,6 +128,12 @@ impl axum::response::IntoResponse for FederationError {
             FederationError::Channel(annex_channels::ChannelError::NotFound(_)) => {
                 (axum::http::StatusCode::NOT_FOUND, self.to_string())
             }
+            FederationError::Negotiation(NegotiationError::NotFound) => {
+                (axum::http::StatusCode::NOT_FOUND, self.to_string())
+            }
+            FederationError::Negotiation(NegotiationError::Closed(_)) => {
+                (axum::http::StatusCode::CONFLICT, self.to_string())
+            }
             _ => (
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR,
                 self.to_string(),
@@ -120,6 +152,69 @@ pub struct HandshakeRequest {
     pub handshake: VrpFederationHandshake,
 }
 
+#[derive(Deserialize, serde::Serialize)]
+pub struct RenewalRequest {
+    /// Base URL of the requesting server (to identify the instance).
+    pub base_url: String,
+    /// When the request was made, in Unix seconds.
+    pub requested_at: i64,
+    /// Hex Ed25519 signature by the requesting server over
+    /// [`renewal_signing_payload`].
+    pub signature: String,
+}
+
+/// Constructs the payload a peer signs to ask `target_url` for renewal.
+///
+/// Binding the target keeps a request from being replayed against another
+/// server that federates with the same peer.
+pub fn renewal_signing_payload(base_url: &str, target_url: &str, requested_at: i64) -> String {
+    format!(
+        "annex:federation:renewal:v1\n{}\n{}\n{}",
+        base_url, target_url, requested_at
+    )
+}
+
+/// Builds a signed renewal request from this server to `target_url`.
+pub fn signed_renewal_request(state: &AppState, target_url: &str) -> RenewalRequest {
+    let base_url = state.get_public_url();
+    let requested_at = unix_now();
+    let signature = state
+        .signing_key
+        .sign(renewal_signing_payload(&base_url, target_url, requested_at).as_bytes());
+    RenewalRequest {
+        base_url,
+        requested_at,
+        signature: hex::encode(signature.to_bytes()),
+    }
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+#[derive(Deserialize)]
+pub struct OpenNegotiationRequest {
+    /// Base URL of the requesting server (to identify the instance).
+    pub base_url: String,
+    /// The requesting server's anchor snapshot.
+    pub anchor_snapshot: VrpAnchorSnapshot,
+    /// The requesting server's opening terms.
+    pub offer: VrpContractOffer,
+}
+
+#[derive(Deserialize)]
+pub struct NegotiationStepRequest {
+    /// Base URL of the requesting server; must match the one that opened
+    /// the negotiation.
+    pub base_url: String,
+    /// The peer's next move (`counter_offer`, `accept` or `reject`).
+    #[serde(flatten)]
+    pub message: VrpNegotiationMessage,
+}
+
 #[derive(Deserialize, serde::Serialize)]
 pub struct JoinFederatedChannelRequest {
     /// The base URL of the originating server.
@@ -128,6 +223,11 @@ pub struct JoinFederatedChannelRequest {
     pub pseudonym_id: String,
     /// Signature of SHA256(channel_id + pseudonym_id).
     pub signature: String,
+    /// Selective-disclosure role proof, required when the channel declares
+    /// `required_roles_json`. Must be bound to this channel and pseudonym and
+    /// proven against the root the identity was attested under.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_proof: Option<RoleProofPayload>,
 }
 
 /// Relays a message to all active federation peers.
@@ -304,9 +404,12 @@ pub(crate) fn find_commitment_for_pseudonym(
     }
 
     // Slow fallback: only scan legacy rows that lack the denormalized columns.
-    // Once all rows are backfilled this path becomes a no-op.
-    let mut stmt =
-        conn.prepare("SELECT topic, nullifier_hex FROM zk_nullifiers WHERE pseudonym_id IS NULL")?;
+    // Once all rows are backfilled this path becomes a no-op. Poll vote
+    // nullifiers never carry a pseudonym by design and are skipped.
+    let mut stmt = conn.prepare(
+        "SELECT topic, nullifier_hex FROM zk_nullifiers \
+         WHERE pseudonym_id IS NULL AND topic NOT LIKE 'annex:poll:%'",
+    )?;
     let rows = stmt.query_map([], |row| {
         Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
     })?;
@@ -607,22 +710,9 @@ pub async fn federation_handshake_handler(
             .get()
             .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?; // Wrap pool error
 
-        // 1. Resolve remote instance ID from base_url
-        tracing::debug!("Resolving instance for base_url: {}", payload.base_url);
-        let remote_instance_id: i64 = conn
-            .query_row(
-                "SELECT id FROM instances WHERE base_url = ?1",
-                params![payload.base_url],
-                |row| row.get(0),
-            )
-            .map_err(|e| {
-                tracing::error!("Instance resolution failed: {:?}", e);
-                if e == rusqlite::Error::QueryReturnedNoRows {
-                    FederationError::UnknownRemote(payload.base_url.clone())
-                } else {
-                    FederationError::DbError(e)
-                }
-            })?;
+        // 1. Resolve remote instance ID from base_url and check its anchor
+        let remote_instance_id = resolve_instance_id(&conn, &payload.base_url)?;
+        verify_peer_anchor(&conn, &payload.base_url, &payload.handshake.anchor_snapshot)?;
 
         // 2. Process handshake
         tracing::debug!(
@@ -640,26 +730,337 @@ pub async fn federation_handshake_handler(
             &policy,
             remote_instance_id,
             &payload.handshake,
+            state_clone.embedder.as_ref(),
         )
         .map_err(|e| {
             tracing::error!("Handshake failed: {:?}", e);
             FederationError::Handshake(e)
         })?;
 
-        // Emit FEDERATION_ESTABLISHED to persistent log
-        let observe_payload = EventPayload::FederationEstablished {
-            remote_url: payload.base_url.clone(),
-            alignment_status: report.alignment_status.to_string(),
-        };
-        crate::emit_and_broadcast(
+        record_federation_outcome(&state_clone, &conn, &payload.base_url, &report)?;
+
+        Ok::<_, FederationError>(report)
+    })
+    .await
+    .map_err(|e| {
+        FederationError::DbError(rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+    })??;
+
+    Ok(Json(result))
+}
+
+/// Handler for `POST /api/federation/renewal`.
+///
+/// A peer whose agreement with us is about to expire asks us to repeat the
+/// VRP handshake. The request must be signed by the peer's registered key,
+/// addressed to this server and made within [`RENEWAL_MAX_SKEW_SECS`] of
+/// now; each peer is honoured at most once per
+/// [`RENEWAL_MIN_INTERVAL_SECS`], so replays are refused with `429`.
+///
+/// The re-handshake is sent in the background, so a `202` only means it was
+/// scheduled.
+pub async fn renewal_request_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Json(payload): Json<RenewalRequest>,
+) -> Result<axum::http::StatusCode, FederationError> {
+    let state_clone = state.clone();
+    let base_url = payload.base_url.clone();
+    let requested_at = payload.requested_at;
+    let signature_hex = payload.signature.clone();
+    let remote_instance_id = tokio::task::spawn_blocking(move || {
+        let conn = state_clone
+            .pool
+            .get()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let (remote_instance_id, public_key_hex, last_requested_at): (i64, String, Option<i64>) =
+            conn.query_row(
+                "SELECT id, public_key, renewal_received_at FROM instances WHERE base_url = ?1",
+                params![base_url],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|e| {
+                if e == rusqlite::Error::QueryReturnedNoRows {
+                    FederationError::UnknownRemote(base_url.clone())
+                } else {
+                    FederationError::DbError(e)
+                }
+            })?;
+
+        let public_key_bytes: [u8; 32] = hex::decode(&public_key_hex)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| FederationError::InvalidSignature("Invalid public key".to_string()))?;
+        let public_key = EdVerifyingKey::from_bytes(&public_key_bytes)
+            .map_err(|e| FederationError::InvalidSignature(e.to_string()))?;
+        let signature_bytes: [u8; 64] = hex::decode(&signature_hex)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| {
+                FederationError::InvalidSignature("Invalid signature encoding".to_string())
+            })?;
+        let message =
+            renewal_signing_payload(&base_url, &state_clone.get_public_url(), requested_at);
+        public_key
+            .verify(message.as_bytes(), &Signature::from_bytes(&signature_bytes))
+            .map_err(|e| FederationError::InvalidSignature(format!("renewal: {}", e)))?;
+
+        if (unix_now() - requested_at).abs() > RENEWAL_MAX_SKEW_SECS {
+            return Err(FederationError::InvalidSignature(
+                "renewal request is stale".to_string(),
+            ));
+        }
+        if last_requested_at.is_some_and(|last| requested_at < last + RENEWAL_MIN_INTERVAL_SECS) {
+            return Err(FederationError::RateLimited(
+                "renewal already requested recently".to_string(),
+            ));
+        }
+        conn.execute(
+            "UPDATE instances SET renewal_received_at = ?1 WHERE id = ?2",
+            params![requested_at, remote_instance_id],
+        )?;
+        Ok::<_, FederationError>(remote_instance_id)
+    })
+    .await
+    .map_err(|e| {
+        FederationError::DbError(rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+    })??;
+
+    tracing::info!(peer = %payload.base_url, "peer requested contract renewal");
+    tokio::spawn(crate::policy::send_federation_rehandshakes(
+        state,
+        vec![(payload.base_url, remote_instance_id)],
+    ));
+
+    Ok(axum::http::StatusCode::ACCEPTED)
+}
+
+/// Looks up the instance ID registered for a peer's base URL.
+fn resolve_instance_id(
+    conn: &rusqlite::Connection,
+    base_url: &str,
+) -> Result<i64, FederationError> {
+    tracing::debug!("Resolving instance for base_url: {}", base_url);
+    conn.query_row(
+        "SELECT id FROM instances WHERE base_url = ?1",
+        params![base_url],
+        |row| row.get(0),
+    )
+    .map_err(|e| {
+        tracing::error!("Instance resolution failed: {:?}", e);
+        if e == rusqlite::Error::QueryReturnedNoRows {
+            FederationError::UnknownRemote(base_url.to_string())
+        } else {
+            FederationError::DbError(e)
+        }
+    })
+}
+
+/// Verifies a peer's anchor signature against the key registered for
+/// `base_url`, and that the principle texts match the signed hashes.
+///
+/// Unsigned anchors are accepted from peers that predate anchor signing, but
+/// once a peer has presented a signed anchor every later anchor must be
+/// signed. Signed anchors older than the newest one seen are refused, so a
+/// captured anchor cannot be replayed after the peer's policy changed.
+fn verify_peer_anchor(
+    conn: &rusqlite::Connection,
+    base_url: &str,
+    anchor: &VrpAnchorSnapshot,
+) -> Result<(), FederationError> {
+    let (public_key_hex, anchor_signed, last_timestamp): (String, bool, Option<i64>) = conn
+        .query_row(
+            "SELECT public_key, anchor_signed, anchor_timestamp FROM instances WHERE base_url = ?1",
+            params![base_url],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| {
+            if e == rusqlite::Error::QueryReturnedNoRows {
+                FederationError::UnknownRemote(base_url.to_string())
+            } else {
+                FederationError::DbError(e)
+            }
+        })?;
+
+    let Some(signature_hex) = anchor.signature.as_deref() else {
+        if anchor_signed {
+            return Err(FederationError::InvalidSignature(
+                "peer signs its anchors; unsigned anchor refused".to_string(),
+            ));
+        }
+        tracing::debug!(peer = %base_url, "peer presented an unsigned anchor");
+        return Ok(());
+    };
+
+    let public_key_bytes: [u8; 32] = hex::decode(&public_key_hex)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| FederationError::InvalidSignature("Invalid public key".to_string()))?;
+    let public_key = EdVerifyingKey::from_bytes(&public_key_bytes)
+        .map_err(|e| FederationError::InvalidSignature(e.to_string()))?;
+    let signature_bytes: [u8; 64] = hex::decode(signature_hex)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| {
+            FederationError::InvalidSignature("Invalid anchor signature encoding".to_string())
+        })?;
+
+    public_key
+        .verify(
+            anchor.signing_payload().as_bytes(),
+            &Signature::from_bytes(&signature_bytes),
+        )
+        .map_err(|e| FederationError::InvalidSignature(format!("anchor: {}", e)))?;
+
+    if !anchor.texts_match_hashes() {
+        return Err(FederationError::InvalidSignature(
+            "anchor principles do not match the signed hashes".to_string(),
+        ));
+    }
+
+    let timestamp = i64::try_from(anchor.timestamp).map_err(|_| {
+        FederationError::InvalidSignature("anchor timestamp out of range".to_string())
+    })?;
+    if last_timestamp.is_some_and(|last| timestamp < last) {
+        return Err(FederationError::InvalidSignature(
+            "anchor is older than the last anchor presented by this peer".to_string(),
+        ));
+    }
+    conn.execute(
+        "UPDATE instances SET anchor_signed = 1, anchor_timestamp = ?1 WHERE base_url = ?2",
+        params![timestamp, base_url],
+    )?;
+    Ok(())
+}
+
+fn reputation_error(e: ReputationError) -> FederationError {
+    match e {
+        ReputationError::Database(e) => FederationError::DbError(e),
+        ReputationError::Serialization(e) => FederationError::Serialization(e),
+        e @ ReputationError::Duplicate => FederationError::Forbidden(e.to_string()),
+    }
+}
+
+/// Logs a federation handshake outcome and emits `FEDERATION_ESTABLISHED`.
+fn record_federation_outcome(
+    state: &AppState,
+    conn: &rusqlite::Connection,
+    base_url: &str,
+    report: &VrpValidationReport,
+) -> Result<(), FederationError> {
+    // Keep the full report, including its explanation, for operators
+    // debugging misaligned peers.
+    record_vrp_outcome(conn, state.server_id, base_url, "SERVER", report)
+        .map_err(reputation_error)?;
+
+    // Emit FEDERATION_ESTABLISHED to persistent log
+    let observe_payload = EventPayload::FederationEstablished {
+        remote_url: base_url.to_string(),
+        alignment_status: report.alignment_status.to_string(),
+    };
+    crate::emit_and_broadcast(
+        conn,
+        state.server_id,
+        base_url,
+        &observe_payload,
+        &state.observe_tx,
+    );
+    Ok(())
+}
+
+/// Handler for `POST /api/federation/negotiations`.
+///
+/// Opens a contract negotiation with a peer server's first offer. Answer
+/// counter-offers via `POST /api/federation/negotiations/{negotiationId}`.
+pub async fn open_federation_negotiation_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Json(payload): Json<OpenNegotiationRequest>,
+) -> Result<Json<NegotiationStepResponse>, FederationError> {
+    let result = tokio::task::spawn_blocking(move || {
+        let mut conn = state
+            .pool
+            .get()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let remote_instance_id = resolve_instance_id(&conn, &payload.base_url)?;
+        verify_peer_anchor(&conn, &payload.base_url, &payload.anchor_snapshot)?;
+
+        let policy = state
+            .policy
+            .read()
+            .map_err(|_| FederationError::LockPoisoned)?;
+        let local_anchor = ServerPolicyRoot::from_policy(&policy)
+            .to_anchor_snapshot()
+            .map_err(|e| FederationError::Handshake(HandshakeError::Vrp(e)))?;
+        let alignment = explain_peer_anchor(
+            &local_anchor,
+            &payload.anchor_snapshot,
+            &local_alignment_config(&policy),
+            state.embedder.as_ref(),
+        )
+        .map_err(|e| FederationError::Handshake(HandshakeError::Vrp(e)))?;
+        let local_terms = local_negotiation_terms(&policy, alignment.0);
+
+        let (mut negotiation, response) = VrpNegotiation::open(
+            uuid::Uuid::new_v4().to_string(),
+            payload.base_url,
+            "SERVER",
+            payload.anchor_snapshot,
+            alignment,
+            payload.offer,
+            &local_terms,
+        );
+        insert_negotiation(
             &conn,
-            state_clone.server_id,
+            state.server_id,
+            &mut negotiation,
+            policy.negotiation_timeout_secs,
+        )?;
+        drop(policy);
+
+        settle_federation_negotiation(&state, &mut conn, remote_instance_id, &negotiation)?;
+        Ok::<_, FederationError>(NegotiationStepResponse::new(&negotiation, response))
+    })
+    .await
+    .map_err(|e| {
+        FederationError::DbError(rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+    })??;
+
+    Ok(Json(result))
+}
+
+/// Handler for `POST /api/federation/negotiations/{negotiationId}`.
+pub async fn federation_negotiation_step_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(negotiation_id): Path<String>,
+    Json(payload): Json<NegotiationStepRequest>,
+) -> Result<Json<NegotiationStepResponse>, FederationError> {
+    let result = tokio::task::spawn_blocking(move || {
+        let mut conn = state
+            .pool
+            .get()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let remote_instance_id = resolve_instance_id(&conn, &payload.base_url)?;
+
+        let mut negotiation = get_negotiation(
+            &conn,
+            state.server_id,
+            &negotiation_id,
+            "SERVER",
             &payload.base_url,
-            &observe_payload,
-            &state_clone.observe_tx,
-        );
+        )?;
 
-        Ok::<_, FederationError>(report)
+        let local_terms = {
+            let policy = state
+                .policy
+                .read()
+                .map_err(|_| FederationError::LockPoisoned)?;
+            local_negotiation_terms(&policy, negotiation.alignment_status)
+        };
+
+        let response = negotiation.advance(payload.message, &local_terms)?;
+        update_negotiation(&conn, state.server_id, &negotiation)?;
+
+        settle_federation_negotiation(&state, &mut conn, remote_instance_id, &negotiation)?;
+        Ok::<_, FederationError>(NegotiationStepResponse::new(&negotiation, response))
     })
     .await
     .map_err(|e| {
@@ -669,6 +1070,47 @@ pub async fn federation_handshake_handler(
     Ok(Json(result))
 }
 
+/// Records a negotiation that has just closed: an accepted one becomes the
+/// active federation agreement and is logged as a VRP outcome.
+fn settle_federation_negotiation(
+    state: &AppState,
+    conn: &mut rusqlite::Connection,
+    remote_instance_id: i64,
+    negotiation: &VrpNegotiation,
+) -> Result<(), FederationError> {
+    let report = negotiation.report();
+    match negotiation.status {
+        VrpNegotiationStatus::Accepted => {
+            let validity = state
+                .policy
+                .read()
+                .map_err(|_| FederationError::LockPoisoned)?
+                .contract_validity();
+            create_agreement(
+                conn,
+                state.server_id,
+                remote_instance_id,
+                &report,
+                Some(&negotiation.agreed_handshake()),
+                validity,
+            )?;
+            record_federation_outcome(state, conn, &negotiation.peer_id, &report)
+        }
+        // Failing to agree on terms is not evidence of misalignment, so a
+        // rejection neither affects reputation nor touches agreements.
+        VrpNegotiationStatus::Rejected => {
+            tracing::info!(
+                negotiation_id = %negotiation.id,
+                peer = %negotiation.peer_id,
+                reason = negotiation.outcome_reason.as_deref().unwrap_or_default(),
+                "federation contract negotiation failed"
+            );
+            Ok(())
+        }
+        VrpNegotiationStatus::Open | VrpNegotiationStatus::Expired => Ok(()),
+    }
+}
+
 /// Handler for `GET /api/federation/vrp-root`.
 pub async fn get_vrp_root_handler(
     Extension(state): Extension<Arc<AppState>>,
@@ -1018,6 +1460,65 @@ pub async fn join_federated_channel_handler(
             )));
         }
 
+        // 3b. Role-gated channels require a selective-disclosure proof.
+        let required_roles_json: Option<String> = conn
+            .query_row(
+                "SELECT required_roles_json FROM channels WHERE channel_id = ?1",
+                params![channel_id_clone],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(FederationError::DbError)?
+            .flatten();
+
+        if let Some(roles_json) = required_roles_json {
+            let required_roles: Vec<RoleCode> = serde_json::from_str(&roles_json)?;
+            let role_proof = payload.role_proof.as_ref().ok_or_else(|| {
+                FederationError::Forbidden("channel requires a role proof".to_string())
+            })?;
+
+            // The proof must be made against the root the identity was
+            // attested under, and its nullifier is bound to the attested
+            // pseudonym. Attestations recorded before root tracking carry no
+            // root (NULL or the column default of '') and cannot back a role
+            // proof.
+            let attested_root: Option<String> = conn
+                .query_row(
+                    "SELECT root_hex_at_verification FROM federated_identities
+                     WHERE remote_instance_id = ?1 AND pseudonym_id = ?2",
+                    params![remote_instance_id, payload.pseudonym_id],
+                    |row| row.get(0),
+                )
+                .map_err(FederationError::DbError)?;
+            let attested_root = attested_root.filter(|root| !root.is_empty()).ok_or_else(|| {
+                FederationError::Forbidden("identity attestation has no verified root".to_string())
+            })?;
+
+            let context = channel_role_context(&channel_id_clone);
+            let disclosure = check_role_proof(
+                &state_clone,
+                &role_proof.proof,
+                &role_proof.public_signals,
+                &required_roles,
+                context,
+            )
+            .map_err(|reason| FederationError::Forbidden(format!("role proof rejected: {}", reason)))?;
+
+            if field_to_hex(&disclosure.root) != attested_root {
+                return Err(FederationError::Forbidden(
+                    "role proof root does not match attested root".to_string(),
+                ));
+            }
+            bind_role_nullifier(
+                &conn,
+                state_clone.server_id,
+                context,
+                disclosure.nullifier,
+                &payload.pseudonym_id,
+            )
+            .map_err(|e| FederationError::Forbidden(format!("role proof rejected: {}", e)))?;
+        }
+
         // 4. Add Member
         add_member(
             &conn,
@@ -1116,12 +1617,12 @@ pub async fn receive_federated_rtx_handler(
         }
 
         // 2. Verify active federation agreement and check transfer scope
-        let transfer_scope_str: String = conn
+        let (transfer_scope_str, remote_handshake_json): (String, Option<String>) = conn
             .query_row(
-                "SELECT transfer_scope FROM federation_agreements
+                "SELECT transfer_scope, remote_handshake_json FROM federation_agreements
                  WHERE remote_instance_id = ?1 AND active = 1",
                 params![remote_instance_id],
-                |row| row.get(0),
+                |row| Ok((row.get(0)?, row.get(1)?)),
             )
             .map_err(|e| {
                 if e == rusqlite::Error::QueryReturnedNoRows {
@@ -1179,6 +1680,23 @@ pub async fn receive_federated_rtx_handler(
         validate_bundle_structure(&envelope.bundle)
             .map_err(|e| FederationError::Forbidden(format!("Invalid bundle structure: {}", e)))?;
 
+        // 4.5. Reject bundles on topics redacted under the federation agreement
+        let policy = state_clone
+            .policy
+            .read()
+            .map_err(|_| FederationError::LockPoisoned)?
+            .clone();
+        let redacted = redacted_topic_matches(
+            &envelope.bundle,
+            &federation_redactions(&policy, remote_handshake_json.as_deref()),
+        );
+        if !redacted.is_empty() {
+            return Err(FederationError::Forbidden(format!(
+                "bundle carries redacted topics: {}",
+                redacted.join(", ")
+            )));
+        }
+
         // 5. Enforce the local federation agreement's transfer scope on the bundle
         //    (may strip reasoning_chain if our agreement is ReflectionSummariesOnly)
         let scoped_bundle = enforce_transfer_scope(&envelope.bundle, agreement_scope)
@@ -1261,10 +1779,11 @@ pub async fn receive_federated_rtx_handler(
 
         // Collect subscription rows before writing delivery logs to avoid
         // holding a prepared statement open across writes.
-        let subscribers: Vec<(String, String, String)> = {
+        let subscribers: Vec<(String, String, String, String)> = {
             let mut stmt = tx
                 .prepare(
-                    "SELECT s.subscriber_pseudonym, s.domain_filters_json, a.transfer_scope
+                    "SELECT s.subscriber_pseudonym, s.domain_filters_json, a.transfer_scope,
+                            a.capability_contract_json
                      FROM rtx_subscriptions s
                      JOIN agent_registrations a
                        ON a.server_id = s.server_id AND a.pseudonym_id = s.subscriber_pseudonym
@@ -1278,6 +1797,7 @@ pub async fn receive_federated_rtx_handler(
                         row.get::<_, String>(0)?,
                         row.get::<_, String>(1)?,
                         row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
                     ))
                 })
                 .map_err(FederationError::DbError)?;
@@ -1289,7 +1809,7 @@ pub async fn receive_federated_rtx_handler(
             collected
         };
 
-        for (sub_pseudonym, domain_filters_json, scope_str) in subscribers {
+        for (sub_pseudonym, domain_filters_json, scope_str, contract_json) in subscribers {
             // Parse domain filters. Corrupted JSON → skip this subscriber
             // entirely (reject) rather than defaulting to accept-all, which
             // could cause unauthorized knowledge transfer.
@@ -1318,6 +1838,28 @@ pub async fn receive_federated_rtx_handler(
                 continue;
             }
 
+            // Withhold bundles on topics redacted for this subscriber
+            let withheld = redacted_topic_matches(
+                &scoped_bundle,
+                &local_agent_redactions(
+                    &tx,
+                    state_clone.server_id,
+                    &policy,
+                    &sub_pseudonym,
+                    &contract_json,
+                )?,
+            );
+            if !withheld.is_empty() {
+                log_withheld_transfer(
+                    &tx,
+                    state_clone.server_id,
+                    &scoped_bundle,
+                    &sub_pseudonym,
+                    &withheld,
+                );
+                continue;
+            }
+
             // Parse receiver's transfer scope
             let receiver_scope = match parse_transfer_scope(&scope_str) {
                 Some(s) if s >= VrpTransferScope::ReflectionSummariesOnly => s,
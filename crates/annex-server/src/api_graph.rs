@@ -1,14 +1,18 @@
 //! Graph API handlers.
 
 use crate::AppState;
-use annex_graph::{find_path_bfs, get_visible_profile, BfsPath, GraphError, GraphProfile};
+use annex_graph::{
+    BfsPath, ConnectionRequest, ExportFormat, ExportOptions, GraphAnalyticsRelease, GraphChanges,
+    GraphError, GraphProfile, GraphSnapshot, ParticipantStatus, VisibilityPreferences,
+};
+use annex_types::PresenceEvent;
 use axum::{
     extract::{Extension, Path, Query},
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -24,21 +28,97 @@ pub struct GetDegreesParams {
     pub max_depth: u32,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ExportGraphParams {
+    pub format: ExportFormat,
+    /// Replace pseudonyms with salted hashes, drop node metadata and coarsen
+    /// timestamps to the day.
+    #[serde(default)]
+    pub pseudonymize: bool,
+    /// Salt for `pseudonymize`. A random one is used when omitted, so
+    /// separate exports cannot be linked unless the caller reuses a salt.
+    pub salt: Option<String>,
+    /// Comma-separated node types, e.g. `HUMAN,AI_AGENT`.
+    #[serde(rename = "nodeTypes")]
+    pub node_types: Option<String>,
+    /// Comma-separated edge kinds, e.g. `CONNECTED,MEMBER_OF`.
+    #[serde(rename = "edgeKinds")]
+    pub edge_kinds: Option<String>,
+    /// Limit the export to the neighborhood of this pseudonym.
+    pub center: Option<String>,
+    /// Radius of the `center` neighborhood, in edges. Defaults to 1.
+    pub hops: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphSnapshotParams {
+    /// Reconstruct the graph as of this timestamp (ISO 8601).
+    pub at: String,
+    /// Limit the snapshot to this participant's edges.
+    #[serde(rename = "pseudonymId")]
+    pub pseudonym_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphChangesParams {
+    /// Start of the window (ISO 8601). Defaults to the retention horizon.
+    pub since: Option<String>,
+    /// End of the window (ISO 8601). Defaults to now.
+    pub until: Option<String>,
+    /// Only changes touching this participant.
+    #[serde(rename = "pseudonymId")]
+    pub pseudonym_id: Option<String>,
+    /// Maximum node and edge entries each (default: 100, max: 1000).
+    pub limit: Option<u32>,
+}
+
 #[derive(Debug, Error)]
 pub enum GraphApiError {
     #[error("bad request: {0}")]
     BadRequest(String),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
     #[error("not found: {0}")]
     NotFound(String),
+    #[error("conflict: {0}")]
+    Conflict(String),
+    #[error("too many requests: {0}")]
+    TooManyRequests(String),
     #[error("internal server error: {0}")]
     InternalServerError(String),
 }
 
+impl From<GraphError> for GraphApiError {
+    fn from(e: GraphError) -> Self {
+        match e {
+            GraphError::NodeNotFound(_)
+            | GraphError::ConnectionRequestNotFound(_)
+            | GraphError::NotConnected(_) => GraphApiError::NotFound(e.to_string()),
+            GraphError::InvalidConnection(_)
+            | GraphError::InvalidVisibilityPreferences(_)
+            | GraphError::InvalidRelationship(_)
+            | GraphError::InvalidExport(_)
+            | GraphError::InvalidStatus(_)
+            | GraphError::InvalidHistoryQuery(_) => GraphApiError::BadRequest(e.to_string()),
+            GraphError::Blocked(_) => GraphApiError::Forbidden(e.to_string()),
+            GraphError::InvalidAnalyticsConfig(_) => {
+                GraphApiError::InternalServerError(e.to_string())
+            }
+            GraphError::ConnectionConflict(_) => GraphApiError::Conflict(e.to_string()),
+            GraphError::TooManyPendingRequests => GraphApiError::TooManyRequests(e.to_string()),
+            _ => GraphApiError::InternalServerError(e.to_string()),
+        }
+    }
+}
+
 impl IntoResponse for GraphApiError {
     fn into_response(self) -> Response {
         let (status, message) = match self {
             GraphApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            GraphApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             GraphApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            GraphApiError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            GraphApiError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
             GraphApiError::InternalServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
@@ -67,14 +147,16 @@ pub async fn get_degrees_handler(
             GraphApiError::InternalServerError(format!("db connection failed: {}", e))
         })?;
 
-        find_path_bfs(
-            &conn,
-            state.server_id,
-            &params.from,
-            &params.to,
-            params.max_depth,
-        )
-        .map_err(|e| GraphApiError::InternalServerError(e.to_string()))
+        state
+            .graph_index
+            .find_path(
+                &conn,
+                state.server_id,
+                &params.from,
+                &params.to,
+                params.max_depth,
+            )
+            .map_err(|e| GraphApiError::InternalServerError(e.to_string()))
     })
     .await
     .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
@@ -101,15 +183,635 @@ pub async fn get_profile_handler(
             GraphApiError::InternalServerError(format!("db connection failed: {}", e))
         })?;
 
-        get_visible_profile(&conn, state.server_id, &viewer_pseudonym, &target_pseudonym).map_err(
-            |e| match e {
+        state
+            .graph_index
+            .visible_profile(&conn, state.server_id, &viewer_pseudonym, &target_pseudonym)
+            .map_err(|e| match e {
                 GraphError::NodeNotFound(_) => GraphApiError::NotFound(e.to_string()),
                 _ => GraphApiError::InternalServerError(e.to_string()),
-            },
-        )
+            })
     })
     .await
     .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
 
     Ok(Json(result))
 }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateConnectionRequestBody {
+    /// The pseudonym to connect with.
+    pub target_pseudonym: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectionRequestsResponse {
+    pub requests: Vec<ConnectionRequest>,
+}
+
+/// Handler for `POST /api/graph/connections/requests`.
+///
+/// Asks another participant to connect with the caller. The request stays
+/// pending until the recipient accepts or declines it.
+pub async fn create_connection_request_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Json(body): Json<CreateConnectionRequestBody>,
+) -> Result<(StatusCode, Json<ConnectionRequest>), GraphApiError> {
+    let request = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::create_connection_request(
+            &conn,
+            state.server_id,
+            &identity.pseudonym_id,
+            &body.target_pseudonym,
+        )
+        .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok((StatusCode::CREATED, Json(request)))
+}
+
+/// Handler for `GET /api/graph/connections/requests`.
+///
+/// Lists the caller's pending requests, both sent and received.
+pub async fn list_connection_requests_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+) -> Result<Json<ConnectionRequestsResponse>, GraphApiError> {
+    let requests = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::list_connection_requests(&conn, state.server_id, &identity.pseudonym_id)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(ConnectionRequestsResponse { requests }))
+}
+
+/// Handler for `POST /api/graph/connections/requests/{requestId}/accept`.
+///
+/// Only the recipient may accept. Creates a `CONNECTED` edge in each
+/// direction and broadcasts `EdgeAdded` for both.
+pub async fn accept_connection_request_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Path(request_id): Path<i64>,
+) -> Result<Json<ConnectionRequest>, GraphApiError> {
+    let state_clone = state.clone();
+    let (request, edges) = tokio::task::spawn_blocking(move || {
+        let mut conn = state_clone.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::accept_connection_request(
+            &mut conn,
+            &state_clone.graph_index,
+            state_clone.server_id,
+            request_id,
+            &identity.pseudonym_id,
+        )
+        .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    for edge in edges {
+        let _ = state.presence_tx.send(PresenceEvent::EdgeAdded {
+            from_node: edge.from_node,
+            to_node: edge.to_node,
+            kind: edge.kind,
+        });
+    }
+
+    Ok(Json(request))
+}
+
+/// Handler for `POST /api/graph/connections/requests/{requestId}/decline`.
+///
+/// Either party may decline; the requester declining withdraws the request.
+pub async fn decline_connection_request_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Path(request_id): Path<i64>,
+) -> Result<Json<ConnectionRequest>, GraphApiError> {
+    let request = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::decline_connection_request(
+            &conn,
+            state.server_id,
+            request_id,
+            &identity.pseudonym_id,
+        )
+        .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(request))
+}
+
+/// Handler for `DELETE /api/graph/connections/{pseudonymId}`.
+///
+/// Removes the caller's connection with another participant and broadcasts
+/// `EdgeRemoved` for each edge deleted.
+pub async fn remove_connection_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Path(other): Path<String>,
+) -> Result<StatusCode, GraphApiError> {
+    let state_clone = state.clone();
+    let removed = tokio::task::spawn_blocking(move || {
+        let mut conn = state_clone.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::remove_connection(
+            &mut conn,
+            &state_clone.graph_index,
+            state_clone.server_id,
+            &identity.pseudonym_id,
+            &other,
+        )
+        .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    for (from_node, to_node) in removed {
+        let _ = state.presence_tx.send(PresenceEvent::EdgeRemoved {
+            from_node,
+            to_node,
+            kind: annex_types::EdgeKind::Connected,
+        });
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for `GET /api/graph/visibility`.
+///
+/// Returns the caller's visibility preferences (the defaults if never set).
+pub async fn get_visibility_preferences_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+) -> Result<Json<VisibilityPreferences>, GraphApiError> {
+    let preferences = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::get_visibility_preferences(&conn, state.server_id, &identity.pseudonym_id)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(preferences))
+}
+
+/// Handler for `PUT /api/graph/visibility`.
+///
+/// Replaces the caller's visibility preferences. They apply to graph profile
+/// lookups and to the presence stream from the next request or event on.
+pub async fn set_visibility_preferences_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Json(preferences): Json<VisibilityPreferences>,
+) -> Result<Json<VisibilityPreferences>, GraphApiError> {
+    let preferences = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        state
+            .visibility_preferences
+            .set(
+                &conn,
+                state.server_id,
+                &identity.pseudonym_id,
+                preferences.clone(),
+            )
+            .map_err(GraphApiError::from)?;
+        Ok::<_, GraphApiError>(preferences)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(preferences))
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlocksResponse {
+    pub blocked: Vec<String>,
+}
+
+/// Handler for `GET /api/graph/blocks`.
+///
+/// Lists the participants the caller has blocked.
+pub async fn list_blocks_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+) -> Result<Json<BlocksResponse>, GraphApiError> {
+    let blocked = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::list_blocks(&conn, state.server_id, &identity.pseudonym_id)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(BlocksResponse { blocked }))
+}
+
+/// Handler for `PUT /api/graph/blocks/{pseudonymId}`.
+///
+/// Blocks a participant, removing any connection with them and broadcasting
+/// `EdgeRemoved` for each edge deleted.
+pub async fn block_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Path(other): Path<String>,
+) -> Result<StatusCode, GraphApiError> {
+    let state_clone = state.clone();
+    let removed = tokio::task::spawn_blocking(move || {
+        let mut conn = state_clone.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::block_participant(
+            &mut conn,
+            &state_clone.graph_index,
+            state_clone.server_id,
+            &identity.pseudonym_id,
+            &other,
+        )
+        .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    for (from_node, to_node) in removed {
+        let _ = state.presence_tx.send(PresenceEvent::EdgeRemoved {
+            from_node,
+            to_node,
+            kind: annex_types::EdgeKind::Connected,
+        });
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for `DELETE /api/graph/blocks/{pseudonymId}`.
+pub async fn unblock_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Path(other): Path<String>,
+) -> Result<StatusCode, GraphApiError> {
+    tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::unblock_participant(&conn, state.server_id, &identity.pseudonym_id, &other)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Serialize)]
+pub struct MutesResponse {
+    pub muted: Vec<String>,
+}
+
+/// Handler for `GET /api/graph/mutes`.
+///
+/// Lists the participants the caller has muted. Mutes are applied by
+/// clients; the server only stores them.
+pub async fn list_mutes_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+) -> Result<Json<MutesResponse>, GraphApiError> {
+    let muted = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::list_mutes(&conn, state.server_id, &identity.pseudonym_id)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(MutesResponse { muted }))
+}
+
+/// Handler for `PUT /api/graph/mutes/{pseudonymId}`.
+pub async fn mute_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Path(other): Path<String>,
+) -> Result<StatusCode, GraphApiError> {
+    tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::mute_participant(&conn, state.server_id, &identity.pseudonym_id, &other)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for `DELETE /api/graph/mutes/{pseudonymId}`.
+pub async fn unmute_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Path(other): Path<String>,
+) -> Result<StatusCode, GraphApiError> {
+    tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::unmute_participant(&conn, state.server_id, &identity.pseudonym_id, &other)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for `GET /api/graph/status`.
+///
+/// Returns the caller's own stored presence status. Statuses are set over
+/// the WebSocket with `set_status`.
+pub async fn get_own_status_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+) -> Result<Json<ParticipantStatus>, GraphApiError> {
+    let status = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::get_participant_status(&conn, state.server_id, &identity.pseudonym_id)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(status))
+}
+
+/// Handler for `GET /api/public/graph/stats`.
+///
+/// Returns differentially private server-level graph statistics. The same
+/// release is served until `graph_analytics.release_interval_secs` elapses,
+/// and indefinitely once `graph_analytics.epsilon_budget` is spent.
+pub async fn get_graph_stats_handler(
+    Extension(state): Extension<Arc<AppState>>,
+) -> Result<Json<GraphAnalyticsRelease>, GraphApiError> {
+    let config = state
+        .policy
+        .read()
+        .map_err(|_| GraphApiError::InternalServerError("server policy lock poisoned".to_string()))?
+        .graph_analytics
+        .clone();
+
+    let release = tokio::task::spawn_blocking(move || {
+        let mut conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        annex_graph::current_analytics_release(&mut conn, state.server_id, &config)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(release))
+}
+
+/// Splits a comma-separated filter, ignoring blank entries.
+fn parse_filter<T>(
+    list: Option<&str>,
+    parse: impl Fn(&str) -> Result<T, GraphError>,
+) -> Result<Vec<T>, GraphApiError> {
+    list.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| parse(s).map_err(GraphApiError::from))
+        .collect()
+}
+
+/// Handler for `GET /api/admin/graph/export`.
+///
+/// Exports the presence graph as GraphML, DOT or JSON-LD, optionally
+/// pseudonymized, filtered by node type and edge kind, or limited to the
+/// neighborhood of one node. Requires `can_moderate` permission.
+pub async fn export_graph_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Query(params): Query<ExportGraphParams>,
+) -> Result<Response, GraphApiError> {
+    if !identity.can_moderate {
+        return Err(GraphApiError::Forbidden(
+            "insufficient permissions to export the graph".to_string(),
+        ));
+    }
+    if params.center.is_none() && params.hops.is_some() {
+        return Err(GraphApiError::BadRequest(
+            "hops requires center".to_string(),
+        ));
+    }
+    let hops = params.hops.unwrap_or(1);
+    if hops > MAX_BFS_DEPTH {
+        return Err(GraphApiError::BadRequest(format!(
+            "hops must be <= {MAX_BFS_DEPTH}, got {hops}"
+        )));
+    }
+
+    let format = params.format;
+    let pseudonymize_salt = params.pseudonymize.then(|| {
+        params.salt.clone().unwrap_or_else(|| {
+            let mut salt = [0u8; 16];
+            rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut salt);
+            hex::encode(salt)
+        })
+    });
+    let mut options = ExportOptions {
+        node_types: parse_filter(params.node_types.as_deref(), annex_graph::parse_node_type)?,
+        edge_kinds: parse_filter(params.edge_kinds.as_deref(), annex_graph::parse_edge_kind)?,
+        pseudonyms: None,
+        pseudonymize_salt,
+    };
+
+    let body = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+
+        if let Some(center) = &params.center {
+            if annex_graph::get_graph_node(&conn, state.server_id, center)?.is_none() {
+                return Err(GraphApiError::NotFound(format!("node not found: {center}")));
+            }
+            options.pseudonyms = Some(state.graph_index.neighborhood(
+                &conn,
+                state.server_id,
+                center,
+                hops,
+            )?);
+        }
+
+        annex_graph::export_graph(&conn, state.server_id, &options, format)
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"annex-graph.{}\"",
+                    format.extension()
+                ),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Handler for `GET /api/admin/graph/history/snapshot`.
+///
+/// Reconstructs the graph as it was at a past timestamp, including edges
+/// deleted and nodes pruned since. Timestamps older than
+/// `graph_history_retention_days` are rejected.
+pub async fn graph_snapshot_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Query(params): Query<GraphSnapshotParams>,
+) -> Result<Json<GraphSnapshot>, GraphApiError> {
+    if !identity.can_moderate {
+        return Err(GraphApiError::Forbidden(
+            "insufficient permissions to view graph history".to_string(),
+        ));
+    }
+    let retention_days = state
+        .policy
+        .read()
+        .map_err(|_| GraphApiError::InternalServerError("server policy lock poisoned".to_string()))?
+        .graph_history_retention_days;
+
+    let snapshot = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        let at = annex_graph::resolve_history_timestamp(&conn, &params.at, retention_days)?;
+        annex_graph::graph_snapshot_at(&conn, state.server_id, &at, params.pseudonym_id.as_deref())
+            .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(snapshot))
+}
+
+/// Handler for `GET /api/admin/graph/history/changes`.
+///
+/// Lists the node and edge change log within the retention window.
+pub async fn graph_changes_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(crate::middleware::IdentityContext(identity)): Extension<
+        crate::middleware::IdentityContext,
+    >,
+    Query(params): Query<GraphChangesParams>,
+) -> Result<Json<GraphChanges>, GraphApiError> {
+    if !identity.can_moderate {
+        return Err(GraphApiError::Forbidden(
+            "insufficient permissions to view graph history".to_string(),
+        ));
+    }
+    let retention_days = state
+        .policy
+        .read()
+        .map_err(|_| GraphApiError::InternalServerError("server policy lock poisoned".to_string()))?
+        .graph_history_retention_days;
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+
+    let changes = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| {
+            GraphApiError::InternalServerError(format!("db connection failed: {}", e))
+        })?;
+        let since = match &params.since {
+            Some(since) => Some(annex_graph::resolve_history_timestamp(
+                &conn,
+                since,
+                retention_days,
+            )?),
+            None => annex_graph::history_horizon(&conn, retention_days)?,
+        };
+        let until = annex_graph::resolve_history_timestamp(
+            &conn,
+            params.until.as_deref().unwrap_or("now"),
+            0,
+        )?;
+        annex_graph::graph_changes(
+            &conn,
+            state.server_id,
+            since.as_deref(),
+            &until,
+            params.pseudonym_id.as_deref(),
+            limit,
+        )
+        .map_err(GraphApiError::from)
+    })
+    .await
+    .map_err(|e| GraphApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(changes))
+}
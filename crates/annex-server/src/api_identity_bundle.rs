@@ -0,0 +1,467 @@
+//! Portable identity bundles for moving between servers.
+//!
+//! `GET /api/registry/export?audience=<url>` packages the caller's commitment,
+//! the VRP topics they hold pseudonyms under, their usernames, and their voice
+//! profile into an [`IdentityBundle`] addressed to the target server and signed
+//! with this server's Ed25519 key.
+//!
+//! `POST /api/registry/import` accepts a [`SignedIdentityBundle`] from a known
+//! federation peer, verifies the attestation against the peer's registered
+//! public key, checks the bundle is addressed to this server, at most
+//! [`IDENTITY_BUNDLE_MAX_AGE_SECS`] old and not imported before, re-registers
+//! the commitment in the local Merkle tree, and records an
+//! `identity_provenance` row. Usernames and the voice profile are
+//! restored lazily when the holder next proves membership for the matching
+//! topic (see [`restore_imported_profile`]), since pseudonyms only exist once
+//! the holder has proved control of the commitment here.
+
+use crate::{
+    api::{ApiError, RegisterResponse},
+    api_usernames::{decrypt_username, encrypt_username},
+    middleware::IdentityContext,
+    AppState,
+};
+use annex_federation::{
+    BundleUsername, BundleVoiceProfile, IdentityBundle, SignedIdentityBundle,
+    IDENTITY_BUNDLE_VERSION,
+};
+use annex_identity::{stage_identity, RoleCode};
+use annex_observe::EventPayload;
+use axum::extract::{Extension, Json, Query};
+use ed25519_dalek::{Signature, Signer, Verifier, VerifyingKey as EdVerifyingKey};
+use rusqlite::{params, OptionalExtension};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// How long after issue a bundle may be imported.
+pub const IDENTITY_BUNDLE_MAX_AGE_SECS: i64 = 3600;
+/// Allowance for clock drift between the origin and this server.
+const IDENTITY_BUNDLE_CLOCK_SKEW_SECS: i64 = 300;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportIdentityParams {
+    /// Base URL of the server the bundle will be imported into.
+    pub audience: String,
+}
+
+/// Constructs the deterministic signing payload for an identity bundle.
+///
+/// The payload is a version-tagged domain separator followed by the bundle's
+/// JSON encoding. Field order is fixed by the struct definition, so the
+/// issuer and verifier produce identical bytes for the same bundle.
+pub fn identity_bundle_signing_payload(
+    bundle: &IdentityBundle,
+) -> Result<String, serde_json::Error> {
+    Ok(format!(
+        "annex-identity-bundle-v{}\n{}",
+        bundle.version,
+        serde_json::to_string(bundle)?
+    ))
+}
+
+/// Handler for `GET /api/registry/export`.
+///
+/// Exports the authenticated identity as a signed, portable bundle that only
+/// the `audience` server will import.
+pub async fn export_identity_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Query(params): Query<ExportIdentityParams>,
+) -> Result<Json<SignedIdentityBundle>, ApiError> {
+    let origin_server = state.get_public_url();
+    let audience = params.audience.trim().trim_end_matches('/').to_string();
+    if audience.is_empty() {
+        return Err(ApiError::BadRequest("audience is required".to_string()));
+    }
+    if audience == origin_server {
+        return Err(ApiError::BadRequest(
+            "audience must be another server".to_string(),
+        ));
+    }
+    let state_clone = state.clone();
+
+    let bundle = tokio::task::spawn_blocking(move || {
+        let conn = state_clone
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+
+        // 1. Resolve the caller's commitment from their pseudonym.
+        let commitment_hex: String = conn
+            .query_row(
+                "SELECT commitment_hex FROM zk_nullifiers
+                 WHERE pseudonym_id = ?1 AND commitment_hex IS NOT NULL",
+                params![identity.pseudonym_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| ApiError::InternalServerError(format!("db query failed: {}", e)))?
+            .ok_or_else(|| {
+                ApiError::NotFound("no local commitment for this pseudonym".to_string())
+            })?;
+
+        let (role_code, node_id): (u8, i64) = conn
+            .query_row(
+                "SELECT role_code, node_id FROM vrp_identities WHERE commitment_hex = ?1",
+                params![commitment_hex],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| ApiError::InternalServerError(format!("db query failed: {}", e)))?
+            .ok_or_else(|| ApiError::NotFound("identity not found in registry".to_string()))?;
+
+        // 2. Collect every topic the commitment holds a pseudonym under.
+        let mut stmt = conn
+            .prepare(
+                "SELECT topic, pseudonym_id FROM zk_nullifiers
+                 WHERE commitment_hex = ?1 AND pseudonym_id IS NOT NULL
+                 ORDER BY topic",
+            )
+            .map_err(|e| ApiError::InternalServerError(format!("query prepare failed: {}", e)))?;
+        let memberships = stmt
+            .query_map(params![commitment_hex], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| ApiError::InternalServerError(format!("query failed: {}", e)))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ApiError::InternalServerError(format!("row read failed: {}", e)))?;
+
+        // 3. Decrypt usernames and find the assigned voice profile.
+        let mut usernames = Vec::new();
+        let mut voice_profile = None;
+        for (topic, pseudonym_id) in &memberships {
+            let encrypted: Option<String> = conn
+                .query_row(
+                    "SELECT encrypted_username FROM user_profiles
+                     WHERE server_id = ?1 AND pseudonym_id = ?2",
+                    params![state_clone.server_id, pseudonym_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| ApiError::InternalServerError(format!("db query failed: {}", e)))?;
+            if let Some(username) = encrypted
+                .and_then(|enc| decrypt_username(&state_clone.signing_key, pseudonym_id, &enc))
+            {
+                usernames.push(BundleUsername {
+                    topic: topic.clone(),
+                    username,
+                });
+            }
+
+            if voice_profile.is_none() {
+                voice_profile = conn
+                    .query_row(
+                        "SELECT vp.profile_id, vp.name, vp.model, vp.speed, vp.pitch, vp.speaker_id
+                         FROM agent_registrations ar
+                         JOIN voice_profiles vp ON ar.voice_profile_id = vp.id
+                         WHERE ar.server_id = ?1 AND ar.pseudonym_id = ?2",
+                        params![state_clone.server_id, pseudonym_id],
+                        |row| {
+                            Ok(BundleVoiceProfile {
+                                profile_id: row.get(0)?,
+                                name: row.get(1)?,
+                                model: row.get(2)?,
+                                speed: row.get(3)?,
+                                pitch: row.get(4)?,
+                                speaker_id: row.get(5)?,
+                            })
+                        },
+                    )
+                    .optional()
+                    .map_err(|e| {
+                        ApiError::InternalServerError(format!("db query failed: {}", e))
+                    })?;
+            }
+        }
+
+        Ok::<IdentityBundle, ApiError>(IdentityBundle {
+            version: IDENTITY_BUNDLE_VERSION,
+            origin_server,
+            origin_public_key: hex::encode(state_clone.signing_key.verifying_key().as_bytes()),
+            audience,
+            nonce: hex::encode(rand::random::<[u8; 16]>()),
+            commitment: commitment_hex,
+            role_code,
+            node_id,
+            topics: memberships.into_iter().map(|(topic, _)| topic).collect(),
+            usernames,
+            voice_profile,
+            issued_at: chrono::Utc::now().to_rfc3339(),
+        })
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    // 4. Attest with this server's signing key.
+    let payload = identity_bundle_signing_payload(&bundle)
+        .map_err(|e| ApiError::InternalServerError(format!("failed to encode bundle: {}", e)))?;
+    let signature = state.signing_key.sign(payload.as_bytes());
+
+    Ok(Json(SignedIdentityBundle {
+        bundle,
+        signature: hex::encode(signature.to_bytes()),
+    }))
+}
+
+/// Handler for `POST /api/registry/import`.
+///
+/// Verifies a bundle issued by a known federation peer and re-registers its
+/// commitment here, returning the same response as `/api/registry/register`
+/// so the holder can immediately generate membership proofs.
+pub async fn import_identity_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Json(payload): Json<SignedIdentityBundle>,
+) -> Result<Json<RegisterResponse>, ApiError> {
+    let bundle = payload.bundle;
+
+    if bundle.version != IDENTITY_BUNDLE_VERSION {
+        return Err(ApiError::BadRequest(format!(
+            "unsupported bundle version: {}",
+            bundle.version
+        )));
+    }
+    if bundle.origin_server == state.get_public_url() {
+        return Err(ApiError::BadRequest(
+            "bundle was issued by this server".to_string(),
+        ));
+    }
+    if bundle.audience != state.get_public_url() {
+        return Err(ApiError::Forbidden(
+            "bundle is addressed to another server".to_string(),
+        ));
+    }
+    let issued_at = chrono::DateTime::parse_from_rfc3339(&bundle.issued_at)
+        .map_err(|e| ApiError::BadRequest(format!("invalid issued_at: {}", e)))?;
+    let age = chrono::Utc::now()
+        .signed_duration_since(issued_at)
+        .num_seconds();
+    if !(-IDENTITY_BUNDLE_CLOCK_SKEW_SECS..=IDENTITY_BUNDLE_MAX_AGE_SECS).contains(&age) {
+        return Err(ApiError::Forbidden(
+            "bundle has expired or is not yet valid".to_string(),
+        ));
+    }
+    let role = RoleCode::from_u8(bundle.role_code)
+        .ok_or_else(|| ApiError::BadRequest(format!("invalid role code: {}", bundle.role_code)))?;
+
+    let result =
+        tokio::task::spawn_blocking(move || {
+            let mut conn = state.pool.get().map_err(|e| {
+                ApiError::InternalServerError(format!("db connection failed: {}", e))
+            })?;
+
+            // 1. The origin must be a known peer, and the bundle must name its key.
+            let (origin_instance_id, public_key_hex, status): (i64, String, String) = conn
+                .query_row(
+                    "SELECT id, public_key, status FROM instances WHERE base_url = ?1",
+                    params![bundle.origin_server],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()
+                .map_err(|e| ApiError::InternalServerError(format!("db query failed: {}", e)))?
+                .ok_or_else(|| {
+                    ApiError::Forbidden(format!("unknown origin server: {}", bundle.origin_server))
+                })?;
+
+            if status != "ACTIVE" {
+                return Err(ApiError::Forbidden(format!(
+                    "instance {} is not active",
+                    bundle.origin_server
+                )));
+            }
+            if !public_key_hex.eq_ignore_ascii_case(&bundle.origin_public_key) {
+                return Err(ApiError::Unauthorized(
+                    "bundle key does not match the origin server's registered key".to_string(),
+                ));
+            }
+
+            // 2. Verify the origin's attestation.
+            let public_key_bytes = hex::decode(&public_key_hex)
+                .map_err(|e| ApiError::Unauthorized(format!("invalid public key hex: {}", e)))?;
+            let signature_bytes = hex::decode(&payload.signature)
+                .map_err(|e| ApiError::Unauthorized(format!("invalid signature hex: {}", e)))?;
+            let public_key =
+                EdVerifyingKey::from_bytes(&public_key_bytes.try_into().map_err(|_| {
+                    ApiError::Unauthorized("invalid public key length".to_string())
+                })?)
+                .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+            let signature = Signature::from_bytes(
+                &signature_bytes
+                    .try_into()
+                    .map_err(|_| ApiError::Unauthorized("invalid signature length".to_string()))?,
+            );
+            let signing_payload = identity_bundle_signing_payload(&bundle)
+                .map_err(|e| ApiError::BadRequest(format!("failed to encode bundle: {}", e)))?;
+            public_key
+                .verify(signing_payload.as_bytes(), &signature)
+                .map_err(|e| ApiError::Unauthorized(format!("invalid bundle signature: {}", e)))?;
+
+            // 3-5 run in one transaction, so a commitment is never registered
+            // without its provenance and a bundle is never imported twice. The
+            // tree lock is held until the staged leaf is applied.
+            let mut tree = state.merkle_tree.lock().map_err(|_| {
+                ApiError::InternalServerError("merkle tree lock poisoned".to_string())
+            })?;
+            let tx = conn
+                .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)
+                .map_err(|e| {
+                    ApiError::InternalServerError(format!("db transaction failed: {}", e))
+                })?;
+
+            // 3. Each bundle is imported at most once, whatever its commitment.
+            let replayed: bool = tx
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM identity_provenance
+                 WHERE origin_instance_id = ?1 AND bundle_nonce = ?2)",
+                    params![origin_instance_id, bundle.nonce],
+                    |row| row.get(0),
+                )
+                .map_err(|e| ApiError::InternalServerError(format!("db query failed: {}", e)))?;
+            if replayed {
+                return Err(ApiError::Conflict(
+                    "bundle has already been imported".to_string(),
+                ));
+            }
+
+            // 4. Re-register the commitment locally.
+            let staged = stage_identity(&tree, &tx, &bundle.commitment, role, bundle.node_id)
+                .map_err(|e| match e {
+                    annex_identity::IdentityError::InvalidCommitmentFormat
+                    | annex_identity::IdentityError::InvalidHex => {
+                        ApiError::BadRequest(e.to_string())
+                    }
+                    annex_identity::IdentityError::DuplicateCommitment(_) => {
+                        ApiError::Conflict(e.to_string())
+                    }
+                    _ => ApiError::InternalServerError(e.to_string()),
+                })?;
+
+            // 5. Record where the identity came from.
+            let commitment_hex = bundle.commitment.to_ascii_lowercase();
+            let bundle_json = serde_json::to_string(&bundle).map_err(|e| {
+                ApiError::InternalServerError(format!("failed to encode bundle: {}", e))
+            })?;
+            tx.execute(
+                "INSERT INTO identity_provenance (
+                server_id, commitment_hex, origin_instance_id, origin_server,
+                bundle_json, signature, issued_at, bundle_nonce
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    state.server_id,
+                    commitment_hex,
+                    origin_instance_id,
+                    bundle.origin_server,
+                    bundle_json,
+                    payload.signature,
+                    bundle.issued_at,
+                    bundle.nonce
+                ],
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::SqliteFailure(err, _)
+                    if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+                {
+                    ApiError::Conflict("bundle has already been imported".to_string())
+                }
+                e => ApiError::InternalServerError(format!("failed to record provenance: {}", e)),
+            })?;
+
+            tx.commit()
+                .map_err(|e| ApiError::InternalServerError(format!("db commit failed: {}", e)))?;
+            let registration = staged
+                .apply(&mut tree)
+                .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+            drop(tree);
+
+            let observe_payload = EventPayload::IdentityImported {
+                commitment_hex: commitment_hex.clone(),
+                role_code: role.as_u8(),
+                origin_server: bundle.origin_server.clone(),
+            };
+            crate::emit_and_broadcast(
+                &conn,
+                state.server_id,
+                &commitment_hex,
+                &observe_payload,
+                &state.observe_tx,
+            );
+
+            tracing::info!(
+                origin = %bundle.origin_server,
+                topics = bundle.topics.len(),
+                "imported identity bundle"
+            );
+
+            Ok(registration)
+        })
+        .await
+        .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(RegisterResponse {
+        identity_id: result.identity_id,
+        leaf_index: result.leaf_index,
+        root_hex: result.root_hex,
+        path_elements: result.path_elements,
+        path_indices: result.path_indices,
+    }))
+}
+
+/// Restores profile data from an imported bundle once the holder has derived
+/// a local pseudonym for `topic`.
+///
+/// Sets the username recorded for the topic (if usernames are enabled and the
+/// pseudonym has none yet) and links the bundle's voice profile to the
+/// pseudonym's agent registration when a profile with the same ID exists
+/// here. Does nothing for identities that were not imported.
+pub(crate) fn restore_imported_profile(
+    conn: &rusqlite::Connection,
+    state: &AppState,
+    commitment_hex: &str,
+    topic: &str,
+    pseudonym_id: &str,
+) -> Result<(), rusqlite::Error> {
+    let bundle_json: Option<String> = conn
+        .query_row(
+            "SELECT bundle_json FROM identity_provenance
+             WHERE server_id = ?1 AND commitment_hex = ?2",
+            params![state.server_id, commitment_hex.to_ascii_lowercase()],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(bundle) = bundle_json.and_then(|json| {
+        serde_json::from_str::<IdentityBundle>(&json)
+            .map_err(|e| tracing::warn!(error = %e, "unreadable identity provenance bundle"))
+            .ok()
+    }) else {
+        return Ok(());
+    };
+
+    let usernames_enabled = state
+        .policy
+        .read()
+        .map(|p| p.usernames_enabled)
+        .unwrap_or(false);
+    if usernames_enabled {
+        if let Some(entry) = bundle.usernames.iter().find(|u| u.topic == topic) {
+            let encrypted = encrypt_username(&state.signing_key, pseudonym_id, &entry.username);
+            conn.execute(
+                "INSERT OR IGNORE INTO user_profiles (server_id, pseudonym_id, encrypted_username)
+                 VALUES (?1, ?2, ?3)",
+                params![state.server_id, pseudonym_id, encrypted],
+            )?;
+        }
+    }
+
+    if let Some(voice) = &bundle.voice_profile {
+        conn.execute(
+            "UPDATE agent_registrations
+             SET voice_profile_id = (
+                 SELECT id FROM voice_profiles WHERE server_id = ?1 AND profile_id = ?3
+             )
+             WHERE server_id = ?1 AND pseudonym_id = ?2 AND voice_profile_id IS NULL
+               AND EXISTS (SELECT 1 FROM voice_profiles WHERE server_id = ?1 AND profile_id = ?3)",
+            params![state.server_id, pseudonym_id, voice.profile_id],
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,513 @@
+//! Anonymous channel polls.
+//!
+//! Poll management is authenticated like the rest of the channel API, but
+//! voting is not: a ballot carries only a `vote.circom` proof. When a poll is
+//! created the server snapshots the identity commitments of the channel's
+//! members into a Merkle tree, and the proof must show inclusion in that
+//! tree, so channel eligibility is enforced by the circuit without the server
+//! learning who voted. The proof also exposes a poll-scoped nullifier,
+//! recorded under the topic `annex:poll:<poll_id>` via
+//! [`check_nullifier_exists`] / [`insert_nullifier`], so each identity votes
+//! once however many pseudonyms it holds.
+//!
+//! Tallies are withheld until the poll closes, both from API responses and
+//! from channel broadcasts, so ballot timing cannot be matched against
+//! channel activity.
+
+use crate::api::ApiError;
+use crate::api_federation::find_commitment_for_pseudonym;
+use crate::api_ws::{OutgoingMessage, WsPollPayload};
+use crate::middleware::IdentityContext;
+use crate::AppState;
+use annex_channels::{
+    close_poll, create_poll, get_channel, get_poll, is_member, is_poll_closed, list_members,
+    list_poll_eligibility, list_polls, record_poll_vote, tally_poll, ChannelError,
+    CreatePollParams, Poll, PollTally,
+};
+use annex_identity::vote::{field_to_hex, poll_topic, poll_topic_hash, poll_vote_hash};
+use annex_identity::zk::{parse_fr_from_hex, parse_proof, parse_public_signals, verify_proof};
+use annex_identity::{check_nullifier_exists, insert_nullifier, IdentityError, MerkleTree};
+use axum::extract::{Extension, Json, Path};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Maximum length for a poll question.
+const MAX_QUESTION_LEN: usize = 1024;
+/// Maximum length for a single option label.
+const MAX_OPTION_LEN: usize = 256;
+/// Minimum and maximum number of options per poll.
+const MIN_OPTIONS: usize = 2;
+const MAX_OPTIONS: usize = 16;
+/// Maximum poll duration (30 days).
+const MAX_DURATION_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Request body for `POST /api/channels/{channelId}/polls`.
+#[derive(Debug, Deserialize)]
+pub struct CreatePollRequest {
+    pub question: String,
+    pub options: Vec<String>,
+    /// Seconds until the poll closes automatically (None = manual close).
+    pub duration_secs: Option<u64>,
+}
+
+/// Request body for `POST /api/polls/{pollId}/vote`.
+#[derive(Debug, Deserialize)]
+pub struct VoteRequest {
+    /// Index of the chosen option.
+    #[serde(rename = "optionIndex")]
+    pub option_index: u32,
+    /// Groth16 proof from `vote.circom` (snarkjs JSON).
+    pub proof: serde_json::Value,
+    /// Public signals: `[root, nullifier, topicHash, voteHash]`.
+    #[serde(rename = "publicSignals")]
+    pub public_signals: Vec<String>,
+}
+
+/// A poll together with its tally, which is only present once the poll has
+/// closed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PollResponse {
+    #[serde(flatten)]
+    pub poll: Poll,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counts: Option<Vec<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_votes: Option<u64>,
+}
+
+impl PollResponse {
+    fn new(poll: Poll, tally: PollTally) -> Self {
+        let closed = poll.closed_at.is_some();
+        Self {
+            poll,
+            counts: closed.then_some(tally.counts),
+            total_votes: closed.then_some(tally.total_votes),
+        }
+    }
+}
+
+/// Response body for `GET /api/channels/{channelId}/polls/{pollId}/eligibility`.
+///
+/// Clients build the Merkle path for their own commitment from `leaves`
+/// locally, so fetching it does not reveal which member is about to vote.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PollEligibilityResponse {
+    pub root: String,
+    pub leaves: Vec<String>,
+}
+
+/// Response body for a successful vote.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VoteResponse {
+    pub ok: bool,
+}
+
+fn channel_err(e: ChannelError) -> ApiError {
+    match e {
+        ChannelError::NotFound(id) => ApiError::NotFound(format!("not found: {}", id)),
+        ChannelError::PollClosed(id) => ApiError::Conflict(format!("poll is closed: {}", id)),
+        ChannelError::InvalidPollOption(i) => {
+            ApiError::BadRequest(format!("invalid poll option: {}", i))
+        }
+        other => ApiError::InternalServerError(format!("poll operation failed: {}", other)),
+    }
+}
+
+fn db_err(e: impl std::fmt::Display) -> ApiError {
+    ApiError::InternalServerError(format!("db error: {}", e))
+}
+
+/// Checks that the requester may see polls in the channel (member or moderator).
+fn ensure_channel_access(
+    conn: &rusqlite::Connection,
+    server_id: i64,
+    channel_id: &str,
+    identity: &annex_identity::PlatformIdentity,
+) -> Result<(), ApiError> {
+    get_channel(conn, channel_id).map_err(channel_err)?;
+    if identity.can_moderate {
+        return Ok(());
+    }
+    let member = is_member(conn, server_id, channel_id, &identity.pseudonym_id).map_err(db_err)?;
+    if !member {
+        return Err(ApiError::Forbidden("not a channel member".to_string()));
+    }
+    Ok(())
+}
+
+/// Loads a poll and checks it is attached to `channel_id`.
+fn load_channel_poll(
+    conn: &rusqlite::Connection,
+    channel_id: &str,
+    poll_id: &str,
+) -> Result<Poll, ApiError> {
+    let poll = get_poll(conn, poll_id).map_err(channel_err)?;
+    if poll.channel_id != channel_id {
+        return Err(ApiError::NotFound(format!("poll not found: {}", poll_id)));
+    }
+    Ok(poll)
+}
+
+/// Builds the eligibility snapshot for a new poll in `channel_id`.
+///
+/// Leaves are the distinct identity commitments behind the channel's members,
+/// sorted so the tree does not encode join order. Members without a local
+/// commitment (e.g. federated participants) cannot produce a vote proof and
+/// are left out. Returns the root and the leaves in order.
+fn build_eligibility_snapshot(
+    conn: &rusqlite::Connection,
+    channel_id: &str,
+    depth: usize,
+) -> Result<(String, Vec<String>), ApiError> {
+    let mut commitments = BTreeSet::new();
+    for member in list_members(conn, channel_id).map_err(channel_err)? {
+        if let Some((commitment_hex, _)) =
+            find_commitment_for_pseudonym(conn, &member.pseudonym_id).map_err(db_err)?
+        {
+            commitments.insert(commitment_hex);
+        }
+    }
+
+    let mut tree = MerkleTree::new(depth).map_err(db_err)?;
+    for commitment_hex in &commitments {
+        let leaf = parse_fr_from_hex(commitment_hex).map_err(db_err)?;
+        tree.insert(leaf).map_err(|e| match e {
+            IdentityError::TreeFull => {
+                ApiError::BadRequest("channel has too many members for a poll".to_string())
+            }
+            other => db_err(other),
+        })?;
+    }
+    Ok((tree.root_hex(), commitments.into_iter().collect()))
+}
+
+/// Serializes and broadcasts a poll event to the poll's channel subscribers.
+async fn broadcast_poll(state: &AppState, message: OutgoingMessage, channel_id: &str) {
+    match serde_json::to_string(&message) {
+        Ok(json) => state.connection_manager.broadcast(channel_id, json).await,
+        Err(e) => {
+            tracing::error!(channel_id = %channel_id, "failed to serialize poll event: {}", e)
+        }
+    }
+}
+
+/// Handler for `POST /api/channels/{channelId}/polls`.
+pub async fn create_poll_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(channel_id): Path<String>,
+    Json(payload): Json<CreatePollRequest>,
+) -> Result<Json<PollResponse>, ApiError> {
+    let question = payload.question.trim().to_string();
+    if question.is_empty() || question.len() > MAX_QUESTION_LEN {
+        return Err(ApiError::BadRequest("invalid question length".to_string()));
+    }
+    let options: Vec<String> = payload
+        .options
+        .iter()
+        .map(|o| o.trim().to_string())
+        .collect();
+    if options.len() < MIN_OPTIONS || options.len() > MAX_OPTIONS {
+        return Err(ApiError::BadRequest(format!(
+            "polls need between {} and {} options",
+            MIN_OPTIONS, MAX_OPTIONS
+        )));
+    }
+    if options
+        .iter()
+        .any(|o| o.is_empty() || o.len() > MAX_OPTION_LEN)
+    {
+        return Err(ApiError::BadRequest("invalid option length".to_string()));
+    }
+    if let Some(secs) = payload.duration_secs {
+        if secs == 0 || secs > MAX_DURATION_SECS {
+            return Err(ApiError::BadRequest("invalid poll duration".to_string()));
+        }
+    }
+
+    let task_state = state.clone();
+    let poll = tokio::task::spawn_blocking(move || {
+        // Proofs are generated against a tree of the same depth as the
+        // identity registry.
+        let depth = task_state
+            .merkle_tree
+            .lock()
+            .map_err(|_| ApiError::InternalServerError("merkle tree lock poisoned".to_string()))?
+            .depth;
+
+        let mut conn = task_state.pool.get().map_err(db_err)?;
+        ensure_channel_access(&conn, task_state.server_id, &channel_id, &identity)?;
+
+        let tx = conn.transaction().map_err(db_err)?;
+        let (eligibility_root_hex, eligible_commitments) =
+            build_eligibility_snapshot(&tx, &channel_id, depth)?;
+        let params = CreatePollParams {
+            server_id: task_state.server_id,
+            poll_id: uuid::Uuid::new_v4().to_string(),
+            channel_id,
+            question,
+            options,
+            created_by: identity.pseudonym_id.clone(),
+            duration_secs: payload.duration_secs,
+            eligibility_root_hex,
+            eligible_commitments,
+        };
+        let poll = create_poll(&tx, &params).map_err(channel_err)?;
+        tx.commit().map_err(db_err)?;
+        Ok::<_, ApiError>(poll)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    let tally = PollTally {
+        poll_id: poll.poll_id.clone(),
+        counts: vec![0; poll.options.len()],
+        total_votes: 0,
+    };
+    broadcast_poll(
+        &state,
+        OutgoingMessage::PollCreated(WsPollPayload::new(poll.clone(), tally.clone())),
+        &poll.channel_id,
+    )
+    .await;
+
+    Ok(Json(PollResponse::new(poll, tally)))
+}
+
+/// Handler for `GET /api/channels/{channelId}/polls`.
+pub async fn list_polls_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(channel_id): Path<String>,
+) -> Result<Json<Vec<PollResponse>>, ApiError> {
+    let polls = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(db_err)?;
+        ensure_channel_access(&conn, state.server_id, &channel_id, &identity)?;
+        let polls = list_polls(&conn, &channel_id).map_err(channel_err)?;
+        polls
+            .into_iter()
+            .map(|poll| {
+                let tally = tally_poll(&conn, &poll.poll_id).map_err(channel_err)?;
+                Ok(PollResponse::new(poll, tally))
+            })
+            .collect::<Result<Vec<_>, ApiError>>()
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(polls))
+}
+
+/// Handler for `GET /api/channels/{channelId}/polls/{pollId}`.
+pub async fn get_poll_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path((channel_id, poll_id)): Path<(String, String)>,
+) -> Result<Json<PollResponse>, ApiError> {
+    let response = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(db_err)?;
+        ensure_channel_access(&conn, state.server_id, &channel_id, &identity)?;
+        let poll = load_channel_poll(&conn, &channel_id, &poll_id)?;
+        let tally = tally_poll(&conn, &poll_id).map_err(channel_err)?;
+        Ok::<_, ApiError>(PollResponse::new(poll, tally))
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(response))
+}
+
+/// Handler for `GET /api/channels/{channelId}/polls/{pollId}/eligibility`.
+pub async fn get_poll_eligibility_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path((channel_id, poll_id)): Path<(String, String)>,
+) -> Result<Json<PollEligibilityResponse>, ApiError> {
+    let response = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(db_err)?;
+        ensure_channel_access(&conn, state.server_id, &channel_id, &identity)?;
+        let poll = load_channel_poll(&conn, &channel_id, &poll_id)?;
+        let root = poll.eligibility_root_hex.ok_or_else(|| {
+            ApiError::NotFound(format!("poll has no eligibility snapshot: {}", poll_id))
+        })?;
+        let leaves = list_poll_eligibility(&conn, &poll_id).map_err(channel_err)?;
+        Ok::<_, ApiError>(PollEligibilityResponse { root, leaves })
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(response))
+}
+
+/// Handler for `POST /api/channels/{channelId}/polls/{pollId}/close`.
+///
+/// Only the poll author or a moderator may close a poll early.
+pub async fn close_poll_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path((channel_id, poll_id)): Path<(String, String)>,
+) -> Result<Json<PollResponse>, ApiError> {
+    let task_state = state.clone();
+    let (poll, tally, newly_closed) = tokio::task::spawn_blocking(move || {
+        let conn = task_state.pool.get().map_err(db_err)?;
+        let poll = load_channel_poll(&conn, &channel_id, &poll_id)?;
+        if poll.created_by != identity.pseudonym_id && !identity.can_moderate {
+            return Err(ApiError::Forbidden(
+                "only the poll author or a moderator can close a poll".to_string(),
+            ));
+        }
+        let newly_closed = close_poll(&conn, &poll_id).map_err(channel_err)?;
+        let poll = get_poll(&conn, &poll_id).map_err(channel_err)?;
+        let tally = tally_poll(&conn, &poll_id).map_err(channel_err)?;
+        Ok((poll, tally, newly_closed))
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    if newly_closed {
+        broadcast_poll(
+            &state,
+            OutgoingMessage::PollClosed(WsPollPayload::new(poll.clone(), tally.clone())),
+            &poll.channel_id,
+        )
+        .await;
+    }
+
+    Ok(Json(PollResponse::new(poll, tally)))
+}
+
+/// Handler for `POST /api/polls/{pollId}/vote`.
+///
+/// Unauthenticated: the proof alone shows the voter was a channel member when
+/// the poll was created. The ballot is recorded against the proof's
+/// nullifier only, and nothing is broadcast until the poll closes.
+pub async fn vote_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(poll_id): Path<String>,
+    Json(payload): Json<VoteRequest>,
+) -> Result<Json<VoteResponse>, ApiError> {
+    tokio::task::spawn_blocking(move || {
+        let mut conn = state.pool.get().map_err(db_err)?;
+
+        let poll = get_poll(&conn, &poll_id).map_err(channel_err)?;
+        if payload.option_index as usize >= poll.options.len() {
+            return Err(ApiError::BadRequest(format!(
+                "invalid poll option: {}",
+                payload.option_index
+            )));
+        }
+
+        if is_poll_closed(&conn, &poll.poll_id).map_err(channel_err)? {
+            return Err(ApiError::Conflict(format!(
+                "poll is closed: {}",
+                poll.poll_id
+            )));
+        }
+
+        // 1. Public signals must bind this poll and the selected option.
+        let public_signals_json = serde_json::to_string(&payload.public_signals).map_err(|e| {
+            ApiError::BadRequest(format!("failed to serialize public signals: {}", e))
+        })?;
+        let public_signals = parse_public_signals(&public_signals_json)
+            .map_err(|e| ApiError::BadRequest(format!("invalid public signals format: {}", e)))?;
+
+        // vote.circom public signals: [root, nullifier, topicHash, voteHash]
+        if public_signals.len() != 4 {
+            return Err(ApiError::BadRequest(
+                "invalid number of public signals".to_string(),
+            ));
+        }
+        if public_signals[2] != poll_topic_hash(&poll.poll_id) {
+            return Err(ApiError::BadRequest(
+                "proof topic does not match poll".to_string(),
+            ));
+        }
+        if public_signals[3] != poll_vote_hash(&poll.poll_id, payload.option_index) {
+            return Err(ApiError::BadRequest(
+                "proof vote does not match selected option".to_string(),
+            ));
+        }
+
+        // 2. The proof must be against this poll's eligibility tree.
+        let root_hex = field_to_hex(&public_signals[0]);
+        if poll.eligibility_root_hex.as_deref() != Some(root_hex.as_str()) {
+            return Err(ApiError::Conflict(format!(
+                "root is not this poll's eligibility root: {}",
+                root_hex
+            )));
+        }
+
+        // 3. Verify proof.
+        let proof = parse_proof(&payload.proof.to_string())
+            .map_err(|e| ApiError::BadRequest(format!("invalid proof format: {}", e)))?;
+        let valid = verify_proof(&state.vote_vkey, &proof, &public_signals)
+            .map_err(|e| ApiError::Unauthorized(format!("proof verification failed: {}", e)))?;
+        if !valid {
+            return Err(ApiError::Unauthorized("invalid proof".to_string()));
+        }
+
+        // 4. Spend the nullifier and record the ballot atomically.
+        let topic = poll_topic(&poll.poll_id);
+        let nullifier_hex = field_to_hex(&public_signals[1]);
+
+        let tx = conn.transaction().map_err(db_err)?;
+        if check_nullifier_exists(&tx, &topic, &nullifier_hex).map_err(db_err)? {
+            return Err(ApiError::Conflict("already voted in this poll".to_string()));
+        }
+        insert_nullifier(&tx, &topic, &nullifier_hex, None, None).map_err(|e| match e {
+            IdentityError::DuplicateNullifier(_) => {
+                ApiError::Conflict("already voted in this poll".to_string())
+            }
+            other => db_err(other),
+        })?;
+        record_poll_vote(&tx, &poll.poll_id, payload.option_index).map_err(channel_err)?;
+        tx.commit().map_err(db_err)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(VoteResponse { ok: true }))
+}
+
+/// Closes polls whose close time has passed and broadcasts final results.
+///
+/// Called periodically from [`crate::background::start_poll_close_task`].
+pub async fn close_expired_polls_and_broadcast(state: &AppState) {
+    let pool = state.pool.clone();
+    let closed = tokio::task::spawn_blocking(move || {
+        let conn = pool.get().map_err(|e| e.to_string())?;
+        let polls = annex_channels::close_expired_polls(&conn).map_err(|e| e.to_string())?;
+        polls
+            .into_iter()
+            .map(|poll| {
+                let tally = tally_poll(&conn, &poll.poll_id).map_err(|e| e.to_string())?;
+                Ok((poll, tally))
+            })
+            .collect::<Result<Vec<_>, String>>()
+    })
+    .await;
+
+    let closed = match closed {
+        Ok(Ok(closed)) => closed,
+        Ok(Err(e)) => {
+            tracing::error!("failed to close expired polls: {}", e);
+            return;
+        }
+        Err(e) => {
+            tracing::error!("poll close task join error: {}", e);
+            return;
+        }
+    };
+
+    for (poll, tally) in closed {
+        tracing::info!(poll_id = %poll.poll_id, "closed expired poll");
+        let channel_id = poll.channel_id.clone();
+        broadcast_poll(
+            state,
+            OutgoingMessage::PollClosed(WsPollPayload::new(poll, tally)),
+            &channel_id,
+        )
+        .await;
+    }
+}
@@ -6,17 +6,22 @@
 //! and delivers it to matching subscribers.
 
 use crate::api::ApiError;
+use crate::api_vrp::{reputation_config, reputation_error};
 use crate::middleware::IdentityContext;
 use crate::parse_transfer_scope;
 use crate::AppState;
 use annex_federation::FederatedRtxEnvelope;
 use annex_rtx::{
-    check_redacted_topics, enforce_transfer_scope, validate_bundle_structure, BundleProvenance,
-    ReflectionSummaryBundle,
+    check_redacted_topics, enforce_transfer_scope, redacted_topic_matches, redacted_topics_note,
+    validate_bundle_structure, BundleProvenance, ReflectionSummaryBundle,
+};
+use annex_types::ServerPolicy;
+use annex_vrp::{
+    compute_reputation, record_reputation_signal, ReputationSignalKind, ReputationSummary,
+    VrpFederationHandshake, VrpTransferScope,
 };
-use annex_vrp::VrpTransferScope;
 use axum::{
-    extract::{Extension, Query},
+    extract::{Extension, Path, Query},
     Json,
 };
 use ed25519_dalek::Signer;
@@ -71,6 +76,11 @@ pub async fn publish_handler(
             let mut conn = state.pool.get().map_err(|e| {
                 ApiError::InternalServerError(format!("db connection failed: {}", e))
             })?;
+            let policy = state
+                .policy
+                .read()
+                .map_err(|_| ApiError::InternalServerError("policy lock poisoned".to_string()))?
+                .clone();
 
             // 4. Check sender has an active agent registration with sufficient transfer scope
             let (transfer_scope_str, capability_contract_json): (String, String) = conn
@@ -102,8 +112,10 @@ pub async fn publish_handler(
                 ));
             }
 
-            // 6. Extract redacted topics from capability contract and enforce
-            let redacted_topics = extract_redacted_topics(&capability_contract_json);
+            // 6. Enforce the redacted topics of the sender's contract and of the
+            //    server's agent policy
+            let mut redacted_topics = extract_redacted_topics(&capability_contract_json);
+            redacted_topics.extend(policy.agent_redacted_topics.iter().cloned());
             check_redacted_topics(&bundle, &redacted_topics)
                 .map_err(|e| ApiError::Forbidden(e.to_string()))?;
 
@@ -192,7 +204,8 @@ pub async fn publish_handler(
 
             let mut stmt = conn
                 .prepare(
-                    "SELECT s.subscriber_pseudonym, s.domain_filters_json, a.transfer_scope
+                    "SELECT s.subscriber_pseudonym, s.domain_filters_json, a.transfer_scope,
+                            a.capability_contract_json
                      FROM rtx_subscriptions s
                      JOIN agent_registrations a
                        ON a.server_id = s.server_id AND a.pseudonym_id = s.subscriber_pseudonym
@@ -201,7 +214,9 @@ pub async fn publish_handler(
                 )
                 .map_err(|e| ApiError::InternalServerError(format!("db prepare failed: {}", e)))?;
 
-            let rows = stmt
+            // Collect subscription rows before writing delivery logs to avoid
+            // holding a prepared statement open across writes.
+            let subscribers = stmt
                 .query_map(
                     rusqlite::params![state.server_id, bundle.source_pseudonym],
                     |row| {
@@ -209,14 +224,15 @@ pub async fn publish_handler(
                             row.get::<_, String>(0)?,
                             row.get::<_, String>(1)?,
                             row.get::<_, String>(2)?,
+                            row.get::<_, String>(3)?,
                         ))
                     },
                 )
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
                 .map_err(|e| ApiError::InternalServerError(format!("db query failed: {}", e)))?;
+            drop(stmt);
 
-            for row in rows {
-                let (sub_pseudonym, domain_filters_json, scope_str) =
-                    row.map_err(|e| ApiError::InternalServerError(format!("db row error: {}", e)))?;
+            for (sub_pseudonym, domain_filters_json, scope_str, contract_json) in subscribers {
 
                 // Parse domain filters. Corrupted JSON → skip this subscriber
                 // entirely (reject) rather than defaulting to accept-all, which
@@ -247,6 +263,27 @@ pub async fn publish_handler(
                     continue;
                 }
 
+                // Withhold bundles on topics redacted for this subscriber
+                let withheld = local_agent_redactions(
+                    &conn,
+                    state.server_id,
+                    &policy,
+                    &sub_pseudonym,
+                    &contract_json,
+                )
+                .map(|topics| redacted_topic_matches(&stored_bundle, &topics))
+                .map_err(|e| ApiError::InternalServerError(format!("db query failed: {}", e)))?;
+                if !withheld.is_empty() {
+                    log_withheld_transfer(
+                        &conn,
+                        state.server_id,
+                        &stored_bundle,
+                        &sub_pseudonym,
+                        &withheld,
+                    );
+                    continue;
+                }
+
                 // Parse receiver's transfer scope
                 let receiver_scope = match parse_transfer_scope(&scope_str) {
                     Some(s) if s >= VrpTransferScope::ReflectionSummariesOnly => s,
@@ -597,6 +634,163 @@ pub async fn get_subscription_handler(
     }))
 }
 
+/// Request body for `POST /api/rtx/bundles/{bundleId}/feedback`.
+#[derive(Debug, Deserialize)]
+pub struct BundleFeedbackRequest {
+    /// Whether the bundle was useful to the recipient.
+    pub useful: bool,
+}
+
+/// Handler for `POST /api/rtx/bundles/{bundleId}/feedback`.
+///
+/// Lets an agent that received a bundle rate it. The rating is recorded as
+/// an RTX reputation signal against the bundle's source. Only recipients in
+/// the transfer log may rate a bundle, and only once.
+pub async fn bundle_feedback_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(bundle_id): Path<String>,
+    Json(req): Json<BundleFeedbackRequest>,
+) -> Result<Json<ReputationSummary>, ApiError> {
+    let reviewer = identity.pseudonym_id.clone();
+
+    let summary = tokio::task::spawn_blocking(move || {
+        let conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+
+        let source: String = conn
+            .query_row(
+                "SELECT source_pseudonym FROM rtx_transfer_log
+                 WHERE server_id = ?1 AND bundle_id = ?2 AND destination_pseudonym = ?3
+                   AND transfer_scope_applied != 'NO_TRANSFER'
+                 LIMIT 1",
+                rusqlite::params![state.server_id, bundle_id, reviewer],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| ApiError::InternalServerError(format!("db query failed: {}", e)))?
+            .ok_or_else(|| {
+                ApiError::NotFound(format!("bundle '{}' was not delivered to you", bundle_id))
+            })?;
+
+        if source == reviewer {
+            return Err(ApiError::BadRequest(
+                "cannot rate your own bundle".to_string(),
+            ));
+        }
+
+        let kind = if req.useful {
+            ReputationSignalKind::RtxPositive
+        } else {
+            ReputationSignalKind::RtxNegative
+        };
+        record_reputation_signal(
+            &conn,
+            state.server_id,
+            &source,
+            kind,
+            &reviewer,
+            Some(&bundle_id),
+            None,
+        )
+        .map_err(reputation_error)?;
+
+        let config = reputation_config(&state)?;
+        compute_reputation(&conn, state.server_id, &source, &config).map_err(reputation_error)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(summary))
+}
+
+/// Topics withheld from a local agent: the redactions in its own capability
+/// contract, the policy's agent redactions, and the redactions of every
+/// channel it is a member of.
+pub(crate) fn local_agent_redactions(
+    conn: &rusqlite::Connection,
+    server_id: i64,
+    policy: &ServerPolicy,
+    pseudonym: &str,
+    contract_json: &str,
+) -> Result<Vec<String>, rusqlite::Error> {
+    let mut topics = extract_redacted_topics(contract_json);
+    topics.extend(policy.agent_redacted_topics.iter().cloned());
+
+    if !policy.channel_redacted_topics.is_empty() {
+        let mut stmt = conn.prepare(
+            "SELECT channel_id FROM channel_members
+             WHERE server_id = ?1 AND pseudonym_id = ?2",
+        )?;
+        let channels = stmt.query_map(rusqlite::params![server_id, pseudonym], |row| {
+            row.get::<_, String>(0)
+        })?;
+        for channel in channels {
+            if let Some(channel_topics) = policy.channel_redacted_topics.get(&channel?) {
+                topics.extend(channel_topics.iter().cloned());
+            }
+        }
+    }
+
+    Ok(topics)
+}
+
+/// Topics withheld across a federation agreement: the policy's federation
+/// redactions plus those the peer declared in its handshake contract.
+///
+/// An unparseable stored handshake contributes nothing, as with
+/// [`extract_redacted_topics`]; the policy's redactions still apply.
+pub(crate) fn federation_redactions(
+    policy: &ServerPolicy,
+    remote_handshake_json: Option<&str>,
+) -> Vec<String> {
+    let mut topics = policy.federation_redacted_topics.clone();
+    if let Some(json) = remote_handshake_json {
+        match serde_json::from_str::<VrpFederationHandshake>(json) {
+            Ok(handshake) => topics.extend(handshake.capability_contract.redacted_topics),
+            Err(e) => tracing::warn!(
+                "corrupted remote handshake JSON, peer redacted topics unavailable: {}",
+                e
+            ),
+        }
+    }
+    topics
+}
+
+/// Records in the transfer log that a bundle was withheld from `destination`
+/// because it carries redacted topics.
+pub(crate) fn log_withheld_transfer(
+    conn: &rusqlite::Connection,
+    server_id: i64,
+    bundle: &ReflectionSummaryBundle,
+    destination: &str,
+    topics: &[String],
+) {
+    if let Err(e) = conn.execute(
+        "INSERT INTO rtx_transfer_log (
+            server_id, bundle_id, source_pseudonym, destination_pseudonym,
+            transfer_scope_applied, redactions_applied
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![
+            server_id,
+            bundle.bundle_id,
+            bundle.source_pseudonym,
+            destination,
+            VrpTransferScope::NoTransfer.to_string(),
+            redacted_topics_note(topics),
+        ],
+    ) {
+        tracing::warn!(
+            bundle_id = %bundle.bundle_id,
+            destination = %destination,
+            "failed to write rtx transfer log for withheld bundle: {}",
+            e
+        );
+    }
+}
+
 /// Extracts redacted topics from a capability contract JSON string.
 ///
 /// The `redacted_topics` field may or may not be present in the stored JSON
@@ -643,6 +837,9 @@ pub fn rtx_relay_signing_payload(
 /// server's Ed25519 key, and POSTs it to the peer's `/api/federation/rtx`
 /// endpoint.
 ///
+/// Peers whose agreement redacts one of the bundle's topics are skipped and
+/// the withheld transfer is recorded in `rtx_transfer_log`.
+///
 /// Transfer scope enforcement:
 /// - `NoTransfer` peers are skipped entirely.
 /// - `ReflectionSummariesOnly` peers receive bundles with `reasoning_chain` stripped.
@@ -651,14 +848,19 @@ pub fn rtx_relay_signing_payload(
 /// The provenance chain tracks the original source server and all relay hops.
 pub async fn relay_rtx_bundles(state: Arc<AppState>, bundle: ReflectionSummaryBundle) {
     let peers = tokio::task::spawn_blocking({
-        let pool = state.pool.clone();
-        let server_id = state.server_id;
+        let state = state.clone();
+        let bundle = bundle.clone();
         move || -> Result<Vec<(String, String)>, String> {
-            let conn = pool.get().map_err(|e| e.to_string())?;
+            let conn = state.pool.get().map_err(|e| e.to_string())?;
+            let policy = state
+                .policy
+                .read()
+                .map_err(|_| "policy lock poisoned".to_string())?
+                .clone();
 
             let mut stmt = conn
                 .prepare(
-                    "SELECT i.base_url, fa.transfer_scope
+                    "SELECT i.base_url, fa.transfer_scope, fa.remote_handshake_json
                      FROM federation_agreements fa
                      JOIN instances i ON fa.remote_instance_id = i.id
                      WHERE fa.local_server_id = ?1 AND fa.active = 1 AND i.status = 'ACTIVE'",
@@ -666,14 +868,27 @@ pub async fn relay_rtx_bundles(state: Arc<AppState>, bundle: ReflectionSummaryBu
                 .map_err(|e| e.to_string())?;
 
             let rows = stmt
-                .query_map(rusqlite::params![server_id], |row| {
-                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                .query_map(rusqlite::params![state.server_id], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
                 })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
                 .map_err(|e| e.to_string())?;
 
             let mut peers = Vec::new();
-            for row in rows {
-                peers.push(row.map_err(|e| e.to_string())?);
+            for (base_url, transfer_scope, remote_handshake_json) in rows {
+                // Withhold bundles on topics redacted under this agreement
+                let topics = federation_redactions(&policy, remote_handshake_json.as_deref());
+                let withheld = redacted_topic_matches(&bundle, &topics);
+                if !withheld.is_empty() {
+                    log_withheld_transfer(&conn, state.server_id, &bundle, &base_url, &withheld);
+                    continue;
+                }
+                peers.push((base_url, transfer_scope));
             }
 
             Ok(peers)
@@ -1,55 +1,306 @@
 //! SSE presence stream handlers.
+//!
+//! A single [`PresenceFanout`] task works out who may see each presence
+//! event and relays it with that audience, so every subscriber only checks
+//! its own viewer against the precomputed audience.
 
-use crate::AppState;
+use crate::{middleware::pseudonym_from_headers, AppState};
+use annex_graph::VisibilityPreferences;
+use annex_identity::get_platform_identity;
+use annex_types::PresenceEvent;
 use axum::{
     extract::Extension,
+    http::{HeaderMap, StatusCode},
     response::{sse::Event, Sse},
 };
-use futures_util::Stream;
-use std::{convert::Infallible, sync::Arc};
-use tokio_stream::wrappers::BroadcastStream;
-use tokio_stream::StreamExt;
+use futures_util::{Stream, StreamExt};
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    sync::{Arc, OnceLock, Weak},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
 
-/// Handler for `GET /events/presence`.
+/// Who may receive a presence event.
+#[derive(Debug)]
+enum Audience {
+    /// Every subscriber, including anonymous ones.
+    Everyone,
+    /// Only these authenticated viewers.
+    Viewers(HashSet<String>),
+    /// No subscriber.
+    Nobody,
+}
+
+/// A presence event with its audience, or a count of events the fan-out
+/// itself missed.
+#[derive(Debug)]
+enum ScopedEvent {
+    Event {
+        event: PresenceEvent,
+        audience: Audience,
+    },
+    Lagged(u64),
+}
+
+/// Relays presence events to stream subscribers together with their
+/// audience, evaluating each event's visibility once rather than once per
+/// subscriber.
 ///
-/// Streams real-time presence events (node added, updated, pruned, edge changes).
-pub async fn get_presence_stream_handler(
-    Extension(state): Extension<Arc<AppState>>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    let rx = state.presence_tx.subscribe();
-    let stream = BroadcastStream::new(rx);
+/// The relay task starts with the first subscriber and stops when the
+/// server state is dropped. Cheap to clone; clones share the relay.
+#[derive(Debug, Clone)]
+pub struct PresenceFanout {
+    capacity: usize,
+    tx: Arc<OnceLock<broadcast::Sender<Arc<ScopedEvent>>>>,
+}
+
+impl PresenceFanout {
+    /// Creates a fan-out whose relay buffers up to `capacity` events per
+    /// subscriber, like `presence_tx`.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tx: Arc::new(OnceLock::new()),
+        }
+    }
+
+    fn subscribe(&self, state: &Arc<AppState>) -> broadcast::Receiver<Arc<ScopedEvent>> {
+        self.tx
+            .get_or_init(|| {
+                let (tx, _) = broadcast::channel(self.capacity);
+                let rx = state.presence_tx.subscribe();
+                // The state owns this fan-out, so the task must not keep it alive.
+                tokio::spawn(relay(Arc::downgrade(state), rx, tx.clone()));
+                tx
+            })
+            .subscribe()
+    }
+}
 
-    let mapped_stream = stream.filter_map(|result| {
-        match result {
+async fn relay(
+    state: Weak<AppState>,
+    mut rx: broadcast::Receiver<PresenceEvent>,
+    tx: broadcast::Sender<Arc<ScopedEvent>>,
+) {
+    loop {
+        let scoped = match rx.recv().await {
             Ok(event) => {
-                // Serialize event to JSON
-                match serde_json::to_string(&event) {
-                    Ok(data) => Some(Ok(Event::default().data(data))),
-                    Err(e) => {
-                        tracing::error!("failed to serialize presence event: {}", e);
-                        None
-                    }
-                }
+                let Some(state) = state.upgrade() else {
+                    return;
+                };
+                let audience = audience(&state, &event).await;
+                ScopedEvent::Event { event, audience }
             }
-            Err(tokio_stream::wrappers::errors::BroadcastStreamRecvError::Lagged(count)) => {
-                tracing::warn!(
-                    missed_events = count,
-                    "presence SSE stream lagged; {} events were dropped for this subscriber",
-                    count
-                );
-                // Send a sentinel event so the client knows it missed events
-                // and can take corrective action (e.g., re-fetch full state).
-                let sentinel = serde_json::json!({
-                    "type": "lagged",
-                    "missed_events": count
-                });
-                match serde_json::to_string(&sentinel) {
-                    Ok(data) => Some(Ok(Event::default().event("lagged").data(data))),
-                    Err(_) => None,
-                }
+            Err(RecvError::Lagged(count)) => ScopedEvent::Lagged(count),
+            Err(RecvError::Closed) => return,
+        };
+        // No subscribers right now is fine; later ones get later events.
+        let _ = tx.send(Arc::new(scoped));
+    }
+}
+
+async fn audience(state: &Arc<AppState>, event: &PresenceEvent) -> Audience {
+    match event {
+        PresenceEvent::StatusChanged { pseudonym_id, .. } => {
+            status_audience(state, pseudonym_id).await
+        }
+        _ if is_publicly_visible(state, event).await => Audience::Everyone,
+        _ => Audience::Nobody,
+    }
+}
+
+/// Whether `preferences` withhold a participant's activity (`activity`) or
+/// arrival and edges from the anonymous stream.
+fn hides(preferences: &VisibilityPreferences, activity: bool) -> bool {
+    if activity {
+        preferences.hides_presence()
+    } else {
+        preferences.private
+    }
+}
+
+/// Returns whether `event` may be shown on the anonymous presence stream.
+///
+/// The stream cannot rank its viewers, so it honors the preferences that
+/// apply to every viewer: activity of participants who are private or hide
+/// `last_seen_at` is withheld, as are the arrival and edges of private
+/// participants. Allow lists only apply to authenticated profile lookups.
+/// Status changes always need a viewer; see [`status_audience`].
+async fn is_publicly_visible(state: &Arc<AppState>, event: &PresenceEvent) -> bool {
+    let (subjects, activity): (Vec<String>, bool) = match event {
+        PresenceEvent::NodeUpdated { pseudonym_id, .. }
+        | PresenceEvent::NodePruned { pseudonym_id } => (vec![pseudonym_id.clone()], true),
+        PresenceEvent::NodeAdded { pseudonym_id, .. } => (vec![pseudonym_id.clone()], false),
+        PresenceEvent::EdgeAdded {
+            from_node, to_node, ..
+        }
+        | PresenceEvent::EdgeRemoved {
+            from_node, to_node, ..
+        } => (vec![from_node.clone(), to_node.clone()], false),
+        PresenceEvent::FederationRealigned { .. } | PresenceEvent::FederationSevered { .. } => {
+            return true
+        }
+        PresenceEvent::StatusChanged { .. } => return false,
+    };
+
+    // Most preferences are already cached; only misses need the database.
+    let cached: Option<Vec<_>> = subjects
+        .iter()
+        .map(|subject| {
+            state
+                .visibility_preferences
+                .cached(state.server_id, subject)
+        })
+        .collect();
+    if let Some(preferences) = cached {
+        return !preferences.iter().any(|p| hides(p, activity));
+    }
+
+    let state = state.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| e.to_string())?;
+        for subject in &subjects {
+            let preferences = state
+                .visibility_preferences
+                .get(&conn, state.server_id, subject)
+                .map_err(|e| e.to_string())?;
+            if hides(&preferences, activity) {
+                return Ok(false);
             }
         }
+        Ok::<_, String>(true)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(visible)) => visible,
+        Ok(Err(e)) => {
+            tracing::error!("failed to load visibility preferences: {}", e);
+            false
+        }
+        Err(e) => {
+            tracing::error!("visibility preference task failed: {}", e);
+            false
+        }
+    }
+}
+
+/// Returns the viewers who may see `subject`'s status changes.
+///
+/// Statuses are shown to viewers within three degrees, after the subject's
+/// blocks and visibility preferences are applied, so private participants
+/// only share them with their allow list.
+async fn status_audience(state: &Arc<AppState>, subject: &str) -> Audience {
+    let state = state.clone();
+    let subject = subject.to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = state.pool.get().map_err(|e| e.to_string())?;
+        state
+            .graph_index
+            .close_viewers(&conn, state.server_id, &subject)
+            .map_err(|e| e.to_string())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(viewers)) => Audience::Viewers(viewers),
+        Ok(Err(e)) => {
+            tracing::debug!("status visibility check failed: {}", e);
+            Audience::Nobody
+        }
+        Err(e) => {
+            tracing::error!("status visibility task failed: {}", e);
+            Audience::Nobody
+        }
+    }
+}
+
+/// A sentinel telling the client it missed events and should re-fetch.
+fn lagged_event(count: u64) -> Option<Result<Event, Infallible>> {
+    tracing::warn!(
+        missed_events = count,
+        "presence SSE stream lagged; {} events were dropped for this subscriber",
+        count
+    );
+    let sentinel = serde_json::json!({
+        "type": "lagged",
+        "missed_events": count
     });
+    serde_json::to_string(&sentinel)
+        .ok()
+        .map(|data| Ok(Event::default().event("lagged").data(data)))
+}
+
+/// Converts a relayed event into an SSE event for `viewer`, if they may see it.
+fn to_sse_event(
+    result: Result<Arc<ScopedEvent>, BroadcastStreamRecvError>,
+    viewer: Option<&str>,
+) -> Option<Result<Event, Infallible>> {
+    let scoped = match result {
+        Ok(scoped) => scoped,
+        Err(BroadcastStreamRecvError::Lagged(count)) => return lagged_event(count),
+    };
+    let (event, audience) = match scoped.as_ref() {
+        ScopedEvent::Event { event, audience } => (event, audience),
+        ScopedEvent::Lagged(count) => return lagged_event(*count),
+    };
+    let visible = match audience {
+        Audience::Everyone => true,
+        Audience::Viewers(viewers) => viewer.is_some_and(|v| viewers.contains(v)),
+        Audience::Nobody => false,
+    };
+    if !visible {
+        return None;
+    }
+    match serde_json::to_string(event) {
+        Ok(data) => Some(Ok(Event::default().data(data))),
+        Err(e) => {
+            tracing::error!("failed to serialize presence event: {}", e);
+            None
+        }
+    }
+}
+
+/// Resolves the optional viewer of the presence stream from the same
+/// headers `auth_middleware` accepts.
+async fn resolve_viewer(
+    state: &Arc<AppState>,
+    headers: &HeaderMap,
+) -> Result<Option<String>, StatusCode> {
+    let Some(pseudonym) = pseudonym_from_headers(headers)? else {
+        return Ok(None);
+    };
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = state
+            .pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        match get_platform_identity(&conn, state.server_id, &pseudonym) {
+            Ok(identity) if identity.active => Ok(Some(pseudonym)),
+            _ => Err(StatusCode::UNAUTHORIZED),
+        }
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+}
+
+/// Handler for `GET /events/presence`.
+///
+/// Streams real-time presence events (node added, updated, pruned, edge
+/// changes), minus those withheld by participants' visibility preferences.
+/// Callers authenticated like other API requests also receive the status
+/// changes of participants visible to them.
+pub async fn get_presence_stream_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let viewer = resolve_viewer(&state, &headers).await?;
+    let stream = BroadcastStream::new(state.presence_fanout.subscribe(&state));
+    let mapped_stream = stream
+        .filter_map(move |result| std::future::ready(to_sse_event(result, viewer.as_deref())));
 
-    Sse::new(mapped_stream).keep_alive(axum::response::sse::KeepAlive::default())
+    Ok(Sse::new(mapped_stream).keep_alive(axum::response::sse::KeepAlive::default()))
 }
@@ -46,7 +46,11 @@ fn derive_aead_key(signing_key: &SigningKey, pseudonym_id: &str) -> [u8; 32] {
 ///
 /// Output format (hex-encoded): `nonce(12) || ciphertext || tag(16)`.
 /// Each call produces a different ciphertext due to the random nonce.
-fn encrypt_username(signing_key: &SigningKey, pseudonym_id: &str, username: &str) -> String {
+pub(crate) fn encrypt_username(
+    signing_key: &SigningKey,
+    pseudonym_id: &str,
+    username: &str,
+) -> String {
     let key = derive_aead_key(signing_key, pseudonym_id);
     let cipher = ChaCha20Poly1305::new_from_slice(&key).expect("valid 256-bit key");
     let nonce_bytes: [u8; 12] = rand::random();
@@ -64,7 +68,7 @@ fn encrypt_username(signing_key: &SigningKey, pseudonym_id: &str, username: &str
 /// Tries the new AEAD format first. If the data is too short for AEAD
 /// (nonce + tag overhead) or AEAD decryption fails, falls back to the
 /// legacy XOR format for migration compatibility.
-fn decrypt_username(
+pub(crate) fn decrypt_username(
     signing_key: &SigningKey,
     pseudonym_id: &str,
     encrypted_hex: &str,
@@ -1,16 +1,28 @@
 //! VRP Handshake API handlers.
 
-use crate::{api::ApiError, AppState};
+use crate::{api::ApiError, middleware::IdentityContext, AppState};
 use annex_graph::update_node_activity;
+use annex_identity::PlatformIdentity;
 use annex_observe::EventPayload;
 use annex_types::PresenceEvent;
+use annex_types::{ReputationConfig, RoleCode, ServerPolicy};
 use annex_vrp::{
-    check_reputation_score, record_vrp_outcome, validate_federation_handshake, ServerPolicyRoot,
-    VrpAlignmentConfig, VrpAlignmentStatus, VrpCapabilitySharingContract, VrpFederationHandshake,
+    compute_reputation, diff_anchors, explain_peer_anchor, get_anchor_version, get_negotiation,
+    insert_negotiation, list_anchor_versions, record_reputation_signal, record_vrp_outcome,
+    resolve_transfer_scope, update_negotiation, validate_federation_handshake, AnchorHistoryError,
+    NegotiationError, ReputationError, ReputationSignalKind, ReputationSummary, ServerPolicyRoot,
+    VrpAlignmentConfig, VrpAlignmentStatus, VrpAnchorDiff, VrpAnchorSnapshot, VrpAnchorVersion,
+    VrpCapabilitySharingContract, VrpContractOffer, VrpFederationHandshake, VrpNegotiation,
+    VrpNegotiationMessage, VrpNegotiationResponse, VrpNegotiationStatus,
     VrpTransferAcceptanceConfig, VrpValidationReport,
 };
-use axum::{extract::Extension, Json};
-use serde::Deserialize;
+use axum::{
+    extract::{Extension, Path, Query},
+    Json,
+};
+use ed25519_dalek::{Signer, SigningKey};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 /// Request body for agent VRP handshake.
@@ -23,6 +35,137 @@ pub struct AgentHandshakeRequest {
     pub handshake: VrpFederationHandshake,
 }
 
+/// Request body for `POST /api/vrp/reputation/{peer}/reports`.
+#[derive(Debug, Deserialize)]
+pub struct ReportPeerRequest {
+    /// Why the peer is being reported.
+    pub reason: String,
+}
+
+/// Request body for `POST /api/vrp/negotiations`.
+#[derive(Debug, Deserialize)]
+pub struct OpenAgentNegotiationRequest {
+    /// The agent's pseudonym ID; must be the authenticated caller's.
+    #[serde(rename = "pseudonymId")]
+    pub pseudonym_id: String,
+    /// The agent's anchor snapshot.
+    #[serde(rename = "anchorSnapshot")]
+    pub anchor_snapshot: VrpAnchorSnapshot,
+    /// The agent's opening terms.
+    pub offer: VrpContractOffer,
+}
+
+/// Request body for `POST /api/vrp/negotiations/{negotiationId}`.
+#[derive(Debug, Deserialize)]
+pub struct AgentNegotiationStepRequest {
+    /// The agent's pseudonym ID; must be the authenticated caller's and match
+    /// the one that opened the negotiation.
+    #[serde(rename = "pseudonymId")]
+    pub pseudonym_id: String,
+    /// The agent's next move (`counter_offer`, `accept` or `reject`).
+    #[serde(flatten)]
+    pub message: VrpNegotiationMessage,
+}
+
+/// Response body for negotiation steps, shared by agent and federation
+/// negotiations.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NegotiationStepResponse {
+    pub negotiation_id: String,
+    pub status: VrpNegotiationStatus,
+    pub round: u32,
+    pub expires_at: String,
+    /// The server's answer to the peer's last message.
+    pub response: VrpNegotiationResponse,
+    /// The final validation report, once the negotiation is closed.
+    pub report: Option<VrpValidationReport>,
+}
+
+impl NegotiationStepResponse {
+    pub(crate) fn new(negotiation: &VrpNegotiation, response: VrpNegotiationResponse) -> Self {
+        Self {
+            negotiation_id: negotiation.id.clone(),
+            status: negotiation.status,
+            round: negotiation.round,
+            expires_at: negotiation.expires_at.clone(),
+            response,
+            report: (negotiation.status != VrpNegotiationStatus::Open)
+                .then(|| negotiation.report()),
+        }
+    }
+}
+
+/// The capability contract this server presents to agents.
+fn local_agent_contract(policy: &ServerPolicy) -> VrpCapabilitySharingContract {
+    let mut offered_capabilities = Vec::new();
+    if policy.voice_enabled {
+        offered_capabilities.push("VOICE".to_string());
+    }
+    if policy.federation_enabled {
+        offered_capabilities.push("FEDERATION".to_string());
+    }
+    offered_capabilities.push("TEXT".to_string());
+    offered_capabilities.push("VRP".to_string());
+
+    VrpCapabilitySharingContract {
+        required_capabilities: policy.agent_required_capabilities.clone(),
+        offered_capabilities,
+        redacted_topics: policy.agent_redacted_topics.clone(),
+    }
+}
+
+fn agent_alignment_config(policy: &ServerPolicy) -> VrpAlignmentConfig {
+    VrpAlignmentConfig {
+        semantic_alignment_required: true,
+        min_alignment_score: policy.agent_min_alignment_score,
+    }
+}
+
+fn agent_transfer_config() -> VrpTransferAcceptanceConfig {
+    VrpTransferAcceptanceConfig {
+        allow_reflection_summaries: true,
+        allow_full_knowledge: false, // Conservative default
+    }
+}
+
+/// This server's terms for an agent whose anchor has `alignment_status`.
+fn agent_negotiation_terms(
+    policy: &ServerPolicy,
+    alignment_status: VrpAlignmentStatus,
+) -> VrpContractOffer {
+    let mut terms = VrpContractOffer::from_contract(
+        &local_agent_contract(policy),
+        resolve_transfer_scope(alignment_status, &agent_transfer_config()),
+    );
+    terms.optional_capabilities = policy.agent_optional_capabilities.clone();
+    terms
+}
+
+pub(crate) fn reputation_error(e: ReputationError) -> ApiError {
+    match e {
+        ReputationError::Duplicate => ApiError::Conflict(e.to_string()),
+        _ => ApiError::InternalServerError(format!("reputation update failed: {}", e)),
+    }
+}
+
+/// The reputation weights and half-life from the current server policy.
+pub(crate) fn reputation_config(state: &AppState) -> Result<ReputationConfig, ApiError> {
+    state
+        .policy
+        .read()
+        .map(|policy| policy.reputation.clone())
+        .map_err(|_| ApiError::InternalServerError("server policy lock poisoned".to_string()))
+}
+
+pub(crate) fn negotiation_error(e: NegotiationError) -> ApiError {
+    match e {
+        NegotiationError::NotFound => ApiError::NotFound(e.to_string()),
+        NegotiationError::Closed(_) => ApiError::Conflict(e.to_string()),
+        _ => ApiError::InternalServerError(format!("negotiation failed: {}", e)),
+    }
+}
+
 /// Handler for `POST /api/vrp/agent-handshake`.
 pub async fn agent_handshake_handler(
     Extension(state): Extension<Arc<AppState>>,
@@ -46,34 +189,11 @@ pub async fn agent_handshake_handler(
             ApiError::InternalServerError(format!("failed to create anchor snapshot: {}", e))
         })?;
 
-        // 4. Construct Local Capability Contract from Policy
-        let mut offered_capabilities = Vec::new();
-        if policy.voice_enabled {
-            offered_capabilities.push("VOICE".to_string());
-        }
-        if policy.federation_enabled {
-            offered_capabilities.push("FEDERATION".to_string());
-        }
-        offered_capabilities.push("TEXT".to_string());
-        offered_capabilities.push("VRP".to_string());
-
-        let local_contract = VrpCapabilitySharingContract {
-            required_capabilities: policy.agent_required_capabilities.clone(),
-            offered_capabilities,
-            redacted_topics: vec![],
-        };
-
-        // 5. Construct Alignment Config
-        let alignment_config = VrpAlignmentConfig {
-            semantic_alignment_required: true,
-            min_alignment_score: policy.agent_min_alignment_score,
-        };
-
-        // 6. Construct Transfer Acceptance Config
-        let transfer_config = VrpTransferAcceptanceConfig {
-            allow_reflection_summaries: true,
-            allow_full_knowledge: false, // Conservative default
-        };
+        // 4-6. Local capability contract, alignment and transfer configs
+        let local_contract = local_agent_contract(&policy);
+        let alignment_config = agent_alignment_config(&policy);
+        let transfer_config = agent_transfer_config();
+        drop(policy);
 
         // 7. Validate Handshake
         let report = validate_federation_handshake(
@@ -82,169 +202,605 @@ pub async fn agent_handshake_handler(
             &payload.handshake,
             &alignment_config,
             &transfer_config,
-        );
-
-        // 8-10. Record outcome, check reputation, and upsert registration atomically.
-        let tx = conn.transaction().map_err(|e| {
-            ApiError::InternalServerError(format!("failed to begin transaction: {}", e))
-        })?;
+            state.embedder.as_ref(),
+        )
+        .map_err(|e| ApiError::InternalServerError(format!("anchor comparison failed: {}", e)))?;
 
-        // 8. Record Outcome
-        record_vrp_outcome(
-            &tx,
-            state.server_id,
+        // 8-10. Record outcome, check reputation, and upsert registration.
+        finalize_agent_handshake(
+            &state,
+            &mut conn,
             &payload.pseudonym_id,
-            "AI_AGENT",
+            &payload.handshake,
             &report,
-        )
+        )?;
+
+        Ok(report)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(result))
+}
+
+/// Records a completed agent handshake: logs the outcome, refreshes
+/// reputation, and upserts or deactivates the agent registration according
+/// to the report's alignment status.
+///
+/// Shared by the single-shot handshake and by contract negotiations that
+/// have been accepted or rejected.
+fn finalize_agent_handshake(
+    state: &AppState,
+    conn: &mut rusqlite::Connection,
+    pseudonym_id: &str,
+    handshake: &VrpFederationHandshake,
+    report: &VrpValidationReport,
+) -> Result<(), ApiError> {
+    // 8-10. Record outcome, check reputation, and upsert registration atomically.
+    let tx = conn.transaction().map_err(|e| {
+        ApiError::InternalServerError(format!("failed to begin transaction: {}", e))
+    })?;
+
+    // 8. Record Outcome
+    record_vrp_outcome(&tx, state.server_id, pseudonym_id, "AI_AGENT", report)
         .map_err(|e| ApiError::InternalServerError(format!("failed to log vrp outcome: {}", e)))?;
 
-        // 9. Check Longitudinal Reputation
-        let reputation_score =
-            check_reputation_score(&tx, state.server_id, &payload.pseudonym_id).map_err(
-                |e| ApiError::InternalServerError(format!("failed to check reputation: {}", e)),
-            )?;
-
-        // 10. Upsert Agent Registration
-        if report.alignment_status == VrpAlignmentStatus::Aligned
-            || report.alignment_status == VrpAlignmentStatus::Partial
-        {
-            // Update graph node activity if it exists
-            match update_node_activity(&tx, state.server_id, &payload.pseudonym_id) {
-                Ok(true) => {
-                    let _ = state.presence_tx.send(PresenceEvent::NodeUpdated {
-                        pseudonym_id: payload.pseudonym_id.clone(),
-                        active: true,
-                    });
-
-                    let observe_payload = EventPayload::NodeReactivated {
-                        pseudonym_id: payload.pseudonym_id.clone(),
-                    };
-                    crate::emit_and_broadcast(
-                        &tx,
-                        state.server_id,
-                        &payload.pseudonym_id,
-                        &observe_payload,
-                        &state.observe_tx,
-                    );
-                }
-                Ok(false) => {
-                    // Node does not exist or was already active; no action needed
-                }
-                Err(e) => {
-                    tracing::warn!(
-                        pseudonym_id = %payload.pseudonym_id,
-                        "failed to update graph node activity during VRP handshake: {}", e
-                    );
-                }
-            }
+    // 9. Check Longitudinal Reputation
+    let reputation_score = compute_reputation(
+        &tx,
+        state.server_id,
+        pseudonym_id,
+        &reputation_config(state)?,
+    )
+    .map_err(|e| ApiError::InternalServerError(format!("failed to check reputation: {}", e)))?
+    .score;
+
+    // 10. Upsert Agent Registration
+    if report.alignment_status == VrpAlignmentStatus::Aligned
+        || report.alignment_status == VrpAlignmentStatus::Partial
+    {
+        // Update graph node activity if it exists
+        match update_node_activity(&tx, state.server_id, pseudonym_id) {
+            Ok(true) => {
+                let _ = state.presence_tx.send(PresenceEvent::NodeUpdated {
+                    pseudonym_id: pseudonym_id.to_string(),
+                    active: true,
+                });
 
-            let contract_json = serde_json::to_string(&payload.handshake.capability_contract)
-                .map_err(|e| {
-                    ApiError::InternalServerError(format!("failed to serialize contract: {}", e))
-                })?;
-
-            let anchor_json = serde_json::to_string(&payload.handshake.anchor_snapshot)
-                .map_err(|e| {
-                    ApiError::InternalServerError(format!("failed to serialize anchor: {}", e))
-                })?;
-
-            let now = chrono::Utc::now().to_rfc3339();
-
-            tx.execute(
-                "INSERT INTO agent_registrations (
-                    server_id, pseudonym_id, alignment_status, transfer_scope,
-                    capability_contract_json, anchor_snapshot_json, reputation_score, last_handshake_at, active, created_at, updated_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1, datetime('now'), datetime('now'))
-                ON CONFLICT(server_id, pseudonym_id) DO UPDATE SET
-                    alignment_status = excluded.alignment_status,
-                    transfer_scope = excluded.transfer_scope,
-                    capability_contract_json = excluded.capability_contract_json,
-                    anchor_snapshot_json = excluded.anchor_snapshot_json,
-                    reputation_score = excluded.reputation_score,
-                    last_handshake_at = excluded.last_handshake_at,
-                    active = 1,
-                    updated_at = datetime('now')
-                ",
-                rusqlite::params![
+                let observe_payload = EventPayload::NodeReactivated {
+                    pseudonym_id: pseudonym_id.to_string(),
+                };
+                crate::emit_and_broadcast(
+                    &tx,
                     state.server_id,
-                    payload.pseudonym_id,
-                    report.alignment_status.to_string(),
-                    report.transfer_scope.to_string(),
-                    contract_json,
-                    anchor_json,
-                    reputation_score,
-                    now
-                ],
+                    pseudonym_id,
+                    &observe_payload,
+                    &state.observe_tx,
+                );
+            }
+            Ok(false) => {
+                // Node does not exist or was already active; no action needed
+            }
+            Err(e) => {
+                tracing::warn!(
+                    pseudonym_id = %pseudonym_id,
+                    "failed to update graph node activity during VRP handshake: {}", e
+                );
+            }
+        }
+
+        let contract_json = serde_json::to_string(&handshake.capability_contract).map_err(|e| {
+            ApiError::InternalServerError(format!("failed to serialize contract: {}", e))
+        })?;
+
+        let anchor_json = serde_json::to_string(&handshake.anchor_snapshot).map_err(|e| {
+            ApiError::InternalServerError(format!("failed to serialize anchor: {}", e))
+        })?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let validity = state
+            .policy
+            .read()
+            .map_err(|_| ApiError::InternalServerError("policy lock poisoned".to_string()))?
+            .contract_validity()
+            .map(|secs| format!("+{} seconds", secs));
+
+        // A fresh handshake renews the contract.
+        tx.execute(
+            "INSERT INTO agent_registrations (
+                server_id, pseudonym_id, alignment_status, transfer_scope,
+                capability_contract_json, anchor_snapshot_json, reputation_score, last_handshake_at, active, created_at, updated_at,
+                expires_at, renewal_requested_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1, datetime('now'), datetime('now'), datetime('now', ?9), NULL)
+            ON CONFLICT(server_id, pseudonym_id) DO UPDATE SET
+                alignment_status = excluded.alignment_status,
+                transfer_scope = excluded.transfer_scope,
+                capability_contract_json = excluded.capability_contract_json,
+                anchor_snapshot_json = excluded.anchor_snapshot_json,
+                reputation_score = excluded.reputation_score,
+                last_handshake_at = excluded.last_handshake_at,
+                active = 1,
+                updated_at = datetime('now'),
+                expires_at = excluded.expires_at,
+                renewal_requested_at = NULL
+            ",
+            rusqlite::params![
+                state.server_id,
+                pseudonym_id,
+                report.alignment_status.to_string(),
+                report.transfer_scope.to_string(),
+                contract_json,
+                anchor_json,
+                reputation_score,
+                now,
+                validity
+            ],
+        )
+        .map_err(|e| {
+            ApiError::InternalServerError(format!("failed to upsert registration: {}", e))
+        })?;
+
+        tx.commit().map_err(|e| {
+            ApiError::InternalServerError(format!("failed to commit transaction: {}", e))
+        })?;
+
+        // Emit AGENT_CONNECTED to persistent log (after commit)
+        let observe_payload = EventPayload::AgentConnected {
+            pseudonym_id: pseudonym_id.to_string(),
+            alignment_status: report.alignment_status.to_string(),
+        };
+        crate::emit_and_broadcast(
+            conn,
+            state.server_id,
+            pseudonym_id,
+            &observe_payload,
+            &state.observe_tx,
+        );
+    } else if report.alignment_status == VrpAlignmentStatus::Conflict {
+        // If an existing agent re-handshakes and gets Conflict, update their
+        // status in the DB and deactivate them. New agents with Conflict are
+        // simply not inserted (they never had a row).
+        let updated = tx
+            .execute(
+                "UPDATE agent_registrations
+                 SET alignment_status = 'Conflict',
+                     transfer_scope = 'NO_TRANSFER',
+                     active = 0,
+                     updated_at = datetime('now')
+                 WHERE server_id = ?1 AND pseudonym_id = ?2",
+                rusqlite::params![state.server_id, pseudonym_id],
             )
             .map_err(|e| {
-                ApiError::InternalServerError(format!("failed to upsert registration: {}", e))
+                ApiError::InternalServerError(format!("failed to deactivate conflict agent: {}", e))
             })?;
 
-            tx.commit().map_err(|e| {
-                ApiError::InternalServerError(format!("failed to commit transaction: {}", e))
-            })?;
+        tx.commit().map_err(|e| {
+            ApiError::InternalServerError(format!("failed to commit transaction: {}", e))
+        })?;
 
-            // Emit AGENT_CONNECTED to persistent log (after commit)
-            let observe_payload = EventPayload::AgentConnected {
-                pseudonym_id: payload.pseudonym_id.clone(),
-                alignment_status: report.alignment_status.to_string(),
+        if updated > 0 {
+            let observe_payload = EventPayload::AgentDisconnected {
+                pseudonym_id: pseudonym_id.to_string(),
+                reason: "VRP handshake resulted in Conflict alignment".to_string(),
             };
             crate::emit_and_broadcast(
-                &conn,
+                conn,
                 state.server_id,
-                &payload.pseudonym_id,
+                pseudonym_id,
                 &observe_payload,
                 &state.observe_tx,
             );
-        } else if report.alignment_status == VrpAlignmentStatus::Conflict {
-            // If an existing agent re-handshakes and gets Conflict, update their
-            // status in the DB and deactivate them. New agents with Conflict are
-            // simply not inserted (they never had a row).
-            let updated = tx
-                .execute(
-                    "UPDATE agent_registrations
-                     SET alignment_status = 'Conflict',
-                         transfer_scope = 'NO_TRANSFER',
-                         active = 0,
-                         updated_at = datetime('now')
-                     WHERE server_id = ?1 AND pseudonym_id = ?2",
-                    rusqlite::params![state.server_id, payload.pseudonym_id],
-                )
-                .map_err(|e| {
-                    ApiError::InternalServerError(format!(
-                        "failed to deactivate conflict agent: {}",
-                        e
-                    ))
-                })?;
-
-            tx.commit().map_err(|e| {
-                ApiError::InternalServerError(format!("failed to commit transaction: {}", e))
+        }
+    } else {
+        tx.commit().map_err(|e| {
+            ApiError::InternalServerError(format!("failed to commit transaction: {}", e))
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Handler for `POST /api/vrp/negotiations`.
+///
+/// Opens a contract negotiation with the agent's first offer. The response
+/// is an acceptance, a counter-offer to answer via
+/// `POST /api/vrp/negotiations/{negotiationId}`, or a rejection.
+pub async fn open_agent_negotiation_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Json(payload): Json<OpenAgentNegotiationRequest>,
+) -> Result<Json<NegotiationStepResponse>, ApiError> {
+    ensure_negotiating_agent(&identity, &payload.pseudonym_id)?;
+    let result = tokio::task::spawn_blocking(move || {
+        let mut conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+
+        let policy = state.policy.read().map_err(|_| {
+            ApiError::InternalServerError("server policy lock poisoned".to_string())
+        })?;
+
+        let local_anchor = ServerPolicyRoot::from_policy(&policy)
+            .to_anchor_snapshot()
+            .map_err(|e| {
+                ApiError::InternalServerError(format!("failed to create anchor snapshot: {}", e))
             })?;
+        let alignment = explain_peer_anchor(
+            &local_anchor,
+            &payload.anchor_snapshot,
+            &agent_alignment_config(&policy),
+            state.embedder.as_ref(),
+        )
+        .map_err(|e| ApiError::InternalServerError(format!("anchor comparison failed: {}", e)))?;
+        let local_terms = agent_negotiation_terms(&policy, alignment.0);
 
-            if updated > 0 {
-                let observe_payload = EventPayload::AgentDisconnected {
-                    pseudonym_id: payload.pseudonym_id.clone(),
-                    reason: "VRP handshake resulted in Conflict alignment".to_string(),
-                };
-                crate::emit_and_broadcast(
-                    &conn,
-                    state.server_id,
-                    &payload.pseudonym_id,
-                    &observe_payload,
-                    &state.observe_tx,
-                );
+        let (mut negotiation, response) = VrpNegotiation::open(
+            uuid::Uuid::new_v4().to_string(),
+            payload.pseudonym_id,
+            "AI_AGENT",
+            payload.anchor_snapshot,
+            alignment,
+            payload.offer,
+            &local_terms,
+        );
+        insert_negotiation(
+            &conn,
+            state.server_id,
+            &mut negotiation,
+            policy.negotiation_timeout_secs,
+        )
+        .map_err(negotiation_error)?;
+        drop(policy);
+
+        settle_agent_negotiation(&state, &mut conn, &negotiation)?;
+        Ok(NegotiationStepResponse::new(&negotiation, response))
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(result))
+}
+
+/// Handler for `POST /api/vrp/negotiations/{negotiationId}`.
+///
+/// Applies the agent's counter-offer, acceptance or rejection. Negotiations
+/// past their timeout answer `409 Conflict`.
+pub async fn agent_negotiation_step_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(negotiation_id): Path<String>,
+    Json(payload): Json<AgentNegotiationStepRequest>,
+) -> Result<Json<NegotiationStepResponse>, ApiError> {
+    ensure_negotiating_agent(&identity, &payload.pseudonym_id)?;
+    let result = tokio::task::spawn_blocking(move || {
+        let mut conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+
+        let mut negotiation = get_negotiation(
+            &conn,
+            state.server_id,
+            &negotiation_id,
+            "AI_AGENT",
+            &payload.pseudonym_id,
+        )
+        .map_err(negotiation_error)?;
+
+        let local_terms = {
+            let policy = state.policy.read().map_err(|_| {
+                ApiError::InternalServerError("server policy lock poisoned".to_string())
+            })?;
+            agent_negotiation_terms(&policy, negotiation.alignment_status)
+        };
+
+        let response = negotiation
+            .advance(payload.message, &local_terms)
+            .map_err(negotiation_error)?;
+        update_negotiation(&conn, state.server_id, &negotiation).map_err(negotiation_error)?;
+
+        settle_agent_negotiation(&state, &mut conn, &negotiation)?;
+        Ok(NegotiationStepResponse::new(&negotiation, response))
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(result))
+}
+
+/// Only an authenticated agent may negotiate, and only for itself.
+fn ensure_negotiating_agent(
+    identity: &PlatformIdentity,
+    pseudonym_id: &str,
+) -> Result<(), ApiError> {
+    if identity.participant_type != RoleCode::AiAgent {
+        return Err(ApiError::Forbidden(
+            "only agents can negotiate contracts".to_string(),
+        ));
+    }
+    if identity.pseudonym_id != pseudonym_id {
+        return Err(ApiError::Forbidden(
+            "pseudonymId does not match the authenticated agent".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Finalizes an accepted negotiation as if it were a single-shot handshake
+/// with the agreed contract.
+///
+/// A rejected negotiation only means the two sides found no common terms,
+/// not that the agent is misaligned: it is kept in `vrp_negotiations` but
+/// neither affects reputation nor changes an existing registration.
+fn settle_agent_negotiation(
+    state: &AppState,
+    conn: &mut rusqlite::Connection,
+    negotiation: &VrpNegotiation,
+) -> Result<(), ApiError> {
+    match negotiation.status {
+        VrpNegotiationStatus::Accepted => finalize_agent_handshake(
+            state,
+            conn,
+            &negotiation.peer_id,
+            &negotiation.agreed_handshake(),
+            &negotiation.report(),
+        ),
+        VrpNegotiationStatus::Rejected => {
+            tracing::info!(
+                negotiation_id = %negotiation.id,
+                pseudonym_id = %negotiation.peer_id,
+                reason = negotiation.outcome_reason.as_deref().unwrap_or_default(),
+                "agent contract negotiation failed"
+            );
+            Ok(())
+        }
+        VrpNegotiationStatus::Open | VrpNegotiationStatus::Expired => Ok(()),
+    }
+}
+
+/// Handler for `GET /api/vrp/reputation/{peer}`.
+///
+/// Returns the time-decayed reputation of an agent pseudonym or federation
+/// peer base URL, computed with the policy's weights and half-life. Peers
+/// with no history score a neutral 0.5.
+pub async fn get_reputation_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(peer): Path<String>,
+) -> Result<Json<ReputationSummary>, ApiError> {
+    let summary = tokio::task::spawn_blocking(move || {
+        let conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+        let config = reputation_config(&state)?;
+        compute_reputation(&conn, state.server_id, &peer, &config).map_err(reputation_error)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(summary))
+}
+
+/// Handler for `POST /api/vrp/reputation/{peer}/reports`.
+///
+/// Files a moderation report against an agent on this server, which counts
+/// as a negative reputation signal. Each member may report a given agent
+/// once; repeats answer `409 Conflict`.
+pub async fn report_peer_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(IdentityContext(identity)): Extension<IdentityContext>,
+    Path(peer): Path<String>,
+    Json(body): Json<ReportPeerRequest>,
+) -> Result<Json<ReputationSummary>, ApiError> {
+    let reason = body.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(ApiError::BadRequest("reason must not be empty".to_string()));
+    }
+    if peer == identity.pseudonym_id {
+        return Err(ApiError::BadRequest("cannot report yourself".to_string()));
+    }
+
+    let summary = tokio::task::spawn_blocking(move || {
+        let conn = state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+
+        let participant_type: Option<String> = conn
+            .query_row(
+                "SELECT participant_type FROM platform_identities
+                 WHERE server_id = ?1 AND pseudonym_id = ?2",
+                rusqlite::params![state.server_id, peer],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| ApiError::InternalServerError(format!("db query failed: {}", e)))?;
+        match participant_type.as_deref() {
+            Some("AI_AGENT") => {}
+            Some(_) => {
+                return Err(ApiError::BadRequest(
+                    "only agents can be reported".to_string(),
+                ))
             }
-        } else {
-            tx.commit().map_err(|e| {
-                ApiError::InternalServerError(format!("failed to commit transaction: {}", e))
+            None => return Err(ApiError::NotFound(format!("unknown peer '{}'", peer))),
+        }
+
+        record_reputation_signal(
+            &conn,
+            state.server_id,
+            &peer,
+            ReputationSignalKind::ModerationReport,
+            &identity.pseudonym_id,
+            None,
+            Some(&reason),
+        )
+        .map_err(reputation_error)?;
+
+        let config = reputation_config(&state)?;
+        compute_reputation(&conn, state.server_id, &peer, &config).map_err(reputation_error)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    Ok(Json(summary))
+}
+
+/// Signs `anchor` with the server's Ed25519 key, replacing any existing
+/// signature.
+pub(crate) fn sign_anchor(signing_key: &SigningKey, anchor: &mut VrpAnchorSnapshot) {
+    let signature = signing_key.sign(anchor.signing_payload().as_bytes());
+    anchor.signature = Some(hex::encode(signature.to_bytes()));
+}
+
+/// Signs a historical `anchor` under the history domain, so it cannot be
+/// presented as the server's live anchor.
+fn sign_history_anchor(signing_key: &SigningKey, anchor: &mut VrpAnchorSnapshot) {
+    let signature = signing_key.sign(anchor.history_signing_payload().as_bytes());
+    anchor.signature = Some(hex::encode(signature.to_bytes()));
+}
+
+/// Builds the server's current anchor from its policy and signs it.
+pub(crate) fn signed_local_anchor(
+    signing_key: &SigningKey,
+    policy: &ServerPolicy,
+) -> Result<VrpAnchorSnapshot, annex_vrp::VrpError> {
+    let mut anchor = ServerPolicyRoot::from_policy(policy).to_anchor_snapshot()?;
+    sign_anchor(signing_key, &mut anchor);
+    Ok(anchor)
+}
+
+fn anchor_history_error(e: AnchorHistoryError) -> ApiError {
+    ApiError::InternalServerError(format!("failed to read anchor history: {}", e))
+}
+
+/// Response for `GET /api/vrp/anchor`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedAnchorResponse {
+    /// Hex-encoded Ed25519 public key that signed the anchor.
+    pub public_key: String,
+    /// The server's current anchor.
+    pub anchor: VrpAnchorSnapshot,
+}
+
+/// Response for `GET /api/vrp/anchor/history`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorHistoryResponse {
+    /// Hex-encoded Ed25519 public key that signed the anchors.
+    pub public_key: String,
+    /// Anchor versions, oldest first.
+    pub versions: Vec<VrpAnchorVersion>,
+}
+
+/// Query parameters for `GET /api/vrp/anchor/diff`.
+#[derive(Debug, Deserialize)]
+pub struct AnchorDiffQuery {
+    /// Policy version to diff from.
+    pub from: String,
+    /// Policy version to diff to.
+    pub to: String,
+}
+
+/// Response for `GET /api/vrp/anchor/diff`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorDiffResponse {
+    /// The older anchor version.
+    pub from: VrpAnchorVersion,
+    /// The newer anchor version.
+    pub to: VrpAnchorVersion,
+    /// What changed between them.
+    pub diff: VrpAnchorDiff,
+}
+
+/// Handler for `GET /api/vrp/anchor`.
+///
+/// Returns the server's current anchor signed with its Ed25519 key, so peers
+/// can check that anchors presented in handshakes were published by us.
+pub async fn get_anchor_handler(
+    Extension(state): Extension<Arc<AppState>>,
+) -> Result<Json<SignedAnchorResponse>, ApiError> {
+    let policy = state
+        .policy
+        .read()
+        .map_err(|_| ApiError::InternalServerError("policy lock poisoned".to_string()))?
+        .clone();
+    let anchor = signed_local_anchor(&state.signing_key, &policy).map_err(|e| {
+        ApiError::InternalServerError(format!("failed to create anchor snapshot: {}", e))
+    })?;
+
+    Ok(Json(SignedAnchorResponse {
+        public_key: hex::encode(state.signing_key.verifying_key().as_bytes()),
+        anchor,
+    }))
+}
+
+/// Handler for `GET /api/vrp/anchor/history`.
+///
+/// Lists every anchor the server has published, derived from its policy
+/// versions. Each anchor is timestamped with its activation time and signed
+/// under the history domain rather than as a live anchor.
+pub async fn get_anchor_history_handler(
+    Extension(state): Extension<Arc<AppState>>,
+) -> Result<Json<AnchorHistoryResponse>, ApiError> {
+    let versions = tokio::task::spawn_blocking({
+        let state = state.clone();
+        move || {
+            let conn = state.pool.get().map_err(|e| {
+                ApiError::InternalServerError(format!("db connection failed: {}", e))
             })?;
+            list_anchor_versions(&conn, state.server_id).map_err(anchor_history_error)
         }
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
 
-        Ok(report)
+    let versions = versions
+        .into_iter()
+        .map(|mut version| {
+            sign_history_anchor(&state.signing_key, &mut version.anchor);
+            version
+        })
+        .collect();
+
+    Ok(Json(AnchorHistoryResponse {
+        public_key: hex::encode(state.signing_key.verifying_key().as_bytes()),
+        versions,
+    }))
+}
+
+/// Handler for `GET /api/vrp/anchor/diff?from=<versionId>&to=<versionId>`.
+///
+/// Shows which principles and prohibited actions were added or removed
+/// between two policy versions.
+pub async fn get_anchor_diff_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(query): Query<AnchorDiffQuery>,
+) -> Result<Json<AnchorDiffResponse>, ApiError> {
+    let (mut from, mut to) = tokio::task::spawn_blocking({
+        let state = state.clone();
+        move || {
+            let conn = state.pool.get().map_err(|e| {
+                ApiError::InternalServerError(format!("db connection failed: {}", e))
+            })?;
+            let load = |version_id: &str| {
+                get_anchor_version(&conn, state.server_id, version_id)
+                    .map_err(anchor_history_error)?
+                    .ok_or_else(|| {
+                        ApiError::NotFound(format!("unknown policy version '{}'", version_id))
+                    })
+            };
+            Ok::<_, ApiError>((load(&query.from)?, load(&query.to)?))
+        }
     })
     .await
     .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
 
-    Ok(Json(result))
+    sign_history_anchor(&state.signing_key, &mut from.anchor);
+    sign_history_anchor(&state.signing_key, &mut to.anchor);
+    let diff = diff_anchors(&from.anchor, &to.anchor);
+
+    Ok(Json(AnchorDiffResponse { from, to, diff }))
 }
@@ -1,13 +1,16 @@
 //! WebSocket API handler and connection management.
 
+use crate::api::ApiError;
 use crate::api_federation::relay_message;
 use crate::AppState;
 use annex_channels::{
     create_message, delete_message, edit_message, get_channel, is_member, CreateMessageParams,
-    Message,
+    Message, Poll, PollTally,
 };
-use annex_identity::{get_platform_identity, PlatformIdentity};
-use annex_types::{FederationScope, RoleCode};
+use annex_identity::{
+    count_active_devices, get_device, get_platform_identity, touch_device, PlatformIdentity,
+};
+use annex_types::{FederationScope, PresenceStatus, RoleCode};
 use axum::{
     extract::{
         ws::{Message as AxumMessage, WebSocket},
@@ -24,12 +27,12 @@ use std::{
     net::SocketAddr,
     sync::Arc,
 };
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, Notify, RwLock};
 use uuid::Uuid;
 
 /// Duration for which a WebSocket session token is valid (60 seconds).
 /// Tokens are single-use: the short TTL limits replay risk for unused tokens.
-const WS_TOKEN_TTL_SECS: u64 = 60;
+pub(crate) const WS_TOKEN_TTL_SECS: u64 = 60;
 
 /// Derive a 32-byte HMAC key for WebSocket session tokens from the server's
 /// Ed25519 signing key. Uses SHA-256 with a domain-separation prefix so the
@@ -47,10 +50,16 @@ pub fn derive_ws_token_secret(signing_key: &ed25519_dalek::SigningKey) -> [u8; 3
 
 /// Generates an HMAC-SHA256 signed WebSocket session token.
 ///
-/// Token format: `base64(pseudonym|expires_unix_secs|hmac_signature)`
-/// The token binds the pseudonym to a time window, preventing both
-/// impersonation (different pseudonym) and replay (after expiry).
-fn generate_ws_token(pseudonym: &str, secret: &[u8; 32]) -> String {
+/// Token format: `base64(pseudonym|expires_unix_secs|device_id|hmac_signature)`
+/// The token binds the pseudonym (and, for device-issued tokens, the device)
+/// to a time window, preventing both impersonation (different pseudonym or
+/// device) and replay (after expiry). `device_id` is empty for tokens that
+/// are not bound to a registered device.
+pub(crate) fn generate_ws_token(
+    pseudonym: &str,
+    device_id: Option<&str>,
+    secret: &[u8; 32],
+) -> String {
     use hmac::{Hmac, Mac};
     use sha2::Sha256;
 
@@ -60,7 +69,7 @@ fn generate_ws_token(pseudonym: &str, secret: &[u8; 32]) -> String {
         .as_secs()
         + WS_TOKEN_TTL_SECS;
 
-    let payload = format!("{}|{}", pseudonym, expires);
+    let payload = format!("{}|{}|{}", pseudonym, expires, device_id.unwrap_or(""));
 
     let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC key length is valid");
     mac.update(payload.as_bytes());
@@ -72,8 +81,8 @@ fn generate_ws_token(pseudonym: &str, secret: &[u8; 32]) -> String {
 }
 
 /// Verifies an HMAC-SHA256 signed WebSocket session token.
-/// Returns the pseudonym if valid and not expired.
-fn verify_ws_token(token: &str, secret: &[u8; 32]) -> Result<String, StatusCode> {
+/// Returns the pseudonym and bound device ID (if any) if valid and not expired.
+fn verify_ws_token(token: &str, secret: &[u8; 32]) -> Result<(String, Option<String>), StatusCode> {
     use base64::Engine;
     use hmac::{Hmac, Mac};
     use sha2::Sha256;
@@ -84,18 +93,19 @@ fn verify_ws_token(token: &str, secret: &[u8; 32]) -> Result<String, StatusCode>
 
     let token_str = String::from_utf8(decoded).map_err(|_| StatusCode::UNAUTHORIZED)?;
 
-    // Parse: pseudonym|expires|signature_hex
-    let parts: Vec<&str> = token_str.splitn(3, '|').collect();
-    if parts.len() != 3 {
+    // Parse: pseudonym|expires|device_id|signature_hex
+    let parts: Vec<&str> = token_str.splitn(4, '|').collect();
+    if parts.len() != 4 {
         return Err(StatusCode::UNAUTHORIZED);
     }
 
     let pseudonym = parts[0];
     let expires_str = parts[1];
-    let sig_hex = parts[2];
+    let device_id = parts[2];
+    let sig_hex = parts[3];
 
     // Verify HMAC
-    let payload = format!("{}|{}", pseudonym, expires_str);
+    let payload = format!("{}|{}|{}", pseudonym, expires_str, device_id);
     let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC key length is valid");
     mac.update(payload.as_bytes());
     let expected_sig = mac.finalize().into_bytes();
@@ -116,7 +126,8 @@ fn verify_ws_token(token: &str, secret: &[u8; 32]) -> Result<String, StatusCode>
         return Err(StatusCode::UNAUTHORIZED);
     }
 
-    Ok(pseudonym.to_string())
+    let device_id = (!device_id.is_empty()).then(|| device_id.to_string());
+    Ok((pseudonym.to_string(), device_id))
 }
 
 /// Query parameters for the WebSocket connection.
@@ -173,6 +184,19 @@ pub enum IncomingMessage {
         channel_id: String,
         text: String,
     },
+    /// Stops the agent's current voice intent and drops any queued ones.
+    #[serde(rename = "voice_cancel")]
+    VoiceCancel,
+    #[serde(rename = "set_status")]
+    SetStatus {
+        status: PresenceStatus,
+        #[serde(rename = "statusText", default)]
+        status_text: Option<String>,
+    },
+    /// Reports user activity without doing anything else, keeping the
+    /// session from going idle.
+    #[serde(rename = "heartbeat")]
+    Heartbeat,
 }
 
 /// Outgoing WebSocket message payload with camelCase field names.
@@ -219,6 +243,8 @@ pub enum OutgoingMessage {
     MessageEdited(WsMessagePayload),
     #[serde(rename = "message_deleted")]
     MessageDeleted(WsMessagePayload),
+    /// Interim (`isFinal: false`) or final hypothesis for an utterance;
+    /// later frames with the same speaker and `segmentId` replace it.
     #[serde(rename = "transcription")]
     Transcription {
         #[serde(rename = "channelId")]
@@ -226,122 +252,317 @@ pub enum OutgoingMessage {
         #[serde(rename = "speakerPseudonym")]
         speaker_pseudonym: String,
         text: String,
+        #[serde(rename = "segmentId")]
+        segment_id: u64,
+        #[serde(rename = "isFinal")]
+        is_final: bool,
+        #[serde(rename = "startMs")]
+        start_ms: u64,
+        #[serde(rename = "endMs")]
+        end_ms: u64,
+    },
+    #[serde(rename = "caption")]
+    Caption(WsCaptionPayload),
+    #[serde(rename = "poll_created")]
+    PollCreated(WsPollPayload),
+    #[serde(rename = "poll_closed")]
+    PollClosed(WsPollPayload),
+    /// The agent's capability contract expires soon and should be renewed
+    /// by repeating the VRP handshake.
+    #[serde(rename = "vrp_renewal_requested")]
+    RenewalRequested {
+        #[serde(rename = "expiresAt")]
+        expires_at: String,
+    },
+    /// The participant's own stored status, sent in reply to `set_status`.
+    #[serde(rename = "status")]
+    Status {
+        status: PresenceStatus,
+        #[serde(rename = "statusText")]
+        status_text: Option<String>,
     },
     #[serde(rename = "error")]
     Error { message: String },
 }
 
-/// Type alias for session map to satisfy clippy complexity checks.
-type SessionMap = HashMap<String, (Uuid, mpsc::Sender<String>)>;
+/// Outgoing WebSocket live caption for a voice channel.
+///
+/// Transcribed speech carries the utterance's `segmentId` and timing; an
+/// interim caption is replaced by the next one with the same speaker and
+/// `segmentId`. Captions of agent voice intents come from the intent text
+/// and have no timing.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WsCaptionPayload {
+    pub channel_id: String,
+    pub speaker_pseudonym: String,
+    pub text: String,
+    pub is_final: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_ms: Option<u64>,
+    /// Message the caption was stored as, when the channel persists captions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+}
+
+/// Outgoing WebSocket poll payload: poll metadata plus the current tally.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WsPollPayload {
+    pub channel_id: String,
+    pub poll_id: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub counts: Vec<u64>,
+    pub total_votes: u64,
+    pub closes_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed_at: Option<String>,
+}
+
+impl WsPollPayload {
+    pub fn new(poll: Poll, tally: PollTally) -> Self {
+        Self {
+            channel_id: poll.channel_id,
+            poll_id: poll.poll_id,
+            question: poll.question,
+            options: poll.options,
+            counts: tally.counts,
+            total_votes: tally.total_votes,
+            closes_at: poll.closes_at,
+            closed_at: poll.closed_at,
+        }
+    }
+}
+
+/// A single live WebSocket session.
+struct SessionHandle {
+    pseudonym: String,
+    /// The registered device the session authenticated as, if any.
+    device_id: Option<String>,
+    sender: mpsc::Sender<String>,
+    /// Signalled when the server closes the session (revocation, policy).
+    close: Arc<Notify>,
+    /// Whether the client has sent nothing for the idle timeout.
+    idle: bool,
+}
+
+/// Live sessions, indexed by session ID and by pseudonym.
+#[derive(Default)]
+struct SessionTable {
+    by_id: HashMap<Uuid, SessionHandle>,
+    by_pseudonym: HashMap<String, HashSet<Uuid>>,
+}
+
+impl SessionTable {
+    fn ids_for(&self, pseudonym: &str) -> Vec<Uuid> {
+        self.by_pseudonym
+            .get(pseudonym)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
 
 /// Manages active WebSocket connections and subscriptions.
+///
+/// A pseudonym may hold several concurrent sessions, one per connected
+/// device. Subscriptions are tracked per session, so each device receives
+/// broadcasts only for the channels it subscribed to, and one device
+/// disconnecting leaves the others untouched.
 #[derive(Clone, Default)]
 pub struct ConnectionManager {
-    /// Active sessions: pseudonym -> (session_id, sender).
-    sessions: Arc<RwLock<SessionMap>>,
-    /// Subscriptions: channel_id -> set of pseudonyms.
-    channel_subscriptions: Arc<RwLock<HashMap<String, HashSet<String>>>>,
-    /// Reverse mapping: pseudonym -> set of channel_ids.
-    user_subscriptions: Arc<RwLock<HashMap<String, HashSet<String>>>>,
+    /// Active sessions.
+    sessions: Arc<RwLock<SessionTable>>,
+    /// Subscriptions: channel_id -> set of session IDs.
+    channel_subscriptions: Arc<RwLock<HashMap<String, HashSet<Uuid>>>>,
+    /// Reverse mapping: session ID -> set of channel_ids.
+    session_subscriptions: Arc<RwLock<HashMap<Uuid, HashSet<String>>>>,
 }
 
 impl ConnectionManager {
     pub fn new() -> Self {
-        Self {
-            sessions: Arc::new(RwLock::new(HashMap::new())),
-            channel_subscriptions: Arc::new(RwLock::new(HashMap::new())),
-            user_subscriptions: Arc::new(RwLock::new(HashMap::new())),
-        }
+        Self::default()
     }
 
-    /// Registers a new session for a pseudonym.
-    ///
-    /// If the pseudonym already has a session, the old session's subscriptions
-    /// are cleaned up before replacement to prevent orphaned entries in
-    /// `channel_subscriptions` and `user_subscriptions`.
+    /// Registers a new session for a pseudonym without a device binding.
     ///
     /// Returns the unique session ID.
     pub async fn add_session(&self, pseudonym: String, sender: mpsc::Sender<String>) -> Uuid {
-        let session_id = Uuid::new_v4();
-
-        // Check for and clean up an existing session for this pseudonym.
-        let had_previous = {
-            let sessions = self.sessions.read().await;
-            sessions.contains_key(&pseudonym)
-        };
-
-        if had_previous {
-            // Clean up old subscriptions (channel_subscriptions → user_subscriptions order).
-            let channels = {
-                let user_subs = self.user_subscriptions.read().await;
-                user_subs.get(&pseudonym).cloned()
-            };
-
-            if let Some(ref channels) = channels {
-                let mut chan_subs = self.channel_subscriptions.write().await;
-                for channel_id in channels {
-                    if let Some(listeners) = chan_subs.get_mut(channel_id) {
-                        listeners.remove(&pseudonym);
-                        if listeners.is_empty() {
-                            chan_subs.remove(channel_id);
-                        }
-                    }
-                }
-            }
+        self.add_device_session(pseudonym, None, sender).await.0
+    }
 
-            if channels.is_some() {
-                let mut user_subs = self.user_subscriptions.write().await;
-                user_subs.remove(&pseudonym);
+    /// Registers a new session for a pseudonym, optionally bound to a device.
+    ///
+    /// Existing sessions for the pseudonym stay connected. If the same
+    /// device already has a session (e.g. it reconnected before the old
+    /// socket timed out), that stale session is closed and replaced.
+    ///
+    /// Returns the session ID and a handle that is notified when the server
+    /// closes the session.
+    pub async fn add_device_session(
+        &self,
+        pseudonym: String,
+        device_id: Option<String>,
+        sender: mpsc::Sender<String>,
+    ) -> (Uuid, Arc<Notify>) {
+        let session_id = Uuid::new_v4();
+        let close = Arc::new(Notify::new());
+
+        let stale = match device_id {
+            Some(ref device) => {
+                let sessions = self.sessions.read().await;
+                sessions.ids_for(&pseudonym).into_iter().find(|id| {
+                    sessions
+                        .by_id
+                        .get(id)
+                        .is_some_and(|s| s.device_id.as_deref() == Some(device.as_str()))
+                })
             }
-
+            None => None,
+        };
+        if let Some(stale_id) = stale {
+            self.close_session(stale_id).await;
             tracing::info!(
                 pseudonym = %pseudonym,
-                "replaced existing WebSocket session; cleaned up old subscriptions"
+                device_id = ?device_id,
+                "replaced existing WebSocket session for device"
             );
         }
 
+        let mut sessions = self.sessions.write().await;
+        sessions
+            .by_pseudonym
+            .entry(pseudonym.clone())
+            .or_default()
+            .insert(session_id);
+        sessions.by_id.insert(
+            session_id,
+            SessionHandle {
+                pseudonym,
+                device_id,
+                sender,
+                close: close.clone(),
+                idle: false,
+            },
+        );
+        (session_id, close)
+    }
+
+    /// Returns `None` if a pseudonym has no live session, otherwise whether
+    /// every one of its sessions is idle.
+    pub async fn presence(&self, pseudonym: &str) -> Option<bool> {
+        let sessions = self.sessions.read().await;
+        let ids = sessions.by_pseudonym.get(pseudonym)?;
+        Some(
+            ids.iter()
+                .all(|id| sessions.by_id.get(id).is_some_and(|s| s.idle)),
+        )
+    }
+
+    /// Marks a session idle or active again.
+    ///
+    /// Returns whether this changed the [`presence`](Self::presence) of the
+    /// session's pseudonym.
+    pub async fn set_session_idle(&self, session_id: Uuid, idle: bool) -> bool {
+        let mut sessions = self.sessions.write().await;
+        let Some(session) = sessions.by_id.get_mut(&session_id) else {
+            return false;
+        };
+        if session.idle == idle {
+            return false;
+        }
+        session.idle = idle;
+        let pseudonym = session.pseudonym.clone();
+        // Going idle matters only if it was the last active session; coming
+        // back only if every session was idle.
+        sessions
+            .ids_for(&pseudonym)
+            .iter()
+            .filter(|id| **id != session_id)
+            .all(|id| sessions.by_id.get(id).is_some_and(|s| s.idle))
+    }
+
+    /// Returns the number of live sessions for a pseudonym.
+    pub async fn session_count(&self, pseudonym: &str) -> usize {
         self.sessions
-            .write()
+            .read()
             .await
-            .insert(pseudonym, (session_id, sender));
-        session_id
+            .by_pseudonym
+            .get(pseudonym)
+            .map_or(0, HashSet::len)
     }
 
-    /// Disconnects a user by pseudonym, closing their WebSocket session.
+    /// Disconnects every session of a pseudonym, closing their WebSockets.
     pub async fn disconnect_user(&self, pseudonym: &str) {
-        let session_id = {
+        let ids = self.sessions.read().await.ids_for(pseudonym);
+        for id in ids {
+            self.close_session(id).await;
+        }
+    }
+
+    /// Disconnects the sessions a specific device holds for a pseudonym.
+    pub async fn disconnect_device(&self, pseudonym: &str, device_id: &str) {
+        let ids: Vec<Uuid> = {
             let sessions = self.sessions.read().await;
-            sessions.get(pseudonym).map(|(id, _)| *id)
+            sessions
+                .ids_for(pseudonym)
+                .into_iter()
+                .filter(|id| {
+                    sessions
+                        .by_id
+                        .get(id)
+                        .is_some_and(|s| s.device_id.as_deref() == Some(device_id))
+                })
+                .collect()
         };
-
-        if let Some(id) = session_id {
-            self.remove_session(pseudonym, id).await;
+        for id in ids {
+            self.close_session(id).await;
         }
     }
 
+    /// Removes a session and signals its socket task to close.
+    async fn close_session(&self, session_id: Uuid) {
+        let (pseudonym, close) = {
+            let sessions = self.sessions.read().await;
+            match sessions.by_id.get(&session_id) {
+                Some(s) => (s.pseudonym.clone(), s.close.clone()),
+                None => return,
+            }
+        };
+        self.remove_session(&pseudonym, session_id).await;
+        close.notify_one();
+    }
+
     /// Removes a session for a pseudonym if the session ID matches.
     ///
-    /// Lock ordering: sessions → channel_subscriptions → user_subscriptions.
+    /// Lock ordering: sessions → channel_subscriptions → session_subscriptions.
     /// This matches the ordering used by `subscribe` and `unsubscribe`
-    /// (channel_subscriptions → user_subscriptions) to prevent deadlocks.
+    /// (channel_subscriptions → session_subscriptions) to prevent deadlocks.
     pub async fn remove_session(&self, pseudonym: &str, session_id: Uuid) {
         // 1. Remove from sessions (independent lock, always acquired first).
         {
             let mut sessions = self.sessions.write().await;
-            if let Some((current_id, _)) = sessions.get(pseudonym) {
-                if *current_id != session_id {
-                    return; // Stale removal request
+            match sessions.by_id.get(&session_id) {
+                Some(s) if s.pseudonym == pseudonym => {}
+                _ => return, // Stale or mismatched removal request
+            }
+            sessions.by_id.remove(&session_id);
+            if let Some(ids) = sessions.by_pseudonym.get_mut(pseudonym) {
+                ids.remove(&session_id);
+                if ids.is_empty() {
+                    sessions.by_pseudonym.remove(pseudonym);
                 }
-            } else {
-                return; // Already removed
             }
-            sessions.remove(pseudonym);
         }
 
-        // 2. Collect the channels this user was subscribed to.
+        // 2. Collect the channels this session was subscribed to.
         let channels = {
-            let user_subs = self.user_subscriptions.read().await;
-            user_subs.get(pseudonym).cloned()
+            let session_subs = self.session_subscriptions.read().await;
+            session_subs.get(&session_id).cloned()
         };
 
         // 3. Remove from channel_subscriptions first (consistent with subscribe/unsubscribe).
@@ -349,7 +570,7 @@ impl ConnectionManager {
             let mut chan_subs = self.channel_subscriptions.write().await;
             for channel_id in channels {
                 if let Some(listeners) = chan_subs.get_mut(channel_id) {
-                    listeners.remove(pseudonym);
+                    listeners.remove(&session_id);
                     if listeners.is_empty() {
                         chan_subs.remove(channel_id);
                     }
@@ -357,54 +578,88 @@ impl ConnectionManager {
             }
         }
 
-        // 4. Remove from user_subscriptions last.
+        // 4. Remove from session_subscriptions last.
         if channels.is_some() {
-            let mut user_subs = self.user_subscriptions.write().await;
-            user_subs.remove(pseudonym);
+            let mut session_subs = self.session_subscriptions.write().await;
+            session_subs.remove(&session_id);
         }
     }
 
-    /// Subscribes a pseudonym to a channel.
-    pub async fn subscribe(&self, channel_id: String, pseudonym: String) {
+    /// Subscribes a single session to a channel.
+    pub async fn subscribe_session(&self, channel_id: String, session_id: Uuid) {
         let mut chan_subs = self.channel_subscriptions.write().await;
         chan_subs
             .entry(channel_id.clone())
             .or_default()
-            .insert(pseudonym.clone());
+            .insert(session_id);
 
-        let mut user_subs = self.user_subscriptions.write().await;
-        user_subs.entry(pseudonym).or_default().insert(channel_id);
+        let mut session_subs = self.session_subscriptions.write().await;
+        session_subs
+            .entry(session_id)
+            .or_default()
+            .insert(channel_id);
     }
 
-    /// Unsubscribes a pseudonym from a channel.
-    pub async fn unsubscribe(&self, channel_id: &str, pseudonym: &str) {
+    /// Unsubscribes a single session from a channel.
+    pub async fn unsubscribe_session(&self, channel_id: &str, session_id: Uuid) {
         let mut chan_subs = self.channel_subscriptions.write().await;
         if let Some(listeners) = chan_subs.get_mut(channel_id) {
-            listeners.remove(pseudonym);
+            listeners.remove(&session_id);
             if listeners.is_empty() {
                 chan_subs.remove(channel_id);
             }
         }
 
-        let mut user_subs = self.user_subscriptions.write().await;
-        if let Some(channels) = user_subs.get_mut(pseudonym) {
+        let mut session_subs = self.session_subscriptions.write().await;
+        if let Some(channels) = session_subs.get_mut(&session_id) {
             channels.remove(channel_id);
             if channels.is_empty() {
-                user_subs.remove(pseudonym);
+                session_subs.remove(&session_id);
             }
         }
     }
 
+    /// Subscribes every live session of a pseudonym to a channel.
+    pub async fn subscribe(&self, channel_id: String, pseudonym: String) {
+        let ids = self.sessions.read().await.ids_for(&pseudonym);
+        for id in ids {
+            self.subscribe_session(channel_id.clone(), id).await;
+        }
+    }
+
+    /// Unsubscribes every live session of a pseudonym from a channel.
+    pub async fn unsubscribe(&self, channel_id: &str, pseudonym: &str) {
+        let ids = self.sessions.read().await.ids_for(pseudonym);
+        for id in ids {
+            self.unsubscribe_session(channel_id, id).await;
+        }
+    }
+
     /// Broadcasts a message string to all subscribers of a channel.
     pub async fn broadcast(&self, channel_id: &str, message_json: String) {
+        self.broadcast_except(channel_id, message_json, &HashSet::new())
+            .await;
+    }
+
+    /// Broadcasts a message string to all subscribers of a channel except the
+    /// sessions of the `excluded` pseudonyms.
+    pub async fn broadcast_except(
+        &self,
+        channel_id: &str,
+        message_json: String,
+        excluded: &HashSet<String>,
+    ) {
         let chan_subs = self.channel_subscriptions.read().await;
         if let Some(listeners) = chan_subs.get(channel_id) {
             let sessions = self.sessions.read().await;
-            for pseudonym in listeners {
-                if let Some((_, sender)) = sessions.get(pseudonym) {
-                    if let Err(e) = sender.try_send(message_json.clone()) {
+            for session_id in listeners {
+                if let Some(session) = sessions.by_id.get(session_id) {
+                    if excluded.contains(&session.pseudonym) {
+                        continue;
+                    }
+                    if let Err(e) = session.sender.try_send(message_json.clone()) {
                         tracing::warn!(
-                            pseudonym = %pseudonym,
+                            pseudonym = %session.pseudonym,
                             channel_id = %channel_id,
                             "dropping broadcast message for slow consumer: {}",
                             e
@@ -415,16 +670,18 @@ impl ConnectionManager {
         }
     }
 
-    /// Sends a message string to a specific user (pseudonym).
+    /// Sends a message string to every session of a specific user (pseudonym).
     pub async fn send(&self, pseudonym: &str, message_json: String) {
         let sessions = self.sessions.read().await;
-        if let Some((_, sender)) = sessions.get(pseudonym) {
-            if let Err(e) = sender.try_send(message_json) {
-                tracing::warn!(
-                    pseudonym = %pseudonym,
-                    "dropping direct message for slow consumer: {}",
-                    e
-                );
+        for session_id in sessions.by_pseudonym.get(pseudonym).into_iter().flatten() {
+            if let Some(session) = sessions.by_id.get(session_id) {
+                if let Err(e) = session.sender.try_send(message_json.clone()) {
+                    tracing::warn!(
+                        pseudonym = %pseudonym,
+                        "dropping direct message for slow consumer: {}",
+                        e
+                    );
+                }
             }
         }
     }
@@ -434,14 +691,35 @@ impl ConnectionManager {
 /// token for the authenticated user. Clients should call this endpoint and
 /// then connect to `/ws?token=<token>` instead of passing raw pseudonyms.
 ///
+/// The token is not bound to a device, so it is refused once the identity has
+/// registered devices; those must use `POST /api/devices/{deviceId}/ws-token`.
+///
 /// Requires authentication via `auth_middleware` (X-Annex-Pseudonym or Bearer).
 pub async fn create_ws_token_handler(
     Extension(state): Extension<Arc<AppState>>,
     Extension(crate::middleware::IdentityContext(identity)): Extension<
         crate::middleware::IdentityContext,
     >,
-) -> Result<axum::Json<serde_json::Value>, StatusCode> {
-    let token = generate_ws_token(&identity.pseudonym_id, &state.ws_token_secret);
+) -> Result<axum::Json<serde_json::Value>, ApiError> {
+    let task_state = state.clone();
+    let pseudonym = identity.pseudonym_id.clone();
+    let devices = tokio::task::spawn_blocking(move || {
+        let conn = task_state
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+        count_active_devices(&conn, task_state.server_id, &pseudonym)
+            .map_err(|e| ApiError::InternalServerError(format!("failed to load devices: {}", e)))
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+    if devices > 0 {
+        return Err(ApiError::Forbidden(
+            "identity has registered devices; request a device token instead".to_string(),
+        ));
+    }
+
+    let token = generate_ws_token(&identity.pseudonym_id, None, &state.ws_token_secret);
     Ok(axum::Json(serde_json::json!({
         "token": token,
         "expires_in_secs": WS_TOKEN_TTL_SECS,
@@ -466,9 +744,9 @@ pub async fn ws_handler(
     Query(params): Query<WsConnectParams>,
 ) -> impl IntoResponse {
     // 1. Resolve pseudonym — prefer signed token over raw pseudonym
-    let pseudonym = if let Some(ref token) = params.token {
+    let (pseudonym, device_id) = if let Some(ref token) = params.token {
         match verify_ws_token(token, &state.ws_token_secret) {
-            Ok(p) => p,
+            Ok(resolved) => resolved,
             Err(code) => {
                 tracing::warn!(
                     remote_addr = %addr,
@@ -484,7 +762,7 @@ pub async fn ws_handler(
             remote_addr = %addr,
             "websocket auth via legacy pseudonym parameter (deprecated)"
         );
-        p.clone()
+        (p.clone(), None)
     } else {
         tracing::warn!(remote_addr = %addr, "websocket connect missing token and pseudonym");
         return StatusCode::UNAUTHORIZED.into_response();
@@ -493,6 +771,7 @@ pub async fn ws_handler(
     // 2. Authenticate via DB
     let server_id = state.server_id;
     let pseudonym_clone = pseudonym.clone();
+    let device_clone = device_id.clone();
 
     let state_clone = state.clone();
     let auth_result = tokio::task::spawn_blocking(move || {
@@ -500,11 +779,33 @@ pub async fn ws_handler(
             .pool
             .get()
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        match get_platform_identity(&conn, server_id, &pseudonym_clone) {
-            Ok(identity) if identity.active => Ok(identity),
-            Ok(_) => Err(StatusCode::FORBIDDEN), // Inactive
-            Err(_) => Err(StatusCode::UNAUTHORIZED),
+        let identity = match get_platform_identity(&conn, server_id, &pseudonym_clone) {
+            Ok(identity) if identity.active => identity,
+            Ok(_) => return Err(StatusCode::FORBIDDEN), // Inactive
+            Err(_) => return Err(StatusCode::UNAUTHORIZED),
+        };
+
+        // Device-bound tokens are only honoured while the device is active,
+        // and once the identity has devices, only device-bound tokens are.
+        if device_clone.is_none() {
+            match count_active_devices(&conn, server_id, &pseudonym_clone) {
+                Ok(0) => {}
+                Ok(_) => return Err(StatusCode::UNAUTHORIZED),
+                Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            }
+        }
+        if let Some(ref device_id) = device_clone {
+            match get_device(&conn, server_id, device_id) {
+                Ok(Some(device))
+                    if device.pseudonym_id == pseudonym_clone && device.revoked_at.is_none() => {}
+                Ok(_) => return Err(StatusCode::UNAUTHORIZED),
+                Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            }
+            if let Err(e) = touch_device(&conn, server_id, device_id) {
+                tracing::warn!(device_id = %device_id, "failed to update device last_seen_at: {}", e);
+            }
         }
+        Ok(identity)
     })
     .await;
 
@@ -514,9 +815,10 @@ pub async fn ws_handler(
                 pseudonym = %pseudonym,
                 remote_addr = %addr,
                 token_auth = params.token.is_some(),
+                device_id = ?device_id,
                 "websocket auth success"
             );
-            ws.on_upgrade(move |socket| handle_socket(socket, state, identity))
+            ws.on_upgrade(move |socket| handle_socket(socket, state, identity, device_id))
         }
         Ok(Err(code)) => {
             tracing::warn!(
@@ -600,7 +902,12 @@ const MAX_VOICE_INTENT_TEXT_LEN: usize = 2_048;
 const ACTIVITY_DEBOUNCE: std::time::Duration = std::time::Duration::from_secs(30);
 
 /// Handles the WebSocket connection.
-async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: PlatformIdentity) {
+async fn handle_socket(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    identity: PlatformIdentity,
+    device_id: Option<String>,
+) {
     let pseudonym = identity.pseudonym_id.clone();
 
     // 1. Mark as active immediately
@@ -613,11 +920,24 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
     // operation; beyond that the client is too slow and messages are dropped.
     let (tx, mut rx) = mpsc::channel::<String>(256);
 
-    // Register session
-    let session_id = state
+    // Register session. Other devices of the same pseudonym stay connected.
+    let presence_before = state.connection_manager.presence(&pseudonym).await;
+    let (session_id, closed) = state
         .connection_manager
-        .add_session(pseudonym.clone(), tx.clone())
+        .add_device_session(pseudonym.clone(), device_id, tx.clone())
         .await;
+    publish_presence_change(&state, &pseudonym, presence_before).await;
+
+    let idle_timeout = state
+        .policy
+        .read()
+        .map(|p| std::time::Duration::from_secs(p.presence_idle_timeout_secs))
+        .unwrap_or_else(|e| {
+            tracing::error!("policy lock poisoned: {}", e);
+            std::time::Duration::ZERO
+        });
+    let mut idle_deadline = tokio::time::Instant::now() + idle_timeout;
+    let mut idle = false;
 
     // Spawn a task to forward messages from rx to the websocket sender
     let send_task = tokio::spawn(async move {
@@ -631,8 +951,46 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
     // Track last activity update to debounce DB writes
     let mut last_activity = std::time::Instant::now();
 
-    // Handle incoming messages
-    while let Some(Ok(msg)) = receiver.next().await {
+    // Voice intents being spoken or queued, oldest first.
+    let mut speech_tasks: Vec<tokio::task::AbortHandle> = Vec::new();
+    let mut last_speech: Option<tokio::task::JoinHandle<()>> = None;
+
+    // Handle incoming messages until the client leaves or the server closes
+    // the session (device revoked, identity deactivated).
+    loop {
+        let msg = tokio::select! {
+            msg = receiver.next() => msg,
+            _ = closed.notified() => {
+                tracing::info!(pseudonym = %pseudonym, "websocket session closed by server");
+                break;
+            }
+            _ = tokio::time::sleep_until(idle_deadline), if !idle && !idle_timeout.is_zero() => {
+                idle = true;
+                if state.connection_manager.set_session_idle(session_id, true).await {
+                    publish_status(&state, &pseudonym).await;
+                }
+                continue;
+            }
+        };
+        let Some(Ok(msg)) = msg else {
+            break;
+        };
+
+        // Only client-sent frames count as activity; protocol pings do not.
+        if matches!(msg, AxumMessage::Text(_)) {
+            idle_deadline = tokio::time::Instant::now() + idle_timeout;
+            if idle {
+                idle = false;
+                if state
+                    .connection_manager
+                    .set_session_idle(session_id, false)
+                    .await
+                {
+                    publish_status(&state, &pseudonym).await;
+                }
+            }
+        }
+
         // Debounce activity updates: only spawn a DB write if enough time has passed
         if last_activity.elapsed() >= ACTIVITY_DEBOUNCE {
             tokio::spawn(touch_activity(state.clone(), pseudonym.clone()));
@@ -654,7 +1012,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
                             MembershipResult::Allowed => {
                                 state
                                     .connection_manager
-                                    .subscribe(channel_id, pseudonym.clone())
+                                    .subscribe_session(channel_id, session_id)
                                     .await;
                             }
                             MembershipResult::Denied => {
@@ -677,10 +1035,53 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
                             }
                         }
                     }
+                    IncomingMessage::Heartbeat => {}
+                    IncomingMessage::SetStatus {
+                        status,
+                        status_text,
+                    } => {
+                        let state_clone = state.clone();
+                        let pid = pseudonym.clone();
+                        let result = tokio::task::spawn_blocking(move || {
+                            let conn = state_clone.pool.get().map_err(|e| e.to_string())?;
+                            let previous = annex_graph::get_participant_status(
+                                &conn,
+                                state_clone.server_id,
+                                &pid,
+                            )
+                            .map_err(|e| e.to_string())?;
+                            let updated = annex_graph::set_participant_status(
+                                &conn,
+                                state_clone.server_id,
+                                &pid,
+                                status,
+                                status_text.as_deref(),
+                            )
+                            .map_err(|e| e.to_string())?;
+                            Ok::<_, String>((previous, updated))
+                        })
+                        .await;
+
+                        match result {
+                            Ok(Ok((previous, updated))) => {
+                                let presence = state.connection_manager.presence(&pseudonym).await;
+                                let event = updated.event(&pseudonym, presence);
+                                if previous.event(&pseudonym, presence) != event {
+                                    let _ = state.presence_tx.send(event);
+                                }
+                                send_status_message(&tx, updated);
+                            }
+                            Ok(Err(e)) => send_ws_error(&tx, e),
+                            Err(e) => {
+                                tracing::error!("set_status task failed: {}", e);
+                                send_ws_error(&tx, "Internal error setting status".to_string());
+                            }
+                        }
+                    }
                     IncomingMessage::Unsubscribe { channel_id } => {
                         state
                             .connection_manager
-                            .unsubscribe(&channel_id, &pseudonym)
+                            .unsubscribe_session(&channel_id, session_id)
                             .await;
                     }
                     IncomingMessage::Message {
@@ -755,12 +1156,20 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
                             let is_federated =
                                 matches!(channel.federation_scope, FederationScope::Federated);
 
-                            Ok::<_, String>((msg, is_federated))
+                            // Participants who blocked the sender don't receive the message.
+                            let blockers = annex_graph::blockers_of(
+                                &conn,
+                                state_clone.server_id,
+                                &msg.sender_pseudonym,
+                            )
+                            .map_err(|e| e.to_string())?;
+
+                            Ok::<_, String>((msg, is_federated, blockers))
                         })
                         .await;
 
                         match res {
-                            Ok(Ok((message, is_federated))) => {
+                            Ok(Ok((message, is_federated, blockers))) => {
                                 // Broadcast via WebSocket (camelCase payload)
                                 let ws_payload: WsMessagePayload = message.clone().into();
                                 let broadcast_channel_id = message.channel_id.clone();
@@ -769,7 +1178,11 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
                                     Ok(json) => {
                                         state
                                             .connection_manager
-                                            .broadcast(&broadcast_channel_id, json)
+                                            .broadcast_except(
+                                                &broadcast_channel_id,
+                                                json,
+                                                &blockers,
+                                            )
                                             .await;
                                     }
                                     Err(e) => {
@@ -868,13 +1281,21 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
 
                         let res = tokio::task::spawn_blocking(move || {
                             let conn = state_clone.pool.get().map_err(|e| e.to_string())?;
-                            edit_message(&conn, &message_id, &pseudonym_clone, &content)
-                                .map_err(|e| e.to_string())
+                            let updated =
+                                edit_message(&conn, &message_id, &pseudonym_clone, &content)
+                                    .map_err(|e| e.to_string())?;
+                            let blockers = annex_graph::blockers_of(
+                                &conn,
+                                state_clone.server_id,
+                                &updated.sender_pseudonym,
+                            )
+                            .map_err(|e| e.to_string())?;
+                            Ok::<_, String>((updated, blockers))
                         })
                         .await;
 
                         match res {
-                            Ok(Ok(updated)) => {
+                            Ok(Ok((updated, blockers))) => {
                                 // Use the persisted channel_id from DB, not the
                                 // client-supplied one, to prevent cross-channel
                                 // broadcast spoofing.
@@ -885,7 +1306,11 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
                                     Ok(json) => {
                                         state
                                             .connection_manager
-                                            .broadcast(&persisted_channel_id, json)
+                                            .broadcast_except(
+                                                &persisted_channel_id,
+                                                json,
+                                                &blockers,
+                                            )
                                             .await;
                                     }
                                     Err(e) => {
@@ -946,13 +1371,20 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
 
                         let res = tokio::task::spawn_blocking(move || {
                             let conn = state_clone.pool.get().map_err(|e| e.to_string())?;
-                            delete_message(&conn, &message_id, &pseudonym_clone)
-                                .map_err(|e| e.to_string())
+                            let updated = delete_message(&conn, &message_id, &pseudonym_clone)
+                                .map_err(|e| e.to_string())?;
+                            let blockers = annex_graph::blockers_of(
+                                &conn,
+                                state_clone.server_id,
+                                &updated.sender_pseudonym,
+                            )
+                            .map_err(|e| e.to_string())?;
+                            Ok::<_, String>((updated, blockers))
                         })
                         .await;
 
                         match res {
-                            Ok(Ok(updated)) => {
+                            Ok(Ok((updated, blockers))) => {
                                 // Use the persisted channel_id from DB, not the
                                 // client-supplied one, to prevent cross-channel
                                 // broadcast spoofing.
@@ -963,7 +1395,11 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
                                     Ok(json) => {
                                         state
                                             .connection_manager
-                                            .broadcast(&persisted_channel_id, json)
+                                            .broadcast_except(
+                                                &persisted_channel_id,
+                                                json,
+                                                &blockers,
+                                            )
                                             .await;
                                     }
                                     Err(e) => {
@@ -1077,140 +1513,24 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
                             }
                         };
 
-                        // Synthesize
-                        match state.tts_service.synthesize(&text, &voice_profile_id).await {
-                            Ok(audio) => {
-                                // Get or create voice client.
-                                // Fast-path: read lock to check for existing session.
-                                let client_opt = match state.voice_sessions.read() {
-                                    Ok(sessions) => sessions.get(&pseudonym).cloned(),
-                                    Err(_) => {
-                                        tracing::error!("voice_sessions lock poisoned");
-                                        continue;
-                                    }
-                                };
-
-                                let client = if let Some(c) = client_opt {
-                                    c
-                                } else {
-                                    // Connect a new voice client
-                                    let room_name = channel_id.clone();
-                                    let token = match state
-                                        .voice_service
-                                        .generate_join_token(&room_name, &pseudonym, &pseudonym)
-                                    {
-                                        Ok(t) => t,
-                                        Err(e) => {
-                                            tracing::error!(
-                                                pseudonym = %pseudonym,
-                                                room = %room_name,
-                                                "failed to generate voice join token: {}",
-                                                e
-                                            );
-                                            send_ws_error(
-                                                &tx,
-                                                "Failed to generate voice token".to_string(),
-                                            );
-                                            continue;
-                                        }
-                                    };
-                                    let url = state.voice_service.get_url();
-
-                                    match annex_voice::AgentVoiceClient::connect(
-                                        url,
-                                        &token,
-                                        &room_name,
-                                        state.stt_service.clone(),
-                                        state.voice_service.api_key(),
-                                        state.voice_service.api_secret(),
-                                    )
-                                    .await
-                                    {
-                                        Ok(c) => {
-                                            let arc = Arc::new(c);
-
-                                            // Double-check under write lock to prevent
-                                            // TOCTOU race with concurrent voice intents.
-                                            match state.voice_sessions.write() {
-                                                Ok(mut sessions) => {
-                                                    use std::collections::hash_map::Entry;
-                                                    match sessions.entry(pseudonym.clone()) {
-                                                        Entry::Vacant(entry) => {
-                                                            // Subscribe to transcriptions only for the winning insert
-                                                            let mut rx =
-                                                                arc.subscribe_transcriptions();
-                                                            let cm =
-                                                                state.connection_manager.clone();
-                                                            let p_clone = pseudonym.clone();
-
-                                                            tokio::spawn(async move {
-                                                                while let Ok(event) =
-                                                                    rx.recv().await
-                                                                {
-                                                                    let msg = OutgoingMessage::Transcription {
-                                                                        channel_id: event.channel_id,
-                                                                        speaker_pseudonym: event.speaker_pseudonym,
-                                                                        text: event.text,
-                                                                    };
-
-                                                                    match serde_json::to_string(
-                                                                        &msg,
-                                                                    ) {
-                                                                        Ok(json) => {
-                                                                            cm.send(&p_clone, json)
-                                                                                .await;
-                                                                        }
-                                                                        Err(e) => {
-                                                                            tracing::error!(
-                                                                                "failed to serialize transcription message: {}", e
-                                                                            );
-                                                                        }
-                                                                    }
-                                                                }
-                                                            });
-
-                                                            entry.insert(arc.clone());
-                                                        }
-                                                        Entry::Occupied(_) => {
-                                                            // Concurrent request won; drop our client
-                                                        }
-                                                    }
-                                                    match sessions.get(&pseudonym).cloned() {
-                                                        Some(s) => s,
-                                                        None => {
-                                                            // Should never happen: we either just inserted or the Occupied branch
-                                                            // guarantees presence. If it does, log and skip the voice operation.
-                                                            tracing::error!(
-                                                                pseudonym = %pseudonym,
-                                                                "voice session missing after insert; this is a bug"
-                                                            );
-                                                            continue;
-                                                        }
-                                                    }
-                                                }
-                                                Err(_) => {
-                                                    tracing::error!("voice_sessions lock poisoned");
-                                                    continue;
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            send_ws_error(
-                                                &tx,
-                                                format!("Failed to connect voice: {}", e),
-                                            );
-                                            continue;
-                                        }
-                                    }
-                                };
-
-                                if let Err(e) = client.publish_audio(&audio).await {
-                                    send_ws_error(&tx, format!("Failed to publish audio: {}", e));
-                                }
-                            }
-                            Err(e) => {
-                                send_ws_error(&tx, format!("TTS failed: {}", e));
-                            }
+                        // Speak once the connection's previous intent is
+                        // done, without blocking this receive loop.
+                        let speech = tokio::spawn(speak(
+                            state.clone(),
+                            pseudonym.clone(),
+                            channel_id,
+                            text,
+                            voice_profile_id,
+                            tx.clone(),
+                            last_speech.take(),
+                        ));
+                        speech_tasks.retain(|task| !task.is_finished());
+                        speech_tasks.push(speech.abort_handle());
+                        last_speech = Some(speech);
+                    }
+                    IncomingMessage::VoiceCancel => {
+                        for task in speech_tasks.drain(..) {
+                            task.abort();
                         }
                     }
                 }
@@ -1223,12 +1543,24 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
         }
     }
 
+    // An agent that leaves stops speaking.
+    for task in speech_tasks {
+        task.abort();
+    }
+
     // Cleanup with session_id check
+    let presence_before = state.connection_manager.presence(&pseudonym).await;
     state
         .connection_manager
         .remove_session(&pseudonym, session_id)
         .await;
     send_task.abort();
+    publish_presence_change(&state, &pseudonym, presence_before).await;
+
+    // Other devices may still be connected; they keep the voice session.
+    if state.connection_manager.session_count(&pseudonym).await > 0 {
+        return;
+    }
 
     // Clean up voice session for this pseudonym. Dropping the Arc will
     // decrement the reference count; when it reaches zero the
@@ -1247,6 +1579,211 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, identity: Platfo
     }
 }
 
+/// Synthesizes a voice intent and publishes it to the agent's voice session
+/// frame by frame as it is rendered. Waits for `previous`, the connection's
+/// prior intent, so intents are spoken in order.
+async fn speak(
+    state: Arc<AppState>,
+    pseudonym: String,
+    channel_id: String,
+    text: String,
+    voice_profile_id: String,
+    tx: mpsc::Sender<String>,
+    previous: Option<tokio::task::JoinHandle<()>>,
+) {
+    if let Some(previous) = previous {
+        // A cancelled predecessor is not an error.
+        let _ = previous.await;
+    }
+
+    let mut stream = match state
+        .tts_service
+        .synthesize_stream(&text, &voice_profile_id)
+        .await
+    {
+        Ok(stream) => stream,
+        Err(e) => {
+            send_ws_error(&tx, format!("TTS failed: {}", e));
+            return;
+        }
+    };
+
+    // Report synthesis errors before opening a voice session.
+    match stream.ready().await {
+        Some(Ok(())) => {}
+        Some(Err(e)) => {
+            send_ws_error(&tx, format!("TTS failed: {}", e));
+            return;
+        }
+        None => return,
+    };
+
+    let Some(client) = agent_voice_client(&state, &pseudonym, &channel_id, &tx).await else {
+        return;
+    };
+    crate::api_captions::caption_agent_speech(&state, &channel_id, &pseudonym, &text).await;
+
+    match client.publish_stream(&mut stream).await {
+        Ok(_) => {}
+        Err(e @ annex_voice::VoiceError::Tts(_)) => {
+            send_ws_error(&tx, format!("TTS failed: {}", e));
+        }
+        Err(e) => {
+            send_ws_error(&tx, format!("Failed to publish audio: {}", e));
+        }
+    }
+}
+
+/// Returns the agent's voice client, connecting one to `channel_id` if the
+/// agent has no voice session yet. Errors are reported on `tx`.
+async fn agent_voice_client(
+    state: &Arc<AppState>,
+    pseudonym: &str,
+    channel_id: &str,
+    tx: &mpsc::Sender<String>,
+) -> Option<Arc<annex_voice::AgentVoiceClient>> {
+    // Fast-path: read lock to check for existing session.
+    match state.voice_sessions.read() {
+        Ok(sessions) => {
+            if let Some(client) = sessions.get(pseudonym) {
+                return Some(client.clone());
+            }
+        }
+        Err(_) => {
+            tracing::error!("voice_sessions lock poisoned");
+            return None;
+        }
+    }
+
+    // Connect a new voice client
+    let token = match state
+        .voice_service
+        .generate_join_token(channel_id, pseudonym, pseudonym)
+    {
+        Ok(t) => t,
+        Err(e) => {
+            tracing::error!(
+                pseudonym = %pseudonym,
+                room = %channel_id,
+                "failed to generate voice join token: {}",
+                e
+            );
+            send_ws_error(tx, "Failed to generate voice token".to_string());
+            return None;
+        }
+    };
+    let url = state.voice_service.get_url();
+
+    let client = match annex_voice::AgentVoiceClient::connect(
+        url,
+        &token,
+        channel_id,
+        state.stt_service.clone(),
+        state.voice_service.api_key(),
+        state.voice_service.api_secret(),
+    )
+    .await
+    {
+        Ok(c) => Arc::new(c),
+        Err(e) => {
+            send_ws_error(tx, format!("Failed to connect voice: {}", e));
+            return None;
+        }
+    };
+
+    // Double-check under write lock to prevent TOCTOU race with concurrent
+    // voice intents.
+    let Ok(mut sessions) = state.voice_sessions.write() else {
+        tracing::error!("voice_sessions lock poisoned");
+        return None;
+    };
+    let entry = match sessions.entry(pseudonym.to_string()) {
+        std::collections::hash_map::Entry::Occupied(entry) => {
+            // Concurrent request won; drop our client
+            return Some(entry.get().clone());
+        }
+        std::collections::hash_map::Entry::Vacant(entry) => entry,
+    };
+
+    // Subscribe to transcriptions only for the winning insert
+    let mut rx = client.subscribe_transcriptions();
+    let cm = state.connection_manager.clone();
+    let p_clone = pseudonym.to_string();
+    tokio::spawn(async move {
+        while let Ok(event) = rx.recv().await {
+            let msg = OutgoingMessage::Transcription {
+                channel_id: event.channel_id,
+                speaker_pseudonym: event.speaker_pseudonym,
+                text: event.text,
+                segment_id: event.segment_id,
+                is_final: event.is_final,
+                start_ms: event.start_ms,
+                end_ms: event.end_ms,
+            };
+
+            match serde_json::to_string(&msg) {
+                Ok(json) => {
+                    cm.send(&p_clone, json).await;
+                }
+                Err(e) => {
+                    tracing::error!("failed to serialize transcription message: {}", e);
+                }
+            }
+        }
+    });
+
+    entry.insert(client.clone());
+    Some(client)
+}
+
+/// Announces a participant's current status on the presence stream.
+async fn publish_status(state: &Arc<AppState>, pseudonym: &str) {
+    let presence = state.connection_manager.presence(pseudonym).await;
+    let state_clone = state.clone();
+    let pid = pseudonym.to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = state_clone.pool.get().map_err(|e| e.to_string())?;
+        annex_graph::get_participant_status(&conn, state_clone.server_id, &pid)
+            .map_err(|e| e.to_string())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(status)) => {
+            let _ = state.presence_tx.send(status.event(pseudonym, presence));
+        }
+        Ok(Err(e)) => {
+            tracing::warn!(pseudonym = %pseudonym, "failed to load presence status: {}", e);
+        }
+        Err(e) => {
+            tracing::error!("presence status task failed: {}", e);
+        }
+    }
+}
+
+/// Announces a participant's status if their presence (connected, idle)
+/// differs from `before`.
+async fn publish_presence_change(state: &Arc<AppState>, pseudonym: &str, before: Option<bool>) {
+    if state.connection_manager.presence(pseudonym).await != before {
+        publish_status(state, pseudonym).await;
+    }
+}
+
+fn send_status_message(tx: &mpsc::Sender<String>, status: annex_graph::ParticipantStatus) {
+    let message = OutgoingMessage::Status {
+        status: status.status,
+        status_text: status.status_text,
+    };
+    match serde_json::to_string(&message) {
+        Ok(json) => {
+            if let Err(e) = tx.try_send(json) {
+                tracing::warn!("failed to send status to client: {}", e);
+            }
+        }
+        Err(e) => tracing::error!("failed to serialize status message: {}", e),
+    }
+}
+
 async fn touch_activity(state: Arc<AppState>, pseudonym: String) {
     let pool = state.pool.clone();
     let server_id = state.server_id;
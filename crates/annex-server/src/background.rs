@@ -2,11 +2,14 @@
 //!
 //! Includes:
 //! - Pruning inactive graph nodes.
+//! - Compacting graph history past its retention window.
 //! - Periodic rate limiter cleanup.
+//! - Closing polls whose close time has passed.
+//! - Renewing and downgrading expiring capability contracts.
 
 use crate::middleware::RateLimiter;
 use crate::AppState;
-use annex_graph::prune_inactive_nodes;
+use annex_graph::{compact_graph_history, prune_inactive_nodes};
 use annex_observe::EventPayload;
 use annex_types::PresenceEvent;
 use std::sync::Arc;
@@ -82,6 +85,47 @@ pub async fn start_pruning_task(state: Arc<AppState>, threshold_seconds: u64) {
     }
 }
 
+/// Periodically compacts the presence graph change log down to
+/// `graph_history_retention_days`. Runs every hour.
+pub async fn start_graph_history_compaction_task(state: Arc<AppState>) {
+    let interval = Duration::from_secs(60 * 60);
+    tracing::info!("starting graph history compaction task (every 1h)");
+
+    loop {
+        sleep(interval).await;
+
+        let retention_days = match state.policy.read() {
+            Ok(policy) => policy.graph_history_retention_days,
+            Err(_) => {
+                tracing::error!("server policy lock poisoned, skipping graph history compaction");
+                continue;
+            }
+        };
+        let pool = state.pool.clone();
+        let server_id = state.server_id;
+
+        let res = tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get().map_err(|e| e.to_string())?;
+            compact_graph_history(&mut conn, server_id, retention_days).map_err(|e| e.to_string())
+        })
+        .await;
+
+        match res {
+            Ok(Ok(count)) => {
+                if count > 0 {
+                    tracing::info!(count, "compacted graph history");
+                }
+            }
+            Ok(Err(e)) => {
+                tracing::error!("failed to compact graph history: {}", e);
+            }
+            Err(e) => {
+                tracing::error!("graph history compaction task join error: {}", e);
+            }
+        }
+    }
+}
+
 /// Periodically evicts expired entries from the in-memory rate limiter.
 ///
 /// This prevents unbounded memory growth from many unique IPs/pseudonyms
@@ -95,3 +139,29 @@ pub async fn start_rate_limit_cleanup_task(rate_limiter: RateLimiter) {
         rate_limiter.cleanup_expired();
     }
 }
+
+/// Periodically closes polls whose close time has passed and broadcasts
+/// their final tallies. Runs every 30 seconds.
+pub async fn start_poll_close_task(state: Arc<AppState>) {
+    let interval = Duration::from_secs(30);
+    tracing::info!("starting poll close task (every 30s)");
+
+    loop {
+        sleep(interval).await;
+        crate::api_polls::close_expired_polls_and_broadcast(&state).await;
+    }
+}
+
+/// Periodically renews capability contracts nearing expiry and downgrades
+/// lapsed ones. Runs every 60 seconds.
+pub async fn start_contract_renewal_task(state: Arc<AppState>) {
+    let interval = Duration::from_secs(60);
+    tracing::info!("starting contract renewal task (every 60s)");
+
+    loop {
+        sleep(interval).await;
+        if let Err(e) = crate::policy::renew_expiring_contracts(state.clone()).await {
+            tracing::error!("failed to renew expiring contracts: {:?}", e);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! Server configuration loading from file and environment variables.
 
 use annex_voice::LiveKitConfig;
+use annex_vrp::EmbedderConfig;
 use serde::Deserialize;
 use std::net::{IpAddr, Ipv4Addr};
 use std::str::FromStr;
@@ -36,6 +37,10 @@ pub struct Config {
     /// Security enforcement settings.
     #[serde(default)]
     pub security: SecurityConfig,
+
+    /// Semantic embedder used for VRP alignment.
+    #[serde(default)]
+    pub embedder: EmbedderConfig,
 }
 
 /// Security enforcement configuration.
@@ -393,6 +398,12 @@ fn parse_env_bool(name: &'static str) -> Result<Option<bool>, ConfigError> {
 /// - `ANNEX_TTS_BINARY_PATH` overrides `voice.tts_binary_path`
 /// - `ANNEX_STT_MODEL_PATH` overrides `voice.stt_model_path`
 /// - `ANNEX_STT_BINARY_PATH` overrides `voice.stt_binary_path`
+/// - `ANNEX_EMBEDDER_KIND` overrides `embedder.kind`
+///   (`bag_of_words`, `tf_idf`, `http` or `onnx`)
+/// - `ANNEX_EMBEDDER_URL` overrides `embedder.url`
+/// - `ANNEX_EMBEDDER_MODEL` overrides `embedder.model`
+/// - `ANNEX_EMBEDDER_MODEL_PATH` overrides `embedder.model_path`
+/// - `ANNEX_EMBEDDER_VOCAB_PATH` overrides `embedder.vocab_path`
 ///
 /// # Errors
 ///
@@ -506,6 +517,21 @@ pub fn load_config(path: Option<&str>) -> Result<Config, ConfigError> {
     if let Some(enforce) = parse_env_bool("ANNEX_ENFORCE_ZK_PROOFS")? {
         config.security.enforce_zk_proofs = enforce;
     }
+    if let Some(kind) = parse_env_var("ANNEX_EMBEDDER_KIND")? {
+        config.embedder.kind = kind;
+    }
+    if let Some(url) = parse_env_var::<String>("ANNEX_EMBEDDER_URL")? {
+        config.embedder.url = Some(url);
+    }
+    if let Some(model) = parse_env_var::<String>("ANNEX_EMBEDDER_MODEL")? {
+        config.embedder.model = Some(model);
+    }
+    if let Some(path) = parse_env_var::<String>("ANNEX_EMBEDDER_MODEL_PATH")? {
+        config.embedder.model_path = Some(path);
+    }
+    if let Some(path) = parse_env_var::<String>("ANNEX_EMBEDDER_VOCAB_PATH")? {
+        config.embedder.vocab_path = Some(path);
+    }
 
     validate_config(&config)?;
 
@@ -536,6 +562,8 @@ mod tests {
         std::env::remove_var("ANNEX_TTS_BINARY_PATH");
         std::env::remove_var("ANNEX_STT_MODEL_PATH");
         std::env::remove_var("ANNEX_STT_BINARY_PATH");
+        std::env::remove_var("ANNEX_EMBEDDER_KIND");
+        std::env::remove_var("ANNEX_EMBEDDER_URL");
     }
 
     fn write_temp_config(contents: &str) -> String {
@@ -768,6 +796,42 @@ tts_voices_dir = "/from/config/voices"
         clear_env();
     }
 
+    #[test]
+    fn embedder_from_config_file_and_env() {
+        let _guard = env_lock().lock().expect("env lock poisoned");
+        clear_env();
+
+        let path = write_temp_config(
+            r#"
+[embedder]
+kind = "http"
+url = "http://127.0.0.1:11434/v1/embeddings"
+model = "nomic-embed-text"
+"#,
+        );
+
+        let cfg = load_config(Some(path.as_str())).expect("load should succeed");
+        assert_eq!(cfg.embedder.kind, annex_vrp::EmbedderKind::Http);
+        assert_eq!(
+            cfg.embedder.url.as_deref(),
+            Some("http://127.0.0.1:11434/v1/embeddings")
+        );
+        assert_eq!(cfg.embedder.model.as_deref(), Some("nomic-embed-text"));
+
+        std::env::set_var("ANNEX_EMBEDDER_KIND", "tf_idf");
+        let cfg = load_config(Some(path.as_str())).expect("load should succeed");
+        assert_eq!(cfg.embedder.kind, annex_vrp::EmbedderKind::TfIdf);
+
+        std::env::set_var("ANNEX_EMBEDDER_KIND", "word2vec");
+        assert!(matches!(
+            load_config(Some(path.as_str())),
+            Err(ConfigError::InvalidEnvVar { .. })
+        ));
+
+        fs::remove_file(path).expect("failed to remove temp config");
+        clear_env();
+    }
+
     #[test]
     fn zero_retention_check_interval_returns_error() {
         let _guard = env_lock().lock().expect("env lock poisoned");
@@ -3,11 +3,15 @@
 pub mod api;
 pub mod api_admin;
 pub mod api_agent;
+pub mod api_captions;
 pub mod api_channels;
+pub mod api_devices;
 pub mod api_federation;
 pub mod api_graph;
+pub mod api_identity_bundle;
 pub mod api_link_preview;
 pub mod api_observe;
+pub mod api_polls;
 pub mod api_rtx;
 pub mod api_sse;
 pub mod api_upload;
@@ -52,6 +56,10 @@ pub struct AppState {
     pub merkle_tree: Arc<Mutex<MerkleTree>>,
     /// ZK Membership verification key.
     pub membership_vkey: Arc<VerifyingKey<Bn254>>,
+    /// ZK anonymous vote verification key (`vote.circom`).
+    pub vote_vkey: Arc<VerifyingKey<Bn254>>,
+    /// ZK selective-disclosure role verification key (`role.circom`).
+    pub role_vkey: Arc<VerifyingKey<Bn254>>,
     /// The local server ID.
     pub server_id: i64,
     /// The local server signing key (Ed25519).
@@ -89,6 +97,16 @@ pub struct AppState {
     pub upload_dir: String,
     /// In-memory cache for link preview metadata and proxied images.
     pub preview_cache: api_link_preview::PreviewCache,
+    /// Cached presence-graph adjacency used for path and visibility queries.
+    pub graph_index: annex_graph::AdjacencyIndex,
+    /// Cached per-participant visibility preferences, consulted by the
+    /// presence stream for every event.
+    pub visibility_preferences: annex_graph::VisibilityPreferenceCache,
+    /// Relays presence events to SSE subscribers with their audience
+    /// evaluated once per event.
+    pub presence_fanout: api_sse::PresenceFanout,
+    /// Running live-caption pipelines of voice channels.
+    pub captions: api_captions::CaptionHub,
     /// HMAC secret for signing WebSocket session tokens. Derived at startup
     /// from the server's Ed25519 key to avoid managing a separate secret.
     pub ws_token_secret: Arc<[u8; 32]>,
@@ -97,6 +115,9 @@ pub struct AppState {
     /// When true, channel access endpoints require ZK membership proof via
     /// the `x-annex-zk-proof` header.
     pub enforce_zk_proofs: bool,
+    /// Semantic embedder used for VRP principle comparison, selected by the
+    /// `[embedder]` config section.
+    pub embedder: Arc<dyn annex_vrp::SemanticEmbedder>,
 }
 
 impl AppState {
@@ -193,6 +214,9 @@ pub enum StartupError {
     /// The `ANNEX_SIGNING_KEY` environment variable was malformed.
     #[error("invalid ANNEX_SIGNING_KEY: {0}")]
     InvalidSigningKey(String),
+    /// The configured semantic embedder could not be built.
+    #[error("failed to initialize semantic embedder: {0}")]
+    Embedder(#[from] annex_vrp::VrpError),
 }
 
 /// Checks whether a LiveKit server is reachable at the given WebSocket URL
@@ -534,32 +558,30 @@ pub async fn prepare_server(config: config::Config) -> Result<(TcpListener, Rout
         }
     };
 
-    // Load ZK verification key.
+    // Load ZK verification keys.
     //
     // Priority:
-    // 1. ANNEX_ZK_KEY_PATH env var (explicit path)
-    // 2. Default path: zk/keys/membership_vkey.json
+    // 1. ANNEX_ZK_KEY_PATH / ANNEX_ZK_VOTE_KEY_PATH / ANNEX_ZK_ROLE_KEY_PATH
+    //    env vars (explicit path)
+    // 2. Default paths: zk/keys/{membership,vote,role}_vkey.json
     // 3. Fallback: generate a dummy vkey so the server can still start.
     //    With a dummy vkey all real proof verifications will fail, so identity
-    //    creation will be blocked — but the server process won't crash.
-    let vkey_path = std::env::var("ANNEX_ZK_KEY_PATH")
-        .unwrap_or_else(|_| "zk/keys/membership_vkey.json".to_string());
-    let membership_vkey = match std::fs::read_to_string(&vkey_path) {
-        Ok(vkey_json) => {
-            annex_identity::zk::parse_verification_key(&vkey_json).map_err(StartupError::ZkError)?
-        }
-        Err(e) => {
-            tracing::warn!(
-                path = %vkey_path,
-                error = %e,
-                "ZK verification key not found — using dummy key. \
-                 Identity creation will fail until a real key is provided. \
-                 Run the ZK build (cd zk && npm ci && node scripts/build-circuits.js && \
-                 node scripts/setup-groth16.js) to generate one."
-            );
-            annex_identity::zk::generate_dummy_vkey()
-        }
-    };
+    //    creation (or poll voting, role disclosure) will be blocked — but the
+    //    server process won't crash.
+    let membership_vkey =
+        load_verification_key("ANNEX_ZK_KEY_PATH", "zk/keys/membership_vkey.json")?;
+    let vote_vkey = load_verification_key("ANNEX_ZK_VOTE_KEY_PATH", "zk/keys/vote_vkey.json")?;
+    let role_vkey = load_verification_key("ANNEX_ZK_ROLE_KEY_PATH", "zk/keys/role_vkey.json")?;
+
+    // The http embedder wraps a blocking HTTP client, which must not be
+    // created on an async worker thread.
+    let embedder_config = config.embedder.clone();
+    let embedder = tokio::task::spawn_blocking(move || annex_vrp::build_embedder(&embedder_config))
+        .await
+        .map_err(|e| {
+            annex_vrp::VrpError::Embedder(format!("embedder initialization panicked: {}", e))
+        })??;
+    tracing::info!(kind = ?config.embedder.kind, "semantic embedder initialized");
 
     // Load or generate Signing Key.
     // Priority: (1) ANNEX_SIGNING_KEY env var, (2) persistent file on disk, (3) generate + persist.
@@ -600,6 +622,8 @@ pub async fn prepare_server(config: config::Config) -> Result<(TcpListener, Rout
         pool,
         merkle_tree: Arc::new(Mutex::new(tree)),
         membership_vkey: Arc::new(membership_vkey),
+        vote_vkey: Arc::new(vote_vkey),
+        role_vkey: Arc::new(role_vkey),
         server_id,
         signing_key: Arc::new(signing_key),
         public_url: Arc::new(RwLock::new(config.server.public_url.clone())),
@@ -614,9 +638,14 @@ pub async fn prepare_server(config: config::Config) -> Result<(TcpListener, Rout
         observe_tx,
         upload_dir,
         preview_cache: api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: api_sse::PresenceFanout::new(config.server.presence_broadcast_capacity),
+        captions: api_captions::CaptionHub::new(),
         ws_token_secret: Arc::new(ws_token_secret),
         cors_origins: config.cors.allowed_origins.clone(),
         enforce_zk_proofs: config.security.enforce_zk_proofs,
+        embedder,
     };
 
     // Start background pruning task
@@ -630,6 +659,34 @@ pub async fn prepare_server(config: config::Config) -> Result<(TcpListener, Rout
         }
     });
 
+    // Start graph history compaction task
+    let history_handle = tokio::spawn(background::start_graph_history_compaction_task(Arc::new(
+        state.clone(),
+    )));
+    tokio::spawn(async move {
+        if let Err(e) = history_handle.await {
+            tracing::error!("graph history background task panicked: {}", e);
+        }
+    });
+
+    // Start poll close task
+    let poll_handle = tokio::spawn(background::start_poll_close_task(Arc::new(state.clone())));
+    tokio::spawn(async move {
+        if let Err(e) = poll_handle.await {
+            tracing::error!("poll close background task panicked: {}", e);
+        }
+    });
+
+    // Start contract renewal task
+    let renewal_handle = tokio::spawn(background::start_contract_renewal_task(Arc::new(
+        state.clone(),
+    )));
+    tokio::spawn(async move {
+        if let Err(e) = renewal_handle.await {
+            tracing::error!("contract renewal background task panicked: {}", e);
+        }
+    });
+
     // Start rate limiter cleanup task
     tokio::spawn(background::start_rate_limit_cleanup_task(
         state.rate_limiter.clone(),
@@ -649,6 +706,31 @@ pub async fn prepare_server(config: config::Config) -> Result<(TcpListener, Rout
     Ok((listener, router))
 }
 
+/// Loads a snarkjs verification key from the path in `env_var` (or
+/// `default_path`), falling back to a dummy key if the file is missing.
+fn load_verification_key(
+    env_var: &str,
+    default_path: &str,
+) -> Result<VerifyingKey<Bn254>, StartupError> {
+    let vkey_path = std::env::var(env_var).unwrap_or_else(|_| default_path.to_string());
+    match std::fs::read_to_string(&vkey_path) {
+        Ok(vkey_json) => {
+            annex_identity::zk::parse_verification_key(&vkey_json).map_err(StartupError::ZkError)
+        }
+        Err(e) => {
+            tracing::warn!(
+                path = %vkey_path,
+                error = %e,
+                "ZK verification key not found — using dummy key. \
+                 Proofs against this key will fail until a real key is provided. \
+                 Run the ZK build (cd zk && npm ci && node scripts/build-circuits.js && \
+                 node scripts/setup-groth16.js) to generate one."
+            );
+            Ok(annex_identity::zk::generate_dummy_vkey())
+        }
+    }
+}
+
 /// Maximum request body size (2 MiB). Protects against OOM from oversized payloads.
 const MAX_REQUEST_BODY_BYTES: usize = 2 * 1024 * 1024;
 
@@ -735,6 +817,34 @@ pub fn app(state: AppState) -> Router {
             "/api/channels/{channelId}/messages/{messageId}/edits",
             get(api_channels::get_message_edits_handler),
         )
+        .route(
+            "/api/channels/{channelId}/polls",
+            post(api_polls::create_poll_handler).get(api_polls::list_polls_handler),
+        )
+        .route(
+            "/api/channels/{channelId}/polls/{pollId}",
+            get(api_polls::get_poll_handler),
+        )
+        .route(
+            "/api/channels/{channelId}/polls/{pollId}/close",
+            post(api_polls::close_poll_handler),
+        )
+        .route(
+            "/api/channels/{channelId}/polls/{pollId}/eligibility",
+            get(api_polls::get_poll_eligibility_handler),
+        )
+        .route(
+            "/api/channels/{channelId}/captions",
+            get(api_captions::get_captions_handler).put(api_captions::update_captions_handler),
+        )
+        .route(
+            "/api/channels/{channelId}/captions/consent",
+            put(api_captions::set_caption_consent_handler),
+        )
+        .route(
+            "/api/channels/{channelId}/captions/audio",
+            post(api_captions::caption_audio_handler),
+        )
         .route(
             "/api/agents/{pseudonymId}",
             get(api_agent::get_agent_profile_handler),
@@ -743,6 +853,14 @@ pub fn app(state: AppState) -> Router {
             "/api/agents/{pseudonymId}/voice-profile",
             put(api_agent::update_agent_voice_profile_handler),
         )
+        .route(
+            "/api/vrp/reputation/{peer}",
+            get(api_vrp::get_reputation_handler),
+        )
+        .route(
+            "/api/vrp/reputation/{peer}/reports",
+            post(api_vrp::report_peer_handler),
+        )
         .route("/api/rtx/publish", post(api_rtx::publish_handler))
         .route(
             "/api/rtx/subscribe",
@@ -752,6 +870,10 @@ pub fn app(state: AppState) -> Router {
             "/api/rtx/subscriptions",
             get(api_rtx::get_subscription_handler),
         )
+        .route(
+            "/api/rtx/bundles/{bundleId}/feedback",
+            post(api_rtx::bundle_feedback_handler),
+        )
         .route(
             "/api/rtx/governance/transfers",
             get(api_rtx::governance_transfers_handler),
@@ -764,6 +886,10 @@ pub fn app(state: AppState) -> Router {
             "/api/admin/policy",
             get(api_admin::get_policy_handler).put(api_admin::update_policy_handler),
         )
+        .route(
+            "/api/admin/policy/dry-run",
+            post(api_admin::policy_dry_run_handler),
+        )
         .route(
             "/api/admin/server",
             get(api_admin::get_server_handler).patch(api_admin::rename_server_handler),
@@ -781,6 +907,51 @@ pub fn app(state: AppState) -> Router {
             "/api/admin/members/{pseudonymId}/capabilities",
             patch(api_admin::update_member_capabilities_handler),
         )
+        .route(
+            "/api/admin/vrp/topics",
+            post(api_admin::create_topic_handler),
+        )
+        .route(
+            "/api/admin/vrp/topics/{topic}",
+            patch(api_admin::update_topic_handler).delete(api_admin::delete_topic_handler),
+        )
+        .route(
+            "/api/admin/vrp/topics/{topic}/deprecate",
+            post(api_admin::deprecate_topic_handler),
+        )
+        .route("/api/admin/vrp/roles", post(api_admin::create_role_handler))
+        .route(
+            "/api/admin/vrp/roles/{roleCode}",
+            patch(api_admin::update_role_handler).delete(api_admin::delete_role_handler),
+        )
+        .route(
+            "/api/admin/vrp/reputation/{peer}/incidents",
+            post(api_admin::flag_incident_handler),
+        )
+        .route(
+            "/api/vrp/negotiations",
+            post(api_vrp::open_agent_negotiation_handler),
+        )
+        .route(
+            "/api/vrp/negotiations/{negotiationId}",
+            post(api_vrp::agent_negotiation_step_handler),
+        )
+        .route(
+            "/api/admin/graph/export",
+            get(api_graph::export_graph_handler),
+        )
+        .route(
+            "/api/admin/graph/history/snapshot",
+            get(api_graph::graph_snapshot_handler),
+        )
+        .route(
+            "/api/admin/graph/history/changes",
+            get(api_graph::graph_changes_handler),
+        )
+        .route(
+            "/api/registry/export",
+            get(api_identity_bundle::export_identity_handler),
+        )
         .route(
             "/api/profile/username",
             put(api_usernames::set_username_handler).delete(api_usernames::delete_username_handler),
@@ -806,10 +977,55 @@ pub fn app(state: AppState) -> Router {
             get(api_link_preview::link_preview_handler),
         )
         .route("/api/ws/token", post(api_ws::create_ws_token_handler))
+        .route(
+            "/api/devices",
+            get(api_devices::list_devices_handler).post(api_devices::register_device_handler),
+        )
+        .route(
+            "/api/devices/{deviceId}",
+            delete(api_devices::revoke_device_handler),
+        )
+        .route(
+            "/api/devices/{deviceId}/ws-token",
+            post(api_devices::create_device_ws_token_handler),
+        )
         .route(
             "/api/graph/profile/{targetPseudonym}",
             get(api_graph::get_profile_handler),
         )
+        .route(
+            "/api/graph/connections/requests",
+            post(api_graph::create_connection_request_handler)
+                .get(api_graph::list_connection_requests_handler),
+        )
+        .route(
+            "/api/graph/connections/requests/{requestId}/accept",
+            post(api_graph::accept_connection_request_handler),
+        )
+        .route(
+            "/api/graph/connections/requests/{requestId}/decline",
+            post(api_graph::decline_connection_request_handler),
+        )
+        .route(
+            "/api/graph/connections/{pseudonymId}",
+            delete(api_graph::remove_connection_handler),
+        )
+        .route(
+            "/api/graph/visibility",
+            get(api_graph::get_visibility_preferences_handler)
+                .put(api_graph::set_visibility_preferences_handler),
+        )
+        .route("/api/graph/blocks", get(api_graph::list_blocks_handler))
+        .route(
+            "/api/graph/blocks/{pseudonymId}",
+            put(api_graph::block_handler).delete(api_graph::unblock_handler),
+        )
+        .route("/api/graph/status", get(api_graph::get_own_status_handler))
+        .route("/api/graph/mutes", get(api_graph::list_mutes_handler))
+        .route(
+            "/api/graph/mutes/{pseudonymId}",
+            put(api_graph::mute_handler).delete(api_graph::unmute_handler),
+        )
         .layer(axum::middleware::from_fn(middleware::auth_middleware));
 
     // Upload routes need a larger body limit for media uploads.
@@ -829,6 +1045,10 @@ pub fn app(state: AppState) -> Router {
     let router = Router::new()
         .route("/health", get(health))
         .route("/api/registry/register", post(api::register_handler))
+        .route(
+            "/api/registry/import",
+            post(api_identity_bundle::import_identity_handler),
+        )
         .route(
             "/api/registry/path/{commitmentHex}",
             get(api::get_path_handler),
@@ -841,6 +1061,8 @@ pub fn app(state: AppState) -> Router {
             "/api/zk/verify-membership",
             post(api::verify_membership_handler),
         )
+        .route("/api/zk/verify-role", post(api::verify_role_handler))
+        .route("/api/polls/{pollId}/vote", post(api_polls::vote_handler))
         .route("/api/registry/topics", get(api::get_topics_handler))
         .route("/api/registry/roles", get(api::get_roles_handler))
         .route(
@@ -855,10 +1077,31 @@ pub fn app(state: AppState) -> Router {
             "/api/vrp/agent-handshake",
             post(api_vrp::agent_handshake_handler),
         )
+        .route("/api/vrp/anchor", get(api_vrp::get_anchor_handler))
+        .route(
+            "/api/vrp/anchor/history",
+            get(api_vrp::get_anchor_history_handler),
+        )
+        .route(
+            "/api/vrp/anchor/diff",
+            get(api_vrp::get_anchor_diff_handler),
+        )
         .route(
             "/api/federation/handshake",
             post(api_federation::federation_handshake_handler),
         )
+        .route(
+            "/api/federation/renewal",
+            post(api_federation::renewal_request_handler),
+        )
+        .route(
+            "/api/federation/negotiations",
+            post(api_federation::open_federation_negotiation_handler),
+        )
+        .route(
+            "/api/federation/negotiations/{negotiationId}",
+            post(api_federation::federation_negotiation_step_handler),
+        )
         .route(
             "/api/federation/vrp-root",
             get(api_federation::get_vrp_root_handler),
@@ -899,6 +1142,10 @@ pub fn app(state: AppState) -> Router {
             get(api_observe::get_federation_peers_handler),
         )
         .route("/api/public/agents", get(api_observe::get_agents_handler))
+        .route(
+            "/api/public/graph/stats",
+            get(api_graph::get_graph_stats_handler),
+        )
         .route("/api/voice/config-status", get(voice_config_status))
         .route(
             "/api/public/server/image",
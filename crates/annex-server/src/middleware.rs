@@ -1,9 +1,10 @@
-use annex_identity::zk::{parse_fr_from_hex, parse_proof, verify_proof};
+use annex_identity::disclosure::{bind_role_nullifier, verify_role_disclosure, RoleDisclosure};
+use annex_identity::zk::{parse_fr_from_hex, parse_proof, parse_public_signals, verify_proof};
 use annex_identity::{get_platform_identity, PlatformIdentity};
 use axum::{
     body::Body,
     extract::ConnectInfo,
-    http::{header, Request, StatusCode},
+    http::{header, HeaderMap, Method, Request, StatusCode},
     middleware::Next,
     response::Response,
 };
@@ -19,6 +20,26 @@ use crate::AppState;
 #[derive(Clone, Debug)]
 pub struct IdentityContext(pub PlatformIdentity);
 
+/// Reads the caller's pseudonym from `X-Annex-Pseudonym` or
+/// `Authorization: Bearer`.
+///
+/// Returns `Ok(None)` when neither header is present and `UNAUTHORIZED` when
+/// one is present but malformed.
+pub(crate) fn pseudonym_from_headers(headers: &HeaderMap) -> Result<Option<String>, StatusCode> {
+    if let Some(val) = headers.get("X-Annex-Pseudonym") {
+        let pseudonym = val.to_str().map_err(|_| StatusCode::UNAUTHORIZED)?;
+        Ok(Some(pseudonym.to_string()))
+    } else if let Some(val) = headers.get("Authorization") {
+        let val_str = val.to_str().map_err(|_| StatusCode::UNAUTHORIZED)?;
+        match val_str.strip_prefix("Bearer ") {
+            Some(token) => Ok(Some(token.to_string())),
+            None => Err(StatusCode::UNAUTHORIZED),
+        }
+    } else {
+        Ok(None)
+    }
+}
+
 /// Middleware to authenticate requests via `X-Annex-Pseudonym` or `Authorization: Bearer`.
 ///
 /// # Security Note
@@ -31,20 +52,7 @@ pub struct IdentityContext(pub PlatformIdentity);
 /// For now, the "Bearer" token IS the pseudonym.
 pub async fn auth_middleware(mut req: Request<Body>, next: Next) -> Result<Response, StatusCode> {
     // 1. Extract pseudonym from header
-    let pseudonym = if let Some(val) = req.headers().get("X-Annex-Pseudonym") {
-        val.to_str()
-            .map_err(|_| StatusCode::UNAUTHORIZED)?
-            .to_string()
-    } else if let Some(val) = req.headers().get("Authorization") {
-        let val_str = val.to_str().map_err(|_| StatusCode::UNAUTHORIZED)?;
-        if let Some(token) = val_str.strip_prefix("Bearer ") {
-            token.to_string()
-        } else {
-            return Err(StatusCode::UNAUTHORIZED);
-        }
-    } else {
-        return Err(StatusCode::UNAUTHORIZED);
-    };
+    let pseudonym = pseudonym_from_headers(req.headers())?.ok_or(StatusCode::UNAUTHORIZED)?;
 
     // 2. Get AppState
     let state = req
@@ -85,6 +93,7 @@ pub async fn auth_middleware(mut req: Request<Body>, next: Next) -> Result<Respo
 pub enum RateLimitCategory {
     Registration,
     Verification,
+    ConnectionRequest,
     Default,
 }
 
@@ -381,16 +390,24 @@ pub async fn rate_limit_middleware(req: Request<Body>, next: Next) -> Result<Res
             }
         };
         let path = req.uri().path();
-        if path == "/api/registry/register" {
+        if path == "/api/registry/register" || path == "/api/registry/import" {
             (
                 RateLimitCategory::Registration,
                 policy.rate_limit.registration_limit,
             )
-        } else if path == "/api/zk/verify-membership" {
+        } else if path == "/api/zk/verify-membership"
+            || path == "/api/zk/verify-role"
+            || (path.starts_with("/api/polls/") && path.ends_with("/vote"))
+        {
             (
                 RateLimitCategory::Verification,
                 policy.rate_limit.verification_limit,
             )
+        } else if path == "/api/graph/connections/requests" && req.method() == Method::POST {
+            (
+                RateLimitCategory::ConnectionRequest,
+                policy.rate_limit.connection_request_limit,
+            )
         } else {
             (RateLimitCategory::Default, policy.rate_limit.default_limit)
         }
@@ -517,6 +534,117 @@ pub fn verify_zk_membership_header(
     Ok(())
 }
 
+/// JSON payload for a selective-disclosure role proof, submitted base64-encoded
+/// via the `x-annex-role-proof` header.
+#[derive(Debug, Clone, Deserialize, serde::Serialize)]
+pub struct RoleProofPayload {
+    /// The Groth16 proof JSON (snarkjs format) from `role.circom`.
+    pub proof: serde_json::Value,
+    /// Public signals: `[root, nullifier, allowedRoles[0..5], contextHash]`.
+    #[serde(rename = "publicSignals")]
+    pub public_signals: Vec<String>,
+}
+
+/// Checks a role disclosure proof against `required_roles` and `context_hash`.
+///
+/// Returns the proven root and nullifier; the caller decides which roots are
+/// acceptable and binds the nullifier to whoever presented the proof.
+/// Returns a human-readable reason on failure.
+pub(crate) fn check_role_proof(
+    state: &AppState,
+    proof: &serde_json::Value,
+    public_signals: &[String],
+    required_roles: &[annex_types::RoleCode],
+    context_hash: annex_identity::zk::Fr,
+) -> Result<RoleDisclosure, String> {
+    let proof_json = serde_json::to_string(proof).map_err(|e| e.to_string())?;
+    let proof = parse_proof(&proof_json).map_err(|e| format!("invalid proof format: {}", e))?;
+    let signals_json = serde_json::to_string(public_signals).map_err(|e| e.to_string())?;
+    let signals = parse_public_signals(&signals_json)
+        .map_err(|e| format!("invalid public signals format: {}", e))?;
+
+    verify_role_disclosure(
+        &state.role_vkey,
+        &proof,
+        &signals,
+        required_roles,
+        context_hash,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Returns `true` if `root_hex` is a Merkle root this server has published.
+pub(crate) fn is_local_root(conn: &rusqlite::Connection, root_hex: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM vrp_roots WHERE root_hex = ?1",
+        [root_hex],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+    .map_err(|e| format!("db query failed: {}", e))
+}
+
+/// Verifies a selective-disclosure role proof from the `x-annex-role-proof`
+/// header, proving the role of `pseudonym_id` is in `required_roles` without
+/// revealing their commitment. The proof's nullifier is bound to
+/// `pseudonym_id` on first use, so no other pseudonym can present a proof from
+/// the same identity in this context.
+///
+/// Unlike [`verify_zk_membership_header`], this is not gated on
+/// `enforce_zk_proofs`: a channel that declares required roles always
+/// demands the proof.
+///
+/// Returns `Err(StatusCode::FORBIDDEN)` if the header is missing or the proof
+/// does not verify for the given context.
+pub fn verify_role_proof_header(
+    conn: &rusqlite::Connection,
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    required_roles: &[annex_types::RoleCode],
+    context_hash: annex_identity::zk::Fr,
+    pseudonym_id: &str,
+) -> Result<(), StatusCode> {
+    let header_str = headers
+        .get("x-annex-role-proof")
+        .ok_or(StatusCode::FORBIDDEN)?
+        .to_str()
+        .map_err(|_| StatusCode::FORBIDDEN)?;
+
+    use base64::Engine;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(header_str)
+        .map_err(|_| StatusCode::FORBIDDEN)?;
+    let payload: RoleProofPayload =
+        serde_json::from_slice(&decoded).map_err(|_| StatusCode::FORBIDDEN)?;
+
+    let result = check_role_proof(
+        state,
+        &payload.proof,
+        &payload.public_signals,
+        required_roles,
+        context_hash,
+    )
+    .and_then(|disclosure| {
+        let root_hex = annex_identity::vote::field_to_hex(&disclosure.root);
+        if !is_local_root(conn, &root_hex)? {
+            return Err(format!("stale or invalid root: {}", root_hex));
+        }
+        bind_role_nullifier(
+            conn,
+            state.server_id,
+            context_hash,
+            disclosure.nullifier,
+            pseudonym_id,
+        )
+        .map_err(|e| e.to_string())
+    });
+
+    result.map_err(|reason| {
+        tracing::warn!(reason = %reason, "role disclosure proof rejected");
+        StatusCode::FORBIDDEN
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,32 +1,81 @@
 //! Policy management and re-evaluation logic.
 
 use crate::{api::ApiError, AppState};
+use annex_channels::list_channels;
 use annex_observe::EventPayload;
-use annex_types::PresenceEvent;
+use annex_types::{AlignmentStatus, PresenceEvent, ServerPolicy};
 use annex_vrp::{
-    validate_federation_handshake, ServerPolicyRoot, VrpAlignmentConfig, VrpAlignmentStatus,
-    VrpAnchorSnapshot, VrpCapabilitySharingContract, VrpFederationHandshake,
-    VrpTransferAcceptanceConfig,
+    validate_federation_handshake, SemanticEmbedder, ServerPolicyRoot, VrpAlignmentConfig,
+    VrpAlignmentStatus, VrpAnchorSnapshot, VrpCapabilitySharingContract, VrpFederationHandshake,
+    VrpTransferAcceptanceConfig, VrpTransferScope, VrpValidationReport,
 };
+use rusqlite::Connection;
+use serde::Serialize;
 use std::sync::Arc;
 
-/// Recalculates alignment for all active agents based on the current server policy.
-///
-/// This should be called whenever the server policy is updated.
-pub async fn recalculate_agent_alignments(state: Arc<AppState>) -> Result<(), ApiError> {
-    // 1. Get Server Policy (Read Lock)
-    let policy = state
-        .policy
-        .read()
-        .map_err(|_| ApiError::InternalServerError("policy lock poisoned".to_string()))?
-        .clone();
+/// An active agent whose alignment or transfer scope differs under a policy.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRealignment {
+    /// The agent's pseudonym.
+    pub pseudonym_id: String,
+    /// Alignment status currently on record.
+    pub previous_status: String,
+    /// Transfer scope currently on record.
+    pub previous_scope: String,
+    /// The handshake re-evaluated against the policy.
+    pub report: VrpValidationReport,
+}
+
+/// An active federation agreement whose alignment or transfer scope differs
+/// under a policy.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FederationRealignment {
+    /// The `federation_agreements` row.
+    pub agreement_id: i64,
+    /// The peer's base URL.
+    pub base_url: String,
+    /// The peer's `instances` row.
+    pub remote_instance_id: i64,
+    /// Alignment status currently on record.
+    pub previous_status: String,
+    /// Transfer scope currently on record.
+    pub previous_scope: String,
+    /// The peer's handshake re-evaluated against the policy.
+    pub report: VrpValidationReport,
+}
+
+/// A channel an agent can join today but could not after a realignment,
+/// because of the channel's `agent_min_alignment`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelLockout {
+    /// The channel that would lock the agent out.
+    pub channel_id: String,
+    /// The channel's minimum alignment.
+    pub agent_min_alignment: AlignmentStatus,
+    /// The agent that would be locked out.
+    pub pseudonym_id: String,
+    /// Whether the agent is currently a member of the channel.
+    pub is_member: bool,
+}
 
-    // 2. Prepare Validation Context
-    let local_root = ServerPolicyRoot::from_policy(&policy);
-    let local_anchor = local_root.to_anchor_snapshot().map_err(|e| {
-        ApiError::InternalServerError(format!("failed to create anchor snapshot: {}", e))
-    })?;
+/// What applying a policy would change. Produced by [`preview_policy_change`]
+/// without writing anything.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyImpact {
+    /// Agents whose alignment or transfer scope would change.
+    pub agents: Vec<AgentRealignment>,
+    /// Federation agreements whose alignment or transfer scope would change.
+    pub federation_agreements: Vec<FederationRealignment>,
+    /// Channels that would lock out realigned agents.
+    pub channel_lockouts: Vec<ChannelLockout>,
+}
 
+/// Capabilities the server offers to agents and federation peers under `policy`.
+fn local_offered_capabilities(policy: &ServerPolicy) -> Vec<String> {
     let mut offered_capabilities = Vec::new();
     if policy.voice_enabled {
         offered_capabilities.push("VOICE".to_string());
@@ -36,113 +85,335 @@ pub async fn recalculate_agent_alignments(state: Arc<AppState>) -> Result<(), Ap
     }
     offered_capabilities.push("TEXT".to_string());
     offered_capabilities.push("VRP".to_string());
+    offered_capabilities
+}
+
+fn local_anchor(policy: &ServerPolicy) -> Result<VrpAnchorSnapshot, ApiError> {
+    ServerPolicyRoot::from_policy(policy)
+        .to_anchor_snapshot()
+        .map_err(|e| {
+            ApiError::InternalServerError(format!("failed to create anchor snapshot: {}", e))
+        })
+}
 
+/// Re-evaluates every active agent's stored handshake against `policy` and
+/// returns the agents whose alignment or transfer scope would change.
+pub fn evaluate_agent_alignments(
+    conn: &Connection,
+    server_id: i64,
+    policy: &ServerPolicy,
+    embedder: &dyn SemanticEmbedder,
+) -> Result<Vec<AgentRealignment>, ApiError> {
+    let local_anchor = local_anchor(policy)?;
     let local_contract = VrpCapabilitySharingContract {
         required_capabilities: policy.agent_required_capabilities.clone(),
-        offered_capabilities,
-        redacted_topics: vec![],
+        offered_capabilities: local_offered_capabilities(policy),
+        redacted_topics: policy.agent_redacted_topics.clone(),
     };
-
     let alignment_config = VrpAlignmentConfig {
         semantic_alignment_required: true,
         min_alignment_score: policy.agent_min_alignment_score,
     };
-
     let transfer_config = VrpTransferAcceptanceConfig {
         allow_reflection_summaries: true,
         allow_full_knowledge: false,
     };
 
-    let state_clone = state.clone();
+    let mut stmt = conn
+        .prepare(
+            "SELECT pseudonym_id, alignment_status, transfer_scope, capability_contract_json, anchor_snapshot_json
+             FROM agent_registrations
+             WHERE active = 1 AND server_id = ?1",
+        )
+        .map_err(|e| ApiError::InternalServerError(format!("prepare failed: {}", e)))?;
+
+    let agent_iter = stmt
+        .query_map([server_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })
+        .map_err(|e| ApiError::InternalServerError(format!("query failed: {}", e)))?;
+
+    let mut changes = Vec::new();
+    for agent in agent_iter {
+        let (pseudonym, old_alignment_str, old_scope_str, contract_json, anchor_json) =
+            agent.map_err(|e| ApiError::InternalServerError(format!("row error: {}", e)))?;
+
+        let anchor_json = match anchor_json {
+            Some(json) => json,
+            None => {
+                tracing::warn!(
+                    "Agent {} has no anchor snapshot, skipping re-evaluation",
+                    pseudonym
+                );
+                continue;
+            }
+        };
 
-    // 3. Process Agents in Background (wrapped in a transaction for atomicity)
-    let agents_to_disconnect = tokio::task::spawn_blocking(move || {
-        let mut conn = state_clone
-            .pool
-            .get()
-            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+        let anchor: VrpAnchorSnapshot = serde_json::from_str(&anchor_json)
+            .map_err(|_| ApiError::InternalServerError("failed to parse anchor".to_string()))?;
 
-        let tx = conn.transaction().map_err(|e| {
-            ApiError::InternalServerError(format!("failed to begin transaction: {}", e))
-        })?;
+        let contract: VrpCapabilitySharingContract = serde_json::from_str(&contract_json)
+            .map_err(|_| ApiError::InternalServerError("failed to parse contract".to_string()))?;
 
-        let (agents_to_update, agents_to_disconnect) = {
-            let mut stmt = tx
-                .prepare(
-                    "SELECT pseudonym_id, alignment_status, transfer_scope, capability_contract_json, anchor_snapshot_json
-                     FROM agent_registrations
-                     WHERE active = 1 AND server_id = ?1"
-                )
-                .map_err(|e| ApiError::InternalServerError(format!("prepare failed: {}", e)))?;
-
-            let agent_iter = stmt
-                .query_map([state_clone.server_id], |row| {
-                    Ok((
-                        row.get::<_, String>(0)?,
-                        row.get::<_, String>(1)?,
-                        row.get::<_, String>(2)?,
-                        row.get::<_, String>(3)?,
-                        row.get::<_, Option<String>>(4)?,
-                    ))
-                })
-                .map_err(|e| ApiError::InternalServerError(format!("query failed: {}", e)))?;
-
-            let mut updates = Vec::new();
-            let mut disconnects = Vec::new();
-
-            for agent in agent_iter {
-                let (pseudonym, old_alignment_str, old_scope_str, contract_json, anchor_json) =
-                    agent.map_err(|e| ApiError::InternalServerError(format!("row error: {}", e)))?;
-
-                let anchor_json = match anchor_json {
-                    Some(json) => json,
-                    None => {
-                        tracing::warn!("Agent {} has no anchor snapshot, skipping re-evaluation", pseudonym);
-                        continue;
-                    }
-                };
+        let handshake = VrpFederationHandshake {
+            anchor_snapshot: anchor,
+            capability_contract: contract,
+        };
 
-                let anchor: VrpAnchorSnapshot = serde_json::from_str(&anchor_json).map_err(|_| {
-                    ApiError::InternalServerError("failed to parse anchor".to_string())
-                })?;
+        let report = match validate_federation_handshake(
+            &local_anchor,
+            &local_contract,
+            &handshake,
+            &alignment_config,
+            &transfer_config,
+            embedder,
+        ) {
+            Ok(report) => report,
+            Err(e) => {
+                tracing::warn!(
+                    "Could not re-evaluate agent {}, keeping its contract: {}",
+                    pseudonym,
+                    e
+                );
+                continue;
+            }
+        };
 
-                let contract: VrpCapabilitySharingContract = serde_json::from_str(&contract_json)
-                    .map_err(|_| {
-                        ApiError::InternalServerError("failed to parse contract".to_string())
-                    })?;
+        if report.alignment_status.to_string() != old_alignment_str
+            || report.transfer_scope.to_string() != old_scope_str
+        {
+            changes.push(AgentRealignment {
+                pseudonym_id: pseudonym,
+                previous_status: old_alignment_str,
+                previous_scope: old_scope_str,
+                report,
+            });
+        }
+    }
+    Ok(changes)
+}
 
-                let handshake = VrpFederationHandshake {
-                    anchor_snapshot: anchor,
-                    capability_contract: contract,
-                };
+/// Re-evaluates every active federation agreement's stored handshake against
+/// `policy` and returns the agreements whose alignment or transfer scope
+/// would change.
+pub fn evaluate_federation_agreements(
+    conn: &Connection,
+    policy: &ServerPolicy,
+    embedder: &dyn SemanticEmbedder,
+) -> Result<Vec<FederationRealignment>, ApiError> {
+    let local_anchor = local_anchor(policy)?;
+    let local_contract = VrpCapabilitySharingContract {
+        required_capabilities: policy.agent_required_capabilities.clone(),
+        offered_capabilities: local_offered_capabilities(policy),
+        redacted_topics: policy.federation_redacted_topics.clone(),
+    };
+    let alignment_config = VrpAlignmentConfig {
+        semantic_alignment_required: true,
+        min_alignment_score: policy.agent_min_alignment_score,
+    };
+    let transfer_config = VrpTransferAcceptanceConfig {
+        allow_reflection_summaries: policy.federation_enabled,
+        allow_full_knowledge: false,
+    };
 
-                let report = validate_federation_handshake(
-                    &local_anchor,
-                    &local_contract,
-                    &handshake,
-                    &alignment_config,
-                    &transfer_config,
+    let mut stmt = conn
+        .prepare(
+            "SELECT fa.id, i.base_url, fa.alignment_status, fa.transfer_scope, fa.remote_handshake_json, fa.remote_instance_id
+             FROM federation_agreements fa
+             JOIN instances i ON fa.remote_instance_id = i.id
+             WHERE fa.active = 1",
+        )
+        .map_err(|e| ApiError::InternalServerError(format!("prepare failed: {}", e)))?;
+
+    let iter = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })
+        .map_err(|e| ApiError::InternalServerError(format!("query failed: {}", e)))?;
+
+    let mut changes = Vec::new();
+    for row in iter {
+        let (id, base_url, old_alignment_str, old_scope_str, handshake_json, remote_instance_id) =
+            row.map_err(|e| ApiError::InternalServerError(format!("row error: {}", e)))?;
+
+        let handshake_json = match handshake_json {
+            Some(json) => json,
+            None => {
+                tracing::warn!(
+                    "Federation agreement {} ({}) has no handshake data, skipping re-evaluation",
+                    id,
+                    base_url
                 );
+                continue;
+            }
+        };
 
-                let new_alignment_str = report.alignment_status.to_string();
-                let new_scope_str = report.transfer_scope.to_string();
-
-                if new_alignment_str != old_alignment_str || new_scope_str != old_scope_str {
-                    if report.alignment_status == VrpAlignmentStatus::Conflict {
-                        disconnects.push(pseudonym.clone());
-                        updates.push((pseudonym, old_alignment_str, report, false)); // active = false
-                    } else {
-                        updates.push((pseudonym, old_alignment_str, report, true)); // active = true
-                    }
-                }
+        let handshake: VrpFederationHandshake = serde_json::from_str(&handshake_json)
+            .map_err(|_| ApiError::InternalServerError("failed to parse handshake".to_string()))?;
+
+        let report = match validate_federation_handshake(
+            &local_anchor,
+            &local_contract,
+            &handshake,
+            &alignment_config,
+            &transfer_config,
+            embedder,
+        ) {
+            Ok(report) => report,
+            Err(e) => {
+                tracing::warn!(
+                    "Could not re-evaluate federation agreement {} ({}), keeping it: {}",
+                    id,
+                    base_url,
+                    e
+                );
+                continue;
             }
-            (updates, disconnects)
         };
 
+        if report.alignment_status.to_string() != old_alignment_str
+            || report.transfer_scope.to_string() != old_scope_str
+        {
+            changes.push(FederationRealignment {
+                agreement_id: id,
+                base_url,
+                remote_instance_id,
+                previous_status: old_alignment_str,
+                previous_scope: old_scope_str,
+                report,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+fn parse_alignment_status(status: &str) -> Option<AlignmentStatus> {
+    serde_json::from_str(&format!("\"{}\"", status)).ok()
+}
+
+/// Finds channels whose `agent_min_alignment` the realigned agents meet today
+/// but would no longer meet.
+fn channel_lockouts(
+    conn: &Connection,
+    server_id: i64,
+    agents: &[AgentRealignment],
+) -> Result<Vec<ChannelLockout>, ApiError> {
+    let channels = list_channels(conn, server_id)
+        .map_err(|e| ApiError::InternalServerError(format!("failed to list channels: {}", e)))?;
+
+    let mut lockouts = Vec::new();
+    for agent in agents {
+        let before = parse_alignment_status(&agent.previous_status);
+        let after = parse_alignment_status(&agent.report.alignment_status.to_string());
+        let Some(after) = after else { continue };
+
+        for channel in &channels {
+            let Some(min_alignment) = channel.agent_min_alignment else {
+                continue;
+            };
+            let allowed_before =
+                before.is_some_and(|b| crate::api_channels::meets_min_alignment(b, min_alignment));
+            if !allowed_before || crate::api_channels::meets_min_alignment(after, min_alignment) {
+                continue;
+            }
+            let is_member = annex_channels::is_member(
+                conn,
+                server_id,
+                &channel.channel_id,
+                &agent.pseudonym_id,
+            )
+            .map_err(|e| {
+                ApiError::InternalServerError(format!("failed to check membership: {}", e))
+            })?;
+            lockouts.push(ChannelLockout {
+                channel_id: channel.channel_id.clone(),
+                agent_min_alignment: min_alignment,
+                pseudonym_id: agent.pseudonym_id.clone(),
+                is_member,
+            });
+        }
+    }
+    Ok(lockouts)
+}
+
+/// Previews what applying `policy` would change, without persisting anything.
+pub fn preview_policy_change(
+    conn: &Connection,
+    server_id: i64,
+    policy: &ServerPolicy,
+    embedder: &dyn SemanticEmbedder,
+) -> Result<PolicyImpact, ApiError> {
+    let agents = evaluate_agent_alignments(conn, server_id, policy, embedder)?;
+    let federation_agreements = evaluate_federation_agreements(conn, policy, embedder)?;
+    let channel_lockouts = channel_lockouts(conn, server_id, &agents)?;
+    Ok(PolicyImpact {
+        agents,
+        federation_agreements,
+        channel_lockouts,
+    })
+}
+
+/// Recalculates alignment for all active agents based on the current server policy.
+///
+/// This should be called whenever the server policy is updated.
+pub async fn recalculate_agent_alignments(state: Arc<AppState>) -> Result<(), ApiError> {
+    // 1. Get Server Policy (Read Lock)
+    let policy = state
+        .policy
+        .read()
+        .map_err(|_| ApiError::InternalServerError("policy lock poisoned".to_string()))?
+        .clone();
+
+    let state_clone = state.clone();
+
+    // 2. Process Agents in Background (wrapped in a transaction for atomicity)
+    let agents_to_disconnect = tokio::task::spawn_blocking(move || {
+        let mut conn = state_clone
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+
+        // Embedding may call out to an external service, so evaluate before
+        // taking the write transaction.
+        let changes = evaluate_agent_alignments(
+            &conn,
+            state_clone.server_id,
+            &policy,
+            state_clone.embedder.as_ref(),
+        )?;
+
+        let tx = conn.transaction().map_err(|e| {
+            ApiError::InternalServerError(format!("failed to begin transaction: {}", e))
+        })?;
+
         // Apply updates within the transaction
-        for (pseudonym, previous_status, report, active) in agents_to_update {
-             let active_int = if active { 1 } else { 0 };
-             tx.execute(
+        let mut agents_to_disconnect = Vec::new();
+        for change in changes {
+            let AgentRealignment {
+                pseudonym_id: pseudonym,
+                previous_status,
+                report,
+                ..
+            } = change;
+            let active = report.alignment_status != VrpAlignmentStatus::Conflict;
+            if !active {
+                agents_to_disconnect.push(pseudonym.clone());
+            }
+            let active_int = if active { 1 } else { 0 };
+            tx.execute(
                 "UPDATE agent_registrations SET
                     alignment_status = ?1,
                     transfer_scope = ?2,
@@ -156,10 +427,11 @@ pub async fn recalculate_agent_alignments(state: Arc<AppState>) -> Result<(), Ap
                     state_clone.server_id,
                     pseudonym
                 ],
-            ).map_err(|e| ApiError::InternalServerError(format!("update failed: {}", e)))?;
+            )
+            .map_err(|e| ApiError::InternalServerError(format!("update failed: {}", e)))?;
 
             // Emit presence event (SSE)
-             let _ = state_clone.presence_tx.send(PresenceEvent::NodeUpdated {
+            let _ = state_clone.presence_tx.send(PresenceEvent::NodeUpdated {
                 pseudonym_id: pseudonym.clone(),
                 active,
             });
@@ -170,6 +442,7 @@ pub async fn recalculate_agent_alignments(state: Arc<AppState>) -> Result<(), Ap
                     pseudonym_id: pseudonym.clone(),
                     alignment_status: report.alignment_status.to_string(),
                     previous_status,
+                    transfer_scope: Some(report.transfer_scope.to_string()),
                 };
                 crate::emit_and_broadcast(
                     &tx,
@@ -219,118 +492,35 @@ pub async fn recalculate_federation_agreements(state: Arc<AppState>) -> Result<(
         .map_err(|_| ApiError::InternalServerError("policy lock poisoned".to_string()))?
         .clone();
 
-    // 2. Prepare Validation Context
-    let local_root = ServerPolicyRoot::from_policy(&policy);
-    let local_anchor = local_root.to_anchor_snapshot().map_err(|e| {
-        ApiError::InternalServerError(format!("failed to create anchor snapshot: {}", e))
-    })?;
-
-    let mut offered_capabilities = Vec::new();
-    if policy.voice_enabled {
-        offered_capabilities.push("VOICE".to_string());
-    }
-    if policy.federation_enabled {
-        offered_capabilities.push("FEDERATION".to_string());
-    }
-    offered_capabilities.push("TEXT".to_string());
-    offered_capabilities.push("VRP".to_string());
-
-    let local_contract = VrpCapabilitySharingContract {
-        required_capabilities: policy.agent_required_capabilities.clone(),
-        offered_capabilities,
-        redacted_topics: vec![],
-    };
-
-    let alignment_config = VrpAlignmentConfig {
-        semantic_alignment_required: true,
-        min_alignment_score: policy.agent_min_alignment_score,
-    };
-
-    let transfer_config = VrpTransferAcceptanceConfig {
-        allow_reflection_summaries: policy.federation_enabled,
-        allow_full_knowledge: false,
-    };
-
     let state_clone = state.clone();
 
-    // 3. Process in Background (wrapped in a transaction for atomicity)
+    // 2. Process in Background (wrapped in a transaction for atomicity)
     let affected_peers = tokio::task::spawn_blocking(move || {
         let mut conn = state_clone
             .pool
             .get()
             .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
 
+        // Embedding may call out to an external service, so evaluate before
+        // taking the write transaction.
+        let updates =
+            evaluate_federation_agreements(&conn, &policy, state_clone.embedder.as_ref())?;
+
         let tx = conn.transaction().map_err(|e| {
             ApiError::InternalServerError(format!("failed to begin transaction: {}", e))
         })?;
 
-        let updates = {
-            let mut stmt = tx
-                .prepare(
-                    "SELECT fa.id, i.base_url, fa.alignment_status, fa.transfer_scope, fa.remote_handshake_json, fa.remote_instance_id
-                     FROM federation_agreements fa
-                     JOIN instances i ON fa.remote_instance_id = i.id
-                     WHERE fa.active = 1",
-                )
-                .map_err(|e| ApiError::InternalServerError(format!("prepare failed: {}", e)))?;
-
-            let iter = stmt
-                .query_map([], |row| {
-                    Ok((
-                        row.get::<_, i64>(0)?,
-                        row.get::<_, String>(1)?,
-                        row.get::<_, String>(2)?,
-                        row.get::<_, String>(3)?,
-                        row.get::<_, Option<String>>(4)?,
-                        row.get::<_, i64>(5)?,
-                    ))
-                })
-                .map_err(|e| ApiError::InternalServerError(format!("query failed: {}", e)))?;
-
-            let mut updates = Vec::new();
-
-            for row in iter {
-                let (id, base_url, old_alignment_str, old_scope_str, handshake_json, remote_instance_id) =
-                    row.map_err(|e| ApiError::InternalServerError(format!("row error: {}", e)))?;
-
-                let handshake_json = match handshake_json {
-                    Some(json) => json,
-                    None => {
-                        tracing::warn!(
-                            "Federation agreement {} ({}) has no handshake data, skipping re-evaluation",
-                            id,
-                            base_url
-                        );
-                        continue;
-                    }
-                };
-
-                let handshake: VrpFederationHandshake = serde_json::from_str(&handshake_json)
-                    .map_err(|_| {
-                        ApiError::InternalServerError("failed to parse handshake".to_string())
-                    })?;
-
-                let report = validate_federation_handshake(
-                    &local_anchor,
-                    &local_contract,
-                    &handshake,
-                    &alignment_config,
-                    &transfer_config,
-                );
-
-                let new_alignment_str = report.alignment_status.to_string();
-                let new_scope_str = report.transfer_scope.to_string();
-
-                if new_alignment_str != old_alignment_str || new_scope_str != old_scope_str {
-                    updates.push((id, base_url, old_alignment_str, report, remote_instance_id));
-                }
-            }
-            updates
-        };
-
         // Apply updates within the transaction
         let mut affected_peers: Vec<(String, i64)> = Vec::new();
-        for (id, base_url, previous_status, report, remote_instance_id) in updates {
+        for change in updates {
+            let FederationRealignment {
+                agreement_id: id,
+                base_url,
+                remote_instance_id,
+                previous_status,
+                report,
+                ..
+            } = change;
             let active_int = if report.alignment_status == VrpAlignmentStatus::Conflict {
                 0
             } else {
@@ -366,9 +556,11 @@ pub async fn recalculate_federation_agreements(state: Arc<AppState>) -> Result<(
                     "Federation severed with {} due to policy conflict",
                     base_url
                 );
-                let _ = state_clone.presence_tx.send(PresenceEvent::FederationSevered {
-                    remote_base_url: base_url.clone(),
-                });
+                let _ = state_clone
+                    .presence_tx
+                    .send(PresenceEvent::FederationSevered {
+                        remote_base_url: base_url.clone(),
+                    });
 
                 let observe_payload = EventPayload::FederationSevered {
                     remote_url: base_url.clone(),
@@ -405,6 +597,7 @@ pub async fn recalculate_federation_agreements(state: Arc<AppState>) -> Result<(
                     remote_url: base_url.clone(),
                     alignment_status: report.alignment_status.to_string(),
                     previous_status,
+                    transfer_scope: Some(report.transfer_scope.to_string()),
                 };
                 crate::emit_and_broadcast(
                     &tx,
@@ -441,13 +634,21 @@ pub async fn recalculate_federation_agreements(state: Arc<AppState>) -> Result<(
 
 /// Notifies federation peers of a policy change by initiating outbound re-handshakes.
 ///
-/// For each affected peer, constructs a new VRP handshake from the current server
-/// policy and POSTs it to the peer's `/api/federation/handshake` endpoint.
 /// Failures are logged but do not propagate -- this is a best-effort notification.
 pub async fn notify_federation_peers_of_policy_change(
     state: Arc<AppState>,
     peers: Vec<(String, i64)>,
 ) {
+    send_federation_rehandshakes(state, peers).await;
+}
+
+/// Initiates outbound VRP re-handshakes with the given peers.
+///
+/// For each peer, constructs a new VRP handshake from the current server
+/// policy, with the anchor signed by the server's key, and POSTs it to the
+/// peer's `/api/federation/handshake` endpoint. Each request runs in its own
+/// task; failures are logged.
+pub async fn send_federation_rehandshakes(state: Arc<AppState>, peers: Vec<(String, i64)>) {
     // Build the local handshake from the current policy
     let (local_handshake, public_url) = {
         let policy = match state.policy.read() {
@@ -458,8 +659,8 @@ pub async fn notify_federation_peers_of_policy_change(
             }
         };
 
-        let local_root = ServerPolicyRoot::from_policy(&policy);
-        let local_anchor = match local_root.to_anchor_snapshot() {
+        // Signed so peers can tell the realignment really came from us.
+        let local_anchor = match crate::api_vrp::signed_local_anchor(&state.signing_key, &policy) {
             Ok(a) => a,
             Err(e) => {
                 tracing::error!("failed to create anchor snapshot for re-handshake: {}", e);
@@ -480,7 +681,7 @@ pub async fn notify_federation_peers_of_policy_change(
         let local_contract = VrpCapabilitySharingContract {
             required_capabilities: policy.agent_required_capabilities.clone(),
             offered_capabilities,
-            redacted_topics: vec![],
+            redacted_topics: policy.federation_redacted_topics.clone(),
         };
 
         let handshake = VrpFederationHandshake {
@@ -517,7 +718,7 @@ pub async fn notify_federation_peers_of_policy_change(
                     tracing::info!(
                         peer = %base_url,
                         remote_instance_id = remote_instance_id,
-                        "policy re-handshake succeeded"
+                        "federation re-handshake succeeded"
                     );
                 }
                 Ok(resp) => {
@@ -525,14 +726,14 @@ pub async fn notify_federation_peers_of_policy_change(
                         peer = %base_url,
                         remote_instance_id = remote_instance_id,
                         status = %resp.status(),
-                        "policy re-handshake received non-success response"
+                        "federation re-handshake received non-success response"
                     );
                 }
                 Err(e) => {
                     tracing::warn!(
                         peer = %base_url,
                         remote_instance_id = remote_instance_id,
-                        "failed to send policy re-handshake: {}", e
+                        "failed to send federation re-handshake: {}", e
                     );
                 }
             }
@@ -546,3 +747,312 @@ pub async fn recalculate_all_alignments(state: Arc<AppState>) -> Result<(), ApiE
     recalculate_federation_agreements(state).await?;
     Ok(())
 }
+
+/// Contracts the renewal sweep acted on, for the async follow-up.
+#[derive(Default)]
+struct ContractSweep {
+    /// Agents to ask for a fresh handshake, with their contract expiry.
+    agent_renewals: Vec<(String, String)>,
+    /// Peers to ask for a fresh handshake.
+    peer_renewals: Vec<String>,
+}
+
+/// Downgrades agent contracts that have lapsed and marks those about to
+/// lapse for renewal.
+fn sweep_agent_contracts(
+    tx: &Connection,
+    state: &AppState,
+    grace: &str,
+    sweep: &mut ContractSweep,
+) -> Result<(), ApiError> {
+    let db_err = |e: rusqlite::Error| ApiError::InternalServerError(e.to_string());
+
+    let expired = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT pseudonym_id, alignment_status, transfer_scope
+                 FROM agent_registrations
+                 WHERE server_id = ?1 AND active = 1
+                   AND expires_at IS NOT NULL AND expires_at <= datetime('now')",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map([state.server_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)?
+    };
+
+    for (pseudonym, alignment_status, scope) in expired {
+        let scope = scope
+            .parse::<VrpTransferScope>()
+            .unwrap_or(VrpTransferScope::NoTransfer)
+            .downgraded();
+        tx.execute(
+            "UPDATE agent_registrations SET
+                transfer_scope = ?1,
+                expires_at = CASE WHEN ?2 THEN NULL ELSE datetime('now', ?3) END,
+                renewal_requested_at = NULL,
+                updated_at = datetime('now')
+             WHERE server_id = ?4 AND pseudonym_id = ?5",
+            rusqlite::params![
+                scope.to_string(),
+                scope == VrpTransferScope::NoTransfer,
+                grace,
+                state.server_id,
+                pseudonym
+            ],
+        )
+        .map_err(db_err)?;
+
+        tracing::info!(pseudonym = %pseudonym, scope = %scope, "agent contract expired");
+        let observe_payload = EventPayload::AgentRealigned {
+            pseudonym_id: pseudonym.clone(),
+            alignment_status: alignment_status.clone(),
+            previous_status: alignment_status,
+            transfer_scope: Some(scope.to_string()),
+        };
+        crate::emit_and_broadcast(
+            tx,
+            state.server_id,
+            &pseudonym,
+            &observe_payload,
+            &state.observe_tx,
+        );
+    }
+
+    let due = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT pseudonym_id, expires_at
+                 FROM agent_registrations
+                 WHERE server_id = ?1 AND active = 1 AND renewal_requested_at IS NULL
+                   AND expires_at IS NOT NULL AND expires_at <= datetime('now', ?2)",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map(rusqlite::params![state.server_id, grace], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)?
+    };
+
+    for (pseudonym, expires_at) in due {
+        tx.execute(
+            "UPDATE agent_registrations SET renewal_requested_at = datetime('now')
+             WHERE server_id = ?1 AND pseudonym_id = ?2",
+            rusqlite::params![state.server_id, pseudonym],
+        )
+        .map_err(db_err)?;
+        sweep.agent_renewals.push((pseudonym, expires_at));
+    }
+
+    Ok(())
+}
+
+/// Downgrades federation agreements that have lapsed and marks those about
+/// to lapse for renewal.
+fn sweep_federation_contracts(
+    tx: &Connection,
+    state: &AppState,
+    grace: &str,
+    sweep: &mut ContractSweep,
+) -> Result<(), ApiError> {
+    let db_err = |e: rusqlite::Error| ApiError::InternalServerError(e.to_string());
+
+    let expired = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT fa.id, i.base_url, fa.alignment_status, fa.agreement_json
+                 FROM federation_agreements fa
+                 JOIN instances i ON fa.remote_instance_id = i.id
+                 WHERE fa.local_server_id = ?1 AND fa.active = 1
+                   AND fa.expires_at IS NOT NULL AND fa.expires_at <= datetime('now')",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map([state.server_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)?
+    };
+
+    for (id, base_url, alignment_status, agreement_json) in expired {
+        let mut report: VrpValidationReport =
+            serde_json::from_str(&agreement_json).map_err(|e| {
+                ApiError::InternalServerError(format!("failed to parse agreement: {}", e))
+            })?;
+        report.transfer_scope = report.transfer_scope.downgraded();
+        report.negotiation_notes.push(format!(
+            "Contract expired without renewal; transfer scope downgraded to {}",
+            report.transfer_scope
+        ));
+        let report_json = serde_json::to_string(&report).map_err(|e| {
+            ApiError::InternalServerError(format!("failed to serialize report: {}", e))
+        })?;
+
+        tx.execute(
+            "UPDATE federation_agreements SET
+                transfer_scope = ?1,
+                agreement_json = ?2,
+                expires_at = CASE WHEN ?3 THEN NULL ELSE datetime('now', ?4) END,
+                renewal_requested_at = NULL,
+                updated_at = datetime('now')
+             WHERE id = ?5",
+            rusqlite::params![
+                report.transfer_scope.to_string(),
+                report_json,
+                report.transfer_scope == VrpTransferScope::NoTransfer,
+                grace,
+                id
+            ],
+        )
+        .map_err(db_err)?;
+
+        tracing::info!(peer = %base_url, scope = %report.transfer_scope, "federation contract expired");
+        if let Some(status) = parse_alignment_status(&alignment_status) {
+            let _ = state.presence_tx.send(PresenceEvent::FederationRealigned {
+                remote_base_url: base_url.clone(),
+                alignment_status: status,
+            });
+        }
+        let observe_payload = EventPayload::FederationRealigned {
+            remote_url: base_url.clone(),
+            alignment_status: alignment_status.clone(),
+            previous_status: alignment_status,
+            transfer_scope: Some(report.transfer_scope.to_string()),
+        };
+        crate::emit_and_broadcast(
+            tx,
+            state.server_id,
+            &base_url,
+            &observe_payload,
+            &state.observe_tx,
+        );
+    }
+
+    let due = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT fa.id, i.base_url
+                 FROM federation_agreements fa
+                 JOIN instances i ON fa.remote_instance_id = i.id
+                 WHERE fa.local_server_id = ?1 AND fa.active = 1
+                   AND fa.renewal_requested_at IS NULL
+                   AND fa.expires_at IS NOT NULL AND fa.expires_at <= datetime('now', ?2)",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map(rusqlite::params![state.server_id, grace], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)?
+    };
+
+    for (id, base_url) in due {
+        tx.execute(
+            "UPDATE federation_agreements SET renewal_requested_at = datetime('now')
+             WHERE id = ?1",
+            [id],
+        )
+        .map_err(db_err)?;
+        sweep.peer_renewals.push(base_url);
+    }
+
+    Ok(())
+}
+
+/// Renews capability contracts that are about to expire and downgrades those
+/// that have.
+///
+/// Contracts whose `expires_at` falls inside the policy's renewal window are
+/// marked and the counterparty is asked to re-handshake: agents over their
+/// WebSocket, peers via `POST /api/federation/renewal`. A contract that
+/// lapses without renewal has its transfer scope narrowed one step and gets
+/// another renewal window, until it reaches `NO_TRANSFER`. Each downgrade
+/// emits `AGENT_REALIGNED` or `FEDERATION_REALIGNED` with the new scope.
+pub async fn renew_expiring_contracts(state: Arc<AppState>) -> Result<(), ApiError> {
+    let window = state
+        .policy
+        .read()
+        .map_err(|_| ApiError::InternalServerError("policy lock poisoned".to_string()))?
+        .contract_renewal_window_secs;
+    let grace = format!("+{} seconds", window);
+
+    let state_clone = state.clone();
+    let sweep = tokio::task::spawn_blocking(move || {
+        let mut conn = state_clone
+            .pool
+            .get()
+            .map_err(|e| ApiError::InternalServerError(format!("db connection failed: {}", e)))?;
+
+        let tx = conn.transaction().map_err(|e| {
+            ApiError::InternalServerError(format!("failed to begin transaction: {}", e))
+        })?;
+
+        let mut sweep = ContractSweep::default();
+        sweep_agent_contracts(&tx, &state_clone, &grace, &mut sweep)?;
+        sweep_federation_contracts(&tx, &state_clone, &grace, &mut sweep)?;
+
+        tx.commit().map_err(|e| {
+            ApiError::InternalServerError(format!("failed to commit transaction: {}", e))
+        })?;
+
+        Ok::<_, ApiError>(sweep)
+    })
+    .await
+    .map_err(|e| ApiError::InternalServerError(format!("task join error: {}", e)))??;
+
+    for (pseudonym, expires_at) in sweep.agent_renewals {
+        let message = crate::api_ws::OutgoingMessage::RenewalRequested { expires_at };
+        if let Ok(json) = serde_json::to_string(&message) {
+            state.connection_manager.send(&pseudonym, json).await;
+        }
+    }
+
+    if !sweep.peer_renewals.is_empty() {
+        let client = crate::api_federation::federation_http_client().map_err(|e| {
+            ApiError::InternalServerError(format!("failed to build federation client: {}", e))
+        })?;
+        for base_url in sweep.peer_renewals {
+            let client = client.clone();
+            let payload = crate::api_federation::signed_renewal_request(&state, &base_url);
+            tokio::spawn(async move {
+                let url = format!("{}/api/federation/renewal", base_url);
+                match client.post(&url).json(&payload).send().await {
+                    Ok(resp) if resp.status().is_success() => {
+                        tracing::info!(peer = %base_url, "requested federation contract renewal");
+                    }
+                    Ok(resp) => {
+                        tracing::warn!(
+                            peer = %base_url,
+                            status = %resp.status(),
+                            "federation renewal request received non-success response"
+                        );
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            peer = %base_url,
+                            "failed to send federation renewal request: {}", e
+                        );
+                    }
+                }
+            });
+        }
+    }
+
+    Ok(())
+}
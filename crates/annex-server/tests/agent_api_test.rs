@@ -39,6 +39,8 @@ async fn test_get_agent_profile() {
         pool: pool.clone(),
         merkle_tree: Arc::new(Mutex::new(annex_identity::MerkleTree::new(20).unwrap())),
         membership_vkey: Arc::new(vk),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
         server_id: 1,
         signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
             &mut rand::rngs::OsRng,
@@ -59,8 +61,13 @@ async fn test_get_agent_profile() {
         observe_tx: tokio::sync::broadcast::channel(256).0,
         upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
         preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
         cors_origins: vec![],
         enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
         ws_token_secret: std::sync::Arc::new([0u8; 32]),
     };
 
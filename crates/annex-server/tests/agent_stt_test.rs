@@ -46,6 +46,8 @@ async fn setup_app_with_mock_stt(
         pool: pool.clone(),
         merkle_tree: Arc::new(Mutex::new(tree)),
         membership_vkey: load_vkey(),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
         server_id: 1,
         signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
             &mut rand::rngs::OsRng,
@@ -64,8 +66,13 @@ async fn setup_app_with_mock_stt(
         observe_tx: tokio::sync::broadcast::channel(256).0,
         upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
         preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
         cors_origins: vec![],
         enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
         ws_token_secret: std::sync::Arc::new([0u8; 32]),
     };
 
@@ -119,6 +126,7 @@ async fn test_agent_stt_pipeline() {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None,
             retention_days: None,
             federation_scope: FederationScope::Local,
@@ -196,6 +204,9 @@ async fn test_agent_stt_pipeline() {
                 "human-speaker"
             );
             assert_eq!(v.get("text").unwrap().as_str().unwrap(), "Transcribed text");
+            assert!(v.get("isFinal").unwrap().as_bool().unwrap());
+            assert_eq!(v.get("segmentId").unwrap().as_u64().unwrap(), 0);
+            assert_eq!(v.get("startMs").unwrap().as_u64().unwrap(), 0);
         }
         _ => panic!("Expected transcription message, got {:?}", msg),
     }
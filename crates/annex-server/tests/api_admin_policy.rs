@@ -93,6 +93,8 @@ async fn test_update_policy_and_recalculate() {
         pool: pool.clone(),
         merkle_tree: Arc::new(Mutex::new(tree)),
         membership_vkey: load_dummy_vkey(),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
         server_id: 1,
         signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
             &mut rand::rngs::OsRng,
@@ -113,8 +115,13 @@ async fn test_update_policy_and_recalculate() {
         observe_tx: tokio::sync::broadcast::channel(256).0,
         upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
         preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
         cors_origins: vec![],
         enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
         ws_token_secret: std::sync::Arc::new([0u8; 32]),
     };
     let app = app(state);
@@ -169,3 +176,221 @@ async fn test_update_policy_and_recalculate() {
         .unwrap();
     assert_eq!(count, 1);
 }
+
+fn insert_channel(
+    conn: &rusqlite::Connection,
+    channel_id: &str,
+    agent_min_alignment: Option<annex_types::AlignmentStatus>,
+) {
+    annex_channels::create_channel(
+        conn,
+        &annex_channels::CreateChannelParams {
+            server_id: 1,
+            channel_id: channel_id.to_string(),
+            name: channel_id.to_string(),
+            channel_type: annex_types::ChannelType::Text,
+            topic: None,
+            vrp_topic_binding: None,
+            required_capabilities_json: None,
+            required_roles_json: None,
+            agent_min_alignment,
+            retention_days: None,
+            federation_scope: annex_types::FederationScope::Local,
+        },
+    )
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_policy_dry_run_previews_without_persisting() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let pool = create_pool(db_path.to_str().unwrap(), DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'test', 'Test', '{}')",
+        [],
+    )
+    .unwrap();
+    for (pseudonym, participant_type, can_moderate) in [
+        ("mod_user", "HUMAN", 1),
+        ("member_user", "HUMAN", 0),
+        ("agent-a", "AI_AGENT", 0),
+    ] {
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, can_moderate, active)
+             VALUES (1, ?1, ?2, ?3, 1)",
+            rusqlite::params![pseudonym, participant_type, can_moderate],
+        )
+        .unwrap();
+    }
+
+    // An aligned agent and an aligned federation peer, both on the empty anchor.
+    let anchor_json = serde_json::to_string(&VrpAnchorSnapshot::new(&[], &[]).unwrap()).unwrap();
+    let contract = VrpCapabilitySharingContract {
+        required_capabilities: vec![],
+        offered_capabilities: vec!["TEXT".to_string(), "VRP".to_string()],
+        redacted_topics: vec![],
+    };
+    conn.execute(
+        "INSERT INTO agent_registrations (
+            server_id, pseudonym_id, alignment_status, transfer_scope,
+            capability_contract_json, anchor_snapshot_json, reputation_score, last_handshake_at
+        ) VALUES (1, 'agent-a', 'ALIGNED', 'REFLECTION_SUMMARIES_ONLY', ?1, ?2, 0.5, datetime('now'))",
+        rusqlite::params![serde_json::to_string(&contract).unwrap(), anchor_json],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO instances (id, base_url, public_key, label, status) VALUES (10, 'http://remote.com', 'pubkey', 'Remote', 'ACTIVE')",
+        [],
+    )
+    .unwrap();
+    let handshake = VrpFederationHandshake {
+        anchor_snapshot: VrpAnchorSnapshot::new(&[], &[]).unwrap(),
+        capability_contract: contract,
+    };
+    conn.execute(
+        "INSERT INTO federation_agreements (
+            local_server_id, remote_instance_id, alignment_status, transfer_scope, agreement_json, remote_handshake_json, active
+        ) VALUES (1, 10, 'ALIGNED', 'REFLECTION_SUMMARIES_ONLY', '{}', ?1, 1)",
+        [serde_json::to_string(&handshake).unwrap()],
+    )
+    .unwrap();
+
+    insert_channel(
+        &conn,
+        "chan-partial",
+        Some(annex_types::AlignmentStatus::Partial),
+    );
+    insert_channel(&conn, "chan-open", None);
+    conn.execute(
+        "INSERT INTO channel_members (server_id, channel_id, pseudonym_id) VALUES (1, 'chan-partial', 'agent-a')",
+        [],
+    )
+    .unwrap();
+    drop(conn);
+
+    let initial_policy = ServerPolicy {
+        federation_enabled: true,
+        ..Default::default()
+    };
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: load_dummy_vkey(),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
+            &mut rand::rngs::OsRng,
+        )),
+        public_url: std::sync::Arc::new(std::sync::RwLock::new(
+            "http://localhost:3000".to_string(),
+        )),
+        policy: Arc::new(RwLock::new(initial_policy.clone())),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: std::sync::Arc::new([0u8; 32]),
+    };
+    let policy_lock = state.policy.clone();
+    let app = app(state);
+
+    // A new principle puts both counterparties in conflict.
+    let proposed = ServerPolicy {
+        principles: vec!["We value privacy".to_string()],
+        federation_enabled: true,
+        ..Default::default()
+    };
+    let dry_run = |pseudonym: &'static str| {
+        let mut request = Request::builder()
+            .uri("/api/admin/policy/dry-run")
+            .method("POST")
+            .header("content-type", "application/json")
+            .header("X-Annex-Pseudonym", pseudonym)
+            .body(Body::from(serde_json::to_string(&proposed).unwrap()))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+        request
+    };
+
+    let response = app.clone().oneshot(dry_run("member_user")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app.oneshot(dry_run("mod_user")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+
+    let agents = body["agents"].as_array().unwrap();
+    assert_eq!(agents.len(), 1);
+    assert_eq!(agents[0]["pseudonymId"], "agent-a");
+    assert_eq!(agents[0]["previousStatus"], "ALIGNED");
+    assert_eq!(agents[0]["report"]["alignment_status"], "Conflict");
+
+    let agreements = body["federationAgreements"].as_array().unwrap();
+    assert_eq!(agreements.len(), 1);
+    assert_eq!(agreements[0]["baseUrl"], "http://remote.com");
+    assert_eq!(agreements[0]["report"]["alignment_status"], "Conflict");
+
+    let lockouts = body["channelLockouts"].as_array().unwrap();
+    assert_eq!(
+        lockouts.len(),
+        1,
+        "only channels with a minimum alignment lock agents out"
+    );
+    assert_eq!(lockouts[0]["channelId"], "chan-partial");
+    assert_eq!(lockouts[0]["pseudonymId"], "agent-a");
+    assert_eq!(lockouts[0]["isMember"], true);
+
+    // Nothing was applied.
+    assert_eq!(*policy_lock.read().unwrap(), initial_policy);
+    let conn = pool.get().unwrap();
+    let (status, active): (String, bool) = conn
+        .query_row(
+            "SELECT alignment_status, active FROM agent_registrations WHERE pseudonym_id = 'agent-a'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(status, "ALIGNED");
+    assert!(active);
+    let status: String = conn
+        .query_row(
+            "SELECT alignment_status FROM federation_agreements WHERE remote_instance_id = 10",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(status, "ALIGNED");
+    let versions: i64 = conn
+        .query_row("SELECT COUNT(*) FROM server_policy_versions", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(versions, 0);
+}
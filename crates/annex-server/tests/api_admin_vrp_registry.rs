@@ -0,0 +1,354 @@
+use annex_db::{create_pool, run_migrations, DbRuntimeSettings};
+use annex_identity::MerkleTree;
+use annex_server::{app, middleware::RateLimiter, AppState};
+use annex_types::ServerPolicy;
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tower::ServiceExt;
+
+async fn setup_app() -> (axum::Router, annex_db::DbPool) {
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    {
+        let conn = pool.get().unwrap();
+        run_migrations(&conn).unwrap();
+        let policy_json = serde_json::to_string(&ServerPolicy::default()).unwrap();
+        conn.execute(
+            "INSERT INTO servers (slug, label, policy_json) VALUES ('test', 'Test', ?1)",
+            [policy_json],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO channels (server_id, channel_id, name, channel_type, vrp_topic_binding, federation_scope)
+             VALUES (1, 'chan-1', 'General', '\"Text\"', 'annex:channel:v1', '\"Local\"')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, can_moderate, active)
+             VALUES (1, 'mod', 'HUMAN', 1, 1), (1, 'user', 'HUMAN', 0, 1)",
+            [],
+        )
+        .unwrap();
+    }
+
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    (app(state), pool)
+}
+
+async fn send(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    pseudonym: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("content-type", "application/json");
+    if let Some(p) = pseudonym {
+        builder = builder.header("X-Annex-Pseudonym", p);
+    }
+    let mut request = builder
+        .body(match body {
+            Some(b) => Body::from(b.to_string()),
+            None => Body::empty(),
+        })
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, value)
+}
+
+fn event_count(pool: &annex_db::DbPool, event_type: &str) -> i64 {
+    let conn = pool.get().unwrap();
+    conn.query_row(
+        "SELECT COUNT(*) FROM public_event_log WHERE event_type = ?1 AND domain = 'IDENTITY'",
+        [event_type],
+        |row| row.get(0),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_topic_crud() {
+    let (app, pool) = setup_app().await;
+
+    // Non-moderators cannot change the registry.
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/admin/vrp/topics",
+        Some("user"),
+        Some(json!({ "topic": "annex:lab:v1", "description": "Lab" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/admin/vrp/topics",
+        Some("mod"),
+        Some(json!({ "topic": "annex:lab:v1", "description": "Lab" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["topic"], "annex:lab:v1");
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/admin/vrp/topics",
+        Some("mod"),
+        Some(json!({ "topic": "annex:lab:v1" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send(
+        &app,
+        "PATCH",
+        "/api/admin/vrp/topics/annex:lab:v1",
+        Some("mod"),
+        Some(json!({ "description": "Laboratory" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["description"], "Laboratory");
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/admin/vrp/topics/annex:lab:v1/deprecate",
+        Some("mod"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["deprecated_at"].is_string());
+
+    // The public listing shows the deprecation.
+    let (_, topics) = send(&app, "GET", "/api/registry/topics", None, None).await;
+    let lab = topics
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["topic"] == "annex:lab:v1")
+        .unwrap();
+    assert!(lab["deprecated_at"].is_string());
+
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        "/api/admin/vrp/topics/annex:lab:v1",
+        Some("mod"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        "/api/admin/vrp/topics/annex:lab:v1",
+        Some("mod"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    assert_eq!(event_count(&pool, "VRP_TOPIC_CHANGED"), 4);
+}
+
+#[tokio::test]
+async fn test_bound_topic_cannot_be_deleted() {
+    let (app, pool) = setup_app().await;
+
+    let (status, body) = send(
+        &app,
+        "DELETE",
+        "/api/admin/vrp/topics/annex:channel:v1",
+        Some("mod"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(body["error"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("channel"));
+    assert_eq!(event_count(&pool, "VRP_TOPIC_CHANGED"), 0);
+}
+
+#[tokio::test]
+async fn test_deprecated_topic_refuses_new_bindings_and_verifications() {
+    let (app, _pool) = setup_app().await;
+    for topic in ["annex:lab:v1", "annex:lab:v2"] {
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/admin/vrp/topics",
+            Some("mod"),
+            Some(json!({ "topic": topic, "description": "Lab" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/admin/vrp/topics/annex:lab:v1/deprecate",
+        Some("mod"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let channel = |channel_id: &str, binding: &str| {
+        json!({
+            "channel_id": channel_id,
+            "name": "Lab",
+            "channel_type": "Text",
+            "vrp_topic_binding": binding,
+            "federation_scope": "Local",
+        })
+    };
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/channels",
+        Some("mod"),
+        Some(channel("lab-old", "annex:lab:v1")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/channels",
+        Some("mod"),
+        Some(channel("lab-new", "annex:lab:v2")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Refused before the proof is even looked at.
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/zk/verify-membership",
+        None,
+        Some(json!({
+            "root": "00",
+            "commitment": "00",
+            "topic": "annex:lab:v1",
+            "proof": {},
+            "publicSignals": [],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(body["error"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("deprecated"));
+}
+
+#[tokio::test]
+async fn test_role_crud() {
+    let (app, pool) = setup_app().await;
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/admin/vrp/roles",
+        Some("mod"),
+        Some(json!({ "role_code": 6, "label": "AUDITOR" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["label"], "AUDITOR");
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/admin/vrp/roles",
+        Some("mod"),
+        Some(json!({ "role_code": 7, "label": "not valid" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = send(
+        &app,
+        "PATCH",
+        "/api/admin/vrp/roles/6",
+        Some("mod"),
+        Some(json!({ "label": "REVIEWER" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["label"], "REVIEWER");
+
+    // Built-in roles are fixed.
+    let (status, _) = send(&app, "DELETE", "/api/admin/vrp/roles/1", Some("mod"), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = send(&app, "DELETE", "/api/admin/vrp/roles/6", Some("mod"), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, roles) = send(&app, "GET", "/api/registry/roles", None, None).await;
+    assert!(roles
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|r| r["role_code"] != 6));
+
+    assert_eq!(event_count(&pool, "VRP_ROLE_CHANGED"), 3);
+}
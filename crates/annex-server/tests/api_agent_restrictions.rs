@@ -48,6 +48,8 @@ async fn setup_app() -> (axum::Router, annex_db::DbPool) {
         pool: pool.clone(),
         merkle_tree: Arc::new(Mutex::new(tree)),
         membership_vkey: load_vkey(),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
         server_id: 1,
         signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
             &mut rand::rngs::OsRng,
@@ -68,8 +70,13 @@ async fn setup_app() -> (axum::Router, annex_db::DbPool) {
         observe_tx: tokio::sync::broadcast::channel(256).0,
         upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
         preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
         cors_origins: vec![],
         enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
         ws_token_secret: std::sync::Arc::new([0u8; 32]),
     };
 
@@ -102,6 +109,7 @@ async fn test_join_channel_conflict_agent_implicit_allow() {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: None, // No restriction specified
             retention_days: None,
             federation_scope: FederationScope::Local,
@@ -149,6 +157,7 @@ async fn test_join_voice_channel_partial_agent() {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: Some(AlignmentStatus::Partial), // Explicitly allows Partial
             retention_days: None,
             federation_scope: FederationScope::Local,
@@ -196,6 +205,7 @@ async fn test_join_text_channel_partial_agent() {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: Some(AlignmentStatus::Partial), // Allows Partial
             retention_days: None,
             federation_scope: FederationScope::Local,
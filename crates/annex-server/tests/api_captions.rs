@@ -0,0 +1,291 @@
+use annex_channels::{add_member, create_channel, CreateChannelParams};
+use annex_db::{create_pool, run_migrations, DbPool, DbRuntimeSettings};
+use annex_server::{app, middleware, AppState};
+use annex_types::{ChannelType, FederationScope, ServerPolicy};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+/// Starts a server whose STT engine always hears "hello world".
+async fn start_server(stt_script: &Path) -> (SocketAddr, DbPool) {
+    std::fs::write(
+        stt_script,
+        "#!/bin/sh\ncat >/dev/null\necho -n 'hello world'",
+    )
+    .unwrap();
+    std::fs::set_permissions(stt_script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    {
+        let conn = pool.get().unwrap();
+        run_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'default', 'Default', '{}')",
+            [],
+        )
+        .unwrap();
+        for (p, can_moderate) in [("mod", 1), ("alice", 0), ("bob", 0), ("carol", 0)] {
+            conn.execute(
+                "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, can_moderate, active) VALUES (1, ?1, 'HUMAN', ?2, 1)",
+                rusqlite::params![p, can_moderate],
+            )
+            .unwrap();
+        }
+        for (channel_id, channel_type) in [
+            ("voice-1", ChannelType::Voice),
+            ("text-1", ChannelType::Text),
+        ] {
+            create_channel(
+                &conn,
+                &CreateChannelParams {
+                    server_id: 1,
+                    channel_id: channel_id.to_string(),
+                    name: channel_id.to_string(),
+                    channel_type,
+                    topic: None,
+                    vrp_topic_binding: None,
+                    required_capabilities_json: None,
+                    required_roles_json: None,
+                    agent_min_alignment: None,
+                    retention_days: Some(7),
+                    federation_scope: FederationScope::Local,
+                },
+            )
+            .unwrap();
+            // carol is not a member of either channel.
+            for p in ["mod", "alice", "bob"] {
+                add_member(&conn, 1, channel_id, p).unwrap();
+            }
+        }
+    }
+
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(annex_identity::MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: middleware::RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", stt_script)),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app(state).into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    (addr, pool)
+}
+
+fn put(
+    addr: SocketAddr,
+    path: &str,
+    pseudonym: &str,
+    body: serde_json::Value,
+) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .put(format!("http://{}{}", addr, path))
+        .header("X-Annex-Pseudonym", pseudonym)
+        .json(&body)
+}
+
+/// Posts `ms` milliseconds of loud 16 kHz PCM as one complete utterance.
+async fn post_speech(addr: SocketAddr, channel_id: &str, pseudonym: &str, ms: usize) -> u16 {
+    reqwest::Client::new()
+        .post(format!(
+            "http://{}/api/channels/{}/captions/audio?end=true",
+            addr, channel_id
+        ))
+        .header("X-Annex-Pseudonym", pseudonym)
+        .body(4_000i16.to_le_bytes().repeat(16 * ms))
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn test_caption_settings_require_moderator_and_voice() {
+    let dir = tempfile::tempdir().unwrap();
+    let (addr, _pool) = start_server(&dir.path().join("whisper.sh")).await;
+    let enable = json!({ "enabled": true, "persist": false });
+
+    let res = put(
+        addr,
+        "/api/channels/voice-1/captions",
+        "alice",
+        enable.clone(),
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 403);
+
+    let res = put(addr, "/api/channels/text-1/captions", "mod", enable.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+
+    let res = put(addr, "/api/channels/voice-1/captions", "mod", enable)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+
+    let settings: serde_json::Value = reqwest::Client::new()
+        .get(format!("http://{}/api/channels/voice-1/captions", addr))
+        .header("X-Annex-Pseudonym", "alice")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(settings["enabled"], true);
+    assert_eq!(settings["persist"], false);
+    assert_eq!(settings["consented"], false);
+
+    let res = reqwest::Client::new()
+        .get(format!("http://{}/api/channels/voice-1/captions", addr))
+        .header("X-Annex-Pseudonym", "carol")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+}
+
+#[tokio::test]
+async fn test_captions_require_consent_and_reach_subscribers() {
+    let dir = tempfile::tempdir().unwrap();
+    let (addr, pool) = start_server(&dir.path().join("whisper.sh")).await;
+
+    // Captions are off by default.
+    assert_eq!(post_speech(addr, "voice-1", "alice", 200).await, 409);
+
+    let res = put(
+        addr,
+        "/api/channels/voice-1/captions",
+        "mod",
+        json!({ "enabled": true, "persist": true }),
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 200);
+
+    assert_eq!(post_speech(addr, "voice-1", "alice", 200).await, 403);
+    assert_eq!(post_speech(addr, "voice-1", "carol", 200).await, 403);
+
+    let res = put(
+        addr,
+        "/api/channels/voice-1/captions/consent",
+        "alice",
+        json!({ "consent": true }),
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["consented"], true);
+
+    let (mut ws, _) = connect_async(format!("ws://{}/ws?pseudonym=bob", addr))
+        .await
+        .unwrap();
+    ws.send(Message::Text(
+        json!({ "type": "subscribe", "channelId": "voice-1" })
+            .to_string()
+            .into(),
+    ))
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(post_speech(addr, "voice-1", "alice", 200).await, 202);
+
+    let caption = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(Ok(msg)) = ws.next().await {
+            if let Message::Text(text) = msg {
+                let frame: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if frame["type"] == "caption" {
+                    return frame;
+                }
+            }
+        }
+        panic!("websocket closed before a caption arrived");
+    })
+    .await
+    .expect("caption frame");
+
+    assert_eq!(caption["channelId"], "voice-1");
+    assert_eq!(caption["speakerPseudonym"], "alice");
+    assert_eq!(caption["text"], "hello world");
+    assert_eq!(caption["isFinal"], true);
+    assert_eq!(caption["startMs"], 0);
+    assert_eq!(caption["endMs"], 200);
+
+    // Persisted captions are ordinary messages and expire with the channel.
+    let message_id = caption["messageId"].as_str().expect("persisted caption");
+    let conn = pool.get().unwrap();
+    let (sender, content, expires_at): (String, String, Option<String>) = conn
+        .query_row(
+            "SELECT sender_pseudonym, content, expires_at FROM messages WHERE message_id = ?1",
+            [message_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(
+        (sender.as_str(), content.as_str()),
+        ("alice", "hello world")
+    );
+    assert!(expires_at.is_some());
+    drop(conn);
+
+    // Withdrawing consent stops further captioning.
+    let res = put(
+        addr,
+        "/api/channels/voice-1/captions/consent",
+        "alice",
+        json!({ "consent": false }),
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(post_speech(addr, "voice-1", "alice", 200).await, 403);
+}
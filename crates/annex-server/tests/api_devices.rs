@@ -0,0 +1,451 @@
+use annex_channels::{add_member, create_channel, CreateChannelParams};
+use annex_db::{create_pool, run_migrations, DbRuntimeSettings};
+use annex_identity::MerkleTree;
+use annex_server::api_devices::{
+    device_approval_message, device_registration_message, device_revocation_message,
+    device_token_message, DeviceSignaturePayload,
+};
+use annex_server::{app, middleware::RateLimiter, AppState};
+use annex_types::{ChannelType, FederationScope, ServerPolicy};
+use ed25519_dalek::{Signer, SigningKey};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+type WsStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Starts a server with one identity (`alice`) who is a member of `chan-1`.
+async fn start_server() -> SocketAddr {
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    {
+        let conn = pool.get().unwrap();
+        run_migrations(&conn).unwrap();
+        let policy_json = serde_json::to_string(&ServerPolicy::default()).unwrap();
+        conn.execute(
+            "INSERT INTO servers (slug, label, policy_json) VALUES ('test', 'Test', ?1)",
+            [policy_json],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, active)
+             VALUES (1, 'alice', 'HUMAN', 1)",
+            [],
+        )
+        .unwrap();
+        create_channel(
+            &conn,
+            &CreateChannelParams {
+                server_id: 1,
+                channel_id: "chan-1".to_string(),
+                name: "General".to_string(),
+                channel_type: ChannelType::Text,
+                topic: None,
+                vrp_topic_binding: None,
+                required_capabilities_json: None,
+                required_roles_json: None,
+                agent_min_alignment: None,
+                retention_days: None,
+                federation_scope: FederationScope::Local,
+            },
+        )
+        .unwrap();
+        add_member(&conn, 1, "chan-1", "alice").unwrap();
+    }
+
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app(state).into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// An active device that signs device-management requests.
+type Approver<'a> = Option<(&'a str, &'a SigningKey)>;
+
+/// Encodes the `x-annex-device-signature` header for `message`.
+fn device_signature_header(
+    device_id: &str,
+    key: &SigningKey,
+    message: impl Fn(&str, u64) -> String,
+) -> String {
+    use base64::Engine;
+    let timestamp = now_secs();
+    let signature = key.sign(message(device_id, timestamp).as_bytes());
+    let payload = DeviceSignaturePayload {
+        device_id: device_id.to_string(),
+        timestamp,
+        signature: hex::encode(signature.to_bytes()),
+    };
+    base64::engine::general_purpose::STANDARD.encode(serde_json::to_vec(&payload).unwrap())
+}
+
+async fn register(
+    client: &reqwest::Client,
+    addr: SocketAddr,
+    label: &str,
+    key: &SigningKey,
+    approver: Approver<'_>,
+) -> reqwest::Response {
+    let public_key = hex::encode(key.verifying_key().as_bytes());
+    let signature = key.sign(device_registration_message("alice", &public_key).as_bytes());
+    let mut request = client
+        .post(format!("http://{}/api/devices", addr))
+        .header("X-Annex-Pseudonym", "alice");
+    if let Some((device_id, approver_key)) = approver {
+        request = request.header(
+            "x-annex-device-signature",
+            device_signature_header(device_id, approver_key, |id, ts| {
+                device_approval_message("alice", id, &public_key, ts)
+            }),
+        );
+    }
+    request
+        .json(&json!({
+            "label": label,
+            "public_key": public_key,
+            "signature": hex::encode(signature.to_bytes()),
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn device_token(
+    client: &reqwest::Client,
+    addr: SocketAddr,
+    device_id: &str,
+    key: &SigningKey,
+) -> reqwest::Response {
+    let timestamp = now_secs();
+    let signature = key.sign(device_token_message(device_id, timestamp).as_bytes());
+    client
+        .post(format!(
+            "http://{}/api/devices/{}/ws-token",
+            addr, device_id
+        ))
+        .header("X-Annex-Pseudonym", "alice")
+        .json(&json!({
+            "timestamp": timestamp,
+            "signature": hex::encode(signature.to_bytes()),
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn revoke(
+    client: &reqwest::Client,
+    addr: SocketAddr,
+    target: &str,
+    signer: Approver<'_>,
+) -> reqwest::Response {
+    let mut request = client
+        .delete(format!("http://{}/api/devices/{}", addr, target))
+        .header("X-Annex-Pseudonym", "alice");
+    if let Some((device_id, key)) = signer {
+        request = request.header(
+            "x-annex-device-signature",
+            device_signature_header(device_id, key, |id, ts| {
+                device_revocation_message("alice", id, target, ts)
+            }),
+        );
+    }
+    request.send().await.unwrap()
+}
+
+/// Registers a device (approved by `approver` if given), opens a WebSocket
+/// with a device token and subscribes it to `chan-1`.
+async fn connect_device(
+    client: &reqwest::Client,
+    addr: SocketAddr,
+    label: &str,
+    approver: Approver<'_>,
+) -> (String, SigningKey, WsStream) {
+    let key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let resp = register(client, addr, label, &key, approver).await;
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    let device: Value = resp.json().await.unwrap();
+    let device_id = device["device_id"].as_str().unwrap().to_string();
+
+    let resp = device_token(client, addr, &device_id, &key).await;
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    let token: Value = resp.json().await.unwrap();
+
+    let url = format!(
+        "ws://{}/ws?token={}",
+        addr,
+        token["token"].as_str().unwrap()
+    );
+    let (mut ws, _) = connect_async(url).await.expect("failed to connect");
+    ws.send(Message::Text(
+        json!({ "type": "subscribe", "channelId": "chan-1" })
+            .to_string()
+            .into(),
+    ))
+    .await
+    .unwrap();
+    (device_id, key, ws)
+}
+
+/// Waits for the next text frame carrying a chat message.
+async fn next_message(ws: &mut WsStream) -> Option<Value> {
+    loop {
+        match tokio::time::timeout(Duration::from_secs(2), ws.next()).await {
+            Ok(Some(Ok(Message::Text(text)))) => {
+                let value: Value = serde_json::from_str(&text).unwrap();
+                if value["type"] == "message" {
+                    return Some(value);
+                }
+            }
+            Ok(Some(Ok(Message::Close(_)))) | Ok(None) | Ok(Some(Err(_))) | Err(_) => return None,
+            Ok(Some(Ok(_))) => {}
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_register_device_requires_key_possession() {
+    let addr = start_server().await;
+    let client = reqwest::Client::new();
+
+    let key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let other = SigningKey::generate(&mut rand::rngs::OsRng);
+    let public_key = hex::encode(key.verifying_key().as_bytes());
+    let signature = other.sign(device_registration_message("alice", &public_key).as_bytes());
+    let resp = client
+        .post(format!("http://{}/api/devices", addr))
+        .header("X-Annex-Pseudonym", "alice")
+        .json(&json!({
+            "label": "Laptop",
+            "public_key": public_key,
+            "signature": hex::encode(signature.to_bytes()),
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let resp = register(&client, addr, "Laptop", &key, None).await;
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    let device: Value = resp.json().await.unwrap();
+    let device_id = device["device_id"].as_str().unwrap();
+
+    // The same key cannot be registered twice.
+    let resp = register(&client, addr, "Laptop again", &key, Some((device_id, &key))).await;
+    assert_eq!(resp.status(), reqwest::StatusCode::CONFLICT);
+
+    let devices: Value = client
+        .get(format!("http://{}/api/devices", addr))
+        .header("X-Annex-Pseudonym", "alice")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(devices.as_array().unwrap().len(), 1);
+    assert_eq!(devices[0]["label"], "Laptop");
+}
+
+#[tokio::test]
+async fn test_devices_stay_connected_and_revoke_independently() {
+    let addr = start_server().await;
+    let client = reqwest::Client::new();
+
+    let (laptop_id, laptop_key, mut laptop) = connect_device(&client, addr, "Laptop", None).await;
+    let (phone_id, _phone_key, mut phone) =
+        connect_device(&client, addr, "Phone", Some((&laptop_id, &laptop_key))).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // A message from one device reaches both.
+    laptop
+        .send(Message::Text(
+            json!({ "type": "message", "channelId": "chan-1", "content": "hi", "replyTo": null })
+                .to_string()
+                .into(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(next_message(&mut laptop).await.unwrap()["content"], "hi");
+    assert_eq!(next_message(&mut phone).await.unwrap()["content"], "hi");
+
+    // Revoking the phone closes its socket but leaves the laptop connected.
+    let resp = revoke(&client, addr, &phone_id, Some((&laptop_id, &laptop_key))).await;
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    assert!(next_message(&mut phone).await.is_none());
+
+    laptop
+        .send(Message::Text(
+            json!({ "type": "message", "channelId": "chan-1", "content": "still here", "replyTo": null })
+                .to_string()
+                .into(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        next_message(&mut laptop).await.unwrap()["content"],
+        "still here"
+    );
+
+    // A revoked device can no longer obtain tokens.
+    let key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let resp = device_token(&client, addr, &phone_id, &key).await;
+    assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let devices: Value = client
+        .get(format!("http://{}/api/devices", addr))
+        .header("X-Annex-Pseudonym", "alice")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let phone = devices
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["device_id"] == phone_id.as_str())
+        .unwrap();
+    assert!(phone["revoked_at"].is_string());
+}
+
+#[tokio::test]
+async fn test_devices_replace_pseudonym_credentials() {
+    let addr = start_server().await;
+    let client = reqwest::Client::new();
+
+    // Without devices, the pseudonym alone gets an unbound token.
+    let resp = client
+        .post(format!("http://{}/api/ws/token", addr))
+        .header("X-Annex-Pseudonym", "alice")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    let token: Value = resp.json().await.unwrap();
+    let unbound_token = token["token"].as_str().unwrap().to_string();
+
+    let (laptop_id, laptop_key, _laptop) = connect_device(&client, addr, "Laptop", None).await;
+
+    // Once a device exists, unbound tokens and the legacy pseudonym parameter
+    // are refused.
+    let resp = client
+        .post(format!("http://{}/api/ws/token", addr))
+        .header("X-Annex-Pseudonym", "alice")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+    assert!(
+        connect_async(format!("ws://{}/ws?token={}", addr, unbound_token))
+            .await
+            .is_err()
+    );
+    assert!(connect_async(format!("ws://{}/ws?pseudonym=alice", addr))
+        .await
+        .is_err());
+
+    // Registering another device needs an active device's approval.
+    let phone_key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let resp = register(&client, addr, "Phone", &phone_key, None).await;
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let resp = register(
+        &client,
+        addr,
+        "Phone",
+        &phone_key,
+        Some(("unknown", &phone_key)),
+    )
+    .await;
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let resp = register(
+        &client,
+        addr,
+        "Phone",
+        &phone_key,
+        Some((&laptop_id, &phone_key)),
+    )
+    .await;
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let resp = register(
+        &client,
+        addr,
+        "Phone",
+        &phone_key,
+        Some((&laptop_id, &laptop_key)),
+    )
+    .await;
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    let phone: Value = resp.json().await.unwrap();
+    let phone_id = phone["device_id"].as_str().unwrap();
+
+    // So does revoking one; a signature for another target does not carry over.
+    let resp = revoke(&client, addr, phone_id, None).await;
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let resp = client
+        .delete(format!("http://{}/api/devices/{}", addr, phone_id))
+        .header("X-Annex-Pseudonym", "alice")
+        .header(
+            "x-annex-device-signature",
+            device_signature_header(&laptop_id, &laptop_key, |id, ts| {
+                device_revocation_message("alice", id, &laptop_id, ts)
+            }),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let resp = revoke(&client, addr, phone_id, Some((phone_id, &phone_key))).await;
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+}
@@ -61,6 +61,8 @@ async fn setup_app() -> (axum::Router, Arc<AppState>, TempDir) {
         pool,
         merkle_tree: Arc::new(Mutex::new(tree)),
         membership_vkey: Arc::new(membership_vkey),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
         server_id,
         signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
             &mut rand::rngs::OsRng,
@@ -79,8 +81,13 @@ async fn setup_app() -> (axum::Router, Arc<AppState>, TempDir) {
         observe_tx: tokio::sync::broadcast::channel(256).0,
         upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
         preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
         cors_origins: vec![],
         enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
         ws_token_secret: std::sync::Arc::new([0u8; 32]),
     };
 
@@ -250,3 +257,72 @@ async fn test_join_federated_channel() {
 
     assert!(is_member, "Remote user should be added to channel members");
 }
+
+#[tokio::test]
+async fn test_join_role_gated_federated_channel_requires_attested_root() {
+    let (app, state, _temp_dir) = setup_app().await;
+    let channel_id = "fed-humans";
+    let pseudonym_id = "remote-user-2";
+
+    let payload = {
+        let conn = state.pool.get().unwrap();
+        conn.execute(
+            r#"INSERT INTO channels (
+                server_id, channel_id, name, channel_type, federation_scope, required_roles_json
+            ) VALUES (?1, ?2, 'Federated Humans', '"Text"', '"Federated"', '["Human"]')"#,
+            rusqlite::params![state.server_id, channel_id],
+        )
+        .unwrap();
+
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let remote_base_url = "https://remote.example.com";
+        conn.execute(
+            "INSERT INTO instances (base_url, public_key, label, status) VALUES (?1, ?2, 'Remote', 'ACTIVE')",
+            rusqlite::params![remote_base_url, hex::encode(signing_key.verifying_key().as_bytes())],
+        )
+        .unwrap();
+        let remote_instance_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO federation_agreements (
+                local_server_id, remote_instance_id, alignment_status, transfer_scope, agreement_json, active
+            ) VALUES (?1, ?2, 'ALIGNED', 'REFLECTION_SUMMARIES_ONLY', '{}', 1)",
+            rusqlite::params![state.server_id, remote_instance_id],
+        )
+        .unwrap();
+
+        // Attested before root tracking: root_hex_at_verification is NULL.
+        conn.execute(
+            "INSERT INTO federated_identities (server_id, remote_instance_id, commitment_hex, pseudonym_id, vrp_topic, root_hex_at_verification) VALUES (?1, ?2, ?3, ?4, 'topic', NULL)",
+            rusqlite::params![state.server_id, remote_instance_id, "0".repeat(64), pseudonym_id],
+        )
+        .unwrap();
+
+        let signature = signing_key.sign(format!("{}\n{}", channel_id, pseudonym_id).as_bytes());
+        json!({
+            "originating_server": remote_base_url,
+            "pseudonym_id": pseudonym_id,
+            "signature": hex::encode(signature.to_bytes()),
+            "role_proof": { "proof": {}, "publicSignals": [] },
+        })
+    };
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/federation/channels/{}/join", channel_id))
+                .header("Content-Type", "application/json")
+                .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 8080))))
+                .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("no verified root"), "{}", body);
+}
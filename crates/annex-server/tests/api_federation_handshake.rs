@@ -47,6 +47,8 @@ async fn setup_app() -> (axum::Router, annex_db::DbPool) {
         pool: pool.clone(),
         merkle_tree: Arc::new(Mutex::new(tree)),
         membership_vkey: load_vkey(),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
         server_id: 1,
         signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
             &mut rand::rngs::OsRng,
@@ -67,8 +69,13 @@ async fn setup_app() -> (axum::Router, annex_db::DbPool) {
         observe_tx: tokio::sync::broadcast::channel(256).0,
         upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
         preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
         cors_origins: vec![],
         enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
         ws_token_secret: std::sync::Arc::new([0u8; 32]),
     };
 
@@ -129,6 +136,29 @@ async fn test_federation_handshake_success() {
         )
         .unwrap();
     assert_eq!(count, 1);
+
+    // The agreement expires after the default contract validity period.
+    let expires_in: i64 = conn
+        .query_row(
+            "SELECT CAST(strftime('%s', expires_at) - strftime('%s', 'now') AS INTEGER)
+             FROM federation_agreements WHERE remote_instance_id = 10",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!((2_591_990..=2_592_000).contains(&expires_in));
+
+    let (peer_type, report_json): (String, String) = conn
+        .query_row(
+            "SELECT peer_type, report_json FROM vrp_handshake_log
+             WHERE peer_pseudonym = 'https://remote.example.com'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(peer_type, "SERVER");
+    let logged: VrpValidationReport = serde_json::from_str(&report_json).unwrap();
+    assert_eq!(logged, report);
 }
 
 #[tokio::test]
@@ -168,3 +198,326 @@ async fn test_federation_handshake_unknown_instance() {
     // 3. Verify Response
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
+
+fn renewal_request(payload: serde_json::Value) -> Request<Body> {
+    let mut req = Request::builder()
+        .uri("/api/federation/renewal")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    req
+}
+
+/// A renewal request from `base_url` to `target`, signed with `key`.
+fn signed_renewal(
+    key: &ed25519_dalek::SigningKey,
+    base_url: &str,
+    target: &str,
+    requested_at: i64,
+) -> serde_json::Value {
+    use ed25519_dalek::Signer;
+
+    let message =
+        annex_server::api_federation::renewal_signing_payload(base_url, target, requested_at);
+    serde_json::json!({
+        "base_url": base_url,
+        "requested_at": requested_at,
+        "signature": hex::encode(key.sign(message.as_bytes()).to_bytes()),
+    })
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+#[tokio::test]
+async fn test_federation_renewal_request_accepted_once_when_signed() {
+    let (app, pool) = setup_app().await;
+    let key = register_signing_peer(&pool, "https://signed.example.com");
+    let now = unix_now();
+    let request = signed_renewal(
+        &key,
+        "https://signed.example.com",
+        "http://localhost:3000",
+        now,
+    );
+
+    let response = app
+        .clone()
+        .oneshot(renewal_request(request.clone()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    // Replaying the request, or asking again right away, is throttled.
+    let response = app.clone().oneshot(renewal_request(request)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let again = signed_renewal(
+        &key,
+        "https://signed.example.com",
+        "http://localhost:3000",
+        now + 1,
+    );
+    let response = app.oneshot(renewal_request(again)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_federation_renewal_request_requires_peer_signature() {
+    let (app, pool) = setup_app().await;
+    register_signing_peer(&pool, "https://signed.example.com");
+    let now = unix_now();
+
+    // Unsigned.
+    let response = app
+        .clone()
+        .oneshot(renewal_request(
+            serde_json::json!({ "base_url": "https://signed.example.com" }),
+        ))
+        .await
+        .unwrap();
+    assert!(response.status().is_client_error());
+
+    // Signed by a key the peer never registered.
+    let forger = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+    let forged = signed_renewal(
+        &forger,
+        "https://signed.example.com",
+        "http://localhost:3000",
+        now,
+    );
+    let response = app.clone().oneshot(renewal_request(forged)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let key = ed25519_dalek::SigningKey::from_bytes(&[0u8; 32]);
+    let conn = pool.get().unwrap();
+    conn.execute(
+        "UPDATE instances SET public_key = ?1 WHERE base_url = 'https://signed.example.com'",
+        rusqlite::params![hex::encode(key.verifying_key().as_bytes())],
+    )
+    .unwrap();
+    drop(conn);
+
+    // Addressed to another server.
+    let elsewhere = signed_renewal(
+        &key,
+        "https://signed.example.com",
+        "https://other.example.com",
+        now,
+    );
+    let response = app
+        .clone()
+        .oneshot(renewal_request(elsewhere))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Too old.
+    let stale = signed_renewal(
+        &key,
+        "https://signed.example.com",
+        "http://localhost:3000",
+        now - 3_600,
+    );
+    let response = app.oneshot(renewal_request(stale)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_federation_renewal_request_unknown_instance() {
+    let (app, _) = setup_app().await;
+    let key = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+
+    let response = app
+        .oneshot(renewal_request(signed_renewal(
+            &key,
+            "https://unknown.example.com",
+            "http://localhost:3000",
+            unix_now(),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Registers a peer with a real Ed25519 key and returns the key.
+fn register_signing_peer(pool: &annex_db::DbPool, base_url: &str) -> ed25519_dalek::SigningKey {
+    let key = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+    let conn = pool.get().unwrap();
+    conn.execute(
+        "INSERT INTO instances (base_url, public_key, label, status) VALUES (?1, ?2, 'Signed Peer', 'ACTIVE')",
+        rusqlite::params![base_url, hex::encode(key.verifying_key().as_bytes())],
+    )
+    .unwrap();
+    key
+}
+
+fn signed_handshake_request(base_url: &str, anchor: &VrpAnchorSnapshot) -> Request<Body> {
+    let payload = serde_json::json!({
+        "base_url": base_url,
+        "anchor_snapshot": anchor,
+        "capability_contract": VrpCapabilitySharingContract {
+            required_capabilities: vec![],
+            offered_capabilities: vec![],
+            redacted_topics: vec![],
+        }
+    });
+    let mut req = Request::builder()
+        .uri("/api/federation/handshake")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    req
+}
+
+#[tokio::test]
+async fn test_federation_handshake_accepts_signed_anchor() {
+    use ed25519_dalek::Signer;
+
+    let (app, pool) = setup_app().await;
+    let key = register_signing_peer(&pool, "https://signed.example.com");
+
+    let mut anchor = VrpAnchorSnapshot::new(&[], &[]).unwrap();
+    anchor.signature = Some(hex::encode(
+        key.sign(anchor.signing_payload().as_bytes()).to_bytes(),
+    ));
+
+    let response = app
+        .oneshot(signed_handshake_request(
+            "https://signed.example.com",
+            &anchor,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_federation_handshake_rejects_forged_anchor() {
+    use ed25519_dalek::Signer;
+
+    let (app, pool) = setup_app().await;
+    register_signing_peer(&pool, "https://signed.example.com");
+
+    // Signed by a key the peer never registered.
+    let impostor = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+    let mut anchor = VrpAnchorSnapshot::new(&[], &[]).unwrap();
+    anchor.signature = Some(hex::encode(
+        impostor
+            .sign(anchor.signing_payload().as_bytes())
+            .to_bytes(),
+    ));
+
+    let response = app
+        .oneshot(signed_handshake_request(
+            "https://signed.example.com",
+            &anchor,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let conn = pool.get().unwrap();
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM federation_agreements", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(count, 0, "a forged anchor must not create an agreement");
+}
+
+#[tokio::test]
+async fn test_federation_handshake_rejects_texts_outside_signed_hashes() {
+    use ed25519_dalek::Signer;
+
+    let (app, pool) = setup_app().await;
+    let key = register_signing_peer(&pool, "https://signed.example.com");
+
+    let mut anchor = VrpAnchorSnapshot::new(&["be honest".to_string()], &[]).unwrap();
+    anchor.signature = Some(hex::encode(
+        key.sign(anchor.signing_payload().as_bytes()).to_bytes(),
+    ));
+    // Swap the principle text while keeping the signed hash.
+    anchor.principles = vec!["anything goes".to_string()];
+
+    let response = app
+        .oneshot(signed_handshake_request(
+            "https://signed.example.com",
+            &anchor,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_federation_handshake_refuses_unsigned_or_older_anchor_after_signed() {
+    use ed25519_dalek::Signer;
+
+    let (app, pool) = setup_app().await;
+    let key = register_signing_peer(&pool, "https://signed.example.com");
+    let sign = |anchor: &mut VrpAnchorSnapshot| {
+        anchor.signature = Some(hex::encode(
+            key.sign(anchor.signing_payload().as_bytes()).to_bytes(),
+        ));
+    };
+
+    let mut current = VrpAnchorSnapshot::new(&["be kind".to_string()], &[]).unwrap();
+    sign(&mut current);
+    let mut stale = VrpAnchorSnapshot::new(&["be honest".to_string()], &[]).unwrap();
+    stale.timestamp = current.timestamp - 3600;
+    sign(&mut stale);
+
+    let response = app
+        .clone()
+        .oneshot(signed_handshake_request(
+            "https://signed.example.com",
+            &current,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // A validly signed but superseded anchor cannot be replayed.
+    let response = app
+        .clone()
+        .oneshot(signed_handshake_request(
+            "https://signed.example.com",
+            &stale,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Nor can the peer be downgraded to unsigned anchors.
+    let mut unsigned = current.clone();
+    unsigned.signature = None;
+    let response = app
+        .clone()
+        .oneshot(signed_handshake_request(
+            "https://signed.example.com",
+            &unsigned,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Re-presenting the current anchor is fine.
+    let response = app
+        .oneshot(signed_handshake_request(
+            "https://signed.example.com",
+            &current,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,306 @@
+use annex_channels::{add_member, create_channel, CreateChannelParams};
+use annex_db::{create_pool, DbPool, DbRuntimeSettings};
+use annex_graph::{ensure_graph_node, GraphProfile};
+use annex_server::{app, middleware, AppState};
+use annex_types::{
+    AlignmentStatus, ChannelType, EdgeKind, FederationScope, NodeType, PresenceEvent, ServerPolicy,
+    VisibilityLevel,
+};
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use futures_util::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use tower::ServiceExt;
+
+/// alice, bob and carol, all members of `chan-1`.
+fn setup_state(db: &tempfile::NamedTempFile) -> AppState {
+    let pool = create_pool(db.path().to_str().unwrap(), DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'default', 'Default Server', '{}')",
+        [],
+    )
+    .unwrap();
+    create_channel(
+        &conn,
+        &CreateChannelParams {
+            server_id: 1,
+            channel_id: "chan-1".to_string(),
+            name: "General".to_string(),
+            channel_type: ChannelType::Text,
+            topic: None,
+            vrp_topic_binding: None,
+            required_capabilities_json: None,
+            required_roles_json: None,
+            agent_min_alignment: Some(AlignmentStatus::Aligned),
+            retention_days: None,
+            federation_scope: FederationScope::Local,
+        },
+    )
+    .unwrap();
+    for p in ["alice", "bob", "carol"] {
+        ensure_graph_node(&conn, 1, p, NodeType::Human, None).unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, active) VALUES (1, ?1, 'HUMAN', 1)",
+            [p],
+        )
+        .unwrap();
+        add_member(&conn, 1, "chan-1", p).unwrap();
+    }
+    drop(conn);
+
+    AppState {
+        pool,
+        merkle_tree: Arc::new(Mutex::new(annex_identity::MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: middleware::RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    }
+}
+
+async fn send(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    caller: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("X-Annex-Pseudonym", caller);
+    let body = match body {
+        Some(json) => {
+            builder = builder.header("content-type", "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    let mut req = builder.body(body).unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
+
+async fn visibility(app: &axum::Router, viewer: &str, target: &str) -> VisibilityLevel {
+    let (status, json) = send(
+        app,
+        "GET",
+        &format!("/api/graph/profile/{}", target),
+        viewer,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_value::<GraphProfile>(json)
+        .unwrap()
+        .visibility
+}
+
+#[tokio::test]
+async fn test_block_endpoints_sever_and_hide() {
+    let db = tempfile::NamedTempFile::new().unwrap();
+    let state = setup_state(&db);
+    state
+        .graph_index
+        .create_edge(
+            &state.pool.get().unwrap(),
+            1,
+            "alice",
+            "bob",
+            EdgeKind::Connected,
+            1.0,
+        )
+        .unwrap();
+    let mut presence_rx = state.presence_tx.subscribe();
+    let app = app(state);
+    assert_eq!(
+        visibility(&app, "bob", "alice").await,
+        VisibilityLevel::Degree1
+    );
+
+    let (status, _) = send(&app, "PUT", "/api/graph/blocks/bob", "alice", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(matches!(
+        presence_rx.try_recv(),
+        Ok(PresenceEvent::EdgeRemoved {
+            kind: EdgeKind::Connected,
+            ..
+        })
+    ));
+    let (_, listed) = send(&app, "GET", "/api/graph/blocks", "alice", None).await;
+    assert_eq!(listed["blocked"], serde_json::json!(["bob"]));
+    assert_eq!(
+        visibility(&app, "bob", "alice").await,
+        VisibilityLevel::None
+    );
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/graph/connections/requests",
+        "bob",
+        Some(serde_json::json!({ "targetPseudonym": "alice" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(&app, "PUT", "/api/graph/blocks/alice", "alice", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(&app, "PUT", "/api/graph/blocks/nobody", "alice", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(&app, "DELETE", "/api/graph/blocks/bob", "alice", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, listed) = send(&app, "GET", "/api/graph/blocks", "alice", None).await;
+    assert!(listed["blocked"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_mutes_are_persisted_without_server_side_effects() {
+    let db = tempfile::NamedTempFile::new().unwrap();
+    let app = app(setup_state(&db));
+
+    let (status, _) = send(&app, "PUT", "/api/graph/mutes/bob", "alice", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, listed) = send(&app, "GET", "/api/graph/mutes", "alice", None).await;
+    assert_eq!(listed["muted"], serde_json::json!(["bob"]));
+    let (_, listed) = send(&app, "GET", "/api/graph/blocks", "alice", None).await;
+    assert!(listed["blocked"].as_array().unwrap().is_empty());
+
+    let (status, _) = send(&app, "DELETE", "/api/graph/mutes/bob", "alice", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, listed) = send(&app, "GET", "/api/graph/mutes", "alice", None).await;
+    assert!(listed["muted"].as_array().unwrap().is_empty());
+}
+
+type WsStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn connect(addr: SocketAddr, pseudonym: &str) -> WsStream {
+    let (mut ws, _) = connect_async(format!("ws://{}/ws?pseudonym={}", addr, pseudonym))
+        .await
+        .expect("failed to connect");
+    ws.send(Message::Text(
+        serde_json::json!({ "type": "subscribe", "channelId": "chan-1" })
+            .to_string()
+            .into(),
+    ))
+    .await
+    .unwrap();
+    ws
+}
+
+async fn say(ws: &mut WsStream, content: &str) {
+    ws.send(Message::Text(
+        serde_json::json!({
+            "type": "message",
+            "channelId": "chan-1",
+            "content": content,
+            "replyTo": null
+        })
+        .to_string()
+        .into(),
+    ))
+    .await
+    .unwrap();
+}
+
+/// Returns the content of the next chat message received.
+async fn next_message(ws: &mut WsStream) -> String {
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("no message received")
+            .expect("connection closed")
+            .unwrap();
+        if let Message::Text(text) = frame {
+            let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if value["type"] == "message" {
+                return value["content"].as_str().unwrap().to_string();
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_blocked_sender_is_kept_out_of_blocker_stream() {
+    let db = tempfile::NamedTempFile::new().unwrap();
+    let state = setup_state(&db);
+    let pool: DbPool = state.pool.clone();
+    annex_graph::block_participant(
+        &mut pool.get().unwrap(),
+        &state.graph_index,
+        1,
+        "alice",
+        "bob",
+    )
+    .unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = app(state);
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+
+    let mut alice = connect(addr, "alice").await;
+    let mut bob = connect(addr, "bob").await;
+    let mut carol = connect(addr, "carol").await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    say(&mut bob, "from bob").await;
+    assert_eq!(next_message(&mut carol).await, "from bob");
+    assert_eq!(next_message(&mut bob).await, "from bob");
+
+    // alice's next message is carol's: bob's was never delivered to her.
+    say(&mut carol, "from carol").await;
+    assert_eq!(next_message(&mut alice).await, "from carol");
+    // The block is one-way.
+    assert_eq!(next_message(&mut bob).await, "from carol");
+}
@@ -0,0 +1,282 @@
+use annex_db::{create_pool, DbRuntimeSettings};
+use annex_graph::{ensure_graph_node, GraphProfile};
+use annex_server::{app, middleware, AppState};
+use annex_types::{EdgeKind, NodeType, PresenceEvent, ServerPolicy, VisibilityLevel};
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+use tower::ServiceExt;
+
+struct TestApp {
+    app: axum::Router,
+    presence_rx: broadcast::Receiver<PresenceEvent>,
+    _db: tempfile::NamedTempFile,
+}
+
+fn setup_app(policy: ServerPolicy) -> TestApp {
+    let db = tempfile::NamedTempFile::new().unwrap();
+    let pool = create_pool(db.path().to_str().unwrap(), DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'default', 'Default Server', '{}')",
+        [],
+    )
+    .unwrap();
+    for p in ["alice", "bob", "carol"] {
+        ensure_graph_node(&conn, 1, p, NodeType::Human, None).unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, active) VALUES (1, ?1, 'HUMAN', 1)",
+            [p],
+        )
+        .unwrap();
+    }
+    drop(conn);
+
+    let presence_tx = broadcast::channel(100).0;
+    let presence_rx = presence_tx.subscribe();
+    let state = AppState {
+        pool,
+        merkle_tree: Arc::new(Mutex::new(annex_identity::MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(policy)),
+        rate_limiter: middleware::RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    TestApp {
+        app: app(state),
+        presence_rx,
+        _db: db,
+    }
+}
+
+async fn send(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    caller: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("X-Annex-Pseudonym", caller);
+    let body = match body {
+        Some(json) => {
+            builder = builder.header("content-type", "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    let mut req = builder.body(body).unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
+
+async fn visibility(app: &axum::Router, viewer: &str, target: &str) -> VisibilityLevel {
+    let (status, json) = send(
+        app,
+        "GET",
+        &format!("/api/graph/profile/{}", target),
+        viewer,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_value::<GraphProfile>(json)
+        .unwrap()
+        .visibility
+}
+
+#[tokio::test]
+async fn test_connection_request_lifecycle() {
+    let TestApp {
+        app,
+        mut presence_rx,
+        _db,
+    } = setup_app(ServerPolicy::default());
+
+    assert_eq!(
+        visibility(&app, "alice", "bob").await,
+        VisibilityLevel::None
+    );
+
+    let (status, request) = send(
+        &app,
+        "POST",
+        "/api/graph/connections/requests",
+        "alice",
+        Some(serde_json::json!({ "targetPseudonym": "bob" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(request["status"], "PENDING");
+    let request_id = request["id"].as_i64().unwrap();
+
+    // Only the two parties can see or act on the request.
+    let (_, listed) = send(&app, "GET", "/api/graph/connections/requests", "bob", None).await;
+    assert_eq!(listed["requests"].as_array().unwrap().len(), 1);
+    let (_, listed) = send(
+        &app,
+        "GET",
+        "/api/graph/connections/requests",
+        "carol",
+        None,
+    )
+    .await;
+    assert!(listed["requests"].as_array().unwrap().is_empty());
+    let accept_uri = format!("/api/graph/connections/requests/{}/accept", request_id);
+    let (status, _) = send(&app, "POST", &accept_uri, "carol", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, accepted) = send(&app, "POST", &accept_uri, "bob", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(accepted["status"], "ACCEPTED");
+    for _ in 0..2 {
+        assert!(matches!(
+            presence_rx.try_recv(),
+            Ok(PresenceEvent::EdgeAdded {
+                kind: EdgeKind::Connected,
+                ..
+            })
+        ));
+    }
+    assert_eq!(
+        visibility(&app, "alice", "bob").await,
+        VisibilityLevel::Degree1
+    );
+
+    let (status, _) = send(&app, "POST", &accept_uri, "bob", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = send(&app, "DELETE", "/api/graph/connections/alice", "bob", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    for _ in 0..2 {
+        assert!(matches!(
+            presence_rx.try_recv(),
+            Ok(PresenceEvent::EdgeRemoved {
+                kind: EdgeKind::Connected,
+                ..
+            })
+        ));
+    }
+    assert_eq!(
+        visibility(&app, "alice", "bob").await,
+        VisibilityLevel::None
+    );
+
+    let (status, _) = send(&app, "DELETE", "/api/graph/connections/alice", "bob", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_connection_request_decline() {
+    let TestApp { app, _db, .. } = setup_app(ServerPolicy::default());
+
+    let (_, request) = send(
+        &app,
+        "POST",
+        "/api/graph/connections/requests",
+        "alice",
+        Some(serde_json::json!({ "targetPseudonym": "carol" })),
+    )
+    .await;
+    let decline_uri = format!(
+        "/api/graph/connections/requests/{}/decline",
+        request["id"].as_i64().unwrap()
+    );
+
+    let (status, declined) = send(&app, "POST", &decline_uri, "carol", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(declined["status"], "DECLINED");
+    assert_eq!(
+        visibility(&app, "alice", "carol").await,
+        VisibilityLevel::None
+    );
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/graph/connections/requests",
+        "alice",
+        Some(serde_json::json!({ "targetPseudonym": "alice" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_connection_requests_are_rate_limited() {
+    let mut policy = ServerPolicy::default();
+    policy.rate_limit.connection_request_limit = 2;
+    let TestApp { app, _db, .. } = setup_app(policy);
+
+    let mut statuses = Vec::new();
+    for target in ["bob", "carol", "bob"] {
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/graph/connections/requests",
+            "alice",
+            Some(serde_json::json!({ "targetPseudonym": target })),
+        )
+        .await;
+        statuses.push(status);
+    }
+    assert_eq!(
+        statuses,
+        vec![
+            StatusCode::CREATED,
+            StatusCode::CREATED,
+            StatusCode::TOO_MANY_REQUESTS
+        ]
+    );
+
+    // Listing uses the default budget.
+    let (status, _) = send(
+        &app,
+        "GET",
+        "/api/graph/connections/requests",
+        "alice",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,175 @@
+use annex_db::{create_pool, DbRuntimeSettings};
+use annex_graph::{create_edge, ensure_graph_node};
+use annex_server::{app, middleware, AppState};
+use annex_types::{EdgeKind, NodeType, ServerPolicy};
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+use tower::ServiceExt;
+
+fn setup_app() -> (axum::Router, tempfile::NamedTempFile) {
+    let db = tempfile::NamedTempFile::new().unwrap();
+    let pool = create_pool(db.path().to_str().unwrap(), DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'default', 'Default Server', '{}')",
+        [],
+    )
+    .unwrap();
+    for (p, can_moderate) in [("mod", 1), ("alice", 0), ("bob", 0), ("carol", 0)] {
+        ensure_graph_node(&conn, 1, p, NodeType::Human, None).unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, can_moderate, active) VALUES (1, ?1, 'HUMAN', ?2, 1)",
+            rusqlite::params![p, can_moderate],
+        )
+        .unwrap();
+    }
+    ensure_graph_node(&conn, 1, "bot", NodeType::AiAgent, None).unwrap();
+    // alice - bob - carol, bot - alice; mod is isolated.
+    create_edge(&conn, 1, "alice", "bob", EdgeKind::Connected, 1.0).unwrap();
+    create_edge(&conn, 1, "bob", "carol", EdgeKind::Connected, 1.0).unwrap();
+    create_edge(&conn, 1, "bot", "alice", EdgeKind::AgentServing, 1.0).unwrap();
+    drop(conn);
+
+    let state = AppState {
+        pool,
+        merkle_tree: Arc::new(Mutex::new(annex_identity::MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: middleware::RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    (app(state), db)
+}
+
+async fn export(app: &axum::Router, pseudonym: &str, query: &str) -> (StatusCode, String, String) {
+    let mut req = Request::builder()
+        .uri(format!("/api/admin/graph/export?{query}"))
+        .header("X-Annex-Pseudonym", pseudonym)
+        .body(Body::empty())
+        .unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .map(|v| v.to_str().unwrap().to_string())
+        .unwrap_or_default();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        content_type,
+        String::from_utf8(bytes.to_vec()).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_export_requires_moderator() {
+    let (app, _db) = setup_app();
+    let (status, _, _) = export(&app, "alice", "format=graphml").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_export_formats() {
+    let (app, _db) = setup_app();
+
+    let (status, content_type, body) = export(&app, "mod", "format=graphml").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type, "application/graphml+xml");
+    assert_eq!(body.matches("<node ").count(), 5);
+    assert_eq!(body.matches("<edge ").count(), 3);
+
+    let (status, content_type, body) = export(&app, "mod", "format=dot").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type, "text/vnd.graphviz");
+    assert!(body.starts_with("digraph annex {"));
+    assert!(body.contains("\"alice\" -> \"bob\""));
+
+    let (status, content_type, body) = export(&app, "mod", "format=jsonld").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type, "application/ld+json");
+    let document: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(document["@graph"].as_array().unwrap().len(), 8);
+
+    let (status, _, _) = export(&app, "mod", "format=csv").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_export_filters_and_neighborhood() {
+    let (app, _db) = setup_app();
+
+    let (_, _, body) = export(
+        &app,
+        "mod",
+        "format=dot&nodeTypes=AI_AGENT,HUMAN&edgeKinds=AGENT_SERVING",
+    )
+    .await;
+    assert_eq!(body.matches(" -> ").count(), 1);
+    assert!(body.contains("\"bot\" -> \"alice\""));
+
+    let (_, _, body) = export(&app, "mod", "format=dot&center=carol&hops=1").await;
+    assert!(body.contains("\"carol\" ["));
+    assert!(body.contains("\"bob\" ["));
+    assert!(!body.contains("\"alice\" ["));
+    assert_eq!(body.matches(" -> ").count(), 1);
+
+    let (status, _, _) = export(&app, "mod", "format=dot&nodeTypes=ALIEN").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _, _) = export(&app, "mod", "format=dot&center=nobody").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = export(&app, "mod", "format=dot&hops=2").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_pseudonymized_export() {
+    let (app, _db) = setup_app();
+
+    let (_, _, salted) = export(&app, "mod", "format=dot&pseudonymize=true&salt=s1").await;
+    assert!(!salted.contains("alice"));
+    assert_eq!(salted.matches(" -> ").count(), 3);
+    let (_, _, again) = export(&app, "mod", "format=dot&pseudonymize=true&salt=s1").await;
+    assert_eq!(salted, again);
+
+    // Without a salt, each export gets a fresh one.
+    let (_, _, first) = export(&app, "mod", "format=dot&pseudonymize=true").await;
+    let (_, _, second) = export(&app, "mod", "format=dot&pseudonymize=true").await;
+    assert!(!first.contains("alice"));
+    assert_ne!(first, second);
+}
@@ -0,0 +1,162 @@
+use annex_db::{create_pool, DbRuntimeSettings};
+use annex_graph::{create_edge, ensure_graph_node};
+use annex_server::{app, middleware, AppState};
+use annex_types::{EdgeKind, NodeType, ServerPolicy};
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+use tower::ServiceExt;
+
+fn setup_app() -> (axum::Router, tempfile::NamedTempFile) {
+    let db = tempfile::NamedTempFile::new().unwrap();
+    let pool = create_pool(db.path().to_str().unwrap(), DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'default', 'Default Server', '{}')",
+        [],
+    )
+    .unwrap();
+    for (p, can_moderate) in [("mod", 1), ("alice", 0), ("bob", 0), ("carol", 0)] {
+        ensure_graph_node(&conn, 1, p, NodeType::Human, None).unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, can_moderate, active) VALUES (1, ?1, 'HUMAN', ?2, 1)",
+            rusqlite::params![p, can_moderate],
+        )
+        .unwrap();
+    }
+    create_edge(&conn, 1, "bob", "carol", EdgeKind::Connected, 1.0).unwrap();
+    // alice and bob were connected from 10 to 5 days ago.
+    for (change, days) in [("ADDED", 10), ("REMOVED", 5)] {
+        conn.execute(
+            "INSERT INTO graph_edge_history (server_id, from_node, to_node, kind, weight, change, changed_at)
+             VALUES (1, 'alice', 'bob', 'CONNECTED', 1.0, ?1, datetime('now', '-' || ?2 || ' days'))",
+            rusqlite::params![change, days],
+        )
+        .unwrap();
+    }
+    drop(conn);
+
+    let state = AppState {
+        pool,
+        merkle_tree: Arc::new(Mutex::new(annex_identity::MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: middleware::RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    (app(state), db)
+}
+
+async fn get(app: &axum::Router, pseudonym: &str, uri: &str) -> (StatusCode, serde_json::Value) {
+    let mut req = Request::builder()
+        .uri(uri)
+        .header("X-Annex-Pseudonym", pseudonym)
+        .body(Body::empty())
+        .unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+fn days_ago(days: i64) -> String {
+    (chrono::Utc::now() - chrono::Duration::days(days))
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_history_requires_moderator() {
+    let (app, _db) = setup_app();
+    let uri = format!("/api/admin/graph/history/snapshot?at={}", days_ago(1));
+    let (status, _) = get(&app, "alice", &uri).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = get(&app, "alice", "/api/admin/graph/history/changes").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_snapshot_reconstructs_deleted_edges() {
+    let (app, _db) = setup_app();
+
+    let uri = format!(
+        "/api/admin/graph/history/snapshot?at={}&pseudonymId=alice",
+        days_ago(7)
+    );
+    let (status, snapshot) = get(&app, "mod", &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let edges = snapshot["edges"].as_array().unwrap();
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0]["from_node"], "alice");
+    assert_eq!(edges[0]["to_node"], "bob");
+
+    let uri = format!("/api/admin/graph/history/snapshot?at={}", days_ago(0));
+    let (_, snapshot) = get(&app, "mod", &uri).await;
+    let edges = snapshot["edges"].as_array().unwrap();
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0]["from_node"], "bob");
+    assert_eq!(snapshot["nodes"].as_array().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_history_respects_retention() {
+    let (app, _db) = setup_app();
+
+    let uri = format!("/api/admin/graph/history/snapshot?at={}", days_ago(200));
+    let (status, _) = get(&app, "mod", &uri).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = get(
+        &app,
+        "mod",
+        "/api/admin/graph/history/snapshot?at=yesterday",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, changes) = get(
+        &app,
+        "mod",
+        "/api/admin/graph/history/changes?pseudonymId=alice",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let edges = changes["edges"].as_array().unwrap();
+    assert_eq!(edges.len(), 2);
+    assert_eq!(edges[0]["change"], "ADDED");
+    assert_eq!(edges[1]["change"], "REMOVED");
+    assert_eq!(changes["nodes"].as_array().unwrap().len(), 1);
+}
@@ -0,0 +1,128 @@
+use annex_db::{create_pool, DbRuntimeSettings};
+use annex_graph::{create_edge, ensure_graph_node, GraphAnalyticsRelease};
+use annex_server::{app, middleware, AppState};
+use annex_types::{EdgeKind, GraphAnalyticsConfig, NodeType, ServerPolicy};
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+use tower::ServiceExt;
+
+fn setup_app(policy: ServerPolicy) -> (axum::Router, tempfile::NamedTempFile) {
+    let db = tempfile::NamedTempFile::new().unwrap();
+    let pool = create_pool(db.path().to_str().unwrap(), DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'default', 'Default Server', '{}')",
+        [],
+    )
+    .unwrap();
+    for p in ["alice", "bob", "carol", "dave"] {
+        ensure_graph_node(&conn, 1, p, NodeType::Human, None).unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, active) VALUES (1, ?1, 'HUMAN', 1)",
+            [p],
+        )
+        .unwrap();
+    }
+    // alice - bob - carol; dave is isolated.
+    create_edge(&conn, 1, "alice", "bob", EdgeKind::Connected, 1.0).unwrap();
+    create_edge(&conn, 1, "bob", "carol", EdgeKind::Connected, 1.0).unwrap();
+    drop(conn);
+
+    let state = AppState {
+        pool,
+        merkle_tree: Arc::new(Mutex::new(annex_identity::MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(policy)),
+        rate_limiter: middleware::RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    (app(state), db)
+}
+
+async fn get_stats(app: &axum::Router) -> GraphAnalyticsRelease {
+    let mut req = Request::builder()
+        .uri("/api/public/graph/stats")
+        .body(Body::empty())
+        .unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn test_graph_stats_are_public_and_stable_within_interval() {
+    let (app, _db) = setup_app(ServerPolicy::default());
+
+    let first = get_stats(&app).await;
+    assert_eq!(first.epsilon, 1.0);
+    assert_eq!(first.nodes_by_type.len(), 5);
+    assert_eq!(first.degree_distribution.len(), 6);
+    assert_eq!(first.activity.len(), 5);
+
+    // Repeated queries get the same noise rather than fresh samples.
+    assert_eq!(get_stats(&app).await, first);
+}
+
+#[tokio::test]
+async fn test_graph_stats_with_large_budget_track_exact_counts() {
+    let policy = ServerPolicy {
+        graph_analytics: GraphAnalyticsConfig {
+            epsilon: 6_000.0,
+            epsilon_budget: 6_000.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (app, _db) = setup_app(policy);
+
+    let release = get_stats(&app).await;
+    assert_eq!(release.nodes_by_type["HUMAN"], 4);
+    assert_eq!(release.component_count, 2);
+    let degree = |label: &str| {
+        release
+            .degree_distribution
+            .iter()
+            .find(|b| b.label == label)
+            .unwrap()
+            .count
+    };
+    assert_eq!(degree("0"), 1.0);
+    assert_eq!(degree("1"), 2.0);
+    assert_eq!(degree("2"), 1.0);
+}
@@ -0,0 +1,273 @@
+use annex_db::{create_pool, DbRuntimeSettings};
+use annex_graph::{create_edge, ensure_graph_node, GraphProfile};
+use annex_server::{app, middleware, AppState};
+use annex_types::{EdgeKind, NodeType, PresenceEvent, ServerPolicy, VisibilityLevel};
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use futures_util::StreamExt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tower::ServiceExt;
+
+struct TestApp {
+    app: axum::Router,
+    presence_tx: broadcast::Sender<PresenceEvent>,
+    _db: tempfile::NamedTempFile,
+}
+
+fn setup_app() -> TestApp {
+    let db = tempfile::NamedTempFile::new().unwrap();
+    let pool = create_pool(db.path().to_str().unwrap(), DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'default', 'Default Server', '{}')",
+        [],
+    )
+    .unwrap();
+    for p in ["alice", "bob", "carol", "dave"] {
+        ensure_graph_node(&conn, 1, p, NodeType::Human, None).unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, active) VALUES (1, ?1, 'HUMAN', 1)",
+            [p],
+        )
+        .unwrap();
+    }
+    // alice - bob - carol
+    create_edge(&conn, 1, "alice", "bob", EdgeKind::Connected, 1.0).unwrap();
+    create_edge(&conn, 1, "bob", "carol", EdgeKind::Connected, 1.0).unwrap();
+    drop(conn);
+
+    let presence_tx = broadcast::channel(100).0;
+    let state = AppState {
+        pool,
+        merkle_tree: Arc::new(Mutex::new(annex_identity::MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: middleware::RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: presence_tx.clone(),
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    TestApp {
+        app: app(state),
+        presence_tx,
+        _db: db,
+    }
+}
+
+async fn send(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    caller: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("X-Annex-Pseudonym", caller);
+    let body = match body {
+        Some(json) => {
+            builder = builder.header("content-type", "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    let mut req = builder.body(body).unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
+
+async fn profile(app: &axum::Router, viewer: &str, target: &str) -> GraphProfile {
+    let (status, json) = send(
+        app,
+        "GET",
+        &format!("/api/graph/profile/{}", target),
+        viewer,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_value(json).unwrap()
+}
+
+async fn set_preferences(app: &axum::Router, caller: &str, preferences: serde_json::Value) {
+    let (status, _) = send(
+        app,
+        "PUT",
+        "/api/graph/visibility",
+        caller,
+        Some(preferences),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_visibility_preferences_round_trip() {
+    let TestApp { app, _db, .. } = setup_app();
+
+    let (status, defaults) = send(&app, "GET", "/api/graph/visibility", "carol", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(defaults["private"], false);
+    assert_eq!(defaults["metadata_max_degree"], 0);
+
+    set_preferences(
+        &app,
+        "carol",
+        serde_json::json!({ "hide_last_seen": true, "deny": ["alice"] }),
+    )
+    .await;
+    let (_, stored) = send(&app, "GET", "/api/graph/visibility", "carol", None).await;
+    assert_eq!(stored["hide_last_seen"], true);
+    assert_eq!(stored["deny"], serde_json::json!(["alice"]));
+
+    let (status, _) = send(
+        &app,
+        "PUT",
+        "/api/graph/visibility",
+        "carol",
+        Some(serde_json::json!({ "allow": ["bob"], "deny": ["bob"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(
+        &app,
+        "PUT",
+        "/api/graph/visibility",
+        "carol",
+        Some(serde_json::json!({ "metadata_max_degree": 9 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_profile_honors_visibility_preferences() {
+    let TestApp { app, _db, .. } = setup_app();
+
+    let direct = profile(&app, "bob", "carol").await;
+    assert_eq!(direct.visibility, VisibilityLevel::Degree1);
+    assert!(direct.last_seen_at.is_some());
+
+    set_preferences(&app, "carol", serde_json::json!({ "hide_last_seen": true })).await;
+    let direct = profile(&app, "bob", "carol").await;
+    assert_eq!(direct.visibility, VisibilityLevel::Degree1);
+    assert!(direct.last_seen_at.is_none());
+    assert!(profile(&app, "carol", "carol").await.last_seen_at.is_some());
+
+    set_preferences(
+        &app,
+        "carol",
+        serde_json::json!({ "private": true, "allow": ["dave"], "deny": ["alice"] }),
+    )
+    .await;
+    assert_eq!(
+        profile(&app, "bob", "carol").await.visibility,
+        VisibilityLevel::AggregateOnly
+    );
+    assert_eq!(
+        profile(&app, "alice", "carol").await.visibility,
+        VisibilityLevel::None
+    );
+    let allowed = profile(&app, "dave", "carol").await;
+    assert_eq!(allowed.visibility, VisibilityLevel::Degree1);
+    assert!(allowed.last_seen_at.is_some());
+}
+
+#[tokio::test]
+async fn test_presence_stream_withholds_hidden_participants() {
+    let TestApp {
+        app,
+        presence_tx,
+        _db,
+    } = setup_app();
+    set_preferences(&app, "carol", serde_json::json!({ "private": true })).await;
+    set_preferences(&app, "dave", serde_json::json!({ "hide_last_seen": true })).await;
+
+    let mut req = Request::builder()
+        .uri("/events/presence")
+        .body(Body::empty())
+        .unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let mut body = resp.into_body().into_data_stream();
+
+    let updated = |pseudonym_id: &str| PresenceEvent::NodeUpdated {
+        pseudonym_id: pseudonym_id.to_string(),
+        active: true,
+    };
+    presence_tx.send(updated("carol")).unwrap();
+    presence_tx.send(updated("dave")).unwrap();
+    presence_tx
+        .send(PresenceEvent::EdgeAdded {
+            from_node: "bob".to_string(),
+            to_node: "carol".to_string(),
+            kind: EdgeKind::Connected,
+        })
+        .unwrap();
+    // dave's edges are still public; only their activity is hidden.
+    presence_tx
+        .send(PresenceEvent::EdgeAdded {
+            from_node: "alice".to_string(),
+            to_node: "dave".to_string(),
+            kind: EdgeKind::Connected,
+        })
+        .unwrap();
+    presence_tx.send(updated("bob")).unwrap();
+
+    let mut received = String::new();
+    while !received.contains("\"bob\",\"active\"") {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+            .await
+            .expect("presence stream stalled")
+            .expect("presence stream closed")
+            .unwrap();
+        received.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+
+    assert!(
+        !received.contains("carol"),
+        "private node leaked: {received}"
+    );
+    assert!(!received.contains("\"dave\",\"active\""));
+    assert!(received.contains("\"to_node\":\"dave\""));
+}
@@ -0,0 +1,416 @@
+use annex_db::{create_pool, run_migrations, DbRuntimeSettings};
+use annex_federation::SignedIdentityBundle;
+use annex_identity::MerkleTree;
+use annex_server::{
+    api_identity_bundle::identity_bundle_signing_payload, app, middleware::RateLimiter, AppState,
+};
+use annex_types::ServerPolicy;
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use ed25519_dalek::{Signature, SigningKey, Verifier};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tower::ServiceExt;
+
+const ORIGIN_URL: &str = "http://origin.example";
+const TARGET_URL: &str = "http://target.example";
+const COMMITMENT: &str = "0000000000000000000000000000000000000000000000000000000000000abc";
+
+async fn setup_app(public_url: &str, signing_key: SigningKey) -> (axum::Router, annex_db::DbPool) {
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    let policy = ServerPolicy {
+        usernames_enabled: true,
+        ..ServerPolicy::default()
+    };
+    {
+        let conn = pool.get().unwrap();
+        run_migrations(&conn).unwrap();
+        let policy_json = serde_json::to_string(&policy).unwrap();
+        conn.execute(
+            "INSERT INTO servers (slug, label, policy_json) VALUES ('test', 'Test', ?1)",
+            [policy_json],
+        )
+        .unwrap();
+    }
+
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(signing_key),
+        public_url: Arc::new(RwLock::new(public_url.to_string())),
+        policy: Arc::new(RwLock::new(policy)),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    (app(state), pool)
+}
+
+async fn send(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    pseudonym: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("content-type", "application/json");
+    if let Some(p) = pseudonym {
+        builder = builder.header("X-Annex-Pseudonym", p);
+    }
+    let mut request = builder
+        .body(match body {
+            Some(b) => Body::from(b.to_string()),
+            None => Body::empty(),
+        })
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, value)
+}
+
+/// Registers `COMMITMENT` on the origin server with a derived pseudonym and a
+/// username, then exports it for `TARGET_URL`.
+async fn export_from_origin(origin_key: &SigningKey) -> SignedIdentityBundle {
+    export_from_origin_to(origin_key, TARGET_URL).await
+}
+
+async fn export_from_origin_to(origin_key: &SigningKey, audience: &str) -> SignedIdentityBundle {
+    let (origin, pool) = setup_app(ORIGIN_URL, origin_key.clone()).await;
+
+    let (status, _) = send(
+        &origin,
+        "POST",
+        "/api/registry/register",
+        None,
+        Some(json!({ "commitmentHex": COMMITMENT, "roleCode": 1, "nodeId": 7 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO zk_nullifiers (topic, nullifier_hex, pseudonym_id, commitment_hex)
+             VALUES ('annex:server:v1', 'n1', 'alice', ?1)",
+            [COMMITMENT],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, active)
+             VALUES (1, 'alice', 'HUMAN', 1)",
+            [],
+        )
+        .unwrap();
+    }
+
+    let (status, _) = send(
+        &origin,
+        "PUT",
+        "/api/profile/username",
+        Some("alice"),
+        Some(json!({ "username": "Alice" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(
+        &origin,
+        "GET",
+        &format!("/api/registry/export?audience={}", audience),
+        Some("alice"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_value(body).unwrap()
+}
+
+/// Sets up the target server with the origin registered as a known peer.
+async fn setup_target(origin_key: &SigningKey) -> (axum::Router, annex_db::DbPool) {
+    let (target, pool) = setup_app(TARGET_URL, SigningKey::generate(&mut rand::rngs::OsRng)).await;
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO instances (base_url, public_key, label, status) VALUES (?1, ?2, 'Origin', 'ACTIVE')",
+            [ORIGIN_URL.to_string(), hex::encode(origin_key.verifying_key().as_bytes())],
+        )
+        .unwrap();
+    }
+    (target, pool)
+}
+
+#[tokio::test]
+async fn test_export_bundle_is_signed_by_origin() {
+    let origin_key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let signed = export_from_origin(&origin_key).await;
+
+    assert_eq!(signed.bundle.origin_server, ORIGIN_URL);
+    assert_eq!(signed.bundle.audience, TARGET_URL);
+    assert_eq!(signed.bundle.nonce.len(), 32);
+    assert_eq!(signed.bundle.commitment, COMMITMENT);
+    assert_eq!(signed.bundle.role_code, 1);
+    assert_eq!(signed.bundle.node_id, 7);
+    assert_eq!(signed.bundle.topics, vec!["annex:server:v1".to_string()]);
+    assert_eq!(signed.bundle.usernames.len(), 1);
+    assert_eq!(signed.bundle.usernames[0].username, "Alice");
+
+    let payload = identity_bundle_signing_payload(&signed.bundle).unwrap();
+    let signature_bytes: [u8; 64] = hex::decode(&signed.signature).unwrap().try_into().unwrap();
+    origin_key
+        .verifying_key()
+        .verify(payload.as_bytes(), &Signature::from_bytes(&signature_bytes))
+        .expect("bundle signature should verify with the origin key");
+}
+
+#[tokio::test]
+async fn test_export_requires_local_commitment() {
+    let (app, pool) = setup_app(ORIGIN_URL, SigningKey::generate(&mut rand::rngs::OsRng)).await;
+    pool.get()
+        .unwrap()
+        .execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, active)
+             VALUES (1, 'bob', 'HUMAN', 1)",
+            [],
+        )
+        .unwrap();
+
+    let (status, _) = send(
+        &app,
+        "GET",
+        &format!("/api/registry/export?audience={}", TARGET_URL),
+        Some("bob"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The target server must be named.
+    let (status, _) = send(&app, "GET", "/api/registry/export", Some("bob"), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_import_registers_identity_with_provenance() {
+    let origin_key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let signed = export_from_origin(&origin_key).await;
+    let (target, pool) = setup_target(&origin_key).await;
+
+    let (status, body) = send(
+        &target,
+        "POST",
+        "/api/registry/import",
+        None,
+        Some(serde_json::to_value(&signed).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["leafIndex"], 0);
+    assert!(body["rootHex"].is_string());
+
+    {
+        let conn = pool.get().unwrap();
+        let (origin, role_code): (String, u8) = conn
+            .query_row(
+                "SELECT ip.origin_server, vi.role_code
+                 FROM identity_provenance ip
+                 JOIN vrp_identities vi ON vi.commitment_hex = ip.commitment_hex
+                 WHERE ip.commitment_hex = ?1",
+                [COMMITMENT],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(origin, ORIGIN_URL);
+        assert_eq!(role_code, 1);
+    }
+
+    // Importing the same identity twice conflicts.
+    let (status, _) = send(
+        &target,
+        "POST",
+        "/api/registry/import",
+        None,
+        Some(serde_json::to_value(&signed).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_import_rejects_tampered_or_unknown_bundles() {
+    let origin_key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let signed = export_from_origin(&origin_key).await;
+    let (target, pool) = setup_target(&origin_key).await;
+
+    // Escalating the role invalidates the signature.
+    let mut tampered = signed.clone();
+    tampered.bundle.role_code = 5;
+    let (status, _) = send(
+        &target,
+        "POST",
+        "/api/registry/import",
+        None,
+        Some(serde_json::to_value(&tampered).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A bundle re-signed by an unrelated key does not match the origin's key.
+    let forger = SigningKey::generate(&mut rand::rngs::OsRng);
+    let mut forged = signed.clone();
+    forged.bundle.origin_public_key = hex::encode(forger.verifying_key().as_bytes());
+    let payload = identity_bundle_signing_payload(&forged.bundle).unwrap();
+    forged.signature =
+        hex::encode(ed25519_dalek::Signer::sign(&forger, payload.as_bytes()).to_bytes());
+    let (status, _) = send(
+        &target,
+        "POST",
+        "/api/registry/import",
+        None,
+        Some(serde_json::to_value(&forged).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Unknown origin server.
+    let mut unknown = signed.clone();
+    unknown.bundle.origin_server = "http://stranger.example".to_string();
+    let (status, _) = send(
+        &target,
+        "POST",
+        "/api/registry/import",
+        None,
+        Some(serde_json::to_value(&unknown).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let conn = pool.get().unwrap();
+    let imported: i64 = conn
+        .query_row("SELECT COUNT(*) FROM vrp_identities", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(imported, 0);
+}
+
+/// Re-signs `signed` with the origin key after it was modified.
+fn resign(signed: &mut SignedIdentityBundle, origin_key: &SigningKey) {
+    let payload = identity_bundle_signing_payload(&signed.bundle).unwrap();
+    signed.signature =
+        hex::encode(ed25519_dalek::Signer::sign(origin_key, payload.as_bytes()).to_bytes());
+}
+
+#[tokio::test]
+async fn test_import_rejects_bundles_for_other_servers_or_expired() {
+    let origin_key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let (target, pool) = setup_target(&origin_key).await;
+
+    // Addressed to a different server.
+    let elsewhere = export_from_origin_to(&origin_key, "http://elsewhere.example").await;
+    let (status, _) = send(
+        &target,
+        "POST",
+        "/api/registry/import",
+        None,
+        Some(serde_json::to_value(&elsewhere).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Issued too long ago.
+    let mut stale = export_from_origin(&origin_key).await;
+    stale.bundle.issued_at = (chrono::Utc::now() - chrono::Duration::hours(2)).to_rfc3339();
+    resign(&mut stale, &origin_key);
+    let (status, _) = send(
+        &target,
+        "POST",
+        "/api/registry/import",
+        None,
+        Some(serde_json::to_value(&stale).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let conn = pool.get().unwrap();
+    let imported: i64 = conn
+        .query_row("SELECT COUNT(*) FROM vrp_identities", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(imported, 0);
+}
+
+#[tokio::test]
+async fn test_import_refuses_reused_bundle_nonce() {
+    let origin_key = SigningKey::generate(&mut rand::rngs::OsRng);
+    let signed = export_from_origin(&origin_key).await;
+    let (target, pool) = setup_target(&origin_key).await;
+
+    let (status, _) = send(
+        &target,
+        "POST",
+        "/api/registry/import",
+        None,
+        Some(serde_json::to_value(&signed).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Another commitment under the same nonce is still a replay.
+    let mut reused = signed.clone();
+    reused.bundle.commitment =
+        "0000000000000000000000000000000000000000000000000000000000000def".to_string();
+    resign(&mut reused, &origin_key);
+    let (status, body) = send(
+        &target,
+        "POST",
+        "/api/registry/import",
+        None,
+        Some(serde_json::to_value(&reused).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(body["error"]
+        .as_str()
+        .unwrap()
+        .contains("already been imported"));
+
+    let conn = pool.get().unwrap();
+    let imported: i64 = conn
+        .query_row("SELECT COUNT(*) FROM vrp_identities", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(imported, 1);
+}
@@ -44,6 +44,8 @@ async fn test_get_identity_endpoints() {
         pool: pool.clone(),
         merkle_tree: Arc::new(Mutex::new(tree)),
         membership_vkey: Arc::new(vk),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
         server_id: 1,
         signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
             &mut rand::rngs::OsRng,
@@ -64,8 +66,13 @@ async fn test_get_identity_endpoints() {
         observe_tx: tokio::sync::broadcast::channel(256).0,
         upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
         preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
         cors_origins: vec![],
         enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
         ws_token_secret: std::sync::Arc::new([0u8; 32]),
     };
     let app = app(state);
@@ -96,10 +103,12 @@ async fn test_get_identity_endpoints() {
     let body_bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
         .await
         .unwrap();
-    let identity: GetIdentityResponse = serde_json::from_slice(&body_bytes).unwrap();
+    let raw: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+    // The role is only ever disclosed through role proofs.
+    assert!(raw.get("participantType").is_none());
+    let identity: GetIdentityResponse = serde_json::from_value(raw).unwrap();
 
     assert_eq!(identity.pseudonym_id, pseudonym_id);
-    assert_eq!(identity.participant_type, role);
     assert!(identity.active);
     assert!(identity.capabilities.can_voice);
     assert!(identity.capabilities.can_moderate);
@@ -0,0 +1,428 @@
+use annex_db::{create_pool, run_migrations, DbRuntimeSettings};
+use annex_identity::vote::{field_to_hex, poll_topic_hash, poll_vote_hash};
+use annex_identity::zk::parse_fr_from_hex;
+use annex_identity::MerkleTree;
+use annex_server::{app, middleware::RateLimiter, AppState};
+use annex_types::ServerPolicy;
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tower::ServiceExt;
+
+/// A snarkjs-format proof built from the curve generators. It parses and
+/// passes point validation but never verifies.
+fn well_formed_proof() -> Value {
+    json!({
+        "pi_a": ["1", "2", "1"],
+        "pi_b": [
+            [
+                "10857046999023057135944570762232829481370756359578518086990519993285655852781",
+                "11559732032986387107991004021392285783925812861821192530917403151452391805634"
+            ],
+            [
+                "8495653923123431417604973247489272438418190587263600148770280649306958101930",
+                "4082367875863433681332203403145435568316851327593401208105741076214120093531"
+            ],
+            ["1", "0"]
+        ],
+        "pi_c": ["1", "2", "1"],
+        "protocol": "groth16",
+        "curve": "bn128"
+    })
+}
+
+/// Identity commitments behind the test pseudonyms.
+const AUTHOR_COMMITMENT: &str = "00000000000000000000000000000000000000000000000000000000000000aa";
+const OTHER_COMMITMENT: &str = "00000000000000000000000000000000000000000000000000000000000000bb";
+const OUTSIDER_COMMITMENT: &str =
+    "00000000000000000000000000000000000000000000000000000000000000cc";
+
+async fn setup_app() -> (axum::Router, annex_db::DbPool) {
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    {
+        let conn = pool.get().unwrap();
+        run_migrations(&conn).unwrap();
+        let policy_json = serde_json::to_string(&ServerPolicy::default()).unwrap();
+        conn.execute(
+            "INSERT INTO servers (slug, label, policy_json) VALUES ('test', 'Test', ?1)",
+            [policy_json],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO channels (server_id, channel_id, name, channel_type, federation_scope)
+             VALUES (1, 'chan-1', 'General', '\"Text\"', '\"Local\"')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, can_moderate, active)
+             VALUES (1, 'author', 'HUMAN', 0, 1), (1, 'other', 'HUMAN', 0, 1), (1, 'outsider', 'HUMAN', 0, 1)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO channel_members (server_id, channel_id, pseudonym_id)
+             VALUES (1, 'chan-1', 'author'), (1, 'chan-1', 'other')",
+            [],
+        )
+        .unwrap();
+        for (pseudonym, commitment) in [
+            ("author", AUTHOR_COMMITMENT),
+            ("other", OTHER_COMMITMENT),
+            ("outsider", OUTSIDER_COMMITMENT),
+        ] {
+            conn.execute(
+                "INSERT INTO zk_nullifiers (topic, nullifier_hex, pseudonym_id, commitment_hex)
+                 VALUES ('annex:server:v1', ?1, ?1, ?2)",
+                [pseudonym, commitment],
+            )
+            .unwrap();
+        }
+    }
+
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    (app(state), pool)
+}
+
+async fn send(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    pseudonym: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("content-type", "application/json");
+    if let Some(p) = pseudonym {
+        builder = builder.header("X-Annex-Pseudonym", p);
+    }
+    let mut request = builder
+        .body(match body {
+            Some(b) => Body::from(b.to_string()),
+            None => Body::empty(),
+        })
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, value)
+}
+
+async fn create_test_poll(app: &axum::Router) -> String {
+    let (status, body) = send(
+        app,
+        "POST",
+        "/api/channels/chan-1/polls",
+        Some("author"),
+        Some(json!({ "question": "Lunch?", "options": ["Pizza", "Salad"], "duration_secs": 3600 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    // Open polls carry no tally.
+    assert!(body.get("counts").is_none());
+    assert!(body.get("total_votes").is_none());
+    body["poll_id"].as_str().unwrap().to_string()
+}
+
+/// Returns the poll's eligibility root as a decimal public signal.
+async fn eligibility_root_signal(app: &axum::Router, poll_id: &str) -> String {
+    let (status, body) = send(
+        app,
+        "GET",
+        &format!("/api/channels/chan-1/polls/{}/eligibility", poll_id),
+        Some("other"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    parse_fr_from_hex(body["root"].as_str().unwrap())
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_create_list_and_close_poll() {
+    let (app, _pool) = setup_app().await;
+    let poll_id = create_test_poll(&app).await;
+
+    let (status, body) = send(
+        &app,
+        "GET",
+        "/api/channels/chan-1/polls",
+        Some("other"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["question"], "Lunch?");
+
+    // Non-members cannot see polls.
+    let (status, _) = send(
+        &app,
+        "GET",
+        "/api/channels/chan-1/polls",
+        Some("outsider"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Only the author (or a moderator) can close.
+    let close_uri = format!("/api/channels/chan-1/polls/{}/close", poll_id);
+    let (status, _) = send(&app, "POST", &close_uri, Some("other"), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send(&app, "POST", &close_uri, Some("author"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["closed_at"].is_string());
+    // The tally is released once the poll closes.
+    assert_eq!(body["counts"], json!([0, 0]));
+    assert_eq!(body["total_votes"], 0);
+}
+
+#[tokio::test]
+async fn test_create_poll_rejects_single_option() {
+    let (app, _pool) = setup_app().await;
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/channels/chan-1/polls",
+        Some("author"),
+        Some(json!({ "question": "Lunch?", "options": ["Pizza"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_vote_rejects_mismatched_signals() {
+    let (app, pool) = setup_app().await;
+    let poll_id = create_test_poll(&app).await;
+    let vote_uri = format!("/api/polls/{}/vote", poll_id);
+
+    let topic = poll_topic_hash(&poll_id).to_string();
+    let vote0 = poll_vote_hash(&poll_id, 0).to_string();
+
+    // Topic from another poll.
+    let other_topic = poll_topic_hash("another-poll").to_string();
+    let (status, _) = send(
+        &app,
+        "POST",
+        &vote_uri,
+        None,
+        Some(json!({
+            "optionIndex": 0,
+            "proof": well_formed_proof(),
+            "publicSignals": ["1", "2", other_topic, vote0],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Vote hash for option 0, but option 1 claimed.
+    let (status, _) = send(
+        &app,
+        "POST",
+        &vote_uri,
+        None,
+        Some(json!({
+            "optionIndex": 1,
+            "proof": well_formed_proof(),
+            "publicSignals": ["1", "2", topic, vote0],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A root that is not the poll's eligibility root.
+    let (status, _) = send(
+        &app,
+        "POST",
+        &vote_uri,
+        None,
+        Some(json!({
+            "optionIndex": 0,
+            "proof": well_formed_proof(),
+            "publicSignals": ["1", "2", topic, vote0],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Eligibility root, but the proof does not verify.
+    let root = eligibility_root_signal(&app, &poll_id).await;
+    let (status, _) = send(
+        &app,
+        "POST",
+        &vote_uri,
+        None,
+        Some(json!({
+            "optionIndex": 0,
+            "proof": well_formed_proof(),
+            "publicSignals": [root, "2", topic, vote0],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // No ballot or nullifier was recorded.
+    let conn = pool.get().unwrap();
+    let votes: i64 = conn
+        .query_row("SELECT COUNT(*) FROM poll_votes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(votes, 0);
+    let nullifiers: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM zk_nullifiers WHERE topic LIKE 'annex:poll:%'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(nullifiers, 0);
+}
+
+#[tokio::test]
+async fn test_vote_on_closed_poll_conflicts() {
+    let (app, _pool) = setup_app().await;
+    let poll_id = create_test_poll(&app).await;
+
+    let close_uri = format!("/api/channels/chan-1/polls/{}/close", poll_id);
+    let (status, _) = send(&app, "POST", &close_uri, Some("author"), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/polls/{}/vote", poll_id),
+        None,
+        Some(json!({
+            "optionIndex": 0,
+            "proof": well_formed_proof(),
+            "publicSignals": [],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_eligibility_snapshot_covers_channel_members() {
+    let (app, pool) = setup_app().await;
+    let poll_id = create_test_poll(&app).await;
+    let uri = format!("/api/channels/chan-1/polls/{}/eligibility", poll_id);
+
+    let (status, _) = send(&app, "GET", &uri, Some("outsider"), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send(&app, "GET", &uri, Some("other"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["leaves"], json!([AUTHOR_COMMITMENT, OTHER_COMMITMENT]));
+
+    let mut tree = MerkleTree::new(20).unwrap();
+    for leaf in [AUTHOR_COMMITMENT, OTHER_COMMITMENT] {
+        tree.insert(parse_fr_from_hex(leaf).unwrap()).unwrap();
+    }
+    assert_eq!(body["root"], tree.root_hex());
+
+    // Members who join later are not in the snapshot.
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO channel_members (server_id, channel_id, pseudonym_id)
+             VALUES (1, 'chan-1', 'outsider')",
+            [],
+        )
+        .unwrap();
+    }
+    let (status, body) = send(&app, "GET", &uri, Some("outsider"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["leaves"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_vote_rejects_registry_root() {
+    let (app, pool) = setup_app().await;
+    let poll_id = create_test_poll(&app).await;
+
+    // A root of the full identity registry proves only registration, not
+    // channel membership.
+    let registry_root = annex_identity::zk::parse_fr("1").unwrap();
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO vrp_roots (root_hex, active) VALUES (?1, 1)",
+            [field_to_hex(&registry_root)],
+        )
+        .unwrap();
+    }
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/api/polls/{}/vote", poll_id),
+        None,
+        Some(json!({
+            "optionIndex": 0,
+            "proof": well_formed_proof(),
+            "publicSignals": [
+                registry_root.to_string(),
+                "2",
+                poll_topic_hash(&poll_id).to_string(),
+                poll_vote_hash(&poll_id, 0).to_string(),
+            ],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let conn = pool.get().unwrap();
+    let votes: i64 = conn
+        .query_row("SELECT COUNT(*) FROM poll_votes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(votes, 0);
+}
@@ -0,0 +1,329 @@
+use annex_db::{create_pool, run_migrations, DbRuntimeSettings};
+use annex_identity::disclosure::{
+    channel_role_context, derive_role_nullifier, role_context, role_set_inputs,
+};
+use annex_identity::vote::field_to_hex;
+use annex_identity::MerkleTree;
+use annex_server::{app, middleware::RateLimiter, AppState};
+use annex_types::{RoleCode, ServerPolicy};
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use base64::Engine;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tower::ServiceExt;
+
+/// A snarkjs-format proof built from the curve generators. It parses and
+/// passes point validation but never verifies.
+fn well_formed_proof() -> Value {
+    json!({
+        "pi_a": ["1", "2", "1"],
+        "pi_b": [
+            [
+                "10857046999023057135944570762232829481370756359578518086990519993285655852781",
+                "11559732032986387107991004021392285783925812861821192530917403151452391805634"
+            ],
+            [
+                "8495653923123431417604973247489272438418190587263600148770280649306958101930",
+                "4082367875863433681332203403145435568316851327593401208105741076214120093531"
+            ],
+            ["1", "0"]
+        ],
+        "pi_c": ["1", "2", "1"],
+        "protocol": "groth16",
+        "curve": "bn128"
+    })
+}
+
+/// Secret key of the test identity.
+const ALICE_SK: u64 = 11;
+
+/// Builds a role disclosure payload for `roles` and `context`, proven
+/// against root `1` by the identity with secret key `sk`.
+fn role_proof(roles: &[RoleCode], context: annex_identity::zk::Fr, sk: u64) -> Value {
+    let nullifier = derive_role_nullifier(annex_identity::zk::Fr::from(sk), context).unwrap();
+    let mut signals = vec!["1".to_string(), nullifier.to_string()];
+    signals.extend(
+        role_set_inputs(roles)
+            .unwrap()
+            .iter()
+            .map(|f| f.to_string()),
+    );
+    signals.push(context.to_string());
+    json!({ "proof": well_formed_proof(), "publicSignals": signals })
+}
+
+async fn setup_app() -> (axum::Router, annex_db::DbPool) {
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    {
+        let conn = pool.get().unwrap();
+        run_migrations(&conn).unwrap();
+        let policy_json = serde_json::to_string(&ServerPolicy::default()).unwrap();
+        conn.execute(
+            "INSERT INTO servers (slug, label, policy_json) VALUES ('test', 'Test', ?1)",
+            [policy_json],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO channels (server_id, channel_id, name, channel_type, federation_scope, required_roles_json)
+             VALUES (1, 'humans-only', 'Humans', '\"Text\"', '\"Local\"', '[\"Human\"]')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, can_moderate, active)
+             VALUES (1, 'mod', 'HUMAN', 1, 1), (1, 'alice', 'HUMAN', 0, 1)",
+            [],
+        )
+        .unwrap();
+        let root_hex = field_to_hex(&annex_identity::zk::parse_fr("1").unwrap());
+        conn.execute(
+            "INSERT INTO vrp_roots (root_hex, active) VALUES (?1, 1)",
+            [root_hex],
+        )
+        .unwrap();
+    }
+
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    (app(state), pool)
+}
+
+async fn send(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    pseudonym: Option<&str>,
+    role_proof: Option<&Value>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("content-type", "application/json");
+    if let Some(p) = pseudonym {
+        builder = builder.header("X-Annex-Pseudonym", p);
+    }
+    if let Some(proof) = role_proof {
+        builder = builder.header(
+            "X-Annex-Role-Proof",
+            base64::engine::general_purpose::STANDARD.encode(proof.to_string()),
+        );
+    }
+    let mut request = builder
+        .body(match body {
+            Some(b) => Body::from(b.to_string()),
+            None => Body::empty(),
+        })
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, value)
+}
+
+#[tokio::test]
+async fn test_create_channel_rejects_invalid_required_roles() {
+    let (app, _pool) = setup_app().await;
+    for roles in ["[]", "not json", "[\"Wizard\"]"] {
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/channels",
+            Some("mod"),
+            None,
+            Some(json!({
+                "channel_id": "gated",
+                "name": "Gated",
+                "channel_type": "Text",
+                "federation_scope": "Local",
+                "required_roles_json": roles,
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "roles: {}", roles);
+    }
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/channels",
+        Some("mod"),
+        None,
+        Some(json!({
+            "channel_id": "gated",
+            "name": "Gated",
+            "channel_type": "Text",
+            "federation_scope": "Local",
+            "required_roles_json": "[\"Human\", \"Collective\"]",
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_join_role_gated_channel_requires_valid_proof() {
+    let (app, pool) = setup_app().await;
+    let uri = "/api/channels/humans-only/join";
+
+    // No proof at all.
+    let (status, _) = send(&app, "POST", uri, Some("alice"), None, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Proof scoped to another channel.
+    let elsewhere = role_proof(&[RoleCode::Human], channel_role_context("other"), ALICE_SK);
+    let (status, _) = send(&app, "POST", uri, Some("alice"), Some(&elsewhere), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Proof for a different role set.
+    let wrong_roles = role_proof(
+        &[RoleCode::AiAgent],
+        channel_role_context("humans-only"),
+        ALICE_SK,
+    );
+    let (status, _) = send(&app, "POST", uri, Some("alice"), Some(&wrong_roles), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Correct public inputs, but the proof does not verify.
+    let forged = role_proof(
+        &[RoleCode::Human],
+        channel_role_context("humans-only"),
+        ALICE_SK,
+    );
+    let (status, _) = send(&app, "POST", uri, Some("alice"), Some(&forged), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let conn = pool.get().unwrap();
+    let members: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM channel_members WHERE channel_id = 'humans-only'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(members, 0);
+
+    // Rejected proofs bind no nullifier.
+    let bound: i64 = conn
+        .query_row("SELECT COUNT(*) FROM role_nullifiers", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(bound, 0);
+}
+
+#[tokio::test]
+async fn test_verify_role_endpoint_checks_inputs() {
+    let (app, _pool) = setup_app().await;
+    let proof = role_proof(&[RoleCode::Human], role_context("nonce-1"), ALICE_SK);
+
+    // Role set mismatch.
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/zk/verify-role",
+        None,
+        None,
+        Some(json!({
+            "roles": ["AiAgent"],
+            "context": "nonce-1",
+            "proof": proof["proof"],
+            "publicSignals": proof["publicSignals"],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Context mismatch.
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/zk/verify-role",
+        None,
+        None,
+        Some(json!({
+            "roles": ["Human"],
+            "context": "nonce-2",
+            "proof": proof["proof"],
+            "publicSignals": proof["publicSignals"],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Inputs match, but the proof does not verify.
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/zk/verify-role",
+        None,
+        None,
+        Some(json!({
+            "roles": ["Human"],
+            "context": "nonce-1",
+            "proof": proof["proof"],
+            "publicSignals": proof["publicSignals"],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Unknown root.
+    let mut signals = proof["publicSignals"].clone();
+    signals[0] = json!("2");
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/zk/verify-role",
+        None,
+        None,
+        Some(json!({
+            "roles": ["Human"],
+            "context": "nonce-1",
+            "proof": proof["proof"],
+            "publicSignals": signals,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
@@ -27,6 +27,10 @@ fn load_vkey() -> Arc<annex_identity::zk::VerifyingKey<annex_identity::zk::Bn254
 }
 
 async fn setup_app() -> (axum::Router, annex_db::DbPool) {
+    setup_app_with_policy(ServerPolicy::default()).await
+}
+
+async fn setup_app_with_policy(policy: ServerPolicy) -> (axum::Router, annex_db::DbPool) {
     let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
     let conn = pool.get().unwrap();
     annex_db::run_migrations(&conn).unwrap();
@@ -41,12 +45,13 @@ async fn setup_app() -> (axum::Router, annex_db::DbPool) {
     drop(conn);
 
     let tree = MerkleTree::new(20).unwrap();
-    let policy = ServerPolicy::default();
 
     let state = AppState {
         pool: pool.clone(),
         merkle_tree: Arc::new(Mutex::new(tree)),
         membership_vkey: load_vkey(),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
         server_id: 1,
         signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
         public_url: std::sync::Arc::new(std::sync::RwLock::new(
@@ -65,8 +70,13 @@ async fn setup_app() -> (axum::Router, annex_db::DbPool) {
         observe_tx: tokio::sync::broadcast::channel(256).0,
         upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
         preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
         cors_origins: vec![],
         enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
         ws_token_secret: std::sync::Arc::new([0u8; 32]),
     };
 
@@ -473,6 +483,90 @@ async fn test_publish_delivers_to_subscribers() {
     assert_eq!(log_count, 1, "delivery should be logged in transfer log");
 }
 
+#[tokio::test]
+async fn test_publish_enforces_policy_redacted_topics() {
+    let policy = ServerPolicy {
+        agent_redacted_topics: vec!["rust".to_string()],
+        ..ServerPolicy::default()
+    };
+    let (app, pool) = setup_app_with_policy(policy).await;
+    register_agent(&pool, "agent-policy-redact", "FULL_KNOWLEDGE_BUNDLE");
+
+    let bundle = make_bundle("agent-policy-redact");
+    let req = build_publish_request("agent-policy-redact", &bundle);
+
+    let response = app.oneshot(req).await.unwrap();
+    assert_eq!(
+        response.status(),
+        StatusCode::FORBIDDEN,
+        "server policy redactions must block publishing"
+    );
+}
+
+#[tokio::test]
+async fn test_publish_withholds_channel_redacted_topics() {
+    let mut policy = ServerPolicy::default();
+    policy
+        .channel_redacted_topics
+        .insert("chan-private".to_string(), vec!["rust".to_string()]);
+    let (app, pool) = setup_app_with_policy(policy).await;
+    register_agent(&pool, "agent-sender-ch", "FULL_KNOWLEDGE_BUNDLE");
+    register_agent(&pool, "agent-member-ch", "REFLECTION_SUMMARIES_ONLY");
+    register_agent(&pool, "agent-outsider-ch", "REFLECTION_SUMMARIES_ONLY");
+
+    let conn = pool.get().unwrap();
+    conn.execute(
+        "INSERT INTO channels (server_id, channel_id, name, channel_type)
+         VALUES (1, 'chan-private', 'Private', 'AGENT')",
+        [],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO channel_members (server_id, channel_id, pseudonym_id)
+         VALUES (1, 'chan-private', 'agent-member-ch')",
+        [],
+    )
+    .unwrap();
+    for subscriber in ["agent-member-ch", "agent-outsider-ch"] {
+        conn.execute(
+            "INSERT INTO rtx_subscriptions (server_id, subscriber_pseudonym, domain_filters_json)
+             VALUES (1, ?1, '[]')",
+            [subscriber],
+        )
+        .unwrap();
+    }
+    drop(conn);
+
+    let bundle = make_bundle("agent-sender-ch");
+    let req = build_publish_request("agent-sender-ch", &bundle);
+
+    let response = app.oneshot(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = serde_json::from_slice(
+        &axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        body["delivered_to"], 1,
+        "only the subscriber outside the redacted channel should receive the bundle"
+    );
+
+    let conn = pool.get().unwrap();
+    let (scope, note): (String, Option<String>) = conn
+        .query_row(
+            "SELECT transfer_scope_applied, redactions_applied FROM rtx_transfer_log
+             WHERE bundle_id = ?1 AND destination_pseudonym = 'agent-member-ch'",
+            [bundle["bundle_id"].as_str().unwrap()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(scope, "NO_TRANSFER");
+    assert_eq!(note.as_deref(), Some("redacted_topics:rust"));
+}
+
 #[tokio::test]
 async fn test_publish_respects_subscriber_domain_filters() {
     let (app, pool) = setup_app().await;
@@ -0,0 +1,181 @@
+use annex_db::{create_pool, DbRuntimeSettings};
+use annex_identity::MerkleTree;
+use annex_server::{app, middleware::RateLimiter, AppState};
+use annex_types::ServerPolicy;
+use annex_vrp::VrpAnchorSnapshot;
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tower::ServiceExt; // for oneshot
+
+async fn setup_app(policy: ServerPolicy) -> (axum::Router, annex_db::DbPool) {
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'test-server', 'Test Server', '{}')",
+        [],
+    )
+    .unwrap();
+    drop(conn);
+
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
+            &mut rand::rngs::OsRng,
+        )),
+        public_url: std::sync::Arc::new(std::sync::RwLock::new(
+            "http://localhost:3000".to_string(),
+        )),
+        policy: Arc::new(RwLock::new(policy)),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: std::sync::Arc::new([0u8; 32]),
+    };
+
+    (app(state), pool)
+}
+
+async fn get(app: &axum::Router, uri: &str) -> (StatusCode, Value) {
+    let mut req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    let response = app.clone().oneshot(req).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, body)
+}
+
+fn insert_policy_version(pool: &annex_db::DbPool, version_id: &str, policy: Value) {
+    let conn = pool.get().unwrap();
+    conn.execute(
+        "INSERT INTO server_policy_versions (server_id, version_id, policy_json) VALUES (1, ?1, ?2)",
+        rusqlite::params![version_id, policy.to_string()],
+    )
+    .unwrap();
+}
+
+/// Verifies the anchor's signature, returning whether it was made over the
+/// live payload (`true`) or the history payload (`false`).
+fn verify_signed(public_key_hex: &str, anchor: &Value) -> bool {
+    let anchor: VrpAnchorSnapshot = serde_json::from_value(anchor.clone()).unwrap();
+    let key_bytes: [u8; 32] = hex::decode(public_key_hex).unwrap().try_into().unwrap();
+    let key = VerifyingKey::from_bytes(&key_bytes).unwrap();
+    let signature_bytes: [u8; 64] = hex::decode(anchor.signature.as_deref().unwrap())
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let signature = Signature::from_bytes(&signature_bytes);
+    if key
+        .verify(anchor.signing_payload().as_bytes(), &signature)
+        .is_ok()
+    {
+        return true;
+    }
+    key.verify(anchor.history_signing_payload().as_bytes(), &signature)
+        .expect("anchor signature should verify with the server key");
+    false
+}
+
+fn assert_signed(public_key_hex: &str, anchor: &Value) {
+    assert!(
+        verify_signed(public_key_hex, anchor),
+        "live anchor should be signed under the live domain"
+    );
+}
+
+fn assert_history_signed(public_key_hex: &str, anchor: &Value) {
+    assert!(
+        !verify_signed(public_key_hex, anchor),
+        "archived anchors must not verify as live anchors"
+    );
+}
+
+#[tokio::test]
+async fn current_anchor_is_signed_by_server_key() {
+    let policy = ServerPolicy {
+        principles: vec!["be honest".to_string()],
+        ..ServerPolicy::default()
+    };
+    let (app, _pool) = setup_app(policy).await;
+
+    let (status, body) = get(&app, "/api/vrp/anchor").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["anchor"]["principles"], json!(["be honest"]));
+    assert_signed(body["publicKey"].as_str().unwrap(), &body["anchor"]);
+}
+
+#[tokio::test]
+async fn anchor_history_collapses_unchanged_versions_and_diffs() {
+    let (app, pool) = setup_app(ServerPolicy::default()).await;
+    insert_policy_version(
+        &pool,
+        "v1",
+        json!({ "principles": ["be honest"], "prohibited_actions": ["spam"] }),
+    );
+    // Same anchor, different unrelated settings.
+    insert_policy_version(
+        &pool,
+        "v2",
+        json!({ "principles": ["be honest"], "prohibited_actions": ["spam"], "voice_enabled": false }),
+    );
+    insert_policy_version(
+        &pool,
+        "v3",
+        json!({ "principles": ["be honest", "be kind"], "prohibited_actions": [] }),
+    );
+
+    let (status, body) = get(&app, "/api/vrp/anchor/history").await;
+    assert_eq!(status, StatusCode::OK);
+    let versions = body["versions"].as_array().unwrap();
+    let ids: Vec<&str> = versions
+        .iter()
+        .map(|v| v["versionId"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["v1", "v3"]);
+    for version in versions {
+        assert_history_signed(body["publicKey"].as_str().unwrap(), &version["anchor"]);
+    }
+
+    let (status, body) = get(&app, "/api/vrp/anchor/diff?from=v1&to=v3").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["diff"]["addedPrinciples"], json!(["be kind"]));
+    assert_eq!(body["diff"]["removedPrinciples"], json!([]));
+    assert_eq!(body["diff"]["addedProhibitedActions"], json!([]));
+    assert_eq!(body["diff"]["removedProhibitedActions"], json!(["spam"]));
+
+    let (status, _) = get(&app, "/api/vrp/anchor/diff?from=v1&to=missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -58,6 +58,8 @@ async fn setup_app() -> (axum::Router, annex_db::DbPool) {
         pool: pool.clone(),
         merkle_tree: Arc::new(Mutex::new(tree)),
         membership_vkey: load_vkey(),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
         server_id: 1,
         signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
             &mut rand::rngs::OsRng,
@@ -78,8 +80,13 @@ async fn setup_app() -> (axum::Router, annex_db::DbPool) {
         observe_tx: tokio::sync::broadcast::channel(256).0,
         upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
         preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
         cors_origins: vec![],
         enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
         ws_token_secret: std::sync::Arc::new([0u8; 32]),
     };
 
@@ -151,6 +158,15 @@ async fn test_agent_handshake_aligned() {
         .unwrap();
     assert!(exists, "agent registration should be created");
 
+    let expires_at: Option<String> = conn
+        .query_row(
+            "SELECT expires_at FROM agent_registrations WHERE pseudonym_id = 'agent-123'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(expires_at.is_some(), "contract should carry an expiry");
+
     // Check handshake log
     let log_count: i64 = conn
         .query_row(
@@ -235,4 +251,19 @@ async fn test_agent_handshake_conflict() {
         )
         .unwrap();
     assert_eq!(log_count, 1, "handshake should be logged even on conflict");
+
+    // The logged report explains the conflict.
+    let report_json: String = conn
+        .query_row(
+            "SELECT report_json FROM vrp_handshake_log WHERE peer_pseudonym = 'agent-conflict'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let logged: VrpValidationReport = serde_json::from_str(&report_json).unwrap();
+    assert_eq!(logged.explanation, report.explanation);
+    assert_eq!(
+        logged.explanation.transfer_scope_reason,
+        "alignment conflict prohibits all transfers"
+    );
 }
@@ -0,0 +1,386 @@
+use annex_db::{create_pool, DbRuntimeSettings};
+use annex_identity::MerkleTree;
+use annex_server::{app, middleware::RateLimiter, AppState};
+use annex_types::ServerPolicy;
+use annex_vrp::VrpAnchorSnapshot;
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tower::ServiceExt; // for oneshot
+
+const REMOTE_URL: &str = "https://remote.example.com";
+
+async fn setup_app() -> (axum::Router, annex_db::DbPool) {
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'test-server', 'Test Server', '{}')",
+        [],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO instances (id, base_url, public_key, label, status) VALUES (10, ?1, 'pubkey', 'Remote Instance', 'ACTIVE')",
+        [REMOTE_URL],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, active)
+         VALUES (1, 'agent-neg', 'AI_AGENT', 1), (1, 'agent-other', 'AI_AGENT', 1),
+                (1, 'human-1', 'HUMAN', 1)",
+        [],
+    )
+    .unwrap();
+    drop(conn);
+
+    let policy = ServerPolicy {
+        agent_optional_capabilities: vec!["AUDIT_LOG".to_string()],
+        ..Default::default()
+    };
+
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
+            &mut rand::rngs::OsRng,
+        )),
+        public_url: std::sync::Arc::new(std::sync::RwLock::new(
+            "http://localhost:3000".to_string(),
+        )),
+        policy: Arc::new(RwLock::new(policy)),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: std::sync::Arc::new([0u8; 32]),
+    };
+
+    (app(state), pool)
+}
+
+async fn send(app: &axum::Router, uri: &str, body: Value) -> (StatusCode, Value) {
+    send_as(app, uri, None, body).await
+}
+
+async fn send_as(
+    app: &axum::Router,
+    uri: &str,
+    pseudonym: Option<&str>,
+    body: Value,
+) -> (StatusCode, Value) {
+    let mut builder = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("content-type", "application/json");
+    if let Some(pseudonym) = pseudonym {
+        builder = builder.header("X-Annex-Pseudonym", pseudonym);
+    }
+    let mut request = builder.body(Body::from(body.to_string())).unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, value)
+}
+
+/// An anchor matching the default (empty) server policy.
+fn aligned_anchor() -> Value {
+    serde_json::to_value(VrpAnchorSnapshot::new(&[], &[]).unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_agent_negotiation_counter_offer_then_accept() {
+    let (app, pool) = setup_app().await;
+
+    // The agent wants SCREEN_SHARE (not offered) and full knowledge
+    // transfer (above the server's limit).
+    let (status, body) = send_as(
+        &app,
+        "/api/vrp/negotiations",
+        Some("agent-neg"),
+        json!({
+            "pseudonymId": "agent-neg",
+            "anchorSnapshot": aligned_anchor(),
+            "offer": {
+                "required_capabilities": ["TEXT"],
+                "optional_capabilities": ["SCREEN_SHARE"],
+                "offered_capabilities": ["TEXT"],
+                "transfer_scope": "FullKnowledgeBundle"
+            }
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "Open");
+    assert_eq!(body["round"], 1);
+    assert_eq!(body["response"]["action"], "counter_offer");
+    assert_eq!(
+        body["response"]["terms"]["transfer_scope"],
+        "ReflectionSummariesOnly"
+    );
+    // The agent does not offer AUDIT_LOG, so the server dropped it.
+    assert_eq!(
+        body["response"]["terms"]["optional_capabilities"],
+        json!([])
+    );
+    assert!(body["report"].is_null());
+    let negotiation_id = body["negotiationId"].as_str().unwrap().to_string();
+
+    // Another agent cannot act on this negotiation.
+    let (status, _) = send_as(
+        &app,
+        &format!("/api/vrp/negotiations/{negotiation_id}"),
+        Some("agent-other"),
+        json!({ "pseudonymId": "agent-other", "action": "accept" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send_as(
+        &app,
+        &format!("/api/vrp/negotiations/{negotiation_id}"),
+        Some("agent-neg"),
+        json!({ "pseudonymId": "agent-neg", "action": "accept" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "Accepted");
+    assert_eq!(body["report"]["alignment_status"], "Aligned");
+    assert_eq!(body["report"]["transfer_scope"], "ReflectionSummariesOnly");
+
+    let conn = pool.get().unwrap();
+    let (scope, contract_json): (String, String) = conn
+        .query_row(
+            "SELECT transfer_scope, capability_contract_json FROM agent_registrations
+             WHERE pseudonym_id = 'agent-neg'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(scope, "REFLECTION_SUMMARIES_ONLY");
+    let contract: Value = serde_json::from_str(&contract_json).unwrap();
+    assert_eq!(contract["required_capabilities"], json!(["TEXT"]));
+
+    let state: String = conn
+        .query_row(
+            "SELECT status FROM vrp_negotiations WHERE id = ?1",
+            [&negotiation_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(state, "ACCEPTED");
+    drop(conn);
+
+    // A closed negotiation cannot be advanced.
+    let (status, _) = send_as(
+        &app,
+        &format!("/api/vrp/negotiations/{negotiation_id}"),
+        Some("agent-neg"),
+        json!({ "pseudonymId": "agent-neg", "action": "accept" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_federation_negotiation_reject_accept_and_expiry() {
+    let (app, pool) = setup_app().await;
+
+    // A mandatory capability the server does not offer is rejected outright.
+    let (status, body) = send(
+        &app,
+        "/api/federation/negotiations",
+        json!({
+            "base_url": REMOTE_URL,
+            "anchor_snapshot": aligned_anchor(),
+            "offer": {
+                "required_capabilities": ["telepathy"],
+                "offered_capabilities": [],
+                "transfer_scope": "ReflectionSummariesOnly"
+            }
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "Rejected");
+    assert!(body["response"]["reason"]
+        .as_str()
+        .unwrap()
+        .contains("telepathy"));
+    // Failing to agree on terms is not a conflict of principles.
+    assert_eq!(body["report"]["alignment_status"], "Aligned");
+    assert_eq!(body["report"]["transfer_scope"], "NoTransfer");
+
+    // Terms the server can meet are accepted in the first round.
+    let (status, body) = send(
+        &app,
+        "/api/federation/negotiations",
+        json!({
+            "base_url": REMOTE_URL,
+            "anchor_snapshot": aligned_anchor(),
+            "offer": {
+                "required_capabilities": ["federation"],
+                "offered_capabilities": ["AUDIT_LOG"],
+                "transfer_scope": "ReflectionSummariesOnly"
+            }
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "Accepted");
+
+    let conn = pool.get().unwrap();
+    let (agreement_scope, outcomes): (String, i64) = conn
+        .query_row(
+            "SELECT transfer_scope,
+                    (SELECT COUNT(*) FROM vrp_handshake_log WHERE peer_pseudonym = ?1)
+             FROM federation_agreements WHERE remote_instance_id = 10 AND active = 1",
+            [REMOTE_URL],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(agreement_scope, "REFLECTION_SUMMARIES_ONLY");
+    assert_eq!(outcomes, 1, "only the acceptance is logged");
+    drop(conn);
+
+    // An open negotiation past its timeout can no longer be advanced.
+    let (status, body) = send(
+        &app,
+        "/api/federation/negotiations",
+        json!({
+            "base_url": REMOTE_URL,
+            "anchor_snapshot": aligned_anchor(),
+            "offer": {
+                "required_capabilities": [],
+                "offered_capabilities": [],
+                "transfer_scope": "FullKnowledgeBundle"
+            }
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "Open");
+    let negotiation_id = body["negotiationId"].as_str().unwrap().to_string();
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE vrp_negotiations SET expires_at = datetime('now', '-1 seconds') WHERE id = ?1",
+            [&negotiation_id],
+        )
+        .unwrap();
+
+    let (status, body) = send(
+        &app,
+        &format!("/api/federation/negotiations/{negotiation_id}"),
+        json!({ "base_url": REMOTE_URL, "action": "accept" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    assert!(body["error"].as_str().unwrap().contains("EXPIRED"));
+}
+
+#[tokio::test]
+async fn test_agent_negotiation_requires_the_named_agent() {
+    let (app, pool) = setup_app().await;
+    let open = |pseudonym: &str| {
+        json!({
+            "pseudonymId": pseudonym,
+            "anchorSnapshot": aligned_anchor(),
+            "offer": {
+                "required_capabilities": ["telepathy"],
+                "offered_capabilities": ["TEXT"],
+                "transfer_scope": "ReflectionSummariesOnly"
+            }
+        })
+    };
+
+    let (status, _) = send(&app, "/api/vrp/negotiations", open("agent-neg")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Neither another agent nor a human can negotiate in the agent's name.
+    for caller in ["agent-other", "human-1"] {
+        let (status, _) = send_as(
+            &app,
+            "/api/vrp/negotiations",
+            Some(caller),
+            open("agent-neg"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "caller {caller}");
+    }
+    let (status, _) = send_as(
+        &app,
+        "/api/vrp/negotiations",
+        Some("human-1"),
+        open("human-1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // The agent's own failed negotiation leaves its registration alone and
+    // costs no reputation.
+    pool.get()
+        .unwrap()
+        .execute(
+            "INSERT INTO agent_registrations (
+                server_id, pseudonym_id, alignment_status, transfer_scope,
+                capability_contract_json, reputation_score, last_handshake_at, active
+             ) VALUES (1, 'agent-neg', 'Aligned', 'REFLECTION_SUMMARIES_ONLY', '{}', 0.5, datetime('now'), 1)",
+            [],
+        )
+        .unwrap();
+    let (status, body) = send_as(
+        &app,
+        "/api/vrp/negotiations",
+        Some("agent-neg"),
+        open("agent-neg"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "Rejected");
+
+    let conn = pool.get().unwrap();
+    let (alignment, active, outcomes): (String, bool, i64) = conn
+        .query_row(
+            "SELECT alignment_status, active,
+                    (SELECT COUNT(*) FROM vrp_handshake_log WHERE peer_pseudonym = 'agent-neg')
+             FROM agent_registrations WHERE pseudonym_id = 'agent-neg'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(alignment, "Aligned");
+    assert!(active);
+    assert_eq!(outcomes, 0);
+}
@@ -0,0 +1,289 @@
+use annex_db::{create_pool, DbRuntimeSettings};
+use annex_identity::MerkleTree;
+use annex_server::{app, middleware::RateLimiter, AppState};
+use annex_types::{ChannelType, FederationScope, ServerPolicy};
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tower::ServiceExt; // for oneshot
+
+async fn setup_app(policy: ServerPolicy) -> (axum::Router, annex_db::DbPool) {
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'test-server', 'Test Server', '{}')",
+        [],
+    )
+    .unwrap();
+    for (pseudonym, participant_type, can_moderate) in [
+        ("agent-1", "AI_AGENT", 0),
+        ("agent-2", "AI_AGENT", 0),
+        ("member-1", "HUMAN", 0),
+        ("mod-1", "HUMAN", 1),
+    ] {
+        conn.execute(
+            "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, can_moderate, active)
+             VALUES (1, ?1, ?2, ?3, 1)",
+            rusqlite::params![pseudonym, participant_type, can_moderate],
+        )
+        .unwrap();
+    }
+    drop(conn);
+
+    let state = AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: std::sync::Arc::new(ed25519_dalek::SigningKey::generate(
+            &mut rand::rngs::OsRng,
+        )),
+        public_url: std::sync::Arc::new(std::sync::RwLock::new(
+            "http://localhost:3000".to_string(),
+        )),
+        policy: Arc::new(RwLock::new(policy)),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: std::sync::Arc::new([0u8; 32]),
+    };
+
+    (app(state), pool)
+}
+
+async fn send(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    pseudonym: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("X-Annex-Pseudonym", pseudonym)
+        .header("content-type", "application/json")
+        .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, value)
+}
+
+fn score(body: &Value) -> f64 {
+    body["score"]
+        .as_f64()
+        .expect("summary should carry a score")
+}
+
+#[tokio::test]
+async fn test_reports_and_incidents_lower_reputation_and_gate_joins() {
+    let policy = ServerPolicy {
+        agent_min_reputation: 0.3,
+        ..Default::default()
+    };
+    let (app, pool) = setup_app(policy).await;
+
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO agent_registrations (server_id, pseudonym_id, alignment_status, transfer_scope, capability_contract_json, reputation_score, last_handshake_at)
+             VALUES (1, 'agent-1', 'Aligned', 'ReflectionSummariesOnly', '{}', 0.5, datetime('now'))",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO channels (server_id, channel_id, name, channel_type, federation_scope)
+             VALUES (1, 'general', 'General', ?1, ?2)",
+            rusqlite::params![
+                serde_json::to_string(&ChannelType::Text).unwrap(),
+                serde_json::to_string(&FederationScope::Local).unwrap()
+            ],
+        )
+        .unwrap();
+    }
+
+    // No history: neutral.
+    let (status, body) = send(&app, "GET", "/api/vrp/reputation/agent-1", "member-1", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!((score(&body) - 0.5).abs() < 0.001);
+
+    // A member report: 0.5 - 0.15 * 0.5 = 0.425
+    let report = json!({ "reason": "posted spam" });
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/vrp/reputation/agent-1/reports",
+        "member-1",
+        Some(report.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!((score(&body) - 0.425).abs() < 0.001);
+    assert_eq!(body["moderationReports"], 1);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/vrp/reputation/agent-1/reports",
+        "member-1",
+        Some(report.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/vrp/reputation/agent-1/reports",
+        "agent-1",
+        Some(report.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/vrp/reputation/mod-1/reports",
+        "member-1",
+        Some(report),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Still above the gate.
+    let (status, _) = send(&app, "POST", "/api/channels/general/join", "agent-1", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, "POST", "/api/channels/general/leave", "agent-1", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Only moderators flag incidents.
+    let incident = json!({ "note": "exfiltrated redacted topics" });
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/admin/vrp/reputation/agent-1/incidents",
+        "member-1",
+        Some(incident.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // 0.425 - 0.4 * 0.425 = 0.255
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/admin/vrp/reputation/agent-1/incidents",
+        "mod-1",
+        Some(incident),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!((score(&body) - 0.255).abs() < 0.001);
+    assert_eq!(body["incidents"], 1);
+
+    // Now below the policy minimum.
+    let (status, _) = send(&app, "POST", "/api/channels/general/join", "agent-1", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let conn = pool.get().unwrap();
+    let events: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM public_event_log WHERE event_type = 'MODERATION_ACTION' AND entity_id = 'agent-1'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(events, 1);
+}
+
+#[tokio::test]
+async fn test_rtx_feedback_requires_delivery_and_is_single_use() {
+    let (app, pool) = setup_app(ServerPolicy::default()).await;
+
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO rtx_transfer_log (server_id, bundle_id, source_pseudonym, destination_pseudonym, transfer_scope_applied)
+             VALUES (1, 'bundle-1', 'agent-1', 'agent-2', 'REFLECTION_SUMMARIES_ONLY')",
+            [],
+        )
+        .unwrap();
+    }
+
+    let uri = "/api/rtx/bundles/bundle-1/feedback";
+
+    // member-1 never received the bundle.
+    let (status, _) = send(
+        &app,
+        "POST",
+        uri,
+        "member-1",
+        Some(json!({ "useful": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 0.5 + 0.05 * 0.5 = 0.525, credited to the bundle's source.
+    let (status, body) = send(
+        &app,
+        "POST",
+        uri,
+        "agent-2",
+        Some(json!({ "useful": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["peer"], "agent-1");
+    assert!((score(&body) - 0.525).abs() < 0.001);
+    assert_eq!(body["rtxPositive"], 1);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        uri,
+        "agent-2",
+        Some(json!({ "useful": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send(&app, "GET", "/api/vrp/reputation/agent-1", "agent-2", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["rtxPositive"], 1);
+    assert_eq!(body["rtxNegative"], 0);
+}
@@ -108,7 +108,7 @@ async fn test_concurrent_session_replacement() {
             .expect("concurrent session replacement should not panic");
     }
 
-    // After all reconnections settle, exactly one session should remain.
+    // Unbound sessions are independent, so every connection remains.
     // Verify the user can still receive broadcasts without error.
     cm.broadcast("shared_channel", r#"{"type":"test"}"#.to_string())
         .await;
@@ -219,3 +219,116 @@ async fn test_subscribe_unsubscribe_cleans_up_empty_sets() {
         .expect("channel should not be closed");
     assert_eq!(msg, "ping");
 }
+
+#[tokio::test]
+async fn test_multiple_sessions_per_pseudonym_receive_broadcasts() {
+    let cm = ConnectionManager::new();
+
+    let (tx_a, mut rx_a) = mpsc::channel::<String>(16);
+    let (tx_b, mut rx_b) = mpsc::channel::<String>(16);
+    let (laptop, _) = cm
+        .add_device_session("alice".to_string(), Some("laptop".to_string()), tx_a)
+        .await;
+    let (phone, _) = cm
+        .add_device_session("alice".to_string(), Some("phone".to_string()), tx_b)
+        .await;
+    assert_eq!(cm.session_count("alice").await, 2);
+
+    // Subscriptions are per session.
+    cm.subscribe_session("ch1".to_string(), laptop).await;
+    cm.subscribe_session("ch1".to_string(), phone).await;
+    cm.subscribe_session("ch2".to_string(), laptop).await;
+
+    cm.broadcast("ch1", "one".to_string()).await;
+    cm.broadcast("ch2", "two".to_string()).await;
+    assert_eq!(rx_a.recv().await.unwrap(), "one");
+    assert_eq!(rx_a.recv().await.unwrap(), "two");
+    assert_eq!(rx_b.recv().await.unwrap(), "one");
+    assert!(rx_b.try_recv().is_err());
+
+    // Direct sends reach every device.
+    cm.send("alice", "dm".to_string()).await;
+    assert_eq!(rx_a.recv().await.unwrap(), "dm");
+    assert_eq!(rx_b.recv().await.unwrap(), "dm");
+
+    // Removing one session leaves the other subscribed.
+    cm.remove_session("alice", phone).await;
+    assert_eq!(cm.session_count("alice").await, 1);
+    cm.broadcast("ch1", "after".to_string()).await;
+    assert_eq!(rx_a.recv().await.unwrap(), "after");
+}
+
+#[tokio::test]
+async fn test_disconnect_device_closes_only_that_device() {
+    let cm = ConnectionManager::new();
+
+    let (laptop, laptop_closed) = cm
+        .add_device_session(
+            "alice".to_string(),
+            Some("laptop".to_string()),
+            dummy_sender(),
+        )
+        .await;
+    let (_phone, phone_closed) = cm
+        .add_device_session(
+            "alice".to_string(),
+            Some("phone".to_string()),
+            dummy_sender(),
+        )
+        .await;
+
+    cm.disconnect_device("alice", "phone").await;
+    tokio::time::timeout(
+        std::time::Duration::from_millis(100),
+        phone_closed.notified(),
+    )
+    .await
+    .expect("revoked device should be signalled to close");
+    assert_eq!(cm.session_count("alice").await, 1);
+
+    // Reconnecting the same device replaces its previous session.
+    let (_laptop2, _) = cm
+        .add_device_session(
+            "alice".to_string(),
+            Some("laptop".to_string()),
+            dummy_sender(),
+        )
+        .await;
+    tokio::time::timeout(
+        std::time::Duration::from_millis(100),
+        laptop_closed.notified(),
+    )
+    .await
+    .expect("stale device session should be signalled to close");
+    assert_eq!(cm.session_count("alice").await, 1);
+
+    // A stale removal for the replaced session is a no-op.
+    cm.remove_session("alice", laptop).await;
+    assert_eq!(cm.session_count("alice").await, 1);
+}
+
+#[tokio::test]
+async fn test_presence_is_idle_only_when_every_session_is() {
+    let cm = ConnectionManager::new();
+    assert_eq!(cm.presence("alice").await, None);
+
+    let laptop = cm.add_session("alice".to_string(), dummy_sender()).await;
+    let phone = cm.add_session("alice".to_string(), dummy_sender()).await;
+    assert_eq!(cm.presence("alice").await, Some(false));
+
+    // One idle device leaves alice active.
+    assert!(!cm.set_session_idle(laptop, true).await);
+    assert_eq!(cm.presence("alice").await, Some(false));
+    assert!(!cm.set_session_idle(laptop, true).await);
+
+    assert!(cm.set_session_idle(phone, true).await);
+    assert_eq!(cm.presence("alice").await, Some(true));
+
+    assert!(cm.set_session_idle(laptop, false).await);
+    assert_eq!(cm.presence("alice").await, Some(false));
+
+    cm.remove_session("alice", laptop).await;
+    assert_eq!(cm.presence("alice").await, Some(true));
+    cm.remove_session("alice", phone).await;
+    assert_eq!(cm.presence("alice").await, None);
+}
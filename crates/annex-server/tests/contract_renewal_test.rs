@@ -0,0 +1,241 @@
+use annex_db::{create_pool, DbPool, DbRuntimeSettings};
+use annex_identity::MerkleTree;
+use annex_server::{
+    api_ws::ConnectionManager, middleware::RateLimiter, policy::renew_expiring_contracts, AppState,
+};
+use annex_types::ServerPolicy;
+use annex_vrp::{VrpAlignmentStatus, VrpTransferScope, VrpValidationReport};
+use std::sync::{Arc, Mutex, RwLock};
+use tempfile::NamedTempFile;
+use tokio::sync::broadcast;
+
+fn setup_state(db: &NamedTempFile) -> (Arc<AppState>, DbPool) {
+    let pool = create_pool(db.path().to_str().unwrap(), DbRuntimeSettings::default()).unwrap();
+    let conn = pool.get().unwrap();
+    annex_db::run_migrations(&conn).unwrap();
+    conn.execute(
+        "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'test', 'Test', '{}')",
+        [],
+    )
+    .unwrap();
+    drop(conn);
+
+    let state = Arc::new(AppState {
+        pool: pool.clone(),
+        merkle_tree: Arc::new(Mutex::new(MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(ServerPolicy::default())),
+        rate_limiter: RateLimiter::new(),
+        connection_manager: ConnectionManager::new(),
+        presence_tx: broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    });
+
+    (state, pool)
+}
+
+/// Inserts an active agent whose contract expires at `datetime('now', offset)`.
+fn insert_agent(pool: &DbPool, pseudonym: &str, scope: &str, offset: &str) {
+    pool.get()
+        .unwrap()
+        .execute(
+            "INSERT INTO agent_registrations (
+                server_id, pseudonym_id, alignment_status, transfer_scope,
+                capability_contract_json, anchor_snapshot_json, reputation_score,
+                last_handshake_at, expires_at
+            ) VALUES (1, ?1, 'ALIGNED', ?2, '{}', '{}', 0.0, datetime('now'), datetime('now', ?3))",
+            rusqlite::params![pseudonym, scope, offset],
+        )
+        .unwrap();
+}
+
+/// Returns `(transfer_scope, expires_at, renewal_requested_at)` for an agent.
+fn agent_contract(pool: &DbPool, pseudonym: &str) -> (String, Option<String>, Option<String>) {
+    pool.get()
+        .unwrap()
+        .query_row(
+            "SELECT transfer_scope, expires_at, renewal_requested_at
+             FROM agent_registrations WHERE pseudonym_id = ?1",
+            [pseudonym],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap()
+}
+
+fn event_count(pool: &DbPool, event_type: &str, entity_id: &str) -> i64 {
+    pool.get()
+        .unwrap()
+        .query_row(
+            "SELECT COUNT(*) FROM public_event_log WHERE event_type = ?1 AND entity_id = ?2",
+            [event_type, entity_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_agent_contract_inside_renewal_window_requests_renewal() {
+    let db = NamedTempFile::new().unwrap();
+    let (state, pool) = setup_state(&db);
+    insert_agent(&pool, "agent-due", "FULL_KNOWLEDGE_BUNDLE", "+1 day");
+    insert_agent(&pool, "agent-fresh", "FULL_KNOWLEDGE_BUNDLE", "+20 days");
+
+    renew_expiring_contracts(state.clone()).await.unwrap();
+
+    let (scope, _, requested) = agent_contract(&pool, "agent-due");
+    assert_eq!(scope, "FULL_KNOWLEDGE_BUNDLE");
+    assert!(requested.is_some(), "renewal should be requested");
+
+    let (_, _, requested) = agent_contract(&pool, "agent-fresh");
+    assert!(
+        requested.is_none(),
+        "contract outside the window is left alone"
+    );
+
+    // A second pass does not re-request the same renewal.
+    let first = agent_contract(&pool, "agent-due").2;
+    renew_expiring_contracts(state).await.unwrap();
+    assert_eq!(agent_contract(&pool, "agent-due").2, first);
+    assert_eq!(event_count(&pool, "AGENT_REALIGNED", "agent-due"), 0);
+}
+
+#[tokio::test]
+async fn test_expired_agent_contract_downgrades_transfer_scope() {
+    let db = NamedTempFile::new().unwrap();
+    let (state, pool) = setup_state(&db);
+    insert_agent(&pool, "agent-lapsed", "FULL_KNOWLEDGE_BUNDLE", "-1 minute");
+
+    renew_expiring_contracts(state.clone()).await.unwrap();
+
+    let (scope, expires_at, requested) = agent_contract(&pool, "agent-lapsed");
+    assert_eq!(scope, "REFLECTION_SUMMARIES_ONLY");
+    assert!(
+        expires_at.is_some(),
+        "downgraded contract gets a grace period"
+    );
+    assert!(
+        requested.is_some(),
+        "renewal is requested for the grace period"
+    );
+    assert_eq!(event_count(&pool, "AGENT_REALIGNED", "agent-lapsed"), 1);
+
+    let payload: String = pool
+        .get()
+        .unwrap()
+        .query_row(
+            "SELECT payload_json FROM public_event_log
+             WHERE event_type = 'AGENT_REALIGNED' AND entity_id = 'agent-lapsed'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+    assert_eq!(payload["transfer_scope"], "REFLECTION_SUMMARIES_ONLY");
+    assert_eq!(payload["alignment_status"], "ALIGNED");
+}
+
+#[tokio::test]
+async fn test_expired_reflection_contract_drops_to_no_transfer() {
+    let db = NamedTempFile::new().unwrap();
+    let (state, pool) = setup_state(&db);
+    insert_agent(
+        &pool,
+        "agent-last",
+        "REFLECTION_SUMMARIES_ONLY",
+        "-1 minute",
+    );
+
+    renew_expiring_contracts(state).await.unwrap();
+
+    let (scope, expires_at, requested) = agent_contract(&pool, "agent-last");
+    assert_eq!(scope, "NO_TRANSFER");
+    assert!(
+        expires_at.is_none(),
+        "NO_TRANSFER contracts no longer expire"
+    );
+    assert!(requested.is_none());
+}
+
+#[tokio::test]
+async fn test_expired_federation_agreement_downgrades_transfer_scope() {
+    let db = NamedTempFile::new().unwrap();
+    let (state, pool) = setup_state(&db);
+
+    let report = VrpValidationReport {
+        alignment_status: VrpAlignmentStatus::Aligned,
+        transfer_scope: VrpTransferScope::FullKnowledgeBundle,
+        alignment_score: 1.0,
+        negotiation_notes: vec![],
+        explanation: Default::default(),
+    };
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO instances (id, base_url, public_key, label, status)
+             VALUES (10, 'http://127.0.0.1:9', 'pubkey', 'Remote', 'ACTIVE')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO federation_agreements (
+                local_server_id, remote_instance_id, alignment_status, transfer_scope,
+                agreement_json, active, expires_at
+            ) VALUES (1, 10, 'ALIGNED', 'FULL_KNOWLEDGE_BUNDLE', ?1, 1, datetime('now', '-1 minute'))",
+            [serde_json::to_string(&report).unwrap()],
+        )
+        .unwrap();
+    }
+
+    let mut presence_rx = state.presence_tx.subscribe();
+    renew_expiring_contracts(state).await.unwrap();
+
+    let (scope, agreement_json, requested): (String, String, Option<String>) = pool
+        .get()
+        .unwrap()
+        .query_row(
+            "SELECT transfer_scope, agreement_json, renewal_requested_at
+             FROM federation_agreements WHERE remote_instance_id = 10",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(scope, "REFLECTION_SUMMARIES_ONLY");
+    assert!(requested.is_some(), "renewal is requested from the peer");
+
+    let stored: VrpValidationReport = serde_json::from_str(&agreement_json).unwrap();
+    assert_eq!(
+        stored.transfer_scope,
+        VrpTransferScope::ReflectionSummariesOnly
+    );
+    assert_eq!(stored.negotiation_notes.len(), 1);
+
+    assert_eq!(
+        event_count(&pool, "FEDERATION_REALIGNED", "http://127.0.0.1:9"),
+        1
+    );
+    assert!(matches!(
+        presence_rx.try_recv(),
+        Ok(annex_types::PresenceEvent::FederationRealigned { .. })
+    ));
+}
@@ -0,0 +1,281 @@
+use annex_db::{create_pool, run_migrations, DbRuntimeSettings};
+use annex_graph::{create_edge, ensure_graph_node};
+use annex_server::{app, middleware, AppState};
+use annex_types::{EdgeKind, NodeType, ServerPolicy};
+use futures_util::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+async fn start_server() -> SocketAddr {
+    let pool = create_pool(":memory:", DbRuntimeSettings::default()).unwrap();
+    {
+        let conn = pool.get().unwrap();
+        run_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO servers (id, slug, label, policy_json) VALUES (1, 'default', 'Default', '{}')",
+            [],
+        )
+        .unwrap();
+        for p in ["alice", "bob", "carol", "dave"] {
+            ensure_graph_node(&conn, 1, p, NodeType::Human, None).unwrap();
+            conn.execute(
+                "INSERT INTO platform_identities (server_id, pseudonym_id, participant_type, active) VALUES (1, ?1, 'HUMAN', 1)",
+                [p],
+            )
+            .unwrap();
+        }
+        // bob is alice's direct connection; carol is unrelated.
+        create_edge(&conn, 1, "alice", "bob", EdgeKind::Connected, 1.0).unwrap();
+        // dave went invisible while away.
+        conn.execute(
+            "UPDATE graph_nodes SET active = 0 WHERE pseudonym_id = 'dave'",
+            [],
+        )
+        .unwrap();
+        annex_graph::set_participant_status(
+            &conn,
+            1,
+            "dave",
+            annex_types::PresenceStatus::Invisible,
+            None,
+        )
+        .unwrap();
+    }
+
+    let policy = ServerPolicy {
+        presence_idle_timeout_secs: 1,
+        ..Default::default()
+    };
+    let state = AppState {
+        pool,
+        merkle_tree: Arc::new(Mutex::new(annex_identity::MerkleTree::new(20).unwrap())),
+        membership_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        vote_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        role_vkey: Arc::new(annex_identity::zk::generate_dummy_vkey()),
+        server_id: 1,
+        signing_key: Arc::new(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng)),
+        public_url: Arc::new(RwLock::new("http://localhost:3000".to_string())),
+        policy: Arc::new(RwLock::new(policy)),
+        rate_limiter: middleware::RateLimiter::new(),
+        connection_manager: annex_server::api_ws::ConnectionManager::new(),
+        presence_tx: tokio::sync::broadcast::channel(100).0,
+        voice_service: Arc::new(annex_voice::VoiceService::new(
+            annex_voice::LiveKitConfig::default(),
+        )),
+        tts_service: Arc::new(annex_voice::TtsService::new("voices", "piper", "bark")),
+        stt_service: Arc::new(annex_voice::SttService::new("dummy", "dummy")),
+        voice_sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+        observe_tx: tokio::sync::broadcast::channel(256).0,
+        upload_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        preview_cache: annex_server::api_link_preview::PreviewCache::new(),
+        graph_index: annex_graph::AdjacencyIndex::new(),
+        visibility_preferences: annex_graph::VisibilityPreferenceCache::new(),
+        presence_fanout: annex_server::api_sse::PresenceFanout::new(100),
+        captions: annex_server::api_captions::CaptionHub::new(),
+        cors_origins: vec![],
+        enforce_zk_proofs: false,
+        embedder: Arc::new(annex_vrp::BagOfWordsEmbedder::new()),
+        ws_token_secret: Arc::new([0u8; 32]),
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app(state).into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+/// Collects the `StatusChanged` payloads of an SSE stream.
+struct StatusStream {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+impl StatusStream {
+    async fn open(addr: SocketAddr, viewer: Option<&str>) -> Self {
+        let mut request = reqwest::Client::new().get(format!("http://{}/events/presence", addr));
+        if let Some(viewer) = viewer {
+            request = request.header("X-Annex-Pseudonym", viewer);
+        }
+        let response = request.send().await.unwrap();
+        assert!(response.status().is_success());
+        Self {
+            response,
+            buffer: String::new(),
+        }
+    }
+
+    /// Returns the next status change, or `None` if none arrives in time.
+    async fn next_status(&mut self, wait: Duration) -> Option<serde_json::Value> {
+        self.next_event("StatusChanged", wait).await
+    }
+
+    /// Returns the payload of the next event of type `kind`, or `None` if
+    /// none arrives in time.
+    async fn next_event(&mut self, kind: &str, wait: Duration) -> Option<serde_json::Value> {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            while let Some(end) = self.buffer.find("\n\n") {
+                let frame: String = self.buffer.drain(..end + 2).collect();
+                let Some(data) = frame.lines().find_map(|l| l.strip_prefix("data:")) else {
+                    continue;
+                };
+                let event: serde_json::Value = serde_json::from_str(data.trim()).unwrap();
+                if event["type"] == kind {
+                    return Some(event["payload"].clone());
+                }
+            }
+            let chunk = tokio::time::timeout_at(deadline, self.response.chunk())
+                .await
+                .ok()?
+                .unwrap()?;
+            self.buffer.push_str(&String::from_utf8_lossy(&chunk));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_status_changes_reach_visible_viewers() {
+    let addr = start_server().await;
+    let mut bob = StatusStream::open(addr, Some("bob")).await;
+    let mut carol = StatusStream::open(addr, Some("carol")).await;
+    let mut anonymous = StatusStream::open(addr, None).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (mut ws, _) = connect_async(format!("ws://{}/ws?pseudonym=alice", addr))
+        .await
+        .unwrap();
+
+    let online = bob.next_status(Duration::from_secs(2)).await.unwrap();
+    assert_eq!(online["pseudonym_id"], "alice");
+    assert_eq!(online["status"], "online");
+    assert_eq!(online["idle"], false);
+
+    let set = serde_json::json!({
+        "type": "set_status",
+        "status": "do_not_disturb",
+        "statusText": "writing",
+    });
+    ws.send(Message::Text(set.to_string().into()))
+        .await
+        .unwrap();
+    let Some(Ok(Message::Text(text))) = ws.next().await else {
+        panic!("expected own status");
+    };
+    let own: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(own["type"], "status");
+    assert_eq!(own["statusText"], "writing");
+    let dnd = bob.next_status(Duration::from_secs(2)).await.unwrap();
+    assert_eq!(dnd["status"], "do_not_disturb");
+    assert_eq!(dnd["status_text"], "writing");
+
+    // No input for the idle timeout marks alice idle; a heartbeat clears it.
+    let idle = bob.next_status(Duration::from_secs(3)).await.unwrap();
+    assert_eq!(idle["status"], "do_not_disturb");
+    assert_eq!(idle["idle"], true);
+    ws.send(Message::Text(r#"{"type":"heartbeat"}"#.into()))
+        .await
+        .unwrap();
+    let back = bob.next_status(Duration::from_secs(2)).await.unwrap();
+    assert_eq!(back["idle"], false);
+
+    let invisible = serde_json::json!({ "type": "set_status", "status": "invisible" });
+    ws.send(Message::Text(invisible.to_string().into()))
+        .await
+        .unwrap();
+    let hidden = bob.next_status(Duration::from_secs(2)).await.unwrap();
+    assert_eq!(hidden["status"], "offline");
+    assert!(hidden["status_text"].is_null());
+
+    // Carol has no path to alice and the anonymous stream has no viewer.
+    assert!(carol
+        .next_status(Duration::from_millis(300))
+        .await
+        .is_none());
+    assert!(anonymous
+        .next_status(Duration::from_millis(300))
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_invisible_activity_is_not_broadcast() {
+    let addr = start_server().await;
+    let mut anonymous = StatusStream::open(addr, None).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (mut ws, _) = connect_async(format!("ws://{}/ws?pseudonym=dave", addr))
+        .await
+        .unwrap();
+    ws.send(Message::Text(r#"{"type":"heartbeat"}"#.into()))
+        .await
+        .unwrap();
+
+    assert!(anonymous
+        .next_event("NodeUpdated", Duration::from_millis(500))
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_invalid_status_is_rejected() {
+    let addr = start_server().await;
+    let (mut ws, _) = connect_async(format!("ws://{}/ws?pseudonym=alice", addr))
+        .await
+        .unwrap();
+    let offline = serde_json::json!({ "type": "set_status", "status": "offline" });
+    ws.send(Message::Text(offline.to_string().into()))
+        .await
+        .unwrap();
+    let Some(Ok(Message::Text(text))) = ws.next().await else {
+        panic!("expected error");
+    };
+    let error: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(error["type"], "error");
+}
+
+#[tokio::test]
+async fn test_unknown_viewer_is_rejected() {
+    let addr = start_server().await;
+    let response = reqwest::Client::new()
+        .get(format!("http://{}/events/presence", addr))
+        .header("X-Annex-Pseudonym", "mallory")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_own_status_endpoint() {
+    let addr = start_server().await;
+    let (mut ws, _) = connect_async(format!("ws://{}/ws?pseudonym=alice", addr))
+        .await
+        .unwrap();
+    let away = serde_json::json!({ "type": "set_status", "status": "away", "statusText": "lunch" });
+    ws.send(Message::Text(away.to_string().into()))
+        .await
+        .unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    let status: serde_json::Value = reqwest::Client::new()
+        .get(format!("http://{}/api/graph/status", addr))
+        .header("X-Annex-Pseudonym", "alice")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["status"], "away");
+    assert_eq!(status["status_text"], "lunch");
+}
@@ -42,6 +42,7 @@ async fn test_retention_task_deletes_expired_messages() {
             topic: None,
             vrp_topic_binding: None,
             required_capabilities_json: None,
+            required_roles_json: None,
             agent_min_alignment: Some(AlignmentStatus::Aligned),
             retention_days: Some(30),
             federation_scope: FederationScope::Local,
@@ -186,13 +186,30 @@ pub enum VisibilityLevel {
 }
 
 mod policy;
-pub use policy::ServerPolicy;
+pub use policy::{GraphAnalyticsConfig, ReputationConfig, ServerPolicy};
 
 pub mod voice;
 pub use voice::{VoiceModel, VoiceProfile};
 
+/// A participant's self-declared presence status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    /// Available.
+    #[default]
+    Online,
+    /// Away from the keyboard.
+    Away,
+    /// Present but not accepting interruptions.
+    DoNotDisturb,
+    /// Connected, but shown to everyone else as `Offline`.
+    Invisible,
+    /// No session is connected. Reported by the server; cannot be set.
+    Offline,
+}
+
 /// Event types for the SSE presence stream (Phase 5).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
 pub enum PresenceEvent {
     /// A new node has been added to the graph.
@@ -223,6 +240,14 @@ pub enum PresenceEvent {
     },
     /// A federation agreement has been severed.
     FederationSevered { remote_base_url: String },
+    /// A participant's status, status text or idleness changed. Invisible
+    /// participants are reported as `Offline` without status text.
+    StatusChanged {
+        pseudonym_id: String,
+        status: PresenceStatus,
+        status_text: Option<String>,
+        idle: bool,
+    },
 }
 
 #[cfg(test)]
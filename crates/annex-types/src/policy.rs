@@ -1,6 +1,7 @@
 //! Server policy configuration.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Defines the operational policy of an Annex server.
 ///
@@ -11,6 +12,13 @@ pub struct ServerPolicy {
     pub agent_min_alignment_score: f32,
     /// Capabilities required for agents to join.
     pub agent_required_capabilities: Vec<String>,
+    /// Capabilities the server asks peers for but will drop during VRP
+    /// contract negotiation if the peer does not offer them.
+    #[serde(default)]
+    pub agent_optional_capabilities: Vec<String>,
+    /// How long an open VRP contract negotiation stays valid, in seconds.
+    #[serde(default = "default_negotiation_timeout_secs")]
+    pub negotiation_timeout_secs: u64,
     /// Whether federation with other servers is enabled.
     pub federation_enabled: bool,
     /// Default message retention period in days.
@@ -55,6 +63,45 @@ pub struct ServerPolicy {
     /// Whether server-scoped usernames are enabled.
     #[serde(default)]
     pub usernames_enabled: bool,
+    /// Minimum VRP reputation (0.0 - 1.0) an agent needs to join channels.
+    #[serde(default)]
+    pub agent_min_reputation: f32,
+    /// Weights and decay used to compute VRP reputation.
+    #[serde(default)]
+    pub reputation: ReputationConfig,
+    /// Knowledge domains never exchanged with agents over RTX. Declared in
+    /// the capability contract offered to agents.
+    #[serde(default)]
+    pub agent_redacted_topics: Vec<String>,
+    /// Knowledge domains never exchanged with federated servers over RTX.
+    /// Declared in the capability contract offered to federation peers.
+    #[serde(default)]
+    pub federation_redacted_topics: Vec<String>,
+    /// Knowledge domains withheld from agents that are members of a channel,
+    /// keyed by channel ID.
+    #[serde(default)]
+    pub channel_redacted_topics: BTreeMap<String, Vec<String>>,
+    /// How long an agent registration or federation agreement stays valid
+    /// after a handshake, in seconds. Zero means contracts never expire.
+    #[serde(default = "default_contract_validity_secs")]
+    pub contract_validity_secs: u64,
+    /// How long before a contract expires the server asks the agent or peer
+    /// to re-handshake, in seconds.
+    #[serde(default = "default_contract_renewal_window_secs")]
+    pub contract_renewal_window_secs: u64,
+    /// Privacy budget and publication cadence for public graph statistics.
+    #[serde(default)]
+    pub graph_analytics: GraphAnalyticsConfig,
+    /// How long a WebSocket session may go without client input before its
+    /// participant is reported idle, in seconds. Zero disables idle
+    /// detection.
+    #[serde(default = "default_presence_idle_timeout_secs")]
+    pub presence_idle_timeout_secs: u64,
+    /// How long the presence graph change log is kept, in days. Moderators
+    /// cannot reconstruct the graph further back than this. Zero keeps
+    /// history forever.
+    #[serde(default = "default_graph_history_retention_days")]
+    pub graph_history_retention_days: u32,
 }
 
 fn default_access_mode() -> String {
@@ -69,6 +116,34 @@ fn default_max_upload_mb() -> u32 {
     5
 }
 
+fn default_negotiation_timeout_secs() -> u64 {
+    300
+}
+
+fn default_contract_validity_secs() -> u64 {
+    30 * 24 * 60 * 60
+}
+
+fn default_contract_renewal_window_secs() -> u64 {
+    3 * 24 * 60 * 60
+}
+
+fn default_presence_idle_timeout_secs() -> u64 {
+    5 * 60
+}
+
+fn default_graph_history_retention_days() -> u32 {
+    90
+}
+
+impl ServerPolicy {
+    /// Returns the contract validity period, or `None` if contracts never
+    /// expire.
+    pub fn contract_validity(&self) -> Option<u64> {
+        (self.contract_validity_secs > 0).then_some(self.contract_validity_secs)
+    }
+}
+
 /// Configuration for API rate limiting.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RateLimitConfig {
@@ -78,6 +153,13 @@ pub struct RateLimitConfig {
     pub verification_limit: u32,
     /// Max requests per minute for other endpoints.
     pub default_limit: u32,
+    /// Max connection requests a participant may send per minute.
+    #[serde(default = "default_connection_request_limit")]
+    pub connection_request_limit: u32,
+}
+
+fn default_connection_request_limit() -> u32 {
+    10
 }
 
 impl Default for RateLimitConfig {
@@ -86,6 +168,79 @@ impl Default for RateLimitConfig {
             registration_limit: 10,
             verification_limit: 10,
             default_limit: 60,
+            connection_request_limit: default_connection_request_limit(),
+        }
+    }
+}
+
+/// Differential-privacy settings for published graph statistics.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct GraphAnalyticsConfig {
+    /// Total privacy budget spent on each release, split evenly across its
+    /// statistics. Smaller values add more noise.
+    pub epsilon: f64,
+    /// Lifetime privacy budget across all releases. Once another release
+    /// would exceed it, the latest release is served indefinitely.
+    pub epsilon_budget: f64,
+    /// Channels whose noisy membership is below this size are left out of
+    /// the per-channel ratios.
+    pub min_group_size: u32,
+    /// How long a release is served before a fresh one is computed, in
+    /// seconds. Repeated queries inside the window see the same noise, so
+    /// they cannot be averaged away.
+    pub release_interval_secs: u64,
+}
+
+impl Default for GraphAnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            epsilon: 1.0,
+            epsilon_budget: 30.0,
+            min_group_size: 5,
+            release_interval_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+/// Weights and decay for longitudinal VRP reputation.
+///
+/// Positive signals move a score toward 1.0 by `weight * (1 - score)`;
+/// negative signals move it toward 0.0 by `weight * score`. Between signals
+/// the score relaxes toward neutral (0.5) with the configured half-life.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReputationConfig {
+    /// Seconds for a score's distance from neutral to halve without new
+    /// signals. Zero disables decay.
+    pub half_life_secs: u64,
+    /// Weight of an `ALIGNED` handshake (positive).
+    pub aligned_weight: f32,
+    /// Weight of a `PARTIAL` handshake (negative).
+    pub partial_weight: f32,
+    /// Weight of a `CONFLICT` handshake (negative).
+    pub conflict_weight: f32,
+    /// Weight of an operator-flagged incident (negative).
+    pub incident_weight: f32,
+    /// Weight of positive RTX feedback on a peer's bundle.
+    pub rtx_positive_weight: f32,
+    /// Weight of negative RTX feedback on a peer's bundle.
+    pub rtx_negative_weight: f32,
+    /// Weight of a moderation report against a peer (negative).
+    pub moderation_report_weight: f32,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            half_life_secs: 30 * 24 * 60 * 60,
+            aligned_weight: 0.1,
+            partial_weight: 0.05,
+            conflict_weight: 0.2,
+            incident_weight: 0.4,
+            rtx_positive_weight: 0.05,
+            rtx_negative_weight: 0.1,
+            moderation_report_weight: 0.15,
         }
     }
 }
@@ -95,6 +250,8 @@ impl Default for ServerPolicy {
         Self {
             agent_min_alignment_score: 0.8,
             agent_required_capabilities: Vec::new(),
+            agent_optional_capabilities: Vec::new(),
+            negotiation_timeout_secs: default_negotiation_timeout_secs(),
             federation_enabled: true,
             default_retention_days: 30,
             voice_enabled: true,
@@ -111,6 +268,16 @@ impl Default for ServerPolicy {
             max_video_size_mb: 5,
             max_file_size_mb: 5,
             usernames_enabled: false,
+            agent_min_reputation: 0.0,
+            reputation: ReputationConfig::default(),
+            agent_redacted_topics: Vec::new(),
+            federation_redacted_topics: Vec::new(),
+            channel_redacted_topics: BTreeMap::new(),
+            contract_validity_secs: default_contract_validity_secs(),
+            contract_renewal_window_secs: default_contract_renewal_window_secs(),
+            graph_analytics: GraphAnalyticsConfig::default(),
+            presence_idle_timeout_secs: default_presence_idle_timeout_secs(),
+            graph_history_retention_days: default_graph_history_retention_days(),
         }
     }
 }
@@ -124,6 +291,8 @@ mod tests {
         let policy = ServerPolicy::default();
         assert_eq!(policy.agent_min_alignment_score, 0.8);
         assert!(policy.agent_required_capabilities.is_empty());
+        assert!(policy.agent_optional_capabilities.is_empty());
+        assert_eq!(policy.negotiation_timeout_secs, 300);
         assert!(policy.federation_enabled);
         assert_eq!(policy.default_retention_days, 30);
         assert!(policy.voice_enabled);
@@ -131,6 +300,7 @@ mod tests {
         assert_eq!(policy.rate_limit.registration_limit, 10);
         assert_eq!(policy.rate_limit.verification_limit, 10);
         assert_eq!(policy.rate_limit.default_limit, 60);
+        assert_eq!(policy.rate_limit.connection_request_limit, 10);
         assert!(policy.principles.is_empty());
         assert!(policy.prohibited_actions.is_empty());
         assert_eq!(policy.access_mode, "public");
@@ -142,6 +312,19 @@ mod tests {
         assert_eq!(policy.max_video_size_mb, 5);
         assert_eq!(policy.max_file_size_mb, 5);
         assert!(!policy.usernames_enabled);
+        assert_eq!(policy.agent_min_reputation, 0.0);
+        assert_eq!(policy.reputation.half_life_secs, 2_592_000);
+        assert!(policy.agent_redacted_topics.is_empty());
+        assert!(policy.channel_redacted_topics.is_empty());
+        assert_eq!(policy.contract_validity_secs, 2_592_000);
+        assert_eq!(policy.contract_renewal_window_secs, 259_200);
+        assert_eq!(policy.contract_validity(), Some(2_592_000));
+        assert_eq!(policy.graph_analytics.epsilon, 1.0);
+        assert_eq!(policy.graph_analytics.epsilon_budget, 30.0);
+        assert_eq!(policy.graph_analytics.min_group_size, 5);
+        assert_eq!(policy.graph_analytics.release_interval_secs, 86_400);
+        assert_eq!(policy.presence_idle_timeout_secs, 300);
+        assert_eq!(policy.graph_history_retention_days, 90);
     }
 
     #[test]
@@ -6,7 +6,9 @@
 use serde::{Deserialize, Serialize};
 
 /// Supported TTS model architectures.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+///
+/// Each model is served by a TTS backend registered with the voice service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum VoiceModel {
     /// Piper TTS (ONNX-based, fast, local).
@@ -16,6 +18,8 @@ pub enum VoiceModel {
     Bark,
     /// System TTS (OS-provided).
     System,
+    /// A local HTTP TTS server speaking the OpenAI speech API.
+    Http,
 }
 
 /// A voice profile configuration.
@@ -30,6 +34,7 @@ pub struct VoiceProfile {
     /// The underlying TTS model architecture.
     pub model: VoiceModel,
     /// Path to the model file (relative to `assets/voices/` or absolute).
+    /// For `Http` profiles, the model name sent to the server.
     pub model_path: String,
     /// Path to the model configuration file (if applicable).
     pub config_path: Option<String>,
@@ -39,6 +44,13 @@ pub struct VoiceProfile {
     pub pitch: f32,
     /// Speaker ID within a multi-speaker model (0-indexed).
     pub speaker_id: Option<u32>,
+    /// Speech endpoint URL for `Http` profiles
+    /// (e.g. `http://127.0.0.1:8880/v1/audio/speech`).
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Voice name sent to `Http` profiles' server.
+    #[serde(default)]
+    pub voice: Option<String>,
 }
 
 impl Default for VoiceProfile {
@@ -52,6 +64,8 @@ impl Default for VoiceProfile {
             speed: 1.0,
             pitch: 1.0,
             speaker_id: None,
+            endpoint: None,
+            voice: None,
         }
     }
 }
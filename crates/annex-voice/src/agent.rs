@@ -1,10 +1,15 @@
 use crate::error::VoiceError;
-use crate::stt::SttService;
+use crate::stt::{StreamingTranscriber, SttService};
+use crate::tts::TtsStream;
+use crate::vad::VadConfig;
 use livekit_api::services::room::{RoomClient, SendDataOptions};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tracing::{debug, info, warn};
 
+/// Most audio [`AgentVoiceClient::publish_stream`] sends in one packet.
+const STREAM_BATCH_MS: usize = 1000;
+
 /// Default capacity for the per-agent transcription broadcast channel.
 const DEFAULT_TRANSCRIPTION_BROADCAST_CAPACITY: usize = 256;
 
@@ -12,11 +17,22 @@ const DEFAULT_TRANSCRIPTION_BROADCAST_CAPACITY: usize = 256;
 const CONNECT_VALIDATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
 /// Event emitted when an agent hears and transcribes speech.
+///
+/// Interim events (`is_final == false`) are hypotheses for an utterance
+/// still in progress; each is superseded by the next event with the same
+/// `segment_id` from the same speaker.
 #[derive(Debug, Clone)]
 pub struct TranscriptionEvent {
     pub channel_id: String,
     pub speaker_pseudonym: String,
     pub text: String,
+    /// Utterance number within the speaker's audio stream.
+    pub segment_id: u64,
+    pub is_final: bool,
+    /// Offset of the utterance start in the speaker's stream, in milliseconds.
+    pub start_ms: u64,
+    /// Offset of the end of the transcribed audio, in milliseconds.
+    pub end_ms: u64,
 }
 
 /// A client for an agent to participate in a LiveKit room.
@@ -32,6 +48,7 @@ pub struct AgentVoiceClient {
     pub connected: bool,
     pub stt_service: Arc<SttService>,
     pub transcription_tx: broadcast::Sender<TranscriptionEvent>,
+    transcriber: StreamingTranscriber,
     api_key: String,
     api_secret: String,
 }
@@ -90,6 +107,13 @@ impl AgentVoiceClient {
 
         let (tx, _) = broadcast::channel(DEFAULT_TRANSCRIPTION_BROADCAST_CAPACITY);
 
+        let transcriber = StreamingTranscriber::new(
+            stt_service.clone(),
+            VadConfig::default(),
+            room_name,
+            tx.clone(),
+        );
+
         Ok(Self {
             room_url: url.to_string(),
             token: token.to_string(),
@@ -97,6 +121,7 @@ impl AgentVoiceClient {
             connected: true,
             stt_service,
             transcription_tx: tx,
+            transcriber,
             api_key: api_key.to_string(),
             api_secret: api_secret.to_string(),
         })
@@ -155,6 +180,102 @@ impl AgentVoiceClient {
         Ok(())
     }
 
+    /// Publishes the remaining frames of a TTS stream as they are
+    /// synthesized, returning how many were published.
+    ///
+    /// Frames already synthesized are coalesced into packets of up to
+    /// [`STREAM_BATCH_MS`] of audio, and one room client and runtime are
+    /// reused for the whole stream rather than one per frame.
+    ///
+    /// Stops at the first synthesis or publish error. Dropping the returned
+    /// future stops publishing; the stream can then be cancelled.
+    pub async fn publish_stream(&self, stream: &mut TtsStream) -> Result<usize, VoiceError> {
+        if !self.connected {
+            return Err(VoiceError::RoomService(
+                "Agent is not connected to a room".to_string(),
+            ));
+        }
+
+        // s16le mono: two bytes per sample.
+        let batch_bytes = stream.sample_rate() as usize * 2 * STREAM_BATCH_MS / 1000;
+
+        let room_url = self.room_url.clone();
+        let api_key = self.api_key.clone();
+        let api_secret = self.api_secret.clone();
+        let room_name = self.room_name.clone();
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<u8>>(2);
+
+        // The publisher ends when `tx` is dropped, including when this future
+        // is dropped mid-stream.
+        let publisher = tokio::task::spawn_blocking(move || {
+            let room_client = RoomClient::with_api_key(&room_url, &api_key, &api_secret);
+            let send_opts = SendDataOptions {
+                kind: livekit_protocol::data_packet::Kind::Reliable,
+                topic: Some("audio".to_string()),
+                ..Default::default()
+            };
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| VoiceError::RoomService(format!("runtime build failed: {}", e)))?;
+
+            while let Some(batch) = rx.blocking_recv() {
+                rt.block_on(room_client.send_data(&room_name, batch, send_opts.clone()))
+                    .map_err(|e| {
+                        VoiceError::RoomService(format!("failed to publish audio data: {}", e))
+                    })?;
+            }
+            Ok::<(), VoiceError>(())
+        });
+
+        let mut published = 0;
+        let mut result = Ok(());
+        while let Some(frame) = stream.next_frame().await {
+            let mut batch = match frame {
+                Ok(frame) => frame,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
+            let mut frames = 1;
+            while batch.len() < batch_bytes {
+                match stream.try_next_frame() {
+                    Some(Ok(frame)) => {
+                        batch.extend_from_slice(&frame);
+                        frames += 1;
+                    }
+                    Some(Err(e)) => {
+                        result = Err(e);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+
+            debug!(
+                room = %self.room_name,
+                bytes = batch.len(),
+                frames,
+                "agent publishing streamed audio to room"
+            );
+            if tx.send(batch).await.is_err() {
+                // The publisher stopped early; its error is reported below.
+                break;
+            }
+            published += frames;
+            if result.is_err() {
+                break;
+            }
+        }
+        drop(tx);
+
+        publisher
+            .await
+            .map_err(|e| VoiceError::RoomService(format!("publish task failed: {}", e)))??;
+        result.map(|()| published)
+    }
+
     pub async fn disconnect(&mut self) {
         if self.connected {
             info!(room = %self.room_name, "agent disconnecting from room");
@@ -165,8 +286,11 @@ impl AgentVoiceClient {
     /// Processes incoming audio from a speaker in the room through STT.
     ///
     /// In a full WebRTC implementation, this would be triggered by incoming
-    /// audio frames from LiveKit's audio track subscription. Currently accepts
-    /// raw audio data and processes it through the configured STT service.
+    /// audio frames from LiveKit's audio track subscription. Accepts
+    /// successive chunks of a speaker's raw PCM (s16le, 16 kHz mono), which
+    /// are segmented at pauses and transcribed in the background. Interim
+    /// and final [`TranscriptionEvent`]s follow on
+    /// [`subscribe_transcriptions`](Self::subscribe_transcriptions).
     pub async fn process_incoming_audio(
         &self,
         audio: &[u8],
@@ -185,22 +309,20 @@ impl AgentVoiceClient {
             "agent processing incoming audio"
         );
 
-        let text = self.stt_service.transcribe(audio).await?;
-
-        let event = TranscriptionEvent {
-            channel_id: self.room_name.clone(),
-            speaker_pseudonym: speaker.to_string(),
-            text,
-        };
-
-        let _ = self.transcription_tx.send(event);
+        self.transcriber.push(speaker, audio).await
+    }
 
-        Ok(())
+    /// Marks the end of a speaker's audio (e.g. their track was
+    /// unpublished), finalizing any utterance in progress.
+    pub async fn end_speech(&self, speaker: &str) -> Result<(), VoiceError> {
+        self.transcriber.end_speech(speaker).await
     }
 
-    /// Backward-compatible alias for `process_incoming_audio`.
+    /// Processes a complete utterance: `process_incoming_audio` followed by
+    /// `end_speech`.
     pub async fn simulate_hearing(&self, audio: &[u8], speaker: &str) -> Result<(), VoiceError> {
-        self.process_incoming_audio(audio, speaker).await
+        self.process_incoming_audio(audio, speaker).await?;
+        self.end_speech(speaker).await
     }
 
     /// Subscribes to transcription events from this client.
@@ -19,6 +19,8 @@ pub mod error;
 pub mod service;
 pub mod stt;
 pub mod tts;
+pub mod tts_backends;
+pub mod vad;
 
 pub use agent::{AgentVoiceClient, TranscriptionEvent};
 pub use config::{
@@ -26,5 +28,7 @@ pub use config::{
 };
 pub use error::VoiceError;
 pub use service::VoiceService;
-pub use stt::SttService;
-pub use tts::TtsService;
+pub use stt::{StreamingTranscriber, SttService};
+pub use tts::{split_sentences, BoxFuture, TtsBackend, TtsCapabilities, TtsService, TtsStream};
+pub use tts_backends::{BarkBackend, HttpTtsBackend, PiperBackend, SystemBackend};
+pub use vad::{Segment, Segmenter, VadConfig};
@@ -1,8 +1,15 @@
+use crate::agent::TranscriptionEvent;
 use crate::error::VoiceError;
+use crate::vad::{Segment, Segmenter, VadConfig};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::task::AbortHandle;
+use tracing::warn;
 
 /// Maximum audio input size for STT (10 MiB). Prevents OOM from oversized payloads.
 const MAX_STT_INPUT_BYTES: usize = 10 * 1024 * 1024;
@@ -10,6 +17,9 @@ const MAX_STT_INPUT_BYTES: usize = 10 * 1024 * 1024;
 /// Timeout for STT process execution.
 const STT_TIMEOUT: Duration = Duration::from_secs(120);
 
+/// Segments a speaker may have waiting for transcription.
+const MAX_PENDING_SEGMENTS: usize = 8;
+
 #[derive(Debug, Clone)]
 pub struct SttService {
     model_path: PathBuf,
@@ -85,3 +95,167 @@ impl SttService {
         Ok(text)
     }
 }
+
+/// Continuous transcription of several speakers' audio.
+///
+/// Each speaker's PCM runs through its own [`Segmenter`], and a worker per
+/// speaker transcribes the segments in order, publishing a
+/// [`TranscriptionEvent`] for each non-empty hypothesis. At most
+/// `MAX_PENDING_SEGMENTS` segments queue per speaker: pushing a final
+/// segment waits for room, while interim ones are dropped when the queue is
+/// full since a later segment supersedes them.
+#[derive(Debug)]
+pub struct StreamingTranscriber {
+    stt: Arc<SttService>,
+    config: VadConfig,
+    channel_id: String,
+    events: broadcast::Sender<TranscriptionEvent>,
+    speakers: Mutex<HashMap<String, Arc<Mutex<SpeakerStream>>>>,
+    /// Each speaker's transcription workers, including those of ended
+    /// streams still working through their queue.
+    workers: Mutex<HashMap<String, Vec<AbortHandle>>>,
+}
+
+#[derive(Debug)]
+struct SpeakerStream {
+    segmenter: Segmenter,
+    segments: mpsc::Sender<Segment>,
+}
+
+impl SpeakerStream {
+    async fn enqueue(&self, segments: Vec<Segment>) -> Result<(), VoiceError> {
+        for segment in segments {
+            if segment.is_final {
+                self.segments
+                    .send(segment)
+                    .await
+                    .map_err(|_| VoiceError::Stt("transcription worker stopped".to_string()))?;
+            } else {
+                let _ = self.segments.try_send(segment);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StreamingTranscriber {
+    /// Creates a transcriber publishing events for `channel_id` on `events`.
+    pub fn new(
+        stt: Arc<SttService>,
+        config: VadConfig,
+        channel_id: impl Into<String>,
+        events: broadcast::Sender<TranscriptionEvent>,
+    ) -> Self {
+        Self {
+            stt,
+            config,
+            channel_id: channel_id.into(),
+            events,
+            speakers: Mutex::new(HashMap::new()),
+            workers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Feeds a chunk of a speaker's s16le mono PCM.
+    ///
+    /// Waits while the speaker's transcription queue is full.
+    pub async fn push(&self, speaker: &str, pcm: &[u8]) -> Result<(), VoiceError> {
+        let stream = self.speaker(speaker).await;
+        let mut stream = stream.lock().await;
+        let segments = stream.segmenter.push(pcm);
+        stream.enqueue(segments).await
+    }
+
+    /// Ends a speaker's stream, finalizing the utterance in progress.
+    ///
+    /// Audio pushed afterwards starts a new stream with fresh timestamps.
+    pub async fn end_speech(&self, speaker: &str) -> Result<(), VoiceError> {
+        let Some(stream) = self.speakers.lock().await.remove(speaker) else {
+            return Ok(());
+        };
+        let mut stream = stream.lock().await;
+        let segments = stream.segmenter.flush();
+        stream.enqueue(segments).await
+    }
+
+    /// Drops a speaker's audio without transcribing it: the utterance in
+    /// progress, queued segments and any transcription still running.
+    ///
+    /// Other speakers are unaffected, and audio pushed afterwards starts a
+    /// new stream.
+    pub async fn discard(&self, speaker: &str) {
+        self.speakers.lock().await.remove(speaker);
+        if let Some(workers) = self.workers.lock().await.remove(speaker) {
+            for worker in workers {
+                worker.abort();
+            }
+        }
+    }
+
+    async fn speaker(&self, speaker: &str) -> Arc<Mutex<SpeakerStream>> {
+        let mut speakers = self.speakers.lock().await;
+        if let Some(stream) = speakers.get(speaker) {
+            return stream.clone();
+        }
+
+        let (tx, rx) = mpsc::channel(MAX_PENDING_SEGMENTS);
+        let worker = tokio::spawn(transcribe_segments(
+            self.stt.clone(),
+            self.channel_id.clone(),
+            speaker.to_string(),
+            rx,
+            self.events.clone(),
+        ));
+        {
+            let mut workers = self.workers.lock().await;
+            let handles = workers.entry(speaker.to_string()).or_default();
+            handles.retain(|handle| !handle.is_finished());
+            handles.push(worker.abort_handle());
+        }
+
+        let stream = Arc::new(Mutex::new(SpeakerStream {
+            segmenter: Segmenter::new(self.config.clone()),
+            segments: tx,
+        }));
+        speakers.insert(speaker.to_string(), stream.clone());
+        stream
+    }
+}
+
+/// Transcribes one speaker's segments until their stream ends.
+async fn transcribe_segments(
+    stt: Arc<SttService>,
+    channel_id: String,
+    speaker: String,
+    mut segments: mpsc::Receiver<Segment>,
+    events: broadcast::Sender<TranscriptionEvent>,
+) {
+    while let Some(segment) = segments.recv().await {
+        // A queued segment of the same utterance supersedes an interim one.
+        if !segment.is_final && !segments.is_empty() {
+            continue;
+        }
+        match stt.transcribe(&segment.audio).await {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => {
+                let _ = events.send(TranscriptionEvent {
+                    channel_id: channel_id.clone(),
+                    speaker_pseudonym: speaker.clone(),
+                    text,
+                    segment_id: segment.id,
+                    is_final: segment.is_final,
+                    start_ms: segment.start_ms,
+                    end_ms: segment.end_ms,
+                });
+            }
+            Err(e) => {
+                warn!(
+                    channel = %channel_id,
+                    speaker = %speaker,
+                    segment = segment.id,
+                    "segment transcription failed: {}", e
+                );
+            }
+        }
+    }
+}
@@ -1,46 +1,236 @@
 use crate::error::VoiceError;
+use crate::tts_backends::{BarkBackend, HttpTtsBackend, PiperBackend, SystemBackend};
 use annex_types::voice::{VoiceModel, VoiceProfile};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use std::process::Stdio;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::AsyncWriteExt;
-use tokio::process::Command;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
 
 /// Maximum text input size for TTS (64 KiB). Prevents resource exhaustion from
 /// oversized synthesis requests.
 const MAX_TTS_INPUT_BYTES: usize = 64 * 1024;
 
 /// Timeout for TTS process execution.
-const TTS_TIMEOUT: Duration = Duration::from_secs(60);
+pub(crate) const TTS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Duration of each frame yielded by a [`TtsStream`], in milliseconds.
+const STREAM_FRAME_MS: usize = 100;
+
+/// Frames a [`TtsStream`] may synthesize ahead of its consumer.
+const STREAM_BUFFER_FRAMES: usize = 64;
+
+/// Sample rate assumed for backends that advertise none.
+const DEFAULT_SAMPLE_RATE: u32 = 22_050;
+
+/// A boxed future returned by [`TtsBackend`] methods.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// What a TTS backend supports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TtsCapabilities {
+    /// Whether the engine can emit audio before the whole text is rendered.
+    pub streaming: bool,
+    /// Whether the engine accepts SSML markup (`<speak>...</speak>`).
+    pub ssml: bool,
+    /// Sample rates (Hz) of the s16le PCM the engine produces. The rate
+    /// used depends on the voice.
+    pub sample_rates: Vec<u32>,
+}
+
+/// A speech synthesis engine.
+///
+/// [`TtsService`] dispatches each profile to the backend registered for its
+/// [`VoiceModel`]. Backends are shared across requests, so implementations
+/// must be `Send + Sync`.
+pub trait TtsBackend: Send + Sync + std::fmt::Debug {
+    /// Short engine name, for logs.
+    fn name(&self) -> &'static str;
+
+    /// What the engine supports.
+    fn capabilities(&self) -> TtsCapabilities;
+
+    /// Sample rate (Hz) of the audio rendered for `profile`. Defaults to
+    /// the first advertised rate, which suits single-rate engines.
+    fn sample_rate(&self, profile: &VoiceProfile) -> u32 {
+        let _ = profile;
+        self.capabilities()
+            .sample_rates
+            .first()
+            .copied()
+            .unwrap_or(DEFAULT_SAMPLE_RATE)
+    }
+
+    /// Renders `text` with `profile`, returning raw PCM audio (s16le mono).
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        profile: &'a VoiceProfile,
+    ) -> BoxFuture<'a, Result<Vec<u8>, VoiceError>>;
+}
+
+/// Splits text into sentences for incremental synthesis.
+///
+/// A sentence ends at `.`, `!`, `?` or `;` (plus any closing quotes or
+/// brackets) followed by whitespace, or at a line break. Text without a
+/// boundary is returned whole. SSML documents are never split.
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    if text.trim_start().starts_with("<speak") {
+        return vec![text.trim()];
+    }
+
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let end = match c {
+            '\n' => Some(i),
+            '.' | '!' | '?' | ';' => {
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, next)) = chars.peek() {
+                    if !matches!(next, '"' | '\'' | ')' | ']' | '\u{201D}' | '\u{2019}') {
+                        break;
+                    }
+                    end = j + next.len_utf8();
+                    chars.next();
+                }
+                chars
+                    .peek()
+                    .is_none_or(|&(_, next)| next.is_whitespace())
+                    .then_some(end)
+            }
+            _ => None,
+        };
+        if let Some(end) = end {
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+/// Speech being synthesized sentence by sentence.
+///
+/// Frames are raw PCM (s16le mono) of about 100 ms each, produced by a
+/// background task that runs ahead of the consumer. Cancelling or dropping
+/// the stream stops synthesis, including any running engine process.
+#[derive(Debug)]
+pub struct TtsStream {
+    frames: mpsc::Receiver<Result<Vec<u8>, VoiceError>>,
+    task: JoinHandle<()>,
+    /// A frame received by [`ready`](Self::ready) but not yet consumed.
+    pending: Option<Vec<u8>>,
+    sample_rate: u32,
+}
+
+impl TtsStream {
+    /// Sample rate (Hz) of the stream's s16le mono audio.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Waits for the first frame without consuming it, so callers can
+    /// report synthesis errors before acquiring an output.
+    ///
+    /// Returns `None` if the stream ended without audio, and the error if
+    /// synthesis failed.
+    pub async fn ready(&mut self) -> Option<Result<(), VoiceError>> {
+        if self.pending.is_none() {
+            self.pending = Some(match self.frames.recv().await? {
+                Ok(frame) => frame,
+                Err(e) => return Some(Err(e)),
+            });
+        }
+        Some(Ok(()))
+    }
+
+    /// Returns the next frame, or `None` once the text is fully spoken or
+    /// the stream was cancelled. A synthesis error ends the stream.
+    pub async fn next_frame(&mut self) -> Option<Result<Vec<u8>, VoiceError>> {
+        if let Some(frame) = self.pending.take() {
+            return Some(Ok(frame));
+        }
+        self.frames.recv().await
+    }
+
+    /// Returns the next frame if one has already been synthesized.
+    pub fn try_next_frame(&mut self) -> Option<Result<Vec<u8>, VoiceError>> {
+        if let Some(frame) = self.pending.take() {
+            return Some(Ok(frame));
+        }
+        self.frames.try_recv().ok()
+    }
+
+    /// Stops synthesis. Frames already produced are discarded.
+    pub fn cancel(&mut self) {
+        self.task.abort();
+        self.pending = None;
+        self.frames.close();
+        while self.frames.try_recv().is_ok() {}
+    }
+}
+
+impl Drop for TtsStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
 
 /// Service for generating speech from text.
 #[derive(Debug, Clone)]
 pub struct TtsService {
     profiles: Arc<RwLock<HashMap<String, VoiceProfile>>>,
-    voices_dir: PathBuf,
-    piper_binary: PathBuf,
-    bark_binary: PathBuf,
+    backends: Arc<RwLock<HashMap<VoiceModel, Arc<dyn TtsBackend>>>>,
 }
 
 impl TtsService {
     /// Creates a new `TtsService` with the given voices directory, piper binary path,
     /// and bark binary path.
+    ///
+    /// Registers the built-in backends: piper, bark, `espeak-ng` for
+    /// `System`, and the local HTTP adapter for `Http`.
     pub fn new(
         voices_dir: impl AsRef<Path>,
         piper_binary: impl AsRef<Path>,
         bark_binary: impl AsRef<Path>,
     ) -> Self {
+        let mut backends: HashMap<VoiceModel, Arc<dyn TtsBackend>> = HashMap::new();
+        backends.insert(
+            VoiceModel::Piper,
+            Arc::new(PiperBackend::new(voices_dir, piper_binary)),
+        );
+        backends.insert(VoiceModel::Bark, Arc::new(BarkBackend::new(bark_binary)));
+        backends.insert(VoiceModel::System, Arc::new(SystemBackend));
+        match HttpTtsBackend::new(TTS_TIMEOUT) {
+            Ok(backend) => {
+                backends.insert(VoiceModel::Http, Arc::new(backend));
+            }
+            Err(e) => tracing::warn!("http TTS backend unavailable: {}", e),
+        }
+
         Self {
             profiles: Arc::new(RwLock::new(HashMap::new())),
-            voices_dir: voices_dir.as_ref().to_path_buf(),
-            piper_binary: piper_binary.as_ref().to_path_buf(),
-            bark_binary: bark_binary.as_ref().to_path_buf(),
+            backends: Arc::new(RwLock::new(backends)),
         }
     }
 
+    /// Registers `backend` for profiles using `model`, replacing any
+    /// existing one.
+    pub async fn register_backend(&self, model: VoiceModel, backend: Arc<dyn TtsBackend>) {
+        self.backends.write().await.insert(model, backend);
+    }
+
     /// Adds a voice profile to the service.
     pub async fn add_profile(&self, profile: VoiceProfile) {
         self.profiles
@@ -54,132 +244,46 @@ impl TtsService {
         self.profiles.read().await.get(id).cloned()
     }
 
-    /// Synthesizes speech from the given text using the specified profile.
-    ///
-    /// Returns raw PCM audio data (s16le, usually 22050Hz depending on model).
-    pub async fn synthesize(&self, text: &str, profile_id: &str) -> Result<Vec<u8>, VoiceError> {
+    /// Returns the capabilities of the backend serving a profile.
+    pub async fn capabilities(&self, profile_id: &str) -> Result<TtsCapabilities, VoiceError> {
+        let (_, backend) = self.resolve(profile_id).await?;
+        Ok(backend.capabilities())
+    }
+
+    async fn resolve(
+        &self,
+        profile_id: &str,
+    ) -> Result<(VoiceProfile, Arc<dyn TtsBackend>), VoiceError> {
         let profile = self
             .get_profile(profile_id)
             .await
             .ok_or_else(|| VoiceError::ProfileNotFound(profile_id.to_string()))?;
-
-        match profile.model {
-            VoiceModel::Piper => self.synthesize_piper(text, &profile).await,
-            VoiceModel::Bark => self.synthesize_bark(text, &profile).await,
-            VoiceModel::System => self.synthesize_system(text, &profile).await,
-        }
-    }
-
-    async fn synthesize_piper(
-        &self,
-        text: &str,
-        profile: &VoiceProfile,
-    ) -> Result<Vec<u8>, VoiceError> {
-        if text.len() > MAX_TTS_INPUT_BYTES {
-            return Err(VoiceError::Tts(format!(
-                "text exceeds maximum size: {} bytes (limit: {} bytes)",
-                text.len(),
-                MAX_TTS_INPUT_BYTES
-            )));
-        }
-        let model_path = if Path::new(&profile.model_path).is_absolute() {
-            PathBuf::from(&profile.model_path)
-        } else {
-            self.voices_dir.join(&profile.model_path)
-        };
-
-        if !model_path.exists() {
-            return Err(VoiceError::Tts(format!(
-                "Model file not found: {:?}",
-                model_path
-            )));
-        }
-
-        if profile.speed < 0.1 || profile.speed > 10.0 {
-            return Err(VoiceError::Config(
-                "Speed must be between 0.1 and 10.0".to_string(),
-            ));
-        }
-
-        let mut command = Command::new(&self.piper_binary);
-        command
-            .arg("--model")
-            .arg(model_path)
-            .arg("--output_raw")
-            // Length scale is inverse of speed (roughly).
-            // If speed is 2.0 (faster), length_scale should be 0.5 (shorter).
-            .arg("--length_scale")
-            .arg((1.0 / profile.speed).to_string())
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        // If config path is explicit, maybe pass it? Piper usually infers it as .json
-        if let Some(config) = &profile.config_path {
-            let config_path = if Path::new(config).is_absolute() {
-                PathBuf::from(config)
-            } else {
-                self.voices_dir.join(config)
-            };
-            command.arg("--config").arg(config_path);
-        }
-
-        if let Some(speaker) = profile.speaker_id {
-            command.arg("--speaker").arg(speaker.to_string());
-        }
-
-        let mut child = command
-            .spawn()
-            .map_err(|e| VoiceError::Tts(format!("Failed to spawn piper: {}", e)))?;
-
-        let mut stdin = child
-            .stdin
-            .take()
-            .ok_or_else(|| VoiceError::Tts("Failed to open stdin".to_string()))?;
-        let text_owned = text.to_string();
-
-        // Spawn a task to write to stdin to avoid deadlock if output buffer fills up
-        let write_task = tokio::spawn(async move { stdin.write_all(text_owned.as_bytes()).await });
-
-        let output = tokio::time::timeout(TTS_TIMEOUT, child.wait_with_output())
+        let backend = self
+            .backends
+            .read()
             .await
-            .map_err(|_| {
-                VoiceError::Tts(format!(
-                    "TTS process timed out after {} seconds",
-                    TTS_TIMEOUT.as_secs()
+            .get(&profile.model)
+            .cloned()
+            .ok_or_else(|| {
+                VoiceError::Config(format!(
+                    "no TTS backend registered for model {:?}",
+                    profile.model
                 ))
-            })?
-            .map_err(|e| VoiceError::Tts(format!("Failed to wait for piper: {}", e)))?;
-
-        // Ensure writing finished successfully (or propagate error)
-        match write_task.await {
-            Ok(Ok(_)) => {}
-            Ok(Err(e)) => {
-                return Err(VoiceError::Tts(format!(
-                    "Failed to write to piper stdin: {}",
-                    e
-                )))
-            }
-            Err(e) => return Err(VoiceError::Tts(format!("Stdin task failed: {}", e))),
-        }
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(VoiceError::Tts(format!("Piper failed: {}", stderr)));
-        }
-
-        Ok(output.stdout)
+            })?;
+        Ok((profile, backend))
     }
 
-    /// Synthesizes speech using Bark (Python-based neural TTS).
+    /// Synthesizes speech incrementally, one sentence at a time.
     ///
-    /// Expects `bark_binary` to point to a Python wrapper script that accepts
-    /// `--text <text> --output_raw` and writes raw PCM (s16le) to stdout.
-    async fn synthesize_bark(
+    /// Unknown profiles and oversized text fail immediately; engine errors
+    /// are yielded by the stream.
+    pub async fn synthesize_stream(
         &self,
         text: &str,
-        _profile: &VoiceProfile,
-    ) -> Result<Vec<u8>, VoiceError> {
+        profile_id: &str,
+    ) -> Result<TtsStream, VoiceError> {
+        let (profile, backend) = self.resolve(profile_id).await?;
+
         if text.len() > MAX_TTS_INPUT_BYTES {
             return Err(VoiceError::Tts(format!(
                 "text exceeds maximum size: {} bytes (limit: {} bytes)",
@@ -188,61 +292,52 @@ impl TtsService {
             )));
         }
 
-        if self.bark_binary.as_os_str().is_empty() {
-            return Err(VoiceError::Tts(
-                "Bark TTS binary path is not configured. Set bark_binary_path in config \
-                 or ANNEX_BARK_BINARY_PATH environment variable."
-                    .to_string(),
-            ));
-        }
-
-        if !self.bark_binary.exists() {
-            return Err(VoiceError::Tts(format!(
-                "Bark TTS binary not found: {:?}",
-                self.bark_binary
-            )));
-        }
-
-        let mut command = Command::new(&self.bark_binary);
-        command
-            .arg("--text")
-            .arg(text)
-            .arg("--output_raw")
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let child = command
-            .spawn()
-            .map_err(|e| VoiceError::Tts(format!("Failed to spawn bark: {}", e)))?;
-
-        let output = tokio::time::timeout(TTS_TIMEOUT, child.wait_with_output())
-            .await
-            .map_err(|_| {
-                VoiceError::Tts(format!(
-                    "Bark TTS process timed out after {} seconds",
-                    TTS_TIMEOUT.as_secs()
-                ))
-            })?
-            .map_err(|e| VoiceError::Tts(format!("Failed to wait for bark: {}", e)))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(VoiceError::Tts(format!("Bark failed: {}", stderr)));
-        }
-
-        Ok(output.stdout)
+        let sample_rate = backend.sample_rate(&profile);
+        // Whole s16 samples only.
+        let frame_len = (sample_rate as usize * STREAM_FRAME_MS / 1000).max(1) * 2;
+
+        let sentences: Vec<String> = split_sentences(text)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let (tx, frames) = mpsc::channel(STREAM_BUFFER_FRAMES);
+        let task = tokio::spawn(async move {
+            tracing::debug!(
+                backend = backend.name(),
+                profile = %profile.id,
+                sentences = sentences.len(),
+                "streaming speech"
+            );
+            for sentence in sentences {
+                let audio = match backend.synthesize(&sentence, &profile).await {
+                    Ok(audio) => audio,
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                };
+                for frame in audio.chunks(frame_len) {
+                    if tx.send(Ok(frame.to_vec())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(TtsStream {
+            frames,
+            task,
+            pending: None,
+            sample_rate,
+        })
     }
 
-    /// Synthesizes speech using the system's native TTS engine.
+    /// Synthesizes speech from the given text using the specified profile.
     ///
-    /// Uses `espeak-ng` as the cross-platform fallback. On Linux, `espeak-ng`
-    /// outputs WAV to stdout via `--stdout`; the 44-byte WAV header is stripped
-    /// to return raw PCM data.
-    async fn synthesize_system(
-        &self,
-        text: &str,
-        _profile: &VoiceProfile,
-    ) -> Result<Vec<u8>, VoiceError> {
+    /// Returns raw PCM audio data (s16le, usually 22050Hz depending on model).
+    pub async fn synthesize(&self, text: &str, profile_id: &str) -> Result<Vec<u8>, VoiceError> {
+        let (profile, backend) = self.resolve(profile_id).await?;
+
         if text.len() > MAX_TTS_INPUT_BYTES {
             return Err(VoiceError::Tts(format!(
                 "text exceeds maximum size: {} bytes (limit: {} bytes)",
@@ -251,40 +346,11 @@ impl TtsService {
             )));
         }
 
-        // Use espeak-ng as the cross-platform fallback. It outputs WAV to stdout
-        // via --stdout; we strip the 44-byte WAV header to get raw PCM.
-        let mut command = Command::new("espeak-ng");
-        command
-            .arg("--stdout")
-            .arg(text)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let child = command
-            .spawn()
-            .map_err(|e| VoiceError::Tts(format!("Failed to spawn espeak-ng: {}", e)))?;
-
-        let output = tokio::time::timeout(TTS_TIMEOUT, child.wait_with_output())
-            .await
-            .map_err(|_| {
-                VoiceError::Tts(format!(
-                    "System TTS process timed out after {} seconds",
-                    TTS_TIMEOUT.as_secs()
-                ))
-            })?
-            .map_err(|e| VoiceError::Tts(format!("Failed to wait for espeak-ng: {}", e)))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(VoiceError::Tts(format!("espeak-ng failed: {}", stderr)));
-        }
-
-        // Strip the 44-byte WAV header to return raw PCM data.
-        let wav_data = output.stdout;
-        if wav_data.len() > 44 {
-            Ok(wav_data[44..].to_vec())
-        } else {
-            Ok(wav_data)
-        }
+        tracing::debug!(
+            backend = backend.name(),
+            profile = %profile.id,
+            "synthesizing speech"
+        );
+        backend.synthesize(text, &profile).await
     }
 }
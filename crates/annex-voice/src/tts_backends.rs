@@ -0,0 +1,446 @@
+//! TTS engine implementations.
+//!
+//! Each [`TtsBackend`] renders text for the profiles of one [`VoiceModel`]:
+//!
+//! | Model | Backend | Notes |
+//! |-------|---------|-------|
+//! | `piper` | [`PiperBackend`] | Local ONNX voices via the piper binary. |
+//! | `bark` | [`BarkBackend`] | Python wrapper script around Bark. |
+//! | `system` | [`SystemBackend`] | `espeak-ng`; accepts SSML. |
+//! | `http` | [`HttpTtsBackend`] | Local OpenAI-compatible `/v1/audio/speech` server. |
+//!
+//! [`VoiceModel`]: annex_types::voice::VoiceModel
+
+use crate::error::VoiceError;
+use crate::tts::{BoxFuture, TtsBackend, TtsCapabilities, TTS_TIMEOUT};
+use annex_types::voice::VoiceProfile;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Size of a canonical WAV header.
+const WAV_HEADER_LEN: usize = 44;
+
+/// Strips a WAV header, if present, leaving raw PCM.
+fn strip_wav_header(mut audio: Vec<u8>) -> Vec<u8> {
+    if audio.len() > WAV_HEADER_LEN && audio.starts_with(b"RIFF") {
+        audio.drain(..WAV_HEADER_LEN);
+    }
+    audio
+}
+
+/// Piper TTS (ONNX-based, fast, local).
+#[derive(Debug, Clone)]
+pub struct PiperBackend {
+    voices_dir: PathBuf,
+    binary: PathBuf,
+}
+
+impl PiperBackend {
+    /// Creates a backend resolving relative model paths against `voices_dir`.
+    pub fn new(voices_dir: impl AsRef<Path>, binary: impl AsRef<Path>) -> Self {
+        Self {
+            voices_dir: voices_dir.as_ref().to_path_buf(),
+            binary: binary.as_ref().to_path_buf(),
+        }
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        if Path::new(path).is_absolute() {
+            PathBuf::from(path)
+        } else {
+            self.voices_dir.join(path)
+        }
+    }
+
+    /// Path of the voice's config, which piper reads from `<model>.json`
+    /// unless the profile names one.
+    fn config_path(&self, profile: &VoiceProfile) -> PathBuf {
+        match &profile.config_path {
+            Some(config) => self.resolve(config),
+            None => self.resolve(&format!("{}.json", profile.model_path)),
+        }
+    }
+
+    async fn run(&self, text: &str, profile: &VoiceProfile) -> Result<Vec<u8>, VoiceError> {
+        let model_path = self.resolve(&profile.model_path);
+
+        if !model_path.exists() {
+            return Err(VoiceError::Tts(format!(
+                "Model file not found: {:?}",
+                model_path
+            )));
+        }
+
+        if profile.speed < 0.1 || profile.speed > 10.0 {
+            return Err(VoiceError::Config(
+                "Speed must be between 0.1 and 10.0".to_string(),
+            ));
+        }
+
+        let mut command = Command::new(&self.binary);
+        command
+            .arg("--model")
+            .arg(model_path)
+            .arg("--output_raw")
+            // Length scale is inverse of speed (roughly).
+            // If speed is 2.0 (faster), length_scale should be 0.5 (shorter).
+            .arg("--length_scale")
+            .arg((1.0 / profile.speed).to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Cancelled synthesis must not leave the engine running.
+            .kill_on_drop(true);
+
+        // If config path is explicit, maybe pass it? Piper usually infers it as .json
+        if let Some(config) = &profile.config_path {
+            command.arg("--config").arg(self.resolve(config));
+        }
+
+        if let Some(speaker) = profile.speaker_id {
+            command.arg("--speaker").arg(speaker.to_string());
+        }
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| VoiceError::Tts(format!("Failed to spawn piper: {}", e)))?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| VoiceError::Tts("Failed to open stdin".to_string()))?;
+        let text_owned = text.to_string();
+
+        // Spawn a task to write to stdin to avoid deadlock if output buffer fills up
+        let write_task = tokio::spawn(async move { stdin.write_all(text_owned.as_bytes()).await });
+
+        let output = tokio::time::timeout(TTS_TIMEOUT, child.wait_with_output())
+            .await
+            .map_err(|_| {
+                VoiceError::Tts(format!(
+                    "TTS process timed out after {} seconds",
+                    TTS_TIMEOUT.as_secs()
+                ))
+            })?
+            .map_err(|e| VoiceError::Tts(format!("Failed to wait for piper: {}", e)))?;
+
+        // Ensure writing finished successfully (or propagate error)
+        match write_task.await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => {
+                return Err(VoiceError::Tts(format!(
+                    "Failed to write to piper stdin: {}",
+                    e
+                )))
+            }
+            Err(e) => return Err(VoiceError::Tts(format!("Stdin task failed: {}", e))),
+        }
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(VoiceError::Tts(format!("Piper failed: {}", stderr)));
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+impl TtsBackend for PiperBackend {
+    fn name(&self) -> &'static str {
+        "piper"
+    }
+
+    fn capabilities(&self) -> TtsCapabilities {
+        TtsCapabilities {
+            // `synthesize` returns only once piper has exited; streaming is
+            // per sentence via `TtsService::synthesize_stream`.
+            streaming: false,
+            ssml: false,
+            sample_rates: vec![16_000, 22_050],
+        }
+    }
+
+    /// Reads the rate from the voice's config (`audio.sample_rate`), falling
+    /// back to piper's usual 22.05 kHz.
+    fn sample_rate(&self, profile: &VoiceProfile) -> u32 {
+        std::fs::read(self.config_path(profile))
+            .ok()
+            .and_then(|config| serde_json::from_slice::<serde_json::Value>(&config).ok())
+            .and_then(|config| config["audio"]["sample_rate"].as_u64())
+            .and_then(|rate| u32::try_from(rate).ok())
+            .unwrap_or(22_050)
+    }
+
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        profile: &'a VoiceProfile,
+    ) -> BoxFuture<'a, Result<Vec<u8>, VoiceError>> {
+        Box::pin(self.run(text, profile))
+    }
+}
+
+/// Bark (Python-based neural TTS).
+///
+/// Expects the binary to be a wrapper script that accepts
+/// `--text <text> --output_raw` and writes raw PCM (s16le) to stdout.
+#[derive(Debug, Clone)]
+pub struct BarkBackend {
+    binary: PathBuf,
+}
+
+impl BarkBackend {
+    pub fn new(binary: impl AsRef<Path>) -> Self {
+        Self {
+            binary: binary.as_ref().to_path_buf(),
+        }
+    }
+
+    async fn run(&self, text: &str) -> Result<Vec<u8>, VoiceError> {
+        if self.binary.as_os_str().is_empty() {
+            return Err(VoiceError::Tts(
+                "Bark TTS binary path is not configured. Set bark_binary_path in config \
+                 or ANNEX_BARK_BINARY_PATH environment variable."
+                    .to_string(),
+            ));
+        }
+
+        if !self.binary.exists() {
+            return Err(VoiceError::Tts(format!(
+                "Bark TTS binary not found: {:?}",
+                self.binary
+            )));
+        }
+
+        let mut command = Command::new(&self.binary);
+        command
+            .arg("--text")
+            .arg(text)
+            .arg("--output_raw")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Cancelled synthesis must not leave the engine running.
+            .kill_on_drop(true);
+
+        let child = command
+            .spawn()
+            .map_err(|e| VoiceError::Tts(format!("Failed to spawn bark: {}", e)))?;
+
+        let output = tokio::time::timeout(TTS_TIMEOUT, child.wait_with_output())
+            .await
+            .map_err(|_| {
+                VoiceError::Tts(format!(
+                    "Bark TTS process timed out after {} seconds",
+                    TTS_TIMEOUT.as_secs()
+                ))
+            })?
+            .map_err(|e| VoiceError::Tts(format!("Failed to wait for bark: {}", e)))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(VoiceError::Tts(format!("Bark failed: {}", stderr)));
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+impl TtsBackend for BarkBackend {
+    fn name(&self) -> &'static str {
+        "bark"
+    }
+
+    fn capabilities(&self) -> TtsCapabilities {
+        TtsCapabilities {
+            streaming: false,
+            ssml: false,
+            sample_rates: vec![24_000],
+        }
+    }
+
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _profile: &'a VoiceProfile,
+    ) -> BoxFuture<'a, Result<Vec<u8>, VoiceError>> {
+        Box::pin(self.run(text))
+    }
+}
+
+/// The system's native TTS engine.
+///
+/// Uses `espeak-ng` as the cross-platform fallback. It writes WAV to stdout
+/// via `--stdout`; the header is stripped to return raw PCM. Text wrapped in
+/// `<speak>` is passed through as SSML.
+#[derive(Debug, Clone, Default)]
+pub struct SystemBackend;
+
+impl SystemBackend {
+    async fn run(&self, text: &str) -> Result<Vec<u8>, VoiceError> {
+        let mut command = Command::new("espeak-ng");
+        if text.trim_start().starts_with("<speak") {
+            command.arg("-m");
+        }
+        command
+            .arg("--stdout")
+            .arg(text)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Cancelled synthesis must not leave the engine running.
+            .kill_on_drop(true);
+
+        let child = command
+            .spawn()
+            .map_err(|e| VoiceError::Tts(format!("Failed to spawn espeak-ng: {}", e)))?;
+
+        let output = tokio::time::timeout(TTS_TIMEOUT, child.wait_with_output())
+            .await
+            .map_err(|_| {
+                VoiceError::Tts(format!(
+                    "System TTS process timed out after {} seconds",
+                    TTS_TIMEOUT.as_secs()
+                ))
+            })?
+            .map_err(|e| VoiceError::Tts(format!("Failed to wait for espeak-ng: {}", e)))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(VoiceError::Tts(format!("espeak-ng failed: {}", stderr)));
+        }
+
+        Ok(strip_wav_header(output.stdout))
+    }
+}
+
+impl TtsBackend for SystemBackend {
+    fn name(&self) -> &'static str {
+        "system"
+    }
+
+    fn capabilities(&self) -> TtsCapabilities {
+        TtsCapabilities {
+            streaming: false,
+            ssml: true,
+            sample_rates: vec![22_050],
+        }
+    }
+
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _profile: &'a VoiceProfile,
+    ) -> BoxFuture<'a, Result<Vec<u8>, VoiceError>> {
+        Box::pin(self.run(text))
+    }
+}
+
+/// A local HTTP TTS server speaking the OpenAI speech API
+/// (`POST {"model", "input", "voice", "response_format": "pcm", "speed"}`
+/// returning audio bytes), as served by openedai-speech, Kokoro-FastAPI and
+/// LocalAI.
+///
+/// The endpoint, model and voice come from each profile's `endpoint`,
+/// `model_path` and `voice`. Servers that answer with WAV despite the `pcm`
+/// format have the header stripped.
+#[derive(Debug, Clone)]
+pub struct HttpTtsBackend {
+    client: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct SpeechRequest<'a> {
+    model: &'a str,
+    input: &'a str,
+    voice: &'a str,
+    response_format: &'static str,
+    speed: f32,
+}
+
+impl HttpTtsBackend {
+    /// Creates a backend whose requests time out after `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `VoiceError::Config` if the HTTP client cannot be built.
+    pub fn new(timeout: Duration) -> Result<Self, VoiceError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| VoiceError::Config(format!("failed to build HTTP client: {}", e)))?;
+        Ok(Self { client })
+    }
+
+    async fn run(&self, text: &str, profile: &VoiceProfile) -> Result<Vec<u8>, VoiceError> {
+        let endpoint = profile.endpoint.as_deref().ok_or_else(|| {
+            VoiceError::Config(format!(
+                "voice profile '{}' uses the http model but has no endpoint",
+                profile.id
+            ))
+        })?;
+        let url = reqwest::Url::parse(endpoint)
+            .map_err(|e| VoiceError::Config(format!("invalid TTS endpoint {}: {}", endpoint, e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(VoiceError::Config(format!(
+                "TTS endpoint must be http or https: {}",
+                endpoint
+            )));
+        }
+
+        let request = SpeechRequest {
+            model: &profile.model_path,
+            input: text,
+            voice: profile.voice.as_deref().unwrap_or("default"),
+            response_format: "pcm",
+            speed: profile.speed,
+        };
+        let response = self
+            .client
+            .post(url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| VoiceError::Tts(format!("TTS server request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(VoiceError::Tts(format!(
+                "TTS server returned {}: {}",
+                status, body
+            )));
+        }
+
+        let audio = response
+            .bytes()
+            .await
+            .map_err(|e| VoiceError::Tts(format!("failed to read TTS server response: {}", e)))?;
+        Ok(strip_wav_header(audio.to_vec()))
+    }
+}
+
+impl TtsBackend for HttpTtsBackend {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn capabilities(&self) -> TtsCapabilities {
+        TtsCapabilities {
+            // The response body is read in full before `synthesize` returns.
+            streaming: false,
+            ssml: false,
+            sample_rates: vec![24_000],
+        }
+    }
+
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        profile: &'a VoiceProfile,
+    ) -> BoxFuture<'a, Result<Vec<u8>, VoiceError>> {
+        Box::pin(self.run(text, profile))
+    }
+}
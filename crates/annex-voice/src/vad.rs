@@ -0,0 +1,211 @@
+//! Voice-activity detection and utterance segmentation for streaming STT.
+//!
+//! A [`Segmenter`] consumes a continuous s16le mono PCM stream and cuts it
+//! into utterances using frame energy: an utterance starts at the first
+//! voiced frame and ends after [`VadConfig::silence_ms`] of silence. While
+//! an utterance is in progress, interim segments carrying the audio so far
+//! are emitted so callers can show partial hypotheses.
+
+/// Tuning for a [`Segmenter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VadConfig {
+    /// Sample rate of the incoming PCM, in Hz.
+    pub sample_rate: u32,
+    /// Length of each analysis frame, in milliseconds.
+    pub frame_ms: u32,
+    /// RMS level (in s16 units) at or above which a frame counts as speech.
+    pub energy_threshold: f32,
+    /// Silence after speech that ends an utterance, in milliseconds.
+    pub silence_ms: u32,
+    /// Longest utterance before it is cut into a new segment, in milliseconds.
+    pub max_segment_ms: u32,
+    /// Audio between interim segments, in milliseconds. Zero disables them.
+    pub partial_interval_ms: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 16_000,
+            frame_ms: 30,
+            energy_threshold: 500.0,
+            silence_ms: 600,
+            max_segment_ms: 15_000,
+            partial_interval_ms: 1_000,
+        }
+    }
+}
+
+/// A span of speech ready for transcription.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Sequence number of the utterance in the stream. Interim segments
+    /// share the id of the final segment that replaces them.
+    pub id: u64,
+    /// PCM audio of the utterance so far.
+    pub audio: Vec<u8>,
+    /// Offset of the start of the utterance in the stream, in milliseconds.
+    pub start_ms: u64,
+    /// Offset of the end of `audio` in the stream, in milliseconds.
+    pub end_ms: u64,
+    /// `false` for interim segments, which a later segment supersedes.
+    pub is_final: bool,
+}
+
+/// Utterance in progress. Positions are in samples.
+#[derive(Debug)]
+struct Utterance {
+    id: u64,
+    start: u64,
+    audio: Vec<u8>,
+    trailing_silence: u64,
+    last_partial: u64,
+}
+
+/// Splits a PCM stream into utterances.
+#[derive(Debug)]
+pub struct Segmenter {
+    config: VadConfig,
+    frame_bytes: usize,
+    /// Bytes of an incomplete frame, kept until the next push.
+    pending: Vec<u8>,
+    /// Samples consumed so far.
+    position: u64,
+    utterance: Option<Utterance>,
+    next_id: u64,
+}
+
+impl Segmenter {
+    pub fn new(config: VadConfig) -> Self {
+        let frame_samples = (config.sample_rate as usize * config.frame_ms as usize / 1000).max(1);
+        Self {
+            config,
+            frame_bytes: frame_samples * 2,
+            pending: Vec::new(),
+            position: 0,
+            utterance: None,
+            next_id: 0,
+        }
+    }
+
+    /// Feeds PCM into the segmenter, returning the segments it completes.
+    pub fn push(&mut self, pcm: &[u8]) -> Vec<Segment> {
+        self.pending.extend_from_slice(pcm);
+        let mut segments = Vec::new();
+        let mut pending = std::mem::take(&mut self.pending);
+        let mut frames = pending.chunks_exact(self.frame_bytes);
+        for frame in &mut frames {
+            self.process_frame(frame, &mut segments);
+        }
+        let rest = frames.remainder().len();
+        pending.drain(..pending.len() - rest);
+        self.pending = pending;
+        segments
+    }
+
+    /// Ends the stream, finalizing any utterance in progress.
+    pub fn flush(&mut self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut pending = std::mem::take(&mut self.pending);
+        pending.truncate(pending.len() & !1);
+        if !pending.is_empty() {
+            self.process_frame(&pending, &mut segments);
+        }
+        self.finish(&mut segments);
+        segments
+    }
+
+    fn process_frame(&mut self, frame: &[u8], segments: &mut Vec<Segment>) {
+        let samples = (frame.len() / 2) as u64;
+        let voiced = rms(frame) >= self.config.energy_threshold;
+
+        match &mut self.utterance {
+            Some(utterance) => {
+                utterance.audio.extend_from_slice(frame);
+                if voiced {
+                    utterance.trailing_silence = 0;
+                } else {
+                    utterance.trailing_silence += samples;
+                }
+            }
+            None if voiced => {
+                self.utterance = Some(Utterance {
+                    id: self.next_id,
+                    start: self.position,
+                    audio: frame.to_vec(),
+                    trailing_silence: 0,
+                    last_partial: 0,
+                });
+                self.next_id += 1;
+            }
+            None => {}
+        }
+        self.position += samples;
+
+        let Some(utterance) = &mut self.utterance else {
+            return;
+        };
+        let length = (utterance.audio.len() / 2) as u64;
+        let rate = self.config.sample_rate;
+        if utterance.trailing_silence >= samples_in(self.config.silence_ms, rate) {
+            let speech = utterance.audio.len() - utterance.trailing_silence as usize * 2;
+            utterance.audio.truncate(speech);
+            self.finish(segments);
+        } else if length >= samples_in(self.config.max_segment_ms, rate) {
+            self.finish(segments);
+        } else if self.config.partial_interval_ms > 0
+            && length - utterance.last_partial >= samples_in(self.config.partial_interval_ms, rate)
+        {
+            utterance.last_partial = length;
+            segments.push(Segment {
+                id: utterance.id,
+                audio: utterance.audio.clone(),
+                start_ms: millis(utterance.start, rate),
+                end_ms: millis(utterance.start + length, rate),
+                is_final: false,
+            });
+        }
+    }
+
+    /// Ends the utterance in progress, if any, with a final segment.
+    fn finish(&mut self, segments: &mut Vec<Segment>) {
+        let Some(utterance) = self.utterance.take() else {
+            return;
+        };
+        let rate = self.config.sample_rate;
+        let end = utterance.start + (utterance.audio.len() / 2) as u64;
+        segments.push(Segment {
+            id: utterance.id,
+            start_ms: millis(utterance.start, rate),
+            end_ms: millis(end, rate),
+            audio: utterance.audio,
+            is_final: true,
+        });
+    }
+}
+
+/// Number of samples in `ms` milliseconds.
+fn samples_in(ms: u32, rate: u32) -> u64 {
+    rate as u64 * ms as u64 / 1000
+}
+
+/// Duration of `samples` samples, in milliseconds.
+fn millis(samples: u64, rate: u32) -> u64 {
+    samples * 1000 / rate.max(1) as u64
+}
+
+/// Root-mean-square level of s16le samples.
+fn rms(frame: &[u8]) -> f32 {
+    let samples = frame.len() / 2;
+    if samples == 0 {
+        return 0.0;
+    }
+    let sum: f64 = frame
+        .chunks_exact(2)
+        .map(|s| {
+            let v = i16::from_le_bytes([s[0], s[1]]) as f64;
+            v * v
+        })
+        .sum();
+    (sum / samples as f64).sqrt() as f32
+}
@@ -0,0 +1,171 @@
+use annex_voice::{Segmenter, StreamingTranscriber, SttService, TranscriptionEvent, VadConfig};
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// `ms` milliseconds of 16 kHz s16le PCM at a constant level.
+fn pcm(ms: usize, level: i16) -> Vec<u8> {
+    level.to_le_bytes().repeat(16 * ms)
+}
+
+fn config() -> VadConfig {
+    VadConfig {
+        frame_ms: 10,
+        silence_ms: 100,
+        max_segment_ms: 1_000,
+        partial_interval_ms: 200,
+        ..VadConfig::default()
+    }
+}
+
+async fn next(rx: &mut broadcast::Receiver<TranscriptionEvent>) -> TranscriptionEvent {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("transcription event")
+        .unwrap()
+}
+
+#[test]
+fn test_segmenter_splits_utterances_at_silence() {
+    let mut segmenter = Segmenter::new(config());
+
+    assert!(segmenter.push(&pcm(300, 0)).is_empty());
+    let mut segments = segmenter.push(&pcm(250, 4_000));
+    segments.extend(segmenter.push(&pcm(150, 0)));
+    segments.extend(segmenter.push(&pcm(50, 4_000)));
+    segments.extend(segmenter.flush());
+
+    let shapes: Vec<_> = segments
+        .iter()
+        .map(|s| (s.id, s.is_final, s.start_ms, s.end_ms, s.audio.len()))
+        .collect();
+    assert_eq!(
+        shapes,
+        vec![
+            // Interim hypothesis after 200 ms of speech.
+            (0, false, 300, 500, 6_400),
+            // Trailing silence is trimmed from the final segment.
+            (0, true, 300, 550, 8_000),
+            // Speech still in progress when the stream ends.
+            (1, true, 700, 750, 1_600),
+        ]
+    );
+}
+
+#[test]
+fn test_segmenter_cuts_long_utterances() {
+    let mut segmenter = Segmenter::new(VadConfig {
+        partial_interval_ms: 0,
+        ..config()
+    });
+
+    let segments = segmenter.push(&pcm(2_500, 4_000));
+    let finals: Vec<_> = segments
+        .iter()
+        .map(|s| (s.id, s.is_final, s.start_ms, s.end_ms))
+        .collect();
+    assert_eq!(finals, vec![(0, true, 0, 1_000), (1, true, 1_000, 2_000)]);
+
+    let rest = segmenter.flush();
+    assert_eq!(rest.len(), 1);
+    assert_eq!((rest[0].start_ms, rest[0].end_ms), (2_000, 2_500));
+}
+
+#[tokio::test]
+async fn test_streaming_transcriber_emits_interim_and_final_events() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script_path = temp_dir.path().join("mock_whisper.sh");
+    // Reports how many bytes of audio it was given.
+    std::fs::write(&script_path, "#!/bin/sh\nwc -c | tr -d ' \\n'").unwrap();
+    std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let (tx, mut rx) = broadcast::channel(16);
+    let transcriber = StreamingTranscriber::new(
+        Arc::new(SttService::new("dummy", &script_path)),
+        config(),
+        "voice-room",
+        tx,
+    );
+
+    transcriber.push("alice", &pcm(250, 4_000)).await.unwrap();
+    let interim = next(&mut rx).await;
+    transcriber.push("alice", &pcm(150, 0)).await.unwrap();
+    let last = next(&mut rx).await;
+    transcriber.push("bob", &pcm(50, 4_000)).await.unwrap();
+    transcriber.end_speech("bob").await.unwrap();
+    let bob = next(&mut rx).await;
+
+    let events = [interim, last, bob];
+    let summary: Vec<_> = events
+        .iter()
+        .map(|e| {
+            (
+                e.speaker_pseudonym.as_str(),
+                e.text.as_str(),
+                e.segment_id,
+                e.is_final,
+                e.start_ms,
+                e.end_ms,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("alice", "6400", 0, false, 0, 200),
+            ("alice", "8000", 0, true, 0, 250),
+            ("bob", "1600", 0, true, 0, 50),
+        ]
+    );
+    assert!(events.iter().all(|e| e.channel_id == "voice-room"));
+}
+
+#[tokio::test]
+async fn test_streaming_transcriber_discards_one_speaker() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let script_path = temp_dir.path().join("mock_whisper.sh");
+    std::fs::write(&script_path, "#!/bin/sh\nwc -c | tr -d ' \\n'").unwrap();
+    std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let (tx, mut rx) = broadcast::channel(16);
+    let transcriber = StreamingTranscriber::new(
+        Arc::new(SttService::new("dummy", &script_path)),
+        config(),
+        "voice-room",
+        tx,
+    );
+
+    // Both speakers are mid-utterance when alice's speech is discarded.
+    transcriber.push("alice", &pcm(150, 4_000)).await.unwrap();
+    transcriber.push("bob", &pcm(150, 4_000)).await.unwrap();
+    transcriber.discard("alice").await;
+    transcriber.end_speech("alice").await.unwrap();
+    transcriber.end_speech("bob").await.unwrap();
+    let bob = next(&mut rx).await;
+
+    // alice's next audio starts a fresh stream.
+    transcriber.push("alice", &pcm(50, 4_000)).await.unwrap();
+    transcriber.end_speech("alice").await.unwrap();
+    let alice = next(&mut rx).await;
+
+    let summary: Vec<_> = [bob, alice]
+        .iter()
+        .map(|e| {
+            (
+                e.speaker_pseudonym.clone(),
+                e.text.clone(),
+                e.start_ms,
+                e.end_ms,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("bob".to_string(), "4800".to_string(), 0, 150),
+            ("alice".to_string(), "1600".to_string(), 0, 50),
+        ]
+    );
+    assert!(rx.try_recv().is_err());
+}
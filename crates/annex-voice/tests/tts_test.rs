@@ -1,6 +1,10 @@
 use annex_types::voice::{VoiceModel, VoiceProfile};
-use annex_voice::{TtsService, VoiceError};
+use annex_voice::{
+    split_sentences, BoxFuture, PiperBackend, TtsBackend, TtsCapabilities, TtsService, VoiceError,
+};
 use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 #[tokio::test]
 async fn test_tts_service_instantiation() {
@@ -19,6 +23,8 @@ async fn test_tts_service_instantiation() {
         speed: 1.0,
         pitch: 1.0,
         speaker_id: None,
+        endpoint: None,
+        voice: None,
     };
 
     service.add_profile(profile.clone()).await;
@@ -55,6 +61,8 @@ async fn test_tts_missing_model_file() {
         speed: 1.0,
         pitch: 1.0,
         speaker_id: None,
+        endpoint: None,
+        voice: None,
     };
 
     service.add_profile(profile).await;
@@ -87,6 +95,8 @@ async fn test_tts_invalid_speed() {
         speed: 0.0,
         pitch: 1.0,
         speaker_id: None,
+        endpoint: None,
+        voice: None,
     };
     service.add_profile(profile_zero).await;
 
@@ -108,6 +118,8 @@ async fn test_tts_invalid_speed() {
         speed: 0.001,
         pitch: 1.0,
         speaker_id: None,
+        endpoint: None,
+        voice: None,
     };
     service.add_profile(profile_tiny).await;
 
@@ -124,9 +136,258 @@ async fn test_tts_invalid_speed() {
         speed: 100.0,
         pitch: 1.0,
         speaker_id: None,
+        endpoint: None,
+        voice: None,
     };
     service.add_profile(profile_high).await;
 
     let result = service.synthesize("Hello", "high-speed").await;
     assert!(matches!(result, Err(VoiceError::Config(_))));
 }
+
+#[derive(Debug)]
+struct EchoBackend;
+
+impl TtsBackend for EchoBackend {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    fn capabilities(&self) -> TtsCapabilities {
+        TtsCapabilities {
+            streaming: false,
+            ssml: true,
+            sample_rates: vec![8_000],
+        }
+    }
+
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _profile: &'a VoiceProfile,
+    ) -> BoxFuture<'a, Result<Vec<u8>, VoiceError>> {
+        Box::pin(async move {
+            match text {
+                "Fail." => Err(VoiceError::Tts("engine crashed".to_string())),
+                "Hang." => std::future::pending().await,
+                _ => Ok(text.as_bytes().to_vec()),
+            }
+        })
+    }
+}
+
+async fn echo_service() -> TtsService {
+    let service = TtsService::new("assets/voices", "piper", "bark");
+    service
+        .register_backend(VoiceModel::System, Arc::new(EchoBackend))
+        .await;
+    service
+        .add_profile(VoiceProfile {
+            id: "echo".to_string(),
+            model: VoiceModel::System,
+            ..VoiceProfile::default()
+        })
+        .await;
+    service
+}
+
+fn http_profile(endpoint: Option<String>) -> VoiceProfile {
+    VoiceProfile {
+        id: "http-voice".to_string(),
+        name: "HTTP Voice".to_string(),
+        model: VoiceModel::Http,
+        model_path: "kokoro".to_string(),
+        config_path: None,
+        speed: 1.5,
+        pitch: 1.0,
+        speaker_id: None,
+        endpoint,
+        voice: Some("af_bella".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_tts_dispatches_to_registered_backend() {
+    let service = echo_service().await;
+
+    assert_eq!(service.synthesize("hi", "echo").await.unwrap(), b"hi");
+    let caps = service.capabilities("echo").await.unwrap();
+    assert!(caps.ssml);
+    assert_eq!(caps.sample_rates, vec![8_000]);
+
+    service.add_profile(VoiceProfile::default()).await;
+    // Piper synthesizes whole utterances; only the service streams.
+    assert!(!service.capabilities("default").await.unwrap().streaming);
+}
+
+#[tokio::test]
+async fn test_http_backend_requests_pcm() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        // Read headers and the JSON body, which ends with a closing brace.
+        while !request.ends_with(b"}") {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        let mut response =
+            b"HTTP/1.1 200 OK\r\nContent-Type: audio/pcm\r\nContent-Length: 4\r\n\r\n".to_vec();
+        response.extend_from_slice(&[1, 2, 3, 4]);
+        socket.write_all(&response).await.unwrap();
+        String::from_utf8(request).unwrap()
+    });
+
+    let service = TtsService::new("assets/voices", "piper", "bark");
+    service
+        .add_profile(http_profile(Some(format!(
+            "http://{}/v1/audio/speech",
+            addr
+        ))))
+        .await;
+
+    assert!(!service.capabilities("http-voice").await.unwrap().streaming);
+    let audio = service.synthesize("Hello", "http-voice").await.unwrap();
+    assert_eq!(audio, vec![1, 2, 3, 4]);
+
+    let request = server.await.unwrap();
+    assert!(request.starts_with("POST /v1/audio/speech"));
+    let body: serde_json::Value =
+        serde_json::from_str(&request[request.find("\r\n\r\n").unwrap() + 4..]).unwrap();
+    assert_eq!(body["model"], "kokoro");
+    assert_eq!(body["input"], "Hello");
+    assert_eq!(body["voice"], "af_bella");
+    assert_eq!(body["response_format"], "pcm");
+    assert_eq!(body["speed"], 1.5);
+}
+
+#[tokio::test]
+async fn test_http_backend_requires_endpoint() {
+    let service = TtsService::new("assets/voices", "piper", "bark");
+    service.add_profile(http_profile(None)).await;
+
+    let result = service.synthesize("Hello", "http-voice").await;
+    assert!(matches!(result, Err(VoiceError::Config(_))), "{:?}", result);
+}
+
+#[test]
+fn test_split_sentences() {
+    assert_eq!(
+        split_sentences("Hello there. How are you?  Fine!\nBye"),
+        vec!["Hello there.", "How are you?", "Fine!", "Bye"]
+    );
+    assert_eq!(
+        split_sentences("He said \"stop.\" Then v1.2 shipped"),
+        vec!["He said \"stop.\"", "Then v1.2 shipped"]
+    );
+    assert_eq!(split_sentences("  "), Vec::<&str>::new());
+    assert_eq!(
+        split_sentences("<speak>One. Two.</speak>"),
+        vec!["<speak>One. Two.</speak>"]
+    );
+}
+
+#[tokio::test]
+async fn test_tts_stream_yields_frames_per_sentence() {
+    let service = echo_service().await;
+
+    // 8 kHz s16 audio makes 100 ms frames 1600 bytes long.
+    let long = "x".repeat(2_000);
+    let text = format!("Hello there. {}. Bye.", long);
+    let mut stream = service.synthesize_stream(&text, "echo").await.unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = stream.next_frame().await {
+        frames.push(frame.unwrap());
+    }
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0], b"Hello there.");
+    assert_eq!(frames[1].len(), 1_600);
+    assert_eq!(frames[2].len(), 401);
+    assert_eq!(frames[3], b"Bye.");
+
+    assert!(matches!(
+        service.synthesize_stream("Hi.", "missing").await,
+        Err(VoiceError::ProfileNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_tts_stream_ready_keeps_first_frame() {
+    let service = echo_service().await;
+    let mut stream = service
+        .synthesize_stream("One. Two.", "echo")
+        .await
+        .unwrap();
+    assert_eq!(stream.sample_rate(), 8_000);
+    assert!(matches!(stream.ready().await, Some(Ok(()))));
+    assert_eq!(stream.next_frame().await.unwrap().unwrap(), b"One.");
+
+    let mut failing = service.synthesize_stream("Fail.", "echo").await.unwrap();
+    assert!(matches!(
+        failing.ready().await,
+        Some(Err(VoiceError::Tts(_)))
+    ));
+    assert!(failing.ready().await.is_none());
+}
+
+#[test]
+fn test_piper_sample_rate_follows_voice_config() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("low.onnx.json"),
+        r#"{"audio":{"sample_rate":16000}}"#,
+    )
+    .unwrap();
+    let backend = PiperBackend::new(dir.path(), "piper");
+
+    let mut profile = VoiceProfile {
+        id: "low".to_string(),
+        name: "Low".to_string(),
+        model: VoiceModel::Piper,
+        model_path: "low.onnx".to_string(),
+        config_path: None,
+        speed: 1.0,
+        pitch: 1.0,
+        speaker_id: None,
+        endpoint: None,
+        voice: None,
+    };
+    assert_eq!(backend.sample_rate(&profile), 16_000);
+
+    // Without a readable config, piper's usual rate is assumed.
+    profile.model_path = "other.onnx".to_string();
+    assert_eq!(backend.sample_rate(&profile), 22_050);
+}
+
+#[tokio::test]
+async fn test_tts_stream_error_ends_stream() {
+    let service = echo_service().await;
+    let mut stream = service
+        .synthesize_stream("One. Fail. Two.", "echo")
+        .await
+        .unwrap();
+    assert_eq!(stream.next_frame().await.unwrap().unwrap(), b"One.");
+    assert!(matches!(
+        stream.next_frame().await,
+        Some(Err(VoiceError::Tts(_)))
+    ));
+    assert!(stream.next_frame().await.is_none());
+}
+
+#[tokio::test]
+async fn test_tts_stream_cancel() {
+    let service = echo_service().await;
+    let mut stream = service
+        .synthesize_stream("One. Hang. Two.", "echo")
+        .await
+        .unwrap();
+    assert_eq!(stream.next_frame().await.unwrap().unwrap(), b"One.");
+
+    stream.cancel();
+    let next = tokio::time::timeout(std::time::Duration::from_secs(1), stream.next_frame())
+        .await
+        .unwrap();
+    assert!(next.is_none());
+}
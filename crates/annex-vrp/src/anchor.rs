@@ -0,0 +1,174 @@
+//! Anchor version history and diffs.
+//!
+//! Every policy update is recorded in `server_policy_versions`. The VRP
+//! anchor only depends on the policy's principles and prohibited actions,
+//! so the anchor history is that table with consecutive versions that left
+//! the anchor unchanged collapsed into the version that introduced it.
+
+use crate::{hash_list, list_difference, VrpAnchorSnapshot};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors that can occur while reading anchor history.
+#[derive(Error, Debug)]
+pub enum AnchorHistoryError {
+    /// A database error occurred.
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    /// A stored policy could not be parsed.
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// One version of a server's VRP anchor.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VrpAnchorVersion {
+    /// The policy version that introduced this anchor.
+    pub version_id: String,
+    /// When that policy version was activated (SQLite `datetime` text).
+    pub activated_at: String,
+    /// The anchor, timestamped with the activation time.
+    pub anchor: VrpAnchorSnapshot,
+}
+
+/// The difference between two anchor versions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VrpAnchorDiff {
+    /// Principles present in the newer anchor only.
+    pub added_principles: Vec<String>,
+    /// Principles present in the older anchor only.
+    pub removed_principles: Vec<String>,
+    /// Prohibited actions present in the newer anchor only.
+    pub added_prohibited_actions: Vec<String>,
+    /// Prohibited actions present in the older anchor only.
+    pub removed_prohibited_actions: Vec<String>,
+}
+
+impl VrpAnchorDiff {
+    /// Returns `true` when the two anchors hold the same principles and
+    /// prohibited actions.
+    pub fn is_empty(&self) -> bool {
+        self.added_principles.is_empty()
+            && self.removed_principles.is_empty()
+            && self.added_prohibited_actions.is_empty()
+            && self.removed_prohibited_actions.is_empty()
+    }
+}
+
+/// The anchor-relevant subset of a stored `ServerPolicy`. Parsed leniently so
+/// versions written by older releases still contribute to the history.
+#[derive(Deserialize)]
+struct StoredAnchorFields {
+    #[serde(default)]
+    principles: Vec<String>,
+    #[serde(default)]
+    prohibited_actions: Vec<String>,
+}
+
+fn version_from_row(
+    version_id: String,
+    activated_at: String,
+    activated_at_secs: i64,
+    policy_json: &str,
+) -> Result<VrpAnchorVersion, AnchorHistoryError> {
+    let fields: StoredAnchorFields = serde_json::from_str(policy_json)?;
+    let anchor = VrpAnchorSnapshot {
+        principles_hash: hash_list(&fields.principles),
+        prohibited_actions_hash: hash_list(&fields.prohibited_actions),
+        timestamp: activated_at_secs.max(0) as u64,
+        principles: fields.principles,
+        prohibited_actions: fields.prohibited_actions,
+        signature: None,
+    };
+    Ok(VrpAnchorVersion {
+        version_id,
+        activated_at,
+        anchor,
+    })
+}
+
+/// Lists the server's anchor versions, oldest first.
+///
+/// Policy versions that did not change the principles or prohibited actions
+/// are folded into the version that introduced the anchor.
+pub fn list_anchor_versions(
+    conn: &Connection,
+    server_id: i64,
+) -> Result<Vec<VrpAnchorVersion>, AnchorHistoryError> {
+    let mut stmt = conn.prepare(
+        "SELECT version_id, activated_at, CAST(strftime('%s', activated_at) AS INTEGER), policy_json
+         FROM server_policy_versions
+         WHERE server_id = ?1
+         ORDER BY id ASC",
+    )?;
+    let rows = stmt.query_map(params![server_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<i64>>(2)?.unwrap_or(0),
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut versions: Vec<VrpAnchorVersion> = Vec::new();
+    for row in rows {
+        let (version_id, activated_at, secs, policy_json) = row?;
+        let version = version_from_row(version_id, activated_at, secs, &policy_json)?;
+        let unchanged = versions.last().is_some_and(|last| {
+            last.anchor.principles_hash == version.anchor.principles_hash
+                && last.anchor.prohibited_actions_hash == version.anchor.prohibited_actions_hash
+        });
+        if !unchanged {
+            versions.push(version);
+        }
+    }
+    Ok(versions)
+}
+
+/// Loads the anchor recorded by a single policy version.
+///
+/// Unlike [`list_anchor_versions`], any policy version can be looked up,
+/// including ones that did not change the anchor.
+pub fn get_anchor_version(
+    conn: &Connection,
+    server_id: i64,
+    version_id: &str,
+) -> Result<Option<VrpAnchorVersion>, AnchorHistoryError> {
+    let row = conn
+        .query_row(
+            "SELECT version_id, activated_at, CAST(strftime('%s', activated_at) AS INTEGER), policy_json
+             FROM server_policy_versions
+             WHERE server_id = ?1 AND version_id = ?2",
+            params![server_id, version_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                    row.get::<_, String>(3)?,
+                ))
+            },
+        )
+        .optional()?;
+
+    row.map(|(version_id, activated_at, secs, policy_json)| {
+        version_from_row(version_id, activated_at, secs, &policy_json)
+    })
+    .transpose()
+}
+
+/// Computes what changed from anchor `from` to anchor `to`.
+pub fn diff_anchors(from: &VrpAnchorSnapshot, to: &VrpAnchorSnapshot) -> VrpAnchorDiff {
+    VrpAnchorDiff {
+        added_principles: list_difference(&to.principles, &from.principles),
+        removed_principles: list_difference(&from.principles, &to.principles),
+        added_prohibited_actions: list_difference(&to.prohibited_actions, &from.prohibited_actions),
+        removed_prohibited_actions: list_difference(
+            &from.prohibited_actions,
+            &to.prohibited_actions,
+        ),
+    }
+}
@@ -0,0 +1,325 @@
+//! Configurable [`SemanticEmbedder`] implementations.
+//!
+//! The embedder used for VRP principle comparison is selected by
+//! [`EmbedderConfig`]:
+//!
+//! | `kind` | Implementation | Notes |
+//! |--------|----------------|-------|
+//! | `bag_of_words` | [`BagOfWordsEmbedder`] | Default. Exact token overlap only. |
+//! | `tf_idf` | [`TfIdfEmbedder`] | Stemmed tokens weighted by rarity across both sides. |
+//! | `http` | [`HttpEmbedder`] | Local OpenAI-compatible `/v1/embeddings` endpoint. |
+//! | `onnx` | `OnnxEmbedder` | Local sentence-embedding model; requires the `onnx` feature. |
+
+use crate::semantic::{tokenize, BagOfWordsEmbedder, SemanticEmbedder};
+use crate::VrpError;
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Which embedder implementation to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbedderKind {
+    /// Bag-of-words term frequencies (no external dependencies).
+    #[default]
+    BagOfWords,
+    /// TF-IDF over Porter-stemmed tokens.
+    TfIdf,
+    /// A local HTTP embedding service.
+    Http,
+    /// A local ONNX sentence-embedding model.
+    Onnx,
+}
+
+impl std::str::FromStr for EmbedderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bag_of_words" => Ok(Self::BagOfWords),
+            "tf_idf" => Ok(Self::TfIdf),
+            "http" => Ok(Self::Http),
+            "onnx" => Ok(Self::Onnx),
+            other => Err(format!(
+                "expected one of [bag_of_words,tf_idf,http,onnx], got '{}'",
+                other
+            )),
+        }
+    }
+}
+
+/// Embedder selection and settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbedderConfig {
+    /// The embedder implementation.
+    #[serde(default)]
+    pub kind: EmbedderKind,
+    /// Endpoint URL for the `http` embedder
+    /// (e.g. `http://127.0.0.1:11434/v1/embeddings`).
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Model name sent to the `http` embedder, if the service needs one.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Path to the `.onnx` model file for the `onnx` embedder.
+    #[serde(default)]
+    pub model_path: Option<String>,
+    /// Path to the WordPiece `vocab.txt` for the `onnx` embedder.
+    #[serde(default)]
+    pub vocab_path: Option<String>,
+    /// Request timeout for the `http` embedder, in milliseconds.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    5_000
+}
+
+impl Default for EmbedderConfig {
+    fn default() -> Self {
+        Self {
+            kind: EmbedderKind::default(),
+            url: None,
+            model: None,
+            model_path: None,
+            vocab_path: None,
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+/// Builds the embedder described by `config`.
+///
+/// # Errors
+///
+/// Returns `VrpError::Embedder` if a required setting is missing, the model
+/// cannot be loaded, or the `onnx` kind is selected without the `onnx`
+/// feature.
+pub fn build_embedder(config: &EmbedderConfig) -> Result<Arc<dyn SemanticEmbedder>, VrpError> {
+    match config.kind {
+        EmbedderKind::BagOfWords => Ok(Arc::new(BagOfWordsEmbedder::new())),
+        EmbedderKind::TfIdf => Ok(Arc::new(TfIdfEmbedder::new())),
+        EmbedderKind::Http => {
+            let url = config.url.clone().ok_or_else(|| {
+                VrpError::Embedder("the http embedder requires a url".to_string())
+            })?;
+            Ok(Arc::new(HttpEmbedder::new(
+                url,
+                config.model.clone(),
+                Duration::from_millis(config.timeout_ms),
+            )?))
+        }
+        #[cfg(feature = "onnx")]
+        EmbedderKind::Onnx => {
+            let (Some(model_path), Some(vocab_path)) = (&config.model_path, &config.vocab_path)
+            else {
+                return Err(VrpError::Embedder(
+                    "the onnx embedder requires model_path and vocab_path".to_string(),
+                ));
+            };
+            Ok(Arc::new(crate::onnx::OnnxEmbedder::load(
+                model_path, vocab_path,
+            )?))
+        }
+        #[cfg(not(feature = "onnx"))]
+        EmbedderKind::Onnx => Err(VrpError::Embedder(
+            "the onnx embedder requires annex-vrp to be built with the `onnx` feature".to_string(),
+        )),
+    }
+}
+
+/// A TF-IDF embedder over Porter-stemmed tokens.
+///
+/// Stemming lets "protecting privacy" and "protect private data" share
+/// terms, and IDF weighting keeps words that appear in every principle
+/// (e.g. "must", "all") from dominating the score. The vocabulary and
+/// document frequencies come from a corpus: either one fitted up front with
+/// [`TfIdfEmbedder::fit`], or the batch passed to
+/// [`SemanticEmbedder::embed_batch`], so both sides of a comparison share
+/// one space.
+pub struct TfIdfEmbedder {
+    stemmer: Stemmer,
+    /// Stemmed term → dimension index, from the fitted corpus.
+    vocab: HashMap<String, usize>,
+    /// Smoothed IDF weight of each dimension.
+    idf: Vec<f32>,
+}
+
+impl TfIdfEmbedder {
+    pub fn new() -> Self {
+        Self {
+            stemmer: Stemmer::create(Algorithm::English),
+            vocab: HashMap::new(),
+            idf: Vec::new(),
+        }
+    }
+
+    /// Fits the vocabulary and document frequencies over a corpus, so that
+    /// texts embedded one at a time land in the same space.
+    pub fn fit(&mut self, texts: &[String]) {
+        let documents: Vec<Vec<String>> = texts.iter().map(|text| self.terms(text)).collect();
+
+        self.vocab = documents
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, term)| (term, idx))
+            .collect();
+
+        let mut document_frequency = vec![0usize; self.vocab.len()];
+        for document in &documents {
+            let unique: BTreeSet<usize> = document.iter().map(|term| self.vocab[term]).collect();
+            for idx in unique {
+                document_frequency[idx] += 1;
+            }
+        }
+
+        // Smoothed IDF, as in scikit-learn: never zero, so a term shared by
+        // every document still contributes.
+        let n = documents.len() as f32;
+        self.idf = document_frequency
+            .iter()
+            .map(|&df| ((1.0 + n) / (1.0 + df as f32)).ln() + 1.0)
+            .collect();
+    }
+
+    fn terms(&self, text: &str) -> Vec<String> {
+        tokenize(text)
+            .iter()
+            .map(|word| self.stemmer.stem(word).into_owned())
+            .collect()
+    }
+}
+
+impl Default for TfIdfEmbedder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SemanticEmbedder for TfIdfEmbedder {
+    /// Embeds `text` in the fitted vocabulary. Terms outside the corpus are
+    /// ignored.
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        if self.vocab.is_empty() {
+            return Err("Vocabulary not fitted — call fit first".to_string());
+        }
+        let mut vec = vec![0.0f32; self.vocab.len()];
+        for term in self.terms(text) {
+            if let Some(&idx) = self.vocab.get(&term) {
+                vec[idx] += 1.0;
+            }
+        }
+        for (value, weight) in vec.iter_mut().zip(&self.idf) {
+            *value *= weight;
+        }
+        let norm: f32 = vec.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for v in &mut vec {
+                *v /= norm;
+            }
+        }
+        Ok(vec)
+    }
+
+    /// Uses the fitted corpus if there is one, otherwise fits the batch
+    /// itself.
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        if !self.vocab.is_empty() {
+            return texts.iter().map(|text| self.embed(text)).collect();
+        }
+        let mut embedder = TfIdfEmbedder::new();
+        embedder.fit(texts);
+        if embedder.vocab.is_empty() {
+            return Ok(vec![Vec::new(); texts.len()]);
+        }
+        texts.iter().map(|text| embedder.embed(text)).collect()
+    }
+}
+
+/// An embedder backed by a local HTTP service speaking the OpenAI
+/// embeddings API (`POST {"input": [...], "model": ...}` returning
+/// `{"data": [{"embedding": [...]}]}`), as served by Ollama, llama.cpp and
+/// text-embeddings-inference.
+///
+/// Calls are blocking; VRP validation runs on blocking worker threads. The
+/// embedder must likewise be created outside an async context, since
+/// building a blocking client there panics.
+pub struct HttpEmbedder {
+    client: reqwest::blocking::Client,
+    url: String,
+    model: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    input: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    #[serde(default)]
+    index: Option<usize>,
+}
+
+impl HttpEmbedder {
+    /// Creates an embedder for the given endpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns `VrpError::Embedder` if the HTTP client cannot be built.
+    pub fn new(url: String, model: Option<String>, timeout: Duration) -> Result<Self, VrpError> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| VrpError::Embedder(format!("failed to build http client: {}", e)))?;
+        Ok(Self { client, url, model })
+    }
+}
+
+impl SemanticEmbedder for HttpEmbedder {
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        let mut vectors = self.embed_batch(&[text.to_string()])?;
+        Ok(vectors.remove(0))
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&EmbeddingRequest {
+                input: texts,
+                model: self.model.as_deref(),
+            })
+            .send()
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("embedding request failed: {}", e))?;
+        let mut body: EmbeddingResponse = response
+            .json()
+            .map_err(|e| format!("invalid embedding response: {}", e))?;
+
+        if body.data.len() != texts.len() {
+            return Err(format!(
+                "embedding service returned {} vectors for {} inputs",
+                body.data.len(),
+                texts.len()
+            ));
+        }
+        body.data.sort_by_key(|d| d.index.unwrap_or(0));
+        Ok(body.data.into_iter().map(|d| d.embedding).collect())
+    }
+}
@@ -17,6 +17,11 @@
 //! current skeleton provides the module structure that will be filled in
 //! during that phase.
 
+pub mod anchor;
+pub mod embedders;
+pub mod negotiation;
+#[cfg(feature = "onnx")]
+pub mod onnx;
 pub mod reputation;
 pub mod semantic;
 pub mod server_root;
@@ -25,19 +30,35 @@ pub mod types;
 #[cfg(test)]
 mod tests;
 
-pub use reputation::{check_reputation_score, record_vrp_outcome, ReputationError};
+pub use anchor::{
+    diff_anchors, get_anchor_version, list_anchor_versions, AnchorHistoryError, VrpAnchorDiff,
+    VrpAnchorVersion,
+};
+pub use embedders::{build_embedder, EmbedderConfig, EmbedderKind, HttpEmbedder, TfIdfEmbedder};
+pub use negotiation::{
+    get_negotiation, insert_negotiation, respond_to_offer, update_negotiation, NegotiationError,
+    VrpContractOffer, VrpNegotiation, VrpNegotiationMessage, VrpNegotiationResponse,
+    VrpNegotiationStatus, MAX_NEGOTIATION_ROUNDS,
+};
+#[cfg(feature = "onnx")]
+pub use onnx::OnnxEmbedder;
+pub use reputation::{
+    check_reputation_score, compute_reputation, record_reputation_signal, record_vrp_outcome,
+    ReputationError, ReputationSignalKind, ReputationSummary,
+};
+pub use semantic::{BagOfWordsEmbedder, SemanticEmbedder};
 pub use server_root::ServerPolicyRoot;
 pub use types::{
-    VrpAlignmentConfig, VrpAlignmentStatus, VrpAnchorSnapshot, VrpCapabilitySharingContract,
-    VrpError, VrpFederationHandshake, VrpTransferAcceptanceConfig, VrpTransferAcceptanceError,
-    VrpTransferScope, VrpValidationReport,
+    VrpAlignmentConfig, VrpAlignmentExplanation, VrpAlignmentStatus, VrpAnchorSnapshot,
+    VrpCapabilitySharingContract, VrpError, VrpFederationHandshake, VrpPrincipleMatch,
+    VrpTransferAcceptanceConfig, VrpTransferAcceptanceError, VrpTransferScope, VrpValidationReport,
 };
 
 use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 
 /// Creates a SHA256 hash of a list of strings, sorted to ensure determinism.
-fn hash_list(items: &[String]) -> String {
+pub(crate) fn hash_list(items: &[String]) -> String {
     let mut sorted_items = items.to_vec();
     sorted_items.sort();
     let mut hasher = Sha256::new();
@@ -66,26 +87,111 @@ impl VrpAnchorSnapshot {
             timestamp,
             principles: principles.to_vec(),
             prohibited_actions: prohibited_actions.to_vec(),
+            signature: None,
         })
     }
+
+    /// Returns the bytes a server signs to vouch for this anchor.
+    ///
+    /// Covers both hashes and the timestamp, newline-delimited, so a peer can
+    /// check that a (re)handshake carries an anchor the server actually
+    /// published. The principle texts are bound through their hashes.
+    pub fn signing_payload(&self) -> String {
+        format!(
+            "annex:vrp:anchor:v1\n{}\n{}\n{}",
+            self.principles_hash, self.prohibited_actions_hash, self.timestamp
+        )
+    }
+
+    /// Returns the bytes a server signs when publishing this anchor as part
+    /// of its history.
+    ///
+    /// Uses its own domain tag so archived anchors cannot be replayed as a
+    /// live anchor in a handshake.
+    pub fn history_signing_payload(&self) -> String {
+        format!(
+            "annex:vrp:anchor-history:v1\n{}\n{}\n{}",
+            self.principles_hash, self.prohibited_actions_hash, self.timestamp
+        )
+    }
+
+    /// Returns whether the principle texts hash to the advertised hashes, so
+    /// a signature over the hashes also vouches for the texts. Empty lists
+    /// mean the texts were not shared and always match.
+    pub fn texts_match_hashes(&self) -> bool {
+        (self.principles.is_empty() || hash_list(&self.principles) == self.principles_hash)
+            && (self.prohibited_actions.is_empty()
+                || hash_list(&self.prohibited_actions) == self.prohibited_actions_hash)
+    }
 }
 
 /// Compares two anchor snapshots to determine alignment status.
 ///
 /// 1. Exact hash match on both principles and prohibited actions → `Aligned`
-/// 2. If hashes differ and original text is available on both sides, computes
-///    bag-of-words semantic similarity. Score >= `config.min_alignment_score` → `Partial`
+/// 2. If hashes differ, `config.semantic_alignment_required` is set and
+///    original text is available on both sides, computes semantic similarity
+///    with `embedder`. Score >= `config.min_alignment_score` → `Partial`
 /// 3. Otherwise → `Conflict`
+///
+/// # Errors
+///
+/// Returns [`VrpError::Embedder`] if the embedder fails. An embedder outage
+/// says nothing about the peer, so it is not reported as a conflict.
 pub fn compare_peer_anchor(
     local: &VrpAnchorSnapshot,
     remote: &VrpAnchorSnapshot,
     config: &VrpAlignmentConfig,
-) -> VrpAlignmentStatus {
+    embedder: &dyn SemanticEmbedder,
+) -> Result<VrpAlignmentStatus, VrpError> {
+    Ok(explain_peer_anchor(local, remote, config, embedder)?.0)
+}
+
+/// Like [`compare_peer_anchor`], but also returns the principle match
+/// scores and prohibited-action differences behind the status.
+///
+/// The capability and transfer-scope fields of the returned explanation are
+/// left empty; [`validate_federation_handshake`] fills them in. Principle
+/// scores are only computed, and the embedder only called, when
+/// `config.semantic_alignment_required` is set.
+///
+/// # Errors
+///
+/// Returns [`VrpError::Embedder`] if the embedder fails.
+pub fn explain_peer_anchor(
+    local: &VrpAnchorSnapshot,
+    remote: &VrpAnchorSnapshot,
+    config: &VrpAlignmentConfig,
+    embedder: &dyn SemanticEmbedder,
+) -> Result<(VrpAlignmentStatus, VrpAlignmentExplanation), VrpError> {
+    let mut explanation = VrpAlignmentExplanation::default();
+
     // Fast path: exact hash match
     if local.principles_hash == remote.principles_hash
         && local.prohibited_actions_hash == remote.prohibited_actions_hash
     {
-        return VrpAlignmentStatus::Aligned;
+        return Ok((VrpAlignmentStatus::Aligned, explanation));
+    }
+
+    explanation.prohibited_actions_only_local =
+        list_difference(&local.prohibited_actions, &remote.prohibited_actions);
+    explanation.prohibited_actions_only_remote =
+        list_difference(&remote.prohibited_actions, &local.prohibited_actions);
+
+    // When semantic alignment is in use, principle scores are computed
+    // whenever text is available so operators can see how far apart the
+    // peers are, even if the outcome is already decided by the prohibited
+    // actions. The embedder may be a remote service, so it is not called
+    // when its score could not matter.
+    if config.semantic_alignment_required
+        && local.principles_hash != remote.principles_hash
+        && !local.principles.is_empty()
+        && !remote.principles.is_empty()
+    {
+        let (score, matches) =
+            semantic::explain_semantic_alignment(&local.principles, &remote.principles, embedder)
+                .map_err(VrpError::Embedder)?;
+        explanation.semantic_score = Some(score);
+        explanation.principle_matches = matches;
     }
 
     // Prohibited-action divergence is an immediate conflict regardless of
@@ -93,35 +199,34 @@ pub fn compare_peer_anchor(
     // let peers with conflicting safety boundaries negotiate transfer scopes
     // they shouldn't have.
     if local.prohibited_actions_hash != remote.prohibited_actions_hash {
-        return VrpAlignmentStatus::Conflict;
+        return Ok((VrpAlignmentStatus::Conflict, explanation));
     }
 
     // Semantic alignment: compare original principle text when available.
-    // Only reachable when prohibited actions already match (above).
-    if config.semantic_alignment_required
-        && !local.principles.is_empty()
-        && !remote.principles.is_empty()
-    {
-        let mut embedder = semantic::BagOfWordsEmbedder::new();
-        // Build shared vocabulary from both sides
-        let all_texts: Vec<String> = local
-            .principles
-            .iter()
-            .chain(remote.principles.iter())
-            .cloned()
-            .collect();
-        embedder.build_vocab(&all_texts);
-
-        if let Ok(score) =
-            semantic::calculate_semantic_alignment(&local.principles, &remote.principles, &embedder)
-        {
-            if score >= config.min_alignment_score {
-                return VrpAlignmentStatus::Partial;
-            }
+    // Only reachable when prohibited actions already match (above), and only
+    // scored when semantic alignment is required.
+    if let Some(score) = explanation.semantic_score {
+        if score >= config.min_alignment_score {
+            return Ok((VrpAlignmentStatus::Partial, explanation));
         }
     }
 
-    VrpAlignmentStatus::Conflict
+    Ok((VrpAlignmentStatus::Conflict, explanation))
+}
+
+/// Returns the items of `items` that are not in `other`, preserving order.
+pub(crate) fn list_difference(items: &[String], other: &[String]) -> Vec<String> {
+    let other: HashSet<&String> = other.iter().collect();
+    items
+        .iter()
+        .filter(|item| !other.contains(item))
+        .cloned()
+        .collect()
+}
+
+/// Returns the capabilities in `required` that `offered` does not include.
+pub fn missing_capabilities(required: &[String], offered: &[String]) -> Vec<String> {
+    list_difference(required, offered)
 }
 
 /// Validates that capability contracts are mutually compatible.
@@ -133,20 +238,9 @@ pub fn contracts_mutually_accepted(
     local: &VrpCapabilitySharingContract,
     remote: &VrpCapabilitySharingContract,
 ) -> bool {
-    let local_offered: HashSet<String> = local.offered_capabilities.iter().cloned().collect();
-    let remote_offered: HashSet<String> = remote.offered_capabilities.iter().cloned().collect();
-
-    let remote_required_satisfied = remote
-        .required_capabilities
-        .iter()
-        .all(|req| local_offered.contains(req));
-
-    let local_required_satisfied = local
-        .required_capabilities
-        .iter()
-        .all(|req| remote_offered.contains(req));
-
-    remote_required_satisfied && local_required_satisfied
+    missing_capabilities(&remote.required_capabilities, &local.offered_capabilities).is_empty()
+        && missing_capabilities(&local.required_capabilities, &remote.offered_capabilities)
+            .is_empty()
 }
 
 /// Resolves the transfer scope based on alignment status and local acceptance config.
@@ -154,45 +248,101 @@ pub fn resolve_transfer_scope(
     status: VrpAlignmentStatus,
     config: &VrpTransferAcceptanceConfig,
 ) -> VrpTransferScope {
+    resolve_transfer_scope_with_reason(status, config).0
+}
+
+/// Resolves the transfer scope and explains which rule produced it.
+fn resolve_transfer_scope_with_reason(
+    status: VrpAlignmentStatus,
+    config: &VrpTransferAcceptanceConfig,
+) -> (VrpTransferScope, &'static str) {
     match status {
         VrpAlignmentStatus::Aligned => {
             if config.allow_full_knowledge {
-                VrpTransferScope::FullKnowledgeBundle
+                (
+                    VrpTransferScope::FullKnowledgeBundle,
+                    "aligned and full knowledge bundles are allowed",
+                )
             } else if config.allow_reflection_summaries {
-                VrpTransferScope::ReflectionSummariesOnly
+                (
+                    VrpTransferScope::ReflectionSummariesOnly,
+                    "aligned, but local policy only allows reflection summaries",
+                )
             } else {
-                VrpTransferScope::NoTransfer
+                (
+                    VrpTransferScope::NoTransfer,
+                    "aligned, but local policy allows no transfers",
+                )
             }
         }
         VrpAlignmentStatus::Partial => {
             if config.allow_reflection_summaries {
-                VrpTransferScope::ReflectionSummariesOnly
+                (
+                    VrpTransferScope::ReflectionSummariesOnly,
+                    "partial alignment limits transfers to reflection summaries",
+                )
             } else {
-                VrpTransferScope::NoTransfer
+                (
+                    VrpTransferScope::NoTransfer,
+                    "partial alignment and local policy does not allow reflection summaries",
+                )
             }
         }
-        VrpAlignmentStatus::Conflict => VrpTransferScope::NoTransfer,
+        VrpAlignmentStatus::Conflict => (
+            VrpTransferScope::NoTransfer,
+            "alignment conflict prohibits all transfers",
+        ),
     }
 }
 
 /// Validates a full federation handshake against local policy and state.
+///
+/// The returned report carries a [`VrpAlignmentExplanation`] with the
+/// per-principle scores, prohibited-action differences, missing capabilities
+/// and transfer scope reason.
+///
+/// # Errors
+///
+/// Returns [`VrpError::Embedder`] if the embedder fails while comparing
+/// anchors.
 pub fn validate_federation_handshake(
     local_anchor: &VrpAnchorSnapshot,
     local_contract: &VrpCapabilitySharingContract,
     handshake: &VrpFederationHandshake,
     alignment_config: &VrpAlignmentConfig,
     transfer_config: &VrpTransferAcceptanceConfig,
-) -> VrpValidationReport {
+    embedder: &dyn SemanticEmbedder,
+) -> Result<VrpValidationReport, VrpError> {
     // 1. Compare anchors
-    let alignment_status =
-        compare_peer_anchor(local_anchor, &handshake.anchor_snapshot, alignment_config);
+    let (alignment_status, mut explanation) = explain_peer_anchor(
+        local_anchor,
+        &handshake.anchor_snapshot,
+        alignment_config,
+        embedder,
+    )?;
+
+    let mut notes = Vec::new();
+    if !explanation.prohibited_actions_only_local.is_empty()
+        || !explanation.prohibited_actions_only_remote.is_empty()
+    {
+        notes.push("Prohibited actions differ".to_string());
+    }
 
     // 2. Check capability contracts
-    let contracts_ok = contracts_mutually_accepted(local_contract, &handshake.capability_contract);
+    let remote_contract = &handshake.capability_contract;
+    explanation.capabilities_missing_from_remote = missing_capabilities(
+        &local_contract.required_capabilities,
+        &remote_contract.offered_capabilities,
+    );
+    explanation.capabilities_missing_from_local = missing_capabilities(
+        &remote_contract.required_capabilities,
+        &local_contract.offered_capabilities,
+    );
+    let contracts_ok = explanation.capabilities_missing_from_remote.is_empty()
+        && explanation.capabilities_missing_from_local.is_empty();
 
-    let mut notes = Vec::new();
     let final_status = if !contracts_ok {
-        notes.push("Capability contracts incompatible".to_string());
+        notes.insert(0, "Capability contracts incompatible".to_string());
         // Downgrade status if contracts fail.
         // Even if Aligned on principles, incompatible capabilities mean we can't fully interoperate.
         // We treat this as a conflict for now to prevent broken connections.
@@ -202,20 +352,29 @@ pub fn validate_federation_handshake(
     };
 
     // 3. Resolve transfer scope
-    let transfer_scope = resolve_transfer_scope(final_status, transfer_config);
-
-    // Score is 1.0 for Aligned, 0.0 for Conflict (placeholder for now)
-    let alignment_score = match final_status {
-        VrpAlignmentStatus::Aligned => 1.0,
-        VrpAlignmentStatus::Partial => 0.5,
-        VrpAlignmentStatus::Conflict => 0.0,
+    let (transfer_scope, reason) =
+        resolve_transfer_scope_with_reason(final_status, transfer_config);
+    explanation.transfer_scope_reason = if !contracts_ok {
+        format!("capability contracts incompatible; {}", reason)
+    } else {
+        reason.to_string()
     };
 
-    VrpValidationReport {
+    Ok(VrpValidationReport {
         alignment_status: final_status,
         transfer_scope,
-        alignment_score,
+        alignment_score: status_score(final_status),
         negotiation_notes: notes,
+        explanation,
+    })
+}
+
+/// Score is 1.0 for Aligned, 0.0 for Conflict (placeholder for now)
+pub(crate) fn status_score(status: VrpAlignmentStatus) -> f32 {
+    match status {
+        VrpAlignmentStatus::Aligned => 1.0,
+        VrpAlignmentStatus::Partial => 0.5,
+        VrpAlignmentStatus::Conflict => 0.0,
     }
 }
 
@@ -0,0 +1,539 @@
+//! Multi-round VRP capability contract negotiation.
+//!
+//! A single-shot handshake rejects a peer outright when contracts do not
+//! line up. Negotiation instead lets the peer open with an offer, the local
+//! side answer with a counter-offer that drops optional capabilities or
+//! narrows the transfer scope, and either side accept or reject, for up to
+//! [`MAX_NEGOTIATION_ROUNDS`] rounds. Open negotiations are persisted in
+//! `vrp_negotiations` and expire after the policy's timeout.
+
+use crate::types::{
+    VrpAlignmentExplanation, VrpAlignmentStatus, VrpAnchorSnapshot, VrpCapabilitySharingContract,
+    VrpTransferScope, VrpValidationReport,
+};
+use crate::{list_difference, status_score};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Maximum number of offers a peer may make before the negotiation is
+/// rejected.
+pub const MAX_NEGOTIATION_ROUNDS: u32 = 5;
+
+/// One side's terms in a contract negotiation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VrpContractOffer {
+    /// Capabilities this side will not give up.
+    pub required_capabilities: Vec<String>,
+    /// Capabilities this side wants but will drop if the counterparty does
+    /// not offer them.
+    #[serde(default)]
+    pub optional_capabilities: Vec<String>,
+    /// Capabilities this side offers to the counterparty.
+    pub offered_capabilities: Vec<String>,
+    /// Knowledge domains that must not be shared in RTX transfers.
+    #[serde(default)]
+    pub redacted_topics: Vec<String>,
+    /// The widest transfer scope this side is willing to agree to.
+    pub transfer_scope: VrpTransferScope,
+}
+
+impl VrpContractOffer {
+    /// Builds an offer from a capability contract, with no optional
+    /// capabilities.
+    pub fn from_contract(
+        contract: &VrpCapabilitySharingContract,
+        transfer_scope: VrpTransferScope,
+    ) -> Self {
+        Self {
+            required_capabilities: contract.required_capabilities.clone(),
+            optional_capabilities: Vec::new(),
+            offered_capabilities: contract.offered_capabilities.clone(),
+            redacted_topics: contract.redacted_topics.clone(),
+            transfer_scope,
+        }
+    }
+
+    /// The capability contract these terms describe. Optional capabilities
+    /// still on the table count as required.
+    pub fn to_contract(&self) -> VrpCapabilitySharingContract {
+        let mut required_capabilities = self.required_capabilities.clone();
+        required_capabilities.extend(self.optional_capabilities.iter().cloned());
+        VrpCapabilitySharingContract {
+            required_capabilities,
+            offered_capabilities: self.offered_capabilities.clone(),
+            redacted_topics: self.redacted_topics.clone(),
+        }
+    }
+}
+
+/// The local side's answer to an offer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum VrpNegotiationResponse {
+    /// The offer is accepted as the agreed contract.
+    Accept { terms: VrpContractOffer },
+    /// The local side's revised terms, with notes on what changed.
+    CounterOffer {
+        terms: VrpContractOffer,
+        notes: Vec<String>,
+    },
+    /// The negotiation is over without agreement.
+    Reject { reason: String },
+}
+
+/// A peer's next step in an open negotiation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum VrpNegotiationMessage {
+    /// Revised terms from the peer.
+    CounterOffer { terms: VrpContractOffer },
+    /// The peer accepts the local side's last counter-offer.
+    Accept,
+    /// The peer walks away.
+    Reject {
+        #[serde(default)]
+        reason: Option<String>,
+    },
+}
+
+/// Lifecycle state of a negotiation.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum VrpNegotiationStatus {
+    /// Waiting for the peer's next message.
+    Open,
+    /// Both sides agreed on a contract.
+    Accepted,
+    /// One side rejected, or the round limit was reached.
+    Rejected,
+    /// The peer did not respond before the timeout.
+    Expired,
+}
+
+impl fmt::Display for VrpNegotiationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VrpNegotiationStatus::Open => write!(f, "OPEN"),
+            VrpNegotiationStatus::Accepted => write!(f, "ACCEPTED"),
+            VrpNegotiationStatus::Rejected => write!(f, "REJECTED"),
+            VrpNegotiationStatus::Expired => write!(f, "EXPIRED"),
+        }
+    }
+}
+
+impl FromStr for VrpNegotiationStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "OPEN" => Ok(VrpNegotiationStatus::Open),
+            "ACCEPTED" => Ok(VrpNegotiationStatus::Accepted),
+            "REJECTED" => Ok(VrpNegotiationStatus::Rejected),
+            "EXPIRED" => Ok(VrpNegotiationStatus::Expired),
+            _ => Err(format!("unknown negotiation status: {}", s)),
+        }
+    }
+}
+
+/// Errors that can occur during contract negotiation.
+#[derive(Error, Debug)]
+pub enum NegotiationError {
+    /// A database error occurred.
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    /// A serialization error occurred.
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    /// No negotiation with this ID exists for this peer.
+    #[error("negotiation not found")]
+    NotFound,
+    /// The negotiation is no longer open.
+    #[error("negotiation is {0}")]
+    Closed(VrpNegotiationStatus),
+}
+
+/// A persisted negotiation with one peer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VrpNegotiation {
+    pub id: String,
+    pub peer_id: String,
+    /// `AI_AGENT` or `SERVER`, as in `vrp_handshake_log`.
+    pub peer_type: String,
+    pub status: VrpNegotiationStatus,
+    /// Number of offers the peer has made.
+    pub round: u32,
+    /// Alignment of the peer's anchor, fixed when the negotiation opens.
+    pub alignment_status: VrpAlignmentStatus,
+    pub anchor_snapshot: VrpAnchorSnapshot,
+    pub explanation: VrpAlignmentExplanation,
+    /// The peer's latest terms; the agreed terms once accepted.
+    pub peer_offer: VrpContractOffer,
+    /// The local side's latest terms.
+    pub local_offer: VrpContractOffer,
+    pub outcome_reason: Option<String>,
+    pub expires_at: String,
+}
+
+/// Decides how to answer `offer` given the local side's `local` terms.
+///
+/// - A required capability on either side that the other does not offer
+///   rejects the offer.
+/// - If the offer asks for optional capabilities the local side does not
+///   offer, or a wider transfer scope than `local.transfer_scope`, the answer
+///   is a counter-offer with the local terms narrowed to what the offer can
+///   satisfy.
+/// - Otherwise the offer is accepted. Local optional capabilities the peer
+///   does not offer are dropped silently.
+pub fn respond_to_offer(
+    local: &VrpContractOffer,
+    offer: &VrpContractOffer,
+) -> VrpNegotiationResponse {
+    let peer_unmet = list_difference(&offer.required_capabilities, &local.offered_capabilities);
+    if !peer_unmet.is_empty() {
+        return VrpNegotiationResponse::Reject {
+            reason: format!(
+                "peer requires capabilities we do not offer: {}",
+                peer_unmet.join(", ")
+            ),
+        };
+    }
+    let local_unmet = list_difference(&local.required_capabilities, &offer.offered_capabilities);
+    if !local_unmet.is_empty() {
+        return VrpNegotiationResponse::Reject {
+            reason: format!(
+                "peer does not offer required capabilities: {}",
+                local_unmet.join(", ")
+            ),
+        };
+    }
+
+    let peer_optional_unmet =
+        list_difference(&offer.optional_capabilities, &local.offered_capabilities);
+    let transfer_scope = offer.transfer_scope.min(local.transfer_scope);
+
+    if peer_optional_unmet.is_empty() && transfer_scope == offer.transfer_scope {
+        return VrpNegotiationResponse::Accept {
+            terms: offer.clone(),
+        };
+    }
+
+    let mut notes = Vec::new();
+    if !peer_optional_unmet.is_empty() {
+        notes.push(format!(
+            "drop optional capabilities we do not offer: {}",
+            peer_optional_unmet.join(", ")
+        ));
+    }
+    if transfer_scope != offer.transfer_scope {
+        notes.push(format!("transfer scope narrowed to {}", transfer_scope));
+    }
+
+    let mut terms = local.clone();
+    terms
+        .optional_capabilities
+        .retain(|cap| offer.offered_capabilities.contains(cap));
+    terms.transfer_scope = transfer_scope;
+    VrpNegotiationResponse::CounterOffer { terms, notes }
+}
+
+/// The peer's terms once it accepts the local counter-offer `local`: its
+/// optional capabilities the local side does not offer are dropped, and the
+/// transfer scope narrows to the counter-offer's.
+fn settle(peer: &VrpContractOffer, local: &VrpContractOffer) -> VrpContractOffer {
+    let mut terms = peer.clone();
+    terms
+        .optional_capabilities
+        .retain(|cap| local.offered_capabilities.contains(cap));
+    terms.transfer_scope = peer.transfer_scope.min(local.transfer_scope);
+    terms
+}
+
+impl VrpNegotiation {
+    /// Opens a negotiation with the peer's first offer.
+    ///
+    /// A peer whose anchor is in `Conflict` is rejected without looking at
+    /// the offer.
+    ///
+    /// `alignment` is the result of [`crate::explain_peer_anchor`] for
+    /// `anchor_snapshot`.
+    pub fn open(
+        id: String,
+        peer_id: String,
+        peer_type: &str,
+        anchor_snapshot: VrpAnchorSnapshot,
+        alignment: (VrpAlignmentStatus, VrpAlignmentExplanation),
+        offer: VrpContractOffer,
+        local_terms: &VrpContractOffer,
+    ) -> (Self, VrpNegotiationResponse) {
+        let (alignment_status, explanation) = alignment;
+        let mut negotiation = Self {
+            id,
+            peer_id,
+            peer_type: peer_type.to_string(),
+            status: VrpNegotiationStatus::Open,
+            round: 0,
+            alignment_status,
+            anchor_snapshot,
+            explanation,
+            peer_offer: offer.clone(),
+            local_offer: local_terms.clone(),
+            outcome_reason: None,
+            expires_at: String::new(),
+        };
+
+        if alignment_status == VrpAlignmentStatus::Conflict {
+            let reason = "anchor alignment conflict".to_string();
+            negotiation.round = 1;
+            negotiation.status = VrpNegotiationStatus::Rejected;
+            negotiation.outcome_reason = Some(reason.clone());
+            return (negotiation, VrpNegotiationResponse::Reject { reason });
+        }
+
+        let response = negotiation.apply(
+            VrpNegotiationMessage::CounterOffer { terms: offer },
+            local_terms,
+        );
+        (negotiation, response)
+    }
+
+    /// Applies the peer's next message and returns the local response.
+    ///
+    /// `local_terms` are the local side's full terms for this peer; each
+    /// counter-offer is evaluated against them afresh.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NegotiationError::Closed` if the negotiation is not open.
+    pub fn advance(
+        &mut self,
+        message: VrpNegotiationMessage,
+        local_terms: &VrpContractOffer,
+    ) -> Result<VrpNegotiationResponse, NegotiationError> {
+        if self.status != VrpNegotiationStatus::Open {
+            return Err(NegotiationError::Closed(self.status));
+        }
+        Ok(self.apply(message, local_terms))
+    }
+
+    fn apply(
+        &mut self,
+        message: VrpNegotiationMessage,
+        local_terms: &VrpContractOffer,
+    ) -> VrpNegotiationResponse {
+        let response = match message {
+            VrpNegotiationMessage::CounterOffer { terms } => {
+                self.round += 1;
+                if self.round > MAX_NEGOTIATION_ROUNDS {
+                    VrpNegotiationResponse::Reject {
+                        reason: format!("no agreement after {} rounds", MAX_NEGOTIATION_ROUNDS),
+                    }
+                } else {
+                    let response = respond_to_offer(local_terms, &terms);
+                    self.peer_offer = terms;
+                    response
+                }
+            }
+            VrpNegotiationMessage::Accept => VrpNegotiationResponse::Accept {
+                terms: settle(&self.peer_offer, &self.local_offer),
+            },
+            VrpNegotiationMessage::Reject { reason } => VrpNegotiationResponse::Reject {
+                reason: reason.unwrap_or_else(|| "rejected by peer".to_string()),
+            },
+        };
+
+        match &response {
+            VrpNegotiationResponse::Accept { terms } => {
+                self.status = VrpNegotiationStatus::Accepted;
+                self.peer_offer = terms.clone();
+            }
+            VrpNegotiationResponse::CounterOffer { terms, .. } => {
+                self.local_offer = terms.clone();
+            }
+            VrpNegotiationResponse::Reject { reason } => {
+                self.status = VrpNegotiationStatus::Rejected;
+                self.outcome_reason = Some(reason.clone());
+            }
+        }
+        response
+    }
+
+    /// The handshake this negotiation settled on: the peer's anchor and its
+    /// agreed capability contract.
+    pub fn agreed_handshake(&self) -> crate::VrpFederationHandshake {
+        crate::VrpFederationHandshake {
+            anchor_snapshot: self.anchor_snapshot.clone(),
+            capability_contract: self.peer_offer.to_contract(),
+        }
+    }
+
+    /// The validation report for a closed negotiation.
+    ///
+    /// Every negotiation keeps the anchor's alignment status: failing to
+    /// agree on terms says nothing about the peer's principles. An accepted
+    /// negotiation carries the agreed transfer scope; anything else transfers
+    /// nothing.
+    pub fn report(&self) -> VrpValidationReport {
+        let mut explanation = self.explanation.clone();
+        let transfer_scope = match self.status {
+            VrpNegotiationStatus::Accepted => {
+                explanation.transfer_scope_reason =
+                    format!("negotiated over {} round(s)", self.round);
+                self.peer_offer.transfer_scope
+            }
+            _ => {
+                explanation.transfer_scope_reason =
+                    format!("negotiation {}", self.status.to_string().to_lowercase());
+                VrpTransferScope::NoTransfer
+            }
+        };
+        let alignment_status = self.alignment_status;
+
+        VrpValidationReport {
+            alignment_status,
+            transfer_scope,
+            alignment_score: status_score(alignment_status),
+            negotiation_notes: self.outcome_reason.iter().cloned().collect(),
+            explanation,
+        }
+    }
+}
+
+/// Persists a newly opened negotiation that expires `timeout_secs` from now,
+/// filling in `negotiation.expires_at`.
+pub fn insert_negotiation(
+    conn: &Connection,
+    server_id: i64,
+    negotiation: &mut VrpNegotiation,
+    timeout_secs: u64,
+) -> Result<(), NegotiationError> {
+    let expires_at: String = conn.query_row(
+        "SELECT datetime('now', ?1)",
+        params![format!("+{} seconds", timeout_secs)],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT INTO vrp_negotiations (
+            id, server_id, peer_id, peer_type, status, round, alignment_status,
+            anchor_snapshot_json, explanation_json, peer_offer_json, local_offer_json,
+            outcome_reason, expires_at
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            negotiation.id,
+            server_id,
+            negotiation.peer_id,
+            negotiation.peer_type,
+            negotiation.status.to_string(),
+            negotiation.round,
+            negotiation.alignment_status.to_string(),
+            serde_json::to_string(&negotiation.anchor_snapshot)?,
+            serde_json::to_string(&negotiation.explanation)?,
+            serde_json::to_string(&negotiation.peer_offer)?,
+            serde_json::to_string(&negotiation.local_offer)?,
+            negotiation.outcome_reason,
+            expires_at,
+        ],
+    )?;
+    negotiation.expires_at = expires_at;
+    Ok(())
+}
+
+/// Loads a negotiation belonging to `peer_type`/`peer_id`.
+///
+/// An open negotiation past its `expires_at` is marked `EXPIRED` before it
+/// is returned.
+pub fn get_negotiation(
+    conn: &Connection,
+    server_id: i64,
+    id: &str,
+    peer_type: &str,
+    peer_id: &str,
+) -> Result<VrpNegotiation, NegotiationError> {
+    conn.execute(
+        "UPDATE vrp_negotiations
+         SET status = 'EXPIRED', outcome_reason = 'negotiation timed out',
+             updated_at = datetime('now')
+         WHERE id = ?1 AND server_id = ?2 AND status = 'OPEN'
+           AND expires_at <= datetime('now')",
+        params![id, server_id],
+    )?;
+
+    let row = conn
+        .query_row(
+            "SELECT status, round, alignment_status, anchor_snapshot_json, explanation_json,
+                    peer_offer_json, local_offer_json, outcome_reason, expires_at
+             FROM vrp_negotiations
+             WHERE id = ?1 AND server_id = ?2 AND peer_type = ?3 AND peer_id = ?4",
+            params![id, server_id, peer_type, peer_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, String>(8)?,
+                ))
+            },
+        )
+        .optional()?
+        .ok_or(NegotiationError::NotFound)?;
+
+    let (
+        status,
+        round,
+        alignment_status,
+        anchor,
+        explanation,
+        peer_offer,
+        local_offer,
+        reason,
+        expires_at,
+    ) = row;
+    let parse_err = |e: String| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+    };
+    Ok(VrpNegotiation {
+        id: id.to_string(),
+        peer_id: peer_id.to_string(),
+        peer_type: peer_type.to_string(),
+        status: status.parse().map_err(parse_err)?,
+        round,
+        alignment_status: alignment_status.parse().map_err(parse_err)?,
+        anchor_snapshot: serde_json::from_str(&anchor)?,
+        explanation: serde_json::from_str(&explanation)?,
+        peer_offer: serde_json::from_str(&peer_offer)?,
+        local_offer: serde_json::from_str(&local_offer)?,
+        outcome_reason: reason,
+        expires_at,
+    })
+}
+
+/// Writes back the mutable state of a negotiation after
+/// [`VrpNegotiation::advance`].
+pub fn update_negotiation(
+    conn: &Connection,
+    server_id: i64,
+    negotiation: &VrpNegotiation,
+) -> Result<(), NegotiationError> {
+    conn.execute(
+        "UPDATE vrp_negotiations
+         SET status = ?1, round = ?2, peer_offer_json = ?3, local_offer_json = ?4,
+             outcome_reason = ?5, updated_at = datetime('now')
+         WHERE id = ?6 AND server_id = ?7",
+        params![
+            negotiation.status.to_string(),
+            negotiation.round,
+            serde_json::to_string(&negotiation.peer_offer)?,
+            serde_json::to_string(&negotiation.local_offer)?,
+            negotiation.outcome_reason,
+            negotiation.id,
+            server_id,
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,253 @@
+//! Local sentence-embedding models via ONNX Runtime.
+//!
+//! Supports BERT-family sentence encoders exported to ONNX (e.g.
+//! `all-MiniLM-L6-v2`) together with their WordPiece `vocab.txt`. Token
+//! embeddings are mean-pooled over the attention mask and L2-normalized.
+
+use crate::semantic::SemanticEmbedder;
+use crate::VrpError;
+use ort::session::Session;
+use ort::value::Tensor;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Maximum sequence length fed to the model, including `[CLS]` and `[SEP]`.
+const MAX_SEQUENCE_LEN: usize = 256;
+
+/// Maximum characters in a word before it is mapped to `[UNK]`.
+const MAX_WORD_CHARS: usize = 100;
+
+/// A sentence-embedding model loaded from an `.onnx` file.
+pub struct OnnxEmbedder {
+    // `Session::run` takes `&mut self`.
+    session: Mutex<Session>,
+    tokenizer: WordPieceTokenizer,
+    /// Whether the model declares a `token_type_ids` input.
+    uses_token_types: bool,
+}
+
+impl OnnxEmbedder {
+    /// Loads a model and its WordPiece vocabulary.
+    ///
+    /// # Errors
+    ///
+    /// Returns `VrpError::Embedder` if either file cannot be read or the
+    /// ONNX Runtime library cannot be loaded.
+    pub fn load(model_path: &str, vocab_path: &str) -> Result<Self, VrpError> {
+        let vocab = std::fs::read_to_string(vocab_path)
+            .map_err(|e| VrpError::Embedder(format!("failed to read {}: {}", vocab_path, e)))?;
+        let tokenizer = WordPieceTokenizer::from_vocab(&vocab)?;
+
+        let session = Session::builder()
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .map_err(|e| VrpError::Embedder(format!("failed to load {}: {}", model_path, e)))?;
+        let uses_token_types = session
+            .inputs
+            .iter()
+            .any(|input| input.name == "token_type_ids");
+
+        Ok(Self {
+            session: Mutex::new(session),
+            tokenizer,
+            uses_token_types,
+        })
+    }
+}
+
+impl SemanticEmbedder for OnnxEmbedder {
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        let mut vectors = self.embed_batch(&[text.to_string()])?;
+        Ok(vectors.remove(0))
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Pad every sequence to the longest one in the batch.
+        let encoded: Vec<Vec<i64>> = texts.iter().map(|t| self.tokenizer.encode(t)).collect();
+        let seq_len = encoded.iter().map(Vec::len).max().unwrap_or(0);
+        let batch = texts.len();
+
+        let mut input_ids = vec![self.tokenizer.pad_id; batch * seq_len];
+        let mut attention_mask = vec![0i64; batch * seq_len];
+        for (row, ids) in encoded.iter().enumerate() {
+            let offset = row * seq_len;
+            input_ids[offset..offset + ids.len()].copy_from_slice(ids);
+            attention_mask[offset..offset + ids.len()].fill(1);
+        }
+
+        let shape = [batch as i64, seq_len as i64];
+        let tensor = |data: Vec<i64>| {
+            Tensor::from_array((shape, data)).map_err(|e| format!("invalid input tensor: {}", e))
+        };
+        let mut inputs = ort::inputs![
+            "input_ids" => tensor(input_ids)?,
+            "attention_mask" => tensor(attention_mask.clone())?,
+        ];
+        if self.uses_token_types {
+            inputs.push((
+                "token_type_ids".into(),
+                tensor(vec![0i64; batch * seq_len])?.into(),
+            ));
+        }
+
+        let mut session = self
+            .session
+            .lock()
+            .map_err(|_| "onnx session lock poisoned".to_string())?;
+        let outputs = session
+            .run(inputs)
+            .map_err(|e| format!("onnx inference failed: {}", e))?;
+        let (out_shape, hidden) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| format!("unexpected onnx output: {}", e))?;
+        if out_shape.len() != 3 {
+            return Err(format!(
+                "expected [batch, seq, hidden] output, got {} dimensions",
+                out_shape.len()
+            ));
+        }
+        let dim = out_shape[2] as usize;
+
+        // Mean-pool token embeddings over the attention mask.
+        Ok((0..batch)
+            .map(|row| {
+                let mut pooled = vec![0.0f32; dim];
+                for token in 0..seq_len {
+                    if attention_mask[row * seq_len + token] == 0 {
+                        continue;
+                    }
+                    let start = (row * seq_len + token) * dim;
+                    for (acc, value) in pooled.iter_mut().zip(&hidden[start..start + dim]) {
+                        *acc += value;
+                    }
+                }
+                // The mean and the L2-normalized sum point the same way, so
+                // normalizing the sum directly is enough.
+                let norm: f32 = pooled.iter().map(|v| v * v).sum::<f32>().sqrt();
+                if norm > 0.0 {
+                    for v in &mut pooled {
+                        *v /= norm;
+                    }
+                }
+                pooled
+            })
+            .collect())
+    }
+}
+
+/// A minimal uncased BERT WordPiece tokenizer.
+struct WordPieceTokenizer {
+    vocab: HashMap<String, i64>,
+    cls_id: i64,
+    sep_id: i64,
+    unk_id: i64,
+    pad_id: i64,
+}
+
+impl WordPieceTokenizer {
+    fn from_vocab(contents: &str) -> Result<Self, VrpError> {
+        let vocab: HashMap<String, i64> = contents
+            .lines()
+            .enumerate()
+            .map(|(idx, token)| (token.trim_end().to_string(), idx as i64))
+            .collect();
+        let special = |token: &str| {
+            vocab
+                .get(token)
+                .copied()
+                .ok_or_else(|| VrpError::Embedder(format!("vocabulary is missing {}", token)))
+        };
+        Ok(Self {
+            cls_id: special("[CLS]")?,
+            sep_id: special("[SEP]")?,
+            unk_id: special("[UNK]")?,
+            pad_id: special("[PAD]")?,
+            vocab,
+        })
+    }
+
+    /// Encodes `text` as `[CLS] tokens… [SEP]`, truncated to
+    /// [`MAX_SEQUENCE_LEN`].
+    fn encode(&self, text: &str) -> Vec<i64> {
+        let mut ids = vec![self.cls_id];
+        for word in basic_tokenize(text) {
+            ids.extend(self.word_pieces(&word));
+        }
+        ids.truncate(MAX_SEQUENCE_LEN - 1);
+        ids.push(self.sep_id);
+        ids
+    }
+
+    /// Greedy longest-match-first WordPiece split of a single word.
+    fn word_pieces(&self, word: &str) -> Vec<i64> {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.len() > MAX_WORD_CHARS {
+            return vec![self.unk_id];
+        }
+
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let mut end = chars.len();
+            let mut found = None;
+            while start < end {
+                let mut candidate: String = chars[start..end].iter().collect();
+                if start > 0 {
+                    candidate.insert_str(0, "##");
+                }
+                if let Some(&id) = self.vocab.get(&candidate) {
+                    found = Some(id);
+                    break;
+                }
+                end -= 1;
+            }
+            match found {
+                Some(id) => pieces.push(id),
+                None => return vec![self.unk_id],
+            }
+            start = end;
+        }
+        pieces
+    }
+}
+
+/// Lowercases and splits on whitespace, isolating punctuation as separate
+/// tokens.
+fn basic_tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in text.to_lowercase().split_whitespace() {
+        let mut current = String::new();
+        for c in word.chars() {
+            if c.is_ascii_punctuation() {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                tokens.push(c.to_string());
+            } else {
+                current.push(c);
+            }
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_piece_split() {
+        let vocab = "[PAD]\n[UNK]\n[CLS]\n[SEP]\nprotect\n##ing\nprivacy\n,\n";
+        let tokenizer = WordPieceTokenizer::from_vocab(vocab).unwrap();
+        assert_eq!(
+            tokenizer.encode("Protecting privacy, zebra"),
+            vec![2, 4, 5, 6, 7, 1, 3]
+        );
+    }
+}
@@ -1,5 +1,9 @@
 use crate::types::VrpValidationReport;
+use annex_types::ReputationConfig;
 use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use thiserror::Error;
 
 /// Errors that can occur during reputation operations.
@@ -11,6 +15,75 @@ pub enum ReputationError {
     /// A serialization error occurred.
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+    /// The source already recorded this signal (one report per peer, one
+    /// piece of feedback per bundle).
+    #[error("reputation signal already recorded")]
+    Duplicate,
+}
+
+/// A reputation signal other than a VRP handshake outcome.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReputationSignalKind {
+    /// An operator-flagged incident.
+    Incident,
+    /// A recipient found one of the peer's RTX bundles useful.
+    RtxPositive,
+    /// A recipient found one of the peer's RTX bundles unhelpful.
+    RtxNegative,
+    /// A member reported the peer to moderators.
+    ModerationReport,
+}
+
+impl fmt::Display for ReputationSignalKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReputationSignalKind::Incident => write!(f, "INCIDENT"),
+            ReputationSignalKind::RtxPositive => write!(f, "RTX_POSITIVE"),
+            ReputationSignalKind::RtxNegative => write!(f, "RTX_NEGATIVE"),
+            ReputationSignalKind::ModerationReport => write!(f, "MODERATION_REPORT"),
+        }
+    }
+}
+
+impl FromStr for ReputationSignalKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "INCIDENT" => Ok(ReputationSignalKind::Incident),
+            "RTX_POSITIVE" => Ok(ReputationSignalKind::RtxPositive),
+            "RTX_NEGATIVE" => Ok(ReputationSignalKind::RtxNegative),
+            "MODERATION_REPORT" => Ok(ReputationSignalKind::ModerationReport),
+            _ => Err(format!("unknown reputation signal kind: {}", s)),
+        }
+    }
+}
+
+/// A peer's current reputation and the events behind it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReputationSummary {
+    /// The peer's pseudonym (or base URL for servers).
+    pub peer: String,
+    /// Time-decayed score between 0.0 and 1.0; 0.5 is neutral.
+    pub score: f32,
+    /// `ALIGNED` handshakes considered.
+    pub aligned: u32,
+    /// `PARTIAL` handshakes considered.
+    pub partial: u32,
+    /// `CONFLICT` handshakes considered.
+    pub conflict: u32,
+    /// Operator-flagged incidents considered.
+    pub incidents: u32,
+    /// Positive RTX feedback considered.
+    pub rtx_positive: u32,
+    /// Negative RTX feedback considered.
+    pub rtx_negative: u32,
+    /// Moderation reports considered.
+    pub moderation_reports: u32,
+    /// Timestamp of the most recent event, if any.
+    pub last_event_at: Option<String>,
 }
 
 /// Records the outcome of a VRP handshake in the log.
@@ -33,62 +106,182 @@ pub fn record_vrp_outcome(
     Ok(())
 }
 
-/// Computes the longitudinal reputation score for a peer based on handshake history.
+/// Records a non-handshake reputation signal against a peer.
 ///
-/// The score starts at 0.5 (neutral) and evolves based on historical outcomes:
-/// - `ALIGNED` outcomes increase the score towards 1.0.
-/// - `PARTIAL` outcomes slightly decrease the score.
-/// - `CONFLICT` outcomes significantly decrease the score.
+/// Returns [`ReputationError::Duplicate`] when `source_pseudonym` already
+/// reported this peer, or already gave feedback on the bundle named by
+/// `reference`.
+pub fn record_reputation_signal(
+    conn: &Connection,
+    server_id: i64,
+    peer_pseudonym: &str,
+    kind: ReputationSignalKind,
+    source_pseudonym: &str,
+    reference: Option<&str>,
+    note: Option<&str>,
+) -> Result<(), ReputationError> {
+    let result = conn.execute(
+        "INSERT INTO reputation_signals
+             (server_id, peer_pseudonym, kind, source_pseudonym, reference, note)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            server_id,
+            peer_pseudonym,
+            kind.to_string(),
+            source_pseudonym,
+            reference,
+            note
+        ],
+    );
+    match result {
+        Ok(_) => Ok(()),
+        Err(rusqlite::Error::SqliteFailure(err, _))
+            if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err(ReputationError::Duplicate)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Computes the time-decayed reputation of a peer.
 ///
-/// The calculation processes history from oldest to newest to capture trends.
-pub fn check_reputation_score(
+/// The score starts at 0.5 (neutral) and replays handshake outcomes and
+/// reputation signals from oldest to newest. Positive events (`ALIGNED`,
+/// positive RTX feedback) move the score toward 1.0 by `weight * (1 - score)`;
+/// negative events (`PARTIAL`, `CONFLICT`, incidents, negative RTX feedback,
+/// moderation reports) move it toward 0.0 by `weight * score`. Between
+/// events, and from the last event until now, the score's distance from
+/// neutral halves every `config.half_life_secs`.
+pub fn compute_reputation(
     conn: &Connection,
     server_id: i64,
     peer_pseudonym: &str,
-) -> Result<f32, ReputationError> {
-    // Fetch recent history, ordered by time (oldest first within window).
-    // Limit to 1000 most recent entries: the exponential decay means older
-    // entries have negligible impact on the score, so loading the full
-    // history is unnecessary and progressively more expensive.
+    config: &ReputationConfig,
+) -> Result<ReputationSummary, ReputationError> {
+    // Only the 1000 most recent events are replayed: older events have
+    // negligible impact once weighted and decayed, and loading the full
+    // history gets progressively more expensive.
     let mut stmt = conn.prepare(
-        "SELECT alignment_status FROM (
-             SELECT alignment_status, created_at FROM vrp_handshake_log
+        "SELECT kind, ts, created_at FROM (
+             SELECT alignment_status AS kind,
+                    CAST(strftime('%s', created_at) AS INTEGER) AS ts,
+                    created_at, 0 AS src, id
+             FROM vrp_handshake_log
+             WHERE server_id = ?1 AND peer_pseudonym = ?2
+             UNION ALL
+             SELECT kind, CAST(strftime('%s', created_at) AS INTEGER) AS ts,
+                    created_at, 1 AS src, id
+             FROM reputation_signals
              WHERE server_id = ?1 AND peer_pseudonym = ?2
-             ORDER BY created_at DESC
+             ORDER BY ts DESC, src DESC, id DESC
              LIMIT 1000
-         ) ORDER BY created_at ASC",
+         ) ORDER BY ts ASC, src ASC, id ASC",
     )?;
 
     let rows = stmt.query_map(params![server_id, peer_pseudonym], |row| {
-        let status: String = row.get(0)?;
-        Ok(status)
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, String>(2)?,
+        ))
     })?;
 
+    let mut summary = ReputationSummary {
+        peer: peer_pseudonym.to_string(),
+        ..Default::default()
+    };
     let mut score: f32 = 0.5; // Start neutral
+    let mut last_ts: Option<i64> = None;
 
     for row in rows {
-        let status_str = row?;
-        match status_str.as_str() {
+        let (kind, ts, created_at) = row?;
+        if let Some(prev) = last_ts {
+            score = decay(score, ts - prev, config.half_life_secs);
+        }
+        last_ts = Some(ts);
+
+        let (weight, positive) = match kind.as_str() {
             "ALIGNED" => {
-                // Boost: move 10% of the remaining distance to 1.0
-                score += 0.1 * (1.0 - score);
+                summary.aligned += 1;
+                (config.aligned_weight, true)
             }
             "PARTIAL" => {
-                // Slight penalty: degrade by 5%
-                score -= 0.05 * score;
+                summary.partial += 1;
+                (config.partial_weight, false)
             }
             "CONFLICT" => {
-                // Heavy penalty: degrade by 20%
-                score -= 0.2 * score;
-            }
-            _ => {
-                // Ignore unknown statuses
+                summary.conflict += 1;
+                (config.conflict_weight, false)
             }
+            other => match other.parse::<ReputationSignalKind>() {
+                Ok(ReputationSignalKind::Incident) => {
+                    summary.incidents += 1;
+                    (config.incident_weight, false)
+                }
+                Ok(ReputationSignalKind::RtxPositive) => {
+                    summary.rtx_positive += 1;
+                    (config.rtx_positive_weight, true)
+                }
+                Ok(ReputationSignalKind::RtxNegative) => {
+                    summary.rtx_negative += 1;
+                    (config.rtx_negative_weight, false)
+                }
+                Ok(ReputationSignalKind::ModerationReport) => {
+                    summary.moderation_reports += 1;
+                    (config.moderation_report_weight, false)
+                }
+                // Ignore unknown kinds
+                Err(_) => continue,
+            },
+        };
+
+        let weight = weight.clamp(0.0, 1.0);
+        if positive {
+            score += weight * (1.0 - score);
+        } else {
+            score -= weight * score;
         }
+        summary.last_event_at = Some(created_at);
+    }
+
+    if let Some(prev) = last_ts {
+        let now: i64 =
+            conn.query_row("SELECT CAST(strftime('%s', 'now') AS INTEGER)", [], |row| {
+                row.get(0)
+            })?;
+        score = decay(score, now - prev, config.half_life_secs);
     }
 
     // Ensure bounds (though logic shouldn't exceed them)
-    score = score.clamp(0.0, 1.0);
+    summary.score = score.clamp(0.0, 1.0);
+
+    Ok(summary)
+}
+
+/// Computes the longitudinal reputation score for a peer with the default
+/// [`ReputationConfig`].
+///
+/// See [`compute_reputation`] for how the score evolves.
+pub fn check_reputation_score(
+    conn: &Connection,
+    server_id: i64,
+    peer_pseudonym: &str,
+) -> Result<f32, ReputationError> {
+    Ok(compute_reputation(
+        conn,
+        server_id,
+        peer_pseudonym,
+        &ReputationConfig::default(),
+    )?
+    .score)
+}
 
-    Ok(score)
+/// Relaxes `score` toward neutral (0.5) over `elapsed_secs`.
+fn decay(score: f32, elapsed_secs: i64, half_life_secs: u64) -> f32 {
+    if half_life_secs == 0 || elapsed_secs <= 0 {
+        return score;
+    }
+    let factor = 0.5f64.powf(elapsed_secs as f64 / half_life_secs as f64) as f32;
+    0.5 + (score - 0.5) * factor
 }
@@ -1,9 +1,22 @@
+use crate::types::VrpPrincipleMatch;
 use std::collections::{BTreeSet, HashMap};
 
 /// A trait for text embedding models.
-pub trait SemanticEmbedder {
+///
+/// Embedders are shared across request handlers, so implementations must be
+/// `Send + Sync`.
+pub trait SemanticEmbedder: Send + Sync {
     /// Embeds a text string into a vector of floats.
     fn embed(&self, text: &str) -> Result<Vec<f32>, String>;
+
+    /// Embeds a batch of texts that will be compared with each other.
+    ///
+    /// Corpus-dependent embedders (bag-of-words, TF-IDF) derive their
+    /// vocabulary from the batch; model-backed embedders can use it to make a
+    /// single inference call. The default embeds each text independently.
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        texts.iter().map(|text| self.embed(text)).collect()
+    }
 }
 
 /// A bag-of-words embedder that creates sparse TF vectors from text.
@@ -65,10 +78,21 @@ impl SemanticEmbedder for BagOfWordsEmbedder {
         }
         Ok(vec)
     }
+
+    /// Uses the pre-built vocabulary if there is one, otherwise builds a
+    /// vocabulary from the batch itself.
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        if !self.vocab.is_empty() {
+            return texts.iter().map(|text| self.embed(text)).collect();
+        }
+        let mut embedder = BagOfWordsEmbedder::new();
+        embedder.build_vocab(texts);
+        texts.iter().map(|text| embedder.embed(text)).collect()
+    }
 }
 
 /// Tokenizes text into lowercase words, stripping punctuation.
-fn tokenize(text: &str) -> Vec<String> {
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
     text.to_lowercase()
         .split(|c: char| !c.is_alphanumeric())
         .filter(|w| w.len() >= 2) // Skip single-char tokens
@@ -127,65 +151,105 @@ fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
 }
 
 /// Computes the centroid (mean vector) of a list of embeddings.
-fn compute_centroid(
-    principles: &[String],
-    embedder: &impl SemanticEmbedder,
-) -> Result<Vec<f32>, String> {
-    if principles.is_empty() {
+fn compute_centroid(embeddings: &[Vec<f32>]) -> Result<Vec<f32>, String> {
+    let Some(first) = embeddings.first() else {
         return Ok(Vec::new());
-    }
-
-    let mut sum_vec: Vec<f32> = Vec::new();
-    let mut count = 0;
+    };
 
-    for principle in principles {
-        let embedding = embedder.embed(principle)?;
-        if sum_vec.is_empty() {
-            sum_vec = embedding;
-        } else {
-            if sum_vec.len() != embedding.len() {
-                return Err("Embedding dimension mismatch".to_string());
-            }
-            for (i, val) in embedding.iter().enumerate() {
-                sum_vec[i] += val;
-            }
+    let mut sum_vec = vec![0.0f32; first.len()];
+    for embedding in embeddings {
+        if embedding.len() != sum_vec.len() {
+            return Err("Embedding dimension mismatch".to_string());
+        }
+        for (i, val) in embedding.iter().enumerate() {
+            sum_vec[i] += val;
         }
-        count += 1;
-    }
-
-    if count == 0 {
-        return Ok(Vec::new());
     }
 
-    let centroid: Vec<f32> = sum_vec.into_iter().map(|val| val / count as f32).collect();
-    Ok(centroid)
+    let count = embeddings.len() as f32;
+    Ok(sum_vec.into_iter().map(|val| val / count).collect())
 }
 
 /// Calculates the semantic alignment score between two sets of principles.
 ///
 /// Returns a score between 0.0 (completely orthogonal) and 1.0 (perfectly aligned).
-/// This implementation computes the cosine similarity between the centroids of
-/// the embedded principles.
+/// Both sides are embedded in a single batch (so corpus-dependent embedders
+/// share one vocabulary), then the cosine similarity between the centroids
+/// of each side is returned.
 pub fn calculate_semantic_alignment(
     local_principles: &[String],
     remote_principles: &[String],
-    embedder: &impl SemanticEmbedder,
+    embedder: &dyn SemanticEmbedder,
 ) -> Result<f32, String> {
+    explain_semantic_alignment(local_principles, remote_principles, embedder)
+        .map(|(score, _)| score)
+}
+
+/// Like [`calculate_semantic_alignment`], but also matches each local
+/// principle to its most similar remote principle.
+///
+/// Uses the same single embedding batch for the centroid score and the
+/// per-principle matches.
+pub fn explain_semantic_alignment(
+    local_principles: &[String],
+    remote_principles: &[String],
+    embedder: &dyn SemanticEmbedder,
+) -> Result<(f32, Vec<VrpPrincipleMatch>), String> {
     if local_principles.is_empty() && remote_principles.is_empty() {
-        return Ok(1.0); // Both empty = aligned
+        return Ok((1.0, Vec::new())); // Both empty = aligned
     }
     if local_principles.is_empty() || remote_principles.is_empty() {
-        return Ok(0.0); // One empty, one not = conflict? Or maybe neutral. Let's say 0.0 for now.
+        // One empty, one not = conflict? Or maybe neutral. Let's say 0.0 for now.
+        let unmatched = local_principles
+            .iter()
+            .map(|principle| VrpPrincipleMatch {
+                principle: principle.clone(),
+                best_match: None,
+                score: 0.0,
+            })
+            .collect();
+        return Ok((0.0, unmatched));
+    }
+
+    let texts: Vec<String> = local_principles
+        .iter()
+        .chain(remote_principles.iter())
+        .cloned()
+        .collect();
+    let embeddings = embedder.embed_batch(&texts)?;
+    if embeddings.len() != texts.len() {
+        return Err("Embedder returned the wrong number of vectors".to_string());
     }
+    let (local, remote) = embeddings.split_at(local_principles.len());
+
+    let matches = local_principles
+        .iter()
+        .zip(local)
+        .map(|(principle, embedding)| {
+            let best = remote_principles
+                .iter()
+                .zip(remote)
+                .map(|(candidate, other)| (candidate, cosine_similarity(embedding, other)))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            VrpPrincipleMatch {
+                principle: principle.clone(),
+                best_match: best.map(|(candidate, _)| candidate.clone()),
+                score: best.map_or(0.0, |(_, score)| score),
+            }
+        })
+        .collect();
 
-    let local_centroid = compute_centroid(local_principles, embedder)?;
-    let remote_centroid = compute_centroid(remote_principles, embedder)?;
+    let local_centroid = compute_centroid(local)?;
+    let remote_centroid = compute_centroid(remote)?;
 
     if local_centroid.is_empty() || remote_centroid.is_empty() {
-        return Ok(0.0);
+        return Ok((0.0, matches));
     }
 
-    Ok(cosine_similarity(&local_centroid, &remote_centroid))
+    Ok((
+        cosine_similarity(&local_centroid, &remote_centroid),
+        matches,
+    ))
 }
 
 #[cfg(test)]
@@ -250,4 +314,25 @@ mod tests {
         // Cosine similarity should be close to 0.707
         assert!((score - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-4);
     }
+
+    #[test]
+    fn test_explain_semantic_alignment_best_matches() {
+        let mut embedder = MockEmbedder::new();
+        embedder.insert("A", vec![1.0, 0.0]);
+        embedder.insert("B", vec![0.0, 1.0]);
+        embedder.insert("A2", vec![0.9, 0.1]);
+
+        let local = vec!["A".to_string(), "B".to_string()];
+        let remote = vec!["A2".to_string()];
+
+        let (_, matches) = explain_semantic_alignment(&local, &remote, &embedder).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].best_match.as_deref(), Some("A2"));
+        assert!(matches[0].score > 0.9);
+        assert!(matches[1].score < 0.2);
+
+        let (score, unmatched) = explain_semantic_alignment(&local, &[], &embedder).unwrap();
+        assert_eq!(score, 0.0);
+        assert!(unmatched.iter().all(|m| m.best_match.is_none()));
+    }
 }
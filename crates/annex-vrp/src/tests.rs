@@ -26,6 +26,7 @@ fn test_vrp_anchor_snapshot_serialization() {
         timestamp: 1234567890,
         principles: vec![],
         prohibited_actions: vec![],
+        signature: None,
     };
     let json = serde_json::to_string(&snapshot).unwrap();
     let deserialized: VrpAnchorSnapshot = serde_json::from_str(&json).unwrap();
@@ -41,6 +42,7 @@ fn test_vrp_federation_handshake_serialization() {
             timestamp: 100,
             principles: vec![],
             prohibited_actions: vec![],
+            signature: None,
         },
         capability_contract: VrpCapabilitySharingContract {
             required_capabilities: vec!["cap1".to_string()],
@@ -60,6 +62,7 @@ fn test_vrp_validation_report_serialization() {
         transfer_scope: VrpTransferScope::NoTransfer,
         alignment_score: 0.5,
         negotiation_notes: vec!["note1".to_string()],
+        explanation: Default::default(),
     };
     let json = serde_json::to_string(&report).unwrap();
     let deserialized: VrpValidationReport = serde_json::from_str(&json).unwrap();
@@ -110,7 +113,7 @@ fn test_compare_peer_anchor_aligned() {
         min_alignment_score: 0.8,
     };
 
-    let status = compare_peer_anchor(&snap1, &snap2, &config);
+    let status = compare_peer_anchor(&snap1, &snap2, &config, &BagOfWordsEmbedder::new()).unwrap();
     assert_eq!(status, VrpAlignmentStatus::Aligned);
 }
 
@@ -124,7 +127,7 @@ fn test_compare_peer_anchor_conflict() {
         min_alignment_score: 0.8,
     };
 
-    let status = compare_peer_anchor(&snap1, &snap2, &config);
+    let status = compare_peer_anchor(&snap1, &snap2, &config, &BagOfWordsEmbedder::new()).unwrap();
     assert_eq!(status, VrpAlignmentStatus::Conflict);
 }
 
@@ -147,7 +150,7 @@ fn test_compare_peer_anchor_prohibited_action_mismatch_forces_conflict() {
         min_alignment_score: 0.1, // very low threshold — should still be Conflict
     };
 
-    let status = compare_peer_anchor(&snap1, &snap2, &config);
+    let status = compare_peer_anchor(&snap1, &snap2, &config, &BagOfWordsEmbedder::new()).unwrap();
     assert_eq!(status, VrpAlignmentStatus::Conflict);
 }
 
@@ -170,7 +173,7 @@ fn test_compare_peer_anchor_partial_when_prohibited_actions_match() {
         min_alignment_score: 0.3,
     };
 
-    let status = compare_peer_anchor(&snap1, &snap2, &config);
+    let status = compare_peer_anchor(&snap1, &snap2, &config, &BagOfWordsEmbedder::new()).unwrap();
     assert_eq!(status, VrpAlignmentStatus::Partial);
 }
 
@@ -271,7 +274,9 @@ fn test_validate_federation_handshake_success() {
         &handshake,
         &align_config,
         &transfer_config,
-    );
+        &BagOfWordsEmbedder::new(),
+    )
+    .unwrap();
 
     assert_eq!(report.alignment_status, VrpAlignmentStatus::Aligned);
     assert_eq!(report.transfer_scope, VrpTransferScope::FullKnowledgeBundle);
@@ -308,7 +313,9 @@ fn test_validate_federation_handshake_conflict_principles() {
         &handshake,
         &align_config,
         &transfer_config,
-    );
+        &BagOfWordsEmbedder::new(),
+    )
+    .unwrap();
 
     assert_eq!(report.alignment_status, VrpAlignmentStatus::Conflict);
     assert_eq!(report.transfer_scope, VrpTransferScope::NoTransfer);
@@ -349,13 +356,110 @@ fn test_validate_federation_handshake_contract_fail() {
         &handshake,
         &align_config,
         &transfer_config,
-    );
+        &BagOfWordsEmbedder::new(),
+    )
+    .unwrap();
 
     // Principles align, but contracts fail -> Conflict
     assert_eq!(report.alignment_status, VrpAlignmentStatus::Conflict);
     assert_eq!(report.transfer_scope, VrpTransferScope::NoTransfer);
     assert!(!report.negotiation_notes.is_empty());
     assert!(report.negotiation_notes[0].contains("Capability contracts incompatible"));
+    assert_eq!(
+        report.explanation.capabilities_missing_from_remote,
+        vec!["MustHave".to_string()]
+    );
+    assert!(report
+        .explanation
+        .capabilities_missing_from_local
+        .is_empty());
+    assert!(report
+        .explanation
+        .transfer_scope_reason
+        .contains("capability contracts incompatible"));
+}
+
+#[test]
+fn test_validate_federation_handshake_explains_conflict() {
+    let local_anchor = VrpAnchorSnapshot::new(
+        &[
+            "protect user privacy".to_string(),
+            "be transparent".to_string(),
+        ],
+        &["sell data".to_string(), "impersonate".to_string()],
+    )
+    .unwrap();
+    let remote_anchor = VrpAnchorSnapshot::new(
+        &["protect user privacy always".to_string()],
+        &["sell data".to_string(), "spam".to_string()],
+    )
+    .unwrap();
+    let contract = VrpCapabilitySharingContract {
+        required_capabilities: vec![],
+        offered_capabilities: vec![],
+        redacted_topics: vec![],
+    };
+    let handshake = VrpFederationHandshake {
+        anchor_snapshot: remote_anchor,
+        capability_contract: contract.clone(),
+    };
+
+    let report = validate_federation_handshake(
+        &local_anchor,
+        &contract,
+        &handshake,
+        &VrpAlignmentConfig {
+            semantic_alignment_required: true,
+            min_alignment_score: 0.5,
+        },
+        &VrpTransferAcceptanceConfig {
+            allow_full_knowledge: true,
+            allow_reflection_summaries: true,
+        },
+        &BagOfWordsEmbedder::new(),
+    )
+    .unwrap();
+
+    assert_eq!(report.alignment_status, VrpAlignmentStatus::Conflict);
+    let explanation = &report.explanation;
+    assert_eq!(
+        explanation.prohibited_actions_only_local,
+        vec!["impersonate".to_string()]
+    );
+    assert_eq!(
+        explanation.prohibited_actions_only_remote,
+        vec!["spam".to_string()]
+    );
+    assert!(report
+        .negotiation_notes
+        .iter()
+        .any(|n| n.contains("Prohibited actions differ")));
+
+    // Principle scores are still reported so operators can see how close
+    // the peers were.
+    assert_eq!(explanation.principle_matches.len(), 2);
+    let privacy = &explanation.principle_matches[0];
+    assert_eq!(privacy.principle, "protect user privacy");
+    assert_eq!(
+        privacy.best_match.as_deref(),
+        Some("protect user privacy always")
+    );
+    assert!(privacy.score > explanation.principle_matches[1].score);
+    assert!(explanation.semantic_score.is_some());
+    assert_eq!(
+        explanation.transfer_scope_reason,
+        "alignment conflict prohibits all transfers"
+    );
+
+    // Reports stored before explanations existed still deserialize.
+    let legacy = serde_json::json!({
+        "alignment_status": "Aligned",
+        "transfer_scope": "FullKnowledgeBundle",
+        "alignment_score": 1.0,
+        "negotiation_notes": [],
+    });
+    let parsed: VrpValidationReport = serde_json::from_value(legacy).unwrap();
+    assert_eq!(parsed.explanation, VrpAlignmentExplanation::default());
 }
 
 #[test]
@@ -365,6 +469,7 @@ fn test_check_transfer_acceptance() {
         transfer_scope: VrpTransferScope::FullKnowledgeBundle,
         alignment_score: 1.0,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
 
     let report_partial = VrpValidationReport {
@@ -372,6 +477,7 @@ fn test_check_transfer_acceptance() {
         transfer_scope: VrpTransferScope::ReflectionSummariesOnly,
         alignment_score: 0.5,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
 
     let report_conflict = VrpValidationReport {
@@ -379,6 +485,7 @@ fn test_check_transfer_acceptance() {
         transfer_scope: VrpTransferScope::NoTransfer,
         alignment_score: 0.0,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
 
     // 1. Conflict always fails
@@ -464,3 +571,192 @@ fn test_vrp_error_display() {
         msg
     );
 }
+
+#[test]
+fn test_tf_idf_matches_inflected_principles() {
+    let local = vec!["Protect user privacy".to_string()];
+    let remote = vec!["Protecting the privacy of users".to_string()];
+
+    let bag = semantic::calculate_semantic_alignment(&local, &remote, &BagOfWordsEmbedder::new())
+        .unwrap();
+    let tf_idf =
+        semantic::calculate_semantic_alignment(&local, &remote, &TfIdfEmbedder::new()).unwrap();
+    assert!(
+        tf_idf > bag,
+        "stemming should raise similarity (tf-idf {tf_idf}, bag-of-words {bag})"
+    );
+    assert!(tf_idf > 0.6);
+}
+
+#[test]
+fn test_tf_idf_single_embeddings_share_the_fitted_space() {
+    let corpus = vec![
+        "Protect user privacy".to_string(),
+        "Protecting the privacy of users".to_string(),
+        "Never deceive anyone".to_string(),
+    ];
+    assert!(TfIdfEmbedder::new().embed(&corpus[0]).is_err());
+
+    let mut embedder = TfIdfEmbedder::new();
+    embedder.fit(&corpus);
+    let a = embedder.embed(&corpus[0]).unwrap();
+    let b = embedder.embed(&corpus[1]).unwrap();
+    let c = embedder.embed(&corpus[2]).unwrap();
+    assert_eq!(a.len(), b.len());
+    assert_eq!(a.len(), c.len());
+
+    let cosine = |x: &[f32], y: &[f32]| x.iter().zip(y).map(|(p, q)| p * q).sum::<f32>();
+    assert!(cosine(&a, &b) > 0.6);
+    assert_eq!(cosine(&a, &c), 0.0);
+    assert_eq!(
+        embedder.embed_batch(&corpus[..2]).unwrap(),
+        vec![a.clone(), b.clone()]
+    );
+}
+
+#[test]
+fn test_compare_peer_anchor_uses_supplied_embedder() {
+    let prohibited = vec!["harm".to_string()];
+    let snap1 = VrpAnchorSnapshot::new(&["respect privacy".to_string()], &prohibited).unwrap();
+    let snap2 =
+        VrpAnchorSnapshot::new(&["protect personal data".to_string()], &prohibited).unwrap();
+    let config = VrpAlignmentConfig {
+        semantic_alignment_required: true,
+        min_alignment_score: 0.8,
+    };
+
+    // No shared words: bag-of-words sees a conflict.
+    assert_eq!(
+        compare_peer_anchor(&snap1, &snap2, &config, &BagOfWordsEmbedder::new()).unwrap(),
+        VrpAlignmentStatus::Conflict
+    );
+
+    // An embedder that knows the phrases are paraphrases sees partial alignment.
+    let mut model = semantic::MockEmbedder::new();
+    model.insert("respect privacy", vec![0.9, 0.1]);
+    model.insert("protect personal data", vec![0.85, 0.15]);
+    assert_eq!(
+        compare_peer_anchor(&snap1, &snap2, &config, &model).unwrap(),
+        VrpAlignmentStatus::Partial
+    );
+}
+
+#[test]
+fn test_explain_peer_anchor_skips_embedder_unless_semantic_alignment_required() {
+    let prohibited = vec!["harm".to_string()];
+    let snap1 = VrpAnchorSnapshot::new(&["respect privacy".to_string()], &prohibited).unwrap();
+    let snap2 =
+        VrpAnchorSnapshot::new(&["protect personal data".to_string()], &prohibited).unwrap();
+    let config = VrpAlignmentConfig {
+        semantic_alignment_required: false,
+        min_alignment_score: 0.1,
+    };
+
+    // The empty mock fails every lookup, so any call to it would error.
+    let (status, explanation) =
+        explain_peer_anchor(&snap1, &snap2, &config, &semantic::MockEmbedder::new()).unwrap();
+    assert_eq!(status, VrpAlignmentStatus::Conflict);
+    assert_eq!(explanation.semantic_score, None);
+    assert!(explanation.principle_matches.is_empty());
+}
+
+#[test]
+fn test_explain_peer_anchor_surfaces_embedder_failure() {
+    let prohibited = vec!["harm".to_string()];
+    let snap1 = VrpAnchorSnapshot::new(&["respect privacy".to_string()], &prohibited).unwrap();
+    let snap2 =
+        VrpAnchorSnapshot::new(&["protect personal data".to_string()], &prohibited).unwrap();
+    let config = VrpAlignmentConfig {
+        semantic_alignment_required: true,
+        min_alignment_score: 0.1,
+    };
+
+    assert!(matches!(
+        explain_peer_anchor(&snap1, &snap2, &config, &semantic::MockEmbedder::new()),
+        Err(VrpError::Embedder(_))
+    ));
+}
+
+#[test]
+fn test_build_embedder_validates_config() {
+    assert!(build_embedder(&EmbedderConfig::default()).is_ok());
+    assert!(build_embedder(&EmbedderConfig {
+        kind: EmbedderKind::TfIdf,
+        ..Default::default()
+    })
+    .is_ok());
+    assert!(matches!(
+        build_embedder(&EmbedderConfig {
+            kind: EmbedderKind::Http,
+            ..Default::default()
+        }),
+        Err(VrpError::Embedder(_))
+    ));
+    assert!(matches!(
+        build_embedder(&EmbedderConfig {
+            kind: EmbedderKind::Onnx,
+            ..Default::default()
+        }),
+        Err(VrpError::Embedder(_))
+    ));
+    assert_eq!("tf_idf".parse::<EmbedderKind>(), Ok(EmbedderKind::TfIdf));
+    assert!("word2vec".parse::<EmbedderKind>().is_err());
+}
+
+#[test]
+fn test_http_embedder_batches_requests() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/v1/embeddings", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                content_length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body).unwrap();
+        let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        // Answer out of order to exercise the index sort.
+        let response = serde_json::json!({
+            "data": [
+                { "index": 1, "embedding": [0.0, 1.0] },
+                { "index": 0, "embedding": [1.0, 0.0] },
+            ]
+        })
+        .to_string();
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            response.len(),
+            response
+        )
+        .unwrap();
+        request
+    });
+
+    let embedder = HttpEmbedder::new(
+        url,
+        Some("nomic-embed-text".to_string()),
+        std::time::Duration::from_secs(5),
+    )
+    .unwrap();
+    let vectors = embedder
+        .embed_batch(&["a".to_string(), "b".to_string()])
+        .unwrap();
+    assert_eq!(vectors, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+
+    let request = server.join().unwrap();
+    assert_eq!(request["input"], serde_json::json!(["a", "b"]));
+    assert_eq!(request["model"], "nomic-embed-text");
+}
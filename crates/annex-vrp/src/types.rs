@@ -70,6 +70,19 @@ impl FromStr for VrpTransferScope {
     }
 }
 
+impl VrpTransferScope {
+    /// Returns the next narrower scope, or `NoTransfer` if already at the
+    /// bottom.
+    pub fn downgraded(self) -> Self {
+        match self {
+            VrpTransferScope::FullKnowledgeBundle => VrpTransferScope::ReflectionSummariesOnly,
+            VrpTransferScope::ReflectionSummariesOnly | VrpTransferScope::NoTransfer => {
+                VrpTransferScope::NoTransfer
+            }
+        }
+    }
+}
+
 /// A snapshot of an entity's ethical or policy root for comparison.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct VrpAnchorSnapshot {
@@ -85,6 +98,13 @@ pub struct VrpAnchorSnapshot {
     /// Original prohibited action texts for semantic alignment. Empty when not available.
     #[serde(default)]
     pub prohibited_actions: Vec<String>,
+    /// Hex-encoded Ed25519 signature over [`VrpAnchorSnapshot::signing_payload`]
+    /// by the server that published the anchor, or over
+    /// [`VrpAnchorSnapshot::history_signing_payload`] for anchors served from
+    /// its history. `None` for unsigned snapshots (agents and peers that
+    /// predate anchor signing).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 /// A contract defining required and offered capabilities for an interaction.
@@ -140,6 +160,50 @@ pub struct VrpValidationReport {
     pub alignment_score: f32,
     /// Notes or reasons for the alignment outcome.
     pub negotiation_notes: Vec<String>,
+    /// Structured breakdown of why the handshake reached this outcome.
+    ///
+    /// Defaults to empty when deserializing reports stored before the
+    /// breakdown existed.
+    #[serde(default)]
+    pub explanation: VrpAlignmentExplanation,
+}
+
+/// How closely one local principle matched the counterparty's principles.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VrpPrincipleMatch {
+    /// The local principle text.
+    pub principle: String,
+    /// The most similar remote principle, if the counterparty sent any.
+    pub best_match: Option<String>,
+    /// Cosine similarity with `best_match` (0.0 when there is none).
+    pub score: f32,
+}
+
+/// The per-check breakdown behind a [`VrpValidationReport`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct VrpAlignmentExplanation {
+    /// Per-principle match scores. Empty when the principle lists are
+    /// identical or either side did not send principle text.
+    #[serde(default)]
+    pub principle_matches: Vec<VrpPrincipleMatch>,
+    /// Centroid similarity of both principle sets, when it was computed.
+    #[serde(default)]
+    pub semantic_score: Option<f32>,
+    /// Prohibited actions the local side declares but the counterparty does not.
+    #[serde(default)]
+    pub prohibited_actions_only_local: Vec<String>,
+    /// Prohibited actions the counterparty declares but the local side does not.
+    #[serde(default)]
+    pub prohibited_actions_only_remote: Vec<String>,
+    /// Capabilities the local side requires that the counterparty does not offer.
+    #[serde(default)]
+    pub capabilities_missing_from_remote: Vec<String>,
+    /// Capabilities the counterparty requires that the local side does not offer.
+    #[serde(default)]
+    pub capabilities_missing_from_local: Vec<String>,
+    /// Why the negotiated transfer scope was chosen.
+    #[serde(default)]
+    pub transfer_scope_reason: String,
 }
 
 /// Errors that can occur during VRP operations.
@@ -151,6 +215,10 @@ pub enum VrpError {
     /// recovered at the application layer.
     #[error("system clock returned a time before the UNIX epoch")]
     SystemClockInvalid,
+    /// The configured semantic embedder could not be constructed, or failed
+    /// to embed principle text.
+    #[error("embedder error: {0}")]
+    Embedder(String),
 }
 
 /// Errors that can occur during transfer acceptance validation.
@@ -0,0 +1,96 @@
+use annex_db::run_migrations;
+use annex_vrp::{diff_anchors, get_anchor_version, list_anchor_versions, VrpAnchorSnapshot};
+use rusqlite::Connection;
+
+fn setup_db() -> Connection {
+    let conn = Connection::open_in_memory().expect("should open in-memory db");
+    run_migrations(&conn).expect("migrations should succeed");
+    conn.execute(
+        "INSERT INTO servers (slug, label, policy_json) VALUES ('test-server', 'Test Server', '{}')",
+        [],
+    )
+    .expect("should insert server");
+    conn
+}
+
+fn insert_version(conn: &Connection, version_id: &str, policy_json: &str) {
+    conn.execute(
+        "INSERT INTO server_policy_versions (server_id, version_id, policy_json, activated_at)
+         VALUES (1, ?1, ?2, '2026-01-01 00:00:00')",
+        rusqlite::params![version_id, policy_json],
+    )
+    .expect("should insert policy version");
+}
+
+#[test]
+fn test_anchor_history_folds_versions_that_keep_the_anchor() {
+    let conn = setup_db();
+    insert_version(
+        &conn,
+        "v1",
+        r#"{"principles":["a"],"prohibited_actions":[]}"#,
+    );
+    insert_version(
+        &conn,
+        "v2",
+        r#"{"principles":["a"],"prohibited_actions":[],"max_members":5}"#,
+    );
+    insert_version(&conn, "v3", r#"{"principles":["a","b"]}"#);
+    insert_version(&conn, "v4", r#"{"principles":["a"]}"#);
+
+    let versions = list_anchor_versions(&conn, 1).expect("should list versions");
+    let ids: Vec<&str> = versions.iter().map(|v| v.version_id.as_str()).collect();
+    assert_eq!(ids, vec!["v1", "v3", "v4"]);
+
+    // Anchors are rebuilt from the stored texts and stamped with activation time.
+    let expected = VrpAnchorSnapshot::new(&["a".to_string()], &[]).unwrap();
+    assert_eq!(versions[0].anchor.principles_hash, expected.principles_hash);
+    assert_eq!(versions[0].anchor.timestamp, 1_767_225_600);
+    assert!(versions[0].anchor.signature.is_none());
+}
+
+#[test]
+fn test_get_anchor_version_looks_up_any_policy_version() {
+    let conn = setup_db();
+    insert_version(&conn, "v1", r#"{"principles":["a"]}"#);
+    insert_version(&conn, "v2", r#"{"principles":["a"]}"#);
+
+    let v2 = get_anchor_version(&conn, 1, "v2").expect("should query");
+    assert_eq!(v2.map(|v| v.version_id), Some("v2".to_string()));
+    assert!(get_anchor_version(&conn, 1, "nope")
+        .expect("should query")
+        .is_none());
+}
+
+#[test]
+fn test_diff_anchors_reports_added_and_removed_entries() {
+    let from =
+        VrpAnchorSnapshot::new(&["a".to_string(), "b".to_string()], &["x".to_string()]).unwrap();
+    let to =
+        VrpAnchorSnapshot::new(&["b".to_string(), "c".to_string()], &["x".to_string()]).unwrap();
+
+    let diff = diff_anchors(&from, &to);
+    assert_eq!(diff.added_principles, vec!["c".to_string()]);
+    assert_eq!(diff.removed_principles, vec!["a".to_string()]);
+    assert!(diff.added_prohibited_actions.is_empty());
+    assert!(diff.removed_prohibited_actions.is_empty());
+    assert!(!diff.is_empty());
+    assert!(diff_anchors(&from, &from).is_empty());
+}
+
+#[test]
+fn test_signing_payload_binds_hashes_and_texts() {
+    let mut anchor = VrpAnchorSnapshot::new(&["a".to_string()], &[]).unwrap();
+    let payload = anchor.signing_payload();
+    assert!(payload.contains(&anchor.principles_hash));
+    assert!(payload.ends_with(&anchor.timestamp.to_string()));
+    assert!(anchor.texts_match_hashes());
+
+    anchor.principles = vec!["b".to_string()];
+    assert!(!anchor.texts_match_hashes());
+    assert_eq!(
+        anchor.signing_payload(),
+        payload,
+        "texts are bound via hashes"
+    );
+}
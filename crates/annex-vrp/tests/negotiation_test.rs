@@ -0,0 +1,206 @@
+use annex_db::run_migrations;
+use annex_vrp::{
+    get_negotiation, insert_negotiation, respond_to_offer, update_negotiation, NegotiationError,
+    VrpAlignmentExplanation, VrpAlignmentStatus, VrpAnchorSnapshot, VrpContractOffer,
+    VrpNegotiation, VrpNegotiationMessage, VrpNegotiationResponse, VrpNegotiationStatus,
+    VrpTransferScope, MAX_NEGOTIATION_ROUNDS,
+};
+use rusqlite::Connection;
+
+fn setup_db() -> Connection {
+    let conn = Connection::open_in_memory().expect("should open in-memory db");
+    run_migrations(&conn).expect("migrations should succeed");
+
+    // Insert a dummy server for foreign key constraint
+    conn.execute(
+        "INSERT INTO servers (slug, label, policy_json) VALUES ('test-server', 'Test Server', '{}')",
+        [],
+    )
+    .expect("should insert server");
+
+    conn
+}
+
+fn caps(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+fn server_terms() -> VrpContractOffer {
+    VrpContractOffer {
+        required_capabilities: caps(&["TEXT"]),
+        optional_capabilities: caps(&["AUDIT_LOG"]),
+        offered_capabilities: caps(&["TEXT", "VRP"]),
+        redacted_topics: vec![],
+        transfer_scope: VrpTransferScope::ReflectionSummariesOnly,
+    }
+}
+
+fn agent_offer() -> VrpContractOffer {
+    VrpContractOffer {
+        required_capabilities: caps(&["VRP"]),
+        optional_capabilities: caps(&["VOICE"]),
+        offered_capabilities: caps(&["TEXT"]),
+        redacted_topics: vec![],
+        transfer_scope: VrpTransferScope::FullKnowledgeBundle,
+    }
+}
+
+fn open(offer: VrpContractOffer) -> (VrpNegotiation, VrpNegotiationResponse) {
+    VrpNegotiation::open(
+        "neg-1".to_string(),
+        "agent-1".to_string(),
+        "AI_AGENT",
+        VrpAnchorSnapshot::new(&[], &[]).unwrap(),
+        (
+            VrpAlignmentStatus::Aligned,
+            VrpAlignmentExplanation::default(),
+        ),
+        offer,
+        &server_terms(),
+    )
+}
+
+#[test]
+fn test_counter_offer_drops_optional_and_narrows_scope() {
+    let (mut negotiation, response) = open(agent_offer());
+
+    let VrpNegotiationResponse::CounterOffer { terms, notes } = response else {
+        panic!("expected a counter-offer, got {:?}", response);
+    };
+    assert_eq!(
+        terms.transfer_scope,
+        VrpTransferScope::ReflectionSummariesOnly
+    );
+    // The agent does not offer AUDIT_LOG, so the server drops it.
+    assert!(terms.optional_capabilities.is_empty());
+    assert!(notes.iter().any(|n| n.contains("VOICE")));
+    assert_eq!(negotiation.status, VrpNegotiationStatus::Open);
+    assert_eq!(negotiation.round, 1);
+
+    // The agent accepts the counter-offer: its VOICE wish is dropped and the
+    // scope narrowed.
+    let response = negotiation
+        .advance(VrpNegotiationMessage::Accept, &server_terms())
+        .unwrap();
+    let VrpNegotiationResponse::Accept { terms } = response else {
+        panic!("expected acceptance");
+    };
+    assert!(terms.optional_capabilities.is_empty());
+    assert_eq!(
+        terms.transfer_scope,
+        VrpTransferScope::ReflectionSummariesOnly
+    );
+    assert_eq!(negotiation.status, VrpNegotiationStatus::Accepted);
+
+    let report = negotiation.report();
+    assert_eq!(report.alignment_status, VrpAlignmentStatus::Aligned);
+    assert_eq!(
+        report.transfer_scope,
+        VrpTransferScope::ReflectionSummariesOnly
+    );
+
+    assert!(matches!(
+        negotiation.advance(VrpNegotiationMessage::Accept, &server_terms()),
+        Err(NegotiationError::Closed(VrpNegotiationStatus::Accepted))
+    ));
+}
+
+#[test]
+fn test_required_capability_mismatch_rejects() {
+    let mut offer = agent_offer();
+    offer.required_capabilities.push("VOICE".to_string());
+
+    let response = respond_to_offer(&server_terms(), &offer);
+    assert!(matches!(
+        response,
+        VrpNegotiationResponse::Reject { ref reason } if reason.contains("VOICE")
+    ));
+
+    let mut offer = agent_offer();
+    offer.offered_capabilities.clear();
+    assert!(matches!(
+        respond_to_offer(&server_terms(), &offer),
+        VrpNegotiationResponse::Reject { ref reason } if reason.contains("TEXT")
+    ));
+}
+
+#[test]
+fn test_round_limit_rejects() {
+    let (mut negotiation, _) = open(agent_offer());
+    for _ in 1..MAX_NEGOTIATION_ROUNDS {
+        let response = negotiation
+            .advance(
+                VrpNegotiationMessage::CounterOffer {
+                    terms: agent_offer(),
+                },
+                &server_terms(),
+            )
+            .unwrap();
+        assert!(matches!(
+            response,
+            VrpNegotiationResponse::CounterOffer { .. }
+        ));
+    }
+
+    let response = negotiation
+        .advance(
+            VrpNegotiationMessage::CounterOffer {
+                terms: agent_offer(),
+            },
+            &server_terms(),
+        )
+        .unwrap();
+    assert!(matches!(response, VrpNegotiationResponse::Reject { .. }));
+    assert_eq!(negotiation.status, VrpNegotiationStatus::Rejected);
+    // Failing to agree is not a conflict of principles.
+    let report = negotiation.report();
+    assert_eq!(report.alignment_status, VrpAlignmentStatus::Aligned);
+    assert_eq!(report.transfer_scope, VrpTransferScope::NoTransfer);
+}
+
+#[test]
+fn test_negotiation_persistence_and_expiry() {
+    let conn = setup_db();
+    let (mut negotiation, _) = open(agent_offer());
+    insert_negotiation(&conn, 1, &mut negotiation, 300).unwrap();
+    assert!(!negotiation.expires_at.is_empty());
+
+    let mut loaded = get_negotiation(&conn, 1, "neg-1", "AI_AGENT", "agent-1").unwrap();
+    assert_eq!(loaded, negotiation);
+
+    // Another peer cannot see it.
+    assert!(matches!(
+        get_negotiation(&conn, 1, "neg-1", "AI_AGENT", "agent-2"),
+        Err(NegotiationError::NotFound)
+    ));
+
+    loaded
+        .advance(VrpNegotiationMessage::Accept, &server_terms())
+        .unwrap();
+    update_negotiation(&conn, 1, &loaded).unwrap();
+    let reloaded = get_negotiation(&conn, 1, "neg-1", "AI_AGENT", "agent-1").unwrap();
+    assert_eq!(reloaded.status, VrpNegotiationStatus::Accepted);
+    assert_eq!(reloaded.peer_offer, loaded.peer_offer);
+
+    // An open negotiation past its deadline is expired on load.
+    let (mut stale, _) = VrpNegotiation::open(
+        "neg-2".to_string(),
+        "agent-1".to_string(),
+        "AI_AGENT",
+        VrpAnchorSnapshot::new(&[], &[]).unwrap(),
+        (
+            VrpAlignmentStatus::Aligned,
+            VrpAlignmentExplanation::default(),
+        ),
+        agent_offer(),
+        &server_terms(),
+    );
+    insert_negotiation(&conn, 1, &mut stale, 300).unwrap();
+    conn.execute(
+        "UPDATE vrp_negotiations SET expires_at = datetime('now', '-1 seconds') WHERE id = 'neg-2'",
+        [],
+    )
+    .unwrap();
+    let expired = get_negotiation(&conn, 1, "neg-2", "AI_AGENT", "agent-1").unwrap();
+    assert_eq!(expired.status, VrpNegotiationStatus::Expired);
+}
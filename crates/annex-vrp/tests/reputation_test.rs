@@ -1,6 +1,8 @@
 use annex_db::run_migrations;
+use annex_types::ReputationConfig;
 use annex_vrp::{
-    check_reputation_score, record_vrp_outcome, VrpAlignmentStatus, VrpTransferScope,
+    check_reputation_score, compute_reputation, record_reputation_signal, record_vrp_outcome,
+    ReputationError, ReputationSignalKind, VrpAlignmentStatus, VrpTransferScope,
     VrpValidationReport,
 };
 use rusqlite::Connection;
@@ -36,6 +38,7 @@ fn test_reputation_scoring() {
         transfer_scope: VrpTransferScope::FullKnowledgeBundle,
         alignment_score: 1.0,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
 
     record_vrp_outcome(&conn, server_id, peer_pseudonym, "AGENT", &report_aligned)
@@ -54,6 +57,7 @@ fn test_reputation_scoring() {
         transfer_scope: VrpTransferScope::NoTransfer,
         alignment_score: 0.0,
         negotiation_notes: vec!["Conflict!".to_string()],
+        explanation: Default::default(),
     };
 
     record_vrp_outcome(&conn, server_id, peer_pseudonym, "AGENT", &report_conflict)
@@ -72,6 +76,7 @@ fn test_reputation_scoring() {
         transfer_scope: VrpTransferScope::ReflectionSummariesOnly,
         alignment_score: 0.5,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
 
     record_vrp_outcome(&conn, server_id, peer_pseudonym, "AGENT", &report_partial)
@@ -96,6 +101,7 @@ fn test_reputation_persistence() {
         transfer_scope: VrpTransferScope::FullKnowledgeBundle,
         alignment_score: 1.0,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
 
     record_vrp_outcome(&conn, server_id, peer_pseudonym, "AGENT", &report)
@@ -126,12 +132,14 @@ fn test_reputation_adversarial_oscillation() {
         transfer_scope: VrpTransferScope::FullKnowledgeBundle,
         alignment_score: 1.0,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
     let report_conflict = VrpValidationReport {
         alignment_status: VrpAlignmentStatus::Conflict,
         transfer_scope: VrpTransferScope::NoTransfer,
         alignment_score: 0.0,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
 
     // Record 10 cycles of ALIGNED, CONFLICT
@@ -164,6 +172,7 @@ fn test_reputation_sustained_conflict_floors() {
         transfer_scope: VrpTransferScope::NoTransfer,
         alignment_score: 0.0,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
 
     for _ in 0..50 {
@@ -192,12 +201,14 @@ fn test_reputation_independent_per_pseudonym() {
         transfer_scope: VrpTransferScope::NoTransfer,
         alignment_score: 0.0,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
     let report_aligned = VrpValidationReport {
         alignment_status: VrpAlignmentStatus::Aligned,
         transfer_scope: VrpTransferScope::FullKnowledgeBundle,
         alignment_score: 1.0,
         negotiation_notes: vec![],
+        explanation: Default::default(),
     };
 
     // Bad actor gets many conflicts
@@ -221,3 +232,172 @@ fn test_reputation_independent_per_pseudonym() {
         "new actor should start neutral"
     );
 }
+
+#[test]
+fn test_reputation_decays_toward_neutral() {
+    let conn = setup_db();
+    let server_id = 1;
+    let peer = "decaying-agent";
+    let config = ReputationConfig::default();
+
+    let report_conflict = VrpValidationReport {
+        alignment_status: VrpAlignmentStatus::Conflict,
+        transfer_scope: VrpTransferScope::NoTransfer,
+        alignment_score: 0.0,
+        negotiation_notes: vec![],
+        explanation: Default::default(),
+    };
+    record_vrp_outcome(&conn, server_id, peer, "AGENT", &report_conflict).unwrap();
+
+    // Fresh conflict: 0.5 - 0.2 * 0.5 = 0.4
+    let fresh = compute_reputation(&conn, server_id, peer, &config).unwrap();
+    assert!((fresh.score - 0.4).abs() < 0.001);
+    assert_eq!(fresh.conflict, 1);
+
+    // One half-life later the distance from neutral has halved: 0.45
+    conn.execute(
+        "UPDATE vrp_handshake_log SET created_at = datetime('now', '-30 days')",
+        [],
+    )
+    .unwrap();
+    let decayed = compute_reputation(&conn, server_id, peer, &config).unwrap();
+    assert!(
+        (decayed.score - 0.45).abs() < 0.001,
+        "got {}",
+        decayed.score
+    );
+
+    // Zero half-life disables decay.
+    let no_decay = ReputationConfig {
+        half_life_secs: 0,
+        ..Default::default()
+    };
+    let score = compute_reputation(&conn, server_id, peer, &no_decay)
+        .unwrap()
+        .score;
+    assert!((score - 0.4).abs() < 0.001);
+}
+
+#[test]
+fn test_reputation_signals_and_weights() {
+    let conn = setup_db();
+    let server_id = 1;
+    let peer = "signalled-agent";
+    let config = ReputationConfig::default();
+
+    record_reputation_signal(
+        &conn,
+        server_id,
+        peer,
+        ReputationSignalKind::RtxPositive,
+        "reviewer-1",
+        Some("bundle-1"),
+        None,
+    )
+    .unwrap();
+    // 0.5 + 0.05 * 0.5 = 0.525
+    let summary = compute_reputation(&conn, server_id, peer, &config).unwrap();
+    assert!((summary.score - 0.525).abs() < 0.001);
+    assert_eq!(summary.rtx_positive, 1);
+    assert!(summary.last_event_at.is_some());
+
+    record_reputation_signal(
+        &conn,
+        server_id,
+        peer,
+        ReputationSignalKind::Incident,
+        "moderator",
+        None,
+        Some("spam"),
+    )
+    .unwrap();
+    // 0.525 - 0.4 * 0.525 = 0.315
+    let summary = compute_reputation(&conn, server_id, peer, &config).unwrap();
+    assert!((summary.score - 0.315).abs() < 0.001);
+    assert_eq!(summary.incidents, 1);
+
+    // Operators can tune weights; a zero incident weight ignores incidents.
+    let lenient = ReputationConfig {
+        incident_weight: 0.0,
+        ..Default::default()
+    };
+    let score = compute_reputation(&conn, server_id, peer, &lenient)
+        .unwrap()
+        .score;
+    assert!((score - 0.525).abs() < 0.001);
+
+    // Incidents weigh more than a handshake conflict.
+    assert!(config.incident_weight > config.conflict_weight);
+}
+
+#[test]
+fn test_reputation_signal_duplicates_rejected() {
+    let conn = setup_db();
+    let server_id = 1;
+
+    record_reputation_signal(
+        &conn,
+        server_id,
+        "agent-x",
+        ReputationSignalKind::ModerationReport,
+        "member-1",
+        None,
+        Some("rude"),
+    )
+    .unwrap();
+    assert!(matches!(
+        record_reputation_signal(
+            &conn,
+            server_id,
+            "agent-x",
+            ReputationSignalKind::ModerationReport,
+            "member-1",
+            None,
+            Some("still rude"),
+        ),
+        Err(ReputationError::Duplicate)
+    ));
+
+    record_reputation_signal(
+        &conn,
+        server_id,
+        "agent-x",
+        ReputationSignalKind::RtxNegative,
+        "member-1",
+        Some("bundle-9"),
+        None,
+    )
+    .unwrap();
+    // Flipping the rating on the same bundle is still a duplicate.
+    assert!(matches!(
+        record_reputation_signal(
+            &conn,
+            server_id,
+            "agent-x",
+            ReputationSignalKind::RtxPositive,
+            "member-1",
+            Some("bundle-9"),
+            None,
+        ),
+        Err(ReputationError::Duplicate)
+    ));
+
+    // Incidents are not deduplicated.
+    for _ in 0..2 {
+        record_reputation_signal(
+            &conn,
+            server_id,
+            "agent-x",
+            ReputationSignalKind::Incident,
+            "moderator",
+            None,
+            Some("abuse"),
+        )
+        .unwrap();
+    }
+    let summary =
+        compute_reputation(&conn, server_id, "agent-x", &ReputationConfig::default()).unwrap();
+    assert_eq!(summary.incidents, 2);
+    assert_eq!(summary.moderation_reports, 1);
+    assert_eq!(summary.rtx_negative, 1);
+}
@@ -2,43 +2,7 @@ pragma circom 2.0.0;
 
 include "circomlib/circuits/poseidon.circom";
 include "circomlib/circuits/bitify.circom";
-
-// Merkle Tree Inclusion Proof
-// Verifies that a leaf exists in a Merkle tree at a given index
-template MerkleTreeInclusionProof(depth) {
-    signal input leaf;
-    signal input pathElements[depth];
-    signal input pathIndexBits[depth];
-    signal output root;
-
-    component poseidons[depth];
-    component mux[depth];
-
-    signal currentHash[depth + 1];
-    currentHash[0] <== leaf;
-
-    for (var i = 0; i < depth; i++) {
-        poseidons[i] = Poseidon(2);
-
-        // Path index bit: 0 = left, 1 = right
-        // If 0: hash(current, pathElement)
-        // If 1: hash(pathElement, current)
-
-        // We can use a mathematical trick or a Mux.
-        // Left input = pathIndexBit * (pathElement - current) + current
-        // Right input = pathIndexBit * (current - pathElement) + pathElement
-
-        var left = pathIndexBits[i] * (pathElements[i] - currentHash[i]) + currentHash[i];
-        var right = pathIndexBits[i] * (currentHash[i] - pathElements[i]) + pathElements[i];
-
-        poseidons[i].inputs[0] <== left;
-        poseidons[i].inputs[1] <== right;
-
-        currentHash[i+1] <== poseidons[i].out;
-    }
-
-    root <== currentHash[depth];
-}
+include "merkle_tree.circom";
 
 // Membership Circuit
 // Proves ownership of an identity commitment included in the Merkle tree
@@ -0,0 +1,87 @@
+pragma circom 2.0.0;
+
+include "circomlib/circuits/poseidon.circom";
+include "circomlib/circuits/bitify.circom";
+include "merkle_tree.circom";
+
+// Selective-Disclosure Role Circuit
+// Proves that the prover owns an identity commitment included in the Merkle
+// tree whose role code is one of `allowedRoles`, without revealing the
+// commitment or the exact role.
+//
+// Unused slots in `allowedRoles` are padded with 0, which is never a valid
+// role code.
+//
+// The proof outputs a context-scoped nullifier = Poseidon(sk, contextHash).
+// It is stable for one identity in one context, so a verifier can bind it to
+// the first caller that presents it and refuse it for anyone else, but it
+// depends on the secret key and cannot be recomputed from the public
+// commitment list.
+//
+// Public signals (snarkjs order):
+//   [root, nullifier, allowedRoles[0..setSize], contextHash]
+template RoleDisclosure(depth, setSize) {
+    signal input sk;
+    signal input roleCode;
+    signal input nodeId;
+
+    signal input leafIndex;
+    signal input pathElements[depth];
+    signal input pathIndexBits[depth];
+
+    // Public inputs: the disclosed role set, and a verifier-chosen context
+    // (e.g. a channel) that scopes the nullifier.
+    signal input allowedRoles[setSize];
+    signal input contextHash;
+
+    signal output root;
+    signal output nullifier;
+
+    // 1. Recompute Identity Commitment
+    component identity = Poseidon(3);
+    identity.inputs[0] <== sk;
+    identity.inputs[1] <== roleCode;
+    identity.inputs[2] <== nodeId;
+
+    // 2. Verify Merkle Path
+    component merkleProof = MerkleTreeInclusionProof(depth);
+    merkleProof.leaf <== identity.out;
+
+    for (var i = 0; i < depth; i++) {
+        merkleProof.pathElements[i] <== pathElements[i];
+        merkleProof.pathIndexBits[i] <== pathIndexBits[i];
+    }
+
+    root <== merkleProof.root;
+
+    // 3. Constrain leafIndex bits to match pathIndexBits
+    component num2Bits = Num2Bits(depth);
+    num2Bits.in <== leafIndex;
+
+    for (var i = 0; i < depth; i++) {
+        num2Bits.out[i] === pathIndexBits[i];
+    }
+
+    // 4. roleCode must equal one of allowedRoles:
+    //    prod_i (roleCode - allowedRoles[i]) == 0
+    signal partial[setSize];
+    partial[0] <== roleCode - allowedRoles[0];
+    for (var i = 1; i < setSize; i++) {
+        partial[i] <== partial[i - 1] * (roleCode - allowedRoles[i]);
+    }
+    partial[setSize - 1] === 0;
+
+    // 5. Role code 0 is reserved for padding and must not satisfy the set.
+    signal roleInverse;
+    roleInverse <-- roleCode != 0 ? 1 / roleCode : 0;
+    roleInverse * roleCode === 1;
+
+    // 6. Derive the context-scoped nullifier from the secret key.
+    component nullifierHash = Poseidon(2);
+    nullifierHash.inputs[0] <== sk;
+    nullifierHash.inputs[1] <== contextHash;
+
+    nullifier <== nullifierHash.out;
+}
+
+component main {public [allowedRoles, contextHash]} = RoleDisclosure(20, 5);
@@ -0,0 +1,75 @@
+pragma circom 2.0.0;
+
+include "circomlib/circuits/poseidon.circom";
+include "circomlib/circuits/bitify.circom";
+include "merkle_tree.circom";
+
+// Anonymous Vote Circuit
+// Proves that the voter owns an identity commitment included in the Merkle
+// tree and derives a topic-scoped nullifier without revealing the commitment.
+//
+// For channel polls the tree is the poll's eligibility snapshot: the
+// commitments of the channel's members when the poll was created. The server
+// only accepts a `root` equal to that snapshot's root, so the proof itself
+// shows the voter is eligible and the ballot needs no authentication.
+//
+// nullifier = Poseidon(sk, topicHash)
+//
+// Public signals (snarkjs order): [root, nullifier, topicHash, voteHash]
+template Vote(depth) {
+    signal input sk;
+    signal input roleCode;
+    signal input nodeId;
+
+    signal input leafIndex;
+    signal input pathElements[depth];
+    signal input pathIndexBits[depth];
+
+    // Public inputs: the poll topic and the chosen option, both hashed to
+    // field elements by the caller. Binding voteHash into the proof prevents
+    // a relay from swapping the selected option.
+    signal input topicHash;
+    signal input voteHash;
+
+    signal output root;
+    signal output nullifier;
+
+    // 1. Recompute Identity Commitment
+    component identity = Poseidon(3);
+    identity.inputs[0] <== sk;
+    identity.inputs[1] <== roleCode;
+    identity.inputs[2] <== nodeId;
+
+    // 2. Verify Merkle Path
+    component merkleProof = MerkleTreeInclusionProof(depth);
+    merkleProof.leaf <== identity.out;
+
+    for (var i = 0; i < depth; i++) {
+        merkleProof.pathElements[i] <== pathElements[i];
+        merkleProof.pathIndexBits[i] <== pathIndexBits[i];
+    }
+
+    root <== merkleProof.root;
+
+    // 3. Constrain leafIndex bits to match pathIndexBits
+    component num2Bits = Num2Bits(depth);
+    num2Bits.in <== leafIndex;
+
+    for (var i = 0; i < depth; i++) {
+        num2Bits.out[i] === pathIndexBits[i];
+    }
+
+    // 4. Derive the poll-scoped nullifier from the secret key, so the
+    // server cannot recompute it from the (public) commitment list.
+    component nullifierHash = Poseidon(2);
+    nullifierHash.inputs[0] <== sk;
+    nullifierHash.inputs[1] <== topicHash;
+
+    nullifier <== nullifierHash.out;
+
+    // 5. Constrain voteHash so it cannot be optimized out of the proof.
+    signal voteSquare;
+    voteSquare <== voteHash * voteHash;
+}
+
+component main {public [topicHash, voteHash]} = Vote(20);
@@ -54,7 +54,7 @@ if (!fs.existsSync(buildPath)) {
     fs.mkdirSync(buildPath);
 }
 
-const circuits = ['identity', 'membership'];
+const circuits = ['identity', 'membership', 'vote', 'role'];
 
 circuits.forEach(circuit => {
     console.log(`Building ${circuit}...`);
@@ -9,7 +9,7 @@ if (!fs.existsSync(keysPath)) {
     fs.mkdirSync(keysPath);
 }
 
-const circuits = ['identity', 'membership'];
+const circuits = ['identity', 'membership', 'vote', 'role'];
 
 function run(cmd) {
     console.log(`Running: ${cmd}`);
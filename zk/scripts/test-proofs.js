@@ -23,6 +23,8 @@ async function run() {
     const poseidon = await buildPoseidon();
     const idVKey = JSON.parse(fs.readFileSync(path.join(keysPath, "identity_vkey.json")));
     const memVKey = JSON.parse(fs.readFileSync(path.join(keysPath, "membership_vkey.json")));
+    const voteVKey = JSON.parse(fs.readFileSync(path.join(keysPath, "vote_vkey.json")));
+    const roleVKey = JSON.parse(fs.readFileSync(path.join(keysPath, "role_vkey.json")));
 
     // ═══════════════════════════════════════════
     // Identity Circuit — Valid Proof
@@ -190,6 +192,89 @@ async function run() {
         assert(true, "mismatched leafIndex/pathIndexBits rejected at witness generation");
     }
 
+    // ═══════════════════════════════════════════
+    // Vote Circuit — Valid Proof and Poll-Scoped Nullifier
+    // ═══════════════════════════════════════════
+    console.log("\n=== Vote Circuit: Valid Proof ===");
+
+    // topicHash/voteHash are arbitrary field elements here; the server derives
+    // them as sha256(label) mod r (see annex-identity vote.rs).
+    const topicHash = 1111n;
+    const voteHash = 2222n;
+    const voteInput = {
+        sk: sk.toString(), roleCode: roleCode.toString(), nodeId: nodeId.toString(),
+        leafIndex: "0", pathElements: pathElements0, pathIndexBits: pathIndexBits0,
+        topicHash: topicHash.toString(), voteHash: voteHash.toString(),
+    };
+
+    const { proof: voteProof, publicSignals: voteSignals } = await snarkjs.groth16.fullProve(
+        voteInput,
+        path.join(buildPath, "vote_js/vote.wasm"),
+        path.join(keysPath, "vote_final.zkey")
+    );
+
+    const voteVerified = await snarkjs.groth16.verify(voteVKey, voteSignals, voteProof);
+    assert(voteVerified, "valid vote proof verifies");
+    assert(voteSignals[0] === expectedRoot0, "vote root matches membership root");
+    assert(
+        voteSignals[1] === poseidon.F.toString(poseidon([sk, topicHash])),
+        "nullifier equals Poseidon(sk, topicHash)"
+    );
+
+    const swappedVoteSignals = [...voteSignals];
+    swappedVoteSignals[3] = "3333";
+    const swappedVerified = await snarkjs.groth16.verify(voteVKey, swappedVoteSignals, voteProof);
+    assert(!swappedVerified, "vote proof with swapped option is rejected");
+
+    const { publicSignals: otherPollSignals } = await snarkjs.groth16.fullProve(
+        { ...voteInput, topicHash: "4444" },
+        path.join(buildPath, "vote_js/vote.wasm"),
+        path.join(keysPath, "vote_final.zkey")
+    );
+    assert(otherPollSignals[1] !== voteSignals[1], "different poll produces different nullifier");
+
+    // ═══════════════════════════════════════════
+    // Role Circuit — Selective Disclosure
+    // ═══════════════════════════════════════════
+    console.log("\n=== Role Circuit: Selective Disclosure ===");
+
+    const roleInput = {
+        sk: sk.toString(), roleCode: roleCode.toString(), nodeId: nodeId.toString(),
+        leafIndex: "0", pathElements: pathElements0, pathIndexBits: pathIndexBits0,
+        allowedRoles: ["1", "3", "0", "0", "0"], contextHash: "5555",
+    };
+
+    const { proof: roleProof, publicSignals: roleSignals } = await snarkjs.groth16.fullProve(
+        roleInput,
+        path.join(buildPath, "role_js/role.wasm"),
+        path.join(keysPath, "role_final.zkey")
+    );
+
+    const roleVerified = await snarkjs.groth16.verify(roleVKey, roleSignals, roleProof);
+    assert(roleVerified, "role proof for set {HUMAN, COLLECTIVE} verifies");
+    assert(roleSignals[0] === expectedRoot0, "role proof root matches membership root");
+    assert(!roleSignals.includes(expectedCommitment), "role proof does not reveal the commitment");
+    assert(
+        roleSignals[1] === poseidon.F.toString(poseidon([sk, 5555n])),
+        "role proof nullifier equals Poseidon(sk, contextHash)"
+    );
+
+    const widenedSignals = [...roleSignals];
+    widenedSignals[2] = "2";
+    const widenedVerified = await snarkjs.groth16.verify(roleVKey, widenedSignals, roleProof);
+    assert(!widenedVerified, "role proof with altered role set is rejected");
+
+    try {
+        await snarkjs.groth16.fullProve(
+            { ...roleInput, allowedRoles: ["2", "0", "0", "0", "0"] },
+            path.join(buildPath, "role_js/role.wasm"),
+            path.join(keysPath, "role_final.zkey")
+        );
+        assert(false, "role outside the allowed set should fail witness generation");
+    } catch (e) {
+        assert(true, "role outside the allowed set rejected at witness generation");
+    }
+
     // ═══════════════════════════════════════════
     // Summary
     // ═══════════════════════════════════════════